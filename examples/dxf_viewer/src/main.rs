@@ -10,33 +10,42 @@ use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tracing_subscriber::prelude::*;
 use ui_events::{
     ScrollDelta,
     pointer::{PointerButton, PointerEvent, PointerId, PointerInfo, PointerType, PointerUpdate},
 };
 use ui_events_winit::{WindowEventReducer, WindowEventTranslation};
-use vello::kurbo::{
-    Affine, DEFAULT_ACCURACY, ParamCurveNearest, PathSeg, Point, Rect, Shape, Stroke, Vec2,
-};
-use vello::peniko::{Brush, Color, color::palette};
+use vello::kurbo::{Affine, Point, Rect, Stroke, Vec2};
+use vello::peniko::{Brush, Color, Fill, color::palette};
 use vello::util::{RenderContext, RenderSurface};
 use vello::{AaConfig, Renderer, RendererOptions, Scene};
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
 use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::window::Window;
 
 use vello::wgpu;
 
-use tabulon_dxf::{EntityHandle, RestrokePaint, TDDrawing};
+use tabulon_dxf::{EntityHandle, TDDrawing};
+use tabulon_vello::{
+    FragmentId, GpuTimer, Greeking, Quality, QualityController, RasterOptions, RenderOptions,
+    StyleOverrides,
+};
 
 use tabulon::{
     GraphicsBag, GraphicsItem, ItemHandle, PaintHandle,
+    bounds::EstimatedText,
+    device_pixel_tolerance,
+    occlusion::unoccluded_items,
     render_layer::RenderLayer,
-    shape::{FatPaint, FatShape},
+    shape::{DashUnits, FatPaint, FatShape},
+    snap_to_pixel_centers,
+    spatial_index::SpatialIndex,
+    uniform_scale,
+    view_controller::{InputSmoothing, ViewController},
 };
 
 extern crate alloc;
@@ -54,36 +63,28 @@ enum RenderState<'s> {
     Suspended(Option<Arc<Window>>),
 }
 
-#[derive(Default)]
-struct GestureState {
-    /// Pointer currently panning.
-    pan: Option<PointerId>,
-    /// Cursor position.
-    cursor_pos: Point,
-}
-
 struct DrawingViewer {
     /// `tabulon_dxf` drawing.
     td: TDDrawing,
 
-    /// Index of bounding boxes for hit testing.
-    picking_index: EntityIndex,
+    /// Index of bounding boxes for culling and hit testing.
+    spatial_index: SpatialIndex,
     /// Which shape is closest to the cursor?
     pick: Option<EntityHandle>,
+    /// Entities within picking tolerance of the cursor, while they are shown.
+    pick_candidates: Vec<EntityHandle>,
 
-    /// Index of bounding boxes for culling texts.
-    text_cull_index: TextCullIndex,
+    /// Number of text items that have not been shaped yet.
+    pending_text: usize,
 
-    /// View transform of the drawing.
-    view_transform: Affine,
-    /// View scale of the drawing.
-    view_scale: f64,
+    /// View transform of the drawing, to device pixels, driven by input.
+    view: ViewController,
 
     /// Defer reprojection until after redraw is completed.
     defer_reprojection: bool,
 
-    /// State of gesture processing (e.g. panning, zooming).
-    gestures: GestureState,
+    /// Pointer currently panning.
+    panning: Option<PointerId>,
 }
 
 struct TabulonDxfViewer<'s> {
@@ -99,12 +100,25 @@ struct TabulonDxfViewer<'s> {
     /// A vello Scene which is a data structure which allows one to build up a description a scene to be
     /// drawn (with paths, fills, images, text, etc) which is then passed to a renderer for rendering.
     scene: Scene,
+    /// Encoded drawing, kept while only the overlay changes.
+    drawing_scene: Scene,
+    /// Encoded highlights drawn above the drawing.
+    overlay_scene: Scene,
 
     /// Tabulon Vello environment.
     tv_environment: tabulon_vello::Environment,
 
+    /// Measures how long frames take on the device of the active surface.
+    gpu_timer: Option<GpuTimer>,
+    /// Rendering quality, adapted to how long frames take.
+    quality: QualityController,
+
     /// ui-events `WindowEvent` reducer.
     event_reducer: WindowEventReducer,
+    /// Origin of the times given to view controllers.
+    clock: Instant,
+    /// Whether to draw every pick candidate under the cursor, toggled with the C key.
+    show_pick_candidates: bool,
 
     /// State related to viewing a specific drawing.
     viewer: Option<DrawingViewer>,
@@ -156,6 +170,8 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
             .resize_with(self.context.devices.len(), || None);
         self.renderers[surface.dev_id]
             .get_or_insert_with(|| create_vello_renderer(&self.context, &surface));
+        let device_handle = &self.context.devices[surface.dev_id];
+        self.gpu_timer = Some(GpuTimer::new(&device_handle.device, &device_handle.queue));
 
         if let Some(path_arg) = std::env::args().next_back() {
             match load_drawing(&path_arg) {
@@ -170,10 +186,12 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                     );
                     window.set_title(&title);
 
-                    let picking_index = EntityIndex::new(&drawing);
-                    let bounds = picking_index.bounds();
+                    let spatial_index = build_spatial_index(&drawing);
+                    let bounds = spatial_index.bounds();
 
-                    let text_cull_index = TextCullIndex::new(&mut self.tv_environment, &drawing);
+                    self.tv_environment.clear_text_layouts();
+                    self.tv_environment.clear_path_lods();
+                    self.tv_environment.clear_fragments();
 
                     let mut scene = Scene::default();
                     let view_scale = (size.height as f64 / bounds.size().height)
@@ -184,33 +202,43 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                         y: -bounds.min_y(),
                     })
                     .then_scale(view_scale);
-                    update_transform(
-                        &mut drawing.graphics,
-                        drawing.restroke_paints.clone(),
-                        view_transform,
-                        view_scale,
-                        scale_factor,
-                    );
+                    update_transform(&mut drawing, view_transform, scale_factor);
                     self.scene.reset();
 
-                    let encode_started = Instant::now();
-                    self.tv_environment.add_render_layer_to_scene(
+                    let stats = self.tv_environment.add_render_layer_to_scene_with_options(
                         &mut scene,
                         &drawing.graphics,
                         &drawing.render_layer,
+                        &RenderOptions {
+                            collect_stats: true,
+                            ..render_options(&self.quality)
+                        },
+                    );
+                    eprintln!(
+                        "Initial encode took {:?}, and shaping {:?}, for {} items \
+                         ({} greeked, {} skipped) with {} path segments and {} glyph runs",
+                        stats.encoding_time,
+                        stats.shaping_time,
+                        stats.items_encoded,
+                        stats.greeked,
+                        stats.skipped_missing + stats.skipped_empty + stats.skipped_text,
+                        stats.path_segments,
+                        stats.glyph_runs,
                     );
-                    let encode_duration = Instant::now().saturating_duration_since(encode_started);
-                    eprintln!("Initial projection/encode took {encode_duration:?}");
 
                     self.viewer = Some(DrawingViewer {
                         td: drawing,
-                        picking_index,
-                        view_scale,
-                        view_transform,
-                        text_cull_index,
-                        gestures: GestureState::default(),
+                        spatial_index,
+                        view: ViewController::new(
+                            view_transform,
+                            scale_factor,
+                            InputSmoothing::default(),
+                        ),
+                        pending_text: 0,
+                        panning: None,
                         defer_reprojection: false,
                         pick: None,
+                        pick_candidates: Vec::new(),
                     });
                 }
                 Err(e) => {
@@ -232,6 +260,31 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
         }
     }
 
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let (RenderState::Active { surface, .. }, Some(viewer)) = (&self.state, &mut self.viewer)
+        else {
+            return;
+        };
+
+        if viewer.pending_text == 0 {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+
+        viewer.pending_text = self.tv_environment.shape_text_items_progressively(
+            &viewer.td.graphics,
+            &viewer.td.render_layer,
+            Rect::new(
+                0.,
+                0.,
+                surface.config.width as f64,
+                surface.config.height as f64,
+            ),
+            TEXT_SHAPING_BATCH,
+        );
+        event_loop.set_control_flow(ControlFlow::Poll);
+    }
+
     #[tracing::instrument(skip_all)]
     fn window_event(
         &mut self,
@@ -246,7 +299,10 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
             _ => return,
         };
 
+        let now = self.clock.elapsed().as_secs_f64();
         let mut reproject = false;
+        // Set if only the highlight of the picked entity changed.
+        let mut repick = false;
         // Set if reprojection is requested as a result of a deferral.
         let mut reproject_deferred = false;
 
@@ -264,6 +320,14 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                         if k.state.is_down() && matches!(k.key, Key::Named(NamedKey::Escape)) {
                             event_loop.exit();
                         }
+                        if k.state.is_down() && matches!(&k.key, Key::Character(c) if c == "c") {
+                            self.show_pick_candidates = !self.show_pick_candidates;
+                            // Candidates are found again when the cursor next moves.
+                            if let Some(viewer) = &mut self.viewer {
+                                viewer.pick_candidates.clear();
+                                repick = true;
+                            }
+                        }
                     }
                     WindowEventTranslation::Pointer(p) => {
                         let Some(viewer) = &mut self.viewer else {
@@ -290,14 +354,13 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                                     },
                                 state,
                                 ..
-                            } => {
-                                if viewer.gestures.pan.is_none() {
-                                    viewer.gestures.pan = pointer_id;
-                                    viewer.gestures.cursor_pos = Point {
-                                        x: state.position.x,
-                                        y: state.position.y,
-                                    }
-                                }
+                            } if viewer.panning.is_none() => {
+                                viewer.panning = pointer_id;
+                                let p = Point {
+                                    x: state.position.x,
+                                    y: state.position.y,
+                                };
+                                viewer.view.drag_start(p, now);
                             }
                             PointerEvent::Move(PointerUpdate {
                                 pointer: PointerInfo { pointer_id, .. },
@@ -309,20 +372,39 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                                     y: current.position.y,
                                 };
 
-                                let dp = viewer.view_transform.inverse() * p;
+                                let dp = viewer.view.view().inverse() * p;
 
-                                if viewer.gestures.pan == pointer_id {
-                                    viewer.view_transform = viewer
-                                        .view_transform
-                                        .then_translate(-(viewer.gestures.cursor_pos - p));
+                                // Other pointers don't move the anchor of a pan.
+                                if viewer.panning.is_none() || viewer.panning == pointer_id {
+                                    viewer.view.pointer_move(p, now);
+                                }
+                                if viewer.panning == pointer_id {
                                     reproject = true;
                                 } else if pointer_id == Some(PointerId::PRIMARY) {
-                                    let pick_dist: f64 = window.scale_factor() * 1.414;
+                                    let tolerance = device_pixel_tolerance(
+                                        viewer.view.scale_factor() * 1.414,
+                                        viewer.view.view(),
+                                    );
                                     let pick_started = Instant::now();
 
-                                    let pick = viewer
-                                        .picking_index
-                                        .pick(dp, pick_dist * viewer.view_scale.recip());
+                                    let candidates = if self.show_pick_candidates {
+                                        viewer.td.item_entity_map.pick_candidates(
+                                            &viewer.spatial_index,
+                                            dp,
+                                            tolerance,
+                                        )
+                                    } else {
+                                        Vec::new()
+                                    };
+                                    let pick = if self.show_pick_candidates {
+                                        candidates.first().copied()
+                                    } else {
+                                        viewer.td.item_entity_map.pick(
+                                            &viewer.spatial_index,
+                                            dp,
+                                            tolerance,
+                                        )
+                                    };
 
                                     if viewer.pick != pick {
                                         if let Some(pick) = pick {
@@ -335,32 +417,38 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                                             eprintln!("Pick took {pick_duration:?}");
                                         }
                                         viewer.pick = pick;
-                                        reproject = true;
+                                        repick = true;
+                                    }
+                                    if viewer.pick_candidates != candidates {
+                                        viewer.pick_candidates = candidates;
+                                        repick = true;
                                     }
                                 }
-
-                                viewer.gestures.cursor_pos = p;
                             }
                             PointerEvent::Up {
                                 pointer: PointerInfo { pointer_id, .. },
                                 ..
                             }
-                            | PointerEvent::Cancel(PointerInfo { pointer_id, .. }) => {
-                                if viewer.gestures.pan == pointer_id {
-                                    viewer.gestures.pan = None;
-                                }
+                            | PointerEvent::Cancel(PointerInfo { pointer_id, .. })
+                                if viewer.panning == pointer_id =>
+                            {
+                                viewer.panning = None;
+                                viewer.view.drag_end(now);
+                                // Released drags may coast, which is ticked on redraw.
+                                reproject = viewer.view.is_animating();
                             }
                             PointerEvent::Scroll { delta, .. } => {
+                                // Pixel deltas are in device pixels, so they are taken in
+                                // logical pixels to zoom alike on every monitor.
                                 let d = match delta {
                                     ScrollDelta::LineDelta(_, y) => y as f64 * 0.1,
-                                    ScrollDelta::PixelDelta(pd) => pd.y * 0.05,
+                                    ScrollDelta::PixelDelta(pd) => {
+                                        pd.y / viewer.view.scale_factor() * 0.05
+                                    }
                                     _ => 0.,
                                 };
 
-                                viewer.view_transform = viewer
-                                    .view_transform
-                                    .then_scale_about(1. + d, viewer.gestures.cursor_pos);
-                                viewer.view_scale *= 1. + d;
+                                viewer.view.wheel_zoom(1. + d, now);
                                 reproject = true;
                             }
                             _ => {}
@@ -410,10 +498,13 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                 );
                 window.set_title(&title);
 
-                let picking_index = EntityIndex::new(&drawing);
-                let bounds = picking_index.bounds();
+                let spatial_index = build_spatial_index(&drawing);
+                let bounds = spatial_index.bounds();
 
-                let text_cull_index = TextCullIndex::new(&mut self.tv_environment, &drawing);
+                self.tv_environment.clear_text_layouts();
+                self.tv_environment.clear_path_lods();
+                self.tv_environment.clear_fragments();
+                self.quality.reset();
 
                 let view_scale = (surface.config.height as f64 / bounds.size().height)
                     .min(surface.config.width as f64 / bounds.size().width);
@@ -426,12 +517,16 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
 
                 self.viewer = Some(DrawingViewer {
                     td: drawing,
-                    picking_index,
-                    view_scale,
-                    view_transform,
-                    text_cull_index,
+                    spatial_index,
+                    view: ViewController::new(
+                        view_transform,
+                        window.scale_factor(),
+                        InputSmoothing::default(),
+                    ),
+                    pending_text: 0,
                     pick: None,
-                    gestures: GestureState::default(),
+                    pick_candidates: Vec::new(),
+                    panning: None,
                     defer_reprojection: false,
                 });
 
@@ -443,12 +538,18 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                     return;
                 };
 
-                viewer.view_transform = viewer
-                    .view_transform
-                    .then_translate(-viewer.gestures.cursor_pos.to_vec2())
-                    .then_scale(1. + d)
-                    .then_translate(viewer.gestures.cursor_pos.to_vec2());
-                viewer.view_scale *= 1. + d;
+                viewer.view.pinch_zoom(1. + d);
+                reproject = true;
+            }
+
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                let Some(viewer) = &mut self.viewer else {
+                    return;
+                };
+
+                // Keep the drawing at the same logical size and position, and the cursor
+                // over the same point, so that zooming stays anchored under it.
+                viewer.view.set_scale_factor(scale_factor);
                 reproject = true;
             }
 
@@ -464,6 +565,16 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                         .expect("failed to get surface texture")
                 });
 
+                let gpu_timer = self.gpu_timer.as_mut().unwrap();
+                let mut encoder =
+                    device_handle
+                        .device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Frame Start"),
+                        });
+                gpu_timer.begin(&mut encoder);
+                device_handle.queue.submit([encoder.finish()]);
+
                 tracing::info_span!("render_to_texture").in_scope(|| {
                     // Render to the surface's texture
                     self.renderers[surface.dev_id]
@@ -474,12 +585,11 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                             &device_handle.queue,
                             &self.scene,
                             &surface.target_view,
-                            &vello::RenderParams {
-                                base_color: Color::WHITE, // Background color
-                                width,
-                                height,
-                                antialiasing_method: AaConfig::Area,
-                            },
+                            &RasterOptions {
+                                antialiasing: self.quality.quality().antialiasing,
+                                ..RasterOptions::HAIRLINES
+                            }
+                            .render_params(width, height),
                         )
                         .expect("failed to render to the texture");
                 });
@@ -498,7 +608,9 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                             .texture
                             .create_view(&wgpu::TextureViewDescriptor::default()),
                     );
+                    gpu_timer.end(&mut encoder);
                     device_handle.queue.submit([encoder.finish()]);
+                    gpu_timer.submitted(&device_handle.queue);
                 });
 
                 tracing::info_span!("present_surface").in_scope(|| {
@@ -510,10 +622,20 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
 
                 device_handle.device.poll(wgpu::Maintain::Poll);
 
-                if let Some(viewer) = &self.viewer {
+                if let Some(viewer) = &mut self.viewer {
                     if viewer.defer_reprojection {
                         reproject_deferred = true;
                     }
+                    // Smoothed zooms and coasting pans move the view between inputs.
+                    if viewer.view.is_animating() {
+                        viewer.view.tick(now);
+                        reproject_deferred = true;
+                    }
+                    // Encoded scenes depend on the quality, so encode them again;
+                    // cached fragments notice that their options have changed.
+                    if gpu_timer.poll().is_some_and(|t| self.quality.record(t)) {
+                        reproject_deferred = true;
+                    }
                 };
             }
             _ => {}
@@ -533,95 +655,65 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
                 // direct requests for reprojection until after the next redraw is complete.
                 viewer.defer_reprojection = reproject;
                 let reproject_started = Instant::now();
-                update_transform(
-                    &mut viewer.td.graphics,
-                    viewer.td.restroke_paints.clone(),
-                    viewer.view_transform,
-                    viewer.view_scale,
-                    window.scale_factor(),
-                );
+                let options = render_options(&self.quality);
+                let view_transform = viewer.view.view();
+                update_transform(&mut viewer.td, view_transform, viewer.view.scale_factor());
 
-                let tl = viewer.view_transform.inverse() * Point { x: 0., y: 0. };
-                let br = viewer.view_transform.inverse()
+                let tl = view_transform.inverse() * Point { x: 0., y: 0. };
+                let br = view_transform.inverse()
                     * Point {
                         x: surface.config.width as f64,
                         y: surface.config.height as f64,
                     };
 
-                #[allow(
-                    clippy::cast_possible_truncation,
-                    reason = "The loss of range and precision is acceptable."
-                )]
-                let visible = viewer.picking_index.query_items(
-                    tl.x as f32,
-                    tl.y as f32,
-                    br.x as f32,
-                    br.y as f32,
-                );
-
-                #[allow(
-                    clippy::cast_possible_truncation,
-                    reason = "The loss of range and precision is acceptable."
-                )]
-                let visible_text = viewer.text_cull_index.query_items(
-                    tl.x as f32,
-                    tl.y as f32,
-                    br.x as f32,
-                    br.y as f32,
+                let visible = viewer
+                    .spatial_index
+                    .query_items_stroked(&viewer.td.graphics, Rect::from_points(tl, br));
+                let is_visible = |ih: &ItemHandle| visible.contains(*ih);
+                let viewport = Rect::new(
+                    0.,
+                    0.,
+                    surface.config.width as f64,
+                    surface.config.height as f64,
                 );
+                self.drawing_scene.reset();
+                if visible.len() == viewer.td.render_layer.indices.len() {
+                    // Nothing is culled, so the geometry encoded at an earlier view is
+                    // reused, and only its strokes are updated.
+                    self.tv_environment.add_cached_render_layer_to_scene(
+                        &mut self.drawing_scene,
+                        DRAWING_FRAGMENT,
+                        &viewer.td.graphics,
+                        &viewer.td.render_layer,
+                        &options,
+                    );
+                } else {
+                    let unoccluded = unoccluded_items(
+                        &viewer.td.graphics,
+                        viewer.td.render_layer.iter_filtered(is_visible),
+                        viewport,
+                        OCCLUSION_TILE_SIZE,
+                        &mut EstimatedText,
+                    );
+                    self.tv_environment.add_items_to_scene(
+                        &mut self.drawing_scene,
+                        &viewer.td.graphics,
+                        unoccluded.iter().copied(),
+                        &options,
+                    );
+                }
 
-                let culled_render_layer =
-                    viewer
-                        .td
-                        .render_layer
-                        .filter(|ih| match viewer.td.graphics.get(*ih) {
-                            Some(GraphicsItem::FatShape(..)) => visible.binary_search(ih).is_ok(),
-                            Some(GraphicsItem::FatText(..)) => visible_text.contains(ih),
-                            _ => false,
-                        });
-                self.scene.reset();
-                self.tv_environment.add_render_layer_to_scene(
-                    &mut self.scene,
+                // Everything on screen was shaped while encoding, so continue shaping
+                // the rest of the drawing in batches between frames.
+                viewer.pending_text = self.tv_environment.shape_text_items_progressively(
                     &viewer.td.graphics,
-                    &culled_render_layer,
+                    &viewer.td.render_layer,
+                    viewport,
+                    TEXT_SHAPING_BATCH,
                 );
 
-                if let Some(pick) = viewer.pick {
-                    let mut gb = GraphicsBag::default();
-                    let mut rl = RenderLayer::default();
-
-                    gb.update_transform(Default::default(), viewer.view_transform);
-
-                    let paint = gb.register_paint(FatPaint {
-                        stroke: Stroke::new(1.414 / viewer.view_scale),
-                        stroke_paint: Some(palette::css::GOLDENROD.into()),
-                        fill_paint: None,
-                    });
-
-                    culled_render_layer
-                        .indices
-                        .iter()
-                        .filter(|ih| viewer.td.item_entity_map[ih] == pick)
-                        .for_each(|ih| {
-                            let Some(GraphicsItem::FatShape(FatShape {
-                                transform, path, ..
-                            })) = viewer.td.graphics.get(*ih)
-                            else {
-                                return;
-                            };
-                            rl.push_with_bag(
-                                &mut gb,
-                                FatShape {
-                                    transform: *transform,
-                                    path: path.clone(),
-                                    paint,
-                                },
-                            );
-                        });
-
-                    self.tv_environment
-                        .add_render_layer_to_scene(&mut self.scene, &gb, &rl);
-                }
+                encode_pick_overlay(&mut self.tv_environment, &mut self.overlay_scene, viewer);
+                compose_scenes(&mut self.scene, &self.drawing_scene, &self.overlay_scene);
 
                 let reproject_duration =
                     Instant::now().saturating_duration_since(reproject_started);
@@ -629,8 +721,93 @@ impl ApplicationHandler for TabulonDxfViewer<'_> {
 
                 window.request_redraw();
             });
+        } else if repick {
+            let Some(viewer) = &self.viewer else {
+                return;
+            };
+            // The drawing is unchanged, so only the overlay is encoded again.
+            encode_pick_overlay(&mut self.tv_environment, &mut self.overlay_scene, viewer);
+            compose_scenes(&mut self.scene, &self.drawing_scene, &self.overlay_scene);
+            window.request_redraw();
+        }
+    }
+}
+
+/// Encode a highlight of the picked entity, if there is one, over faint highlights of
+/// the other pick candidates, if they are shown.
+fn encode_pick_overlay(
+    tv_environment: &mut tabulon_vello::Environment,
+    scene: &mut Scene,
+    viewer: &DrawingViewer,
+) {
+    scene.reset();
+    let Some(pick) = viewer.pick else {
+        return;
+    };
+    let mut gb = GraphicsBag::default();
+    let mut rl = RenderLayer::default();
+
+    let stroke = Stroke::new(1.414 / uniform_scale(viewer.view.view()));
+    let candidate_paint = gb.register_paint(FatPaint {
+        stroke: stroke.clone(),
+        stroke_paint: Some(palette::css::GOLDENROD.with_alpha(0.35).into()),
+        fill_paint: None,
+        fill_rule: None,
+    });
+    let pick_paint = gb.register_paint(FatPaint {
+        stroke,
+        stroke_paint: Some(palette::css::GOLDENROD.into()),
+        fill_paint: None,
+        fill_rule: None,
+    });
+
+    // The overlay is placed with the view snapped as in `update_transform`, so that
+    // highlights land on the same pixels as the lines they cover.
+    gb.update_transform(
+        Default::default(),
+        snap_to_pixel_centers(viewer.view.view(), Point::ORIGIN, 1.0),
+    );
+    let from_view = viewer
+        .td
+        .graphics
+        .get_transform(Default::default())
+        .inverse();
+
+    let candidates = viewer.pick_candidates.iter().filter(|c| **c != pick);
+    for (entity, paint) in candidates
+        .map(|c| (*c, candidate_paint))
+        .chain([(pick, pick_paint)])
+    {
+        for FatShape {
+            transform, shape, ..
+        } in viewer.td.entity_shapes(entity)
+        {
+            // Handles belong to the drawing's bag, so the final transform is copied
+            // over, less the view of the drawing's root.
+            let transform = gb.register_transform(
+                Default::default(),
+                from_view * viewer.td.graphics.get_transform(transform),
+            );
+            rl.push_with_bag(
+                &mut gb,
+                FatShape {
+                    transform,
+                    shape,
+                    paint,
+                    ..Default::default()
+                },
+            );
         }
     }
+
+    tv_environment.add_render_layer_to_scene(scene, &gb, &rl);
+}
+
+/// Composite the drawing and its overlay into the scene that is rendered.
+fn compose_scenes(scene: &mut Scene, drawing: &Scene, overlay: &Scene) {
+    scene.reset();
+    scene.append(drawing, None);
+    scene.append(overlay, None);
 }
 
 /// Load a drawing file into a drawing, and print some stats.
@@ -640,26 +817,20 @@ fn load_drawing(p: impl AsRef<Path>) -> Result<TDDrawing> {
 
     let drawing_load_duration = Instant::now().saturating_duration_since(drawing_load_started);
     eprintln!("Drawing took {drawing_load_duration:?} to load and translate.");
+    eprintln!("{}", drawing.report);
 
     light_adapt_paints(&mut drawing.graphics, &drawing.render_layer);
+    // The default paint is drawn in black whatever it was adapted to.
+    drawing
+        .graphics
+        .get_paint_mut(Default::default())
+        .stroke_paint = Some(Color::BLACK.into());
 
     {
-        let mut segment_count = 0;
-        let mut text_count = 0;
-        for item_handle in drawing.item_entity_map.keys() {
-            match drawing.graphics.get(*item_handle) {
-                Some(GraphicsItem::FatShape(FatShape { path, .. })) => {
-                    segment_count += path.segments().count();
-                }
-                Some(GraphicsItem::FatText(_)) => text_count += 1,
-                None => {}
-            }
-        }
         eprintln!(
-            "Loaded {} unique entities, {} path segments, {} text blocks.",
+            "Loaded {} unique entities: {}.",
             drawing.item_entity_map.len(),
-            segment_count,
-            text_count
+            drawing.graphics.stats()
         );
         let linewidths: BTreeSet<u64> = drawing.restroke_paints.iter().map(|r| r.weight).collect();
         eprintln!(
@@ -699,8 +870,14 @@ fn main() -> Result<()> {
         renderers: vec![],
         state: RenderState::Suspended(None),
         scene: Scene::new(),
+        drawing_scene: Scene::new(),
+        overlay_scene: Scene::new(),
         tv_environment: Default::default(),
+        gpu_timer: None,
+        quality: QualityController::new(QUALITY_LEVELS, TARGET_GPU_FRAME_TIME),
         event_reducer: Default::default(),
+        clock: Instant::now(),
+        show_pick_candidates: false,
         viewer: None,
         hover_threads: Default::default(),
     };
@@ -726,46 +903,36 @@ fn create_vello_renderer(render_cx: &RenderContext, surface: &RenderSurface<'_>)
     Renderer::new(
         &render_cx.devices[surface.dev_id].device,
         RendererOptions {
-            use_cpu: false,
-            antialiasing_support: vello::AaSupport::area_only(),
             num_init_threads: NonZeroUsize::new(1),
-            pipeline_cache: None,
+            // The quality levels only use the antialiasing for hairlines.
+            ..RasterOptions::HAIRLINES.renderer_options()
         },
     )
     .expect("Couldn't create renderer")
 }
 
-/// Update the transform/scale in all the items in a `GraphicsBag`.
+/// Update the transform/scale in all the items of a drawing.
 ///
 /// This also adapts line widths from the drawing so they are the correct
-/// size after scaling.
+/// size after scaling, which is skipped for pans, where the scale is unchanged.
 #[tracing::instrument(skip_all)]
-fn update_transform(
-    graphics: &mut GraphicsBag,
-    restroke_paints: Arc<[RestrokePaint]>,
-    transform: Affine,
-    view_scale: f64,
-    scale_factor: f64,
-) {
-    // Update root transform.
-    graphics.update_transform(Default::default(), transform);
+fn update_transform(td: &mut TDDrawing, transform: Affine, scale_factor: f64) {
+    let view_scale = uniform_scale(transform);
 
-    // Update default stroke.
-    graphics.update_paint(
+    // Update root transform, snapped so that hairlines through the drawing origin
+    // stay crisp. The view transform is already in device pixels.
+    td.graphics.update_transform(
         Default::default(),
-        FatPaint {
-            // Unfortunately, post-transform stroke widths are not supported.
-            stroke: Stroke::new(1.0 / view_scale),
-            stroke_paint: Some(Color::BLACK.into()),
-            fill_paint: None,
-        },
+        snap_to_pixel_centers(transform, Point::ORIGIN, 1.0),
     );
 
     #[allow(clippy::cast_possible_truncation, reason = "Deliberate truncation.")]
     let pixel_pitch = INCH / (96_f64 * scale_factor).trunc() as u64;
 
-    for r in restroke_paints.iter() {
-        r.adapt(graphics, pixel_pitch, view_scale, 1.0, f64::INFINITY);
+    if td.restroke(pixel_pitch, view_scale, 1.0, f64::INFINITY) {
+        // Update default stroke.
+        // Unfortunately, post-transform stroke widths are not supported.
+        *td.graphics.get_stroke_mut(Default::default()) = Stroke::new(1.0 / view_scale);
     }
 }
 
@@ -779,10 +946,14 @@ fn light_adapt_paints(graphics: &mut GraphicsBag, render_layer: &RenderLayer) {
         .indices
         .iter()
         .flat_map(|ih| {
-            graphics.get(*ih).map(|i| match i {
-                GraphicsItem::FatShape(s) => s.paint,
-                GraphicsItem::FatText(t) => t.paint,
-            })
+            let (paint, background) = match graphics.get(*ih) {
+                Some(GraphicsItem::FatShape(s)) => (Some(s.paint), None),
+                Some(GraphicsItem::FatText(t)) => (Some(t.paint), t.background.map(|b| b.paint)),
+                Some(GraphicsItem::FatTextOnPath(t)) => (Some(t.paint), None),
+                Some(GraphicsItem::FatMarker(m)) => (Some(m.paint), None),
+                _ => (None, None),
+            };
+            paint.into_iter().chain(background)
         })
         .collect();
 
@@ -797,169 +968,68 @@ fn light_adapt_paints(graphics: &mut GraphicsBag, render_layer: &RenderLayer) {
     }
 }
 
-use static_aabb2d_index::{StaticAABB2DIndex, StaticAABB2DIndexBuilder};
-
-/// Bounding box index for entities.
-struct EntityIndex {
-    bounds_index: StaticAABB2DIndex<f32>,
-    lines: Box<[PathSeg]>,
-    entity_mapping: Box<[EntityHandle]>,
-    item_mapping: Box<[ItemHandle]>,
+/// Build a spatial index for a drawing, reporting how long it took.
+fn build_spatial_index(d: &TDDrawing) -> SpatialIndex {
+    let build_started = Instant::now();
+    let spatial_index = SpatialIndex::new(&d.graphics, &d.render_layer);
+    let build_duration = Instant::now().saturating_duration_since(build_started);
+    eprintln!("Spatial index took {build_duration:?} to build.");
+    spatial_index
 }
 
-#[allow(
-    clippy::cast_possible_truncation,
-    reason = "The loss of range and precision is acceptable."
-)]
-impl EntityIndex {
-    fn new(d: &TDDrawing) -> Self {
-        let build_started = Instant::now();
-
-        let mut lines: Vec<PathSeg> = vec![];
-        let mut entity_mapping = vec![];
-        let mut item_mapping = vec![];
-        for (k, v) in d.item_entity_map.iter() {
-            let Some(GraphicsItem::FatShape(FatShape { path, .. })) = d.graphics.get(*k) else {
-                continue;
-            };
-
-            for seg in path.segments() {
-                entity_mapping.push(*v);
-                item_mapping.push(*k);
-                lines.push(seg);
-            }
-        }
-        let lines = Box::from(lines.as_slice());
-        let entity_mapping = Box::from(entity_mapping.as_slice());
-        let item_mapping = Box::from(item_mapping.as_slice());
-
-        let bounds_index = compute_bounds_index(&lines);
-
-        let build_duration = Instant::now().saturating_duration_since(build_started);
-        eprintln!("Bounds index took {build_duration:?} to build.");
-
-        Self {
-            bounds_index,
-            lines,
-            entity_mapping,
-            item_mapping,
-        }
-    }
+/// Size of the tiles used to find items hidden beneath opaque fills, in device pixels.
+const OCCLUSION_TILE_SIZE: f64 = 32.0;
 
-    /// Pick entity that is closest to dp.
-    #[tracing::instrument(skip_all)]
-    fn pick(&self, dp: Point, sp: f64) -> Option<EntityHandle> {
-        self.bounds_index
-            .query(
-                (dp.x - sp) as f32,
-                (dp.y - sp) as f32,
-                (dp.x + sp) as f32,
-                (dp.y + sp) as f32,
-            )
-            .into_iter()
-            .fold((f64::INFINITY, None), |(dsq, i), b| {
-                let ndsq = self.lines[b].nearest(dp, DEFAULT_ACCURACY).distance_sq;
-                if ndsq < dsq && ndsq < (sp * sp) {
-                    (ndsq, Some(b))
-                } else {
-                    (dsq, i)
-                }
-            })
-            .1
-            .map(|i| self.entity_mapping[i])
-    }
+/// Fragment holding the whole drawing, for views where nothing is culled.
+const DRAWING_FRAGMENT: FragmentId = FragmentId(0);
 
-    /// Query which entities' geometry overlaps with the bounds.
-    #[tracing::instrument(skip_all)]
-    fn query_items(&self, left: f32, top: f32, right: f32, bottom: f32) -> Vec<ItemHandle> {
-        let mut is: Vec<ItemHandle> = vec![];
-        for ih in self
-            .bounds_index
-            .query(left, top, right, bottom)
-            .iter()
-            .map(|&i| self.item_mapping[i])
-        {
-            if let Err(i) = is.binary_search(&ih) {
-                is.insert(i, ih);
-            }
-        }
-        is
-    }
+/// Number of offscreen text items to shape between frames.
+const TEXT_SHAPING_BATCH: usize = 256;
 
-    fn bounds(&self) -> Rect {
-        self.bounds_index
-            .bounds()
-            .map_or(Rect::default(), |b| Rect {
-                x0: b.min_x as f64,
-                y0: b.min_y as f64,
-                x1: b.max_x as f64,
-                y1: b.max_y as f64,
-            })
-    }
-}
+/// GPU time to aim for when adapting quality, leaving time for the CPU in a 60 Hz frame.
+const TARGET_GPU_FRAME_TIME: Duration = Duration::from_millis(12);
 
-/// Compute an index of bounding boxes for shapes.
-#[allow(
-    clippy::cast_possible_truncation,
-    reason = "The loss of range and precision is acceptable."
-)]
-#[tracing::instrument(skip_all)]
-fn compute_bounds_index(lines: &[PathSeg]) -> StaticAABB2DIndex<f32> {
-    let mut builder = StaticAABB2DIndexBuilder::<f32>::new(lines.len());
-    for shape in lines.iter() {
-        let bbox = Shape::bounding_box(&shape);
-        builder.add(
-            bbox.min_x() as f32,
-            bbox.min_y() as f32,
-            bbox.max_x() as f32,
-            bbox.max_y() as f32,
-        );
-    }
-    builder.build().unwrap()
-}
+/// Quality levels for drawings, from best to fastest.
+///
+/// Area antialiasing is already the fastest method, so only detail is reduced.
+const QUALITY_LEVELS: [Quality; 3] = [
+    Quality {
+        antialiasing: AaConfig::Area,
+        // Detail smaller than half a pixel can't be seen.
+        lod_tolerance: 0.5,
+        // Text smaller than this many pixels is illegible anyway.
+        greek_threshold: 3.0,
+    },
+    Quality {
+        antialiasing: AaConfig::Area,
+        lod_tolerance: 1.0,
+        greek_threshold: 5.0,
+    },
+    Quality {
+        antialiasing: AaConfig::Area,
+        lod_tolerance: 2.0,
+        greek_threshold: 8.0,
+    },
+];
 
-/// Index for culling text items.
-struct TextCullIndex {
-    bounds_index: StaticAABB2DIndex<f32>,
-    item_mapping: Box<[ItemHandle]>,
+/// Get the render options for drawings at the current quality.
+fn render_options(quality: &QualityController) -> RenderOptions<'static> {
+    let mut options = RENDER_OPTIONS;
+    quality.quality().apply(&mut options);
+    options
 }
 
-#[allow(
-    clippy::cast_possible_truncation,
-    reason = "The loss of range and precision is acceptable."
-)]
-impl TextCullIndex {
-    fn new(tv_env: &mut tabulon_vello::Environment, d: &TDDrawing) -> Self {
-        let measurements = tv_env.measure_text_items(&d.graphics, &d.render_layer);
-        let mut builder = StaticAABB2DIndexBuilder::<f32>::new(measurements.len());
-        let mut item_mapping = vec![];
-
-        for (ih, (di, s)) in measurements {
-            item_mapping.push(ih);
-            let bbox = (Affine::from(di)
-                * Rect::from_origin_size(Point::ZERO, s).to_path(DEFAULT_ACCURACY))
-            .bounding_box();
-            builder.add(
-                bbox.min_x() as f32,
-                bbox.min_y() as f32,
-                bbox.max_x() as f32,
-                bbox.max_y() as f32,
-            );
-        }
-
-        Self {
-            bounds_index: builder.build().unwrap(),
-            item_mapping: item_mapping.into(),
-        }
-    }
-
-    /// Query which text layouts overlap with the bounds.
-    #[tracing::instrument(skip_all)]
-    fn query_items(&self, left: f32, top: f32, right: f32, bottom: f32) -> BTreeSet<ItemHandle> {
-        self.bounds_index
-            .query(left, top, right, bottom)
-            .iter()
-            .map(|&l| self.item_mapping[l])
-            .collect()
-    }
-}
+/// Render options for drawings, before applying the quality.
+const RENDER_OPTIONS: RenderOptions<'static> = RenderOptions {
+    fill_rule: Fill::NonZero,
+    text_enabled: true,
+    hinting: false,
+    greek_threshold: QUALITY_LEVELS[0].greek_threshold,
+    greeking: Greeking::Bar,
+    lod_tolerance: QUALITY_LEVELS[0].lod_tolerance,
+    pixels_per_millimeter: 96.0 / 25.4,
+    dash_units: DashUnits::Local,
+    overrides: &StyleOverrides::new(),
+    deterministic: false,
+    collect_stats: false,
+};
@@ -7,7 +7,7 @@
 use anyhow::Result;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
-use vello::kurbo::{Circle, DEFAULT_ACCURACY, Ellipse, Line, RoundedRect, Shape, Stroke};
+use vello::kurbo::{Circle, Ellipse, Line, RoundedRect, Stroke};
 use vello::peniko::Color;
 use vello::peniko::color::palette;
 use vello::util::{RenderContext, RenderSurface};
@@ -20,6 +20,8 @@ use winit::window::Window;
 
 use vello::wgpu;
 
+use tabulon_vello::RasterOptions;
+
 enum RenderState<'s> {
     /// `RenderSurface` and `Window` for active rendering.
     Active {
@@ -135,12 +137,7 @@ impl ApplicationHandler for SimpleVelloApp<'_> {
                         &device_handle.queue,
                         &self.scene,
                         &surface.target_view,
-                        &vello::RenderParams {
-                            base_color: palette::css::BLACK, // Background color
-                            width,
-                            height,
-                            antialiasing_method: AaConfig::Msaa16,
-                        },
+                        &RASTER_OPTIONS.render_params(width, height),
                     )
                     .expect("failed to render to surface");
 
@@ -199,10 +196,8 @@ fn create_vello_renderer(render_cx: &RenderContext, surface: &RenderSurface<'_>)
     Renderer::new(
         &render_cx.devices[surface.dev_id].device,
         RendererOptions {
-            use_cpu: false,
-            antialiasing_support: vello::AaSupport::all(),
             num_init_threads: NonZeroUsize::new(1),
-            pipeline_cache: None,
+            ..RASTER_OPTIONS.renderer_options()
         },
     )
     .expect("Couldn't create renderer")
@@ -210,6 +205,13 @@ fn create_vello_renderer(render_cx: &RenderContext, surface: &RenderSurface<'_>)
 
 /// Add shapes to a vello scene. This does not actually render the shapes, but adds them
 /// to the Scene data structure which represents a set of objects to draw.
+/// How scenes are rasterized, on black.
+const RASTER_OPTIONS: RasterOptions = RasterOptions {
+    antialiasing: AaConfig::Msaa16,
+    base_color: palette::css::BLACK,
+    use_cpu: false,
+};
+
 fn add_shapes_to_scene(tv_environment: &mut tabulon_vello::Environment, scene: &mut Scene) {
     use tabulon::shape::{FatPaint, FatShape};
     use tabulon::{graphics_bag::GraphicsBag, render_layer::RenderLayer};
@@ -222,15 +224,15 @@ fn add_shapes_to_scene(tv_environment: &mut tabulon_vello::Environment, scene: &
         stroke: Stroke::new(6.0),
         stroke_paint: Some(Color::new([0.9804, 0.702, 0.5294, 1.]).into()),
         fill_paint: None,
+        fill_rule: None,
     });
     rl.push_with_bag(
         &mut gb,
         FatShape {
             transform: Default::default(),
+            clip: Default::default(),
             paint,
-            path: Arc::from(
-                RoundedRect::new(10.0, 10.0, 240.0, 240.0, 20.0).to_path(DEFAULT_ACCURACY),
-            ),
+            shape: Arc::new(RoundedRect::new(10.0, 10.0, 240.0, 240.0, 20.0).into()),
         },
     );
 
@@ -239,13 +241,15 @@ fn add_shapes_to_scene(tv_environment: &mut tabulon_vello::Environment, scene: &
         stroke: Default::default(),
         stroke_paint: None,
         fill_paint: Some(Color::new([0.9529, 0.5451, 0.6588, 1.]).into()),
+        fill_rule: None,
     });
     rl.push_with_bag(
         &mut gb,
         FatShape {
             transform: Default::default(),
+            clip: Default::default(),
             paint,
-            path: Arc::from(Circle::new((420.0, 200.0), 120.0).to_path(DEFAULT_ACCURACY)),
+            shape: Arc::new(Circle::new((420.0, 200.0), 120.0).into()),
         },
     );
 
@@ -254,15 +258,15 @@ fn add_shapes_to_scene(tv_environment: &mut tabulon_vello::Environment, scene: &
         stroke: Default::default(),
         stroke_paint: None,
         fill_paint: Some(Color::new([0.7961, 0.651, 0.9686, 1.]).into()),
+        fill_rule: None,
     });
     rl.push_with_bag(
         &mut gb,
         FatShape {
             transform: Default::default(),
+            clip: Default::default(),
             paint,
-            path: Arc::from(
-                Ellipse::new((250.0, 420.0), (100.0, 160.0), -90.0).to_path(DEFAULT_ACCURACY),
-            ),
+            shape: Arc::new(Ellipse::new((250.0, 420.0), (100.0, 160.0), -90.0).into()),
         },
     );
 
@@ -271,13 +275,15 @@ fn add_shapes_to_scene(tv_environment: &mut tabulon_vello::Environment, scene: &
         stroke: Stroke::new(6.0),
         stroke_paint: Some(Color::new([0.5373, 0.7059, 0.9804, 1.]).into()),
         fill_paint: None,
+        fill_rule: None,
     });
     rl.push_with_bag(
         &mut gb,
         FatShape {
             transform: Default::default(),
+            clip: Default::default(),
             paint,
-            path: Arc::from(Line::new((260.0, 20.0), (620.0, 100.0)).to_path(DEFAULT_ACCURACY)),
+            shape: Arc::new(Line::new((260.0, 20.0), (620.0, 100.0)).into()),
         },
     );
 
@@ -0,0 +1,544 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Animation of transforms and dash offsets over time.
+//!
+//! An [`Animator`] interpolates the local parts of transforms in a [`GraphicsBag`]
+//! towards targets, such as a view transform for zooming to fit or to a selection.
+//! Each frame, [`Animator::tick`] produces a batch of updates for
+//! [`GraphicsBag::update_transforms`].
+//!
+//! An [`Animator`] can also [march the dashes](Animator::march_dashes) of stroked
+//! paints along their strokes, as for selection marquees, by advancing their dash
+//! offsets in place, without registering new paints, and [flash](Animator::flash)
+//! paints by fading them out, as for highlighting an item that was navigated to.
+//!
+//! Time is supplied by the caller in seconds, from any fixed origin, so this works
+//! without a clock from the standard library.
+
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+
+use core::f64::consts::TAU;
+
+use peniko::kurbo::{Affine, Vec2};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+use crate::{DecomposedAffine, GraphicsBag, PaintHandle, TransformHandle, shape::FatPaint};
+
+/// Timing curve of an animation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Start slowly, then accelerate.
+    EaseIn,
+    /// Start quickly, then decelerate.
+    EaseOut,
+    /// Start and end slowly.
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map the fraction of time elapsed, from 0 to 1, to the fraction of progress.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t) * (1.0 - t) * (1.0 - t),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    let u = 2.0 - 2.0 * t;
+                    1.0 - u * u * u * 0.5
+                }
+            }
+        }
+    }
+}
+
+/// Interpolate between two transforms.
+///
+/// The transforms are [decomposed](DecomposedAffine), so rotations turn the short way
+/// round without distorting, and scales change geometrically, so zooming appears to
+/// proceed at a constant rate.
+pub fn interpolate(from: Affine, to: Affine, t: f64) -> Affine {
+    let (a, b) = (DecomposedAffine::from(from), DecomposedAffine::from(to));
+    let lerp = |x: f64, y: f64| x + (y - x) * t;
+    let scale = |x: f64, y: f64| {
+        if x * y > 0.0 {
+            x * (y / x).powf(t)
+        } else {
+            lerp(x, y)
+        }
+    };
+    let turn = b.rotation - a.rotation;
+    let turn = turn - (turn / TAU).round() * TAU;
+    DecomposedAffine {
+        translation: a.translation.lerp(b.translation, t),
+        rotation: a.rotation + turn * t,
+        scale: Vec2::new(scale(a.scale.x, b.scale.x), scale(a.scale.y, b.scale.y)),
+        shear: lerp(a.shear, b.shear),
+    }
+    .into()
+}
+
+/// Identifier of an animation started by [`Animator::animate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AnimationId(u64);
+
+/// An animation of the local part of one transform.
+struct TransformAnimation {
+    id: AnimationId,
+    target: TransformHandle,
+    from: Affine,
+    to: Affine,
+    start: f64,
+    duration: f64,
+    easing: Easing,
+    on_complete: Option<Box<dyn FnOnce()>>,
+}
+
+/// Dash offsets of one paint, advancing at a constant speed.
+struct DashAnimation {
+    paint: PaintHandle,
+    from: f64,
+    speed: f64,
+    start: f64,
+    /// Length after which the dash pattern repeats, or zero if it doesn't.
+    period: f64,
+}
+
+impl DashAnimation {
+    fn offset(&self, now: f64) -> f64 {
+        let offset = self.from + self.speed * (now - self.start);
+        if self.period > 0.0 {
+            // Keep offsets small, so they don't lose precision over long runs.
+            let o = offset % self.period;
+            if o < 0.0 { o + self.period } else { o }
+        } else {
+            offset
+        }
+    }
+}
+
+/// A paint fading out from its original colors.
+struct FlashAnimation {
+    paint: PaintHandle,
+    original: FatPaint,
+    start: f64,
+    duration: f64,
+    easing: Easing,
+}
+
+impl FlashAnimation {
+    /// Get the faded paint at fraction `t` of the duration.
+    fn paint(&self, t: f64) -> FatPaint {
+        #[allow(
+            clippy::cast_possible_truncation,
+            reason = "Alpha doesn't need more precision than f32."
+        )]
+        let alpha = (1.0 - self.easing.apply(t)) as f32;
+        let original = &self.original;
+        FatPaint {
+            stroke: original.stroke.clone(),
+            stroke_paint: original
+                .stroke_paint
+                .clone()
+                .map(|b| b.multiply_alpha(alpha)),
+            fill_paint: original.fill_paint.clone().map(|b| b.multiply_alpha(alpha)),
+            fill_rule: original.fill_rule,
+        }
+    }
+}
+
+/// Interpolates transforms towards targets over time.
+///
+/// See the [module documentation](self) for details.
+#[derive(Default)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Completion callbacks don't implement Debug."
+)]
+pub struct Animator {
+    animations: Vec<TransformAnimation>,
+    dashes: Vec<DashAnimation>,
+    flashes: Vec<FlashAnimation>,
+    next_id: u64,
+}
+
+impl Animator {
+    /// Animate the local part of `target` from its current value in `graphics` to `to`.
+    ///
+    /// The animation starts at time `now` and lasts `duration` seconds. An animation
+    /// already running on `target` is replaced, starting from wherever `graphics`
+    /// currently has it, so retargeting mid-flight does not jump.
+    pub fn animate(
+        &mut self,
+        graphics: &GraphicsBag,
+        target: TransformHandle,
+        to: Affine,
+        now: f64,
+        duration: f64,
+        easing: Easing,
+    ) -> AnimationId {
+        self.cancel(target);
+        let id = AnimationId(self.next_id);
+        self.next_id += 1;
+        self.animations.push(TransformAnimation {
+            id,
+            target,
+            from: graphics.get_local_transform(target),
+            to,
+            start: now,
+            duration,
+            easing,
+            on_complete: None,
+        });
+        id
+    }
+
+    /// Call `f` when an animation completes.
+    ///
+    /// `f` is not called if the animation is cancelled or replaced. Returns `false`
+    /// if the animation is no longer running.
+    pub fn on_complete(&mut self, id: AnimationId, f: impl FnOnce() + 'static) -> bool {
+        match self.animations.iter_mut().find(|a| a.id == id) {
+            Some(a) => {
+                a.on_complete = Some(Box::new(f));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop animating `target`, leaving it where it is.
+    pub fn cancel(&mut self, target: TransformHandle) {
+        self.animations.retain(|a| a.target != target);
+    }
+
+    /// March the dashes of `paint` along its strokes at `speed`, from time `now`.
+    ///
+    /// `speed` is in the units of the paint's stroke per second, and negative speeds
+    /// march backwards. Dashes march from their current offset in `graphics` until
+    /// [stopped](Self::stop_dashes), replacing any march already running on `paint`.
+    pub fn march_dashes(
+        &mut self,
+        graphics: &GraphicsBag,
+        paint: PaintHandle,
+        speed: f64,
+        now: f64,
+    ) {
+        self.stop_dashes(paint);
+        let stroke = &graphics.get_paint(paint).stroke;
+        let length: f64 = stroke.dash_pattern.iter().sum();
+        // Patterns with an odd number of lengths alternate dashes and gaps on repeat.
+        let period = if stroke.dash_pattern.len() % 2 == 1 {
+            length * 2.0
+        } else {
+            length
+        };
+        self.dashes.push(DashAnimation {
+            paint,
+            from: stroke.dash_offset,
+            speed,
+            start: now,
+            period,
+        });
+    }
+
+    /// Stop marching the dashes of `paint`, leaving them where they are.
+    pub fn stop_dashes(&mut self, paint: PaintHandle) {
+        self.dashes.retain(|d| d.paint != paint);
+    }
+
+    /// Flash `paint` by fading it out from its current colors in `graphics`.
+    ///
+    /// The fill and stroke fade from time `now` to transparent over `duration` seconds,
+    /// with `easing` giving the progress of the fade, so [`Easing::EaseIn`] holds the
+    /// highlight before fading it quickly. The paint is left transparent, and whatever
+    /// it draws can be removed once the paint is no longer [flashing](Self::is_flashing).
+    /// A flash already running on `paint` is restarted from its original colors.
+    pub fn flash(
+        &mut self,
+        graphics: &GraphicsBag,
+        paint: PaintHandle,
+        now: f64,
+        duration: f64,
+        easing: Easing,
+    ) {
+        let original = match self.flashes.iter().position(|f| f.paint == paint) {
+            Some(i) => self.flashes.swap_remove(i).original,
+            None => graphics.get_paint(paint).clone(),
+        };
+        self.flashes.push(FlashAnimation {
+            paint,
+            original,
+            start: now,
+            duration,
+            easing,
+        });
+    }
+
+    /// Stop flashing `paint`, returning its original colors, or `None` if it was not
+    /// flashing.
+    ///
+    /// The paint in the bag is left as it is; set it to the returned paint to restore it.
+    pub fn stop_flash(&mut self, paint: PaintHandle) -> Option<FatPaint> {
+        let i = self.flashes.iter().position(|f| f.paint == paint)?;
+        Some(self.flashes.swap_remove(i).original)
+    }
+
+    /// Check whether `paint` is flashing.
+    pub fn is_flashing(&self, paint: PaintHandle) -> bool {
+        self.flashes.iter().any(|f| f.paint == paint)
+    }
+
+    /// Check whether any animations are running, including marching dashes and flashes.
+    pub fn is_animating(&self) -> bool {
+        !self.animations.is_empty() || !self.dashes.is_empty() || !self.flashes.is_empty()
+    }
+
+    /// Advance animations to time `now`, returning the transforms to update.
+    ///
+    /// Animations that reach their target are removed after their final value is
+    /// included, and their completion callbacks are called.
+    #[must_use]
+    pub fn tick(&mut self, now: f64) -> Vec<(TransformHandle, Affine)> {
+        let mut updates = Vec::with_capacity(self.animations.len());
+        let mut finished = Vec::new();
+        self.animations.retain_mut(|a| {
+            let t = if a.duration > 0.0 {
+                (now - a.start) / a.duration
+            } else {
+                1.0
+            };
+            if t >= 1.0 {
+                updates.push((a.target, a.to));
+                finished.extend(a.on_complete.take());
+                false
+            } else {
+                updates.push((a.target, interpolate(a.from, a.to, a.easing.apply(t))));
+                true
+            }
+        });
+        for f in finished {
+            f();
+        }
+        updates
+    }
+
+    /// Get the dash offsets of marching paints at time `now`.
+    #[must_use]
+    pub fn tick_dashes(&self, now: f64) -> Vec<(PaintHandle, f64)> {
+        self.dashes
+            .iter()
+            .map(|d| (d.paint, d.offset(now)))
+            .collect()
+    }
+
+    /// Advance flashes to time `now`, returning the paints to update.
+    ///
+    /// Flashes that have faded out are removed after their transparent paint is included.
+    #[must_use]
+    pub fn tick_flashes(&mut self, now: f64) -> Vec<(PaintHandle, FatPaint)> {
+        let mut updates = Vec::with_capacity(self.flashes.len());
+        self.flashes.retain(|f| {
+            let t = if f.duration > 0.0 {
+                (now - f.start) / f.duration
+            } else {
+                1.0
+            };
+            updates.push((f.paint, f.paint(t)));
+            t < 1.0
+        });
+        updates
+    }
+
+    /// Advance animations to time `now`, and apply them to `graphics`.
+    ///
+    /// Returns whether any animations are still running, so another frame is needed.
+    pub fn apply(&mut self, graphics: &mut GraphicsBag, now: f64) -> bool {
+        graphics.update_transforms(self.tick(now));
+        for (paint, offset) in self.tick_dashes(now) {
+            graphics.get_stroke_mut(paint).dash_offset = offset;
+        }
+        for (paint, faded) in self.tick_flashes(now) {
+            *graphics.get_paint_mut(paint) = faded;
+        }
+        self.is_animating()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+    use core::f64::consts::FRAC_PI_2;
+    use peniko::kurbo::Point;
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert!(
+                easing.apply(0.0) == 0.0 && easing.apply(1.0) == 1.0,
+                "{easing:?} should start at 0 and end at 1."
+            );
+        }
+        assert_eq!(
+            Easing::EaseInOut.apply(0.5),
+            0.5,
+            "EaseInOut should be halfway at half time."
+        );
+    }
+
+    #[test]
+    fn interpolation_keeps_shape() {
+        let from = Affine::rotate(0.1);
+        let to = Affine::rotate(TAU - 0.1) * Affine::scale(4.0);
+        let mid = interpolate(from, to, 0.5);
+        let p = mid * Point::new(1.0, 0.0);
+        assert!(
+            (p.to_vec2().length() - 2.0).abs() < 1e-9,
+            "Scale should change geometrically, without distortion."
+        );
+        assert!(
+            p.y.abs() < 1e-9 && p.x > 0.0,
+            "Rotation should take the short way round."
+        );
+    }
+
+    #[test]
+    fn animate_view_transform() {
+        let mut graphics = GraphicsBag::default();
+        let child = graphics.register_transform(Default::default(), Affine::translate((1.0, 0.0)));
+        let mut animator = Animator::default();
+        let id = animator.animate(
+            &graphics,
+            Default::default(),
+            Affine::rotate(FRAC_PI_2),
+            10.0,
+            2.0,
+            Easing::Linear,
+        );
+        let done = Rc::new(Cell::new(false));
+        let flag = done.clone();
+        assert!(
+            animator.on_complete(id, move || flag.set(true)),
+            "Callbacks can be added to running animations."
+        );
+
+        assert!(
+            animator.apply(&mut graphics, 11.0),
+            "The animation should be running halfway through."
+        );
+        let p = graphics.get_transform(child) * Point::ORIGIN;
+        assert!(
+            (p - Point::new(0.5_f64.sqrt(), 0.5_f64.sqrt())).hypot() < 1e-9,
+            "Children should follow the animated transform, got {p:?}."
+        );
+        assert!(!done.get(), "Callbacks should wait for completion.");
+
+        assert!(
+            !animator.apply(&mut graphics, 12.5),
+            "The animation should finish after its duration."
+        );
+        let p = graphics.get_transform(child) * Point::ORIGIN;
+        assert!(
+            (p - Point::new(0.0, 1.0)).hypot() < 1e-9,
+            "The animation should end at its target, got {p:?}."
+        );
+        assert!(done.get(), "Callbacks should be called on completion.");
+    }
+
+    #[test]
+    fn march_dashes() {
+        use crate::shape::FatPaint;
+        use peniko::kurbo::Stroke;
+
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0).with_dashes(1.0, [4.0, 2.0]),
+            ..Default::default()
+        });
+        let mut animator = Animator::default();
+        animator.march_dashes(&graphics, paint, 2.0, 10.0);
+        assert!(
+            animator.apply(&mut graphics, 11.0),
+            "Marching dashes should keep animating."
+        );
+        assert_eq!(
+            graphics.get_paint(paint).stroke.dash_offset,
+            3.0,
+            "Dashes should march from their offset at the given speed."
+        );
+        animator.apply(&mut graphics, 13.0);
+        assert_eq!(
+            graphics.get_paint(paint).stroke.dash_offset,
+            1.0,
+            "Offsets should wrap around the length of the pattern."
+        );
+
+        animator.stop_dashes(paint);
+        assert!(
+            !animator.apply(&mut graphics, 14.0),
+            "Stopped dashes should not need more frames."
+        );
+        assert_eq!(
+            graphics.get_paint(paint).stroke.dash_offset,
+            1.0,
+            "Stopped dashes should stay where they are."
+        );
+    }
+
+    #[test]
+    fn flash_fades_out() {
+        use peniko::{Brush, Color};
+
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::WHITE.into()),
+            ..Default::default()
+        });
+        let alpha = |graphics: &GraphicsBag| match &graphics.get_paint(paint).fill_paint {
+            Some(Brush::Solid(c)) => c.components[3],
+            _ => unreachable!(),
+        };
+        let mut animator = Animator::default();
+        animator.flash(&graphics, paint, 10.0, 2.0, Easing::Linear);
+        assert!(
+            animator.apply(&mut graphics, 11.0),
+            "The flash should be running halfway through."
+        );
+        assert_eq!(alpha(&graphics), 0.5, "The paint should fade with time.");
+
+        animator.flash(&graphics, paint, 11.0, 2.0, Easing::Linear);
+        animator.apply(&mut graphics, 11.0);
+        assert_eq!(
+            alpha(&graphics),
+            1.0,
+            "Restarting a flash should fade from the original colors."
+        );
+
+        assert!(
+            !animator.apply(&mut graphics, 13.0),
+            "The flash should finish after its duration."
+        );
+        assert!(
+            alpha(&graphics) == 0.0 && !animator.is_flashing(paint),
+            "The paint should be left transparent."
+        );
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Drawing graphics items with any renderer.
+//!
+//! A backend implements [`Renderer`], which fills and strokes paths, draws images and
+//! text, and clips and groups what it draws. [`draw_items`] and [`draw_layer_stack`]
+//! resolve the transforms, paints, and clips of items, convert shapes and markers to
+//! paths, and call the renderer, so backends don't match on [`GraphicsItem`]
+//! themselves, and keep working as kinds of items are added.
+//!
+//! Text is passed on as items, because how it is shaped and drawn is up to each
+//! backend; a backend without text support can leave those methods as they are, and
+//! text is not drawn.
+
+use peniko::{
+    Brush, Fill, Image,
+    kurbo::{Affine, BezPath, Rect, Stroke},
+};
+
+use crate::{
+    GraphicsBag, GraphicsItem, ItemHandle,
+    layer_stack::LayerStack,
+    shape::{DashUnits, FatPaint, pre_dash},
+    uniform_scale,
+};
+#[cfg(feature = "text")]
+use crate::{text::FatText, text_on_path::FatTextOnPath};
+
+/// Target that graphics items are drawn into.
+///
+/// Transforms map the coordinates of paths and images to device coordinates.
+pub trait Renderer {
+    /// Start drawing `item`, before the calls that draw it, if any.
+    ///
+    /// This lets renderers tell items apart, such as to count them. By default, it does
+    /// nothing.
+    fn begin_item(&mut self, item: ItemHandle) {
+        let _ = item;
+    }
+
+    /// Fill a path.
+    fn fill(&mut self, transform: Affine, fill_rule: Fill, brush: &Brush, path: &BezPath);
+
+    /// Stroke a path.
+    ///
+    /// The stroke is in the coordinates of `path`, and scaled by `transform` with it.
+    /// Strokes only have dashes if [`dashes_strokes`](Self::dashes_strokes) is `true`.
+    fn stroke(&mut self, transform: Affine, stroke: &Stroke, brush: &Brush, path: &BezPath);
+
+    /// Whether [`stroke`](Self::stroke) draws the dashes of strokes.
+    ///
+    /// If not, dashed strokes are split into their dashes with [`pre_dash`] before they
+    /// are passed on. By default, renderers are expected to draw dashes.
+    fn dashes_strokes(&self) -> bool {
+        true
+    }
+
+    /// Draw an image, mapping its pixels with `transform`.
+    fn image(&mut self, transform: Affine, image: &Image, opacity: f32);
+
+    /// Draw a text item with its paint, and the paint of its background if it has one.
+    ///
+    /// `transform` is the text's transform, with the origin at its insertion point.
+    /// By default, text is not drawn.
+    #[cfg(feature = "text")]
+    fn text(
+        &mut self,
+        item: ItemHandle,
+        transform: Affine,
+        text: &FatText,
+        paint: &FatPaint,
+        background: Option<&FatPaint>,
+    ) {
+        let _ = (item, transform, text, paint, background);
+    }
+
+    /// Draw a text item along its path, with its paint.
+    ///
+    /// By default, text on paths is not drawn.
+    #[cfg(feature = "text")]
+    fn text_on_path(
+        &mut self,
+        item: ItemHandle,
+        transform: Affine,
+        text: &FatTextOnPath,
+        paint: &FatPaint,
+    ) {
+        let _ = (item, transform, text, paint);
+    }
+
+    /// Clip what is drawn until the matching [`pop`](Self::pop) to the inside of `path`.
+    fn push_clip(&mut self, transform: Affine, path: &BezPath);
+
+    /// Draw what follows as a group, composited with `opacity` at the matching
+    /// [`pop`](Self::pop).
+    ///
+    /// `bounds` contains everything drawn in the group, in device coordinates.
+    fn push_layer(&mut self, opacity: f32, bounds: Rect);
+
+    /// End the most recent clip or layer.
+    fn pop(&mut self);
+}
+
+/// Options for [`draw_items`].
+#[derive(Debug, Clone, Copy)]
+pub struct DrawOptions {
+    /// Fill rule for filling shapes whose paints do not have one.
+    pub fill_rule: Fill,
+    /// Tolerance for converting shapes to paths, in device pixels.
+    pub tolerance: f64,
+    /// Pixel density of the device, for sizing [markers](crate::marker::FatMarker) in
+    /// physical units.
+    pub pixels_per_millimeter: f64,
+    /// Units of the dash patterns of strokes.
+    pub dash_units: DashUnits,
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        Self {
+            fill_rule: Fill::NonZero,
+            tolerance: 0.1,
+            // The CSS reference density of 96 pixels per inch.
+            pixels_per_millimeter: 96.0 / 25.4,
+            dash_units: DashUnits::Local,
+        }
+    }
+}
+
+/// Draw items from `graphics` with `renderer`, in iteration order.
+pub fn draw_items(
+    renderer: &mut dyn Renderer,
+    graphics: &GraphicsBag,
+    items: impl IntoIterator<Item = ItemHandle>,
+    options: &DrawOptions,
+) {
+    for idx in items {
+        let Some(item) = graphics.get(idx) else {
+            continue;
+        };
+        renderer.begin_item(idx);
+        match item {
+            GraphicsItem::FatShape(s) => {
+                let transform = graphics.get_transform(s.transform);
+                let path = s
+                    .shape
+                    .path(options.tolerance / uniform_scale(transform).max(f64::EPSILON));
+                let clip = graphics.get_clip(s.clip);
+                if let Some(clip) = clip {
+                    renderer.push_clip(graphics.get_transform(clip.transform), &clip.path);
+                }
+                draw_path(
+                    renderer,
+                    transform,
+                    graphics.get_paint(s.paint),
+                    &path,
+                    options,
+                );
+                if clip.is_some() {
+                    renderer.pop();
+                }
+            }
+            #[cfg(feature = "text")]
+            GraphicsItem::FatText(t) => renderer.text(
+                idx,
+                graphics.get_transform(t.transform),
+                t,
+                graphics.get_paint(t.paint),
+                t.background.map(|b| graphics.get_paint(b.paint)),
+            ),
+            #[cfg(feature = "text")]
+            GraphicsItem::FatTextOnPath(t) => renderer.text_on_path(
+                idx,
+                graphics.get_transform(t.transform),
+                t,
+                graphics.get_paint(t.paint),
+            ),
+            GraphicsItem::FatImage(i) => {
+                if i.opacity > 0.0 {
+                    renderer.image(
+                        graphics.get_transform(i.transform) * i.placement,
+                        &i.image,
+                        i.opacity,
+                    );
+                }
+            }
+            GraphicsItem::FatMarker(m) => {
+                let transform = graphics.get_transform(m.transform);
+                if let Some(path) = m.local_path(transform, options.pixels_per_millimeter) {
+                    draw_path(
+                        renderer,
+                        transform,
+                        graphics.get_paint(m.paint),
+                        &path,
+                        options,
+                    );
+                }
+            }
+            GraphicsItem::FatChunk(c) => {
+                let transform = graphics.get_transform(c.transform);
+                let tolerance = options.tolerance / uniform_scale(transform).max(f64::EPSILON);
+                let item_paint = c.paint.map(|p| graphics.get_paint(p));
+                for (shape, paint) in c.shapes() {
+                    draw_path(
+                        renderer,
+                        transform,
+                        item_paint.unwrap_or(paint),
+                        &shape.path(tolerance),
+                        options,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Draw the visible layers of a [`LayerStack`] with `renderer`.
+///
+/// Layers are drawn from the lowest `z` to the highest, and layers that are not fully
+/// opaque are drawn as a group.
+pub fn draw_layer_stack(
+    renderer: &mut dyn Renderer,
+    graphics: &GraphicsBag,
+    stack: &LayerStack,
+    options: &DrawOptions,
+) {
+    for l in stack.visible_layers() {
+        if l.opacity <= 0.0 || l.layer.indices.is_empty() {
+            continue;
+        }
+        let group = l.opacity < 1.0;
+        if group {
+            renderer.push_layer(
+                l.opacity,
+                graphics.layer_bounds(&l.layer).unwrap_or(Rect::ZERO),
+            );
+        }
+        draw_items(renderer, graphics, l.layer.indices.iter().copied(), options);
+        if group {
+            renderer.pop();
+        }
+    }
+}
+
+/// Fill and stroke a path with a paint.
+fn draw_path(
+    renderer: &mut dyn Renderer,
+    transform: Affine,
+    paint: &FatPaint,
+    path: &BezPath,
+    options: &DrawOptions,
+) {
+    if let Some(fill_paint) = &paint.fill_paint {
+        renderer.fill(
+            transform,
+            paint.fill_rule.unwrap_or(options.fill_rule),
+            fill_paint,
+            path,
+        );
+    }
+    if let Some(stroke_paint) = &paint.stroke_paint {
+        let stroke = options.dash_units.apply(&paint.stroke, transform);
+        if renderer.dashes_strokes() {
+            renderer.stroke(transform, &stroke, stroke_paint, path);
+        } else {
+            let (path, stroke) = pre_dash(path, &stroke);
+            renderer.stroke(transform, &stroke, stroke_paint, &path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layer_stack::StackedLayer,
+        render_layer::RenderLayer,
+        shape::{FatClip, FatShape},
+    };
+    use peniko::{
+        Color,
+        kurbo::{Circle, Line, PathEl, Shape},
+    };
+
+    extern crate alloc;
+    use alloc::{sync::Arc, vec, vec::Vec};
+
+    /// Records calls, by name.
+    #[derive(Default)]
+    struct Calls(Vec<&'static str>);
+
+    impl Renderer for Calls {
+        fn fill(&mut self, _: Affine, _: Fill, _: &Brush, _: &BezPath) {
+            self.0.push("fill");
+        }
+        fn stroke(&mut self, _: Affine, _: &Stroke, _: &Brush, _: &BezPath) {
+            self.0.push("stroke");
+        }
+        fn image(&mut self, _: Affine, _: &Image, _: f32) {
+            self.0.push("image");
+        }
+        fn push_clip(&mut self, _: Affine, _: &BezPath) {
+            self.0.push("clip");
+        }
+        fn push_layer(&mut self, _: f32, _: Rect) {
+            self.0.push("layer");
+        }
+        fn pop(&mut self) {
+            self.0.push("pop");
+        }
+    }
+
+    #[test]
+    fn draw_clipped_shape_in_group() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: Some(Color::WHITE.into()),
+            fill_rule: None,
+        });
+        let clip = graphics.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(Rect::new(0.0, 0.0, 1.0, 1.0).to_path(0.1)),
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                clip,
+                shape: Arc::new(Circle::new((0.0, 0.0), 1.0).into()),
+                ..Default::default()
+            },
+        );
+        let mut stack = LayerStack::default();
+        stack.push(StackedLayer {
+            layer,
+            opacity: 0.5,
+            ..Default::default()
+        });
+
+        let mut calls = Calls::default();
+        draw_layer_stack(&mut calls, &graphics, &stack, &DrawOptions::default());
+        assert_eq!(
+            calls.0,
+            vec!["layer", "clip", "fill", "stroke", "pop", "pop"],
+            "Clips and groups should enclose the fill and stroke of the shape."
+        );
+    }
+
+    /// Records the dashes and subpaths of strokes, without drawing dashes itself.
+    #[derive(Default)]
+    struct Dashless(Vec<(usize, usize)>);
+
+    impl Renderer for Dashless {
+        fn fill(&mut self, _: Affine, _: Fill, _: &Brush, _: &BezPath) {}
+        fn stroke(&mut self, _: Affine, stroke: &Stroke, _: &Brush, path: &BezPath) {
+            let subpaths = path
+                .iter()
+                .filter(|el| matches!(el, PathEl::MoveTo(_)))
+                .count();
+            self.0.push((stroke.dash_pattern.len(), subpaths));
+        }
+        fn dashes_strokes(&self) -> bool {
+            false
+        }
+        fn image(&mut self, _: Affine, _: &Image, _: f32) {}
+        fn push_clip(&mut self, _: Affine, _: &BezPath) {}
+        fn push_layer(&mut self, _: f32, _: Rect) {}
+        fn pop(&mut self) {}
+    }
+
+    #[test]
+    fn pre_dash_for_renderers_without_dashes() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0).with_dashes(0.0, [2.0, 3.0]),
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let line = graphics.push(FatShape {
+            paint,
+            shape: Arc::new(Line::new((0.0, 0.0), (50.0, 0.0)).into()),
+            ..Default::default()
+        });
+
+        let mut dashless = Dashless::default();
+        draw_items(&mut dashless, &graphics, [line], &DrawOptions::default());
+        assert_eq!(
+            dashless.0,
+            [(0, 10)],
+            "Dashed strokes should be split into one subpath for each dash."
+        );
+
+        // Dashes of 2 and 3 pixels at a scale of 5 are 0.4 and 0.6 units long.
+        graphics.update_transform(Default::default(), Affine::scale(5.0));
+        let mut dashless = Dashless::default();
+        let options = DrawOptions {
+            dash_units: DashUnits::Device,
+            ..Default::default()
+        };
+        draw_items(&mut dashless, &graphics, [line], &options);
+        assert_eq!(
+            dashless.0,
+            [(0, 50)],
+            "Dashes in device pixels should be shorter in local units when zoomed in."
+        );
+    }
+}
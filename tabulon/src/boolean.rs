@@ -0,0 +1,498 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Boolean operations on filled paths, and offsetting of their regions.
+//!
+//! Paths are flattened to polygons within a tolerance, so results are polygons.
+//! Points are snapped to a grid much finer than the tolerance, which makes coincident
+//! edges and vertices of the operands exactly equal. Edges are split where they cross
+//! or touch, and each piece is kept if the result is filled on one side of it and not
+//! on the other, with the winding of each operand on either side found by ray casting.
+//! This handles overlapping edges, self-intersecting operands, and holes, and works
+//! with either fill rule.
+//!
+//! Open subpaths are treated as closed, as they are when filled.
+//!
+//! The cost is quadratic in the number of flattened edges in the worst case, so very
+//! large paths should be simplified first.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use peniko::{
+    Fill,
+    kurbo::{BezPath, Join, PathEl, Point, Stroke, StrokeOpts, Vec2, flatten, stroke},
+};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+/// A boolean operation on the regions filled by two paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BooleanOp {
+    /// Points in either region.
+    Union,
+    /// Points in both regions.
+    Intersection,
+    /// Points in the first region but not the second.
+    Difference,
+    /// Points in exactly one of the regions.
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersection => a && b,
+            Self::Difference => a && !b,
+            Self::Xor => a != b,
+        }
+    }
+}
+
+/// Apply `op` to the regions filled by `a` and `b` with `fill`.
+///
+/// The result is a polygon within `tolerance` of the exact result. Its subpaths are
+/// closed, and wind so that the result is the same filled with either fill rule.
+pub fn boolean(a: &BezPath, b: &BezPath, op: BooleanOp, fill: Fill, tolerance: f64) -> BezPath {
+    boolean_with_fills([(a, fill), (b, fill)], op, tolerance)
+}
+
+/// Get the union of the regions filled by `a` and `b` with `fill`.
+///
+/// See [`boolean`] for details.
+pub fn union(a: &BezPath, b: &BezPath, fill: Fill, tolerance: f64) -> BezPath {
+    boolean(a, b, BooleanOp::Union, fill, tolerance)
+}
+
+/// Get the intersection of the regions filled by `a` and `b` with `fill`.
+///
+/// See [`boolean`] for details.
+pub fn intersection(a: &BezPath, b: &BezPath, fill: Fill, tolerance: f64) -> BezPath {
+    boolean(a, b, BooleanOp::Intersection, fill, tolerance)
+}
+
+/// Get the region filled by `a` with `fill` less the region filled by `b`.
+///
+/// See [`boolean`] for details.
+pub fn difference(a: &BezPath, b: &BezPath, fill: Fill, tolerance: f64) -> BezPath {
+    boolean(a, b, BooleanOp::Difference, fill, tolerance)
+}
+
+/// Grow the region filled by `path` with `fill` by `distance`, or shrink it if negative.
+///
+/// Corners are joined with `join` when growing, and the corners of holes when shrinking.
+/// The result is a polygon within `tolerance` of the exact offset, as with [`boolean`].
+pub fn offset(path: &BezPath, fill: Fill, distance: f64, join: Join, tolerance: f64) -> BezPath {
+    if distance == 0.0 || distance.is_nan() {
+        return boolean(path, &BezPath::new(), BooleanOp::Union, fill, tolerance);
+    }
+    // The boundary of the region, stroked to twice the distance, covers everything
+    // within the distance of the boundary on both sides.
+    let mut closed = BezPath::new();
+    for el in path {
+        if matches!(el, PathEl::MoveTo(_)) && !closed.elements().is_empty() {
+            closed.close_path();
+        }
+        if el != PathEl::ClosePath {
+            closed.push(el);
+        }
+    }
+    if !closed.elements().is_empty() {
+        closed.close_path();
+    }
+    let band = stroke(
+        &closed,
+        &Stroke::new(distance.abs() * 2.0).with_join(join),
+        &StrokeOpts::default(),
+        tolerance * 0.5,
+    );
+    let op = if distance > 0.0 {
+        BooleanOp::Union
+    } else {
+        BooleanOp::Difference
+    };
+    boolean_with_fills([(path, fill), (&band, Fill::NonZero)], op, tolerance)
+}
+
+/// A point snapped to the grid, in grid units.
+type Key = (i64, i64);
+
+/// Apply `op` to two paths with their own fill rules.
+fn boolean_with_fills(operands: [(&BezPath, Fill); 2], op: BooleanOp, tolerance: f64) -> BezPath {
+    let tolerance = if tolerance > 0.0 { tolerance } else { 0.1 };
+    let grid = Grid {
+        spacing: tolerance * 1e-3,
+    };
+
+    // Flatten both operands to edges between snapped points.
+    let mut edges: Vec<(Key, Key, usize)> = Vec::new();
+    for (operand, (path, _)) in operands.iter().enumerate() {
+        let mut start = None;
+        let mut last = None;
+        let close = |edges: &mut Vec<_>, start: &mut Option<Key>, last: &mut Option<Key>| {
+            if let (Some(s), Some(l)) = (*start, *last) {
+                if s != l {
+                    edges.push((l, s, operand));
+                }
+            }
+            *last = *start;
+        };
+        flatten(*path, tolerance * 0.5, |el| match el {
+            PathEl::MoveTo(p) => {
+                close(&mut edges, &mut start, &mut last);
+                start = Some(grid.snap(p));
+                last = start;
+            }
+            PathEl::LineTo(p) => {
+                let k = grid.snap(p);
+                if let Some(l) = last {
+                    if l != k {
+                        edges.push((l, k, operand));
+                    }
+                }
+                last = Some(k);
+            }
+            PathEl::ClosePath => close(&mut edges, &mut start, &mut last),
+            PathEl::QuadTo(..) | PathEl::CurveTo(..) => {}
+        });
+        close(&mut edges, &mut start, &mut last);
+    }
+
+    // Net winding contribution of each operand to each edge, from the lesser key to the
+    // greater, after splitting edges where they meet, so coincident pieces are merged.
+    let mut merged: BTreeMap<(Key, Key), [i32; 2]> = BTreeMap::new();
+    for (i, splits) in split_edges(&edges, &grid).into_iter().enumerate() {
+        let (a, b, operand) = edges[i];
+        let mut points: Vec<(f64, Key)> = splits;
+        points.push((0.0, a));
+        points.push((1.0, b));
+        points.sort_by(|x, y| x.0.total_cmp(&y.0));
+        points.dedup_by_key(|p| p.1);
+        for w in points.windows(2) {
+            let (p, q) = (w[0].1, w[1].1);
+            if p == q {
+                continue;
+            }
+            let (key, sign) = if p < q { ((p, q), 1) } else { ((q, p), -1) };
+            merged.entry(key).or_default()[operand] += sign;
+        }
+    }
+    let merged: Vec<(Key, Key, [i32; 2])> = merged
+        .into_iter()
+        .filter(|(_, c)| *c != [0, 0])
+        .map(|((p, q), c)| (p, q, c))
+        .collect();
+
+    // Keep pieces that have the result filled on exactly one side.
+    let inside = |w: i32, fill: Fill| match fill {
+        Fill::NonZero => w != 0,
+        Fill::EvenOdd => w % 2 != 0,
+    };
+    let mut outgoing: BTreeMap<Key, Vec<Key>> = BTreeMap::new();
+    for (i, &(p, q, c)) in merged.iter().enumerate() {
+        let (plus, plus_is_left) = winding_beside(&merged, i);
+        let side = |left: bool| {
+            let w = |operand: usize| {
+                if left == plus_is_left {
+                    plus[operand]
+                } else if left {
+                    plus[operand] + c[operand]
+                } else {
+                    plus[operand] - c[operand]
+                }
+            };
+            op.apply(inside(w(0), operands[0].1), inside(w(1), operands[1].1))
+        };
+        let (left, right) = (side(true), side(false));
+        if left != right {
+            // The filled side is kept on the left.
+            let (from, to) = if left { (p, q) } else { (q, p) };
+            outgoing.entry(from).or_default().push(to);
+        }
+    }
+
+    // Every vertex of the boundary has as many pieces leaving it as arriving, so the
+    // pieces can be linked into closed loops. How they are linked does not change the
+    // winding of the result.
+    let mut out = BezPath::new();
+    while let Some((&start, _)) = outgoing.iter().find(|(_, v)| !v.is_empty()) {
+        let mut ring = alloc::vec![start];
+        let mut at = start;
+        while let Some(next) = outgoing.get_mut(&at).and_then(Vec::pop) {
+            if next == start {
+                break;
+            }
+            ring.push(next);
+            at = next;
+        }
+        push_ring(&mut out, &ring, &grid);
+    }
+    out
+}
+
+/// Snapping of points to a square grid.
+struct Grid {
+    spacing: f64,
+}
+
+impl Grid {
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "Coordinates beyond the range of i64 grid units are not supported."
+    )]
+    fn snap(&self, p: Point) -> Key {
+        (
+            (p.x / self.spacing).round() as i64,
+            (p.y / self.spacing).round() as i64,
+        )
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "Keys come from snapping f64 coordinates."
+    )]
+    fn point(&self, k: Key) -> Point {
+        Point::new(k.0 as f64 * self.spacing, k.1 as f64 * self.spacing)
+    }
+}
+
+/// Get twice the signed area of the triangle `a`, `b`, `c`, exactly.
+fn orient(a: Key, b: Key, c: Key) -> i128 {
+    let ab = (i128::from(b.0 - a.0), i128::from(b.1 - a.1));
+    let ac = (i128::from(c.0 - a.0), i128::from(c.1 - a.1));
+    ab.0 * ac.1 - ab.1 * ac.0
+}
+
+/// Find where each edge must be split, as parameters along it and snapped points.
+fn split_edges(edges: &[(Key, Key, usize)], grid: &Grid) -> Vec<Vec<(f64, Key)>> {
+    let mut splits: Vec<Vec<(f64, Key)>> = alloc::vec![Vec::new(); edges.len()];
+    let bounds =
+        |&(a, b, _): &(Key, Key, usize)| (a.0.min(b.0), a.1.min(b.1), a.0.max(b.0), a.1.max(b.1));
+    // Sweep along x, so only edges that overlap in x are compared.
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by_key(|&i| bounds(&edges[i]).0);
+    for (n, &i) in order.iter().enumerate() {
+        let bi = bounds(&edges[i]);
+        for &j in &order[n + 1..] {
+            let bj = bounds(&edges[j]);
+            if bj.0 > bi.2 + 1 {
+                break;
+            }
+            if bj.1 > bi.3 + 1 || bi.1 > bj.3 + 1 {
+                continue;
+            }
+            let (a, b, _) = edges[i];
+            let (c, d, _) = edges[j];
+            // Endpoints of one edge that touch the other split it.
+            for (e, f, target) in [(a, b, j), (c, d, i)] {
+                for p in [e, f] {
+                    if let Some(t) = touch(edges[target].0, edges[target].1, p) {
+                        splits[target].push((t, p));
+                    }
+                }
+            }
+            // Proper crossings split both.
+            let (o1, o2) = (orient(a, b, c), orient(a, b, d));
+            let (o3, o4) = (orient(c, d, a), orient(c, d, b));
+            if o1.signum() * o2.signum() < 0 && o3.signum() * o4.signum() < 0 {
+                #[allow(
+                    clippy::cast_precision_loss,
+                    reason = "Only the ratio is needed, and it is snapped afterwards."
+                )]
+                let t = o1 as f64 / (o1 - o2) as f64;
+                let (pc, pd) = (grid.point(c), grid.point(d));
+                let k = grid.snap(pc.lerp(pd, t));
+                let (pa, pb) = (grid.point(a), grid.point(b));
+                splits[i].push((param(pa, pb, grid.point(k)), k));
+                splits[j].push((t, k));
+            }
+        }
+    }
+    splits
+}
+
+/// Get the parameter of `p` along the edge from `a` to `b`, if it touches the edge
+/// between its endpoints, within a grid unit.
+fn touch(a: Key, b: Key, p: Key) -> Option<f64> {
+    if p == a || p == b {
+        return None;
+    }
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "Distances only need to be compared with a grid unit."
+    )]
+    let (ab, ap) = (
+        Vec2::new((b.0 - a.0) as f64, (b.1 - a.1) as f64),
+        Vec2::new((p.0 - a.0) as f64, (p.1 - a.1) as f64),
+    );
+    let len_sq = ab.hypot2();
+    let t = ap.dot(ab) / len_sq;
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "Distances only need to be compared with a grid unit."
+    )]
+    let area = orient(a, b, p) as f64;
+    let distance_sq = area * area / len_sq;
+    (t > 0.0 && t < 1.0 && distance_sq <= 1.0).then_some(t)
+}
+
+/// Get the parameter of `p` projected on the line through `a` and `b`.
+fn param(a: Point, b: Point, p: Point) -> f64 {
+    let ab = b - a;
+    (p - a).dot(ab) / ab.hypot2()
+}
+
+/// Get the winding of each operand beside the middle of edge `i` of `edges`.
+///
+/// Returns the winding on the side facing +x, or +y for edges closer to horizontal,
+/// and whether that is the left side of the edge, from its lesser key to its greater.
+fn winding_beside(edges: &[(Key, Key, [i32; 2])], i: usize) -> ([i32; 2], bool) {
+    let (p, q, _) = edges[i];
+    // Twice the midpoint, to stay on the grid.
+    let m = (p.0 + q.0, p.1 + q.1);
+    let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+    let along_x = dx.abs() > dy.abs();
+    let mut w = [0, 0];
+    for (j, &(a, b, c)) in edges.iter().enumerate() {
+        if j == i {
+            continue;
+        }
+        let (a2, b2) = ((a.0 * 2, a.1 * 2), (b.0 * 2, b.1 * 2));
+        // Cast a ray from the midpoint along +x, or +y, counting crossings of edges with
+        // half open intervals so that vertices on the ray are counted once.
+        let sign = if along_x {
+            // The ray is along +y; edges crossing it towards -x wind positively.
+            if b2.0 <= m.0 && m.0 < a2.0 && orient(b2, a2, m) < 0 {
+                1
+            } else if a2.0 <= m.0 && m.0 < b2.0 && orient(a2, b2, m) < 0 {
+                -1
+            } else {
+                0
+            }
+        } else if a2.1 <= m.1 && m.1 < b2.1 && orient(a2, b2, m) > 0 {
+            1
+        } else if b2.1 <= m.1 && m.1 < a2.1 && orient(b2, a2, m) > 0 {
+            -1
+        } else {
+            0
+        };
+        w[0] += sign * c[0];
+        w[1] += sign * c[1];
+    }
+    let plus_is_left = if along_x { dx > 0 } else { dy < 0 };
+    (w, plus_is_left)
+}
+
+/// Append a closed polygon through snapped points, leaving out collinear points.
+fn push_ring(out: &mut BezPath, ring: &[Key], grid: &Grid) {
+    let n = ring.len();
+    let corners: Vec<Key> = (0..n)
+        .filter(|&i| {
+            let (prev, p, next) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            orient(prev, p, next) != 0
+        })
+        .map(|i| ring[i])
+        .collect();
+    if corners.len() < 3 {
+        return;
+    }
+    out.move_to(grid.point(corners[0]));
+    for k in &corners[1..] {
+        out.line_to(grid.point(*k));
+    }
+    out.close_path();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::{Rect, Shape};
+
+    fn square(x: f64, y: f64, size: f64) -> BezPath {
+        Rect::new(x, y, x + size, y + size).to_path(0.1)
+    }
+
+    fn area(path: &BezPath) -> f64 {
+        path.area().abs()
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let (a, b) = (square(0.0, 0.0, 2.0), square(1.0, 1.0, 2.0));
+        for (op, expected) in [
+            (BooleanOp::Union, 7.0),
+            (BooleanOp::Intersection, 1.0),
+            (BooleanOp::Difference, 3.0),
+            (BooleanOp::Xor, 6.0),
+        ] {
+            let result = boolean(&a, &b, op, Fill::NonZero, 0.01);
+            assert!(
+                (area(&result) - expected).abs() < 1e-6,
+                "{op:?} should have area {expected}, got {}.",
+                area(&result)
+            );
+        }
+    }
+
+    #[test]
+    fn shared_edges_and_holes() {
+        // Squares sharing an edge merge into one rectangle.
+        let joined = union(
+            &square(0.0, 0.0, 1.0),
+            &square(1.0, 0.0, 1.0),
+            Fill::NonZero,
+            0.01,
+        );
+        assert_eq!(
+            joined.elements().len(),
+            5,
+            "The shared edge should be removed, leaving 4 corners."
+        );
+
+        // Cutting a hole leaves a ring.
+        let ring = difference(
+            &square(0.0, 0.0, 4.0),
+            &square(1.0, 1.0, 2.0),
+            Fill::EvenOdd,
+            0.01,
+        );
+        assert!(
+            (area(&ring) - 12.0).abs() < 1e-6,
+            "The hole should be cut out, got area {}.",
+            area(&ring)
+        );
+        let filled = union(&ring, &square(1.0, 1.0, 2.0), Fill::EvenOdd, 0.01);
+        assert!(
+            (area(&filled) - 16.0).abs() < 1e-6,
+            "Filling the hole should restore the square, got area {}.",
+            area(&filled)
+        );
+    }
+
+    #[test]
+    fn offset_square() {
+        let s = square(0.0, 0.0, 2.0);
+        let grown = offset(&s, Fill::NonZero, 1.0, Join::Miter, 0.001);
+        assert!(
+            (area(&grown) - 16.0).abs() < 1e-3,
+            "Mitered growth should be square, got area {}.",
+            area(&grown)
+        );
+        let rounded = offset(&s, Fill::NonZero, 1.0, Join::Round, 0.001);
+        let expected = 4.0 + 8.0 + core::f64::consts::PI;
+        assert!(
+            (area(&rounded) - expected).abs() < 1e-2,
+            "Rounded growth should have area {expected}, got {}.",
+            area(&rounded)
+        );
+        let shrunk = offset(&s, Fill::NonZero, -0.5, Join::Miter, 0.001);
+        assert!(
+            (area(&shrunk) - 1.0).abs() < 1e-3,
+            "Shrinking should move every edge in, got area {}.",
+            area(&shrunk)
+        );
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Bounding boxes of graphics items.
+//!
+//! Shapes have exact bounds, but the size of text depends on how it is shaped.
+//! A [`TextMeasurer`] provides that size; [`EstimatedText`] works without a
+//! renderer, and renderers can implement [`TextMeasurer`] using their layouts.
+
+use core::f64::consts::{PI, TAU};
+
+use peniko::kurbo::{Arc, Rect, Shape, Vec2};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+use crate::{
+    GraphicsBag, GraphicsItem, ItemHandle,
+    geometry_chunk::FatChunk,
+    image::FatImage,
+    marker::FatMarker,
+    shape::{FatPaint, FatShape},
+};
+#[cfg(feature = "text")]
+use {
+    crate::{text::FatText, text_on_path::FatTextOnPath},
+    peniko::kurbo::Size,
+};
+
+/// Provides the size of text items.
+///
+/// Without the `text` feature there are no text items, and this has no methods.
+pub trait TextMeasurer {
+    /// Get the size of the layout box of a text item.
+    #[cfg(feature = "text")]
+    fn text_size(&mut self, item: ItemHandle, text: &FatText) -> Size;
+}
+
+/// Measures text with `FatText::estimated_size`, without shaping it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EstimatedText;
+
+impl TextMeasurer for EstimatedText {
+    #[cfg(feature = "text")]
+    fn text_size(&mut self, _item: ItemHandle, text: &FatText) -> Size {
+        text.estimated_size()
+    }
+}
+
+/// Items with a bounding box.
+pub trait Bounds {
+    /// Get the bounds in the item's local coordinates, before its transform is applied.
+    ///
+    /// `item` is the handle of the item in `graphics`, passed on to `measurer`.
+    fn local_bounds(
+        &self,
+        item: ItemHandle,
+        graphics: &GraphicsBag,
+        measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect>;
+}
+
+impl Bounds for FatShape {
+    /// Stroked shapes include half the stroke width on every side.
+    fn local_bounds(
+        &self,
+        _item: ItemHandle,
+        graphics: &GraphicsBag,
+        _measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        let FatPaint {
+            stroke,
+            stroke_paint,
+            ..
+        } = graphics.get_paint(self.paint);
+        let half_width = if stroke_paint.is_some() {
+            stroke.width * 0.5
+        } else {
+            0.0
+        };
+        self.bounding_box()
+            .map(|b| b.inflate(half_width, half_width))
+    }
+}
+
+#[cfg(feature = "text")]
+impl Bounds for FatText {
+    /// Bounds include the background box and half the width of its frame.
+    fn local_bounds(
+        &self,
+        item: ItemHandle,
+        graphics: &GraphicsBag,
+        measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        let bounds = self.bounds_for_size(measurer.text_size(item, self));
+        let half_width = match self.background.map(|b| graphics.get_paint(b.paint)) {
+            Some(FatPaint {
+                stroke,
+                stroke_paint: Some(_),
+                ..
+            }) => stroke.width * 0.5,
+            _ => 0.0,
+        };
+        Some(bounds.inflate(half_width, half_width))
+    }
+}
+
+impl Bounds for FatImage {
+    fn local_bounds(
+        &self,
+        _item: ItemHandle,
+        _graphics: &GraphicsBag,
+        _measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        Some(self.bounding_box())
+    }
+}
+
+impl Bounds for FatMarker {
+    /// Markers are sized on the display, so only their position is included.
+    fn local_bounds(
+        &self,
+        _item: ItemHandle,
+        _graphics: &GraphicsBag,
+        _measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        Some(self.position_bounds())
+    }
+}
+
+impl Bounds for FatChunk {
+    /// Stroked shapes include half the stroke width on every side, using the paint of
+    /// the item when it is set, or else the paint of each shape in the chunk.
+    fn local_bounds(
+        &self,
+        _item: ItemHandle,
+        graphics: &GraphicsBag,
+        _measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        let half_width = |paint: &FatPaint| {
+            if paint.stroke_paint.is_some() {
+                paint.stroke.width * 0.5
+            } else {
+                0.0
+            }
+        };
+        let fixed = self.paint.map(|p| half_width(graphics.get_paint(p)));
+        self.shapes()
+            .map(|(shape, paint)| {
+                let w = fixed.unwrap_or_else(|| half_width(paint));
+                shape.bounding_box().inflate(w, w)
+            })
+            .reduce(|a, b| a.union(b))
+    }
+}
+
+#[cfg(feature = "text")]
+impl Bounds for FatTextOnPath {
+    /// Text on a path is not measured; see [`FatTextOnPath::estimated_bounds`].
+    fn local_bounds(
+        &self,
+        _item: ItemHandle,
+        _graphics: &GraphicsBag,
+        _measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        Some(self.estimated_bounds())
+    }
+}
+
+/// Get the exact bounding box of an elliptical arc.
+///
+/// [`Shape::bounding_box`](peniko::kurbo::Shape::bounding_box) for an [`Arc`] bounds
+/// its cubic Bézier approximation; this instead finds the extrema of the arc itself,
+/// which is tighter. Use it for analytic shapes, such as when
+/// building spatial indices.
+pub fn arc_bounds(arc: &Arc) -> Rect {
+    let Vec2 { x: rx, y: ry } = arc.radii;
+    let (sin_rot, cos_rot) = arc.x_rotation.sin_cos();
+    let point = |t: f64| {
+        let (sin, cos) = t.sin_cos();
+        arc.center
+            + Vec2::new(
+                rx * cos * cos_rot - ry * sin * sin_rot,
+                rx * cos * sin_rot + ry * sin * cos_rot,
+            )
+    };
+    // Check whether the angle `t` is within the sweep.
+    let sweep = arc.sweep_angle.abs();
+    let swept = |t: f64| {
+        let d = (t - arc.start_angle) * arc.sweep_angle.signum();
+        sweep >= TAU || d - (d / TAU).floor() * TAU <= sweep
+    };
+
+    let start = point(arc.start_angle);
+    let mut bounds = Rect::from_points(start, point(arc.start_angle + arc.sweep_angle));
+    // Angles where the derivatives of x and y are zero, each with its opposite.
+    let tx = (-ry * sin_rot).atan2(rx * cos_rot);
+    let ty = (ry * cos_rot).atan2(rx * sin_rot);
+    for t in [tx, tx + PI, ty, ty + PI] {
+        if swept(t) {
+            bounds = bounds.union_pt(point(t));
+        }
+    }
+    bounds
+}
+
+impl Bounds for GraphicsItem {
+    fn local_bounds(
+        &self,
+        item: ItemHandle,
+        graphics: &GraphicsBag,
+        measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        match self {
+            Self::FatShape(s) => s.local_bounds(item, graphics, measurer),
+            #[cfg(feature = "text")]
+            Self::FatText(t) => t.local_bounds(item, graphics, measurer),
+            Self::FatImage(i) => i.local_bounds(item, graphics, measurer),
+            #[cfg(feature = "text")]
+            Self::FatTextOnPath(t) => t.local_bounds(item, graphics, measurer),
+            Self::FatMarker(m) => m.local_bounds(item, graphics, measurer),
+            Self::FatChunk(c) => c.local_bounds(item, graphics, measurer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::{Ellipse, Shape};
+    #[cfg(feature = "text")]
+    use {
+        crate::{
+            DirectIsometry,
+            render_layer::RenderLayer,
+            text::{AttachmentPoint, TextBackground},
+        },
+        alloc::sync::Arc,
+        parley::StyleSet,
+        peniko::{
+            Color,
+            kurbo::{Affine, Line, Stroke, Vec2},
+        },
+    };
+
+    extern crate alloc;
+
+    #[cfg(feature = "text")]
+    struct FixedSize(Size);
+
+    #[cfg(feature = "text")]
+    impl TextMeasurer for FixedSize {
+        fn text_size(&mut self, _item: ItemHandle, _text: &FatText) -> Size {
+            self.0
+        }
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn item_and_layer_bounds() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let paint = bag.register_paint(FatPaint {
+            stroke: Stroke::new(2.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: None,
+            fill_rule: None,
+        });
+        let transform = bag.register_transform(Default::default(), Affine::scale(2.0));
+        let line = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                transform,
+                paint,
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+        let label = layer.push_with_bag(
+            &mut bag,
+            FatText {
+                transform: Default::default(),
+                paint,
+                text: "Label".into(),
+                style: StyleSet::new(10.0),
+                spans: Default::default(),
+                alignment: Default::default(),
+                direction: Default::default(),
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.0, Vec2::new(100.0, 100.0)),
+                attachment_point: AttachmentPoint::BottomLeft,
+            },
+        );
+
+        assert_eq!(
+            bag.item_bounds(line),
+            Some(Rect::new(-2.0, -2.0, 22.0, 2.0)),
+            "Shape bounds should include the stroke and the transform."
+        );
+        bag.update_transform(Default::default(), Affine::translate((5.0, 5.0)));
+        assert_eq!(
+            bag.item_bounds_below_root_with(line, &mut EstimatedText),
+            Some(Rect::new(-2.0, -2.0, 22.0, 2.0)),
+            "Bounds below the root should not include the root transform."
+        );
+        bag.update_transform(Default::default(), Affine::IDENTITY);
+        assert_eq!(
+            bag.item_bounds_with(label, &mut FixedSize(Size::new(30.0, 12.0))),
+            Some(Rect::new(100.0, 88.0, 130.0, 100.0)),
+            "Text bounds should use the measured size and attachment point."
+        );
+        assert_eq!(
+            bag.layer_bounds_with(&layer, &mut FixedSize(Size::new(30.0, 12.0))),
+            Some(Rect::new(-2.0, -2.0, 130.0, 100.0)),
+            "Layer bounds should cover every item."
+        );
+
+        let Some(GraphicsItem::FatText(t)) = bag.get(label) else {
+            unreachable!();
+        };
+        let framed = bag.push(FatText {
+            background: Some(TextBackground { paint, margin: 3.0 }),
+            ..(**t).clone()
+        });
+        assert_eq!(
+            bag.item_bounds_with(framed, &mut FixedSize(Size::new(30.0, 12.0))),
+            Some(Rect::new(96.0, 84.0, 134.0, 104.0)),
+            "Text bounds should include the background margin and frame."
+        );
+        assert_eq!(
+            bag.layer_bounds(&RenderLayer::default()),
+            None,
+            "An empty layer has no bounds."
+        );
+    }
+
+    #[test]
+    fn analytic_arc_bounds() {
+        let quarter = peniko::kurbo::Arc::new((0.0, 0.0), (1.0, 1.0), 0.0, PI / 2.0, 0.0);
+        let b = arc_bounds(&quarter);
+        assert!(
+            (b.x0.abs() + b.y0.abs() + (b.x1 - 1.0).abs() + (b.y1 - 1.0).abs()) < 1e-12,
+            "A quarter circle should be bounded by its end points."
+        );
+        let clockwise = peniko::kurbo::Arc::new((0.0, 0.0), (1.0, 1.0), PI / 4.0, -PI / 2.0, 0.0);
+        assert!(
+            (arc_bounds(&clockwise).x1 - 1.0).abs() < 1e-12,
+            "Negative sweeps should include the extrema they pass."
+        );
+        let full = peniko::kurbo::Arc::new((1.0, 2.0), (3.0, 1.0), 0.3, TAU, 0.5);
+        let ellipse = Ellipse::new((1.0, 2.0), (3.0, 1.0), 0.5).bounding_box();
+        let b = arc_bounds(&full);
+        assert!(
+            (b.x0 - ellipse.x0).abs() < 1e-9
+                && (b.y0 - ellipse.y0).abs() < 1e-9
+                && (b.x1 - ellipse.x1).abs() < 1e-9
+                && (b.y1 - ellipse.y1).abs() < 1e-9,
+            "A full rotated ellipse should have the bounds of the ellipse."
+        );
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Paths stored with single precision coordinates.
+//!
+//! A [`BezPath`] stores each element as a [`PathEl`] of 56 bytes, even for a line that
+//! only has one point. A [`CompactPath`](crate::compact_path::CompactPath) stores one
+//! byte per element and eight bytes per point, which is about a sixth of the size for
+//! polylines. Points are stored relative to an origin near the path, so they keep
+//! about a ten millionth of the size of the path in precision, however far the path is
+//! from the origin of the drawing. That is far finer than a device pixel unless a path
+//! is zoomed to millions of pixels across.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use peniko::kurbo::{
+    BezPath, ParamCurveArclen, ParamCurveArea, ParamCurveExtrema, PathEl, Point, Rect, Shape, Vec2,
+    segments,
+};
+
+/// Verb for [`PathEl::MoveTo`].
+const MOVE_TO: u8 = 0;
+/// Verb for [`PathEl::LineTo`].
+const LINE_TO: u8 = 1;
+/// Verb for [`PathEl::QuadTo`].
+const QUAD_TO: u8 = 2;
+/// Verb for [`PathEl::CurveTo`].
+const CURVE_TO: u8 = 3;
+/// Verb for [`PathEl::ClosePath`].
+const CLOSE_PATH: u8 = 4;
+
+/// A path with points stored as `f32` offsets from an origin.
+///
+/// This implements [`Shape`], converting points back to `f64` as elements are read.
+/// Use [`Shape::to_path`] to get a [`BezPath`].
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactPath {
+    /// Point that the stored points are relative to.
+    origin: Point,
+    /// Kinds of elements.
+    verbs: Vec<u8>,
+    /// Points of all elements, in order.
+    points: Vec<[f32; 2]>,
+}
+
+impl CompactPath {
+    /// Store `path` with single precision.
+    pub fn new(path: &BezPath) -> Self {
+        let origin = if path.elements().is_empty() {
+            Point::ZERO
+        } else {
+            path.control_box().center()
+        };
+        let mut verbs = Vec::with_capacity(path.elements().len());
+        let mut points = Vec::new();
+        #[allow(
+            clippy::cast_possible_truncation,
+            reason = "Reducing precision is the point."
+        )]
+        let mut push = |p: Point| {
+            let d = p - origin;
+            points.push([d.x as f32, d.y as f32]);
+        };
+        for el in path.elements() {
+            match *el {
+                PathEl::MoveTo(p) => {
+                    verbs.push(MOVE_TO);
+                    push(p);
+                }
+                PathEl::LineTo(p) => {
+                    verbs.push(LINE_TO);
+                    push(p);
+                }
+                PathEl::QuadTo(p1, p2) => {
+                    verbs.push(QUAD_TO);
+                    push(p1);
+                    push(p2);
+                }
+                PathEl::CurveTo(p1, p2, p3) => {
+                    verbs.push(CURVE_TO);
+                    push(p1);
+                    push(p2);
+                    push(p3);
+                }
+                PathEl::ClosePath => verbs.push(CLOSE_PATH),
+            }
+        }
+        Self {
+            origin,
+            verbs,
+            points,
+        }
+    }
+
+    /// Create a path from its stored parts, checking that they are consistent and
+    /// begin with a move.
+    pub(crate) fn from_parts(origin: Point, verbs: Vec<u8>, points: Vec<[f32; 2]>) -> Option<Self> {
+        if verbs.first().is_some_and(|v| *v != MOVE_TO) {
+            return None;
+        }
+        let mut count = 0_usize;
+        for verb in &verbs {
+            count += match *verb {
+                MOVE_TO | LINE_TO => 1,
+                QUAD_TO => 2,
+                CURVE_TO => 3,
+                CLOSE_PATH => 0,
+                _ => return None,
+            };
+        }
+        (count == points.len()).then_some(Self {
+            origin,
+            verbs,
+            points,
+        })
+    }
+
+    /// Get the stored parts: the origin, the kinds of elements, and their points.
+    pub(crate) fn parts(&self) -> (Point, &[u8], &[[f32; 2]]) {
+        (self.origin, &self.verbs, &self.points)
+    }
+
+    /// Get the number of elements.
+    pub fn len(&self) -> usize {
+        self.verbs.len()
+    }
+
+    /// Check whether the path has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.verbs.is_empty()
+    }
+
+    /// Iterate over the elements of the path.
+    pub fn elements(&self) -> CompactPathElements<'_> {
+        CompactPathElements {
+            path: self,
+            verb: 0,
+            point: 0,
+        }
+    }
+}
+
+impl From<&BezPath> for CompactPath {
+    fn from(path: &BezPath) -> Self {
+        Self::new(path)
+    }
+}
+
+/// Iterator over the [`PathEl`]s of a [`CompactPath`].
+#[derive(Debug, Clone)]
+pub struct CompactPathElements<'a> {
+    path: &'a CompactPath,
+    verb: usize,
+    point: usize,
+}
+
+impl CompactPathElements<'_> {
+    /// Get the next point, in the path's coordinates.
+    fn point(&mut self) -> Point {
+        let [x, y] = self.path.points[self.point];
+        self.point += 1;
+        self.path.origin + Vec2::new(f64::from(x), f64::from(y))
+    }
+}
+
+impl Iterator for CompactPathElements<'_> {
+    type Item = PathEl;
+
+    fn next(&mut self) -> Option<PathEl> {
+        let verb = *self.path.verbs.get(self.verb)?;
+        self.verb += 1;
+        Some(match verb {
+            MOVE_TO => PathEl::MoveTo(self.point()),
+            LINE_TO => PathEl::LineTo(self.point()),
+            QUAD_TO => PathEl::QuadTo(self.point(), self.point()),
+            CURVE_TO => PathEl::CurveTo(self.point(), self.point(), self.point()),
+            _ => PathEl::ClosePath,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.path.verbs.len() - self.verb;
+        (n, Some(n))
+    }
+}
+
+impl Shape for CompactPath {
+    type PathElementsIter<'iter> = CompactPathElements<'iter>;
+
+    fn path_elements(&self, _tolerance: f64) -> CompactPathElements<'_> {
+        self.elements()
+    }
+
+    fn area(&self) -> f64 {
+        segments(self.elements()).map(|s| s.signed_area()).sum()
+    }
+
+    fn perimeter(&self, accuracy: f64) -> f64 {
+        segments(self.elements()).map(|s| s.arclen(accuracy)).sum()
+    }
+
+    fn winding(&self, pt: Point) -> i32 {
+        // kurbo doesn't expose the winding of segments, so this converts to a path.
+        self.to_path(0.0).winding(pt)
+    }
+
+    fn bounding_box(&self) -> Rect {
+        segments(self.elements())
+            .map(|s| ParamCurveExtrema::bounding_box(&s))
+            .reduce(|a, b| a.union(b))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::Circle;
+
+    #[test]
+    fn compact_round_trip() {
+        // Far from the origin, where f32 alone would keep only centimeters.
+        let far = Vec2::new(1.0e6, -2.0e6);
+        let mut path = Circle::new(Point::ZERO, 10.0).to_path(1e-3);
+        path.apply_affine(peniko::kurbo::Affine::translate(far));
+        path.line_to(far.to_point());
+        path.close_path();
+
+        let compact = CompactPath::new(&path);
+        assert_eq!(
+            compact.len(),
+            path.elements().len(),
+            "Elements should be kept."
+        );
+        for (a, b) in compact.elements().zip(path.elements()) {
+            let (Some(pa), Some(pb)) = (a.end_point(), b.end_point()) else {
+                assert_eq!(a, *b, "Closes should stay closes.");
+                continue;
+            };
+            assert!(
+                (pa - pb).hypot() < 1e-5,
+                "Points should be relative to an origin near the path, got {pa:?} for {pb:?}."
+            );
+        }
+        assert!(
+            (compact.area().abs() - path.area().abs()).abs() < 1e-3,
+            "Measurements should match the original path."
+        );
+        assert_eq!(
+            CompactPath::from_parts(Point::ZERO, alloc::vec![LINE_TO], Vec::new()),
+            None,
+            "Elements without their points should be rejected."
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Angle constraints for interactive tools.
+//!
+//! Tools that place a point relative to a base point, such as drawing a line or
+//! measuring a distance, can lock the direction from the base point to the cursor to
+//! the axes, or to multiples of an angle. Points are in view units.
+
+use core::f64::consts::{FRAC_PI_2, TAU};
+
+use peniko::kurbo::{Point, Vec2};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+/// Directions that a point may take from a base point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AngleConstraint {
+    /// Horizontal or vertical.
+    Ortho,
+    /// Multiples of `increment` radians, measured from `offset` radians.
+    Polar {
+        /// Angle between allowed directions, in radians.
+        increment: f64,
+        /// Angle of the first allowed direction, in radians.
+        offset: f64,
+    },
+}
+
+impl AngleConstraint {
+    fn increment_and_offset(self) -> (f64, f64) {
+        match self {
+            Self::Ortho => (FRAC_PI_2, 0.0),
+            Self::Polar { increment, offset } => (increment, offset),
+        }
+    }
+
+    /// Get the allowed direction nearest to the direction from `base` to `p`.
+    ///
+    /// Returns `None` if `p` is `base`, or the increment is not positive, so there
+    /// is no direction to constrain.
+    pub fn nearest_direction(self, base: Point, p: Point) -> Option<Vec2> {
+        let (increment, offset) = self.increment_and_offset();
+        let d = p - base;
+        if d == Vec2::ZERO || increment.is_nan() || increment <= 0.0 {
+            return None;
+        }
+        let steps = ((d.atan2() - offset) / increment).round();
+        let angle = (offset + steps * increment) % TAU;
+        Some(Vec2::from_angle(angle))
+    }
+
+    /// Constrain `cursor` to the nearest allowed direction from `base`.
+    ///
+    /// The cursor is projected onto the ray from `base` in that direction, so the
+    /// constrained point stays as close to the cursor as it can.
+    pub fn constrain(self, base: Point, cursor: Point) -> Point {
+        match self.nearest_direction(base, cursor) {
+            Some(dir) => base + dir * (cursor - base).dot(dir),
+            None => cursor,
+        }
+    }
+
+    /// Get the distance from `p` to the nearest allowed ray from `base`.
+    pub fn deviation(self, base: Point, p: Point) -> f64 {
+        match self.nearest_direction(base, p) {
+            Some(dir) => (p - base).cross(dir).abs(),
+            None => 0.0,
+        }
+    }
+
+    /// Resolve the point placed by a tool, given snap candidates from `base`.
+    ///
+    /// `snaps` are candidates with their points, in the order a snapping engine ranks
+    /// them, best first. The first candidate within `tolerance` of an allowed ray is
+    /// taken as it is, so snapping to geometry keeps its precision. If there is none,
+    /// the [constrained](Self::constrain) cursor is used, without a candidate.
+    pub fn resolve<T>(
+        self,
+        base: Point,
+        cursor: Point,
+        snaps: impl IntoIterator<Item = (T, Point)>,
+        tolerance: f64,
+    ) -> (Option<T>, Point) {
+        snaps
+            .into_iter()
+            .find(|(_, p)| self.deviation(base, *p) <= tolerance)
+            .map_or_else(
+                || (None, self.constrain(base, cursor)),
+                |(t, p)| (Some(t), p),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::FRAC_PI_4;
+
+    #[test]
+    fn constrain_to_angles() {
+        let base = Point::new(10.0, 10.0);
+        let p = AngleConstraint::Ortho.constrain(base, Point::new(13.0, 20.0));
+        assert!(
+            (p - Point::new(10.0, 20.0)).hypot() < 1e-9,
+            "Ortho should lock to the nearest axis, got {p:?}."
+        );
+
+        let polar = AngleConstraint::Polar {
+            increment: FRAC_PI_4,
+            offset: 0.0,
+        };
+        let p = polar.constrain(base, Point::new(14.0, 16.0));
+        assert!(
+            (p - Point::new(15.0, 15.0)).hypot() < 1e-9,
+            "Polar should lock to the nearest increment, got {p:?}."
+        );
+        assert_eq!(
+            polar.constrain(base, base),
+            base,
+            "The base point has no direction to constrain."
+        );
+    }
+
+    #[test]
+    fn snaps_on_rays_win() {
+        let base = Point::ORIGIN;
+        let snaps = [
+            ("off ray", Point::new(5.0, 3.0)),
+            ("on ray", Point::new(0.0, 7.0)),
+            ("also on ray", Point::new(9.0, 0.0)),
+        ];
+        assert_eq!(
+            AngleConstraint::Ortho.resolve(base, Point::new(1.0, 6.0), snaps, 0.01),
+            (Some("on ray"), Point::new(0.0, 7.0)),
+            "The best ranked snap on an allowed ray should be taken."
+        );
+        let (snap, p) =
+            AngleConstraint::Ortho.resolve(base, Point::new(1.0, 6.0), snaps[..1].to_vec(), 0.01);
+        assert!(
+            snap.is_none() && (p - Point::new(0.0, 6.0)).hypot() < 1e-9,
+            "Without a snap on a ray, the cursor should be constrained, got {p:?}."
+        );
+    }
+}
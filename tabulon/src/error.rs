@@ -0,0 +1,38 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use core::fmt;
+
+use crate::{ClipHandle, ItemHandle, PaintHandle, TransformHandle};
+
+/// Errors from fallible [`GraphicsBag`](crate::GraphicsBag) accessors.
+///
+/// These occur when a handle was not created by the bag it is used with,
+/// for example a handle from another bag or from corrupted data. Handles from
+/// another bag are only reliably detected in builds with debug assertions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabulonError {
+    /// The paint handle is not registered with the bag.
+    InvalidPaintHandle(PaintHandle),
+    /// The transform handle is not registered with the bag.
+    InvalidTransformHandle(TransformHandle),
+    /// The clip handle is not registered with the bag.
+    InvalidClipHandle(ClipHandle),
+    /// The item handle is not an item of the bag.
+    InvalidItemHandle(ItemHandle),
+}
+
+impl fmt::Display for TabulonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPaintHandle(h) => write!(f, "invalid paint handle {}", h.0),
+            Self::InvalidTransformHandle(h) => {
+                write!(f, "invalid transform handle {}", usize::from(*h))
+            }
+            Self::InvalidClipHandle(h) => write!(f, "invalid clip handle {}", usize::from(*h)),
+            Self::InvalidItemHandle(h) => write!(f, "invalid item handle {}", h.0),
+        }
+    }
+}
+
+impl core::error::Error for TabulonError {}
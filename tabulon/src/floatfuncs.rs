@@ -56,4 +56,5 @@ define_float_funcs! {
     fn round(self) -> Self => round/roundf;
     fn sin_cos(self) -> (Self, Self) => sincos/sincosf;
     fn sqrt(self) -> Self => sqrt/sqrtf;
+    fn tan(self) -> Self => tan/tanf;
 }
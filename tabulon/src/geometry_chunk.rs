@@ -0,0 +1,244 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Static geometry shared between bags as one unit.
+//!
+//! A [`GeometryChunk`] owns many shapes and the paints they are drawn with, and holds no
+//! handles, so it doesn't belong to any [`GraphicsBag`]. A [`FatChunk`] item draws a
+//! chunk, or a selection of its shapes, with one transform. Cloning a chunk into another
+//! bag, such as an overlay that highlights some of its shapes, costs one reference count
+//! for the whole chunk rather than one for every shape.
+//!
+//! Items in a chunk are picked as a whole: [`pick`](crate::pick::pick) finds the
+//! [`FatChunk`] item, and [`GeometryChunk::nearest`] finds the shape within it.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+use peniko::kurbo::{DEFAULT_ACCURACY, ParamCurveNearest, Point, Rect, Shape};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+use crate::{
+    GraphicsBag, GraphicsItem, ItemHandle, PaintHandle, TransformHandle,
+    shape::{AnyShape, FatPaint, FatShape},
+};
+
+/// Shapes and the paints they are drawn with, to be shared in an [`Arc`].
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeometryChunk {
+    /// Paints, indexed by the shapes.
+    paints: Vec<FatPaint>,
+    /// Shapes, with the index of their paint.
+    shapes: Vec<(AnyShape, u32)>,
+}
+
+impl GeometryChunk {
+    /// Add a paint, returning its index in the chunk.
+    pub fn add_paint(&mut self, paint: FatPaint) -> u32 {
+        self.paints.push(paint);
+        (self.paints.len() - 1).try_into().unwrap()
+    }
+
+    /// Add a shape drawn with the paint at index `paint`, returning its index in the chunk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `paint` is not the index of a paint in the chunk.
+    pub fn push(&mut self, shape: impl Into<AnyShape>, paint: u32) -> u32 {
+        assert!(
+            (paint as usize) < self.paints.len(),
+            "GeometryChunk paint index out of range."
+        );
+        self.shapes.push((shape.into(), paint));
+        (self.shapes.len() - 1).try_into().unwrap()
+    }
+
+    /// Gather the shape items `items` of `graphics` into a chunk.
+    ///
+    /// Shapes are copied with their paints, and paints shared between items are stored
+    /// once. Transforms and clips are not kept, so items should share a transform, which
+    /// is then given to the [`FatChunk`], and not be clipped. Items that are not shapes
+    /// are skipped.
+    pub fn from_items(graphics: &GraphicsBag, items: impl IntoIterator<Item = ItemHandle>) -> Self {
+        let mut chunk = Self::default();
+        let mut paints: BTreeMap<PaintHandle, u32> = BTreeMap::new();
+        for item in items {
+            let Some(GraphicsItem::FatShape(FatShape { paint, shape, .. })) = graphics.get(item)
+            else {
+                continue;
+            };
+            let index = *paints
+                .entry(*paint)
+                .or_insert_with(|| chunk.add_paint(graphics.get_paint(*paint).clone()));
+            chunk.push(AnyShape::clone(shape), index);
+        }
+        chunk
+    }
+
+    /// Get the number of shapes.
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    /// Check whether the chunk has no shapes.
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Get the paints of the chunk.
+    pub fn paints(&self) -> &[FatPaint] {
+        &self.paints
+    }
+
+    /// Iterate over the shapes, with their paints.
+    pub fn iter(&self) -> impl Iterator<Item = (&AnyShape, &FatPaint)> + '_ {
+        self.shapes
+            .iter()
+            .map(|(shape, paint)| (shape, &self.paints[*paint as usize]))
+    }
+
+    /// Get a shape and its paint.
+    pub fn get(&self, index: u32) -> Option<(&AnyShape, &FatPaint)> {
+        let (shape, paint) = self.shapes.get(index as usize)?;
+        Some((shape, &self.paints[*paint as usize]))
+    }
+
+    /// Find the shape with a segment nearest to `point`, within `max_distance`.
+    ///
+    /// `point` is in the coordinate space of the chunk. Returns the index of the shape
+    /// and the distance to it.
+    pub fn nearest(&self, point: Point, max_distance: f64) -> Option<(u32, f64)> {
+        let mut nearest = None;
+        let mut best_sq = max_distance * max_distance;
+        for (i, (shape, _)) in self.shapes.iter().enumerate() {
+            if !shape
+                .bounding_box()
+                .inflate(max_distance, max_distance)
+                .contains(point)
+            {
+                continue;
+            }
+            for segment in shape.segments() {
+                let dsq = segment.nearest(point, DEFAULT_ACCURACY).distance_sq;
+                if dsq <= best_sq {
+                    best_sq = dsq;
+                    nearest = Some(i);
+                }
+            }
+        }
+        nearest.map(|i| (i.try_into().unwrap(), best_sq.sqrt()))
+    }
+}
+
+/// Item that draws the shapes of a [`GeometryChunk`].
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FatChunk {
+    /// Transform of every shape.
+    pub transform: TransformHandle,
+    /// Shared shapes and paints.
+    pub chunk: Arc<GeometryChunk>,
+    /// Indices of the shapes to draw, in order, or `None` to draw all of them.
+    ///
+    /// Indices that are out of range are skipped.
+    pub selection: Option<Vec<u32>>,
+    /// Paint to draw every shape with instead of the chunk's own, such as for highlights.
+    pub paint: Option<PaintHandle>,
+}
+
+impl FatChunk {
+    /// Draw all shapes of `chunk` with their own paints.
+    pub fn new(chunk: Arc<GeometryChunk>) -> Self {
+        Self {
+            chunk,
+            ..Default::default()
+        }
+    }
+
+    /// Iterate over the shapes to draw, with their paints from the chunk.
+    ///
+    /// Use [`FatChunk::paint`] instead of the chunk's paints when it is set.
+    pub fn shapes(&self) -> impl Iterator<Item = (&AnyShape, &FatPaint)> + '_ {
+        let all = self.selection.is_none().then(|| self.chunk.iter());
+        let selected = self.selection.iter().flatten();
+        all.into_iter()
+            .flatten()
+            .chain(selected.filter_map(|i| self.chunk.get(*i)))
+    }
+
+    /// Get the bounding box of the shapes to draw, without strokes, or `None` if there
+    /// are none.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        self.shapes()
+            .map(|(s, _)| s.bounding_box())
+            .reduce(|a, b| a.union(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::{Circle, Line};
+
+    #[test]
+    fn chunk_selection() {
+        let mut bag = GraphicsBag::default();
+        let paint = bag.register_paint(FatPaint::default());
+        let items: Vec<ItemHandle> = (0..3)
+            .map(|i| {
+                bag.push(FatShape {
+                    paint,
+                    shape: Arc::new(
+                        Line::new((f64::from(i) * 10.0, 0.0), (f64::from(i) * 10.0 + 5.0, 0.0))
+                            .into(),
+                    ),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let chunk = Arc::new(GeometryChunk::from_items(&bag, items));
+        assert_eq!(
+            (chunk.len(), chunk.paints().len()),
+            (3, 1),
+            "Shapes should be gathered with their shared paint stored once."
+        );
+
+        let mut overlay = GraphicsBag::default();
+        let highlight = overlay.register_paint(FatPaint::default());
+        let item = overlay.push(FatChunk {
+            selection: Some(alloc::vec![2, 7]),
+            paint: Some(highlight),
+            ..FatChunk::new(chunk.clone())
+        });
+        assert_eq!(
+            Arc::strong_count(&chunk),
+            2,
+            "The overlay should share the chunk."
+        );
+        let Some(GraphicsItem::FatChunk(c)) = overlay.get(item) else {
+            unreachable!();
+        };
+        assert_eq!(
+            c.bounding_box(),
+            Some(Rect::new(20.0, 0.0, 25.0, 0.0)),
+            "Only selected shapes should be drawn, skipping those out of range."
+        );
+        assert_eq!(
+            chunk.nearest(Point::new(12.0, 1.0), 2.0),
+            Some((1, 1.0)),
+            "The nearest shape should be found."
+        );
+
+        let mut circles = GeometryChunk::default();
+        let p = circles.add_paint(FatPaint::default());
+        circles.push(Circle::new((0.0, 0.0), 1.0), p);
+        assert_eq!(
+            FatChunk::new(Arc::new(circles)).shapes().count(),
+            1,
+            "Without a selection, all shapes should be drawn."
+        );
+    }
+}
@@ -2,28 +2,200 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 extern crate alloc;
-use alloc::{vec, vec::Vec};
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
 
-use core::num::NonZeroU32;
+use core::{cmp::Ordering, num::NonZeroU32};
 
 use crate::{
-    shape::{FatPaint, FatShape},
-    text::FatText,
+    TabulonError,
+    bounds::{Bounds, EstimatedText, TextMeasurer},
+    geometry_chunk::FatChunk,
+    image::FatImage,
+    marker::FatMarker,
+    render_layer::RenderLayer,
+    shape::{AnyShape, FatClip, FatPaint, FatShape},
 };
+#[cfg(feature = "text")]
+use {
+    crate::{
+        text::{FatText, TextBackground},
+        text_on_path::FatTextOnPath,
+    },
+    alloc::boxed::Box,
+};
+
+use peniko::kurbo::{Affine, Rect, Shape, Stroke};
+
+/// Identity of a [`GraphicsBag`], which tags the handles it creates.
+///
+/// Identities are only kept in builds with debug assertions, where using a handle with
+/// a bag other than the one that created it is caught; elsewhere this is empty.
+/// Default handles, such as the root transform, are valid in every bag, and bags read
+/// from snapshots or deserialized accept handles from any bag, so that handles saved
+/// alongside them stay valid. Tags are ignored when handles are compared.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct BagId(#[cfg(debug_assertions)] u32);
+
+impl BagId {
+    /// Make a new identity, distinct from all others.
+    pub(crate) fn new() -> Self {
+        #[cfg(debug_assertions)]
+        {
+            use core::sync::atomic::{AtomicU32, Ordering};
+            static NEXT: AtomicU32 = AtomicU32::new(1);
+            Self(NEXT.fetch_add(1, Ordering::Relaxed))
+        }
+        #[cfg(not(debug_assertions))]
+        Self()
+    }
+
+    /// Check whether a handle with this tag may be used with the bag identified by `bag`.
+    fn admits(self, bag: Self) -> bool {
+        #[cfg(debug_assertions)]
+        {
+            self.0 == 0 || bag.0 == 0 || self.0 == bag.0
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            let _ = bag;
+            true
+        }
+    }
+}
+
+impl PartialEq for BagId {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for BagId {}
+
+impl PartialOrd for BagId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BagId {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+/// Version of the items of a [`GraphicsBag`], from [`GraphicsBag::epoch`].
+///
+/// Epochs of different bags never compare equal, so a cache that records the epoch it
+/// was built for notices both when the items of its bag change, and when it is used
+/// with another bag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Epoch {
+    /// Instance of the bag, distinct for every bag made.
+    bag: u32,
+    /// Number of structural changes made to the bag.
+    version: u64,
+}
+
+impl Epoch {
+    /// Make the first epoch of a new bag.
+    pub(crate) fn new() -> Self {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        Self {
+            bag: NEXT.fetch_add(1, Ordering::Relaxed),
+            version: 0,
+        }
+    }
+}
 
-use peniko::kurbo::Affine;
+/// Point in the history of changes to the contents of a [`GraphicsBag`].
+///
+/// Later revisions compare greater. See [`GraphicsBag::revision`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Revision(u64);
+
+/// Revisions at which the items, paints, and local transforms of a bag last changed.
+///
+/// Entries that are missing, such as for bags read from snapshots, are at the first
+/// revision.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Revisions {
+    current: Revision,
+    /// Advanced by changes to strokes through [`GraphicsBag::get_stroke_mut`], which
+    /// don't advance `current`.
+    strokes: Revision,
+    items: Vec<Revision>,
+    paints: Vec<Revision>,
+    transforms: Vec<Revision>,
+}
+
+impl Revisions {
+    /// Record a change to entry `i` of `which` at a new revision.
+    fn stamp(&mut self, which: fn(&mut Self) -> &mut Vec<Revision>, i: usize) {
+        self.current.0 += 1;
+        let current = self.current;
+        let entries = which(self);
+        if entries.len() <= i {
+            entries.resize(i + 1, Revision::default());
+        }
+        entries[i] = current;
+    }
+
+    /// Get the revision of entry `i` of `entries`.
+    fn get(entries: &[Revision], i: usize) -> Revision {
+        entries.get(i).copied().unwrap_or_default()
+    }
+}
 
 /// A handle for a transform.
 #[derive(Debug, Default, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-pub struct TransformHandle(Option<NonZeroU32>);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct TransformHandle(
+    pub(crate) Option<NonZeroU32>,
+    #[cfg_attr(feature = "serde", serde(skip))] pub(crate) BagId,
+);
+
+/// A handle for a [`FatClip`] in a `GraphicsBag`.
+///
+/// The default handle means no clip.
+#[derive(Debug, Default, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct ClipHandle(
+    pub(crate) Option<NonZeroU32>,
+    #[cfg_attr(feature = "serde", serde(skip))] pub(crate) BagId,
+);
 
 /// A handle for a `GraphicsItem` in a `GraphicsBag`.
 #[derive(Debug, Default, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-pub struct ItemHandle(u32);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct ItemHandle(
+    pub(crate) u32,
+    #[cfg_attr(feature = "serde", serde(skip))] pub(crate) BagId,
+);
 
 /// A handle for a `FatPaint` in a `GraphicsBag`.
 #[derive(Debug, Default, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-pub struct PaintHandle(u32);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct PaintHandle(
+    pub(crate) u32,
+    #[cfg_attr(feature = "serde", serde(skip))] pub(crate) BagId,
+);
 
 impl From<PaintHandle> for usize {
     fn from(h: PaintHandle) -> Self {
@@ -37,38 +209,296 @@ impl From<TransformHandle> for usize {
     }
 }
 
+impl From<ClipHandle> for usize {
+    fn from(h: ClipHandle) -> Self {
+        h.0.map_or(0, |x| x.get() as Self)
+    }
+}
+
 /// Transform record for deriving final transforms.
 #[derive(Debug, Clone, Copy, Default)]
-struct ManagedTransform {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ManagedTransform {
     /// `TransformHandle` for the parent transform.
     pub(crate) parent: TransformHandle,
     pub(crate) local: Affine,
 }
 
+/// Mapping from the handles of one [`GraphicsBag`] to another, filled in by
+/// [`GraphicsBag::absorb`].
+#[derive(Debug, Clone, Default)]
+pub struct HandleRemap {
+    /// Transform to attach the root transform of the absorbed bag to.
+    pub root: TransformHandle,
+    items: Vec<ItemHandle>,
+    paints: Vec<PaintHandle>,
+    transforms: Vec<TransformHandle>,
+    clips: Vec<ClipHandle>,
+}
+
+impl HandleRemap {
+    /// Make an empty mapping that attaches an absorbed bag's root transform to `root`.
+    pub fn new(root: TransformHandle) -> Self {
+        Self {
+            root,
+            ..Default::default()
+        }
+    }
+
+    /// Get the new handle for an absorbed item.
+    pub fn item(&self, handle: ItemHandle) -> Option<ItemHandle> {
+        self.items.get(handle.0 as usize).copied()
+    }
+
+    /// Get the new handle for an absorbed paint.
+    pub fn paint(&self, handle: PaintHandle) -> Option<PaintHandle> {
+        self.paints.get(usize::from(handle)).copied()
+    }
+
+    /// Get the new handle for an absorbed transform.
+    pub fn transform(&self, handle: TransformHandle) -> Option<TransformHandle> {
+        self.transforms.get(usize::from(handle)).copied()
+    }
+
+    /// Get the new handle for an absorbed clip.
+    ///
+    /// The default handle, meaning no clip, maps to itself.
+    pub fn clip(&self, handle: ClipHandle) -> Option<ClipHandle> {
+        match usize::from(handle).checked_sub(1) {
+            None => Some(handle),
+            Some(i) => self.clips.get(i).copied(),
+        }
+    }
+
+    /// Map a [`RenderLayer`] of the absorbed bag to the absorbing bag.
+    ///
+    /// Items that were not absorbed are left out.
+    pub fn render_layer(&self, render_layer: &RenderLayer) -> RenderLayer {
+        RenderLayer {
+            indices: render_layer
+                .indices
+                .iter()
+                .filter_map(|ih| self.item(*ih))
+                .collect(),
+        }
+    }
+
+    /// Map the handles referenced by an item.
+    fn remap_item(&self, item: &GraphicsItem) -> GraphicsItem {
+        match item {
+            GraphicsItem::FatShape(s) => GraphicsItem::FatShape(FatShape {
+                transform: self.transform(s.transform).unwrap_or(self.root),
+                paint: self.paint(s.paint).unwrap_or_default(),
+                clip: self.clip(s.clip).unwrap_or_default(),
+                ..s.clone()
+            }),
+            #[cfg(feature = "text")]
+            GraphicsItem::FatText(t) => GraphicsItem::FatText(Box::new(FatText {
+                transform: self.transform(t.transform).unwrap_or(self.root),
+                paint: self.paint(t.paint).unwrap_or_default(),
+                background: t.background.map(|b| TextBackground {
+                    paint: self.paint(b.paint).unwrap_or_default(),
+                    ..b
+                }),
+                ..(**t).clone()
+            })),
+            GraphicsItem::FatImage(i) => GraphicsItem::FatImage(FatImage {
+                transform: self.transform(i.transform).unwrap_or(self.root),
+                ..i.clone()
+            }),
+            #[cfg(feature = "text")]
+            GraphicsItem::FatTextOnPath(t) => {
+                GraphicsItem::FatTextOnPath(Box::new(FatTextOnPath {
+                    transform: self.transform(t.transform).unwrap_or(self.root),
+                    paint: self.paint(t.paint).unwrap_or_default(),
+                    ..(**t).clone()
+                }))
+            }
+            GraphicsItem::FatMarker(m) => GraphicsItem::FatMarker(FatMarker {
+                transform: self.transform(m.transform).unwrap_or(self.root),
+                paint: self.paint(m.paint).unwrap_or_default(),
+                ..m.clone()
+            }),
+            GraphicsItem::FatChunk(c) => GraphicsItem::FatChunk(FatChunk {
+                transform: self.transform(c.transform).unwrap_or(self.root),
+                paint: c.paint.map(|p| self.paint(p).unwrap_or_default()),
+                ..c.clone()
+            }),
+        }
+    }
+}
+
 /// Items for [`GraphicsBag`].
+///
+/// Text variants only exist with the `text` feature, so this is not exhaustive, and
+/// they are boxed, so that the size of an item doesn't depend on the feature and isn't
+/// dominated by the rarer, larger text items.
 #[derive(Debug)]
-#[allow(
-    clippy::large_enum_variant,
-    reason = "Making FatShape more indirect doesn't help, and there is no other elegant way to handle this."
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum GraphicsItem {
     /// See [`FatShape`].
     FatShape(FatShape),
     /// See [`FatText`].
-    FatText(FatText),
+    #[cfg(feature = "text")]
+    FatText(Box<FatText>),
+    /// See [`FatImage`].
+    FatImage(FatImage),
+    /// See [`FatTextOnPath`].
+    #[cfg(feature = "text")]
+    FatTextOnPath(Box<FatTextOnPath>),
+    /// See [`FatMarker`].
+    FatMarker(FatMarker),
+    /// See [`FatChunk`].
+    FatChunk(FatChunk),
+}
+
+/// Kinds of [`GraphicsItem`]s, for partitioning items by kind.
+///
+/// Text kinds exist without the `text` feature, so that partitions have the same shape,
+/// but no items are of those kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ItemKind {
+    /// [`FatShape`] items.
+    Shape,
+    /// `FatText` items.
+    Text,
+    /// [`FatImage`] items.
+    Image,
+    /// `FatTextOnPath` items.
+    TextOnPath,
+    /// [`FatMarker`] items.
+    Marker,
+    /// [`FatChunk`] items.
+    Chunk,
+}
+
+impl ItemKind {
+    /// Every kind, in the order of their discriminants.
+    pub const ALL: [Self; 6] = [
+        Self::Shape,
+        Self::Text,
+        Self::Image,
+        Self::TextOnPath,
+        Self::Marker,
+        Self::Chunk,
+    ];
+}
+
+impl GraphicsItem {
+    /// Get the kind of the item.
+    pub fn kind(&self) -> ItemKind {
+        match self {
+            Self::FatShape(_) => ItemKind::Shape,
+            #[cfg(feature = "text")]
+            Self::FatText(_) => ItemKind::Text,
+            Self::FatImage(_) => ItemKind::Image,
+            #[cfg(feature = "text")]
+            Self::FatTextOnPath(_) => ItemKind::TextOnPath,
+            Self::FatMarker(_) => ItemKind::Marker,
+            Self::FatChunk(_) => ItemKind::Chunk,
+        }
+    }
 }
 
 /// Bag of [`GraphicsItem`]s.
+///
+/// Deserialized bags are checked like [snapshots](Self::read_snapshot), so that every
+/// handle they hold is in range.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "UncheckedBag")
+)]
 pub struct GraphicsBag {
     /// [`GraphicsItem`]s in the bag.
+    ///
+    /// Call [`touch`](Self::touch) after changing these directly.
     pub items: Vec<GraphicsItem>,
     /// Fully realized transforms used for rendering.
-    final_transforms: Vec<Affine>,
+    pub(crate) final_transforms: Vec<Affine>,
     /// Records that
-    managed_transforms: Vec<ManagedTransform>,
+    pub(crate) managed_transforms: Vec<ManagedTransform>,
     /// `FatPaint`s registered with this bag.
+    pub(crate) palette: Vec<FatPaint>,
+    /// `FatClip`s registered with this bag.
+    pub(crate) clips: Vec<FatClip>,
+    /// Identity that tags the handles created by this bag.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: BagId,
+    /// Version of the items, changed on every structural change.
+    #[cfg_attr(feature = "serde", serde(skip, default = "Epoch::new"))]
+    pub(crate) epoch: Epoch,
+    /// Revisions at which contents last changed.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) revisions: Revisions,
+}
+
+/// Fields of a deserialized [`GraphicsBag`], before they are checked.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedBag {
+    items: Vec<GraphicsItem>,
+    final_transforms: Vec<Affine>,
+    managed_transforms: Vec<ManagedTransform>,
     palette: Vec<FatPaint>,
+    clips: Vec<FatClip>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedBag> for GraphicsBag {
+    type Error = &'static str;
+
+    fn try_from(bag: UncheckedBag) -> Result<Self, Self::Error> {
+        let transforms = bag.managed_transforms.len();
+        if transforms == 0 {
+            return Err("missing root transform");
+        }
+        if bag.final_transforms.len() != transforms {
+            return Err("final transforms don't match managed transforms");
+        }
+        let transform_in_range = |h: TransformHandle| usize::from(h) < transforms;
+        for (i, t) in bag.managed_transforms.iter().enumerate().skip(1) {
+            if usize::from(t.parent) >= i {
+                return Err("transform parent out of order");
+            }
+        }
+        if !bag.clips.iter().all(|c| transform_in_range(c.transform)) {
+            return Err("transform handle out of range");
+        }
+        for item in &bag.items {
+            let (transform, paint, clip, background) = item_handles(item);
+            if !transform_in_range(transform) {
+                return Err("transform handle out of range");
+            }
+            if paint
+                .iter()
+                .chain(&background)
+                .any(|p| usize::from(*p) >= bag.palette.len())
+            {
+                return Err("paint handle out of range");
+            }
+            if usize::from(clip) > bag.clips.len() {
+                return Err("clip handle out of range");
+            }
+        }
+        if bag.items.len() > u32::MAX as usize {
+            return Err("too many items");
+        }
+
+        Ok(Self {
+            items: bag.items,
+            final_transforms: bag.final_transforms,
+            managed_transforms: bag.managed_transforms,
+            palette: bag.palette,
+            clips: bag.clips,
+            // Deserialized bags accept the handles of the bag that was serialized.
+            id: BagId::default(),
+            epoch: Epoch::new(),
+            revisions: Revisions::default(),
+        })
+    }
 }
 
 impl Default for GraphicsBag {
@@ -79,6 +509,10 @@ impl Default for GraphicsBag {
             managed_transforms: vec![Default::default()],
             items: Default::default(),
             palette: Default::default(),
+            clips: Default::default(),
+            id: BagId::new(),
+            epoch: Epoch::new(),
+            revisions: Revisions::default(),
         }
     }
 }
@@ -90,14 +524,324 @@ impl GraphicsBag {
         if n >= u32::MAX as usize {
             panic!("GraphicsBag has too many items.");
         }
-        self.items.push(i.into());
-        ItemHandle(n.try_into().unwrap())
+        let item = i.into();
+        debug_assert!(
+            self.owns_handles(&item),
+            "GraphicsItem uses handles from another GraphicsBag."
+        );
+        self.items.push(item);
+        self.touch();
+        self.revisions.stamp(|r| &mut r.items, n);
+        self.item_handle(n)
+    }
+
+    /// Get the current version of the items in the bag.
+    ///
+    /// The epoch changes whenever items are added, as by [`push`](Self::push) or
+    /// [`absorb`](Self::absorb), or their shapes may be replaced, as by
+    /// [`get_mut`](Self::get_mut), [`compact_paths`](Self::compact_paths), and
+    /// [`close_open_fills`](Self::close_open_fills). Caches built from the items, such as
+    /// a [`SpatialIndex`](crate::spatial_index::SpatialIndex), record it to tell when
+    /// they are stale.
+    ///
+    /// Changes to paints and transforms don't change the epoch, because they are
+    /// expected to change every frame, such as when strokes are adapted to the view;
+    /// they advance the [`revision`](Self::revision) instead.
+    #[must_use]
+    pub fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// Start a new epoch, after changing [`items`](Self::items) directly.
+    pub fn touch(&mut self) {
+        self.epoch.version += 1;
+    }
+
+    /// Get the current revision of the contents of the bag.
+    ///
+    /// The revision advances whenever an item, paint, or transform is changed through
+    /// the bag, so caches of encoded items can find the items that changed since they
+    /// were built with [`item_revision`](Self::item_revision), and update only those.
+    /// Changes to the root transform, and to strokes through
+    /// [`get_stroke_mut`](Self::get_stroke_mut), are not counted, because caches can
+    /// apply those to their results without encoding items again.
+    #[must_use]
+    pub fn revision(&self) -> Revision {
+        self.revisions.current
+    }
+
+    /// Get the current revision of the strokes changed through
+    /// [`get_stroke_mut`](Self::get_stroke_mut), which [`revision`](Self::revision)
+    /// doesn't count.
+    ///
+    /// This advances with every such change, whether or not the stroke is changed, so
+    /// caches that apply strokes to their results can skip applying them while neither
+    /// revision advances, such as while panning, when only the root transform changes.
+    #[must_use]
+    pub fn stroke_revision(&self) -> Revision {
+        self.revisions.strokes
+    }
+
+    /// Get the revision at which an item last changed, counting changes to its paints,
+    /// its clip, and its transforms below the root.
+    ///
+    /// Returns the first revision for items that are not in the bag.
+    #[must_use]
+    pub fn item_revision(&self, idx: ItemHandle) -> Revision {
+        let Some(item) = self.items.get(idx.0 as usize) else {
+            return Revision::default();
+        };
+        let (transform, paint, clip, background) = item_handles(item);
+        let paint_revision = |p: PaintHandle| Revisions::get(&self.revisions.paints, p.0 as usize);
+        let clip_transform = self.get_clip(clip).map(|c| c.transform);
+        [
+            Revisions::get(&self.revisions.items, idx.0 as usize),
+            self.transform_revision(transform),
+            clip_transform.map_or_else(Revision::default, |t| self.transform_revision(t)),
+        ]
+        .into_iter()
+        .chain(paint.into_iter().chain(background).map(paint_revision))
+        .max()
+        .unwrap_or_default()
+    }
+
+    /// Get the revision at which a transform or one of its ancestors below the root
+    /// last changed.
+    fn transform_revision(&self, mut handle: TransformHandle) -> Revision {
+        let mut revision = Revision::default();
+        while handle.0.is_some() {
+            let i = usize::from(handle);
+            let Some(m) = self.managed_transforms.get(i) else {
+                break;
+            };
+            revision = revision.max(Revisions::get(&self.revisions.transforms, i));
+            handle = m.parent;
+        }
+        revision
     }
 
     /// Get an individual [`GraphicsItem`].
     #[must_use]
     pub fn get(&self, idx: ItemHandle) -> Option<&GraphicsItem> {
-        self.items.get(idx.0 as usize)
+        self.try_get(idx).ok()
+    }
+
+    /// Get an individual [`GraphicsItem`], or an error if `idx` is not an item of this bag.
+    pub fn try_get(&self, idx: ItemHandle) -> Result<&GraphicsItem, TabulonError> {
+        self.items
+            .get(idx.0 as usize)
+            .filter(|_| idx.1.admits(self.id))
+            .ok_or(TabulonError::InvalidItemHandle(idx))
+    }
+
+    /// Make the handle for the item at index `i`.
+    pub(crate) fn item_handle(&self, i: usize) -> ItemHandle {
+        ItemHandle(i.try_into().unwrap(), self.id)
+    }
+
+    /// Get an individual [`GraphicsItem`] to change it.
+    ///
+    /// The item is counted as changed in the next [`revision`](Self::revision), and a
+    /// new [`epoch`](Self::epoch) is started, as its shape may be replaced. Changes
+    /// must keep using handles from this bag.
+    #[must_use]
+    pub fn get_mut(&mut self, idx: ItemHandle) -> Option<&mut GraphicsItem> {
+        self.try_get_mut(idx).ok()
+    }
+
+    /// Get an individual [`GraphicsItem`] to change it, or an error if `idx` is not an
+    /// item of this bag.
+    pub fn try_get_mut(&mut self, idx: ItemHandle) -> Result<&mut GraphicsItem, TabulonError> {
+        if !idx.1.admits(self.id) || idx.0 as usize >= self.items.len() {
+            return Err(TabulonError::InvalidItemHandle(idx));
+        }
+        self.revisions.stamp(|r| &mut r.items, idx.0 as usize);
+        self.touch();
+        Ok(&mut self.items[idx.0 as usize])
+    }
+
+    /// Check whether the handles that `item` refers to could have been created by this bag.
+    fn owns_handles(&self, item: &GraphicsItem) -> bool {
+        let (transform, paint, clip, background) = item_handles(item);
+        transform.1.admits(self.id)
+            && clip.1.admits(self.id)
+            && paint.iter().chain(&background).all(|p| p.1.admits(self.id))
+    }
+
+    /// Find filled shapes with [open subpaths](AnyShape::open_subpaths).
+    ///
+    /// Fills close open subpaths with straight lines, which is usually a mistake in how
+    /// the shape was built, so a warning is logged for each item found. Use
+    /// [`close_open_fills`](Self::close_open_fills) to close them explicitly.
+    pub fn check_open_fills(&self) -> Vec<ItemHandle> {
+        self.open_fills()
+            .map(|(item, subpaths)| {
+                tracing::warn!(item = item.0, subpaths, "fill applied to open subpaths");
+                item
+            })
+            .collect()
+    }
+
+    /// Close the open subpaths of filled shapes, returning the items that changed.
+    ///
+    /// Shapes shared between items are closed once, and stay shared.
+    pub fn close_open_fills(&mut self) -> Vec<ItemHandle> {
+        let changed: Vec<ItemHandle> = self.open_fills().map(|(item, _)| item).collect();
+        // Originals are kept alive so their addresses are not reused while mapping.
+        let mut closed: BTreeMap<usize, (Arc<AnyShape>, Arc<AnyShape>)> = BTreeMap::new();
+        for item in &changed {
+            let Some(GraphicsItem::FatShape(s)) = self.items.get_mut(item.0 as usize) else {
+                continue;
+            };
+            let (_, shape) = closed
+                .entry(Arc::as_ptr(&s.shape).addr())
+                .or_insert_with(|| {
+                    let mut shape = AnyShape::clone(&s.shape);
+                    shape.close_subpaths();
+                    (s.shape.clone(), Arc::new(shape))
+                });
+            s.shape = shape.clone();
+            self.revisions.stamp(|r| &mut r.items, item.0 as usize);
+        }
+        if !changed.is_empty() {
+            self.touch();
+        }
+        changed
+    }
+
+    /// Store the paths of all shapes with single precision, returning the items that
+    /// changed.
+    ///
+    /// This cuts the memory used by polylines to about a sixth, at a precision far finer than a
+    /// device pixel at any practical zoom; see [`CompactPath`](crate::compact_path::CompactPath).
+    /// Shapes shared between items are converted once, and stay shared.
+    pub fn compact_paths(&mut self) -> Vec<ItemHandle> {
+        let mut changed = Vec::new();
+        // Originals are kept alive so their addresses are not reused while mapping.
+        let mut compacted: BTreeMap<usize, (Arc<AnyShape>, Option<Arc<AnyShape>>)> =
+            BTreeMap::new();
+        for (i, item) in self.items.iter_mut().enumerate() {
+            let GraphicsItem::FatShape(s) = item else {
+                continue;
+            };
+            let (_, shape) = compacted
+                .entry(Arc::as_ptr(&s.shape).addr())
+                .or_insert_with(|| {
+                    let mut shape = AnyShape::clone(&s.shape);
+                    let shape = shape.compact().then(|| Arc::new(shape));
+                    (s.shape.clone(), shape)
+                });
+            if let Some(shape) = shape {
+                s.shape = shape.clone();
+                changed.push(i);
+            }
+        }
+        let changed: Vec<ItemHandle> = changed.into_iter().map(|i| self.item_handle(i)).collect();
+        for item in &changed {
+            self.revisions.stamp(|r| &mut r.items, item.0 as usize);
+        }
+        if !changed.is_empty() {
+            self.touch();
+        }
+        changed
+    }
+
+    /// Iterate over filled shapes with open subpaths, with how many they have.
+    fn open_fills(&self) -> impl Iterator<Item = (ItemHandle, usize)> + '_ {
+        self.items.iter().enumerate().filter_map(|(i, item)| {
+            let GraphicsItem::FatShape(s) = item else {
+                return None;
+            };
+            self.get_paint(s.paint).fill_paint.as_ref()?;
+            let subpaths = s.shape.open_subpaths();
+            (subpaths > 0).then(|| (self.item_handle(i), subpaths))
+        })
+    }
+
+    /// Get the bounds of an item after its transform is applied.
+    ///
+    /// Text is measured with [`EstimatedText`]; see [`GraphicsBag::item_bounds_with`].
+    #[must_use]
+    pub fn item_bounds(&self, idx: ItemHandle) -> Option<Rect> {
+        self.item_bounds_with(idx, &mut EstimatedText)
+    }
+
+    /// Get the bounds of an item after its transform is applied, measuring text with `measurer`.
+    pub fn item_bounds_with(
+        &self,
+        idx: ItemHandle,
+        measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        self.bounds_with(idx, measurer, |handle| self.get_transform(handle))
+    }
+
+    /// Get the bounds of an item after its transform is applied, up to the root transform,
+    /// measuring text with `measurer`.
+    ///
+    /// These are in the coordinates that the root transform maps from, so they don't
+    /// change when the root transform is used as a view.
+    pub fn item_bounds_below_root_with(
+        &self,
+        idx: ItemHandle,
+        measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        self.bounds_with(idx, measurer, |handle| self.transform_below_root(handle))
+    }
+
+    /// Get the bounds of an item, and its clip, with transforms from `transform`.
+    fn bounds_with(
+        &self,
+        idx: ItemHandle,
+        measurer: &mut dyn TextMeasurer,
+        transform: impl Fn(TransformHandle) -> Affine,
+    ) -> Option<Rect> {
+        let item = self.get(idx)?;
+        let item_transform = match item {
+            GraphicsItem::FatShape(s) => s.transform,
+            #[cfg(feature = "text")]
+            GraphicsItem::FatText(t) => t.transform,
+            GraphicsItem::FatImage(i) => i.transform,
+            #[cfg(feature = "text")]
+            GraphicsItem::FatTextOnPath(t) => t.transform,
+            GraphicsItem::FatMarker(m) => m.transform,
+            GraphicsItem::FatChunk(c) => c.transform,
+        };
+        let local = item.local_bounds(idx, self, measurer)?;
+        let bounds = transform(item_transform).transform_rect_bbox(local);
+        match item {
+            GraphicsItem::FatShape(FatShape { clip, .. }) => match self.get_clip(*clip) {
+                Some(c) => {
+                    let clip_bounds =
+                        transform(c.transform).transform_rect_bbox(c.path.bounding_box());
+                    let clipped = bounds.intersect(clip_bounds);
+                    (clipped.width() >= 0.0 && clipped.height() >= 0.0).then_some(clipped)
+                }
+                None => Some(bounds),
+            },
+            _ => Some(bounds),
+        }
+    }
+
+    /// Get the bounds of the items in a [`RenderLayer`] after their transforms are applied.
+    ///
+    /// Text is measured with [`EstimatedText`]; see [`GraphicsBag::layer_bounds_with`].
+    #[must_use]
+    pub fn layer_bounds(&self, render_layer: &RenderLayer) -> Option<Rect> {
+        self.layer_bounds_with(render_layer, &mut EstimatedText)
+    }
+
+    /// Get the bounds of the items in a [`RenderLayer`] after their transforms are applied,
+    /// measuring text with `measurer`.
+    pub fn layer_bounds_with(
+        &self,
+        render_layer: &RenderLayer,
+        measurer: &mut dyn TextMeasurer,
+    ) -> Option<Rect> {
+        render_layer
+            .indices
+            .iter()
+            .filter_map(|idx| self.item_bounds_with(*idx, measurer))
+            .reduce(|a, b| a.union(b))
     }
 
     /// Register a paint.
@@ -110,58 +854,252 @@ impl GraphicsBag {
             panic!("GraphicsBag has too many paints.");
         }
         self.palette.push(paint);
-        PaintHandle(n.try_into().unwrap())
+        PaintHandle(n.try_into().unwrap(), self.id)
     }
 
     /// Get a paint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_get_paint`].
     #[must_use]
     pub fn get_paint(&self, handle: PaintHandle) -> &FatPaint {
-        self.palette.get(usize::from(handle)).unwrap()
+        self.try_get_paint(handle).unwrap_or_else(|e| panic!("{e}"))
     }
 
-    /// Get a paint.
+    /// Get a paint, or an error if `handle` is not registered with this bag.
+    pub fn try_get_paint(&self, handle: PaintHandle) -> Result<&FatPaint, TabulonError> {
+        self.palette
+            .get(usize::from(handle))
+            .filter(|_| handle.1.admits(self.id))
+            .ok_or(TabulonError::InvalidPaintHandle(handle))
+    }
+
+    /// Get a paint to change it.
+    ///
+    /// The paint is counted as changed in the next [`revision`](Self::revision); use
+    /// [`get_stroke_mut`](Self::get_stroke_mut) to change only its stroke.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_get_paint_mut`].
     #[must_use]
     pub fn get_paint_mut(&mut self, handle: PaintHandle) -> &mut FatPaint {
-        self.palette.get_mut(usize::from(handle)).unwrap()
+        self.try_get_paint_mut(handle)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Get a paint, or an error if `handle` is not registered with this bag.
+    pub fn try_get_paint_mut(
+        &mut self,
+        handle: PaintHandle,
+    ) -> Result<&mut FatPaint, TabulonError> {
+        if !handle.1.admits(self.id) || usize::from(handle) >= self.palette.len() {
+            return Err(TabulonError::InvalidPaintHandle(handle));
+        }
+        self.revisions.stamp(|r| &mut r.paints, usize::from(handle));
+        Ok(&mut self.palette[usize::from(handle)])
+    }
+
+    /// Get the stroke of a paint to change it, such as to adapt widths to the view.
+    ///
+    /// Unlike other changes to paints, this doesn't advance the
+    /// [`revision`](Self::revision), because encoded strokes can be updated in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_get_stroke_mut`].
+    #[must_use]
+    pub fn get_stroke_mut(&mut self, handle: PaintHandle) -> &mut Stroke {
+        self.try_get_stroke_mut(handle)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Get the stroke of a paint to change it, or an error if `handle` is not
+    /// registered with this bag.
+    pub fn try_get_stroke_mut(&mut self, handle: PaintHandle) -> Result<&mut Stroke, TabulonError> {
+        if !handle.1.admits(self.id) || usize::from(handle) >= self.palette.len() {
+            return Err(TabulonError::InvalidPaintHandle(handle));
+        }
+        self.revisions.strokes.0 += 1;
+        Ok(&mut self.palette[usize::from(handle)].stroke)
     }
 
     /// Update a paint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_update_paint`].
     pub fn update_paint(&mut self, handle: PaintHandle, paint: FatPaint) {
-        self.palette[handle.0 as usize] = paint;
+        self.try_update_paint(handle, paint)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Update a paint, or return an error if `handle` is not registered with this bag.
+    pub fn try_update_paint(
+        &mut self,
+        handle: PaintHandle,
+        paint: FatPaint,
+    ) -> Result<(), TabulonError> {
+        *self.try_get_paint_mut(handle)? = paint;
+        Ok(())
+    }
+
+    /// Register a clip.
+    ///
+    /// Attach the returned `ClipHandle` to a `FatShape`.
+    #[must_use]
+    pub fn register_clip(&mut self, clip: FatClip) -> ClipHandle {
+        let n = self.clips.len();
+        if n >= u32::MAX as usize - 1 {
+            panic!("GraphicsBag has too many clips.");
+        }
+        self.clips.push(clip);
+        ClipHandle(NonZeroU32::new((n + 1).try_into().unwrap()), self.id)
+    }
+
+    /// Get a clip, or `None` for the default handle.
+    #[must_use]
+    pub fn get_clip(&self, handle: ClipHandle) -> Option<&FatClip> {
+        self.try_get_clip(handle).ok().flatten()
+    }
+
+    /// Get a clip, `None` for the default handle, or an error if `handle`
+    /// is not registered with this bag.
+    pub fn try_get_clip(&self, handle: ClipHandle) -> Result<Option<&FatClip>, TabulonError> {
+        match usize::from(handle).checked_sub(1) {
+            None => Ok(None),
+            Some(i) => self
+                .clips
+                .get(i)
+                .filter(|_| handle.1.admits(self.id))
+                .map(Some)
+                .ok_or(TabulonError::InvalidClipHandle(handle)),
+        }
     }
 
     /// Register a transform.
     ///
     /// Attach the returned `TransformHandle` to a `GraphicsItem`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not registered with this bag; see [`GraphicsBag::try_register_transform`].
     pub fn register_transform(
         &mut self,
         parent: TransformHandle,
         local: Affine,
     ) -> TransformHandle {
+        self.try_register_transform(parent, local)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Register a transform, or return an error if `parent` is not registered with this bag.
+    pub fn try_register_transform(
+        &mut self,
+        parent: TransformHandle,
+        local: Affine,
+    ) -> Result<TransformHandle, TabulonError> {
+        let parent_transform = self.try_get_transform(parent)?;
         #[allow(
             clippy::cast_possible_truncation,
             reason = "The length of managed_transforms is managed."
         )]
-        let handle = TransformHandle(NonZeroU32::new(self.managed_transforms.len() as u32));
+        let handle = TransformHandle(
+            NonZeroU32::new(self.managed_transforms.len() as u32),
+            self.id,
+        );
         let managed = ManagedTransform { parent, local };
 
         self.managed_transforms.push(managed);
 
-        self.final_transforms
-            .push(self.final_transforms[usize::from(parent)] * local);
+        self.final_transforms.push(parent_transform * local);
 
-        handle
+        Ok(handle)
     }
 
     /// Get a transform.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_get_transform`].
     pub fn get_transform(&self, handle: TransformHandle) -> Affine {
-        *self.final_transforms.get(usize::from(handle)).unwrap()
+        self.try_get_transform(handle)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Get a transform, or an error if `handle` is not registered with this bag.
+    pub fn try_get_transform(&self, handle: TransformHandle) -> Result<Affine, TabulonError> {
+        self.final_transforms
+            .get(usize::from(handle))
+            .filter(|_| handle.1.admits(self.id))
+            .copied()
+            .ok_or(TabulonError::InvalidTransformHandle(handle))
+    }
+
+    /// Get the local part of a transform, relative to its parent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_get_local_transform`].
+    pub fn get_local_transform(&self, handle: TransformHandle) -> Affine {
+        self.try_get_local_transform(handle)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Get the local part of a transform, or an error if `handle` is not registered with this bag.
+    pub fn try_get_local_transform(&self, handle: TransformHandle) -> Result<Affine, TabulonError> {
+        self.managed_transforms
+            .get(usize::from(handle))
+            .filter(|_| handle.1.admits(self.id))
+            .map(|m| m.local)
+            .ok_or(TabulonError::InvalidTransformHandle(handle))
+    }
+
+    /// Get a transform without the root transform applied, such as to place items
+    /// relative to a view transform set at the root.
+    pub(crate) fn transform_below_root(&self, handle: TransformHandle) -> Affine {
+        let mut transform = Affine::IDENTITY;
+        let mut i = usize::from(handle);
+        // Parents are registered before their children, so this ends at the root.
+        while let Some(ManagedTransform { parent, local }) =
+            self.managed_transforms.get(i).filter(|_| i != 0)
+        {
+            transform = *local * transform;
+            i = usize::from(*parent);
+        }
+        transform
     }
 
     /// Update a transform.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is not registered with this bag; see [`GraphicsBag::try_update_transform`].
     pub fn update_transform(&mut self, handle: TransformHandle, local: Affine) {
-        self.managed_transforms[usize::from(handle)].local = local;
+        self.try_update_transform(handle, local)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Update a transform, or return an error if `handle` is not registered with this bag.
+    pub fn try_update_transform(
+        &mut self,
+        handle: TransformHandle,
+        local: Affine,
+    ) -> Result<(), TabulonError> {
+        if !handle.1.admits(self.id) {
+            return Err(TabulonError::InvalidTransformHandle(handle));
+        }
+        self.managed_transforms
+            .get_mut(usize::from(handle))
+            .ok_or(TabulonError::InvalidTransformHandle(handle))?
+            .local = local;
+        if handle.0.is_some() {
+            self.revisions
+                .stamp(|r| &mut r.transforms, usize::from(handle));
+        }
         self.finalize_transforms(handle);
+        Ok(())
     }
 
     // TODO: Consider finalizing transforms based on a dirty state immediately
@@ -173,31 +1111,105 @@ impl GraphicsBag {
         pairs: impl IntoIterator<Item = (TransformHandle, Affine)>,
     ) {
         let mut includes_root = false;
-        let mut least = NonZeroU32::MAX;
+        let mut least = None;
         for (k, v) in pairs {
+            debug_assert!(
+                k.1.admits(self.id),
+                "TransformHandle from another GraphicsBag."
+            );
             self.managed_transforms[usize::from(k)].local = v;
 
             if let Some(i) = k.0 {
-                least = least.min(i);
+                self.revisions.stamp(|r| &mut r.transforms, usize::from(k));
+                least = Some(least.map_or(i, |l: NonZeroU32| l.min(i)));
             } else {
                 includes_root = true;
             }
         }
 
         // Empty iterator, do nothing.
-        if least == NonZeroU32::MAX {
+        let Some(least) = least.or(includes_root.then_some(NonZeroU32::MIN)) else {
             return;
-        }
+        };
 
         self.finalize_transforms(if includes_root {
             Default::default()
         } else {
-            TransformHandle(Some(least))
+            TransformHandle(Some(least), self.id)
         });
     }
 
+    /// Copy the items, paints, transforms, and clips of another bag into this one.
+    ///
+    /// The root transform of `other` becomes a child of `remap.root`, so the absorbed
+    /// graphics can be placed as a whole, such as an underlay beneath a plan. The rest
+    /// of the transform hierarchy of `other` is kept. Previous mappings in `remap` are
+    /// replaced with the new handles of everything absorbed, in the same order.
+    ///
+    /// Transforms in `other` that are not registered with it are mapped to `remap.root`,
+    /// and other unregistered handles to their defaults.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `remap.root` is not registered with this bag; see [`GraphicsBag::try_absorb`].
+    pub fn absorb(&mut self, other: &Self, remap: &mut HandleRemap) {
+        self.try_absorb(other, remap)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Copy the contents of another bag into this one, or return an error if
+    /// `remap.root` is not registered with this bag.
+    ///
+    /// See [`GraphicsBag::absorb`].
+    pub fn try_absorb(
+        &mut self,
+        other: &Self,
+        remap: &mut HandleRemap,
+    ) -> Result<(), TabulonError> {
+        self.try_get_transform(remap.root)?;
+
+        remap.transforms.clear();
+        for (i, m) in other.managed_transforms.iter().enumerate() {
+            // Parents are always registered before their children.
+            let parent = if i == 0 {
+                remap.root
+            } else {
+                remap
+                    .transforms
+                    .get(usize::from(m.parent))
+                    .copied()
+                    .unwrap_or(remap.root)
+            };
+            let handle = self.register_transform(parent, m.local);
+            remap.transforms.push(handle);
+        }
+
+        remap.paints = other
+            .palette
+            .iter()
+            .map(|p| self.register_paint(p.clone()))
+            .collect();
+
+        remap.clips.clear();
+        for c in &other.clips {
+            let clip = FatClip {
+                transform: remap.transform(c.transform).unwrap_or(remap.root),
+                path: c.path.clone(),
+            };
+            remap.clips.push(self.register_clip(clip));
+        }
+
+        remap.items.clear();
+        remap.items.reserve(other.items.len());
+        for item in &other.items {
+            let item = remap.remap_item(item);
+            remap.items.push(self.push(item));
+        }
+        Ok(())
+    }
+
     /// Finalize all transforms that may depend on `handle`.
-    fn finalize_transforms(&mut self, handle: TransformHandle) {
+    pub(crate) fn finalize_transforms(&mut self, handle: TransformHandle) {
         for i in usize::from(handle)..self.managed_transforms.len() {
             let ManagedTransform { parent, local } = self.managed_transforms[i];
             // Special case for root transform.
@@ -209,3 +1221,417 @@ impl GraphicsBag {
         }
     }
 }
+
+/// Get the transform, paint, clip, and background paint that an item refers to.
+fn item_handles(
+    item: &GraphicsItem,
+) -> (
+    TransformHandle,
+    Option<PaintHandle>,
+    ClipHandle,
+    Option<PaintHandle>,
+) {
+    match item {
+        GraphicsItem::FatShape(s) => (s.transform, Some(s.paint), s.clip, None),
+        #[cfg(feature = "text")]
+        GraphicsItem::FatText(t) => (
+            t.transform,
+            Some(t.paint),
+            ClipHandle::default(),
+            t.background.map(|b| b.paint),
+        ),
+        GraphicsItem::FatImage(i) => (i.transform, None, ClipHandle::default(), None),
+        #[cfg(feature = "text")]
+        GraphicsItem::FatTextOnPath(t) => (t.transform, Some(t.paint), ClipHandle::default(), None),
+        GraphicsItem::FatMarker(m) => (m.transform, Some(m.paint), ClipHandle::default(), None),
+        GraphicsItem::FatChunk(c) => (c.transform, c.paint, ClipHandle::default(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::{Color, kurbo::BezPath};
+
+    #[test]
+    fn absorb_remaps_handles() {
+        let mut underlay = GraphicsBag::default();
+        let mut underlay_layer = RenderLayer::default();
+        let inner = underlay.register_transform(Default::default(), Affine::scale(2.0));
+        let paint = underlay.register_paint(FatPaint {
+            stroke: peniko::kurbo::Stroke::new(3.0),
+            ..Default::default()
+        });
+        let clip = underlay.register_clip(FatClip {
+            transform: inner,
+            path: Arc::new(BezPath::new()),
+        });
+        let shape = underlay_layer.push_with_bag(
+            &mut underlay,
+            FatShape {
+                transform: inner,
+                paint,
+                clip,
+                ..Default::default()
+            },
+        );
+
+        let mut plan = GraphicsBag::default();
+        let _ = plan.register_paint(FatPaint::default());
+        let placement = plan.register_transform(Default::default(), Affine::translate((5.0, 0.0)));
+        plan.push(FatShape::default());
+
+        let mut remap = HandleRemap::new(placement);
+        plan.absorb(&underlay, &mut remap);
+
+        let absorbed = remap.item(shape).unwrap();
+        assert_eq!(
+            remap.render_layer(&underlay_layer).indices,
+            [absorbed],
+            "Render layers should map to the absorbed items."
+        );
+        let Some(GraphicsItem::FatShape(s)) = plan.get(absorbed) else {
+            panic!("The absorbed item should be a shape.");
+        };
+        assert_eq!(
+            (s.paint, s.clip),
+            (remap.paint(paint).unwrap(), remap.clip(clip).unwrap()),
+            "Item handles should be remapped."
+        );
+        assert_eq!(
+            plan.get_paint(s.paint).stroke.width,
+            3.0,
+            "Paints should be copied."
+        );
+        assert_eq!(
+            plan.get_transform(s.transform),
+            Affine::translate((5.0, 0.0)) * Affine::scale(2.0),
+            "The absorbed transform hierarchy should be attached to the remap root."
+        );
+        assert_eq!(
+            plan.get_clip(s.clip).unwrap().transform,
+            s.transform,
+            "Clip transforms should be remapped."
+        );
+
+        let transform = s.transform;
+        plan.update_transform(placement, Affine::translate((0.0, 7.0)));
+        assert_eq!(
+            plan.get_transform(transform),
+            Affine::translate((0.0, 7.0)) * Affine::scale(2.0),
+            "Absorbed graphics should move with the remap root."
+        );
+    }
+
+    #[test]
+    fn foreign_handles() {
+        let mut other = GraphicsBag::default();
+        let paint = other.register_paint(FatPaint::default());
+        let transform = other.register_transform(Default::default(), Affine::IDENTITY);
+
+        let mut bag = GraphicsBag::default();
+        assert_eq!(
+            bag.try_get_paint(paint).err(),
+            Some(TabulonError::InvalidPaintHandle(paint)),
+            "Paints from another bag should be rejected."
+        );
+        assert_eq!(
+            bag.try_update_paint(paint, FatPaint::default()),
+            Err(TabulonError::InvalidPaintHandle(paint)),
+            "Updating an unregistered paint should be rejected."
+        );
+        assert_eq!(
+            bag.try_get_transform(transform),
+            Err(TabulonError::InvalidTransformHandle(transform)),
+            "Transforms from another bag should be rejected."
+        );
+        assert_eq!(
+            bag.try_register_transform(transform, Affine::IDENTITY),
+            Err(TabulonError::InvalidTransformHandle(transform)),
+            "Registering under an unregistered parent should be rejected."
+        );
+        assert_eq!(
+            bag.try_get_transform(TransformHandle::default()),
+            Ok(Affine::IDENTITY),
+            "The root transform is always present."
+        );
+
+        // Handles that are in range for both bags are caught by their tags.
+        let own_paint = bag.register_paint(FatPaint::default());
+        let own_transform = bag.register_transform(Default::default(), Affine::IDENTITY);
+        let item = other.push(FatShape {
+            paint,
+            transform,
+            ..Default::default()
+        });
+        let own_item = bag.push(FatShape {
+            paint: own_paint,
+            transform: own_transform,
+            ..Default::default()
+        });
+        assert_eq!(
+            (own_paint, own_transform, own_item),
+            (paint, transform, item),
+            "Tags should not affect comparisons."
+        );
+        if cfg!(debug_assertions) {
+            assert_eq!(
+                bag.try_get_paint(paint).err(),
+                Some(TabulonError::InvalidPaintHandle(paint)),
+                "Paints from another bag should be rejected even when in range."
+            );
+            assert_eq!(
+                bag.try_get_transform(transform),
+                Err(TabulonError::InvalidTransformHandle(transform)),
+                "Transforms from another bag should be rejected even when in range."
+            );
+            assert_eq!(
+                bag.try_get(item).err(),
+                Some(TabulonError::InvalidItemHandle(item)),
+                "Items from another bag should be rejected even when in range."
+            );
+        }
+        assert!(
+            bag.try_get_paint(own_paint).is_ok() && bag.try_get(own_item).is_ok(),
+            "Handles from the same bag should be accepted."
+        );
+    }
+
+    #[test]
+    fn close_open_fills() {
+        let mut bag = GraphicsBag::default();
+        let fill = bag.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let stroke = bag.register_paint(FatPaint {
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut open = BezPath::new();
+        open.move_to((0.0, 0.0));
+        open.line_to((1.0, 0.0));
+        open.line_to((1.0, 1.0));
+        let open = Arc::new(AnyShape::from(open));
+        let mut push = |paint, shape: &Arc<AnyShape>| {
+            bag.push(FatShape {
+                paint,
+                shape: shape.clone(),
+                ..Default::default()
+            })
+        };
+        let a = push(fill, &open);
+        let b = push(fill, &open);
+        let _stroked = push(stroke, &open);
+        let _rect = push(fill, &Arc::new(Rect::new(0.0, 0.0, 1.0, 1.0).into()));
+
+        assert_eq!(
+            bag.check_open_fills(),
+            [a, b],
+            "Only filled shapes with open subpaths should be found."
+        );
+        assert_eq!(
+            bag.close_open_fills(),
+            [a, b],
+            "The open fills should be closed."
+        );
+        assert!(
+            bag.check_open_fills().is_empty(),
+            "No open fills should remain."
+        );
+        let (Some(GraphicsItem::FatShape(sa)), Some(GraphicsItem::FatShape(sb))) =
+            (bag.get(a), bag.get(b))
+        else {
+            panic!("Items should still be shapes.");
+        };
+        assert!(
+            Arc::ptr_eq(&sa.shape, &sb.shape),
+            "Shared shapes should stay shared."
+        );
+    }
+
+    #[test]
+    fn compact_paths() {
+        let mut bag = GraphicsBag::default();
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((1.0, 0.0));
+        let path = Arc::new(AnyShape::from(path));
+        let mut push = |shape: &Arc<AnyShape>| {
+            bag.push(FatShape {
+                shape: shape.clone(),
+                ..Default::default()
+            })
+        };
+        let a = push(&path);
+        let b = push(&path);
+        let _rect = push(&Arc::new(Rect::new(0.0, 0.0, 1.0, 1.0).into()));
+
+        assert_eq!(
+            bag.compact_paths(),
+            [a, b],
+            "Only paths should be compacted."
+        );
+        let (Some(GraphicsItem::FatShape(sa)), Some(GraphicsItem::FatShape(sb))) =
+            (bag.get(a), bag.get(b))
+        else {
+            panic!("Items should still be shapes.");
+        };
+        assert!(
+            matches!(*sa.shape, AnyShape::CompactPath(_)) && Arc::ptr_eq(&sa.shape, &sb.shape),
+            "Shared paths should be compacted once and stay shared."
+        );
+        assert!(
+            bag.compact_paths().is_empty(),
+            "Compacting again should do nothing."
+        );
+    }
+
+    #[test]
+    fn epoch() {
+        let mut bag = GraphicsBag::default();
+        let empty = bag.epoch();
+        assert_ne!(
+            empty,
+            GraphicsBag::default().epoch(),
+            "Epochs of different bags should differ."
+        );
+        let paint = bag.register_paint(FatPaint::default());
+        bag.update_transform(Default::default(), Affine::scale(2.0));
+        assert_eq!(
+            bag.epoch(),
+            empty,
+            "Registering paints and updating transforms should keep the epoch."
+        );
+        bag.push(FatShape {
+            paint,
+            shape: Arc::new(Rect::new(0.0, 0.0, 1.0, 1.0).into()),
+            ..Default::default()
+        });
+        let pushed = bag.epoch();
+        assert_ne!(pushed, empty, "Pushing an item should change the epoch.");
+        bag.compact_paths();
+        assert_eq!(
+            bag.epoch(),
+            pushed,
+            "Compacting without changes should keep the epoch."
+        );
+        bag.touch();
+        assert_ne!(bag.epoch(), pushed, "Touching should change the epoch.");
+        let touched = bag.epoch();
+        let _ = bag.get_mut(bag.item_handle(0));
+        assert_ne!(
+            bag.epoch(),
+            touched,
+            "Getting an item to change it should change the epoch."
+        );
+    }
+
+    #[test]
+    fn item_revisions() {
+        let mut bag = GraphicsBag::default();
+        let paint = bag.register_paint(FatPaint::default());
+        let outer = bag.register_transform(Default::default(), Affine::IDENTITY);
+        let inner = bag.register_transform(outer, Affine::IDENTITY);
+        let item = bag.push(FatShape {
+            transform: inner,
+            paint,
+            ..Default::default()
+        });
+        let other = bag.push(FatShape::default());
+        let pushed = bag.revision();
+        let item_pushed = bag.item_revision(item);
+
+        let strokes = bag.stroke_revision();
+        bag.update_transform(Default::default(), Affine::scale(2.0));
+        assert_eq!(
+            bag.stroke_revision(),
+            strokes,
+            "Root transforms should not count as changes to strokes."
+        );
+        bag.get_stroke_mut(paint).width = 2.0;
+        assert_eq!(
+            (bag.revision(), bag.item_revision(item)),
+            (pushed, item_pushed),
+            "Root transforms and strokes should not count as changes."
+        );
+        assert!(
+            bag.stroke_revision() > strokes,
+            "Changing a stroke should advance the stroke revision."
+        );
+
+        bag.update_transforms([(outer, Affine::translate((1.0, 0.0)))]);
+        let moved = bag.item_revision(item);
+        assert!(
+            moved > pushed && bag.item_revision(other) <= pushed,
+            "Changing an ancestor transform should change only the items below it."
+        );
+        bag.get_paint_mut(paint).fill_paint = Some(Color::BLACK.into());
+        assert!(
+            bag.item_revision(item) > moved,
+            "Changing a paint should change the items using it."
+        );
+        let _ = bag.get_mut(other);
+        assert_eq!(
+            bag.item_revision(other),
+            bag.revision(),
+            "Getting an item to change it should change it."
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_checks_handles() {
+        use alloc::string::ToString;
+
+        let mut bag = GraphicsBag::default();
+        let paint = bag.register_paint(FatPaint::default());
+        let transform = bag.register_transform(Default::default(), Affine::scale(2.0));
+        bag.push(FatShape {
+            transform,
+            paint,
+            ..Default::default()
+        });
+        let json = serde_json::to_value(&bag).unwrap();
+        assert!(
+            serde_json::from_value::<GraphicsBag>(json.clone()).is_ok(),
+            "A serialized bag should deserialize."
+        );
+
+        let tampered = |f: fn(&mut serde_json::Value)| {
+            let mut json = json.clone();
+            f(&mut json);
+            serde_json::from_value::<GraphicsBag>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            tampered(|j| j["palette"] = serde_json::json!([])),
+            "paint handle out of range",
+            "Items should not refer to missing paints."
+        );
+        assert_eq!(
+            tampered(|j| j["final_transforms"].as_array_mut().unwrap().truncate(1)),
+            "final transforms don't match managed transforms",
+            "Transform vectors should have matching lengths."
+        );
+        assert_eq!(
+            tampered(|j| {
+                j["managed_transforms"].as_array_mut().unwrap().truncate(1);
+                j["final_transforms"].as_array_mut().unwrap().truncate(1);
+            }),
+            "transform handle out of range",
+            "Items should not refer to missing transforms."
+        );
+        assert_eq!(
+            tampered(|j| j["managed_transforms"][1]["parent"] = serde_json::json!(1)),
+            "transform parent out of order",
+            "Transforms should refer to parents registered before them."
+        );
+        assert_eq!(
+            tampered(|j| j["items"][0]["FatShape"]["clip"] = serde_json::json!(1)),
+            "clip handle out of range",
+            "Items should not refer to missing clips."
+        );
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Line hatching of filled regions.
+//!
+//! A [`HatchPattern`] is a set of families of parallel lines, described the way DXF
+//! and `.pat` files describe them: each [`HatchLine`] has an angle, a base point that
+//! one line of the family passes through, an offset from each line to the next, and
+//! an optional dash pattern that starts at the base point.
+//!
+//! [`hatch`] clips the lines of a pattern to the region filled by a boundary path,
+//! and returns them as open line segments to be stroked. The boundary is flattened
+//! within the tolerance, and open subpaths are treated as closed, as they are when
+//! filled.
+
+extern crate alloc;
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use peniko::{
+    Fill,
+    kurbo::{BezPath, PathEl, Point, Vec2, flatten},
+};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+use crate::{PaintHandle, TransformHandle, shape::FatShape};
+
+/// Maximum number of line segments generated by [`hatch`].
+///
+/// Patterns that are tiny relative to their boundary would otherwise generate an
+/// unbounded amount of geometry, so generation stops with a warning here.
+pub const MAX_HATCH_SEGMENTS: usize = 1 << 20;
+
+/// A family of parallel lines in a [`HatchPattern`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HatchLine {
+    /// Direction of the lines, in radians counterclockwise from the x axis.
+    pub angle: f64,
+    /// Point that one line of the family passes through, and where its dashes start.
+    pub base: Point,
+    /// Offset from each line to the next, with `x` along the lines and `y` across them.
+    ///
+    /// The `x` component shifts the dashes of each line relative to the previous one.
+    pub offset: Vec2,
+    /// Lengths of dashes, which are positive, and gaps, which are negative.
+    ///
+    /// Zero is a dot. If this is empty, the lines are solid.
+    pub dashes: Vec<f64>,
+}
+
+/// A hatch pattern made of families of parallel lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HatchPattern {
+    /// Families of lines.
+    pub lines: Vec<HatchLine>,
+}
+
+/// Definitions of the standard patterns, from `acad.pat` and `acadiso.pat`.
+///
+/// Each line is an angle in degrees, a base point, an offset, and dashes. ANSI patterns
+/// are in inches, and ISO patterns in millimeters.
+#[allow(clippy::type_complexity, reason = "It is a table.")]
+const NAMED: &[(&str, &[(f64, [f64; 2], [f64; 2], &[f64])])] = &[
+    ("LINE", &[(0.0, [0.0, 0.0], [0.0, 0.125], &[])]),
+    (
+        "NET",
+        &[
+            (0.0, [0.0, 0.0], [0.0, 0.125], &[]),
+            (90.0, [0.0, 0.0], [0.0, 0.125], &[]),
+        ],
+    ),
+    (
+        "NET3",
+        &[
+            (0.0, [0.0, 0.0], [0.0, 0.125], &[]),
+            (60.0, [0.0, 0.0], [0.0, 0.125], &[]),
+            (120.0, [0.0, 0.0], [0.0, 0.125], &[]),
+        ],
+    ),
+    (
+        "DASH",
+        &[(0.0, [0.0, 0.0], [0.125, 0.125], &[0.125, -0.125])],
+    ),
+    ("ANSI31", &[(45.0, [0.0, 0.0], [0.0, 0.125], &[])]),
+    (
+        "ANSI32",
+        &[
+            (45.0, [0.0, 0.0], [0.0, 0.375], &[]),
+            (45.0, [0.176_776_695, 0.0], [0.0, 0.375], &[]),
+        ],
+    ),
+    (
+        "ANSI33",
+        &[
+            (45.0, [0.0, 0.0], [0.0, 0.25], &[]),
+            (45.0, [0.176_776_695, 0.0], [0.0, 0.25], &[0.125, -0.0625]),
+        ],
+    ),
+    (
+        "ANSI34",
+        &[
+            (45.0, [0.0, 0.0], [0.0, 0.75], &[]),
+            (45.0, [0.176_776_695, 0.0], [0.0, 0.75], &[]),
+            (45.0, [0.353_553_391, 0.0], [0.0, 0.75], &[]),
+            (45.0, [0.530_330_086, 0.0], [0.0, 0.75], &[]),
+        ],
+    ),
+    (
+        "ANSI35",
+        &[
+            (45.0, [0.0, 0.0], [0.0, 0.25], &[]),
+            (
+                45.0,
+                [0.176_776_695, 0.0],
+                [0.0, 0.25],
+                &[0.3125, -0.0625, 0.0, -0.0625],
+            ),
+        ],
+    ),
+    (
+        "ANSI36",
+        &[(
+            45.0,
+            [0.0, 0.0],
+            [0.3125, 0.125],
+            &[0.3125, -0.0625, 0.0, -0.0625],
+        )],
+    ),
+    (
+        "ANSI37",
+        &[
+            (45.0, [0.0, 0.0], [0.0, 0.125], &[]),
+            (135.0, [0.0, 0.0], [0.0, 0.125], &[]),
+        ],
+    ),
+    (
+        "ANSI38",
+        &[
+            (45.0, [0.0, 0.0], [0.0, 0.125], &[]),
+            (135.0, [0.0, 0.0], [0.25, 0.125], &[0.3125, -0.1875]),
+        ],
+    ),
+    (
+        "ACAD_ISO02W100",
+        &[(0.0, [0.0, 0.0], [0.0, 5.0], &[12.0, -3.0])],
+    ),
+    (
+        "ACAD_ISO03W100",
+        &[(0.0, [0.0, 0.0], [0.0, 5.0], &[12.0, -18.0])],
+    ),
+    (
+        "ACAD_ISO04W100",
+        &[(0.0, [0.0, 0.0], [0.0, 5.0], &[24.0, -3.0, 0.5, -3.0])],
+    ),
+];
+
+impl HatchPattern {
+    /// Make a pattern of solid parallel lines `spacing` apart, through the origin.
+    pub fn parallel(angle: f64, spacing: f64) -> Self {
+        Self {
+            lines: vec![HatchLine {
+                angle,
+                base: Point::ZERO,
+                offset: Vec2::new(0.0, spacing),
+                dashes: Vec::new(),
+            }],
+        }
+    }
+
+    /// Make a pattern of solid lines `spacing` apart, crossing at right angles.
+    ///
+    /// This is the double hatch of DXF user defined patterns.
+    pub fn crossed(angle: f64, spacing: f64) -> Self {
+        let mut pattern = Self::parallel(angle, spacing);
+        pattern
+            .lines
+            .extend(Self::parallel(angle + core::f64::consts::FRAC_PI_2, spacing).lines);
+        pattern
+    }
+
+    /// Get a standard pattern by name, ignoring case.
+    ///
+    /// This covers `LINE`, `NET`, `NET3`, `DASH`, `ANSI31` to `ANSI38`, and
+    /// `ACAD_ISO02W100` to `ACAD_ISO04W100`, at the sizes in their definitions, which are
+    /// inches for the ANSI patterns and millimeters for the ISO ones. Use
+    /// [`placed`](Self::placed) to rotate, scale, and move them.
+    pub fn named(name: &str) -> Option<Self> {
+        let (_, lines) = NAMED.iter().find(|(n, _)| n.eq_ignore_ascii_case(name))?;
+        Some(Self {
+            lines: lines
+                .iter()
+                .map(|(angle, [x, y], [dx, dy], dashes)| HatchLine {
+                    angle: angle.to_radians(),
+                    base: Point::new(*x, *y),
+                    offset: Vec2::new(*dx, *dy),
+                    dashes: dashes.to_vec(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Rotate the pattern by `angle` and scale it by `scale` about the origin, then
+    /// move the origin to `origin`.
+    ///
+    /// This is how DXF places a pattern with the angle, scale, and seed point of a hatch.
+    pub fn placed(&self, angle: f64, scale: f64, origin: Point) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            lines: self
+                .lines
+                .iter()
+                .map(|l| {
+                    let b = l.base.to_vec2() * scale;
+                    HatchLine {
+                        angle: l.angle + angle,
+                        base: origin + Vec2::new(b.x * cos - b.y * sin, b.x * sin + b.y * cos),
+                        offset: l.offset * scale,
+                        dashes: l.dashes.iter().map(|d| d * scale).collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Generate the lines of `pattern` within the region filled by `boundary` with `fill`.
+///
+/// The result is a path of open line segments, to be stroked. Dots in dashed lines are
+/// zero length segments, which are only visible with round or square caps. At most
+/// [`MAX_HATCH_SEGMENTS`] segments are generated.
+pub fn hatch(boundary: &BezPath, fill: Fill, pattern: &HatchPattern, tolerance: f64) -> BezPath {
+    let tolerance = if tolerance > 0.0 { tolerance } else { 0.1 };
+    let edges = edges(boundary, tolerance);
+    let mut out = BezPath::new();
+    let mut budget = MAX_HATCH_SEGMENTS;
+    for line in &pattern.lines {
+        if !hatch_family(&edges, fill, line, &mut budget, &mut out) {
+            tracing::warn!(
+                limit = MAX_HATCH_SEGMENTS,
+                "hatch pattern is too fine for its boundary, lines were left out"
+            );
+            break;
+        }
+    }
+    out
+}
+
+/// Make a shape item that draws `pattern` within the region filled by `boundary`.
+///
+/// See [`hatch`]. `paint` should stroke, as the shape is made of open line segments.
+pub fn hatch_shape(
+    boundary: &BezPath,
+    fill: Fill,
+    pattern: &HatchPattern,
+    tolerance: f64,
+    transform: TransformHandle,
+    paint: PaintHandle,
+) -> FatShape {
+    FatShape {
+        transform,
+        paint,
+        shape: Arc::new(hatch(boundary, fill, pattern, tolerance).into()),
+        ..Default::default()
+    }
+}
+
+/// Flatten a path to the edges of a polygon, closing every subpath.
+fn edges(path: &BezPath, tolerance: f64) -> Vec<(Point, Point)> {
+    let mut edges = Vec::new();
+    let mut start = None;
+    let mut last = None;
+    let close = |edges: &mut Vec<_>, start: Option<Point>, last: &mut Option<Point>| {
+        if let (Some(s), Some(l)) = (start, *last) {
+            if s != l {
+                edges.push((l, s));
+            }
+        }
+        *last = start;
+    };
+    flatten(path, tolerance, |el| match el {
+        PathEl::MoveTo(p) => {
+            close(&mut edges, start, &mut last);
+            start = Some(p);
+            last = start;
+        }
+        PathEl::LineTo(p) => {
+            if let Some(l) = last {
+                if l != p {
+                    edges.push((l, p));
+                }
+            }
+            last = Some(p);
+        }
+        PathEl::ClosePath => close(&mut edges, start, &mut last),
+        PathEl::QuadTo(..) | PathEl::CurveTo(..) => {}
+    });
+    close(&mut edges, start, &mut last);
+    edges
+}
+
+/// Generate one family of lines into `out`, spending `budget` on segments.
+///
+/// Returns `false` if the budget ran out.
+fn hatch_family(
+    edges: &[(Point, Point)],
+    fill: Fill,
+    line: &HatchLine,
+    budget: &mut usize,
+    out: &mut BezPath,
+) -> bool {
+    let (sin, cos) = line.angle.sin_cos();
+    let along = Vec2::new(cos, sin);
+    let across = Vec2::new(-sin, cos);
+    let spacing = line.offset.y;
+    if !spacing.is_finite() || spacing == 0.0 || edges.is_empty() {
+        return true;
+    }
+    let step = along * line.offset.x + across * spacing;
+
+    // Range of lines that can cross the boundary.
+    let (lo, hi) = edges
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (p, _)| {
+            let d = (*p - line.base).dot(across) / spacing;
+            (lo.min(d), hi.max(d))
+        });
+    let (first, last) = (lo.ceil(), hi.floor());
+    if (last - first).is_nan() || last - first >= *budget as f64 {
+        return false;
+    }
+
+    let period: f64 = line.dashes.iter().map(|d| d.abs()).sum();
+    let dashed = !line.dashes.is_empty() && period > 0.0;
+    let mut crossings: Vec<(f64, i32)> = Vec::new();
+    let mut k = first;
+    while k <= last {
+        let base = line.base + step * k;
+        // Where the edges cross the line, as distances along it from its base, with
+        // the direction each crosses in.
+        crossings.clear();
+        for (p0, p1) in edges {
+            let (s0, s1) = ((*p0 - base).dot(across), (*p1 - base).dot(across));
+            if (s0 > 0.0) != (s1 > 0.0) {
+                let p = p0.lerp(*p1, s0 / (s0 - s1));
+                crossings.push(((p - base).dot(along), if s1 > 0.0 { 1 } else { -1 }));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            let inside = match fill {
+                Fill::NonZero => winding != 0,
+                Fill::EvenOdd => winding % 2 != 0,
+            };
+            let (u0, u1) = (pair[0].0, pair[1].0);
+            if !inside || u1 <= u0 {
+                continue;
+            }
+            if !dashed {
+                if *budget == 0 {
+                    return false;
+                }
+                *budget -= 1;
+                out.move_to(base + along * u0);
+                out.line_to(base + along * u1);
+                continue;
+            }
+            // Dashes repeat from the base of the line.
+            let mut pos = (u0 / period).floor() * period;
+            'dashes: while pos <= u1 {
+                for dash in &line.dashes {
+                    let end = pos + dash.abs();
+                    let visible = if *dash > 0.0 {
+                        end > u0 && pos < u1
+                    } else {
+                        *dash == 0.0 && pos >= u0
+                    };
+                    if visible {
+                        if *budget == 0 {
+                            return false;
+                        }
+                        *budget -= 1;
+                        out.move_to(base + along * pos.max(u0));
+                        out.line_to(base + along * end.min(u1));
+                    }
+                    pos = end;
+                    if pos > u1 {
+                        break 'dashes;
+                    }
+                }
+            }
+        }
+        k += 1.0;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::{ParamCurve, Rect, Shape};
+
+    /// Get the total length of the segments in a path of open line segments.
+    fn total_length(path: &BezPath) -> f64 {
+        path.segments().map(|s| (s.end() - s.start()).hypot()).sum()
+    }
+
+    #[test]
+    fn hatch_square() {
+        let square = Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1);
+        let pattern = HatchPattern::parallel(0.0, 1.0).placed(0.0, 1.0, Point::new(0.0, 0.5));
+        let lines = hatch(&square, Fill::NonZero, &pattern, 0.1);
+        assert_eq!(
+            lines.segments().count(),
+            10,
+            "One segment should cross the square for each line."
+        );
+        assert!(
+            (total_length(&lines) - 100.0).abs() < 1e-9,
+            "Lines should be clipped to the square."
+        );
+
+        let mut holed = square.clone();
+        holed.extend(&Rect::new(2.0, 2.0, 8.0, 8.0).to_path(0.1));
+        assert!(
+            (total_length(&hatch(&holed, Fill::EvenOdd, &pattern, 0.1)) - 64.0).abs() < 1e-9,
+            "Holes should be left out with the even-odd rule."
+        );
+        assert!(
+            (total_length(&hatch(&holed, Fill::NonZero, &pattern, 0.1)) - 100.0).abs() < 1e-9,
+            "A hole winding the same way should be filled with the nonzero rule."
+        );
+
+        let dashed = HatchPattern {
+            lines: vec![HatchLine {
+                dashes: vec![1.0, -1.0],
+                ..pattern.lines[0].clone()
+            }],
+        };
+        assert!(
+            (total_length(&hatch(&square, Fill::NonZero, &dashed, 0.1)) - 50.0).abs() < 1e-9,
+            "Gaps should be left out of dashed lines."
+        );
+    }
+
+    #[test]
+    fn named_patterns() {
+        let ansi37 = HatchPattern::named("ansi37").unwrap();
+        assert_eq!(ansi37.lines.len(), 2, "ANSI37 should cross two families.");
+        let placed = ansi37.placed(core::f64::consts::FRAC_PI_4, 8.0, Point::new(1.0, 2.0));
+        assert!(
+            (placed.lines[0].angle - core::f64::consts::FRAC_PI_2).abs() < 1e-12
+                && placed.lines[0].offset == Vec2::new(0.0, 1.0)
+                && placed.lines[0].base == Point::new(1.0, 2.0),
+            "Placing should rotate, scale, and move the pattern."
+        );
+        assert!(
+            HatchPattern::named("NOT_A_PATTERN").is_none(),
+            "Unknown names should not match."
+        );
+    }
+}
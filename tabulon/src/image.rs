@@ -0,0 +1,79 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use peniko::{
+    Image,
+    kurbo::{Affine, Rect},
+};
+
+use crate::TransformHandle;
+
+/// A raster image, such as an underlay, a map tile, or a scanned drawing.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FatImage {
+    /// Affine transform
+    pub transform: TransformHandle,
+    /// Image data.
+    pub image: Image,
+    /// Placement of the image.
+    ///
+    /// This maps image pixels, with the origin at the top left corner of the image,
+    /// into the coordinate space of `transform`.
+    pub placement: Affine,
+    /// Opacity of the image, from 0 to 1.
+    pub opacity: f32,
+}
+
+impl FatImage {
+    /// Create an image placed with its top left corner at the origin, one unit per pixel,
+    /// and fully opaque.
+    pub fn new(image: Image) -> Self {
+        Self {
+            transform: Default::default(),
+            image,
+            placement: Affine::IDENTITY,
+            opacity: 1.0,
+        }
+    }
+
+    /// Get the bounding box of the placed image.
+    ///
+    /// The bounds are in the coordinate space of the image's `transform`.
+    pub fn bounding_box(&self) -> Rect {
+        self.placement.transform_rect_bbox(Rect::new(
+            0.0,
+            0.0,
+            f64::from(self.image.width),
+            f64::from(self.image.height),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::{Blob, ImageFormat, kurbo::Vec2};
+
+    extern crate alloc;
+    use alloc::{sync::Arc, vec};
+
+    #[test]
+    fn placed_bounds() {
+        let image = Image::new(
+            Blob::new(Arc::new(vec![0_u8; 4 * 8 * 4])),
+            ImageFormat::Rgba8,
+            8,
+            4,
+        );
+        let placed = FatImage {
+            placement: Affine::scale(0.5).then_translate(Vec2::new(10.0, 20.0)),
+            ..FatImage::new(image)
+        };
+        assert_eq!(
+            placed.bounding_box(),
+            Rect::new(10.0, 20.0, 14.0, 22.0),
+            "Bounds should cover the placed pixels."
+        );
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Sets of the items of a [`GraphicsBag`](crate::GraphicsBag), stored as bitsets.
+//!
+//! Item handles are dense indices into their bag, so a set of them is a bit per item
+//! of the bag. Building one is a write per item, checking membership is a lookup, and
+//! sets are combined a word at a time, which suits sets that are made every frame, such
+//! as the items found by culling, combined with the items of visible layers and the
+//! items hidden by the application.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::{ItemHandle, graphics_bag::BagId};
+
+/// Bits per word of an [`ItemSet`].
+const BITS: usize = u64::BITS as usize;
+
+/// A set of [`ItemHandle`]s from one [`GraphicsBag`](crate::GraphicsBag).
+///
+/// See the [module documentation](self) for details. Items iterate in the order of
+/// their handles, which is the order they were added to the bag.
+#[derive(Debug, Default, Clone)]
+pub struct ItemSet {
+    words: Vec<u64>,
+    /// Tag of the handles in the set, for the handles it yields.
+    bag: BagId,
+}
+
+impl ItemSet {
+    /// Make an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make an empty set with room for the items of a bag of `len` items.
+    pub fn with_capacity(len: usize) -> Self {
+        Self {
+            words: Vec::with_capacity(len.div_ceil(BITS)),
+            bag: BagId::default(),
+        }
+    }
+
+    /// Add an item, returning whether it was not already in the set.
+    pub fn insert(&mut self, item: ItemHandle) -> bool {
+        let (word, bit) = Self::position(item);
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+        self.bag = item.1;
+        let added = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        added
+    }
+
+    /// Remove an item, returning whether it was in the set.
+    pub fn remove(&mut self, item: ItemHandle) -> bool {
+        let (word, bit) = Self::position(item);
+        let Some(w) = self.words.get_mut(word) else {
+            return false;
+        };
+        let removed = *w & bit != 0;
+        *w &= !bit;
+        removed
+    }
+
+    /// Check whether an item is in the set.
+    pub fn contains(&self, item: ItemHandle) -> bool {
+        let (word, bit) = Self::position(item);
+        self.words.get(word).is_some_and(|w| w & bit != 0)
+    }
+
+    /// Count the items in the set.
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Check whether the set has no items.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    /// Remove all items, keeping the memory for reuse.
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Iterate over the items in the set, in the order of their handles.
+    pub fn iter(&self) -> impl Iterator<Item = ItemHandle> + '_ {
+        self.words.iter().enumerate().flat_map(move |(i, &w)| {
+            let mut rest = w;
+            core::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let bit = rest.trailing_zeros() as usize;
+                rest &= rest - 1;
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    reason = "Handles are u32, so their positions are too."
+                )]
+                Some(ItemHandle((i * BITS + bit) as u32, self.bag))
+            })
+        })
+    }
+
+    /// Add the items of `other`.
+    pub fn union_with(&mut self, other: &Self) {
+        if self.words.len() < other.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w |= o;
+        }
+        if !other.is_empty() {
+            self.bag = other.bag;
+        }
+    }
+
+    /// Keep only the items that are also in `other`.
+    pub fn intersect_with(&mut self, other: &Self) {
+        self.words.truncate(other.words.len());
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w &= o;
+        }
+    }
+
+    /// Remove the items of `other`, such as items that are hidden.
+    pub fn difference_with(&mut self, other: &Self) {
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w &= !o;
+        }
+    }
+
+    /// Get the word and bit of an item.
+    fn position(item: ItemHandle) -> (usize, u64) {
+        let i = item.0 as usize;
+        (i / BITS, 1 << (i % BITS))
+    }
+}
+
+impl PartialEq for ItemSet {
+    fn eq(&self, other: &Self) -> bool {
+        let (short, long) = if self.words.len() <= other.words.len() {
+            (&self.words, &other.words)
+        } else {
+            (&other.words, &self.words)
+        };
+        long[..short.len()] == short[..] && long[short.len()..].iter().all(|w| *w == 0)
+    }
+}
+
+impl Eq for ItemSet {}
+
+impl Extend<ItemHandle> for ItemSet {
+    fn extend<I: IntoIterator<Item = ItemHandle>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl FromIterator<ItemHandle> for ItemSet {
+    fn from_iter<I: IntoIterator<Item = ItemHandle>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphicsBag, shape::FatShape};
+
+    #[test]
+    fn set_operations() {
+        let mut bag = GraphicsBag::default();
+        let items: Vec<ItemHandle> = (0..200).map(|_| bag.push(FatShape::default())).collect();
+
+        let culled: ItemSet = items[50..150].iter().copied().collect();
+        let mut visible: ItemSet = items.iter().copied().step_by(2).collect();
+        let hidden: ItemSet = [items[60], items[199]].into_iter().collect();
+        assert!(
+            culled.contains(items[64]) && !culled.contains(items[150]),
+            "Sets should contain the items inserted."
+        );
+
+        visible.intersect_with(&culled);
+        visible.difference_with(&hidden);
+        let expected: Vec<ItemHandle> = items[50..150]
+            .iter()
+            .copied()
+            .step_by(2)
+            .filter(|ih| *ih != items[60])
+            .collect();
+        assert_eq!(
+            visible.iter().collect::<Vec<_>>(),
+            expected,
+            "Combined sets should iterate in handle order."
+        );
+        assert_eq!(visible.len(), 49, "Sets should count their items.");
+
+        let mut all = visible.clone();
+        all.union_with(&hidden);
+        assert!(all.contains(items[199]), "Unions should grow the set.");
+        assert!(
+            all.remove(items[199]),
+            "Removed items should have been in the set."
+        );
+        assert!(
+            all.remove(items[60]),
+            "Removed items should have been in the set."
+        );
+        assert_eq!(
+            all, visible,
+            "Sets with the same items should be equal, whatever their capacity."
+        );
+    }
+}
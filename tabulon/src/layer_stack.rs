@@ -0,0 +1,152 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use crate::render_layer::RenderLayer;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// A handle for a [`StackedLayer`] in a [`LayerStack`].
+#[derive(Debug, Default, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackedLayerHandle(pub(crate) u32);
+
+impl From<StackedLayerHandle> for usize {
+    fn from(h: StackedLayerHandle) -> Self {
+        h.0 as Self
+    }
+}
+
+/// A [`RenderLayer`] with composition settings.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackedLayer {
+    /// Items in the layer.
+    pub layer: RenderLayer,
+    /// Whether the layer is drawn.
+    pub visible: bool,
+    /// Opacity of the layer as a whole, from 0 to 1.
+    pub opacity: f32,
+    /// Position of the layer in the stack.
+    ///
+    /// Layers with higher `z` are drawn above layers with lower `z`,
+    /// layers with equal `z` are drawn in the order they were pushed.
+    pub z: i32,
+}
+
+impl Default for StackedLayer {
+    fn default() -> Self {
+        Self {
+            layer: Default::default(),
+            visible: true,
+            opacity: 1.0,
+            z: 0,
+        }
+    }
+}
+
+impl From<RenderLayer> for StackedLayer {
+    fn from(layer: RenderLayer) -> Self {
+        Self {
+            layer,
+            ..Default::default()
+        }
+    }
+}
+
+/// Stack of [`RenderLayer`]s composed in z order.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerStack {
+    /// Layers in the order they were pushed.
+    pub layers: Vec<StackedLayer>,
+}
+
+impl LayerStack {
+    /// Push a layer, returning its handle.
+    pub fn push(&mut self, layer: impl Into<StackedLayer>) -> StackedLayerHandle {
+        let n = self.layers.len();
+        if n >= u32::MAX as usize {
+            panic!("LayerStack has too many layers.");
+        }
+        self.layers.push(layer.into());
+        StackedLayerHandle(n.try_into().unwrap())
+    }
+
+    /// Get a layer.
+    #[must_use]
+    pub fn get(&self, handle: StackedLayerHandle) -> Option<&StackedLayer> {
+        self.layers.get(usize::from(handle))
+    }
+
+    /// Get a layer.
+    #[must_use]
+    pub fn get_mut(&mut self, handle: StackedLayerHandle) -> Option<&mut StackedLayer> {
+        self.layers.get_mut(usize::from(handle))
+    }
+
+    /// Set whether a layer is drawn.
+    pub fn set_visible(&mut self, handle: StackedLayerHandle, visible: bool) {
+        self.layers[usize::from(handle)].visible = visible;
+    }
+
+    /// Visible layers in drawing order, from bottom to top.
+    pub fn visible_layers(&self) -> impl Iterator<Item = &StackedLayer> {
+        self.visible_handles().map(|h| &self.layers[usize::from(h)])
+    }
+
+    /// Handles of the visible layers in drawing order, from bottom to top.
+    pub fn visible_handles(&self) -> impl Iterator<Item = StackedLayerHandle> {
+        let mut order: Vec<(i32, StackedLayerHandle)> = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.visible)
+            // `push` keeps the number of layers within the range of `u32`.
+            .map(|(i, l)| (l.z, StackedLayerHandle(i.try_into().unwrap())))
+            .collect();
+        // Stable, so layers with equal `z` keep their push order.
+        order.sort_by_key(|(z, _)| *z);
+        order.into_iter().map(|(_, h)| h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ItemHandle;
+
+    extern crate alloc;
+    use alloc::vec;
+
+    fn layer(i: u32, z: i32) -> StackedLayer {
+        StackedLayer {
+            layer: RenderLayer {
+                indices: vec![ItemHandle(i, Default::default())],
+            },
+            z,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn visible_layers_order() {
+        let mut stack = LayerStack::default();
+        stack.push(layer(0, 1));
+        let hidden = stack.push(layer(1, 0));
+        stack.push(layer(2, 0));
+        stack.push(layer(3, -1));
+        stack.push(layer(4, 0));
+        stack.set_visible(hidden, false);
+
+        let order: Vec<u32> = stack
+            .visible_layers()
+            .map(|l| l.layer.indices[0].0)
+            .collect();
+        assert_eq!(
+            order,
+            [3, 2, 4, 0],
+            "Layers should be ordered by z, then push order, skipping hidden layers."
+        );
+    }
+}
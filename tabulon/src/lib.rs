@@ -8,11 +8,27 @@
 //! - `std` (enabled by default): Get floating point functions from the standard library
 //!   (likely using your target's libc).
 //! - `libm`: Use floating point implementations from [libm][].
+//! - `serde`: Implement serialization for [`GraphicsBag`], [`RenderLayer`](render_layer::RenderLayer),
+//!   their items and handles, so translated scenes can be cached.
+//! - `text` (enabled by default): Text items, `FatText` and `FatTextOnPath`, styled with
+//!   [Parley][], and [`outline`], which lays them out into glyph outlines for backends
+//!   that draw text as paths. Without it, Parley is not compiled, and bags hold only
+//!   shapes, images, and markers, which is enough for converting and measuring geometry.
 //!
 //! At least one of `std` and `libm` is required; `std` overrides `libm`.
 //!
+//! Tabulon is `no_std`, and only needs `alloc`. With `libm` instead of `std`, and without
+//! `text`, it builds the scene model for bare metal targets, such as the firmware of
+//! panels and plotters. Targets need 64-bit atomics, which Peniko uses to identify
+//! images.
+//!
 #![cfg_attr(feature = "libm", doc = "[libm]: libm")]
 #![cfg_attr(not(feature = "libm"), doc = "[libm]: https://crates.io/crates/libm")]
+#![cfg_attr(feature = "text", doc = "[Parley]: parley")]
+#![cfg_attr(
+    not(feature = "text"),
+    doc = "[Parley]: https://crates.io/crates/parley"
+)]
 // LINEBENDER LINT SET - lib.rs - v3
 // See https://linebender.org/wiki/canonical-lints/
 // These lints shouldn't apply to examples or tests.
@@ -35,23 +51,99 @@ fn ensure_libm_dependency_used() -> f32 {
     libm::sqrtf(4_f32)
 }
 
+/// Animation of transforms and dash offsets over time.
+pub mod animation;
+
+/// Drawing graphics items with any renderer.
+pub mod backend;
+
+/// Boolean operations and offsetting of filled paths.
+pub mod boolean;
+
+/// Bounding boxes of graphics items.
+pub mod bounds;
+
+/// Paths stored with single precision.
+pub mod compact_path;
+
+/// Angle constraints for interactive tools.
+pub mod constraint;
+
+/// Errors from fallible accessors.
+pub mod error;
+
+/// Static geometry shared between bags as one unit.
+pub mod geometry_chunk;
+pub use error::TabulonError;
+
 /// Collection of graphics items.
 pub mod graphics_bag;
 pub use graphics_bag::*;
 
+/// Line hatching of filled regions.
+pub mod hatch;
+
+/// Raster image items.
+pub mod image;
+
+/// Sets of items, for culling and visibility.
+pub mod item_set;
+
+/// Stack of render layers with per-layer visibility, opacity, and z order.
+pub mod layer_stack;
+
+/// Point markers sized in device space.
+pub mod marker;
+
+/// Measurement of lengths, areas, and centroids of shapes.
+pub mod measure;
+
+/// Coarse occlusion culling of items beneath opaque fills.
+pub mod occlusion;
+
+/// Hit testing of graphics items.
+pub mod pick;
+pub use pick::{device_pixel_tolerance, pick};
+
 /// Render layer which lists graphics items in a [`GraphicsBag`] for rendering.
 pub mod render_layer;
 
 /// Shapes for rendering and event dispatch.
 pub mod shape;
 
+/// Simplification of paths for drawing at small scales.
+pub mod simplify;
+
+/// Binary snapshots of graphics bags.
+pub mod snapshot;
+
+/// Spatial index of graphics items for culling and picking.
+pub mod spatial_index;
+
+/// Statistics about the contents and memory use of graphics bags.
+pub mod stats;
+
 /// Utilities for transformations.
 pub mod transform;
 pub use transform::*;
 
+/// Panning and zooming of views from pointer input, with smoothing and inertia.
+pub mod view_controller;
+
 /// Text items.
+#[cfg(feature = "text")]
 pub mod text;
 
+/// Layout of text items, and glyph outlines, for backends that draw text as paths.
+#[cfg(feature = "text")]
+pub mod outline;
+
+/// Text items placed along a path, and measurement of paths by arc length.
+pub mod text_on_path;
+
+#[cfg(all(feature = "serde", feature = "text"))]
+mod text_serde;
+
 pub use peniko;
 
 #[cfg(test)]
@@ -0,0 +1,161 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use peniko::kurbo::{Affine, BezPath, Circle, Line, Point, Rect, Shape};
+
+extern crate alloc;
+use alloc::sync::Arc;
+
+use crate::{PaintHandle, TransformHandle};
+
+/// Outline of a [`FatMarker`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MarkerShape {
+    /// A vertical and a horizontal line through the position.
+    Plus,
+    /// Two diagonal lines through the position.
+    Cross,
+    /// A circle around the position.
+    Circle,
+    /// A square around the position.
+    Square,
+    /// A custom path, such as a glyph outline.
+    ///
+    /// The path is one unit across, centered on the origin, with y pointing down;
+    /// it is scaled to the size of the marker.
+    Custom(Arc<BezPath>),
+}
+
+impl MarkerShape {
+    /// Get the outline, one unit across and centered on the origin.
+    pub fn unit_path(&self) -> BezPath {
+        match self {
+            Self::Plus => {
+                let mut path = Line::new((-0.5, 0.0), (0.5, 0.0)).to_path(0.1);
+                path.extend(Line::new((0.0, -0.5), (0.0, 0.5)).path_elements(0.1));
+                path
+            }
+            Self::Cross => {
+                let mut path = Line::new((-0.5, -0.5), (0.5, 0.5)).to_path(0.1);
+                path.extend(Line::new((-0.5, 0.5), (0.5, -0.5)).path_elements(0.1));
+                path
+            }
+            // Tolerance relative to a unit circle; this is drawn at most a few
+            // hundred pixels across.
+            Self::Circle => Circle::new(Point::ZERO, 0.5).to_path(1e-4),
+            Self::Square => Rect::new(-0.5, -0.5, 0.5, 0.5).to_path(0.1),
+            Self::Custom(path) => BezPath::clone(path),
+        }
+    }
+}
+
+/// Size of a [`FatMarker`], which does not change as the view is zoomed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MarkerSize {
+    /// Size in device pixels.
+    Pixels(f64),
+    /// Size in millimeters on the display, converted with the display's pixel density.
+    Millimeters(f64),
+}
+
+impl MarkerSize {
+    /// Get the size in device pixels, for a display with `pixels_per_millimeter`.
+    pub fn device_pixels(self, pixels_per_millimeter: f64) -> f64 {
+        match self {
+            Self::Pixels(px) => px,
+            Self::Millimeters(mm) => mm * pixels_per_millimeter,
+        }
+    }
+}
+
+impl Default for MarkerSize {
+    fn default() -> Self {
+        Self::Pixels(8.0)
+    }
+}
+
+/// A marker at a point, such as a point of a drawing or a snap indicator.
+///
+/// Only the position of a marker is transformed; its shape keeps the same size and
+/// orientation on the display however the view is zoomed or rotated.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FatMarker {
+    /// Affine transform of the position.
+    pub transform: TransformHandle,
+    /// Paint to draw the marker's outline with.
+    pub paint: PaintHandle,
+    /// Position, in the coordinate space of `transform`.
+    pub position: Point,
+    /// Outline.
+    pub shape: MarkerShape,
+    /// Size on the display.
+    pub size: MarkerSize,
+}
+
+impl FatMarker {
+    /// Create a marker at `position`.
+    pub fn new(position: Point, shape: MarkerShape, size: MarkerSize) -> Self {
+        Self {
+            transform: Default::default(),
+            paint: Default::default(),
+            position,
+            shape,
+            size,
+        }
+    }
+
+    /// Get the outline in the coordinate space of `transform`, so that it has the
+    /// marker's size and stays upright once `transform` is applied.
+    ///
+    /// `transform` is the full transform to device pixels, including the view.
+    /// Drawing the outline with `transform` means strokes are scaled the same way as
+    /// those of other items. Returns `None` if `transform` is not invertible.
+    pub fn local_path(&self, transform: Affine, pixels_per_millimeter: f64) -> Option<BezPath> {
+        if transform.determinant() == 0.0 {
+            return None;
+        }
+        let size = self.size.device_pixels(pixels_per_millimeter);
+        let device = Affine::scale(size).then_translate((transform * self.position).to_vec2());
+        Some((transform.inverse() * device) * self.shape.unit_path())
+    }
+
+    /// Get the bounds of the position, in the coordinate space of `transform`.
+    ///
+    /// The marker's outline has no size in this space, as it depends on the view.
+    pub fn position_bounds(&self) -> Rect {
+        Rect::from_origin_size(self.position, (0.0, 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_keep_device_size() {
+        let marker = FatMarker::new(
+            Point::new(10.0, 20.0),
+            MarkerShape::Square,
+            MarkerSize::Pixels(8.0),
+        );
+        let view = Affine::scale(4.0).then_rotate(1.0);
+        let device = view * marker.local_path(view, 1.0).unwrap();
+        let bounds = device.bounding_box();
+        assert!(
+            (bounds.width() - 8.0).abs() < 1e-9 && (bounds.height() - 8.0).abs() < 1e-9,
+            "Markers should be upright and sized in device pixels, got {bounds:?}."
+        );
+        assert!(
+            (bounds.center() - view * marker.position).hypot() < 1e-9,
+            "Markers should be centered on their transformed position."
+        );
+        assert_eq!(
+            MarkerSize::Millimeters(2.0).device_pixels(4.0),
+            8.0,
+            "Physical sizes should be converted with the pixel density."
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Measurement of shapes, for measuring tools and reports.
+//!
+//! These work on anything that implements [`Shape`](peniko::kurbo::Shape), including
+//! [`BezPath`](peniko::kurbo::BezPath) and [`AnyShape`](crate::shape::AnyShape).
+//! Shapes are measured in their own coordinates, so measure the
+//! [`shape`](crate::shape::FatShape::shape) of a [`FatShape`](crate::shape::FatShape)
+//! to get results in the units of its drawing, or transform its path first to measure
+//! it in another space.
+//!
+//! Areas and centroids are exact for lines and Bézier curves, and treat open
+//! subpaths as closed, as they are when filled. Lengths and points along shapes are
+//! accurate to a millionth of the size of the shape.
+
+use peniko::kurbo::{
+    CubicBez, Line, ParamCurve, ParamCurveArclen, ParamCurveDeriv, PathEl, Point, QuadBez, Shape,
+    Vec2,
+};
+
+use crate::text_on_path::MeasuredPath;
+
+/// Get the accuracy for measuring a shape, relative to its size.
+fn accuracy(shape: &impl Shape) -> f64 {
+    let bounds = shape.bounding_box();
+    // Drawings use many different units, so absolute accuracies don't work.
+    ((bounds.width() + bounds.height()) * 1e-6).max(f64::MIN_POSITIVE)
+}
+
+/// Get the length of a shape's outline, as it would be stroked.
+pub fn length(shape: &impl Shape) -> f64 {
+    shape.perimeter(accuracy(shape))
+}
+
+/// Get the area enclosed by a shape.
+///
+/// Subpaths that wind in opposite directions subtract from each other, so holes are
+/// left out when they wind opposite to the outline around them.
+pub fn area(shape: &impl Shape) -> f64 {
+    moments(shape).0.abs()
+}
+
+/// Get the centroid of the area enclosed by a shape.
+///
+/// Shapes that enclose no area, such as lines and polylines, get the centroid of their
+/// outline instead. Returns `None` for empty shapes and single points.
+pub fn centroid(shape: &impl Shape) -> Option<Point> {
+    let (a, mx, my) = moments(shape);
+    let bounds = shape.bounding_box();
+    if a.abs() > bounds.width() * bounds.height() * 1e-12 {
+        return Some(Point::new(mx / a, my / a));
+    }
+
+    // Weight the middle of each segment by its length.
+    let path = shape.to_path(accuracy(shape));
+    let mut total = 0.0;
+    let mut sum = Vec2::ZERO;
+    for seg in path.segments() {
+        let l = seg.arclen(accuracy(shape));
+        total += l;
+        sum += seg.eval(0.5).to_vec2() * l;
+    }
+    (total > 0.0).then(|| (sum / total).to_point())
+}
+
+/// Get the point and unit tangent at `fraction` of the length along a shape's outline.
+///
+/// Returns `None` if `fraction` is not between 0 and 1, or the shape is empty.
+pub fn point_at(shape: &impl Shape, fraction: f64) -> Option<(Point, Vec2)> {
+    let measured = MeasuredPath::new(&shape.to_path(accuracy(shape)));
+    if !(0.0..=1.0).contains(&fraction) || measured.length() <= 0.0 {
+        return None;
+    }
+    measured.at(fraction * measured.length())
+}
+
+/// Get the signed area enclosed by a shape, and its first moments about the axes.
+fn moments(shape: &impl Shape) -> (f64, f64, f64) {
+    let mut totals = (0.0, 0.0, 0.0);
+    let mut add = |c: CubicBez| {
+        let (a, mx, my) = cubic_moments(c);
+        totals.0 += a;
+        totals.1 += mx;
+        totals.2 += my;
+    };
+    let line = |p0: Point, p1: Point| {
+        let l = Line::new(p0, p1);
+        CubicBez::new(p0, l.eval(1.0 / 3.0), l.eval(2.0 / 3.0), p1)
+    };
+
+    let (mut start, mut last) = (Point::ZERO, Point::ZERO);
+    for el in shape.path_elements(accuracy(shape)) {
+        match el {
+            PathEl::MoveTo(p) => {
+                // Open subpaths are closed, as they are when filled.
+                if last != start {
+                    add(line(last, start));
+                }
+                (start, last) = (p, p);
+            }
+            PathEl::LineTo(p) => {
+                add(line(last, p));
+                last = p;
+            }
+            PathEl::QuadTo(p1, p2) => {
+                add(QuadBez::new(last, p1, p2).raise());
+                last = p2;
+            }
+            PathEl::CurveTo(p1, p2, p3) => {
+                add(CubicBez::new(last, p1, p2, p3));
+                last = p3;
+            }
+            PathEl::ClosePath => {
+                if last != start {
+                    add(line(last, start));
+                }
+                last = start;
+            }
+        }
+    }
+    if last != start {
+        add(line(last, start));
+    }
+    totals
+}
+
+/// Get the contributions of a cubic segment to the signed area and first moments of the
+/// region it bounds, by Green's theorem.
+///
+/// The integrands are polynomials of degree at most 8, so five point Gauss-Legendre
+/// quadrature is exact.
+fn cubic_moments(c: CubicBez) -> (f64, f64, f64) {
+    const GAUSS: [(f64, f64); 5] = [
+        (0.0, 0.568_888_888_888_888_9),
+        (-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+        (0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+        (-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+        (0.906_179_845_938_664, 0.236_926_885_056_189_1),
+    ];
+    let d = c.deriv();
+    let (mut a, mut mx, mut my) = (0.0, 0.0, 0.0);
+    for (x, w) in GAUSS {
+        let t = 0.5 * (x + 1.0);
+        let (p, v) = (c.eval(t), d.eval(t).to_vec2());
+        a += w * (p.x * v.y - p.y * v.x);
+        mx += w * p.x * p.x * v.y;
+        my -= w * p.y * p.y * v.x;
+    }
+    // Half for the quadrature interval, and half from each of the integrals.
+    (a * 0.25, mx * 0.25, my * 0.25)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::PI;
+    use peniko::kurbo::{BezPath, Circle, Rect};
+
+    #[test]
+    fn measure_shapes() {
+        let rect = Rect::new(1.0, 2.0, 5.0, 4.0);
+        assert!(
+            (length(&rect) - 12.0).abs() < 1e-9,
+            "A rectangle's length should be its perimeter."
+        );
+        assert!(
+            (area(&rect) - 8.0).abs() < 1e-9,
+            "A rectangle's area should be width by height."
+        );
+        assert!(
+            (centroid(&rect).unwrap() - Point::new(3.0, 3.0)).hypot() < 1e-9,
+            "A rectangle's centroid should be its center."
+        );
+
+        let circle = Circle::new((10.0, -3.0), 2.0).to_path(1e-9);
+        assert!(
+            (area(&circle) - 4.0 * PI).abs() < 1e-6,
+            "A circle's area should be exact up to its approximation, got {}.",
+            area(&circle)
+        );
+        assert!(
+            (centroid(&circle).unwrap() - Point::new(10.0, -3.0)).hypot() < 1e-9,
+            "A circle's centroid should be its center."
+        );
+
+        let mut polyline = BezPath::new();
+        polyline.move_to((0.0, 0.0));
+        polyline.line_to((4.0, 0.0));
+        polyline.line_to((4.0, 4.0));
+        polyline.line_to((4.0, 0.0));
+        assert!(
+            (centroid(&polyline).unwrap() - Point::new(10.0 / 3.0, 4.0 / 3.0)).hypot() < 1e-9,
+            "Shapes without area should get the centroid of their outline."
+        );
+        assert_eq!(
+            point_at(&polyline, 0.5),
+            Some((Point::new(4.0, 2.0), Vec2::new(0.0, 1.0))),
+            "The middle of the polyline should be halfway up the second segment."
+        );
+        assert_eq!(
+            point_at(&polyline, 1.5),
+            None,
+            "Fractions past the end are not on the shape."
+        );
+    }
+}
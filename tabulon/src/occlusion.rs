@@ -0,0 +1,331 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Coarse occlusion culling of items hidden beneath opaque fills.
+//!
+//! The viewport is divided into square tiles. Items are visited from the top of the
+//! z order down, and each tile is marked as covered once an opaque fill covers all of
+//! it. Items whose bounds only touch covered tiles cannot be seen, so they need not be
+//! encoded at all. This pays off for drawings with large filled areas, such as hatches
+//! and wipeouts, and costs little otherwise.
+//!
+//! Only shapes with an opaque solid fill and no clip occlude other items. The pass
+//! assumes items are drawn at full opacity, so it should not be used for layers that
+//! are composited with reduced opacity or a blend mode.
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+use peniko::{
+    Brush, Fill,
+    kurbo::{BezPath, DEFAULT_ACCURACY, Line, ParamCurve, PathEl, Point, Rect, Shape, flatten},
+};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+use crate::{
+    ClipHandle, GraphicsBag, GraphicsItem, ItemHandle,
+    bounds::TextMeasurer,
+    shape::{FatPaint, FatShape},
+};
+
+/// Get the items that are not hidden beneath opaque fills, in z order.
+///
+/// `items` are in z order and `viewport` is in device coordinates, after each item's
+/// transform is applied. Items that are entirely outside `viewport` are left out too.
+/// Smaller `tile_size` finds more occluded items, at the cost of more work per fill.
+///
+/// See the [module documentation](self) for which items occlude others.
+#[tracing::instrument(skip_all)]
+pub fn unoccluded_items(
+    graphics: &GraphicsBag,
+    items: impl IntoIterator<Item = ItemHandle>,
+    viewport: Rect,
+    tile_size: f64,
+    measurer: &mut dyn TextMeasurer,
+) -> Vec<ItemHandle> {
+    let items: Vec<ItemHandle> = items.into_iter().collect();
+    let mut tiles = Tiles::new(viewport, tile_size);
+    let mut visible = vec![];
+    for &ih in items.iter().rev() {
+        let Some(bounds) = graphics.item_bounds_with(ih, measurer) else {
+            continue;
+        };
+        if tiles.is_covered(bounds) {
+            continue;
+        }
+        visible.push(ih);
+        if let Some(GraphicsItem::FatShape(s)) = graphics.get(ih) {
+            if is_occluder(graphics, s) {
+                let path = s.shape.path(DEFAULT_ACCURACY);
+                let fill_rule = graphics.get_paint(s.paint).fill_rule;
+                tiles.cover(
+                    &(graphics.get_transform(s.transform) * path.as_ref()),
+                    fill_rule,
+                );
+            }
+        }
+    }
+    visible.reverse();
+    visible
+}
+
+/// Check whether a shape hides everything inside its fill.
+fn is_occluder(graphics: &GraphicsBag, s: &FatShape) -> bool {
+    if s.clip != ClipHandle::default() {
+        return false;
+    }
+    matches!(
+        graphics.get_paint(s.paint),
+        FatPaint {
+            fill_paint: Some(Brush::Solid(c)),
+            ..
+        } if c.components[3] >= 1.0
+    )
+}
+
+/// Coverage of the tiles of a viewport.
+struct Tiles {
+    viewport: Rect,
+    tile_size: f64,
+    columns: usize,
+    rows: usize,
+    covered: Vec<bool>,
+}
+
+impl Tiles {
+    fn new(viewport: Rect, tile_size: f64) -> Self {
+        let count = |extent: f64| {
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                reason = "Tile counts are small and not negative."
+            )]
+            let n = (extent / tile_size).ceil().max(0.0) as usize;
+            n
+        };
+        let columns = count(viewport.width());
+        let rows = count(viewport.height());
+        Self {
+            viewport,
+            tile_size,
+            columns,
+            rows,
+            covered: vec![false; columns * rows],
+        }
+    }
+
+    /// Get the range of tiles along an axis touched by the span from `a` to `b`.
+    fn touched(&self, a: f64, b: f64, origin: f64, n: usize) -> Range<usize> {
+        let start = ((a - origin) / self.tile_size).floor();
+        let end = ((b - origin) / self.tile_size).floor() + 1.0;
+        self.clamp(start, end, n)
+    }
+
+    /// Get the range of tiles along an axis inside the span from `a` to `b`.
+    fn inside(&self, a: f64, b: f64, origin: f64, n: usize) -> Range<usize> {
+        let start = ((a - origin) / self.tile_size).ceil();
+        let end = ((b - origin) / self.tile_size).floor();
+        self.clamp(start, end, n)
+    }
+
+    fn clamp(&self, start: f64, end: f64, n: usize) -> Range<usize> {
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "Values are clamped to the range of tiles."
+        )]
+        let clamp = |x: f64| x.clamp(0.0, n as f64) as usize;
+        let (start, end) = (clamp(start), clamp(end));
+        start..end.max(start)
+    }
+
+    fn touched_tiles(&self, r: Rect) -> (Range<usize>, Range<usize>) {
+        (
+            self.touched(r.x0, r.x1, self.viewport.x0, self.columns),
+            self.touched(r.y0, r.y1, self.viewport.y0, self.rows),
+        )
+    }
+
+    /// Check whether every tile touched by `bounds` is covered.
+    ///
+    /// This is also true when `bounds` is outside the viewport.
+    fn is_covered(&self, bounds: Rect) -> bool {
+        let (columns, rows) = self.touched_tiles(bounds);
+        rows.into_iter()
+            .all(|y| columns.clone().all(|x| self.covered[y * self.columns + x]))
+    }
+
+    /// Mark the tiles entirely inside a path, filled with `fill_rule`, as covered.
+    ///
+    /// Without a fill rule, tiles are only covered if they are filled with either rule.
+    fn cover(&mut self, path: &BezPath, fill_rule: Option<Fill>) {
+        let bounds = path.bounding_box();
+        let columns = self.inside(bounds.x0, bounds.x1, self.viewport.x0, self.columns);
+        let rows = self.inside(bounds.y0, bounds.y1, self.viewport.y0, self.rows);
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+
+        // Tiles crossed by an edge of the path are only partially covered.
+        // Edges are flattened and split into pieces no longer than a tile, so that
+        // the bounds of each piece only touch the tiles near it.
+        let width = columns.len();
+        let mut crossed = vec![false; width * rows.len()];
+        let tolerance = self.tile_size * 0.1;
+        let (mut first, mut start) = (Point::ZERO, Point::ZERO);
+        flatten(path, tolerance, |el| {
+            let end = match el {
+                PathEl::MoveTo(p) => {
+                    (first, start) = (p, p);
+                    return;
+                }
+                PathEl::LineTo(p) => p,
+                PathEl::ClosePath => first,
+                // Flattening only emits lines.
+                PathEl::QuadTo(..) | PathEl::CurveTo(..) => return,
+            };
+            let line = Line::new(start, end);
+            start = end;
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                reason = "The number of pieces is positive and small for visible edges."
+            )]
+            let pieces = (line.length() / self.tile_size).ceil().max(1.0) as usize;
+            for i in 0..pieces {
+                let piece =
+                    line.subsegment(i as f64 / pieces as f64..(i + 1) as f64 / pieces as f64);
+                let bounds = piece.bounding_box().inflate(tolerance, tolerance);
+                let (x_range, y_range) = self.touched_tiles(bounds);
+                for y in y_range.filter(|y| rows.contains(y)) {
+                    for x in x_range.clone().filter(|x| columns.contains(x)) {
+                        crossed[(y - rows.start) * width + x - columns.start] = true;
+                    }
+                }
+            }
+        });
+
+        // The winding number is the same everywhere in tiles that no edge crosses.
+        for y in rows.clone() {
+            for x in columns.clone() {
+                let i = y * self.columns + x;
+                if self.covered[i] || crossed[(y - rows.start) * width + x - columns.start] {
+                    continue;
+                }
+                let center = Point::new(
+                    self.viewport.x0 + (x as f64 + 0.5) * self.tile_size,
+                    self.viewport.y0 + (y as f64 + 0.5) * self.tile_size,
+                );
+                let winding = path.winding(center);
+                self.covered[i] = match fill_rule {
+                    Some(Fill::NonZero) => winding != 0,
+                    Some(Fill::EvenOdd) | None => winding % 2 != 0,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bounds::EstimatedText, render_layer::RenderLayer, shape::FatShape};
+    use alloc::sync::Arc;
+    use peniko::{Color, kurbo::Circle};
+
+    #[test]
+    fn fills_hide_items_beneath() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let opaque = bag.register_paint(FatPaint {
+            fill_paint: Some(Color::WHITE.into()),
+            ..Default::default()
+        });
+        let translucent = bag.register_paint(FatPaint {
+            fill_paint: Some(Color::WHITE.with_alpha(0.5).into()),
+            ..Default::default()
+        });
+        let mut push = |shape: &dyn Fn() -> BezPath, paint| {
+            layer.push_with_bag(
+                &mut bag,
+                FatShape {
+                    paint,
+                    shape: Arc::new(shape().into()),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let hidden = push(&|| Rect::new(40.0, 40.0, 60.0, 60.0).to_path(0.1), opaque);
+        let peeking = push(&|| Rect::new(40.0, 40.0, 90.0, 60.0).to_path(0.1), opaque);
+        let under_glass = push(&|| Rect::new(10.0, 10.0, 20.0, 20.0).to_path(0.1), opaque);
+        let glass = push(
+            &|| Rect::new(0.0, 0.0, 30.0, 30.0).to_path(0.1),
+            translucent,
+        );
+        let wipeout = push(&|| Circle::new((50.0, 50.0), 35.0).to_path(0.1), opaque);
+
+        let visible = unoccluded_items(
+            &bag,
+            layer.indices.iter().copied(),
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+            10.0,
+            &mut EstimatedText,
+        );
+        assert!(
+            !visible.contains(&hidden),
+            "Items entirely beneath an opaque fill should be skipped."
+        );
+        assert_eq!(
+            visible,
+            [peeking, under_glass, glass, wipeout],
+            "Items that are partly uncovered or beneath translucent fills should be kept in z order."
+        );
+    }
+
+    #[test]
+    fn even_odd_holes_show_items() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let even_odd = bag.register_paint(FatPaint {
+            fill_paint: Some(Color::WHITE.into()),
+            fill_rule: Some(Fill::EvenOdd),
+            ..Default::default()
+        });
+        let in_hole = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                paint: even_odd,
+                shape: Arc::new(Rect::new(40.0, 40.0, 60.0, 60.0).to_path(0.1).into()),
+                ..Default::default()
+            },
+        );
+        // Two squares winding the same way, so the inner one is a hole with even-odd.
+        let mut ring = Rect::new(0.0, 0.0, 100.0, 100.0).to_path(0.1);
+        ring.extend(Rect::new(30.0, 30.0, 70.0, 70.0).to_path(0.1));
+        let ring = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                paint: even_odd,
+                shape: Arc::new(ring.into()),
+                ..Default::default()
+            },
+        );
+
+        let visible = unoccluded_items(
+            &bag,
+            layer.indices.iter().copied(),
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+            10.0,
+            &mut EstimatedText,
+        );
+        assert_eq!(
+            visible,
+            [in_hole, ring],
+            "Items in holes of even-odd fills should be kept."
+        );
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Layout of text items into runs, and conversion of glyphs to outlines.
+//!
+//! Backends that draw paths rather than glyphs, or write text as outlines, share this,
+//! so that text looks the same in each of them. Text is laid out with Parley, and each
+//! glyph is converted to its outline with Skrifa.
+
+extern crate alloc;
+use alloc::{
+    borrow::{Cow, ToOwned},
+    format,
+    vec::Vec,
+};
+use core::fmt;
+use core::ops::Range;
+
+use parley::{FontContext, LayoutContext, PositionedLayoutItem};
+use peniko::{
+    Color, Font,
+    kurbo::{Affine, BezPath, Point, Size},
+};
+use skrifa::{
+    FontRef, GlyphId, MetadataProvider,
+    instance::{LocationRef, NormalizedCoord, Size as FontSize},
+    outline::{DrawSettings, OutlineGlyphCollection, OutlinePen},
+};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+#[cfg(doc)]
+use crate::text_on_path::FatTextOnPath;
+use crate::{
+    text::{FatText, TextDirection},
+    text_on_path::MeasuredPath,
+};
+
+/// Pen that collects a glyph outline into a [`BezPath`].
+struct PathPen(BezPath);
+
+impl OutlinePen for PathPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(Point::new(x.into(), y.into()));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(Point::new(x.into(), y.into()));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0.quad_to(
+            Point::new(cx0.into(), cy0.into()),
+            Point::new(x.into(), y.into()),
+        );
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.curve_to(
+            Point::new(cx0.into(), cy0.into()),
+            Point::new(cx1.into(), cy1.into()),
+            Point::new(x.into(), y.into()),
+        );
+    }
+
+    fn close(&mut self) {
+        self.0.close_path();
+    }
+}
+
+/// Converts the glyphs of a font at a size and variation to outlines.
+pub struct GlyphOutliner<'a> {
+    outlines: OutlineGlyphCollection<'a>,
+    coords: Vec<NormalizedCoord>,
+    size: FontSize,
+}
+
+impl fmt::Debug for GlyphOutliner<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlyphOutliner")
+            .field("coords", &self.coords)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> GlyphOutliner<'a> {
+    /// Make an outliner for `font` at `font_size`, with the normalized variation
+    /// coordinates of a shaped run.
+    ///
+    /// Returns `None` if the font can't be read.
+    pub fn new(font: &'a Font, font_size: f32, normalized_coords: &[i16]) -> Option<Self> {
+        let font_ref = FontRef::from_index(font.data.as_ref(), font.index).ok()?;
+        Some(Self {
+            outlines: font_ref.outline_glyphs(),
+            coords: normalized_coords
+                .iter()
+                .map(|c| NormalizedCoord::from_bits(*c))
+                .collect(),
+            size: FontSize::new(font_size),
+        })
+    }
+
+    /// Get the outline of a glyph, y up as in the font, with its origin on the baseline.
+    ///
+    /// Returns `None` for glyphs without outlines, such as color bitmaps.
+    pub fn outline(&self, glyph_id: u32) -> Option<BezPath> {
+        let outline = self.outlines.get(GlyphId::new(glyph_id))?;
+        let mut pen = PathPen(BezPath::new());
+        outline
+            .draw(
+                DrawSettings::unhinted(self.size, LocationRef::new(&self.coords)),
+                &mut pen,
+            )
+            .ok()?;
+        Some(pen.0)
+    }
+}
+
+/// A glyph of laid out text, as its outline.
+#[derive(Clone, Debug)]
+pub struct OutlinedGlyph {
+    /// Outline of the glyph, y down, with its origin on the baseline.
+    pub outline: BezPath,
+    /// Origin of the glyph in the layout box.
+    pub origin: Point,
+    /// Advance of the glyph.
+    pub advance: f64,
+}
+
+/// A run of text in one font, size, and color, on one line.
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    /// Range of the run in the text of the item.
+    pub range: Range<usize>,
+    /// Start of the run's baseline in the layout box, on its left.
+    pub origin: Point,
+    /// Advance of the run.
+    pub advance: f64,
+    /// Whether the run is right to left.
+    pub rtl: bool,
+    /// Font size of the run.
+    pub font_size: f32,
+    /// Color from the style of the run, if it has one, rather than the paint of the item.
+    pub color: Option<Color>,
+    /// Outlines of the glyphs, if they were asked for.
+    pub glyphs: Vec<OutlinedGlyph>,
+}
+
+/// Text laid out into runs.
+#[derive(Clone, Debug)]
+pub struct LaidOutText {
+    /// Size of the layout box.
+    pub size: Size,
+    /// Runs in layout order.
+    pub runs: Vec<TextRun>,
+}
+
+impl LaidOutText {
+    /// Iterate over the outlined glyphs of all runs, with the colors of their runs.
+    pub fn glyphs(&self) -> impl Iterator<Item = (Option<Color>, &OutlinedGlyph)> {
+        self.runs
+            .iter()
+            .flat_map(|r| r.glyphs.iter().map(move |g| (r.color, g)))
+    }
+
+    /// Get the transform from the layout box of `t`, laid out into this, to the
+    /// coordinate space of its `transform`, with the placement and mirroring of the box.
+    pub fn placement(&self, t: &FatText) -> Affine {
+        t.placement_for_size(self.size) * t.mirror_for_size(self.size)
+    }
+
+    /// Place the outlined glyphs of all runs in the layout box, each with the color of
+    /// its run and its transform.
+    pub fn positioned_glyphs(&self) -> impl Iterator<Item = (Option<Color>, Affine, &BezPath)> {
+        self.glyphs()
+            .map(|(color, g)| (color, Affine::translate(g.origin.to_vec2()), &g.outline))
+    }
+
+    /// Place the outlined glyphs of text laid out on a single line along `path`,
+    /// starting `offset` along it, such as for a [`FatTextOnPath`] and its
+    /// [line text](FatTextOnPath::line_text).
+    ///
+    /// Each glyph is placed with the middle of its baseline on the path, rotated to the
+    /// tangent there, and glyphs past the end of the path are left out. Each comes with
+    /// the color of its run and its transform.
+    pub fn glyphs_on_path<'a>(
+        &'a self,
+        path: &'a MeasuredPath,
+        offset: f64,
+    ) -> impl Iterator<Item = (Option<Color>, Affine, &'a BezPath)> + 'a {
+        self.glyphs().filter_map(move |(color, g)| {
+            let middle = g.origin.x + g.advance * 0.5;
+            let (point, tangent) = path.at(offset + middle)?;
+            let placement = Affine::translate(point.to_vec2())
+                * Affine::rotate(tangent.atan2())
+                * Affine::translate((-g.advance * 0.5, 0.0));
+            Some((color, placement, &g.outline))
+        })
+    }
+}
+
+/// Lay out a text item into runs, with the outlines of their glyphs if `outlines` is
+/// `true`.
+///
+/// Columns are not broken, so text with columns is laid out as a single column of the
+/// column width. Glyphs without outlines, such as color bitmaps, are skipped.
+pub fn layout_text(
+    font_cx: &mut FontContext,
+    layout_cx: &mut LayoutContext<Option<Color>>,
+    t: &FatText,
+    outlines: bool,
+) -> LaidOutText {
+    // Parley takes the base direction from the first strong directional character,
+    // so an explicit direction is applied by prepending a zero width mark.
+    let text: Cow<'_, str> = match t.direction {
+        TextDirection::Auto => Cow::Borrowed(&t.text),
+        TextDirection::LeftToRight => Cow::Owned(format!("\u{200E}{}", t.text)),
+        TextDirection::RightToLeft => Cow::Owned(format!("\u{200F}{}", t.text)),
+    };
+    // Span ranges are offset by the length of the mark, if there is one.
+    let offset = text.len() - t.text.len();
+    let mut builder = layout_cx.ranged_builder(font_cx, &text, 1.0, false);
+    for prop in t.style.inner().values() {
+        builder.push_default(prop.to_owned());
+    }
+    for span in &t.spans {
+        if t.text.get(span.range.clone()).is_none() {
+            continue;
+        }
+        let range = span.range.start + offset..span.range.end + offset;
+        for prop in span.style.inner().values() {
+            builder.push(prop.to_owned(), range.clone());
+        }
+    }
+    let mut layout = builder.build(&text);
+    let max_inline_size = t.columns.map_or(t.max_inline_size, |c| Some(c.width));
+    layout.break_all_lines(max_inline_size);
+    layout.align(max_inline_size, t.alignment, Default::default());
+
+    let mut runs = Vec::new();
+    for line in layout.lines() {
+        for item in line.items() {
+            let PositionedLayoutItem::GlyphRun(glyph_run) = item else {
+                continue;
+            };
+            let run = glyph_run.run();
+            let range = run.text_range();
+            let mut text_run = TextRun {
+                range: range.start.max(offset) - offset..range.end.max(offset) - offset,
+                origin: Point::new(
+                    f64::from(glyph_run.offset()),
+                    f64::from(glyph_run.baseline()),
+                ),
+                advance: f64::from(glyph_run.advance()),
+                rtl: run.is_rtl(),
+                font_size: run.font_size(),
+                color: glyph_run.style().brush,
+                glyphs: Vec::new(),
+            };
+            if outlines {
+                outline_glyphs(&glyph_run, &mut text_run.glyphs);
+            }
+            runs.push(text_run);
+        }
+    }
+
+    LaidOutText {
+        size: Size {
+            width: f64::from(max_inline_size.unwrap_or(layout.width())),
+            height: f64::from(layout.height()),
+        },
+        runs,
+    }
+}
+
+/// Add the outlines of the glyphs of a run to `glyphs`.
+fn outline_glyphs(
+    glyph_run: &parley::GlyphRun<'_, Option<Color>>,
+    glyphs: &mut Vec<OutlinedGlyph>,
+) {
+    let run = glyph_run.run();
+    let Some(outliner) = GlyphOutliner::new(run.font(), run.font_size(), run.normalized_coords())
+    else {
+        return;
+    };
+    // Font outlines are y up, and are flipped onto the y down baseline.
+    let glyph_transform = match run.synthesis().skew() {
+        Some(angle) => Affine::FLIP_Y * Affine::skew(f64::from(angle).to_radians().tan(), 0.0),
+        None => Affine::FLIP_Y,
+    };
+
+    let mut x = glyph_run.offset();
+    let y = glyph_run.baseline();
+    for g in glyph_run.glyphs() {
+        let origin = Point::new(f64::from(x + g.x), f64::from(y - g.y));
+        x += g.advance;
+        let Some(outline) = outliner.outline(g.id.into()) else {
+            continue;
+        };
+        glyphs.push(OutlinedGlyph {
+            outline: glyph_transform * outline,
+            origin,
+            advance: f64::from(g.advance),
+        });
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Hit testing of graphics items.
+//!
+//! Points and tolerances are in view units, the coordinate space items are
+//! defined in before any view transform is applied. Use [`device_pixel_tolerance`]
+//! to express a tolerance in device pixels.
+
+use peniko::kurbo::{Affine, Point};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+#[cfg(doc)]
+use crate::{GraphicsBag, render_layer::RenderLayer};
+use crate::{ItemHandle, spatial_index::SpatialIndex};
+
+/// Find the item under `point` among the items of a [`RenderLayer`] in `index`.
+///
+/// Shapes are hit within `tolerance` of their outline, and the nearest one is picked.
+/// If no shape is hit, text and images are hit within `tolerance` of their bounds,
+/// and markers within `tolerance` of their position, and the topmost one is picked.
+/// Items are hit with their transforms applied, other than the root transform of
+/// the [`GraphicsBag`].
+///
+/// Build the index once with [`SpatialIndex::new`], and keep it for as long as it
+/// [is current](SpatialIndex::is_current), rather than building one for each pick.
+pub fn pick(index: &SpatialIndex, point: Point, tolerance: f64) -> Option<ItemHandle> {
+    index.pick(point, tolerance)
+}
+
+/// Convert a tolerance in device pixels to view units.
+///
+/// `view_transform` maps view units to device pixels. If it does not scale uniformly,
+/// the geometric mean of its scale factors is used.
+pub fn device_pixel_tolerance(device_pixels: f64, view_transform: Affine) -> f64 {
+    let scale = view_transform.determinant().abs().sqrt();
+    if scale > 0.0 {
+        device_pixels / scale
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::Vec2;
+    #[cfg(feature = "text")]
+    use {
+        crate::{
+            DirectIsometry, GraphicsBag, GraphicsItem,
+            render_layer::RenderLayer,
+            shape::FatShape,
+            text::{AttachmentPoint, FatText},
+        },
+        alloc::sync::Arc,
+        parley::StyleSet,
+        peniko::kurbo::Line,
+    };
+
+    extern crate alloc;
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn pick_shapes_then_text() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let line = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                shape: Arc::new(Line::new((0.0, 0.0), (100.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+        let label = layer.push_with_bag(
+            &mut bag,
+            FatText {
+                transform: Default::default(),
+                paint: Default::default(),
+                text: "Label".into(),
+                style: StyleSet::new(10.0),
+                spans: Default::default(),
+                alignment: Default::default(),
+                direction: Default::default(),
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.0, Vec2::new(0.0, 50.0)),
+                attachment_point: AttachmentPoint::TopLeft,
+            },
+        );
+        let Some(GraphicsItem::FatText(t)) = bag.get(label) else {
+            unreachable!();
+        };
+        let label_center = t.estimated_bounds().center();
+        let index = SpatialIndex::new(&bag, &layer);
+
+        assert_eq!(
+            pick(&index, Point::new(50.0, 1.0), 2.0),
+            Some(line),
+            "A shape within tolerance should be picked."
+        );
+        assert_eq!(
+            pick(&index, Point::new(50.0, 3.0), 2.0),
+            None,
+            "A shape out of tolerance should not be picked."
+        );
+        assert_eq!(
+            pick(&index, label_center, 2.0),
+            Some(label),
+            "Text should be picked within its bounds."
+        );
+    }
+
+    #[test]
+    fn pick_after_replacing_a_shape() {
+        use crate::{GraphicsBag, GraphicsItem, render_layer::RenderLayer, shape::FatShape};
+        use alloc::sync::Arc;
+        use peniko::kurbo::Line;
+
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let line = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+        let index = SpatialIndex::new(&bag, &layer);
+        assert_eq!(
+            pick(&index, Point::new(5.0, 0.0), 1.0),
+            Some(line),
+            "The line should be picked where it is."
+        );
+
+        if let Some(GraphicsItem::FatShape(s)) = bag.get_mut(line) {
+            s.shape = Arc::new(Line::new((0.0, 20.0), (10.0, 20.0)).into());
+        }
+        assert!(
+            !index.is_current(&bag),
+            "An index should be stale once a shape may have been replaced."
+        );
+        let index = SpatialIndex::new(&bag, &layer);
+        assert_eq!(
+            (
+                pick(&index, Point::new(5.0, 0.0), 1.0),
+                pick(&index, Point::new(5.0, 20.0), 1.0)
+            ),
+            (None, Some(line)),
+            "The line should be picked where its new shape is."
+        );
+    }
+
+    #[test]
+    fn device_pixels() {
+        let view = Affine::scale(4.0).then_translate(Vec2::new(10.0, 20.0));
+        assert_eq!(
+            device_pixel_tolerance(2.0, view),
+            0.5,
+            "Device pixels should be divided by the view scale."
+        );
+        assert_eq!(
+            device_pixel_tolerance(2.0, Affine::scale(0.0)),
+            0.0,
+            "A degenerate view should have no tolerance."
+        );
+    }
+}
@@ -2,9 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use crate::{
-    graphics_bag::{GraphicsBag, GraphicsItem, ItemHandle},
+    geometry_chunk::FatChunk,
+    graphics_bag::{Epoch, GraphicsBag, GraphicsItem, ItemHandle, ItemKind},
+    image::FatImage,
+    marker::FatMarker,
     shape::FatShape,
-    text::FatText,
+};
+#[cfg(feature = "text")]
+use {
+    crate::{text::FatText, text_on_path::FatTextOnPath},
+    alloc::boxed::Box,
 };
 
 extern crate alloc;
@@ -16,14 +23,41 @@ impl From<FatShape> for GraphicsItem {
     }
 }
 
+#[cfg(feature = "text")]
 impl From<FatText> for GraphicsItem {
     fn from(t: FatText) -> Self {
-        Self::FatText(t)
+        Self::FatText(Box::new(t))
+    }
+}
+
+#[cfg(feature = "text")]
+impl From<FatTextOnPath> for GraphicsItem {
+    fn from(t: FatTextOnPath) -> Self {
+        Self::FatTextOnPath(Box::new(t))
+    }
+}
+
+impl From<FatMarker> for GraphicsItem {
+    fn from(m: FatMarker) -> Self {
+        Self::FatMarker(m)
+    }
+}
+
+impl From<FatImage> for GraphicsItem {
+    fn from(i: FatImage) -> Self {
+        Self::FatImage(i)
+    }
+}
+
+impl From<FatChunk> for GraphicsItem {
+    fn from(c: FatChunk) -> Self {
+        Self::FatChunk(c)
     }
 }
 
 /// Render layer.
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RenderLayer {
     /// Collection of [`GraphicsItem`] indices in z order.
     pub indices: Vec<ItemHandle>,
@@ -47,4 +81,122 @@ impl RenderLayer {
             indices: self.indices.iter().copied().filter(f).collect(),
         }
     }
+
+    /// Iterate over the [`ItemHandle`]s that satisfy a predicate, in z order.
+    ///
+    /// Unlike [`RenderLayer::filter`], this does not allocate a new layer.
+    pub fn iter_filtered<'a>(
+        &'a self,
+        f: impl Fn(&ItemHandle) -> bool + 'a,
+    ) -> impl Iterator<Item = ItemHandle> + 'a {
+        self.indices.iter().copied().filter(move |ih| f(ih))
+    }
+}
+
+/// The items of a [`RenderLayer`] partitioned by [`ItemKind`].
+///
+/// This is built once for a layer and kept, so that passes over one kind of item, such
+/// as measuring or shaping text, go straight to those items, and renderers can handle
+/// each kind in turn without checking the kind of every item. Items of each kind are in
+/// z order, but drawing the kinds in turn only keeps the z order of the layer where
+/// items of different kinds don't overlap.
+#[derive(Debug, Clone, Default)]
+pub struct LayerKinds {
+    /// Items of each kind, by the discriminant of the kind.
+    items: [Vec<ItemHandle>; ItemKind::ALL.len()],
+    /// Epoch of the bag when the partitions were made.
+    epoch: Option<Epoch>,
+}
+
+impl LayerKinds {
+    /// Partition the items of a [`RenderLayer`] by kind.
+    ///
+    /// Items that are not in `graphics` are left out.
+    pub fn new(graphics: &GraphicsBag, render_layer: &RenderLayer) -> Self {
+        let mut items: [Vec<ItemHandle>; ItemKind::ALL.len()] = Default::default();
+        for ih in &render_layer.indices {
+            if let Some(item) = graphics.get(*ih) {
+                items[item.kind() as usize].push(*ih);
+            }
+        }
+        Self {
+            items,
+            epoch: Some(graphics.epoch()),
+        }
+    }
+
+    /// Get the items of a kind, in z order.
+    pub fn get(&self, kind: ItemKind) -> &[ItemHandle] {
+        &self.items[kind as usize]
+    }
+
+    /// Iterate over the text items and then the text on path items.
+    pub fn text(&self) -> impl Iterator<Item = ItemHandle> + '_ {
+        self.get(ItemKind::Text)
+            .iter()
+            .chain(self.get(ItemKind::TextOnPath))
+            .copied()
+    }
+
+    /// Count the items of all kinds.
+    pub fn len(&self) -> usize {
+        self.items.iter().map(Vec::len).sum()
+    }
+
+    /// Check whether there are no items of any kind.
+    pub fn is_empty(&self) -> bool {
+        self.items.iter().all(Vec::is_empty)
+    }
+
+    /// Check whether the partitions were made from the items `graphics` has now.
+    ///
+    /// This is `false` once items have been added to the bag or replaced, or if the
+    /// partitions were made from another bag; see [`GraphicsBag::epoch`]. Changes to
+    /// the [`RenderLayer`] the partitions were made from are not tracked.
+    pub fn is_current(&self, graphics: &GraphicsBag) -> bool {
+        self.epoch == Some(graphics.epoch())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        marker::{MarkerShape, MarkerSize},
+        peniko::kurbo::Point,
+    };
+
+    #[test]
+    fn kinds() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let shapes = [
+            layer.push_with_bag(&mut graphics, FatShape::default()),
+            layer.push_with_bag(&mut graphics, FatShape::default()),
+        ];
+        let marker = layer.push_with_bag(
+            &mut graphics,
+            FatMarker::new(Point::ZERO, MarkerShape::Plus, MarkerSize::Pixels(4.0)),
+        );
+        let shape = layer.push_with_bag(&mut graphics, FatShape::default());
+
+        let kinds = LayerKinds::new(&graphics, &layer);
+        assert_eq!(
+            kinds.get(ItemKind::Shape),
+            [shapes[0], shapes[1], shape],
+            "Items of a kind should be in z order."
+        );
+        assert_eq!(kinds.get(ItemKind::Marker), [marker], "Markers are a kind.");
+        assert!(
+            kinds.get(ItemKind::Image).is_empty() && kinds.text().next().is_none(),
+            "Kinds without items should be empty."
+        );
+        assert_eq!(kinds.len(), 4, "Every item should be in a partition.");
+        assert!(kinds.is_current(&graphics), "Partitions should be current.");
+        graphics.push(FatShape::default());
+        assert!(
+            !kinds.is_current(&graphics),
+            "Adding items should make partitions stale."
+        );
+    }
 }
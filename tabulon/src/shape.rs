@@ -2,17 +2,23 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use peniko::{
-    Brush,
-    kurbo::{BezPath, Rect, Shape, Stroke},
+    Brush, Fill,
+    kurbo::{
+        Affine, Arc, BezPath, Circle, DEFAULT_ACCURACY, Ellipse, Line, PathEl, PathSeg, Point,
+        Rect, RoundedRect, Shape, Stroke, StrokeOpts, dash, stroke,
+    },
 };
 
 extern crate alloc;
-use alloc::sync;
+use alloc::{borrow::Cow, sync};
 
-use crate::{PaintHandle, TransformHandle};
+use crate::{
+    ClipHandle, GraphicsBag, PaintHandle, TransformHandle, compact_path::CompactPath, uniform_scale,
+};
 
 /// Paint style for [`FatShape`].
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FatPaint {
     /// Stroke information
     pub stroke: Stroke,
@@ -20,27 +26,461 @@ pub struct FatPaint {
     pub stroke_paint: Option<Brush>,
     /// `Brush` for fill
     pub fill_paint: Option<Brush>,
+    /// Fill rule for fill
+    ///
+    /// `None` uses the renderer's default, which is normally [`Fill::NonZero`].
+    pub fill_rule: Option<Fill>,
+}
+
+impl FatPaint {
+    /// Get a paint that fills [stroke outlines](FatShape::stroke_outline) the way this
+    /// paint strokes, or `None` if this paint doesn't stroke.
+    pub fn stroke_as_fill(&self) -> Option<Self> {
+        Some(Self {
+            stroke: Stroke::default(),
+            stroke_paint: None,
+            fill_paint: Some(self.stroke_paint.clone()?),
+            fill_rule: Some(Fill::NonZero),
+        })
+    }
+}
+
+/// Expand a stroke of `shape` into an outline that covers the same area when filled.
+///
+/// The width, caps, joins, and dashes of `style` are included. Parts of the outline
+/// overlap where the stroke crosses itself, so it must be filled with [`Fill::NonZero`].
+/// The outline is within `tolerance` of the exact stroke.
+pub fn stroke_outline(shape: &impl Shape, style: &Stroke, tolerance: f64) -> BezPath {
+    stroke(
+        shape.path_elements(tolerance * 0.1),
+        style,
+        &StrokeOpts::default(),
+        tolerance,
+    )
+}
+
+/// Units of the dash patterns of strokes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DashUnits {
+    /// Dashes are in the coordinates of the path, and scale with it, so they keep their
+    /// length in the drawing.
+    #[default]
+    Local,
+    /// Dashes are in device pixels, so linetypes keep their rhythm on screen as the
+    /// view is zoomed, like line weights adapted to the view do.
+    Device,
+}
+
+impl DashUnits {
+    /// Get `stroke` with its dashes in the coordinates of a path drawn with `transform`.
+    ///
+    /// Only strokes with [`Device`](Self::Device) dashes are changed; their dash pattern
+    /// and offset are divided by the [uniform scale](uniform_scale) of `transform`.
+    pub fn apply(self, stroke: &Stroke, transform: Affine) -> Cow<'_, Stroke> {
+        let scale = uniform_scale(transform);
+        if self == Self::Local || stroke.dash_pattern.is_empty() || scale == 0.0 {
+            return Cow::Borrowed(stroke);
+        }
+        Cow::Owned(Stroke {
+            dash_offset: stroke.dash_offset / scale,
+            dash_pattern: stroke.dash_pattern.iter().map(|d| d / scale).collect(),
+            ..stroke.clone()
+        })
+    }
+}
+
+/// Split a path into its dashes, for renderers that can't dash strokes themselves.
+///
+/// Returns the dashes as open subpaths, and `style` without dashes to stroke them
+/// with. Strokes without dashes are returned as they are.
+pub fn pre_dash<'a>(path: &'a BezPath, style: &'a Stroke) -> (Cow<'a, BezPath>, Cow<'a, Stroke>) {
+    // A pattern without length would never end.
+    if style.dash_pattern.iter().sum::<f64>() <= 0.0 {
+        return (Cow::Borrowed(path), Cow::Borrowed(style));
+    }
+    let dashed = BezPath::from_iter(dash(path.iter(), style.dash_offset, &style.dash_pattern));
+    (
+        Cow::Owned(dashed),
+        Cow::Owned(Stroke {
+            dash_pattern: Default::default(),
+            dash_offset: 0.0,
+            ..style.clone()
+        }),
+    )
+}
+
+/// Clip region for [`FatShape`].
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FatClip {
+    /// Affine transform of the clip path, independent of the transform of clipped items.
+    pub transform: TransformHandle,
+    /// Clip path, filled with the nonzero rule.
+    pub path: sync::Arc<BezPath>,
+}
+
+/// Geometry of a [`FatShape`].
+///
+/// Primitives are kept as they are instead of being converted to a [`BezPath`] up
+/// front. This is more compact, and keeps what they are for picking and export.
+/// Backends that need paths convert them when they are drawn, with a tolerance
+/// suited to the scale they are drawn at.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnyShape {
+    /// Line segment.
+    Line(Line),
+    /// Rectangle.
+    Rect(Rect),
+    /// Rectangle with rounded corners.
+    RoundedRect(RoundedRect),
+    /// Circle.
+    Circle(Circle),
+    /// Ellipse.
+    Ellipse(Ellipse),
+    /// Elliptical arc.
+    Arc(Arc),
+    /// Arbitrary path.
+    BezPath(BezPath),
+    /// Arbitrary path stored with single precision, see [`CompactPath`].
+    CompactPath(CompactPath),
+}
+
+impl Default for AnyShape {
+    fn default() -> Self {
+        Self::BezPath(BezPath::new())
+    }
+}
+
+/// Apply an expression to the primitive in each variant of an [`AnyShape`].
+macro_rules! each_shape {
+    ($shape:expr, $s:ident => $e:expr) => {
+        match $shape {
+            AnyShape::Line($s) => $e,
+            AnyShape::Rect($s) => $e,
+            AnyShape::RoundedRect($s) => $e,
+            AnyShape::Circle($s) => $e,
+            AnyShape::Ellipse($s) => $e,
+            AnyShape::Arc($s) => $e,
+            AnyShape::BezPath($s) => $e,
+            AnyShape::CompactPath($s) => $e,
+        }
+    };
+}
+
+macro_rules! impl_from_shape {
+    ($($variant:ident),*) => {
+        $(
+            impl From<$variant> for AnyShape {
+                fn from(s: $variant) -> Self {
+                    Self::$variant(s)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_shape!(
+    Line,
+    Rect,
+    RoundedRect,
+    Circle,
+    Ellipse,
+    Arc,
+    BezPath,
+    CompactPath
+);
+
+impl AnyShape {
+    /// Get the shape as a path, borrowing it if it is already one.
+    pub fn path(&self, tolerance: f64) -> Cow<'_, BezPath> {
+        match self {
+            Self::BezPath(path) => Cow::Borrowed(path),
+            s => Cow::Owned(s.to_path(tolerance)),
+        }
+    }
+
+    /// Get the segments of the shape, with curved primitives converted accurately.
+    pub fn segments(&self) -> impl Iterator<Item = PathSeg> + '_ {
+        self.path_segments(DEFAULT_ACCURACY)
+    }
+
+    /// Count the subpaths that end away from where they start, without being closed.
+    ///
+    /// Fills close these with a straight line, which is rarely what was intended, so
+    /// shapes with open subpaths usually should not be filled.
+    pub fn open_subpaths(&self) -> usize {
+        match self {
+            Self::Line(_) | Self::Arc(_) => 1,
+            Self::Rect(_) | Self::RoundedRect(_) | Self::Circle(_) | Self::Ellipse(_) => 0,
+            Self::BezPath(path) => walk_subpaths(path, None),
+            Self::CompactPath(path) => walk_subpaths(&path.to_path(DEFAULT_ACCURACY), None),
+        }
+    }
+
+    /// Close the subpaths that end away from where they start, returning how many.
+    ///
+    /// Lines and arcs are converted to paths to close them. Compact paths stay compact.
+    pub fn close_subpaths(&mut self) -> usize {
+        if self.open_subpaths() == 0 {
+            return 0;
+        }
+        let mut closed = BezPath::new();
+        let count = walk_subpaths(&self.path(DEFAULT_ACCURACY), Some(&mut closed));
+        *self = match self {
+            Self::CompactPath(_) => Self::CompactPath(CompactPath::new(&closed)),
+            _ => Self::BezPath(closed),
+        };
+        count
+    }
+
+    /// Store paths with single precision, returning whether the shape changed.
+    ///
+    /// Primitives are already compact, so only [`AnyShape::BezPath`] changes.
+    pub fn compact(&mut self) -> bool {
+        let Self::BezPath(path) = self else {
+            return false;
+        };
+        *self = Self::CompactPath(CompactPath::new(path));
+        true
+    }
+}
+
+/// Count the subpaths of `path` that end away from where they start without being
+/// closed, and copy the path to `out` with them closed, if given.
+fn walk_subpaths(path: &BezPath, mut out: Option<&mut BezPath>) -> usize {
+    let mut count = 0;
+    let (mut start, mut last) = (None, None);
+    let mut finish = |out: &mut Option<&mut BezPath>, start: Option<Point>, last| {
+        if start.is_some() && last != start {
+            count += 1;
+            if let Some(out) = out {
+                out.close_path();
+            }
+        }
+    };
+    for el in path.elements() {
+        match *el {
+            PathEl::MoveTo(p) => {
+                finish(&mut out, start, last);
+                (start, last) = (Some(p), Some(p));
+            }
+            PathEl::LineTo(p) | PathEl::QuadTo(_, p) | PathEl::CurveTo(_, _, p) => last = Some(p),
+            PathEl::ClosePath => last = start,
+        }
+        if let Some(out) = &mut out {
+            out.push(*el);
+        }
+    }
+    finish(&mut out, start, last);
+    count
+}
+
+/// Iterator over the [`PathEl`]s of an [`AnyShape`].
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and the iterators of primitives don't all implement Debug."
+)]
+#[allow(
+    clippy::large_enum_variant,
+    reason = "Iterators are short lived, and boxing them would allocate for every shape."
+)]
+pub enum AnyShapeElements<'a> {
+    /// Elements of a line segment.
+    Line(<Line as Shape>::PathElementsIter<'a>),
+    /// Elements of a rectangle.
+    Rect(<Rect as Shape>::PathElementsIter<'a>),
+    /// Elements of a rounded rectangle.
+    RoundedRect(<RoundedRect as Shape>::PathElementsIter<'a>),
+    /// Elements of a circle.
+    Circle(<Circle as Shape>::PathElementsIter<'a>),
+    /// Elements of an ellipse.
+    Ellipse(<Ellipse as Shape>::PathElementsIter<'a>),
+    /// Elements of an elliptical arc.
+    Arc(<Arc as Shape>::PathElementsIter<'a>),
+    /// Elements of a path.
+    BezPath(<BezPath as Shape>::PathElementsIter<'a>),
+    /// Elements of a compact path.
+    CompactPath(<CompactPath as Shape>::PathElementsIter<'a>),
+}
+
+impl Iterator for AnyShapeElements<'_> {
+    type Item = PathEl;
+
+    fn next(&mut self) -> Option<PathEl> {
+        match self {
+            Self::Line(i) => i.next(),
+            Self::Rect(i) => i.next(),
+            Self::RoundedRect(i) => i.next(),
+            Self::Circle(i) => i.next(),
+            Self::Ellipse(i) => i.next(),
+            Self::Arc(i) => i.next(),
+            Self::BezPath(i) => i.next(),
+            Self::CompactPath(i) => i.next(),
+        }
+    }
+}
+
+impl Shape for AnyShape {
+    type PathElementsIter<'iter> = AnyShapeElements<'iter>;
+
+    fn path_elements(&self, tolerance: f64) -> AnyShapeElements<'_> {
+        match self {
+            Self::Line(s) => AnyShapeElements::Line(s.path_elements(tolerance)),
+            Self::Rect(s) => AnyShapeElements::Rect(s.path_elements(tolerance)),
+            Self::RoundedRect(s) => AnyShapeElements::RoundedRect(s.path_elements(tolerance)),
+            Self::Circle(s) => AnyShapeElements::Circle(s.path_elements(tolerance)),
+            Self::Ellipse(s) => AnyShapeElements::Ellipse(s.path_elements(tolerance)),
+            Self::Arc(s) => AnyShapeElements::Arc(s.path_elements(tolerance)),
+            Self::BezPath(s) => AnyShapeElements::BezPath(s.path_elements(tolerance)),
+            Self::CompactPath(s) => AnyShapeElements::CompactPath(s.path_elements(tolerance)),
+        }
+    }
+
+    fn to_path(&self, tolerance: f64) -> BezPath {
+        each_shape!(self, s => s.to_path(tolerance))
+    }
+
+    fn into_path(self, tolerance: f64) -> BezPath {
+        each_shape!(self, s => s.into_path(tolerance))
+    }
+
+    fn area(&self) -> f64 {
+        each_shape!(self, s => s.area())
+    }
+
+    fn perimeter(&self, accuracy: f64) -> f64 {
+        each_shape!(self, s => s.perimeter(accuracy))
+    }
+
+    fn winding(&self, pt: Point) -> i32 {
+        each_shape!(self, s => s.winding(pt))
+    }
+
+    fn bounding_box(&self) -> Rect {
+        each_shape!(self, s => s.bounding_box())
+    }
+
+    fn as_line(&self) -> Option<Line> {
+        each_shape!(self, s => s.as_line())
+    }
+
+    fn as_rect(&self) -> Option<Rect> {
+        each_shape!(self, s => s.as_rect())
+    }
+
+    fn as_rounded_rect(&self) -> Option<RoundedRect> {
+        each_shape!(self, s => s.as_rounded_rect())
+    }
+
+    fn as_circle(&self) -> Option<Circle> {
+        each_shape!(self, s => s.as_circle())
+    }
+
+    fn as_path_slice(&self) -> Option<&[PathEl]> {
+        each_shape!(self, s => s.as_path_slice())
+    }
 }
 
 /// Collection of subshapes with the same transform and paint style.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FatShape {
     /// Affine transform
     pub transform: TransformHandle,
     /// Paint information
     pub paint: PaintHandle,
-    /// Path.
-    pub path: sync::Arc<BezPath>,
+    /// Geometry.
+    pub shape: sync::Arc<AnyShape>,
+    /// Clip region, only the part of the shape inside it is drawn.
+    pub clip: ClipHandle,
 }
 
 impl FatShape {
-    /// Get the bounding box of the path.
+    /// Get the bounding box of the shape, or `None` for an empty path.
     pub fn bounding_box(&self) -> Option<Rect> {
-        let mut s = self.path.segments();
+        let mut s = self.shape.segments();
         let f = s.next()?;
         Some(
             s.map(|x| x.bounding_box())
                 .fold(f.bounding_box(), |a, x| a.union(x)),
         )
     }
+
+    /// Get the outline of this shape's stroke, in its local coordinates.
+    ///
+    /// This is for targets that can't stroke, such as polygon based exports and
+    /// cutting machines, and for testing against the extent of strokes. Fill it with
+    /// [`FatPaint::stroke_as_fill`]. Returns `None` if the shape's paint doesn't stroke.
+    /// See [`stroke_outline`] for details.
+    pub fn stroke_outline(&self, graphics: &GraphicsBag, tolerance: f64) -> Option<BezPath> {
+        let paint = graphics.get_paint(self.paint);
+        paint.stroke_paint.as_ref()?;
+        Some(stroke_outline(
+            self.shape.as_ref(),
+            &paint.stroke,
+            tolerance,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::{
+        Color,
+        kurbo::{Cap, Join},
+    };
+
+    #[test]
+    fn stroke_outlines() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(2.0).with_caps(Cap::Butt).with_join(Join::Miter),
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let line = FatShape {
+            paint,
+            shape: sync::Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+            ..Default::default()
+        };
+        let outline = line.stroke_outline(&graphics, 0.01).unwrap();
+        assert_eq!(
+            outline.bounding_box(),
+            Rect::new(0.0, -1.0, 10.0, 1.0),
+            "A butt capped line should expand to a rectangle of the stroke width."
+        );
+        assert!(
+            (crate::measure::area(&outline) - 20.0).abs() < 1e-9,
+            "The outline should cover the stroked area."
+        );
+        assert!(
+            graphics
+                .get_paint(paint)
+                .stroke_as_fill()
+                .is_some_and(|p| p.fill_paint.is_some() && p.stroke_paint.is_none()),
+            "Stroke outlines should be filled with the stroke brush."
+        );
+
+        let dashed = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(2.0)
+                .with_caps(Cap::Butt)
+                .with_dashes(0.0, [2.0, 3.0]),
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let outline = FatShape {
+            paint: dashed,
+            ..line
+        }
+        .stroke_outline(&graphics, 0.01)
+        .unwrap();
+        assert!(
+            (crate::measure::area(&outline) - 8.0).abs() < 1e-9,
+            "Dashes should leave gaps in the outline, got an area of {}.",
+            crate::measure::area(&outline)
+        );
+    }
 }
@@ -0,0 +1,219 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Simplification of paths for drawing at small scales.
+//!
+//! When a drawing is zoomed out, many segments of its paths are smaller than a device
+//! pixel, but encoding and rasterizing them still costs as much as when they are
+//! visible. [`simplify`](crate::simplify::simplify) removes detail below a tolerance,
+//! and a [`PathLod`](crate::simplify::PathLod) keeps simplified versions of a path at
+//! several tolerances, so that a backend can select the coarsest one that is accurate
+//! enough for the current view scale.
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use peniko::kurbo::{BezPath, PathEl, Point, flatten};
+
+/// Simplify a path, so that it stays within `tolerance` of the original.
+///
+/// Curves are flattened to lines, and points that are within tolerance of the lines
+/// between their neighbors are removed. Subpaths that are smaller than `tolerance`
+/// are kept as a single line, so they still show up when stroked.
+pub fn simplify(path: &BezPath, tolerance: f64) -> BezPath {
+    // Half of the tolerance is used for flattening, and half for removing points.
+    let half = tolerance * 0.5;
+    let mut out = BezPath::new();
+    let mut points: Vec<Point> = Vec::new();
+    let mut start = Point::ZERO;
+    flatten(path, half, |el| match el {
+        PathEl::MoveTo(p) => {
+            push_simplified(&mut out, &points, false, half);
+            points.clear();
+            points.push(p);
+            start = p;
+        }
+        PathEl::LineTo(p) => {
+            if points.is_empty() {
+                points.push(start);
+            }
+            points.push(p);
+        }
+        PathEl::ClosePath => {
+            push_simplified(&mut out, &points, true, half);
+            points.clear();
+        }
+        // Flattening only emits lines.
+        PathEl::QuadTo(..) | PathEl::CurveTo(..) => {}
+    });
+    push_simplified(&mut out, &points, false, half);
+    out
+}
+
+/// Append a simplified polyline through `points` to `out`.
+fn push_simplified(out: &mut BezPath, points: &[Point], closed: bool, tolerance: f64) {
+    if points.len() < 2 {
+        return;
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    // Ramer–Douglas–Peucker, with an explicit stack so long polylines can't overflow.
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((a, b)) = spans.pop() {
+        let (mut farthest, mut distance) = (a, tolerance);
+        for (i, p) in points.iter().enumerate().take(b).skip(a + 1) {
+            let d = segment_distance(*p, points[a], points[b]);
+            if d > distance {
+                (farthest, distance) = (i, d);
+            }
+        }
+        if farthest != a {
+            keep[farthest] = true;
+            spans.push((a, farthest));
+            spans.push((farthest, b));
+        }
+    }
+
+    let mut kept = points
+        .iter()
+        .zip(&keep)
+        .filter(|(_, k)| **k)
+        .map(|(p, _)| *p);
+    out.move_to(kept.next().unwrap());
+    for p in kept {
+        out.line_to(p);
+    }
+    if closed {
+        out.close_path();
+    }
+}
+
+/// Get the distance from `p` to the line segment from `a` to `b`.
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len_sq = ab.hypot2();
+    let t = if len_sq > 0.0 {
+        ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p - a.lerp(b, t)).hypot()
+}
+
+/// Simplified versions of a path at increasing tolerances.
+#[derive(Debug, Clone)]
+pub struct PathLod {
+    /// Levels with their tolerances, from finest to coarsest.
+    levels: Vec<(f64, BezPath)>,
+}
+
+impl PathLod {
+    /// Ratio between the tolerances of successive levels.
+    pub const LEVEL_RATIO: f64 = 4.0;
+
+    /// Simplify `path` at up to `max_levels` tolerances, starting from `finest`.
+    ///
+    /// Each level's tolerance is [`LEVEL_RATIO`](Self::LEVEL_RATIO) times the last.
+    /// Levels stop once simplifying no longer removes elements.
+    pub fn new(path: &BezPath, finest: f64, max_levels: usize) -> Self {
+        let mut levels: Vec<(f64, BezPath)> = Vec::new();
+        let mut tolerance = finest;
+        let mut len = path.elements().len();
+        for _ in 0..max_levels {
+            let simplified = simplify(path, tolerance);
+            if simplified.elements().len() >= len {
+                break;
+            }
+            len = simplified.elements().len();
+            levels.push((tolerance, simplified));
+            tolerance *= Self::LEVEL_RATIO;
+        }
+        Self { levels }
+    }
+
+    /// Get the coarsest level that is within `tolerance` of the original path.
+    ///
+    /// Returns `None` if every level is too coarse, so the original should be used.
+    pub fn select(&self, tolerance: f64) -> Option<&BezPath> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= tolerance)
+            .map(|(_, p)| p)
+    }
+
+    /// Get the number of levels.
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Check whether there are no levels, because the path could not be simplified.
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peniko::kurbo::{Circle, Shape};
+
+    #[test]
+    fn simplify_within_tolerance() {
+        let circle = Circle::new((0.0, 0.0), 100.0);
+        let path = circle.to_path(1e-6);
+        let simplified = simplify(&path, 0.5);
+        assert!(
+            simplified.elements().len() > 8,
+            "A large circle should keep enough points to look round."
+        );
+        for el in simplified.elements() {
+            if let Some(p) = el.end_point() {
+                assert!(
+                    (p.to_vec2().hypot() - 100.0).abs() <= 0.5,
+                    "Simplified points should be within tolerance, got {p:?}."
+                );
+            }
+        }
+
+        let mut zigzag = BezPath::new();
+        zigzag.move_to((0.0, 0.0));
+        for i in 1..1000 {
+            zigzag.line_to((f64::from(i), if i % 2 == 0 { 0.0 } else { 0.1 }));
+        }
+        assert_eq!(
+            simplify(&zigzag, 0.5).elements().len(),
+            2,
+            "Detail below tolerance should collapse to a line."
+        );
+    }
+
+    #[test]
+    fn select_levels() {
+        // A finely flattened circle, like a polyline from a drawing.
+        let mut path = BezPath::new();
+        flatten(
+            Circle::new((0.0, 0.0), 1000.0).path_elements(1e-6),
+            1e-4,
+            |el| {
+                path.push(el);
+            },
+        );
+        let lod = PathLod::new(&path, 0.01, 8);
+        assert!(
+            lod.len() > 2,
+            "A dense polyline should have several levels."
+        );
+        assert!(
+            lod.select(0.001).is_none(),
+            "Tolerances finer than every level should use the original."
+        );
+        let fine = lod.select(0.05).unwrap().elements().len();
+        let coarse = lod.select(10.0).unwrap().elements().len();
+        assert!(
+            coarse < fine,
+            "Coarser tolerances should select simpler levels."
+        );
+    }
+}
@@ -0,0 +1,1326 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Compact binary snapshots of a [`GraphicsBag`].
+//!
+//! This is a purpose-built little-endian format intended for caching translated
+//! drawings and passing them between processes. Shapes and clip paths are stored once
+//! per shared [`Arc`](sync::Arc). Primitive shapes are stored as their parameters, and
+//! paths as a run of verbs followed by a contiguous run of coordinates, so that reading
+//! a snapshot is a single linear pass over the bytes.
+//!
+//! Reading a snapshot copies its data into a new bag, which doesn't borrow the bytes,
+//! so they can be dropped or reused once it is read.
+//!
+//! Not everything in a bag can be written to a snapshot. Writing a bag fails with
+//! [`SnapshotError::Unsupported`] if it contains any of these:
+//!
+//! - [images](crate::image::FatImage), and image brushes,
+//! - [geometry chunks](crate::geometry_chunk::FatChunk),
+//! - [text on paths](crate::text_on_path::FatTextOnPath),
+//! - gradient brushes, as only solid brushes are supported,
+//! - text style properties other than those that describe fonts, sizes, line height,
+//!   spacing, and decorations.
+
+extern crate alloc;
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{self, Arc},
+    vec::Vec,
+};
+
+use core::{fmt, num::NonZeroU32};
+
+use peniko::{
+    Brush, Color, Fill,
+    kurbo::{
+        Affine, Arc as ArcShape, BezPath, Cap, Circle, Ellipse, Join, Line, PathEl, Point, Rect,
+        RoundedRect, RoundedRectRadii, Stroke,
+    },
+};
+
+use crate::{
+    ClipHandle, GraphicsBag, GraphicsItem, PaintHandle, TransformHandle,
+    compact_path::CompactPath,
+    graphics_bag::{BagId, Epoch, ManagedTransform, Revisions},
+    marker::{FatMarker, MarkerShape, MarkerSize},
+    shape::{AnyShape, FatClip, FatPaint, FatShape},
+};
+#[cfg(feature = "text")]
+use {
+    crate::{
+        DirectIsometry,
+        text::{
+            AttachmentPoint, FatText, StyleSpan, TextBackground, TextColumns, TextDirection,
+            font_stack_to_css,
+        },
+    },
+    alloc::{borrow::Cow, boxed::Box, string::String},
+    parley::{
+        Alignment, FontStack, FontStyle, FontWeight, FontWidth, LineHeight, StyleProperty, StyleSet,
+    },
+    peniko::kurbo::Vec2,
+};
+
+/// Magic bytes at the start of every snapshot.
+const MAGIC: [u8; 4] = *b"TBSN";
+
+/// Current snapshot format version.
+///
+/// Snapshots with a different version are rejected when read.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// Errors reading or writing snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The snapshot ended before it was completely read.
+    Truncated,
+    /// The snapshot does not begin with the expected magic bytes.
+    BadMagic,
+    /// The snapshot was written with an unsupported format version.
+    UnsupportedVersion(u16),
+    /// The snapshot contains data that is not valid.
+    InvalidData(&'static str),
+    /// The bag contains something that cannot be represented in a snapshot.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "snapshot is truncated"),
+            Self::BadMagic => write!(f, "not a tabulon snapshot"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {v}"),
+            Self::InvalidData(what) => write!(f, "invalid snapshot data: {what}"),
+            Self::Unsupported(what) => write!(f, "cannot snapshot {what}"),
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+impl GraphicsBag {
+    /// Append a binary snapshot of this bag to `out`.
+    ///
+    /// Returns an error if the bag contains brushes or text styles that the
+    /// snapshot format does not support.
+    pub fn write_snapshot(&self, out: &mut Vec<u8>) -> Result<(), SnapshotError> {
+        let mut w = Writer(out);
+        w.bytes(&MAGIC);
+        w.u16(SNAPSHOT_VERSION);
+        w.u16(0);
+
+        w.len(self.managed_transforms.len())?;
+        for ManagedTransform { parent, local } in &self.managed_transforms {
+            w.len(usize::from(*parent))?;
+            for c in local.as_coeffs() {
+                w.f64(c);
+            }
+        }
+
+        w.len(self.palette.len())?;
+        for FatPaint {
+            stroke,
+            stroke_paint,
+            fill_paint,
+            fill_rule,
+        } in &self.palette
+        {
+            w.stroke(stroke)?;
+            w.brush(stroke_paint.as_ref())?;
+            w.brush(fill_paint.as_ref())?;
+            w.fill_rule(*fill_rule);
+        }
+
+        // Shared shapes and paths are written once, and referred to by index.
+        let mut shape_indices: BTreeMap<*const AnyShape, u32> = BTreeMap::new();
+        let mut shapes: Vec<&AnyShape> = Vec::new();
+        let item_shapes = self.items.iter().filter_map(|item| match item {
+            GraphicsItem::FatShape(FatShape { shape, .. }) => Some(shape),
+            _ => None,
+        });
+        for shape in item_shapes {
+            let n = u32::try_from(shapes.len())
+                .map_err(|_| SnapshotError::Unsupported("more than u32::MAX shapes"))?;
+            shape_indices
+                .entry(sync::Arc::as_ptr(shape))
+                .or_insert_with(|| {
+                    shapes.push(shape);
+                    n
+                });
+        }
+        let mut path_indices: BTreeMap<*const BezPath, u32> = BTreeMap::new();
+        let mut paths: Vec<&BezPath> = Vec::new();
+        let marker_paths = self.items.iter().filter_map(|item| match item {
+            GraphicsItem::FatMarker(FatMarker {
+                shape: MarkerShape::Custom(path),
+                ..
+            }) => Some(path),
+            _ => None,
+        });
+        for path in self.clips.iter().map(|c| &c.path).chain(marker_paths) {
+            let n = u32::try_from(paths.len())
+                .map_err(|_| SnapshotError::Unsupported("more than u32::MAX paths"))?;
+            path_indices
+                .entry(sync::Arc::as_ptr(path))
+                .or_insert_with(|| {
+                    paths.push(path);
+                    n
+                });
+        }
+
+        w.len(shapes.len())?;
+        for shape in shapes {
+            w.shape(shape)?;
+        }
+        w.len(paths.len())?;
+        for path in paths {
+            w.path(path)?;
+        }
+
+        w.len(self.clips.len())?;
+        for FatClip { transform, path } in &self.clips {
+            w.len(usize::from(*transform))?;
+            w.u32(path_indices[&sync::Arc::as_ptr(path)]);
+        }
+
+        w.len(self.items.len())?;
+        for item in &self.items {
+            match item {
+                GraphicsItem::FatShape(FatShape {
+                    transform,
+                    paint,
+                    shape,
+                    clip,
+                }) => {
+                    w.u8(0);
+                    w.len(usize::from(*transform))?;
+                    w.len(usize::from(*paint))?;
+                    w.u32(shape_indices[&sync::Arc::as_ptr(shape)]);
+                    w.len(usize::from(*clip))?;
+                }
+                #[cfg(feature = "text")]
+                GraphicsItem::FatText(t) => {
+                    let FatText {
+                        transform,
+                        paint,
+                        text,
+                        style,
+                        spans,
+                        alignment,
+                        direction,
+                        max_inline_size,
+                        columns,
+                        background,
+                        mirror_x,
+                        mirror_y,
+                        insertion,
+                        attachment_point,
+                    } = &**t;
+                    w.u8(1);
+                    w.len(usize::from(*transform))?;
+                    w.len(usize::from(*paint))?;
+                    w.str(text)?;
+                    w.style(style)?;
+                    w.len(spans.len())?;
+                    for span in spans {
+                        w.len(span.range.start)?;
+                        w.len(span.range.end)?;
+                        w.style(&span.style)?;
+                    }
+                    w.u8(u8::from(*mirror_x) | (u8::from(*mirror_y) << 1));
+                    w.u8(*alignment as u8);
+                    w.u8(*direction as u8);
+                    w.option_f32(*max_inline_size);
+                    match columns {
+                        Some(c) => {
+                            w.u8(1);
+                            w.u32(c.count);
+                            w.f32(c.width);
+                            w.f32(c.gutter);
+                            w.option_f32(c.height);
+                        }
+                        None => w.u8(0),
+                    }
+                    match background {
+                        Some(b) => {
+                            w.u8(1);
+                            w.len(usize::from(b.paint))?;
+                            w.f64(b.margin);
+                        }
+                        None => w.u8(0),
+                    }
+                    w.f64(insertion.angle);
+                    w.f64(insertion.displacement.x);
+                    w.f64(insertion.displacement.y);
+                    w.u8(*attachment_point as u8);
+                }
+                GraphicsItem::FatMarker(FatMarker {
+                    transform,
+                    paint,
+                    position,
+                    shape,
+                    size,
+                }) => {
+                    w.u8(2);
+                    w.len(usize::from(*transform))?;
+                    w.len(usize::from(*paint))?;
+                    w.point(*position);
+                    match shape {
+                        MarkerShape::Plus => w.u8(0),
+                        MarkerShape::Cross => w.u8(1),
+                        MarkerShape::Circle => w.u8(2),
+                        MarkerShape::Square => w.u8(3),
+                        MarkerShape::Custom(path) => {
+                            w.u8(4);
+                            w.u32(path_indices[&sync::Arc::as_ptr(path)]);
+                        }
+                    }
+                    match size {
+                        MarkerSize::Pixels(px) => {
+                            w.u8(0);
+                            w.f64(*px);
+                        }
+                        MarkerSize::Millimeters(mm) => {
+                            w.u8(1);
+                            w.f64(*mm);
+                        }
+                    }
+                }
+                GraphicsItem::FatImage(_) => return Err(SnapshotError::Unsupported("images")),
+                GraphicsItem::FatChunk(_) => {
+                    return Err(SnapshotError::Unsupported("geometry chunks"));
+                }
+                #[cfg(feature = "text")]
+                GraphicsItem::FatTextOnPath(_) => {
+                    return Err(SnapshotError::Unsupported("text on paths"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a bag from a binary snapshot written by [`GraphicsBag::write_snapshot`].
+    pub fn read_snapshot(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut r = Reader(bytes);
+        if r.take(4)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = r.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        r.u16()?;
+        // Bags read from snapshots accept the handles of the bag that was written.
+        let id = BagId::default();
+
+        let transform_count = r.len()?;
+        if transform_count == 0 {
+            return Err(SnapshotError::InvalidData("missing root transform"));
+        }
+        let mut managed_transforms = Vec::with_capacity(transform_count);
+        for i in 0..transform_count {
+            let parent = r.u32()?;
+            if i > 0 && parent as usize >= i {
+                return Err(SnapshotError::InvalidData("transform parent out of order"));
+            }
+            let mut coeffs = [0.0; 6];
+            for c in &mut coeffs {
+                *c = r.f64()?;
+            }
+            managed_transforms.push(ManagedTransform {
+                parent: TransformHandle(NonZeroU32::new(parent), id),
+                local: Affine::new(coeffs),
+            });
+        }
+
+        let paint_count = r.len()?;
+        let mut palette = Vec::with_capacity(paint_count);
+        for _ in 0..paint_count {
+            palette.push(FatPaint {
+                stroke: r.stroke()?,
+                stroke_paint: r.brush()?,
+                fill_paint: r.brush()?,
+                fill_rule: r.fill_rule()?,
+            });
+        }
+
+        let shape_count = r.len()?;
+        let mut shapes = Vec::with_capacity(shape_count);
+        for _ in 0..shape_count {
+            shapes.push(Arc::new(r.shape()?));
+        }
+        let path_count = r.len()?;
+        let mut paths = Vec::with_capacity(path_count);
+        for _ in 0..path_count {
+            paths.push(Arc::new(r.path()?));
+        }
+
+        let transform_handle = |i: u32| {
+            if i as usize >= transform_count {
+                return Err(SnapshotError::InvalidData("transform handle out of range"));
+            }
+            Ok(TransformHandle(NonZeroU32::new(i), id))
+        };
+        let shape = |i: u32| {
+            shapes
+                .get(i as usize)
+                .cloned()
+                .ok_or(SnapshotError::InvalidData("shape index out of range"))
+        };
+        let path = |i: u32| {
+            paths
+                .get(i as usize)
+                .cloned()
+                .ok_or(SnapshotError::InvalidData("path index out of range"))
+        };
+
+        let clip_count = r.len()?;
+        let mut clips = Vec::with_capacity(clip_count);
+        for _ in 0..clip_count {
+            clips.push(FatClip {
+                transform: transform_handle(r.u32()?)?,
+                path: path(r.u32()?)?,
+            });
+        }
+        let clip_handle = |i: u32| {
+            if i as usize > clip_count {
+                return Err(SnapshotError::InvalidData("clip handle out of range"));
+            }
+            Ok(ClipHandle(NonZeroU32::new(i), id))
+        };
+        let paint_handle = |i: u32| {
+            if i as usize >= paint_count {
+                return Err(SnapshotError::InvalidData("paint handle out of range"));
+            }
+            Ok(PaintHandle(i, id))
+        };
+
+        let item_count = r.len()?;
+        let mut items = Vec::with_capacity(item_count);
+        for _ in 0..item_count {
+            items.push(match r.u8()? {
+                0 => {
+                    let transform = transform_handle(r.u32()?)?;
+                    let paint = paint_handle(r.u32()?)?;
+                    let shape = shape(r.u32()?)?;
+                    let clip = clip_handle(r.u32()?)?;
+                    GraphicsItem::FatShape(FatShape {
+                        transform,
+                        paint,
+                        shape,
+                        clip,
+                    })
+                }
+                #[cfg(not(feature = "text"))]
+                1 => {
+                    return Err(SnapshotError::Unsupported(
+                        "text without the `text` feature",
+                    ));
+                }
+                #[cfg(feature = "text")]
+                1 => {
+                    let transform = transform_handle(r.u32()?)?;
+                    let paint = paint_handle(r.u32()?)?;
+                    let text = r.str()?;
+                    let style = r.style()?;
+                    let span_count = r.len()?;
+                    let mut spans = Vec::with_capacity(span_count);
+                    for _ in 0..span_count {
+                        let range = r.u32()? as usize..r.u32()? as usize;
+                        if text.get(range.clone()).is_none() {
+                            return Err(SnapshotError::InvalidData("text span out of range"));
+                        }
+                        spans.push(StyleSpan {
+                            range,
+                            style: r.style()?,
+                        });
+                    }
+                    let mirror = r.u8()?;
+                    if mirror > 0b11 {
+                        return Err(SnapshotError::InvalidData("invalid text mirror flags"));
+                    }
+                    let (mirror_x, mirror_y) = (mirror & 1 != 0, mirror & 2 != 0);
+                    GraphicsItem::FatText(Box::new(FatText {
+                        transform,
+                        paint,
+                        text: text.into(),
+                        style,
+                        spans,
+                        alignment: r.alignment()?,
+                        direction: r.direction()?,
+                        max_inline_size: r.option_f32()?,
+                        columns: if r.bool()? {
+                            Some(TextColumns {
+                                count: r.u32()?,
+                                width: r.f32()?,
+                                gutter: r.f32()?,
+                                height: r.option_f32()?,
+                            })
+                        } else {
+                            None
+                        },
+                        background: if r.bool()? {
+                            Some(TextBackground {
+                                paint: paint_handle(r.u32()?)?,
+                                margin: r.f64()?,
+                            })
+                        } else {
+                            None
+                        },
+                        mirror_x,
+                        mirror_y,
+                        insertion: DirectIsometry::new(r.f64()?, Vec2::new(r.f64()?, r.f64()?)),
+                        attachment_point: r.attachment_point()?,
+                    }))
+                }
+                2 => GraphicsItem::FatMarker(FatMarker {
+                    transform: transform_handle(r.u32()?)?,
+                    paint: paint_handle(r.u32()?)?,
+                    position: r.point()?,
+                    shape: match r.u8()? {
+                        0 => MarkerShape::Plus,
+                        1 => MarkerShape::Cross,
+                        2 => MarkerShape::Circle,
+                        3 => MarkerShape::Square,
+                        4 => MarkerShape::Custom(path(r.u32()?)?),
+                        _ => return Err(SnapshotError::InvalidData("unknown marker shape")),
+                    },
+                    size: match r.u8()? {
+                        0 => MarkerSize::Pixels(r.f64()?),
+                        1 => MarkerSize::Millimeters(r.f64()?),
+                        _ => return Err(SnapshotError::InvalidData("unknown marker size")),
+                    },
+                }),
+                _ => return Err(SnapshotError::InvalidData("unknown item kind")),
+            });
+        }
+
+        if !r.0.is_empty() {
+            return Err(SnapshotError::InvalidData("trailing bytes"));
+        }
+
+        let mut bag = Self {
+            items,
+            final_transforms: alloc::vec![Affine::IDENTITY; transform_count],
+            managed_transforms,
+            palette,
+            clips,
+            id,
+            epoch: Epoch::new(),
+            revisions: Revisions::default(),
+        };
+        bag.finalize_transforms(TransformHandle::default());
+        Ok(bag)
+    }
+}
+
+/// Little-endian writer.
+struct Writer<'a>(&'a mut Vec<u8>);
+
+impl Writer<'_> {
+    fn bytes(&mut self, b: &[u8]) {
+        self.0.extend_from_slice(b);
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn f64(&mut self, v: f64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn len(&mut self, n: usize) -> Result<(), SnapshotError> {
+        self.u32(u32::try_from(n).map_err(|_| SnapshotError::Unsupported("counts over u32::MAX"))?);
+        Ok(())
+    }
+
+    #[cfg(feature = "text")]
+    fn option_f32(&mut self, v: Option<f32>) {
+        match v {
+            Some(v) => {
+                self.u8(1);
+                self.f32(v);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    #[cfg(feature = "text")]
+    fn str(&mut self, s: &str) -> Result<(), SnapshotError> {
+        self.len(s.len())?;
+        self.bytes(s.as_bytes());
+        Ok(())
+    }
+
+    fn color(&mut self, c: Color) {
+        for x in c.components {
+            self.f32(x);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    fn option_color(&mut self, c: Option<Color>) {
+        match c {
+            Some(c) => {
+                self.u8(1);
+                self.color(c);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn brush(&mut self, b: Option<&Brush>) -> Result<(), SnapshotError> {
+        match b {
+            None => self.u8(0),
+            Some(Brush::Solid(c)) => {
+                self.u8(1);
+                self.color(*c);
+            }
+            Some(Brush::Gradient(..)) => {
+                return Err(SnapshotError::Unsupported("gradient brushes"));
+            }
+            Some(Brush::Image(..)) => return Err(SnapshotError::Unsupported("image brushes")),
+        }
+        Ok(())
+    }
+
+    fn stroke(&mut self, s: &Stroke) -> Result<(), SnapshotError> {
+        self.f64(s.width);
+        self.u8(s.join as u8);
+        self.f64(s.miter_limit);
+        self.u8(s.start_cap as u8);
+        self.u8(s.end_cap as u8);
+        self.f64(s.dash_offset);
+        self.len(s.dash_pattern.len())?;
+        for d in &s.dash_pattern {
+            self.f64(*d);
+        }
+        Ok(())
+    }
+
+    fn fill_rule(&mut self, f: Option<Fill>) {
+        self.u8(match f {
+            None => 0,
+            Some(Fill::NonZero) => 1,
+            Some(Fill::EvenOdd) => 2,
+        });
+    }
+
+    fn point(&mut self, p: Point) {
+        self.f64(p.x);
+        self.f64(p.y);
+    }
+
+    fn rect(&mut self, r: Rect) {
+        for x in [r.x0, r.y0, r.x1, r.y1] {
+            self.f64(x);
+        }
+    }
+
+    fn shape(&mut self, s: &AnyShape) -> Result<(), SnapshotError> {
+        match s {
+            AnyShape::BezPath(p) => {
+                self.u8(0);
+                self.path(p)?;
+            }
+            AnyShape::Line(l) => {
+                self.u8(1);
+                self.point(l.p0);
+                self.point(l.p1);
+            }
+            AnyShape::Rect(r) => {
+                self.u8(2);
+                self.rect(*r);
+            }
+            AnyShape::RoundedRect(r) => {
+                self.u8(3);
+                self.rect(r.rect());
+                let radii = r.radii();
+                for x in [
+                    radii.top_left,
+                    radii.top_right,
+                    radii.bottom_right,
+                    radii.bottom_left,
+                ] {
+                    self.f64(x);
+                }
+            }
+            AnyShape::Circle(c) => {
+                self.u8(4);
+                self.point(c.center);
+                self.f64(c.radius);
+            }
+            AnyShape::Ellipse(e) => {
+                self.u8(5);
+                self.point(e.center());
+                self.point(e.radii().to_point());
+                self.f64(e.rotation());
+            }
+            AnyShape::Arc(a) => {
+                self.u8(6);
+                self.point(a.center);
+                self.point(a.radii.to_point());
+                self.f64(a.start_angle);
+                self.f64(a.sweep_angle);
+                self.f64(a.x_rotation);
+            }
+            AnyShape::CompactPath(p) => {
+                self.u8(7);
+                let (origin, verbs, points) = p.parts();
+                self.point(origin);
+                self.len(verbs.len())?;
+                self.bytes(verbs);
+                self.len(points.len())?;
+                for [x, y] in points {
+                    self.f32(*x);
+                    self.f32(*y);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&mut self, p: &BezPath) -> Result<(), SnapshotError> {
+        let elements = p.elements();
+        self.len(elements.len())?;
+        let mut point_count = 0;
+        for el in elements {
+            let (verb, n) = match el {
+                PathEl::MoveTo(..) => (0, 1),
+                PathEl::LineTo(..) => (1, 1),
+                PathEl::QuadTo(..) => (2, 2),
+                PathEl::CurveTo(..) => (3, 3),
+                PathEl::ClosePath => (4, 0),
+            };
+            self.u8(verb);
+            point_count += n;
+        }
+        self.len(point_count)?;
+        for el in elements {
+            let mut point = |p: &Point| {
+                self.f64(p.x);
+                self.f64(p.y);
+            };
+            match el {
+                PathEl::MoveTo(p) | PathEl::LineTo(p) => point(p),
+                PathEl::QuadTo(p1, p2) => {
+                    point(p1);
+                    point(p2);
+                }
+                PathEl::CurveTo(p1, p2, p3) => {
+                    point(p1);
+                    point(p2);
+                    point(p3);
+                }
+                PathEl::ClosePath => {}
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "text")]
+    fn style(&mut self, style: &StyleSet<Option<Color>>) -> Result<(), SnapshotError> {
+        self.len(style.inner().len())?;
+        for prop in style.inner().values() {
+            match prop {
+                StyleProperty::FontStack(s) => {
+                    self.u8(0);
+                    self.str(&font_stack_to_css(s))?;
+                }
+                StyleProperty::FontSize(s) => {
+                    self.u8(1);
+                    self.f32(*s);
+                }
+                StyleProperty::FontWidth(w) => {
+                    self.u8(2);
+                    self.f32(w.ratio());
+                }
+                StyleProperty::FontStyle(s) => {
+                    self.u8(3);
+                    match s {
+                        FontStyle::Normal => self.u8(0),
+                        FontStyle::Italic => self.u8(1),
+                        FontStyle::Oblique(a) => {
+                            self.u8(2);
+                            self.option_f32(*a);
+                        }
+                    }
+                }
+                StyleProperty::FontWeight(w) => {
+                    self.u8(4);
+                    self.f32(w.value());
+                }
+                StyleProperty::Brush(b) => {
+                    self.u8(5);
+                    self.option_color(*b);
+                }
+                StyleProperty::LineHeight(h) => {
+                    self.u8(6);
+                    let (kind, v) = match h {
+                        LineHeight::MetricsRelative(v) => (0, v),
+                        LineHeight::FontSizeRelative(v) => (1, v),
+                        LineHeight::Absolute(v) => (2, v),
+                    };
+                    self.u8(kind);
+                    self.f32(*v);
+                }
+                StyleProperty::WordSpacing(s) => {
+                    self.u8(7);
+                    self.f32(*s);
+                }
+                StyleProperty::LetterSpacing(s) => {
+                    self.u8(8);
+                    self.f32(*s);
+                }
+                StyleProperty::Underline(u) => {
+                    self.u8(9);
+                    self.u8(*u as u8);
+                }
+                StyleProperty::Strikethrough(s) => {
+                    self.u8(10);
+                    self.u8(*s as u8);
+                }
+                _ => return Err(SnapshotError::Unsupported("text style property")),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Little-endian reader.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.0.len() < n {
+            return Err(SnapshotError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    #[cfg(feature = "text")]
+    fn bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::InvalidData("invalid boolean")),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, SnapshotError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, SnapshotError> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    /// Read a count, checking it against the remaining bytes to avoid huge allocations.
+    fn len(&mut self) -> Result<usize, SnapshotError> {
+        let n = self.u32()? as usize;
+        if n > self.0.len() {
+            return Err(SnapshotError::Truncated);
+        }
+        Ok(n)
+    }
+
+    #[cfg(feature = "text")]
+    fn option_f32(&mut self) -> Result<Option<f32>, SnapshotError> {
+        Ok(if self.bool()? {
+            Some(self.f32()?)
+        } else {
+            None
+        })
+    }
+
+    #[cfg(feature = "text")]
+    fn str(&mut self) -> Result<&'a str, SnapshotError> {
+        let n = self.len()?;
+        core::str::from_utf8(self.take(n)?).map_err(|_| SnapshotError::InvalidData("invalid UTF-8"))
+    }
+
+    fn color(&mut self) -> Result<Color, SnapshotError> {
+        Ok(Color::new([
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+        ]))
+    }
+
+    #[cfg(feature = "text")]
+    fn option_color(&mut self) -> Result<Option<Color>, SnapshotError> {
+        Ok(if self.bool()? {
+            Some(self.color()?)
+        } else {
+            None
+        })
+    }
+
+    fn brush(&mut self) -> Result<Option<Brush>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(Brush::Solid(self.color()?))),
+            _ => Err(SnapshotError::InvalidData("unknown brush kind")),
+        }
+    }
+
+    fn join(&mut self) -> Result<Join, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(Join::Bevel),
+            1 => Ok(Join::Miter),
+            2 => Ok(Join::Round),
+            _ => Err(SnapshotError::InvalidData("unknown join")),
+        }
+    }
+
+    fn fill_rule(&mut self) -> Result<Option<Fill>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(Fill::NonZero)),
+            2 => Ok(Some(Fill::EvenOdd)),
+            _ => Err(SnapshotError::InvalidData("unknown fill rule")),
+        }
+    }
+
+    fn cap(&mut self) -> Result<Cap, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(Cap::Butt),
+            1 => Ok(Cap::Square),
+            2 => Ok(Cap::Round),
+            _ => Err(SnapshotError::InvalidData("unknown cap")),
+        }
+    }
+
+    fn stroke(&mut self) -> Result<Stroke, SnapshotError> {
+        let width = self.f64()?;
+        let join = self.join()?;
+        let miter_limit = self.f64()?;
+        let start_cap = self.cap()?;
+        let end_cap = self.cap()?;
+        let dash_offset = self.f64()?;
+        let dash_count = self.len()?;
+        let mut dashes = Vec::with_capacity(dash_count);
+        for _ in 0..dash_count {
+            dashes.push(self.f64()?);
+        }
+        Ok(Stroke::new(width)
+            .with_join(join)
+            .with_miter_limit(miter_limit)
+            .with_start_cap(start_cap)
+            .with_end_cap(end_cap)
+            .with_dashes(dash_offset, dashes))
+    }
+
+    fn point(&mut self) -> Result<Point, SnapshotError> {
+        Ok(Point::new(self.f64()?, self.f64()?))
+    }
+
+    fn rect(&mut self) -> Result<Rect, SnapshotError> {
+        Ok(Rect::new(
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+        ))
+    }
+
+    fn shape(&mut self) -> Result<AnyShape, SnapshotError> {
+        Ok(match self.u8()? {
+            0 => self.path()?.into(),
+            1 => Line::new(self.point()?, self.point()?).into(),
+            2 => self.rect()?.into(),
+            3 => {
+                let rect = self.rect()?;
+                let radii =
+                    RoundedRectRadii::new(self.f64()?, self.f64()?, self.f64()?, self.f64()?);
+                RoundedRect::from_rect(rect, radii).into()
+            }
+            4 => Circle::new(self.point()?, self.f64()?).into(),
+            5 => Ellipse::new(self.point()?, self.point()?.to_vec2(), self.f64()?).into(),
+            6 => ArcShape {
+                center: self.point()?,
+                radii: self.point()?.to_vec2(),
+                start_angle: self.f64()?,
+                sweep_angle: self.f64()?,
+                x_rotation: self.f64()?,
+            }
+            .into(),
+            7 => {
+                let origin = self.point()?;
+                let verb_count = self.len()?;
+                let verbs = self.take(verb_count)?.to_vec();
+                let point_count = self.len()?;
+                let mut reader = Reader(self.take(point_count.saturating_mul(8))?);
+                let mut points = Vec::with_capacity(point_count);
+                for _ in 0..point_count {
+                    points.push([reader.f32()?, reader.f32()?]);
+                }
+                CompactPath::from_parts(origin, verbs, points)
+                    .ok_or(SnapshotError::InvalidData("malformed compact path"))?
+                    .into()
+            }
+            _ => return Err(SnapshotError::InvalidData("unknown shape kind")),
+        })
+    }
+
+    fn path(&mut self) -> Result<BezPath, SnapshotError> {
+        let element_count = self.len()?;
+        let verbs = self.take(element_count)?;
+        let point_count = self.len()?;
+        let mut points = Reader(self.take(point_count.saturating_mul(16))?);
+        let mut elements = Vec::with_capacity(element_count);
+        for verb in verbs {
+            elements.push(match verb {
+                0 => PathEl::MoveTo(points.point()?),
+                1 => PathEl::LineTo(points.point()?),
+                2 => PathEl::QuadTo(points.point()?, points.point()?),
+                3 => PathEl::CurveTo(points.point()?, points.point()?, points.point()?),
+                4 => PathEl::ClosePath,
+                _ => return Err(SnapshotError::InvalidData("unknown path verb")),
+            });
+        }
+        if !points.0.is_empty() {
+            return Err(SnapshotError::InvalidData("path point count mismatch"));
+        }
+        if !matches!(elements.first(), None | Some(PathEl::MoveTo(..))) {
+            return Err(SnapshotError::InvalidData(
+                "path does not begin with MoveTo",
+            ));
+        }
+        Ok(BezPath::from_vec(elements))
+    }
+
+    #[cfg(feature = "text")]
+    fn style(&mut self) -> Result<StyleSet<Option<Color>>, SnapshotError> {
+        let count = self.len()?;
+        let mut style = StyleSet::new(0_f32);
+        style.retain(|_| false);
+        for _ in 0..count {
+            style.insert(match self.u8()? {
+                0 => StyleProperty::FontStack(FontStack::Source(Cow::Owned(String::from(
+                    self.str()?,
+                )))),
+                1 => StyleProperty::FontSize(self.f32()?),
+                2 => StyleProperty::FontWidth(FontWidth::from_ratio(self.f32()?)),
+                3 => StyleProperty::FontStyle(match self.u8()? {
+                    0 => FontStyle::Normal,
+                    1 => FontStyle::Italic,
+                    2 => FontStyle::Oblique(self.option_f32()?),
+                    _ => return Err(SnapshotError::InvalidData("unknown font style")),
+                }),
+                4 => StyleProperty::FontWeight(FontWeight::new(self.f32()?)),
+                5 => StyleProperty::Brush(self.option_color()?),
+                6 => StyleProperty::LineHeight(match self.u8()? {
+                    0 => LineHeight::MetricsRelative(self.f32()?),
+                    1 => LineHeight::FontSizeRelative(self.f32()?),
+                    2 => LineHeight::Absolute(self.f32()?),
+                    _ => return Err(SnapshotError::InvalidData("unknown line height")),
+                }),
+                7 => StyleProperty::WordSpacing(self.f32()?),
+                8 => StyleProperty::LetterSpacing(self.f32()?),
+                9 => StyleProperty::Underline(self.bool()?),
+                10 => StyleProperty::Strikethrough(self.bool()?),
+                _ => return Err(SnapshotError::InvalidData("unknown style property")),
+            });
+        }
+        Ok(style)
+    }
+
+    #[cfg(feature = "text")]
+    fn alignment(&mut self) -> Result<Alignment, SnapshotError> {
+        use Alignment::*;
+        [Start, End, Left, Middle, Right, Justified]
+            .get(self.u8()? as usize)
+            .copied()
+            .ok_or(SnapshotError::InvalidData("unknown alignment"))
+    }
+
+    #[cfg(feature = "text")]
+    fn direction(&mut self) -> Result<TextDirection, SnapshotError> {
+        use TextDirection::*;
+        [Auto, LeftToRight, RightToLeft]
+            .get(self.u8()? as usize)
+            .copied()
+            .ok_or(SnapshotError::InvalidData("unknown text direction"))
+    }
+
+    #[cfg(feature = "text")]
+    fn attachment_point(&mut self) -> Result<AttachmentPoint, SnapshotError> {
+        use AttachmentPoint::*;
+        [
+            TopLeft,
+            TopCenter,
+            TopRight,
+            MiddleLeft,
+            MiddleCenter,
+            MiddleRight,
+            BottomLeft,
+            BottomCenter,
+            BottomRight,
+        ]
+        .get((self.u8()? as usize).wrapping_sub(1))
+        .copied()
+        .ok_or(SnapshotError::InvalidData("unknown attachment point"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "text")]
+    use alloc::vec;
+    use peniko::kurbo::Shape;
+
+    #[test]
+    fn round_trip() {
+        let mut bag = GraphicsBag::default();
+        let t = bag.register_transform(Default::default(), Affine::translate((3.0, 4.0)));
+        let paint = bag.register_paint(FatPaint {
+            stroke: Stroke::new(2.0).with_dashes(0.5, [1.0, 2.0]),
+            stroke_paint: Some(Color::from_rgba8(10, 20, 30, 255).into()),
+            fill_paint: None,
+            fill_rule: Some(Fill::EvenOdd),
+        });
+        let shape = Arc::new(AnyShape::from(Circle::new((1.0, 2.0), 3.0)));
+        let clip = bag.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(Circle::new((0.0, 0.0), 4.0).to_path(0.1)),
+        });
+        let a = bag.push(FatShape {
+            transform: t,
+            paint,
+            shape: shape.clone(),
+            clip,
+        });
+        let b = bag.push(FatShape {
+            transform: t,
+            paint,
+            shape,
+            ..Default::default()
+        });
+        let c = bag.push(FatShape {
+            shape: Arc::new(
+                Ellipse::new((1.0, 2.0), (3.0, 4.0), 0.0)
+                    .to_path(0.1)
+                    .into(),
+            ),
+            ..Default::default()
+        });
+        let compact = CompactPath::new(&Circle::new((5.0, 6.0), 7.0).to_path(0.1));
+        let d = bag.push(FatShape {
+            shape: Arc::new(compact.clone().into()),
+            ..Default::default()
+        });
+        let marker = bag.push(FatMarker {
+            transform: t,
+            paint,
+            ..FatMarker::new(
+                Point::new(7.0, 8.0),
+                MarkerShape::Custom(Arc::new(Rect::new(-0.5, 0.0, 0.5, 0.5).to_path(0.1))),
+                MarkerSize::Millimeters(2.5),
+            )
+        });
+
+        let mut bytes = Vec::new();
+        bag.write_snapshot(&mut bytes).unwrap();
+        let read = GraphicsBag::read_snapshot(&bytes).unwrap();
+
+        assert_eq!(
+            read.get_transform(t),
+            bag.get_transform(t),
+            "Transforms should round trip."
+        );
+        assert_eq!(
+            read.get_paint(paint).stroke,
+            bag.get_paint(paint).stroke,
+            "Strokes should round trip."
+        );
+        assert_eq!(
+            read.get_paint(paint).fill_rule,
+            Some(Fill::EvenOdd),
+            "Fill rules should round trip."
+        );
+        let (
+            Some(GraphicsItem::FatShape(FatShape {
+                shape: sa,
+                clip: ca,
+                ..
+            })),
+            Some(GraphicsItem::FatShape(FatShape {
+                shape: sb,
+                clip: cb,
+                ..
+            })),
+            Some(GraphicsItem::FatShape(FatShape { shape: sc, .. })),
+        ) = (read.get(a), read.get(b), read.get(c))
+        else {
+            panic!("Shapes should round trip.");
+        };
+        assert_eq!(
+            (*ca, *cb),
+            (clip, ClipHandle::default()),
+            "Clip handles should round trip."
+        );
+        assert_eq!(
+            read.get_clip(clip).map(|c| c.path.elements()),
+            bag.get_clip(clip).map(|c| c.path.elements()),
+            "Clip paths should round trip."
+        );
+        assert!(Arc::ptr_eq(sa, sb), "Shared shapes should remain shared.");
+        assert_eq!(
+            **sa,
+            AnyShape::Circle(Circle::new((1.0, 2.0), 3.0)),
+            "Primitive shapes should round trip."
+        );
+        assert_eq!(
+            sc.as_path_slice(),
+            Ellipse::new((1.0, 2.0), (3.0, 4.0), 0.0)
+                .to_path(0.1)
+                .as_path_slice(),
+            "Path elements should round trip."
+        );
+        let Some(GraphicsItem::FatShape(FatShape { shape: sd, .. })) = read.get(d) else {
+            panic!("Compact paths should round trip.");
+        };
+        assert_eq!(
+            **sd,
+            AnyShape::CompactPath(compact),
+            "Compact paths should round trip exactly."
+        );
+        let Some(GraphicsItem::FatMarker(m)) = read.get(marker) else {
+            panic!("Markers should round trip.");
+        };
+        assert_eq!(
+            (m.position, &m.shape, m.size),
+            (
+                Point::new(7.0, 8.0),
+                &MarkerShape::Custom(Arc::new(Rect::new(-0.5, 0.0, 0.5, 0.5).to_path(0.1))),
+                MarkerSize::Millimeters(2.5)
+            ),
+            "Marker position, shape, and size should round trip."
+        );
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_round_trip() {
+        let mut bag = GraphicsBag::default();
+        let t = bag.register_transform(Default::default(), Affine::translate((3.0, 4.0)));
+        let paint = bag.register_paint(FatPaint::default());
+        let text = bag.push(FatText {
+            transform: t,
+            paint,
+            text: "Snap".into(),
+            style: StyleSet::new(4.0),
+            spans: vec![StyleSpan::new(
+                1..3,
+                [StyleProperty::Brush(Some(Color::WHITE))],
+            )],
+            alignment: Alignment::Right,
+            direction: TextDirection::RightToLeft,
+            max_inline_size: None,
+            columns: Some(TextColumns {
+                count: 2,
+                width: 20.0,
+                gutter: 2.0,
+                height: None,
+            }),
+            background: Some(TextBackground { paint, margin: 0.5 }),
+            mirror_x: false,
+            mirror_y: true,
+            insertion: DirectIsometry::new(1.0, Vec2::new(5.0, 6.0)),
+            attachment_point: AttachmentPoint::MiddleCenter,
+        });
+
+        let mut bytes = Vec::new();
+        bag.write_snapshot(&mut bytes).unwrap();
+        let read = GraphicsBag::read_snapshot(&bytes).unwrap();
+
+        let Some(GraphicsItem::FatText(t)) = read.get(text) else {
+            panic!("Text should round trip.");
+        };
+        assert_eq!(t.font_size(), Some(4.0), "Font size should round trip.");
+        assert_eq!(
+            t.spans,
+            [StyleSpan::new(
+                1..3,
+                [StyleProperty::Brush(Some(Color::WHITE))]
+            )],
+            "Style spans should round trip."
+        );
+        assert_eq!(
+            t.columns.map(|c| (c.count, c.height)),
+            Some((2, None)),
+            "Columns should round trip."
+        );
+        assert_eq!(
+            t.background.map(|b| (b.paint, b.margin)),
+            Some((paint, 0.5)),
+            "Backgrounds should round trip."
+        );
+        assert_eq!(
+            (t.mirror_x, t.mirror_y),
+            (false, true),
+            "Mirror flags should round trip."
+        );
+        assert!(
+            matches!(t.attachment_point, AttachmentPoint::MiddleCenter),
+            "Attachment point should round trip."
+        );
+        assert_eq!(
+            t.direction,
+            TextDirection::RightToLeft,
+            "Direction should round trip."
+        );
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(
+            GraphicsBag::read_snapshot(b"nope").err(),
+            Some(SnapshotError::BadMagic),
+            "Unknown data should be rejected."
+        );
+        let mut bytes = Vec::new();
+        GraphicsBag::default().write_snapshot(&mut bytes).unwrap();
+        assert_eq!(
+            GraphicsBag::read_snapshot(&bytes[..bytes.len() - 1]).err(),
+            Some(SnapshotError::Truncated),
+            "Truncated data should be rejected."
+        );
+    }
+}
@@ -0,0 +1,799 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! A static bounding box index over the items of a [`RenderLayer`].
+//!
+//! The index is a packed R-tree with entries sorted along a Hilbert curve.
+//! Shapes are indexed per path segment, so that picking can find the nearest
+//! segment, as are the shapes drawn by [geometry chunks](crate::geometry_chunk), which
+//! are found as the chunk item; text is indexed by its [estimated bounds](crate::text::FatText::estimated_bounds),
+//! text on paths by [theirs](crate::text_on_path::FatTextOnPath::estimated_bounds),
+//! images by their [bounding box](crate::image::FatImage::bounding_box), and markers
+//! by their [position](crate::marker::FatMarker::position_bounds).
+//!
+//! Bounds are in the coordinate space of the root transform, with the transform of
+//! each item applied below it. This suits a view transform applied at the root, which
+//! can change without building the index again, whereas changes to other transforms
+//! need a new index.
+//!
+//! Indexed bounds do not include stroke widths, because strokes are often adapted
+//! to the view scale after the index is built. Use [`SpatialIndex::query_items_stroked`]
+//! for culling, so that wide strokes crossing the edge of the view are not missed.
+
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+
+use peniko::kurbo::{DEFAULT_ACCURACY, ParamCurveExtrema, ParamCurveNearest, PathSeg, Point, Rect};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+
+use crate::{
+    GraphicsBag, GraphicsItem, ItemHandle, PaintHandle,
+    graphics_bag::Epoch,
+    item_set::ItemSet,
+    render_layer::RenderLayer,
+    shape::{FatPaint, FatShape},
+};
+
+/// Maximum number of children for each node.
+const NODE_SIZE: usize = 16;
+
+/// An indexed piece of an item.
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    /// A segment of a shape's path, with its index among the segments of the item.
+    Segment(ItemHandle, PathSeg, usize),
+    /// The bounds of an item without segments, with its position in the render layer.
+    Bounds(ItemHandle, usize),
+}
+
+impl Entry {
+    fn item(&self) -> ItemHandle {
+        match self {
+            Self::Segment(ih, ..) | Self::Bounds(ih, _) => *ih,
+        }
+    }
+}
+
+/// The segment nearest to a point, found by [`SpatialIndex::nearest_segment`].
+#[derive(Debug, Clone, Copy)]
+pub struct NearestSegment {
+    /// The item the segment belongs to.
+    pub item: ItemHandle,
+    /// The segment, with the item's transform applied below the root transform.
+    pub segment: PathSeg,
+    /// Index of the segment among the segments of the item, in the order of
+    /// [`Shape::segments`], continuing across the shapes of a geometry chunk.
+    ///
+    /// [`Shape::segments`]: peniko::kurbo::Shape::segments
+    pub index: usize,
+    /// Distance from the point to the segment.
+    pub distance: f64,
+}
+
+/// Everything within tolerance of a point, found by [`SpatialIndex::pick_candidates`].
+///
+/// This shows which items a pick chose between, such as for drawing them while
+/// hovering to make picking understandable.
+#[derive(Debug, Clone, Default)]
+pub struct PickCandidates {
+    /// Segments of shapes within tolerance, nearest first.
+    pub segments: Vec<NearestSegment>,
+    /// Items without segments whose bounds are within tolerance, topmost first.
+    pub bounds: Vec<ItemHandle>,
+}
+
+impl PickCandidates {
+    /// Get the item that [`SpatialIndex::pick`] picks from the candidates.
+    ///
+    /// This is the item of the nearest segment, or if there is none, the topmost item
+    /// hit by its bounds.
+    pub fn picked(&self) -> Option<ItemHandle> {
+        self.segments
+            .first()
+            .map(|n| n.item)
+            .or_else(|| self.bounds.first().copied())
+    }
+
+    /// Iterate over the candidate items once each, in order of preference, starting
+    /// with the [picked](Self::picked) one.
+    pub fn items(&self) -> impl Iterator<Item = ItemHandle> + '_ {
+        let mut seen = ItemSet::new();
+        self.segments
+            .iter()
+            .map(|n| n.item)
+            .chain(self.bounds.iter().copied())
+            .filter(move |item| seen.insert(*item))
+    }
+
+    /// Check whether nothing is within tolerance.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty() && self.bounds.is_empty()
+    }
+}
+
+/// Static bounding box index over the items of a [`RenderLayer`].
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    /// Entries, in the same order as the leaf boxes.
+    entries: Vec<Entry>,
+    /// Leaf boxes followed by the boxes of each level of nodes, ending with the root.
+    boxes: Vec<Rect>,
+    /// For leaves, the index of the entry; for nodes, the index of the first child box.
+    indices: Vec<usize>,
+    /// End of each level in `boxes`, from the leaves up.
+    level_bounds: Vec<usize>,
+    /// Paints that may stroke indexed items, sorted and without duplicates.
+    stroke_paints: Vec<PaintHandle>,
+    /// Half the widest stroke among the paints of geometry chunks, which are not in a bag.
+    chunk_half_width: f64,
+    /// Epoch of the bag when the index was built.
+    epoch: Option<Epoch>,
+}
+
+impl SpatialIndex {
+    /// Build an index of the items in a [`RenderLayer`].
+    #[tracing::instrument(skip_all)]
+    pub fn new(graphics: &GraphicsBag, render_layer: &RenderLayer) -> Self {
+        let mut entries = vec![];
+        let mut leaf_boxes = vec![];
+        let mut stroke_paints = vec![];
+        let mut chunk_half_width = 0.0;
+        for (position, ih) in render_layer.indices.iter().enumerate() {
+            let transform = |handle| graphics.transform_below_root(handle);
+            match graphics.get(*ih) {
+                Some(GraphicsItem::FatShape(FatShape {
+                    shape,
+                    paint,
+                    transform: t,
+                    ..
+                })) => {
+                    stroke_paints.push(*paint);
+                    let transform = transform(*t);
+                    for (i, seg) in shape.segments().enumerate() {
+                        let seg = transform * seg;
+                        entries.push(Entry::Segment(*ih, seg, i));
+                        leaf_boxes.push(seg.bounding_box());
+                    }
+                }
+                #[cfg(feature = "text")]
+                Some(GraphicsItem::FatText(t)) => {
+                    stroke_paints.extend(t.background.map(|b| b.paint));
+                    entries.push(Entry::Bounds(*ih, position));
+                    leaf_boxes
+                        .push(transform(t.transform).transform_rect_bbox(t.estimated_bounds()));
+                }
+                Some(GraphicsItem::FatImage(i)) => {
+                    entries.push(Entry::Bounds(*ih, position));
+                    leaf_boxes.push(transform(i.transform).transform_rect_bbox(i.bounding_box()));
+                }
+                #[cfg(feature = "text")]
+                Some(GraphicsItem::FatTextOnPath(t)) => {
+                    entries.push(Entry::Bounds(*ih, position));
+                    leaf_boxes
+                        .push(transform(t.transform).transform_rect_bbox(t.estimated_bounds()));
+                }
+                Some(GraphicsItem::FatMarker(m)) => {
+                    stroke_paints.push(m.paint);
+                    entries.push(Entry::Bounds(*ih, position));
+                    leaf_boxes
+                        .push(transform(m.transform).transform_rect_bbox(m.position_bounds()));
+                }
+                Some(GraphicsItem::FatChunk(c)) => {
+                    stroke_paints.extend(c.paint);
+                    let transform = transform(c.transform);
+                    let mut i = 0;
+                    for (shape, paint) in c.shapes() {
+                        if c.paint.is_none() && paint.stroke_paint.is_some() {
+                            chunk_half_width = f64::max(chunk_half_width, paint.stroke.width * 0.5);
+                        }
+                        for seg in shape.segments() {
+                            let seg = transform * seg;
+                            entries.push(Entry::Segment(*ih, seg, i));
+                            leaf_boxes.push(seg.bounding_box());
+                            i += 1;
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+        stroke_paints.sort_unstable();
+        stroke_paints.dedup();
+        Self {
+            stroke_paints,
+            chunk_half_width,
+            epoch: Some(graphics.epoch()),
+            ..Self::from_entries(entries, leaf_boxes)
+        }
+    }
+
+    fn from_entries(entries: Vec<Entry>, leaf_boxes: Vec<Rect>) -> Self {
+        let n = entries.len();
+        if n == 0 {
+            return Self::default();
+        }
+
+        // Sort entries along a Hilbert curve through the centers of their boxes.
+        let extent = leaf_boxes
+            .iter()
+            .copied()
+            .reduce(|a, b| a.union(b))
+            .unwrap();
+        let scale_x = if extent.width() > 0.0 {
+            f64::from(u16::MAX) / extent.width()
+        } else {
+            0.0
+        };
+        let scale_y = if extent.height() > 0.0 {
+            f64::from(u16::MAX) / extent.height()
+        } else {
+            0.0
+        };
+        let mut order: Vec<(u32, usize)> = leaf_boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let c = b.center();
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    reason = "The values are scaled into the range of u16."
+                )]
+                let h = hilbert(
+                    ((c.x - extent.x0) * scale_x) as u32,
+                    ((c.y - extent.y0) * scale_y) as u32,
+                );
+                (h, i)
+            })
+            .collect();
+        order.sort_unstable_by_key(|(h, _)| *h);
+
+        let mut level_bounds = vec![n];
+        let mut count = n;
+        let mut total = n;
+        loop {
+            count = count.div_ceil(NODE_SIZE);
+            total += count;
+            level_bounds.push(total);
+            if count == 1 {
+                break;
+            }
+        }
+
+        let mut boxes = Vec::with_capacity(total);
+        let mut indices = Vec::with_capacity(total);
+        for (_, i) in &order {
+            boxes.push(leaf_boxes[*i]);
+            indices.push(*i);
+        }
+
+        let mut pos = 0;
+        for &end in &level_bounds[..level_bounds.len() - 1] {
+            while pos < end {
+                let first = pos;
+                let group_end = (pos + NODE_SIZE).min(end);
+                let b = boxes[pos..group_end]
+                    .iter()
+                    .copied()
+                    .reduce(|a, b| a.union(b))
+                    .unwrap();
+                pos = group_end;
+                boxes.push(b);
+                indices.push(first);
+            }
+        }
+
+        Self {
+            entries,
+            boxes,
+            indices,
+            level_bounds,
+            stroke_paints: vec![],
+            chunk_half_width: 0.0,
+            epoch: None,
+        }
+    }
+
+    /// Number of indexed entries.
+    ///
+    /// Shapes have one entry per path segment, and other items have one entry per item.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the index has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Check whether the index was built from the items `graphics` has now.
+    ///
+    /// This is `false` once items have been added to the bag or their shapes replaced,
+    /// or if the index was built from another bag; see [`GraphicsBag::epoch`]. Changes
+    /// to the [`RenderLayer`] the index was built from, and to transforms below the
+    /// root, are not tracked.
+    pub fn is_current(&self, graphics: &GraphicsBag) -> bool {
+        self.epoch == Some(graphics.epoch())
+    }
+
+    /// Bounds of everything in the index.
+    pub fn bounds(&self) -> Rect {
+        self.boxes.last().copied().unwrap_or_default()
+    }
+
+    /// Visit every entry whose bounds overlap `rect`.
+    fn visit(&self, rect: Rect, mut f: impl FnMut(&Entry)) {
+        let Some(root) = self.boxes.len().checked_sub(1) else {
+            return;
+        };
+        let leaves = self.entries.len();
+        let mut stack = vec![root];
+        while let Some(first) = stack.pop() {
+            // Children of a node are in the same level, so find where that level ends.
+            let level_end = self
+                .level_bounds
+                .iter()
+                .copied()
+                .find(|&end| end > first)
+                .unwrap_or(self.boxes.len());
+            let end = (first + NODE_SIZE).min(level_end);
+            for pos in first..end {
+                if !overlaps(self.boxes[pos], rect) {
+                    continue;
+                }
+                if first < leaves {
+                    f(&self.entries[self.indices[pos]]);
+                } else {
+                    stack.push(self.indices[pos]);
+                }
+            }
+        }
+    }
+
+    /// Get the items with entries that overlap `rect`.
+    ///
+    /// The set can be combined with others, such as the items of visible layers, and
+    /// [`RenderLayer::iter_filtered`] draws the items in it in z order.
+    #[tracing::instrument(skip_all)]
+    pub fn query_items(&self, rect: Rect) -> ItemSet {
+        let mut items = ItemSet::new();
+        self.visit(rect, |e| {
+            items.insert(e.item());
+        });
+        items
+    }
+
+    /// Get the items that may draw inside `rect` with their current strokes.
+    ///
+    /// This is [`query_items`](Self::query_items) with `rect` grown by half the widest
+    /// stroke among the paints of indexed items, as currently set in `graphics`. It may
+    /// find items whose strokes are narrower than the widest, but it never misses a
+    /// stroke that crosses into `rect`.
+    #[tracing::instrument(skip_all)]
+    pub fn query_items_stroked(&self, graphics: &GraphicsBag, rect: Rect) -> ItemSet {
+        let half_width = self
+            .stroke_paints
+            .iter()
+            .map(|p| match graphics.get_paint(*p) {
+                FatPaint {
+                    stroke,
+                    stroke_paint: Some(_),
+                    ..
+                } => stroke.width * 0.5,
+                _ => 0.0,
+            })
+            .fold(self.chunk_half_width, f64::max);
+        self.query_items(rect.inflate(half_width, half_width))
+    }
+
+    /// Find the shape segment nearest to `point`, within `max_distance`.
+    #[tracing::instrument(skip_all)]
+    pub fn nearest_segment(&self, point: Point, max_distance: f64) -> Option<NearestSegment> {
+        let mut nearest: Option<NearestSegment> = None;
+        let mut best_sq = max_distance * max_distance;
+        let rect = Rect::from_center_size(point, (2.0 * max_distance, 2.0 * max_distance));
+        self.visit(rect, |e| {
+            let Entry::Segment(item, segment, index) = *e else {
+                return;
+            };
+            let dsq = segment.nearest(point, DEFAULT_ACCURACY).distance_sq;
+            if dsq <= best_sq {
+                best_sq = dsq;
+                nearest = Some(NearestSegment {
+                    item,
+                    segment,
+                    index,
+                    distance: dsq.sqrt(),
+                });
+            }
+        });
+        nearest
+    }
+
+    /// Find the item under `point`, within `tolerance`.
+    ///
+    /// See [`pick`](crate::pick::pick) for how items are hit.
+    #[tracing::instrument(skip_all)]
+    pub fn pick(&self, point: Point, tolerance: f64) -> Option<ItemHandle> {
+        if let Some(n) = self.nearest_segment(point, tolerance) {
+            return Some(n.item);
+        }
+        // The item latest in the render layer is topmost.
+        let mut topmost = None;
+        let rect = Rect::from_center_size(point, (2.0 * tolerance, 2.0 * tolerance));
+        self.visit(rect, |e| {
+            if let Entry::Bounds(item, position) = *e {
+                topmost = topmost.max(Some((position, item)));
+            }
+        });
+        topmost.map(|(_, item)| item)
+    }
+
+    /// Find every item within `tolerance` of `point`, in the order [`pick`](Self::pick)
+    /// prefers them.
+    ///
+    /// The [picked](PickCandidates::picked) candidate is the item `pick` finds. This
+    /// visits every entry in range rather than only keeping the best, so use `pick`
+    /// when only the picked item is needed.
+    #[tracing::instrument(skip_all)]
+    pub fn pick_candidates(&self, point: Point, tolerance: f64) -> PickCandidates {
+        let max_sq = tolerance * tolerance;
+        let mut segments = Vec::new();
+        let mut bounds = Vec::new();
+        let rect = Rect::from_center_size(point, (2.0 * tolerance, 2.0 * tolerance));
+        self.visit(rect, |e| match *e {
+            Entry::Segment(item, segment, index) => {
+                let dsq = segment.nearest(point, DEFAULT_ACCURACY).distance_sq;
+                if dsq <= max_sq {
+                    segments.push((
+                        dsq,
+                        NearestSegment {
+                            item,
+                            segment,
+                            index,
+                            distance: dsq.sqrt(),
+                        },
+                    ));
+                }
+            }
+            Entry::Bounds(item, position) => bounds.push((position, item)),
+        });
+        // Of equally near segments, `nearest_segment` keeps the last one visited, so
+        // they are put first in reverse visiting order, which a stable sort keeps.
+        segments.reverse();
+        segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // The item latest in the render layer is topmost.
+        bounds.sort_unstable_by(|a, b| b.cmp(a));
+        PickCandidates {
+            segments: segments.into_iter().map(|(_, n)| n).collect(),
+            bounds: bounds.into_iter().map(|(_, item)| item).collect(),
+        }
+    }
+}
+
+/// Check whether two rectangles overlap, including touching edges.
+///
+/// Unlike [`Rect::overlaps`], this works for zero area rectangles such as the
+/// bounds of horizontal and vertical lines.
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.x0 <= b.x1 && a.x1 >= b.x0 && a.y0 <= b.y1 && a.y1 >= b.y0
+}
+
+/// Position of a point along a Hilbert curve over a 16 bit grid.
+///
+/// From <https://github.com/rawrunprotected/hilbert_curves> (public domain).
+fn hilbert(x: u32, y: u32) -> u32 {
+    let mut a = x ^ y;
+    let mut b = 0xFFFF ^ a;
+    let mut c = 0xFFFF ^ (x | y);
+    let mut d = x & (y ^ 0xFFFF);
+
+    let mut aa = a | (b >> 1);
+    let mut bb = (a >> 1) ^ a;
+    let mut cc = ((c >> 1) ^ (b & (d >> 1))) ^ c;
+    let mut dd = ((a & (c >> 1)) ^ (d >> 1)) ^ d;
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 2)) ^ (b & (b >> 2));
+    bb = (a & (b >> 2)) ^ (b & ((a ^ b) >> 2));
+    cc ^= (a & (c >> 2)) ^ (b & (d >> 2));
+    dd ^= (b & (c >> 2)) ^ ((a ^ b) & (d >> 2));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 4)) ^ (b & (b >> 4));
+    bb = (a & (b >> 4)) ^ (b & ((a ^ b) >> 4));
+    cc ^= (a & (c >> 4)) ^ (b & (d >> 4));
+    dd ^= (b & (c >> 4)) ^ ((a ^ b) & (d >> 4));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    cc ^= (a & (c >> 8)) ^ (b & (d >> 8));
+    dd ^= (b & (c >> 8)) ^ ((a ^ b) & (d >> 8));
+
+    a = cc ^ (cc >> 1);
+    b = dd ^ (dd >> 1);
+
+    let mut i0 = x ^ y;
+    let mut i1 = b | (0xFFFF ^ (i0 | a));
+
+    i0 = (i0 | (i0 << 8)) & 0x00FF00FF;
+    i0 = (i0 | (i0 << 4)) & 0x0F0F0F0F;
+    i0 = (i0 | (i0 << 2)) & 0x33333333;
+    i0 = (i0 | (i0 << 1)) & 0x55555555;
+
+    i1 = (i1 | (i1 << 8)) & 0x00FF00FF;
+    i1 = (i1 | (i1 << 4)) & 0x0F0F0F0F;
+    i1 = (i1 | (i1 << 2)) & 0x33333333;
+    i1 = (i1 | (i1 << 1)) & 0x55555555;
+
+    (i1 << 1) | i0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::FatShape;
+    use peniko::{
+        Color,
+        kurbo::{Affine, Line, Stroke},
+    };
+
+    extern crate alloc;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn query_and_pick() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        // A grid of short horizontal lines, enough to need several levels.
+        let mut handles = vec![];
+        for i in 0..40 {
+            for j in 0..40 {
+                let (x, y) = (f64::from(i) * 10.0, f64::from(j) * 10.0);
+                handles.push(layer.push_with_bag(
+                    &mut bag,
+                    FatShape {
+                        shape: Arc::new(Line::new((x, y), (x + 5.0, y)).into()),
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+        let index = SpatialIndex::new(&bag, &layer);
+
+        assert_eq!(index.len(), 1600, "Every segment should be indexed.");
+        assert_eq!(
+            index.bounds(),
+            Rect::new(0.0, 0.0, 395.0, 390.0),
+            "Bounds should cover every segment."
+        );
+
+        let found = index.query_items(Rect::new(98.0, 98.0, 112.0, 112.0));
+        let mut expected = vec![
+            handles[10 * 40 + 10],
+            handles[10 * 40 + 11],
+            handles[11 * 40 + 10],
+            handles[11 * 40 + 11],
+        ];
+        expected.sort();
+        assert_eq!(
+            found.iter().collect::<Vec<_>>(),
+            expected,
+            "Query should find overlapping lines."
+        );
+
+        let hit = index
+            .nearest_segment(Point::new(203.0, 301.0), 2.0)
+            .expect("A line should be within range.");
+        assert_eq!(
+            hit.item,
+            handles[20 * 40 + 30],
+            "The nearest line should be picked."
+        );
+        assert_eq!(
+            hit.index, 0,
+            "A line should be the first segment of its item."
+        );
+        assert!(
+            (hit.distance - 1.0).abs() < 1e-9,
+            "Distance should be to the nearest point on the line."
+        );
+        assert!(
+            index
+                .nearest_segment(Point::new(207.0, 305.0), 2.0)
+                .is_none(),
+            "Nothing should be picked out of range."
+        );
+    }
+
+    #[test]
+    fn stroked_query() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let paint = bag.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: None,
+            fill_rule: None,
+        });
+        let line = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                paint,
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+        let index = SpatialIndex::new(&bag, &layer);
+        let view = Rect::new(0.0, 3.0, 10.0, 10.0);
+
+        assert!(
+            index.query_items_stroked(&bag, view).is_empty(),
+            "Narrow strokes outside the view should be culled."
+        );
+        bag.update_paint(
+            paint,
+            FatPaint {
+                stroke: Stroke::new(8.0),
+                stroke_paint: Some(Color::BLACK.into()),
+                fill_paint: None,
+                fill_rule: None,
+            },
+        );
+        assert_eq!(
+            index
+                .query_items_stroked(&bag, view)
+                .iter()
+                .collect::<Vec<_>>(),
+            [line],
+            "Strokes widened after indexing should reach into the view."
+        );
+    }
+
+    #[test]
+    fn transformed_items() {
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let moved = bag.register_transform(Default::default(), Affine::translate((100.0, 0.0)));
+        let line = layer.push_with_bag(
+            &mut bag,
+            FatShape {
+                transform: moved,
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+        // A view transform at the root isn't applied.
+        bag.update_transform(Default::default(), Affine::scale(4.0));
+        let index = SpatialIndex::new(&bag, &layer);
+
+        assert_eq!(
+            index.bounds(),
+            Rect::new(100.0, 0.0, 110.0, 0.0),
+            "Bounds should have the item transform applied, but not the root."
+        );
+        let hit = index
+            .nearest_segment(Point::new(105.0, 1.0), 2.0)
+            .expect("The moved line should be within range.");
+        assert_eq!(
+            (hit.item, hit.segment),
+            (line, PathSeg::Line(Line::new((100.0, 0.0), (110.0, 0.0)))),
+            "The segment should have the item transform applied."
+        );
+        assert!(
+            index.pick(Point::new(5.0, 0.0), 2.0).is_none(),
+            "Nothing should be picked where the line is before its transform."
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let index = SpatialIndex::new(&GraphicsBag::default(), &RenderLayer::default());
+        assert!(index.is_empty(), "Index should be empty.");
+        assert!(
+            index
+                .query_items(Rect::new(-1.0, -1.0, 1.0, 1.0))
+                .is_empty(),
+            "Empty index should find nothing."
+        );
+    }
+
+    #[test]
+    fn pick_candidates_in_preference_order() {
+        use crate::{
+            marker::{FatMarker, MarkerShape, MarkerSize},
+            shape::AnyShape,
+        };
+        use peniko::kurbo::Rect;
+
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let mut push_shape = |layer: &mut RenderLayer, shape: AnyShape| {
+            layer.push_with_bag(
+                &mut bag,
+                FatShape {
+                    shape: Arc::new(shape),
+                    ..Default::default()
+                },
+            )
+        };
+        let near = push_shape(&mut layer, Line::new((0.0, 0.0), (10.0, 0.0)).into());
+        let nearer_than_box = push_shape(&mut layer, Line::new((0.0, 1.0), (10.0, 1.0)).into());
+        let boxed = push_shape(&mut layer, Rect::new(0.0, -1.0, 10.0, 2.0).into());
+        push_shape(&mut layer, Line::new((0.0, 10.0), (10.0, 10.0)).into());
+        let marker = layer.push_with_bag(
+            &mut bag,
+            FatMarker::new(
+                Point::new(5.0, 0.5),
+                MarkerShape::Plus,
+                MarkerSize::Pixels(4.0),
+            ),
+        );
+        let index = SpatialIndex::new(&bag, &layer);
+
+        let point = Point::new(5.0, 0.4);
+        let candidates = index.pick_candidates(point, 2.0);
+        assert_eq!(
+            candidates.segments.len(),
+            4,
+            "Every segment in range should be a candidate, including both sides of the box."
+        );
+        assert_eq!(
+            candidates.items().collect::<Vec<_>>(),
+            [near, nearer_than_box, boxed, marker],
+            "Items should be listed once each, nearest shapes first, then bounds."
+        );
+        assert_eq!(
+            candidates.picked(),
+            index.pick(point, 2.0),
+            "The picked candidate should be what picking finds."
+        );
+        assert!(
+            index.pick_candidates(Point::new(5.0, 30.0), 2.0).is_empty(),
+            "Nothing should be a candidate out of range."
+        );
+    }
+
+    #[test]
+    fn topmost_in_render_layer_order() {
+        use crate::marker::{FatMarker, MarkerShape, MarkerSize};
+
+        let mut bag = GraphicsBag::default();
+        let marker = || {
+            FatMarker::new(
+                Point::new(0.0, 0.0),
+                MarkerShape::Plus,
+                MarkerSize::Pixels(4.0),
+            )
+        };
+        let first = bag.push(marker());
+        let second = bag.push(marker());
+        // Drawn in the reverse of the order they were added.
+        let layer = RenderLayer {
+            indices: vec![second, first],
+        };
+        let index = SpatialIndex::new(&bag, &layer);
+
+        assert_eq!(
+            index.pick(Point::new(0.5, 0.5), 2.0),
+            Some(first),
+            "The item drawn last should be picked, whatever its handle."
+        );
+        assert_eq!(
+            index.pick_candidates(Point::new(0.5, 0.5), 2.0).bounds,
+            [first, second],
+            "Bounds should be listed topmost first in render layer order."
+        );
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Statistics about the contents and memory use of a [`GraphicsBag`].
+
+extern crate alloc;
+use alloc::collections::BTreeSet;
+
+use core::fmt;
+
+use peniko::kurbo::{Affine, BezPath};
+
+use crate::{
+    GraphicsBag, GraphicsItem,
+    geometry_chunk::GeometryChunk,
+    graphics_bag::ManagedTransform,
+    marker::MarkerShape,
+    shape::{AnyShape, FatClip, FatPaint},
+};
+
+/// Counts of the contents of a [`GraphicsBag`], from [`GraphicsBag::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BagStats {
+    /// Number of [`FatShape`](crate::shape::FatShape) items.
+    pub shapes: usize,
+    /// Number of [`FatText`](crate::text::FatText) items.
+    pub texts: usize,
+    /// Number of [`FatTextOnPath`](crate::text_on_path::FatTextOnPath) items.
+    pub texts_on_paths: usize,
+    /// Number of [`FatImage`](crate::image::FatImage) items.
+    pub images: usize,
+    /// Number of [`FatMarker`](crate::marker::FatMarker) items.
+    pub markers: usize,
+    /// Number of [`FatChunk`](crate::geometry_chunk::FatChunk) items.
+    pub chunks: usize,
+    /// Number of distinct shapes, counting shapes shared between items once.
+    pub unique_shapes: usize,
+    /// Number of path segments drawn for all shape and chunk items, with curved
+    /// primitives converted accurately.
+    pub path_segments: usize,
+    /// Number of registered paints.
+    pub paints: usize,
+    /// Number of registered transforms, including the root transform.
+    pub transforms: usize,
+    /// Number of registered clips.
+    pub clips: usize,
+    /// Approximate number of bytes used by the bag.
+    ///
+    /// This counts the storage of items, including boxed text items, paints, transforms,
+    /// and clips, and the shapes, paths, text, and image data they refer to, counting
+    /// shared data, including geometry chunks, once. Text
+    /// styles, spare capacity, and allocator overhead are not counted.
+    pub bytes: usize,
+}
+
+impl BagStats {
+    /// Get the total number of items.
+    pub fn items(&self) -> usize {
+        self.shapes + self.texts + self.texts_on_paths + self.images + self.markers + self.chunks
+    }
+}
+
+impl fmt::Display for BagStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} items ({} shapes, {} unique; {} texts; {} texts on paths; {} images; {} markers; {} chunks), \
+             {} path segments, {} paints, {} transforms, {} clips, about {} KiB",
+            self.items(),
+            self.shapes,
+            self.unique_shapes,
+            self.texts,
+            self.texts_on_paths,
+            self.images,
+            self.markers,
+            self.chunks,
+            self.path_segments,
+            self.paints,
+            self.transforms,
+            self.clips,
+            self.bytes.div_ceil(1024),
+        )
+    }
+}
+
+/// Get the bytes a path stores its elements in.
+fn path_bytes(path: &BezPath) -> usize {
+    size_of_val(path.elements())
+}
+
+/// Get the bytes a path stores its elements in, if it is not in `seen` by address.
+fn unseen_path_bytes(seen: &mut BTreeSet<usize>, path: &BezPath) -> usize {
+    if seen.insert(core::ptr::from_ref(path).addr()) {
+        path_bytes(path)
+    } else {
+        0
+    }
+}
+
+/// Get the bytes a shape stores outside of [`AnyShape`] itself.
+fn shape_heap_bytes(shape: &AnyShape) -> usize {
+    match shape {
+        AnyShape::BezPath(path) => path_bytes(path),
+        AnyShape::CompactPath(path) => {
+            let (_, verbs, points) = path.parts();
+            verbs.len() + size_of_val(points)
+        }
+        _ => 0,
+    }
+}
+
+impl GraphicsBag {
+    /// Count the contents of the bag, and estimate how much memory it uses.
+    pub fn stats(&self) -> BagStats {
+        let mut stats = BagStats {
+            paints: self.palette.len(),
+            transforms: self.managed_transforms.len(),
+            clips: self.clips.len(),
+            bytes: self.items.len() * size_of::<GraphicsItem>()
+                + self.palette.len() * size_of::<FatPaint>()
+                + self.managed_transforms.len()
+                    * (size_of::<ManagedTransform>() + size_of::<Affine>())
+                + self.clips.len() * size_of::<FatClip>(),
+            ..Default::default()
+        };
+        // Shared data is counted once, by address, or by id for image data.
+        let mut seen: BTreeSet<usize> = BTreeSet::new();
+        let mut seen_blobs: BTreeSet<u64> = BTreeSet::new();
+        let mut bytes = 0;
+        for clip in &self.clips {
+            bytes += unseen_path_bytes(&mut seen, &clip.path);
+        }
+        for item in &self.items {
+            match item {
+                GraphicsItem::FatShape(s) => {
+                    stats.shapes += 1;
+                    stats.path_segments += s.shape.segments().count();
+                    if seen.insert(alloc::sync::Arc::as_ptr(&s.shape).addr()) {
+                        stats.unique_shapes += 1;
+                        bytes += size_of::<AnyShape>() + shape_heap_bytes(&s.shape);
+                    }
+                }
+                #[cfg(feature = "text")]
+                GraphicsItem::FatText(t) => {
+                    stats.texts += 1;
+                    bytes +=
+                        size_of_val(&**t) + t.spans.len() * size_of::<crate::text::StyleSpan>();
+                    if seen.insert(t.text.as_ptr().addr()) {
+                        bytes += t.text.len();
+                    }
+                }
+                #[cfg(feature = "text")]
+                GraphicsItem::FatTextOnPath(t) => {
+                    stats.texts_on_paths += 1;
+                    bytes += size_of_val(&**t) + unseen_path_bytes(&mut seen, &t.path);
+                    if seen.insert(t.text.as_ptr().addr()) {
+                        bytes += t.text.len();
+                    }
+                }
+                GraphicsItem::FatImage(i) => {
+                    stats.images += 1;
+                    if seen_blobs.insert(i.image.data.id()) {
+                        bytes += i.image.data.len();
+                    }
+                }
+                GraphicsItem::FatMarker(m) => {
+                    stats.markers += 1;
+                    if let MarkerShape::Custom(p) = &m.shape {
+                        bytes += unseen_path_bytes(&mut seen, p);
+                    }
+                }
+                GraphicsItem::FatChunk(c) => {
+                    stats.chunks += 1;
+                    stats.path_segments +=
+                        c.shapes().map(|(s, _)| s.segments().count()).sum::<usize>();
+                    bytes += c.selection.as_ref().map_or(0, |s| size_of_val(&s[..]));
+                    if seen.insert(alloc::sync::Arc::as_ptr(&c.chunk).addr()) {
+                        bytes += size_of::<GeometryChunk>()
+                            + size_of_val(c.chunk.paints())
+                            + c.chunk.len() * (size_of::<AnyShape>() + size_of::<u32>())
+                            + c.chunk
+                                .iter()
+                                .map(|(s, _)| shape_heap_bytes(s))
+                                .sum::<usize>();
+                    }
+                }
+            }
+        }
+        stats.bytes += bytes;
+        stats
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+    use crate::{
+        DirectIsometry,
+        marker::FatMarker,
+        shape::FatShape,
+        text::{AttachmentPoint, FatText},
+    };
+    use alloc::sync::Arc;
+    use parley::StyleSet;
+    use peniko::kurbo::{Circle, Point, Rect, Shape, Vec2};
+
+    #[test]
+    fn bag_stats() {
+        let mut bag = GraphicsBag::default();
+        let _paint = bag.register_paint(FatPaint::default());
+        let _transform = bag.register_transform(Default::default(), Affine::IDENTITY);
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((1.0, 0.0));
+        path.line_to((1.0, 1.0));
+        let shared = Arc::new(AnyShape::from(path));
+        for shape in [
+            shared.clone(),
+            shared,
+            Arc::new(Rect::new(0.0, 0.0, 1.0, 1.0).into()),
+        ] {
+            bag.push(FatShape {
+                shape,
+                ..Default::default()
+            });
+        }
+        bag.push(FatText {
+            transform: Default::default(),
+            paint: Default::default(),
+            text: "Stats".into(),
+            style: StyleSet::new(10.0),
+            spans: Default::default(),
+            alignment: Default::default(),
+            direction: Default::default(),
+            max_inline_size: None,
+            columns: None,
+            background: None,
+            mirror_x: false,
+            mirror_y: false,
+            insertion: DirectIsometry::new(0.0, Vec2::ZERO),
+            attachment_point: AttachmentPoint::BottomLeft,
+        });
+        bag.push(FatMarker::new(
+            Point::ZERO,
+            MarkerShape::Circle,
+            Default::default(),
+        ));
+
+        let stats = bag.stats();
+        assert_eq!(
+            (
+                stats.items(),
+                stats.shapes,
+                stats.unique_shapes,
+                stats.texts,
+                stats.markers
+            ),
+            (5, 3, 2, 1, 1),
+            "Items should be counted by kind, and shared shapes once."
+        );
+        assert_eq!(
+            stats.path_segments,
+            2 + 2 + 4,
+            "Segments should be counted for every shape item."
+        );
+        assert_eq!(
+            (stats.paints, stats.transforms, stats.clips),
+            (1, 2, 0),
+            "Registered resources should be counted, including the root transform."
+        );
+
+        let before = stats.bytes;
+        bag.push(FatShape {
+            shape: Arc::new(Circle::new(Point::ZERO, 1.0).to_path(1e-3).into()),
+            ..Default::default()
+        });
+        assert!(
+            bag.stats().bytes > before + size_of::<GraphicsItem>(),
+            "Bytes should include path storage."
+        );
+    }
+}
@@ -3,12 +3,18 @@
 
 extern crate alloc;
 
-use alloc::sync::Arc;
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+use core::ops::Range;
 
-use parley::{Alignment, StyleSet};
+use parley::{Alignment, FontFamily, FontStack, StyleProperty, StyleSet};
 use peniko::{
     Color,
-    kurbo::{Size, Vec2},
+    kurbo::{Affine, Rect, Size, Vec2},
 };
 
 use crate::{DirectIsometry, PaintHandle, TransformHandle};
@@ -16,6 +22,7 @@ use crate::{DirectIsometry, PaintHandle, TransformHandle};
 /// Reference point where text is attached to an insertion point.
 #[repr(i32)]
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttachmentPoint {
     /// Top left corner.
     #[default]
@@ -65,23 +72,142 @@ impl AttachmentPoint {
     }
 }
 
+/// Base direction of a paragraph of text.
+///
+/// This decides which side lines start from, and the order of runs of text
+/// with mixed directions.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextDirection {
+    /// Use the direction of the first strong directional character in the text,
+    /// as in the Unicode Bidirectional Algorithm.
+    #[default]
+    Auto,
+    /// Left to right, as for Latin scripts.
+    LeftToRight,
+    /// Right to left, as for Arabic and Hebrew.
+    RightToLeft,
+}
+
+/// Style overrides for a range of the text in a [`FatText`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StyleSpan {
+    /// Byte range of the text.
+    ///
+    /// Spans whose range is out of bounds or not on character boundaries are ignored.
+    pub range: Range<usize>,
+    /// Properties overriding the item's style in the range, including the brush color.
+    #[cfg_attr(feature = "serde", serde(with = "crate::text_serde::style_set"))]
+    pub style: StyleSet<Option<Color>>,
+}
+
+impl StyleSpan {
+    /// Create a span overriding only the given properties.
+    pub fn new(
+        range: Range<usize>,
+        properties: impl IntoIterator<Item = StyleProperty<'static, Option<Color>>>,
+    ) -> Self {
+        let mut style = StyleSet::new(0.0);
+        style.retain(|_| false);
+        for p in properties {
+            style.insert(p);
+        }
+        Self { range, style }
+    }
+}
+
+impl PartialEq for StyleSpan {
+    fn eq(&self, other: &Self) -> bool {
+        self.range == other.range && self.style.inner() == other.style.inner()
+    }
+}
+
+/// Column layout of a [`FatText`].
+///
+/// Lines flow down each column and continue at the top of the next, with columns
+/// placed side by side in the inline direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextColumns {
+    /// Number of columns.
+    ///
+    /// If zero, columns are added as needed to fit the text within `height`.
+    /// Otherwise, text that does not fit in the columns continues in the last column.
+    pub count: u32,
+    /// Width of each column, which lines are broken to.
+    ///
+    /// This takes the place of the item's `max_inline_size`.
+    pub width: f32,
+    /// Space between adjacent columns.
+    pub gutter: f32,
+    /// Height of each column.
+    ///
+    /// If `None`, the text is balanced across `count` columns.
+    pub height: Option<f32>,
+}
+
+impl TextColumns {
+    /// Get the width of `n` columns and the gutters between them.
+    pub fn total_width(&self, n: u32) -> f32 {
+        let n = n.max(1) as f32;
+        n * self.width + (n - 1.0) * self.gutter
+    }
+}
+
+/// Box drawn behind a [`FatText`], such as a background mask or a callout frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextBackground {
+    /// Paint for the box.
+    ///
+    /// The fill paint fills the box behind the glyphs, and the stroke paint, if any,
+    /// draws a frame around it.
+    pub paint: PaintHandle,
+    /// Distance from the edges of the layout box to the edges of the box.
+    pub margin: f64,
+}
+
 /// Text item.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FatText {
     /// Primary transform.
     pub transform: TransformHandle,
     /// Paint.
     ///
-    /// Only fills are used currently.
+    /// Only fills are used currently. Text whose style or span sets a brush color is
+    /// drawn in that color instead.
     pub paint: PaintHandle,
     /// Text content.
     pub text: Arc<str>,
     /// Styles for the text.
+    #[cfg_attr(feature = "serde", serde(with = "crate::text_serde::style_set"))]
     pub style: StyleSet<Option<Color>>,
+    /// Style overrides for ranges of the text.
+    ///
+    /// Later spans take precedence where spans overlap.
+    pub spans: Vec<StyleSpan>,
     /// Alignment
+    #[cfg_attr(feature = "serde", serde(with = "crate::text_serde::alignment"))]
     pub alignment: Alignment,
+    /// Base direction.
+    ///
+    /// [`Alignment::Start`] and [`Alignment::End`] are relative to this.
+    /// Attachment points are not, so a right to left label with a left
+    /// attachment point still has its left edge at the insertion point.
+    pub direction: TextDirection,
     /// Maximum inline size before line should break.
     pub max_inline_size: Option<f32>,
+    /// Column layout, if the text flows into more than one column.
+    pub columns: Option<TextColumns>,
+    /// Box drawn behind the text.
+    pub background: Option<TextBackground>,
+    /// Mirror the text left to right within its layout box, as for backward text.
+    pub mirror_x: bool,
+    /// Mirror the text top to bottom within its layout box, as for upside down text.
+    pub mirror_y: bool,
     /// Insertion transform.
     pub insertion: DirectIsometry,
     /// Reference point for insertion.
@@ -89,3 +215,162 @@ pub struct FatText {
     /// The insertion point is at this corner of the text.
     pub attachment_point: AttachmentPoint,
 }
+
+impl FatText {
+    /// Get the font size from the style, if one is set.
+    pub fn font_size(&self) -> Option<f32> {
+        match self
+            .style
+            .inner()
+            .get(&core::mem::discriminant(&StyleProperty::FontSize(0_f32)))
+        {
+            Some(StyleProperty::FontSize(size)) => Some(*size),
+            _ => None,
+        }
+    }
+
+    /// Estimate the size of the text without shaping it.
+    ///
+    /// This assumes every character is one em wide and every line is one and a half
+    /// ems tall, which overestimates most text; it is only intended for coarse culling
+    /// before a layout is available.
+    pub fn estimated_size(&self) -> Size {
+        let size = self.font_size().unwrap_or_default() as f64;
+        let (lines, longest) = self.text.lines().fold((0_usize, 0_usize), |(n, w), l| {
+            (n + 1, w.max(l.chars().count()))
+        });
+        let height = lines.max(1) as f64 * size * 1.5;
+        match self.columns {
+            Some(c) => {
+                // The number of columns added as needed is unknown until the text is laid out.
+                let count = c.count.max(1);
+                Size {
+                    width: c.total_width(count) as f64,
+                    height: c.height.map_or(height / count as f64, f64::from),
+                }
+            }
+            None => Size {
+                width: self
+                    .max_inline_size
+                    .map_or(longest as f64 * size, |w| w as f64),
+                height,
+            },
+        }
+    }
+
+    /// Estimate the bounds of the text without shaping it.
+    ///
+    /// The bounds are in the coordinate space of the text's `transform`.
+    /// See [`FatText::estimated_size`].
+    pub fn estimated_bounds(&self) -> Rect {
+        self.bounds_for_size(self.estimated_size())
+    }
+
+    /// Get the bounds of a layout box of `size` placed at the insertion point.
+    ///
+    /// This includes the background box, if there is one.
+    /// The bounds are in the coordinate space of the text's `transform`.
+    pub fn bounds_for_size(&self, size: Size) -> Rect {
+        let rect = self
+            .background_rect(size)
+            .unwrap_or(Rect::from_origin_size((0.0, 0.0), size));
+        self.placement_for_size(size).transform_rect_bbox(rect)
+    }
+
+    /// Get the transform from a layout box of `size` to the insertion point.
+    ///
+    /// The layout box has its origin at the top left.
+    pub fn placement_for_size(&self, size: Size) -> Affine {
+        Affine::from(self.insertion) * Affine::translate(-self.attachment_point.select(size))
+    }
+
+    /// Get the transform that mirrors a layout box of `size` in place, as set by
+    /// `mirror_x` and `mirror_y`.
+    ///
+    /// This is applied to the glyphs within the layout box, before its placement.
+    pub fn mirror_for_size(&self, size: Size) -> Affine {
+        let (sx, tx) = if self.mirror_x {
+            (-1.0, size.width)
+        } else {
+            (1.0, 0.0)
+        };
+        let (sy, ty) = if self.mirror_y {
+            (-1.0, size.height)
+        } else {
+            (1.0, 0.0)
+        };
+        Affine::new([sx, 0.0, 0.0, sy, tx, ty])
+    }
+
+    /// Get the background box for a layout box of `size`, if there is a background.
+    ///
+    /// The box is in the coordinates of the layout box, with its origin at the top left.
+    pub fn background_rect(&self, size: Size) -> Option<Rect> {
+        let margin = self.background?.margin;
+        Some(Rect::from_origin_size((0.0, 0.0), size).inflate(margin, margin))
+    }
+}
+
+/// Format a [`FontStack`] as a font family list in CSS format.
+pub fn font_stack_to_css(stack: &FontStack<'_>) -> String {
+    match stack {
+        FontStack::Source(s) => s.to_string(),
+        FontStack::Single(f) => f.to_string(),
+        FontStack::List(l) => l
+            .iter()
+            .map(FontFamily::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(mirror_x: bool, mirror_y: bool) -> FatText {
+        FatText {
+            transform: Default::default(),
+            paint: Default::default(),
+            text: "Mirror".into(),
+            style: StyleSet::new(10.0),
+            spans: Vec::new(),
+            alignment: Default::default(),
+            direction: Default::default(),
+            max_inline_size: None,
+            columns: None,
+            background: None,
+            mirror_x,
+            mirror_y,
+            insertion: DirectIsometry::new(0.0, Vec2::ZERO),
+            attachment_point: AttachmentPoint::TopLeft,
+        }
+    }
+
+    #[test]
+    fn mirror_in_place() {
+        let size = Size::new(30.0, 10.0);
+        let layout = Rect::from_origin_size((0.0, 0.0), size);
+        let cases = [
+            ((false, false), Affine::IDENTITY),
+            ((true, false), Affine::new([-1.0, 0.0, 0.0, 1.0, 30.0, 0.0])),
+            ((false, true), Affine::new([1.0, 0.0, 0.0, -1.0, 0.0, 10.0])),
+            (
+                (true, true),
+                Affine::new([-1.0, 0.0, 0.0, -1.0, 30.0, 10.0]),
+            ),
+        ];
+        for ((x, y), expected) in cases {
+            let mirror = text(x, y).mirror_for_size(size);
+            assert_eq!(
+                mirror, expected,
+                "Mirroring ({x}, {y}) should flip the layout box about its center."
+            );
+            assert_eq!(
+                mirror.transform_rect_bbox(layout),
+                layout,
+                "Mirroring ({x}, {y}) should keep the layout box in place."
+            );
+        }
+    }
+}
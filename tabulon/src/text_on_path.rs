@@ -0,0 +1,192 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use peniko::kurbo::{
+    BezPath, ParamCurve, ParamCurveArclen, ParamCurveDeriv, PathSeg, Point, Shape, Vec2,
+};
+
+#[cfg(feature = "text")]
+use {
+    crate::{DirectIsometry, PaintHandle, TransformHandle, text::FatText},
+    alloc::sync::Arc,
+    parley::{StyleProperty, StyleSet},
+    peniko::{Color, kurbo::Rect},
+};
+
+/// Text item placed along a path, such as a label following a contour or a road.
+///
+/// The text is laid out on a single line, and each glyph is placed with the middle of its
+/// baseline on the path, rotated to follow it. Glyphs past the end of the path are not drawn.
+#[cfg(feature = "text")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FatTextOnPath {
+    /// Primary transform.
+    pub transform: TransformHandle,
+    /// Paint.
+    ///
+    /// Only fills are used currently.
+    pub paint: PaintHandle,
+    /// Text content.
+    pub text: Arc<str>,
+    /// Styles for the text.
+    #[cfg_attr(feature = "serde", serde(with = "crate::text_serde::style_set"))]
+    pub style: StyleSet<Option<Color>>,
+    /// Path the baseline follows, in the coordinate space of `transform`.
+    pub path: Arc<BezPath>,
+    /// Distance along the path to the start of the text.
+    pub offset: f64,
+}
+
+#[cfg(feature = "text")]
+impl FatTextOnPath {
+    /// Get the font size from the style, if one is set.
+    pub fn font_size(&self) -> Option<f32> {
+        match self
+            .style
+            .inner()
+            .get(&core::mem::discriminant(&StyleProperty::FontSize(0_f32)))
+        {
+            Some(StyleProperty::FontSize(size)) => Some(*size),
+            _ => None,
+        }
+    }
+
+    /// Make a text item for laying out the text as a single line, which is then placed
+    /// along the path glyph by glyph.
+    pub fn line_text(&self) -> FatText {
+        FatText {
+            transform: self.transform,
+            paint: self.paint,
+            text: self.text.clone(),
+            style: self.style.clone(),
+            spans: Vec::new(),
+            alignment: Default::default(),
+            direction: Default::default(),
+            max_inline_size: None,
+            columns: None,
+            background: None,
+            mirror_x: false,
+            mirror_y: false,
+            insertion: DirectIsometry::new(0.0, Vec2::ZERO),
+            attachment_point: Default::default(),
+        }
+    }
+
+    /// Estimate the bounds of the text without shaping it.
+    ///
+    /// This is the bounding box of the path grown by one and a half ems on every side,
+    /// so it covers glyphs on either side of the path. The bounds are in the coordinate
+    /// space of the text's `transform`.
+    pub fn estimated_bounds(&self) -> Rect {
+        let size = self.font_size().unwrap_or_default() as f64 * 1.5;
+        self.path.bounding_box().inflate(size, size)
+    }
+}
+
+/// A path measured by arc length, for placing things at distances along it.
+#[derive(Debug, Clone)]
+pub struct MeasuredPath {
+    /// Segments with the distance along the path to their start, and their length.
+    segments: Vec<(PathSeg, f64, f64)>,
+    length: f64,
+    /// Accuracy of arc length computations.
+    accuracy: f64,
+}
+
+impl MeasuredPath {
+    /// Measure a path.
+    pub fn new(path: &BezPath) -> Self {
+        let bounds = path.bounding_box();
+        // Accuracy relative to the size of the path, as drawings use many units.
+        let accuracy = (bounds.width() + bounds.height()) * 1e-6;
+        let mut length = 0.0;
+        let segments = path
+            .segments()
+            .map(|seg| {
+                let l = seg.arclen(accuracy);
+                let start = length;
+                length += l;
+                (seg, start, l)
+            })
+            .collect();
+        Self {
+            segments,
+            length,
+            accuracy,
+        }
+    }
+
+    /// Get the total length of the path.
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// Get the point and unit tangent at a distance along the path.
+    ///
+    /// Returns `None` if the distance is not on the path.
+    pub fn at(&self, distance: f64) -> Option<(Point, Vec2)> {
+        if !(0.0..=self.length).contains(&distance) {
+            return None;
+        }
+        let i = self
+            .segments
+            .partition_point(|(_, start, _)| *start <= distance)
+            .checked_sub(1)?;
+        let (seg, start, len) = self.segments[i];
+        let t = if len > 0.0 {
+            seg.inv_arclen((distance - start).min(len), self.accuracy)
+        } else {
+            0.0
+        };
+        let tangent = match seg {
+            PathSeg::Line(l) => l.deriv().eval(t).to_vec2(),
+            PathSeg::Quad(q) => q.deriv().eval(t).to_vec2(),
+            PathSeg::Cubic(c) => c.deriv().eval(t).to_vec2(),
+        };
+        // Fall back to the chord where the derivative vanishes, such as at a cusp.
+        let tangent = if tangent.hypot2() > 0.0 {
+            tangent
+        } else {
+            seg.end() - seg.start()
+        };
+        Some((seg.eval(t), tangent.normalize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measured_corner() {
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((10.0, 0.0));
+        path.line_to((10.0, 5.0));
+        let measured = MeasuredPath::new(&path);
+
+        assert!(
+            (measured.length() - 15.0).abs() < 1e-9,
+            "Length should be the sum of the segments."
+        );
+        assert_eq!(
+            measured.at(4.0),
+            Some((Point::new(4.0, 0.0), Vec2::new(1.0, 0.0))),
+            "Points on the first segment should follow it."
+        );
+        assert_eq!(
+            measured.at(12.0),
+            Some((Point::new(10.0, 2.0), Vec2::new(0.0, 1.0))),
+            "Points past the corner should follow the second segment."
+        );
+        assert_eq!(
+            measured.at(16.0),
+            None,
+            "Points past the end are not on the path."
+        );
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Serde support for Parley types used in [`FatText`](crate::text::FatText).
+//!
+//! Parley does not implement serde traits, so these mirror the relevant types.
+
+extern crate alloc;
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::text::font_stack_to_css;
+use parley::{
+    Alignment, FontSettings, FontStack, FontStyle, FontWeight, FontWidth, LineHeight, OverflowWrap,
+    StyleProperty, StyleSet, WordBreakStrength, swash::Setting,
+};
+use peniko::Color;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, ser::Error};
+
+/// Mirror of [`FontStyle`].
+#[derive(Serialize, Deserialize)]
+enum FontStyleDef {
+    Normal,
+    Italic,
+    Oblique(Option<f32>),
+}
+
+/// Mirror of [`LineHeight`].
+#[derive(Serialize, Deserialize)]
+enum LineHeightDef {
+    MetricsRelative(f32),
+    FontSizeRelative(f32),
+    Absolute(f32),
+}
+
+/// Mirror of [`FontSettings`], with settings as pairs of tag and value.
+#[derive(Serialize, Deserialize)]
+enum FontSettingsDef<T> {
+    Source(String),
+    List(Vec<(u32, T)>),
+}
+
+/// Mirror of [`WordBreakStrength`].
+#[derive(Serialize, Deserialize)]
+enum WordBreakDef {
+    Normal,
+    BreakAll,
+    KeepAll,
+}
+
+/// Mirror of [`OverflowWrap`].
+#[derive(Serialize, Deserialize)]
+enum OverflowWrapDef {
+    Normal,
+    Anywhere,
+    BreakWord,
+}
+
+/// Mirror of [`StyleProperty`].
+///
+/// Font stacks are stored in CSS format.
+#[derive(Serialize, Deserialize)]
+enum StylePropertyDef {
+    FontStack(String),
+    FontSize(f32),
+    FontWidth(f32),
+    FontStyle(FontStyleDef),
+    FontWeight(f32),
+    FontVariations(FontSettingsDef<f32>),
+    FontFeatures(FontSettingsDef<u16>),
+    Brush(Option<Color>),
+    Underline(bool),
+    UnderlineOffset(Option<f32>),
+    UnderlineSize(Option<f32>),
+    UnderlineBrush(Option<Option<Color>>),
+    Strikethrough(bool),
+    StrikethroughOffset(Option<f32>),
+    StrikethroughSize(Option<f32>),
+    StrikethroughBrush(Option<Option<Color>>),
+    LineHeight(LineHeightDef),
+    WordSpacing(f32),
+    LetterSpacing(f32),
+    WordBreak(WordBreakDef),
+    OverflowWrap(OverflowWrapDef),
+}
+
+fn settings_to_def<T: Copy + core::fmt::Debug + PartialEq>(
+    s: &FontSettings<'_, Setting<T>>,
+) -> FontSettingsDef<T>
+where
+    [Setting<T>]: alloc::borrow::ToOwned<Owned = Vec<Setting<T>>>,
+{
+    match s {
+        FontSettings::Source(s) => FontSettingsDef::Source(s.to_string()),
+        FontSettings::List(l) => {
+            FontSettingsDef::List(l.iter().map(|s| (s.tag, s.value)).collect())
+        }
+    }
+}
+
+fn settings_from_def<T: Copy + core::fmt::Debug + PartialEq>(
+    d: FontSettingsDef<T>,
+) -> FontSettings<'static, Setting<T>>
+where
+    [Setting<T>]: alloc::borrow::ToOwned<Owned = Vec<Setting<T>>>,
+{
+    match d {
+        FontSettingsDef::Source(s) => FontSettings::Source(Cow::Owned(s)),
+        FontSettingsDef::List(l) => FontSettings::List(Cow::Owned(
+            l.into_iter()
+                .map(|(tag, value)| Setting { tag, value })
+                .collect(),
+        )),
+    }
+}
+
+impl StylePropertyDef {
+    /// Mirror a [`StyleProperty`], failing for locales which cannot be deserialized.
+    fn from_property(p: &StyleProperty<'static, Option<Color>>) -> Result<Self, &'static str> {
+        use StyleProperty as P;
+        Ok(match p {
+            P::FontStack(s) => Self::FontStack(font_stack_to_css(s)),
+            P::FontSize(s) => Self::FontSize(*s),
+            P::FontWidth(w) => Self::FontWidth(w.ratio()),
+            P::FontStyle(FontStyle::Normal) => Self::FontStyle(FontStyleDef::Normal),
+            P::FontStyle(FontStyle::Italic) => Self::FontStyle(FontStyleDef::Italic),
+            P::FontStyle(FontStyle::Oblique(a)) => Self::FontStyle(FontStyleDef::Oblique(*a)),
+            P::FontWeight(w) => Self::FontWeight(w.value()),
+            P::FontVariations(v) => Self::FontVariations(settings_to_def::<f32>(v)),
+            P::FontFeatures(f) => Self::FontFeatures(settings_to_def::<u16>(f)),
+            P::Locale(_) => return Err("locale style properties cannot be serialized"),
+            P::Brush(b) => Self::Brush(*b),
+            P::Underline(u) => Self::Underline(*u),
+            P::UnderlineOffset(o) => Self::UnderlineOffset(*o),
+            P::UnderlineSize(s) => Self::UnderlineSize(*s),
+            P::UnderlineBrush(b) => Self::UnderlineBrush(*b),
+            P::Strikethrough(s) => Self::Strikethrough(*s),
+            P::StrikethroughOffset(o) => Self::StrikethroughOffset(*o),
+            P::StrikethroughSize(s) => Self::StrikethroughSize(*s),
+            P::StrikethroughBrush(b) => Self::StrikethroughBrush(*b),
+            P::LineHeight(LineHeight::MetricsRelative(h)) => {
+                Self::LineHeight(LineHeightDef::MetricsRelative(*h))
+            }
+            P::LineHeight(LineHeight::FontSizeRelative(h)) => {
+                Self::LineHeight(LineHeightDef::FontSizeRelative(*h))
+            }
+            P::LineHeight(LineHeight::Absolute(h)) => Self::LineHeight(LineHeightDef::Absolute(*h)),
+            P::WordSpacing(s) => Self::WordSpacing(*s),
+            P::LetterSpacing(s) => Self::LetterSpacing(*s),
+            P::WordBreak(WordBreakStrength::Normal) => Self::WordBreak(WordBreakDef::Normal),
+            P::WordBreak(WordBreakStrength::BreakAll) => Self::WordBreak(WordBreakDef::BreakAll),
+            P::WordBreak(WordBreakStrength::KeepAll) => Self::WordBreak(WordBreakDef::KeepAll),
+            P::OverflowWrap(OverflowWrap::Normal) => Self::OverflowWrap(OverflowWrapDef::Normal),
+            P::OverflowWrap(OverflowWrap::Anywhere) => {
+                Self::OverflowWrap(OverflowWrapDef::Anywhere)
+            }
+            P::OverflowWrap(OverflowWrap::BreakWord) => {
+                Self::OverflowWrap(OverflowWrapDef::BreakWord)
+            }
+        })
+    }
+
+    /// Convert back into a [`StyleProperty`].
+    fn into_property(self) -> StyleProperty<'static, Option<Color>> {
+        use StyleProperty as P;
+        match self {
+            Self::FontStack(s) => P::FontStack(FontStack::Source(Cow::Owned(s))),
+            Self::FontSize(s) => P::FontSize(s),
+            Self::FontWidth(w) => P::FontWidth(FontWidth::from_ratio(w)),
+            Self::FontStyle(FontStyleDef::Normal) => P::FontStyle(FontStyle::Normal),
+            Self::FontStyle(FontStyleDef::Italic) => P::FontStyle(FontStyle::Italic),
+            Self::FontStyle(FontStyleDef::Oblique(a)) => P::FontStyle(FontStyle::Oblique(a)),
+            Self::FontWeight(w) => P::FontWeight(FontWeight::new(w)),
+            Self::FontVariations(v) => P::FontVariations(settings_from_def::<f32>(v)),
+            Self::FontFeatures(f) => P::FontFeatures(settings_from_def::<u16>(f)),
+            Self::Brush(b) => P::Brush(b),
+            Self::Underline(u) => P::Underline(u),
+            Self::UnderlineOffset(o) => P::UnderlineOffset(o),
+            Self::UnderlineSize(s) => P::UnderlineSize(s),
+            Self::UnderlineBrush(b) => P::UnderlineBrush(b),
+            Self::Strikethrough(s) => P::Strikethrough(s),
+            Self::StrikethroughOffset(o) => P::StrikethroughOffset(o),
+            Self::StrikethroughSize(s) => P::StrikethroughSize(s),
+            Self::StrikethroughBrush(b) => P::StrikethroughBrush(b),
+            Self::LineHeight(LineHeightDef::MetricsRelative(h)) => {
+                P::LineHeight(LineHeight::MetricsRelative(h))
+            }
+            Self::LineHeight(LineHeightDef::FontSizeRelative(h)) => {
+                P::LineHeight(LineHeight::FontSizeRelative(h))
+            }
+            Self::LineHeight(LineHeightDef::Absolute(h)) => P::LineHeight(LineHeight::Absolute(h)),
+            Self::WordSpacing(s) => P::WordSpacing(s),
+            Self::LetterSpacing(s) => P::LetterSpacing(s),
+            Self::WordBreak(WordBreakDef::Normal) => P::WordBreak(WordBreakStrength::Normal),
+            Self::WordBreak(WordBreakDef::BreakAll) => P::WordBreak(WordBreakStrength::BreakAll),
+            Self::WordBreak(WordBreakDef::KeepAll) => P::WordBreak(WordBreakStrength::KeepAll),
+            Self::OverflowWrap(OverflowWrapDef::Normal) => P::OverflowWrap(OverflowWrap::Normal),
+            Self::OverflowWrap(OverflowWrapDef::Anywhere) => {
+                P::OverflowWrap(OverflowWrap::Anywhere)
+            }
+            Self::OverflowWrap(OverflowWrapDef::BreakWord) => {
+                P::OverflowWrap(OverflowWrap::BreakWord)
+            }
+        }
+    }
+}
+
+/// Serde adapter for [`StyleSet`], for use with `#[serde(with = ...)]`.
+pub(crate) mod style_set {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        style: &StyleSet<Option<Color>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        style
+            .inner()
+            .values()
+            .map(StylePropertyDef::from_property)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<StyleSet<Option<Color>>, D::Error> {
+        let properties = Vec::<StylePropertyDef>::deserialize(deserializer)?;
+        // The font size is always overwritten if present.
+        let mut style = StyleSet::new(0_f32);
+        style.retain(|_| false);
+        for p in properties {
+            style.insert(p.into_property());
+        }
+        Ok(style)
+    }
+}
+
+/// Mirror of [`Alignment`].
+#[derive(Serialize, Deserialize)]
+enum AlignmentDef {
+    Start,
+    End,
+    Left,
+    Middle,
+    Right,
+    Justified,
+}
+
+/// Serde adapter for [`Alignment`], for use with `#[serde(with = ...)]`.
+pub(crate) mod alignment {
+    use super::*;
+
+    #[expect(
+        clippy::trivially_copy_pass_by_ref,
+        reason = "Signature is required by serde."
+    )]
+    pub(crate) fn serialize<S: Serializer>(
+        alignment: &Alignment,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match alignment {
+            Alignment::Start => AlignmentDef::Start,
+            Alignment::End => AlignmentDef::End,
+            Alignment::Left => AlignmentDef::Left,
+            Alignment::Middle => AlignmentDef::Middle,
+            Alignment::Right => AlignmentDef::Right,
+            Alignment::Justified => AlignmentDef::Justified,
+        }
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Alignment, D::Error> {
+        Ok(match AlignmentDef::deserialize(deserializer)? {
+            AlignmentDef::Start => Alignment::Start,
+            AlignmentDef::End => Alignment::End,
+            AlignmentDef::Left => Alignment::Left,
+            AlignmentDef::Middle => Alignment::Middle,
+            AlignmentDef::Right => Alignment::Right,
+            AlignmentDef::Justified => Alignment::Justified,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DirectIsometry, GraphicsBag, GraphicsItem,
+        render_layer::RenderLayer,
+        text::{AttachmentPoint, FatText, TextDirection},
+    };
+    use parley::{Alignment, FontStyle, GenericFamily, StyleProperty, StyleSet};
+    use peniko::{Color, kurbo::Vec2};
+
+    #[test]
+    fn text_round_trip() {
+        let mut style = StyleSet::new(2.5);
+        style.insert(GenericFamily::Serif.into());
+        style.insert(StyleProperty::FontStyle(FontStyle::Oblique(Some(15.0))));
+        style.insert(StyleProperty::Brush(Some(Color::from_rgba8(1, 2, 3, 255))));
+
+        let mut bag = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        // Deserialized bags are checked, so the paint must be registered.
+        let paint = bag.register_paint(Default::default());
+        let ih = layer.push_with_bag(
+            &mut bag,
+            FatText {
+                transform: Default::default(),
+                paint,
+                text: "Hello".into(),
+                style: style.clone(),
+                spans: Default::default(),
+                alignment: Alignment::Middle,
+                direction: TextDirection::RightToLeft,
+                max_inline_size: Some(10.0),
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.5, Vec2::new(1.0, 2.0)),
+                attachment_point: AttachmentPoint::BottomRight,
+            },
+        );
+
+        let bag: GraphicsBag = serde_json::from_str(&serde_json::to_string(&bag).unwrap()).unwrap();
+        let layer: RenderLayer =
+            serde_json::from_str(&serde_json::to_string(&layer).unwrap()).unwrap();
+
+        assert_eq!(layer.indices, [ih], "Render layer should round trip.");
+        let Some(GraphicsItem::FatText(t)) = bag.get(ih) else {
+            panic!("Text item should round trip.");
+        };
+        assert_eq!(&*t.text, "Hello", "Text content should round trip.");
+        assert_eq!(
+            t.alignment,
+            Alignment::Middle,
+            "Alignment should round trip."
+        );
+        assert_eq!(
+            t.direction,
+            TextDirection::RightToLeft,
+            "Direction should round trip."
+        );
+        assert_eq!(
+            t.style.inner().len(),
+            style.inner().len(),
+            "Every style property should round trip."
+        );
+        assert!(
+            t.style
+                .inner()
+                .values()
+                .filter(|p| !matches!(p, StyleProperty::FontStack(..)))
+                .all(|p| style.inner().values().any(|q| p == q)),
+            "Style properties should round trip."
+        );
+    }
+}
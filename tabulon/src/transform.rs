@@ -3,12 +3,16 @@
 
 //! Utilities for transformations
 
-use peniko::kurbo::{Affine, Vec2};
+use peniko::kurbo::{Affine, Point, Rect, Vec2};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
 
 /// A direct isometry.
 ///
 /// Direct isometries do not include reflections.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DirectIsometry {
     /// Angle in radians to rotate at the origin.
     pub angle: f64,
@@ -38,3 +42,373 @@ impl From<DirectIsometry> for Affine {
         Self::rotate(angle).then_translate(displacement)
     }
 }
+
+/// An [`Affine`] decomposed into translation, rotation, scale, and shear.
+///
+/// The parts compose in the order they are listed, so the shear is applied first
+/// and the translation last. Reflections are represented by a negative `scale.y`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecomposedAffine {
+    /// Translation, applied last.
+    pub translation: Vec2,
+    /// Angle in radians to rotate at the origin.
+    pub rotation: f64,
+    /// Scale along the x and y axes.
+    pub scale: Vec2,
+    /// Horizontal shear factor, applied first.
+    pub shear: f64,
+}
+
+impl From<Affine> for DecomposedAffine {
+    fn from(transform: Affine) -> Self {
+        let [a, b, c, d, e, f] = transform.as_coeffs();
+        let sx = a.hypot(b);
+        let det = a * d - b * c;
+        let (rotation, sy, shear) = if sx > 0.0 {
+            (b.atan2(a), det / sx, (a * c + b * d) / (sx * sx))
+        } else {
+            // The x axis is collapsed, so the rotation is taken from the y axis.
+            (-c.atan2(d), c.hypot(d), 0.0)
+        };
+        Self {
+            translation: Vec2::new(e, f),
+            rotation,
+            scale: Vec2::new(sx, sy),
+            shear,
+        }
+    }
+}
+
+impl From<DecomposedAffine> for Affine {
+    #[inline]
+    fn from(
+        DecomposedAffine {
+            translation,
+            rotation,
+            scale,
+            shear,
+        }: DecomposedAffine,
+    ) -> Self {
+        Self::translate(translation)
+            * Self::rotate(rotation)
+            * Self::scale_non_uniform(scale.x, scale.y)
+            * Self::skew(shear, 0.0)
+    }
+}
+
+/// Get the uniform scale factor of a transform, such as the scale of a view.
+///
+/// This is the square root of the absolute determinant, the factor by which the
+/// transform scales lengths on average. It is exact for similarity transforms,
+/// is independent of rotation and reflection, and does not drift like a scale
+/// tracked separately from the transform through many gestures.
+pub fn uniform_scale(transform: Affine) -> f64 {
+    transform.determinant().abs().sqrt()
+}
+
+/// Get a view transform that shows `bounds` centered in `viewport`.
+///
+/// `view` is the current view transform, and `viewport` and `padding` are in the
+/// coordinates it maps to, such as logical pixels. `bounds` is scaled to fit inside
+/// `viewport` with `padding` on every side, or half of `viewport` when the padding
+/// leaves no room. The rotation and reflection of `view` are kept, and so is its scale
+/// if `bounds` is a single point, so zooming to a point pans to it.
+pub fn zoom_to(view: Affine, bounds: Rect, viewport: Rect, padding: f64) -> Affine {
+    let scale = uniform_scale(view);
+    let orientation = if scale > 0.0 {
+        let [a, b, c, d, _, _] = view.as_coeffs();
+        Affine::new([a / scale, b / scale, c / scale, d / scale, 0.0, 0.0])
+    } else {
+        Affine::IDENTITY
+    };
+    let oriented = orientation.transform_rect_bbox(bounds);
+    let fit = |extent: f64, room: f64| {
+        (extent > 0.0).then(|| (room - 2.0 * padding).max(room * 0.5) / extent)
+    };
+    let scale = match (
+        fit(oriented.width(), viewport.width()),
+        fit(oriented.height(), viewport.height()),
+    ) {
+        (Some(x), Some(y)) => x.min(y),
+        (Some(s), None) | (None, Some(s)) => s,
+        (None, None) => scale,
+    };
+    Affine::translate(viewport.center().to_vec2())
+        * Affine::scale(scale)
+        * orientation
+        * Affine::translate(-bounds.center().to_vec2())
+}
+
+/// Zoom a view transform by `factor`, keeping the point under `anchor` in place.
+///
+/// `anchor` is in the coordinates `view` maps to, which must be the coordinates of
+/// pointer positions, such as device pixels for a view that maps to device pixels.
+/// Factors that are not finite and positive are ignored, so that a large scroll can't
+/// collapse or flip the view.
+pub fn zoom_about(view: Affine, factor: f64, anchor: Point) -> Affine {
+    if !(factor.is_finite() && factor > 0.0) {
+        return view;
+    }
+    view.then_scale_about(factor, anchor)
+}
+
+/// Get the transform from device pixels at `old_scale_factor` to device pixels at
+/// `new_scale_factor`, such as when a window moves to a monitor with a different
+/// scale factor.
+///
+/// Apply it to view transforms that map to device pixels, and to device positions kept
+/// across the change, such as the last pointer position. Content then keeps its logical
+/// size and position, and a zoom anchored at the pointer after the change keeps the
+/// content under the pointer in place, rather than drifting by the ratio of the scale
+/// factors. Scale factors that are not finite and positive give the identity.
+pub fn rescale_device_pixels(old_scale_factor: f64, new_scale_factor: f64) -> Affine {
+    let ratio = new_scale_factor / old_scale_factor;
+    if !(ratio.is_finite() && ratio > 0.0) {
+        return Affine::IDENTITY;
+    }
+    Affine::scale(ratio)
+}
+
+/// Find the similarity transform that best maps each point `from` onto its `to`.
+///
+/// This is the least squares fit of a uniform scale, a rotation, and a translation,
+/// such as for registering one drawing over another from pairs of matching points.
+/// Reflections are not considered.
+///
+/// Returns `None` if there are no pairs, or all `from` points coincide.
+pub fn fit_similarity(pairs: &[(Point, Point)]) -> Option<Affine> {
+    if pairs.is_empty() {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let (sum_from, sum_to) = pairs
+        .iter()
+        .fold((Vec2::ZERO, Vec2::ZERO), |(a, b), (f, t)| {
+            (a + f.to_vec2(), b + t.to_vec2())
+        });
+    let (from_center, to_center) = (sum_from / n, sum_to / n);
+
+    // With both sets centered, the best `a + bi` minimizes the sum of |(a + bi) f - t|².
+    let (mut a, mut b, mut norm) = (0.0, 0.0, 0.0);
+    for (f, t) in pairs {
+        let (f, t) = (f.to_vec2() - from_center, t.to_vec2() - to_center);
+        a += f.dot(t);
+        b += f.cross(t);
+        norm += f.hypot2();
+    }
+    if norm <= 0.0 {
+        return None;
+    }
+    let (a, b) = (a / norm, b / norm);
+    let linear = Affine::new([a, b, -b, a, 0.0, 0.0]);
+    Some(linear.then_translate(to_center - (linear * from_center.to_point()).to_vec2()))
+}
+
+/// Snap a view transform so that `anchor` lands on the center of a device pixel.
+///
+/// `transform` maps to logical pixels, and there are `scale_factor` device pixels per
+/// logical pixel. Only the translation is changed, by less than a device pixel, so
+/// points a whole number of device pixels from `anchor` after the transform, such as
+/// lines on a grid, also land on pixel centers. One pixel wide strokes through them
+/// are drawn crisply, and content moves by whole pixels when panning, instead of
+/// blurring and shimmering at fractional offsets.
+///
+/// Keep the unsnapped transform as the view state, and snap it each time it changes,
+/// so that rounding does not accumulate through gestures.
+pub fn snap_to_pixel_centers(transform: Affine, anchor: Point, scale_factor: f64) -> Affine {
+    let p = (transform * anchor).to_vec2() * scale_factor;
+    let snapped = Vec2::new((p.x - 0.5).round() + 0.5, (p.y - 0.5).round() + 0.5);
+    transform.then_translate((snapped - p) / scale_factor)
+}
+
+/// Round a stroke width to a whole number of device pixels, and at least one.
+///
+/// `width` and the result are in logical pixels, and there are `scale_factor` device
+/// pixels per logical pixel. Odd widths are crisp when centered on pixel centers,
+/// see [`snap_to_pixel_centers`].
+pub fn snap_stroke_width(width: f64, scale_factor: f64) -> f64 {
+    (width * scale_factor).round().max(1.0) / scale_factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    fn assert_near(a: Affine, b: Affine, message: &str) {
+        let (a, b) = (a.as_coeffs(), b.as_coeffs());
+        assert!(
+            a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9),
+            "{message} {a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn decompose_round_trip() {
+        for transform in [
+            Affine::IDENTITY,
+            Affine::translate((3.0, -4.0)) * Affine::rotate(0.7) * Affine::scale(2.5),
+            Affine::rotate(-2.0) * Affine::scale_non_uniform(1.5, -0.5) * Affine::skew(0.3, 0.0),
+            Affine::FLIP_X * Affine::rotate(1.0),
+            Affine::new([0.0, 0.0, 1.0, 2.0, 5.0, 6.0]),
+        ] {
+            let parts = DecomposedAffine::from(transform);
+            assert_near(
+                Affine::from(parts),
+                transform,
+                "Parts should compose to the original transform.",
+            );
+        }
+
+        let parts = DecomposedAffine::from(
+            Affine::translate((1.0, 2.0)) * Affine::rotate(0.5) * Affine::scale(4.0),
+        );
+        assert!(
+            (parts.rotation - 0.5).abs() < 1e-12
+                && (parts.scale - Vec2::new(4.0, 4.0)).hypot() < 1e-12
+                && parts.shear.abs() < 1e-12
+                && parts.translation == Vec2::new(1.0, 2.0),
+            "Similarity transforms should decompose exactly, got {parts:?}."
+        );
+    }
+
+    #[test]
+    fn similarity_fit() {
+        let truth = Affine::translate((4.0, -2.0)) * Affine::rotate(0.6) * Affine::scale(1.5);
+        let from = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(3.0, 7.0),
+        ];
+        let pairs: Vec<(Point, Point)> = from.iter().map(|p| (*p, truth * *p)).collect();
+        let fit = fit_similarity(&pairs).expect("Distinct points should fit.");
+        assert!(
+            fit.as_coeffs()
+                .iter()
+                .zip(truth.as_coeffs())
+                .all(|(x, y)| (x - y).abs() < 1e-9),
+            "Exact pairs should recover the transform, got {fit:?}."
+        );
+        assert_eq!(
+            fit_similarity(&[(Point::ORIGIN, Point::new(1.0, 1.0)); 2]),
+            None,
+            "Coincident points do not determine a transform."
+        );
+    }
+
+    #[test]
+    fn pixel_snapping() {
+        let view = Affine::scale(3.0).then_translate(Vec2::new(10.3, -4.8));
+        let snapped = snap_to_pixel_centers(view, Point::new(1.0, 1.0), 2.0);
+        let p = snapped * Point::new(1.0, 1.0);
+        assert_eq!(
+            (p.x * 2.0, p.y * 2.0),
+            (26.5, -3.5),
+            "The anchor should land on the nearest device pixel center."
+        );
+        let q = snapped * Point::new(1.5, 1.0);
+        assert_eq!(
+            q.x * 2.0,
+            29.5,
+            "Points whole device pixels from the anchor should land on pixel centers too."
+        );
+        assert_eq!(
+            snap_stroke_width(0.2, 2.0),
+            0.5,
+            "Hairlines should be at least one device pixel wide."
+        );
+        assert_eq!(
+            snap_stroke_width(1.3, 2.0),
+            1.5,
+            "Widths should be rounded to whole device pixels."
+        );
+    }
+
+    #[test]
+    fn uniform_scale_ignores_rotation_and_translation() {
+        let view = Affine::translate((100.0, 50.0))
+            .then_rotate(1.2)
+            .then_scale_about(3.0, Point::new(10.0, 10.0))
+            .then_scale(0.5);
+        assert!(
+            (uniform_scale(view) - 1.5).abs() < 1e-12,
+            "View scale should be the product of the zoom steps."
+        );
+        assert!(
+            (uniform_scale(Affine::FLIP_Y * Affine::scale(2.0)) - 2.0).abs() < 1e-12,
+            "Reflections should not change the scale."
+        );
+    }
+
+    #[test]
+    fn zoom_keeps_anchor_across_scale_factors() {
+        let drawing_point = Point::new(40.0, 30.0);
+        // At a scale factor of 1, the pointer is over `drawing_point`.
+        let view = Affine::translate((60.0, 20.0)) * Affine::FLIP_Y;
+        let cursor = view * drawing_point;
+        let zoomed = zoom_about(view, 1.5, cursor);
+        assert!(
+            (zoomed * drawing_point - cursor).hypot() < 1e-9,
+            "Zooming should keep the point under the anchor in place."
+        );
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                zoom_about(view, factor, cursor),
+                view,
+                "Factor {factor} should be ignored."
+            );
+        }
+
+        // Moving to a monitor with a scale factor of 2 doubles device positions.
+        let change = rescale_device_pixels(1.0, 2.0);
+        let (view, cursor) = (change * view, change * cursor);
+        assert_eq!(
+            cursor,
+            Point::new(200.0, -20.0),
+            "Device positions should follow the scale factor."
+        );
+        assert!(
+            (view * drawing_point - cursor).hypot() < 1e-9
+                && (uniform_scale(view) - 2.0).abs() < 1e-12,
+            "The view should keep content at the same logical position and size."
+        );
+        let zoomed = zoom_about(view, 0.5, cursor);
+        assert!(
+            (zoomed * drawing_point - cursor).hypot() < 1e-9,
+            "Zooming after the change should keep the point under the pointer in place."
+        );
+        assert_eq!(
+            rescale_device_pixels(0.0, 2.0),
+            Affine::IDENTITY,
+            "Invalid scale factors should not change anything."
+        );
+    }
+
+    #[test]
+    fn zoom_to_bounds() {
+        let viewport = Rect::new(0.0, 0.0, 200.0, 100.0);
+        let bounds = Rect::new(10.0, 10.0, 30.0, 50.0);
+        let view = zoom_to(Affine::FLIP_Y, bounds, viewport, 10.0);
+        let shown = view.transform_rect_bbox(bounds);
+        assert!(
+            [shown.x0, shown.y0, shown.x1, shown.y1]
+                .iter()
+                .zip([80.0, 10.0, 120.0, 90.0])
+                .all(|(x, y)| (x - y).abs() < 1e-9),
+            "Bounds should fit inside the padding, centered, got {shown:?}."
+        );
+        assert!(
+            view.determinant() < 0.0,
+            "The reflection of the view should be kept."
+        );
+        let point = Rect::from_origin_size((5.0, 5.0), (0.0, 0.0));
+        assert_near(
+            zoom_to(Affine::scale(3.0), point, viewport, 10.0),
+            Affine::translate((85.0, 35.0)) * Affine::scale(3.0),
+            "Zooming to a point should pan to it at the same scale.",
+        );
+    }
+}
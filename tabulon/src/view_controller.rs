@@ -0,0 +1,407 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Panning and zooming of a view from pointer input.
+//!
+//! A [`ViewController`] keeps a view transform that maps to device pixels, and updates
+//! it from drags, wheel and pinch zooms, and changes of scale factor, keeping zooms
+//! anchored under the pointer as [`zoom_about`] does. With [`InputSmoothing`], wheel
+//! zooms are low-pass filtered, so that the notches of a wheel and the uneven deltas of
+//! a touchpad zoom smoothly, and drags continue with inertia after they are released,
+//! slowing to a stop.
+//!
+//! Time is supplied by the caller in seconds, from any fixed origin, as for an
+//! [`Animator`](crate::animation::Animator). Call [`ViewController::tick`] each frame
+//! while [`ViewController::is_animating`], and the view moves the same way for the same
+//! input and times, which makes it deterministic in tests.
+
+use core::f64::consts::E;
+
+use peniko::kurbo::{Affine, Point, Vec2};
+
+#[cfg(all(not(feature = "std"), not(test)))]
+use crate::floatfuncs::FloatFuncs;
+use crate::{rescale_device_pixels, zoom_about};
+
+/// How input is smoothed by a [`ViewController`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputSmoothing {
+    /// Time constant of the low-pass filter on wheel zooms, in seconds.
+    ///
+    /// Each wheel zoom is applied over a few time constants, most of it at first.
+    /// Zero applies wheel zooms at once.
+    pub zoom_time_constant: f64,
+    /// Time for the speed of inertial panning to fall by a factor of e, in seconds.
+    ///
+    /// Zero disables inertia.
+    pub pan_decay: f64,
+    /// Speed, in device pixels per second, below which inertial panning stops.
+    ///
+    /// Drags released slower than this don't continue.
+    pub min_pan_speed: f64,
+}
+
+impl InputSmoothing {
+    /// No smoothing or inertia, so input moves the view at once and only while it lasts.
+    pub const NONE: Self = Self {
+        zoom_time_constant: 0.0,
+        pan_decay: 0.0,
+        min_pan_speed: 0.0,
+    };
+}
+
+impl Default for InputSmoothing {
+    fn default() -> Self {
+        Self {
+            zoom_time_constant: 0.05,
+            pan_decay: 0.3,
+            min_pan_speed: 30.0,
+        }
+    }
+}
+
+/// A drag in progress.
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    /// Time of the last move of the drag.
+    last_move: f64,
+}
+
+/// Keeps a view transform, updated from pointer input.
+///
+/// See the [module documentation](self) for details.
+#[derive(Clone, Debug)]
+pub struct ViewController {
+    view: Affine,
+    scale_factor: f64,
+    smoothing: InputSmoothing,
+    /// Last pointer position, in device pixels.
+    cursor: Point,
+    drag: Option<Drag>,
+    /// Velocity of the drag, or of inertial panning after it, in device pixels per second.
+    velocity: Vec2,
+    /// Whether panning continues with `velocity`.
+    coasting: bool,
+    /// Factor of wheel zooms not yet applied.
+    pending_zoom: f64,
+    /// Anchor of the pending zoom, in device pixels.
+    zoom_anchor: Point,
+    /// Time of the last tick.
+    last_tick: Option<f64>,
+}
+
+impl ViewController {
+    /// Time over which the velocity of drags is averaged, in seconds.
+    const VELOCITY_WINDOW: f64 = 0.05;
+
+    /// Time the pointer must rest before a release, in seconds, for a drag not to
+    /// continue with inertia.
+    const REST: f64 = 0.1;
+
+    /// Pending zoom factors closer to 1 than this are applied in full.
+    const ZOOM_EPSILON: f64 = 1e-4;
+
+    /// Make a controller for `view`, which maps to device pixels at `scale_factor`
+    /// device pixels per logical pixel.
+    pub fn new(view: Affine, scale_factor: f64, smoothing: InputSmoothing) -> Self {
+        Self {
+            view,
+            scale_factor,
+            smoothing,
+            cursor: Point::ORIGIN,
+            drag: None,
+            velocity: Vec2::ZERO,
+            coasting: false,
+            pending_zoom: 1.0,
+            zoom_anchor: Point::ORIGIN,
+            last_tick: None,
+        }
+    }
+
+    /// Get the view transform, which maps to device pixels.
+    pub fn view(&self) -> Affine {
+        self.view
+    }
+
+    /// Replace the view transform, such as to zoom to fit, stopping any motion.
+    pub fn set_view(&mut self, view: Affine) {
+        self.view = view;
+        self.stop();
+    }
+
+    /// Get the scale factor the view maps to device pixels for.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Get the last pointer position, in device pixels.
+    pub fn cursor(&self) -> Point {
+        self.cursor
+    }
+
+    /// Get how input is smoothed.
+    pub fn smoothing(&self) -> InputSmoothing {
+        self.smoothing
+    }
+
+    /// Change how input is smoothed, stopping any motion.
+    pub fn set_smoothing(&mut self, smoothing: InputSmoothing) {
+        self.smoothing = smoothing;
+        self.stop();
+    }
+
+    /// Check whether a drag is in progress.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Check whether the view is still moving without input, so that
+    /// [`tick`](Self::tick) should be called for the next frame.
+    pub fn is_animating(&self) -> bool {
+        self.coasting || self.pending_zoom != 1.0
+    }
+
+    /// Stop inertial panning and apply pending zooms at once.
+    pub fn stop(&mut self) {
+        self.coasting = false;
+        self.velocity = Vec2::ZERO;
+        if self.pending_zoom != 1.0 {
+            self.apply_zoom(self.pending_zoom);
+        }
+    }
+
+    /// Move the pointer to `position`, in device pixels, at time `now`.
+    ///
+    /// During a drag, this pans the view with the pointer.
+    pub fn pointer_move(&mut self, position: Point, now: f64) {
+        let delta = position - self.cursor;
+        self.cursor = position;
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+        self.view = self.view.then_translate(delta);
+        let dt = now - drag.last_move;
+        if dt > 0.0 {
+            // An exponential moving average over time, so that uneven event rates
+            // give the same velocity.
+            let weight = 1.0 - E.powf(-dt / Self::VELOCITY_WINDOW);
+            self.velocity += (delta / dt - self.velocity) * weight;
+        }
+        drag.last_move = now;
+    }
+
+    /// Start dragging at `position`, in device pixels, at time `now`.
+    ///
+    /// This stops inertial panning, so that touching a moving view holds it.
+    pub fn drag_start(&mut self, position: Point, now: f64) {
+        self.cursor = position;
+        self.coasting = false;
+        self.velocity = Vec2::ZERO;
+        self.drag = Some(Drag { last_move: now });
+    }
+
+    /// End a drag at time `now`.
+    ///
+    /// The view continues to pan with the velocity of the drag, if there is inertia and
+    /// the pointer didn't rest before the release.
+    pub fn drag_end(&mut self, now: f64) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+        if now - drag.last_move > Self::REST {
+            self.velocity = Vec2::ZERO;
+        }
+        self.coasting = self.smoothing.pan_decay > 0.0
+            && self.velocity.hypot() >= self.smoothing.min_pan_speed.max(f64::EPSILON);
+        if !self.coasting {
+            self.velocity = Vec2::ZERO;
+        }
+        self.last_tick = Some(now);
+    }
+
+    /// Zoom by `factor` about the pointer, smoothed, as for a mouse wheel or touchpad
+    /// scroll at time `now`.
+    ///
+    /// Zooms accumulate, and are applied as the controller [ticks](Self::tick), unless
+    /// there is no zoom smoothing. Factors that are not finite and positive are ignored.
+    pub fn wheel_zoom(&mut self, factor: f64, now: f64) {
+        if !(factor.is_finite() && factor > 0.0) {
+            return;
+        }
+        if !self.is_animating() {
+            self.last_tick = Some(now);
+        }
+        self.pending_zoom *= factor;
+        self.zoom_anchor = self.cursor;
+        if self.smoothing.zoom_time_constant <= 0.0 {
+            self.apply_zoom(self.pending_zoom);
+        }
+    }
+
+    /// Zoom by `factor` about the pointer at once, as for a pinch, whose deltas are
+    /// already continuous.
+    pub fn pinch_zoom(&mut self, factor: f64) {
+        self.view = zoom_about(self.view, factor, self.cursor);
+    }
+
+    /// Change the scale factor of the display, keeping content at the same logical size
+    /// and position, as with [`rescale_device_pixels`].
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        let change = rescale_device_pixels(self.scale_factor, scale_factor);
+        self.view = change * self.view;
+        self.cursor = change * self.cursor;
+        self.zoom_anchor = change * self.zoom_anchor;
+        // The change is a uniform scale, so it scales velocities as it does points.
+        self.velocity = (change * self.velocity.to_point()).to_vec2();
+        self.scale_factor = scale_factor;
+    }
+
+    /// Advance smoothed zooms and inertial panning to time `now`.
+    ///
+    /// Returns whether the view is still moving, as [`is_animating`](Self::is_animating).
+    pub fn tick(&mut self, now: f64) -> bool {
+        let dt = self.last_tick.map_or(0.0, |t| (now - t).max(0.0));
+        self.last_tick = Some(now);
+
+        if self.pending_zoom != 1.0 {
+            let tc = self.smoothing.zoom_time_constant;
+            let share = if tc > 0.0 {
+                1.0 - E.powf(-dt / tc)
+            } else {
+                1.0
+            };
+            let step = self.pending_zoom.powf(share);
+            let rest = self.pending_zoom / step;
+            if (rest - 1.0).abs() < Self::ZOOM_EPSILON {
+                self.apply_zoom(self.pending_zoom);
+            } else {
+                self.apply_zoom(step);
+            }
+        }
+
+        if self.coasting && self.drag.is_none() {
+            let decay = self.smoothing.pan_decay;
+            let fall = E.powf(-dt / decay);
+            // The distance covered while the velocity decays exponentially over `dt`.
+            self.view = self
+                .view
+                .then_translate(self.velocity * (decay * (1.0 - fall)));
+            self.velocity *= fall;
+            if self.velocity.hypot() < self.smoothing.min_pan_speed.max(f64::EPSILON) {
+                self.coasting = false;
+                self.velocity = Vec2::ZERO;
+            }
+        }
+        self.is_animating()
+    }
+
+    /// Apply `factor` of the pending zoom.
+    fn apply_zoom(&mut self, factor: f64) {
+        self.view = zoom_about(self.view, factor, self.zoom_anchor);
+        self.pending_zoom /= factor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uniform_scale;
+
+    extern crate alloc;
+    use alloc::vec;
+
+    const FRAME: f64 = 1.0 / 60.0;
+
+    #[test]
+    fn released_drags_coast_to_a_stop() {
+        let mut vc = ViewController::new(Affine::IDENTITY, 1.0, InputSmoothing::default());
+        vc.drag_start(Point::new(0.0, 0.0), 0.0);
+        for i in 1..=6 {
+            let t = f64::from(i) * FRAME;
+            vc.pointer_move(Point::new(10.0 * f64::from(i), 0.0), t);
+        }
+        assert_eq!(
+            vc.view().translation(),
+            Vec2::new(60.0, 0.0),
+            "Dragging should pan with the pointer."
+        );
+        vc.drag_end(6.0 * FRAME);
+        assert!(vc.is_animating(), "A fast release should coast.");
+
+        let mut t = 6.0 * FRAME;
+        let mut last = vc.view().translation().x;
+        while vc.is_animating() {
+            t += FRAME;
+            vc.tick(t);
+            let x = vc.view().translation().x;
+            assert!(x > last, "Coasting should keep moving the same way.");
+            last = x;
+            assert!(t < 10.0, "Coasting should stop.");
+        }
+        assert_eq!(
+            vc.view().translation().y,
+            0.0,
+            "Coasting should follow the drag."
+        );
+
+        // A release after resting doesn't coast.
+        vc.drag_start(Point::new(0.0, 0.0), t);
+        vc.pointer_move(Point::new(50.0, 0.0), t + FRAME);
+        vc.drag_end(t + 1.0);
+        assert!(
+            !vc.is_animating(),
+            "Releasing after resting should not coast."
+        );
+    }
+
+    #[test]
+    fn wheel_zooms_are_smoothed_and_deterministic() {
+        let run = |smoothing| {
+            let mut vc = ViewController::new(Affine::IDENTITY, 2.0, smoothing);
+            vc.pointer_move(Point::new(100.0, 50.0), 0.0);
+            vc.wheel_zoom(2.0, 0.0);
+            vc.wheel_zoom(2.0, 0.0);
+            let mut scales = vec![uniform_scale(vc.view())];
+            let mut t = 0.0;
+            while vc.tick(t) {
+                t += FRAME;
+                scales.push(uniform_scale(vc.view()));
+            }
+            (vc.view(), scales)
+        };
+
+        let (smoothed, scales) = run(InputSmoothing::default());
+        assert!(
+            scales.len() > 3 && scales.windows(2).all(|w| w[1] >= w[0]),
+            "Smoothed zooms should grow over several frames, got {scales:?}."
+        );
+        let (immediate, _) = run(InputSmoothing::NONE);
+        assert!(
+            (uniform_scale(smoothed) - 4.0).abs() < 1e-9
+                && (uniform_scale(immediate) - 4.0).abs() < 1e-9,
+            "Both should end at the product of the zooms."
+        );
+        assert!(
+            (smoothed * Point::new(100.0, 50.0) - Point::new(100.0, 50.0)).hypot() < 1e-6,
+            "Smoothed zooms should stay anchored under the pointer."
+        );
+        assert_eq!(
+            run(InputSmoothing::default()).1,
+            scales,
+            "The same input and times should give the same frames."
+        );
+    }
+
+    #[test]
+    fn scale_factor_changes_keep_the_anchor() {
+        let mut vc = ViewController::new(Affine::scale(3.0), 1.0, InputSmoothing::NONE);
+        vc.pointer_move(Point::new(30.0, 60.0), 0.0);
+        let under = vc.view().inverse() * vc.cursor();
+        vc.set_scale_factor(1.5);
+        vc.wheel_zoom(1.25, 0.0);
+        assert!(
+            (vc.view() * under - Point::new(45.0, 90.0)).hypot() < 1e-9,
+            "The point under the pointer should stay there across the change."
+        );
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Alignment of one drawing over another.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use dxf::entities::EntityType;
+use tabulon::{
+    fit_similarity,
+    peniko::kurbo::{Affine, Point},
+};
+
+use crate::{DrawingInfo, point_from_dxf_point};
+
+impl DrawingInfo {
+    /// Pair the insertion points of blocks inserted exactly once in both drawings.
+    ///
+    /// Blocks that are inserted once, such as title blocks, north arrows, and column grid
+    /// markers, form a constellation of points that identifies how two revisions or
+    /// disciplines of a drawing line up. Anonymous blocks are skipped, because their
+    /// names are not stable between drawings.
+    ///
+    /// Pairs are `(self, other)` in model space, ordered by block name.
+    pub fn unique_insert_pairs(&self, other: &Self) -> Vec<(Point, Point)> {
+        let (ours, theirs) = (self.unique_inserts(), other.unique_inserts());
+        ours.iter()
+            .filter_map(|(name, p)| Some(((*p)?, (*theirs.get(name)?)?)))
+            .collect()
+    }
+
+    /// Find the similarity transform that registers this drawing over `other`.
+    ///
+    /// The transform is fitted to the [unique inserts](Self::unique_insert_pairs) of the
+    /// drawings. Pairs that disagree with the first fit by much more than is typical, such
+    /// as a block that was moved between revisions, are dropped before fitting again.
+    ///
+    /// Returns `None` if there are fewer than two pairs, or they do not determine a transform.
+    /// Use [`fit_similarity`] directly for points picked by the user.
+    pub fn align_to(&self, other: &Self) -> Option<Affine> {
+        let pairs = self.unique_insert_pairs(other);
+        if pairs.len() < 2 {
+            return None;
+        }
+        let fit = fit_similarity(&pairs)?;
+        if pairs.len() < 4 {
+            return Some(fit);
+        }
+
+        let residual = |(f, t): &(Point, Point)| (fit * *f - *t).hypot();
+        let mut residuals: Vec<f64> = pairs.iter().map(residual).collect();
+        residuals.sort_by(f64::total_cmp);
+        let median = residuals[residuals.len() / 2];
+        let inliers: Vec<(Point, Point)> = pairs
+            .iter()
+            .filter(|pair| residual(pair) <= median * 3.0 + f64::EPSILON)
+            .copied()
+            .collect();
+        if inliers.len() < 2 || inliers.len() == pairs.len() {
+            return Some(fit);
+        }
+        fit_similarity(&inliers).or(Some(fit))
+    }
+
+    /// Get the insertion point of each block name in model space, or `None` for names
+    /// that are inserted more than once.
+    fn unique_inserts(&self) -> BTreeMap<&str, Option<Point>> {
+        let mut inserts: BTreeMap<&str, Option<Point>> = BTreeMap::new();
+        for e in self.drawing.entities() {
+            let EntityType::Insert(ref ins) = e.specific else {
+                continue;
+            };
+            if ins.name.starts_with('*') {
+                continue;
+            }
+            inserts
+                .entry(ins.name.as_str())
+                .and_modify(|p| *p = None)
+                .or_insert(Some(point_from_dxf_point(&ins.location)));
+        }
+        inserts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_page::CodePage;
+    use dxf::{
+        Drawing,
+        entities::{Entity, Insert},
+    };
+    use tabulon::peniko::kurbo::Vec2;
+
+    /// Build a drawing with inserts at points in model space.
+    fn drawing(inserts: &[(&str, Point)]) -> DrawingInfo {
+        let mut drawing = Drawing::new();
+        for (name, p) in inserts {
+            drawing.add_entity(Entity::new(EntityType::Insert(Insert {
+                name: (*name).into(),
+                location: dxf::Point::new(p.x, -p.y, 0.0),
+                ..Default::default()
+            })));
+        }
+        DrawingInfo::new(drawing, CodePage::ANSI_1252)
+    }
+
+    #[test]
+    fn align_revisions() {
+        let truth = Affine::translate((100.0, 50.0)) * Affine::rotate(0.25) * Affine::scale(2.0);
+        let points = [
+            ("TITLE", Point::new(0.0, 0.0)),
+            ("NORTH", Point::new(40.0, 0.0)),
+            ("GRID-A", Point::new(0.0, 30.0)),
+            ("GRID-B", Point::new(40.0, 30.0)),
+            ("PUMP", Point::new(20.0, 15.0)),
+        ];
+        let ours = drawing(&points);
+        let mut moved: Vec<(&str, Point)> = points.iter().map(|(n, p)| (*n, truth * *p)).collect();
+        // The pump was moved in the other revision, and valves are not unique.
+        moved[4].1 += Vec2::new(25.0, -10.0);
+        moved.push(("VALVE", Point::new(1.0, 1.0)));
+        moved.push(("VALVE", Point::new(2.0, 2.0)));
+        let theirs = drawing(&moved);
+
+        assert_eq!(
+            ours.unique_insert_pairs(&theirs).len(),
+            5,
+            "Blocks inserted once in both drawings should be paired."
+        );
+        let fit = ours.align_to(&theirs).expect("The drawings should align.");
+        assert!(
+            fit.as_coeffs()
+                .iter()
+                .zip(truth.as_coeffs())
+                .all(|(x, y)| (x - y).abs() < 1e-9),
+            "Moved blocks should not disturb the alignment, got {fit:?}."
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Bookmarks and comments anchored to entities.
+
+extern crate alloc;
+use alloc::string::String;
+
+use core::num::NonZeroU64;
+
+use dxf::{DrawingItem, Handle, entities::EntityType};
+use tabulon::peniko::kurbo::{Point, Rect, Vec2};
+
+use crate::{EntityHandle, TDDrawing, point_from_dxf_point};
+
+/// A point anchored to an entity.
+///
+/// The anchor is the entity's [stable ID](EntityHandle::stable_id), so the point can be
+/// saved separately from the drawing and resolved again after it is reloaded or edited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Anchor {
+    /// Stable ID of the anchor entity.
+    pub entity: u64,
+    /// Offset from the anchor point of the entity, in the same coordinates as loaded items.
+    pub offset: Vec2,
+}
+
+impl Anchor {
+    /// Anchor `position` to an entity.
+    ///
+    /// Returns `None` if the entity has no anchor point.
+    pub fn new(drawing: &TDDrawing, entity: EntityHandle, position: Point) -> Option<Self> {
+        Some(Self {
+            entity: entity.stable_id(),
+            offset: position - drawing.entity_anchor(entity)?,
+        })
+    }
+
+    /// Get the position in `drawing`.
+    ///
+    /// Returns `None` if the anchor entity is no longer in the drawing.
+    pub fn resolve(&self, drawing: &TDDrawing) -> Option<Point> {
+        Some(drawing.entity_anchor(drawing.entity_by_stable_id(self.entity)?)? + self.offset)
+    }
+}
+
+/// A bookmark or comment anchored to an entity.
+///
+/// The anchor is the entity's [stable ID](EntityHandle::stable_id), so bookmarks can be
+/// saved separately from the drawing and resolved again after it is reloaded or edited.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bookmark {
+    /// Stable ID of the anchor entity.
+    pub entity: u64,
+    /// Offset from the anchor point of the entity, in the same coordinates as loaded items.
+    pub offset: Vec2,
+    /// Label or comment text.
+    pub text: String,
+}
+
+impl Bookmark {
+    /// Create a bookmark at `position`, anchored to an entity.
+    ///
+    /// Returns `None` if the entity has no anchor point.
+    pub fn new(
+        drawing: &TDDrawing,
+        entity: EntityHandle,
+        position: Point,
+        text: impl Into<String>,
+    ) -> Option<Self> {
+        let Anchor { entity, offset } = Anchor::new(drawing, entity, position)?;
+        Some(Self {
+            entity,
+            offset,
+            text: text.into(),
+        })
+    }
+
+    /// Get the position of the bookmark in `drawing`.
+    ///
+    /// Returns `None` if the anchor entity is no longer in the drawing.
+    pub fn resolve(&self, drawing: &TDDrawing) -> Option<Point> {
+        Anchor {
+            entity: self.entity,
+            offset: self.offset,
+        }
+        .resolve(drawing)
+    }
+}
+
+impl TDDrawing {
+    /// Get the handle of the entity with a [stable ID](EntityHandle::stable_id),
+    /// if it is in the drawing.
+    pub fn entity_by_stable_id(&self, id: u64) -> Option<EntityHandle> {
+        let h = NonZeroU64::new(id)?;
+        matches!(
+            self.info.drawing.item_by_handle(Handle(id)),
+            Some(DrawingItem::Entity(_))
+        )
+        .then_some(EntityHandle(h))
+    }
+
+    /// Get the point that bookmarks on an entity are anchored to.
+    ///
+    /// This is the insertion point, center, or start point for entities that have one,
+    /// and otherwise the center of the entity's [extent](TDDrawing::entity_extents).
+    pub fn entity_anchor(&self, eh: EntityHandle) -> Option<Point> {
+        let e = self.info.get_entity(eh);
+        let p = match e.specific {
+            EntityType::Insert(ref ins) => &ins.location,
+            EntityType::Text(ref t) => &t.location,
+            EntityType::MText(ref mt) => &mt.insertion_point,
+            EntityType::Circle(ref c) => &c.center,
+            EntityType::Arc(ref a) => &a.center,
+            EntityType::Ellipse(ref el) => &el.center,
+            EntityType::Line(ref l) => &l.p1,
+            EntityType::ModelPoint(ref mp) => &mp.location,
+            _ => return self.entity_extents.get(&eh).map(Rect::center),
+        };
+        Some(point_from_dxf_point(p))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::load_drawing;
+    use dxf::{
+        Drawing,
+        entities::{Entity, Line},
+    };
+
+    #[test]
+    fn survives_reload() {
+        let mut drawing = Drawing::new();
+        let id = drawing
+            .add_entity(Entity::new(EntityType::Line(Line::new(
+                dxf::Point::new(1.0, 2.0, 0.0),
+                dxf::Point::new(5.0, 2.0, 0.0),
+            ))))
+            .common
+            .handle
+            .0;
+
+        let td = load_drawing("anchor_survives_reload", &drawing);
+        let eh = td.entity_by_stable_id(id).unwrap();
+        let bookmark = Bookmark::new(&td, eh, Point::new(2.0, -3.0), "Check this").unwrap();
+        assert_eq!(
+            bookmark.resolve(&td),
+            Some(Point::new(2.0, -3.0)),
+            "A bookmark should resolve where it was placed."
+        );
+
+        // Move the line and reload.
+        for e in drawing.entities_mut() {
+            if let EntityType::Line(ref mut l) = e.specific {
+                l.p1 = dxf::Point::new(11.0, 2.0, 0.0);
+            }
+        }
+        assert_eq!(
+            bookmark.resolve(&load_drawing("anchor_survives_reload", &drawing)),
+            Some(Point::new(12.0, -3.0)),
+            "A bookmark should follow its entity after reloading."
+        );
+        assert_eq!(
+            bookmark.resolve(&load_drawing("anchor_survives_reload", &Drawing::new())),
+            None,
+            "A bookmark on a missing entity should not resolve."
+        );
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Angles in the units and conventions of a drawing.
+//!
+//! Drawings choose where angles are measured from (`$ANGBASE`), which way they
+//! increase (`$ANGDIR`), and how they are displayed (`$AUNITS` and `$AUPREC`), so
+//! angles reported to users should go through [`AngleUnits`] rather than being taken
+//! from loaded coordinates, whose y axis is also flipped from the drawing's.
+
+extern crate alloc;
+use alloc::{format, string::String};
+
+use core::f64::consts::TAU;
+
+use dxf::{
+    Header,
+    enums::{AngleDirection, AngleFormat},
+};
+use tabulon::peniko::kurbo::Vec2;
+
+use crate::TDDrawing;
+
+/// How a drawing measures and displays angles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AngleUnits {
+    /// Display format, from `$AUNITS`.
+    pub format: AngleFormat,
+    /// Number of decimal places displayed, from `$AUPREC`.
+    ///
+    /// For degrees, minutes, and seconds, and for surveyor's units, this selects
+    /// whether minutes and seconds are shown, as it does in CAD applications.
+    pub precision: u8,
+    /// Direction of angle zero, counterclockwise from the drawing's x axis in
+    /// radians, from `$ANGBASE`.
+    pub base: f64,
+    /// Whether angles increase clockwise, from `$ANGDIR`.
+    pub clockwise: bool,
+}
+
+impl Default for AngleUnits {
+    /// Decimal degrees, counterclockwise from the x axis.
+    fn default() -> Self {
+        Self {
+            format: AngleFormat::DecimalDegrees,
+            precision: 0,
+            base: 0.0,
+            clockwise: false,
+        }
+    }
+}
+
+impl AngleUnits {
+    /// Read the angle settings of a drawing header.
+    pub fn from_header(header: &Header) -> Self {
+        Self {
+            format: header.angle_unit_format,
+            precision: header.angle_unit_precision.clamp(0, 8).try_into().unwrap(),
+            base: header.angle_zero_direction.to_radians(),
+            clockwise: header.angle_direction == AngleDirection::Clockwise,
+        }
+    }
+
+    /// Get the angle of a direction in loaded coordinates, as the drawing measures it.
+    ///
+    /// The result is in radians from 0 to 2π, measured from `$ANGBASE` in the direction
+    /// of `$ANGDIR`.
+    pub fn direction_angle(&self, direction: Vec2) -> f64 {
+        // Loaded coordinates have the y axis of the drawing flipped.
+        self.sweep((-direction.y).atan2(direction.x) - self.base)
+            .rem_euclid(TAU)
+    }
+
+    /// Get the angle swept from direction `from` to direction `to` in loaded
+    /// coordinates, turning the way the drawing's angles increase.
+    ///
+    /// The result is in radians from 0 to 2π.
+    pub fn angle_between(&self, from: Vec2, to: Vec2) -> f64 {
+        // Counterclockwise in the drawing is clockwise in loaded coordinates.
+        let counterclockwise = -from.cross(to).atan2(from.dot(to));
+        self.sweep(counterclockwise).rem_euclid(TAU)
+    }
+
+    /// Convert a counterclockwise angle to one in the direction of `$ANGDIR`.
+    fn sweep(&self, counterclockwise: f64) -> f64 {
+        if self.clockwise {
+            -counterclockwise
+        } else {
+            counterclockwise
+        }
+    }
+
+    /// Format an angle in radians, such as one from [`angle_between`](Self::angle_between).
+    ///
+    /// Surveyor's units describe directions rather than angles, so angles are formatted
+    /// in degrees, minutes, and seconds with them; use
+    /// [`format_direction`](Self::format_direction) for bearings.
+    pub fn format(&self, angle: f64) -> String {
+        let p = usize::from(self.precision);
+        match self.format {
+            AngleFormat::DecimalDegrees => format!("{:.p$}°", angle.to_degrees()),
+            AngleFormat::Gradians => format!("{:.p$}g", angle.to_degrees() / 0.9),
+            AngleFormat::Radians => format!("{angle:.p$}r"),
+            AngleFormat::DegreesMinutesSeconds | AngleFormat::SurveyorsUnits => {
+                dms(angle.to_degrees(), self.precision)
+            }
+        }
+    }
+
+    /// Format a direction in loaded coordinates, as the drawing displays directions.
+    ///
+    /// With surveyor's units, this is a bearing such as `N 45d0' E`, which is measured
+    /// from north or south towards east or west whatever `$ANGBASE` and `$ANGDIR` are.
+    /// Otherwise it is the [`direction_angle`](Self::direction_angle), formatted.
+    pub fn format_direction(&self, direction: Vec2) -> String {
+        if self.format != AngleFormat::SurveyorsUnits {
+            return self.format(self.direction_angle(direction));
+        }
+        // North is up in the drawing, which is down in loaded coordinates.
+        let (east, north) = (direction.x, -direction.y);
+        let ns = if north >= 0.0 { 'N' } else { 'S' };
+        let ew = if east >= 0.0 { 'E' } else { 'W' };
+        if east == 0.0 {
+            return String::from(ns);
+        }
+        if north == 0.0 {
+            return String::from(ew);
+        }
+        let bearing = east.abs().atan2(north.abs());
+        format!("{ns} {} {ew}", dms(bearing.to_degrees(), self.precision))
+    }
+}
+
+/// Format degrees as degrees, minutes, and seconds, with the fields chosen by `precision`.
+fn dms(degrees: f64, precision: u8) -> String {
+    let sign = if degrees < 0.0 { "-" } else { "" };
+    let degrees = degrees.abs();
+    match precision {
+        0 => format!("{sign}{}d", degrees.round()),
+        1 | 2 => {
+            let minutes = (degrees * 60.0).round();
+            format!("{sign}{}d{}'", (minutes / 60.0).floor(), minutes % 60.0)
+        }
+        3 | 4 => {
+            let seconds = (degrees * 3600.0).round();
+            format!(
+                "{sign}{}d{}'{}\"",
+                (seconds / 3600.0).floor(),
+                (seconds / 60.0).floor() % 60.0,
+                seconds % 60.0
+            )
+        }
+        _ => {
+            let p = usize::from(precision - 4);
+            let scale = 10_f64.powi((precision - 4).into());
+            let seconds = (degrees * 3600.0 * scale).round() / scale;
+            let whole = seconds.floor();
+            format!(
+                "{sign}{}d{}'{:.p$}\"",
+                (whole / 3600.0).floor(),
+                (whole / 60.0).floor() % 60.0,
+                seconds - (whole / 60.0).floor() * 60.0
+            )
+        }
+    }
+}
+
+impl TDDrawing {
+    /// Get how the drawing measures and displays angles.
+    pub fn angle_units(&self) -> AngleUnits {
+        AngleUnits::from_header(&self.info.drawing.header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clockwise_from_north() {
+        let units = AngleUnits::from_header(&Header {
+            angle_zero_direction: 90.0,
+            angle_direction: AngleDirection::Clockwise,
+            angle_unit_format: AngleFormat::DegreesMinutesSeconds,
+            angle_unit_precision: 4,
+            ..Default::default()
+        });
+
+        // East in the drawing is +x, and north is -y in loaded coordinates.
+        let (east, north) = (Vec2::new(1.0, 0.0), Vec2::new(0.0, -1.0));
+        assert!(
+            units.direction_angle(north).abs() < 1e-12,
+            "Angles should be measured from $ANGBASE."
+        );
+        assert_eq!(
+            units.format_direction(east),
+            "90d0'0\"",
+            "Angles should increase clockwise with $ANGDIR."
+        );
+        assert_eq!(
+            units.format(units.angle_between(east, north)),
+            "270d0'0\"",
+            "Angles between directions should turn the way angles increase."
+        );
+
+        let surveyor = AngleUnits {
+            format: AngleFormat::SurveyorsUnits,
+            precision: 2,
+            ..units
+        };
+        assert_eq!(
+            surveyor.format_direction(Vec2::new(1.0, 1.0)),
+            "S 45d0' E",
+            "Bearings should be measured from north or south."
+        );
+        let grads = AngleUnits {
+            format: AngleFormat::Gradians,
+            precision: 1,
+            ..AngleUnits::default()
+        };
+        assert_eq!(
+            grads.format_direction(north),
+            "100.0g",
+            "Gradians should divide a right angle into 100."
+        );
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Search of block attributes attached to inserts.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use core::num::NonZeroU64;
+
+use dxf::entities::{Attribute, EntityType};
+use tabulon::peniko::kurbo::Point;
+
+use crate::{
+    DrawingInfo, EntityHandle, point_from_dxf_point,
+    text_codes::{TextFlavor, decode_text},
+};
+
+/// An insert with an attribute matching a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeMatch {
+    /// The INSERT entity.
+    pub entity: EntityHandle,
+    /// Name of the inserted block.
+    pub block_name: String,
+    /// Tag of the matching attribute.
+    pub tag: String,
+    /// Decoded value of the matching attribute.
+    pub value: String,
+    /// Insertion point of the insert, in the same coordinates as loaded items.
+    pub position: Point,
+}
+
+impl DrawingInfo {
+    /// Get the attributes of an INSERT as pairs of tag and decoded value.
+    ///
+    /// Returns nothing if `eh` is not an INSERT.
+    pub fn insert_attributes(&self, eh: EntityHandle) -> Vec<(String, String)> {
+        let EntityType::Insert(ref ins) = self.get_entity(eh).specific else {
+            return Vec::new();
+        };
+        ins.attributes()
+            .map(|a| (a.attribute_tag.clone(), self.attribute_value(a)))
+            .collect()
+    }
+
+    /// Find model space inserts with an attribute named `tag` whose value matches `value_pattern`.
+    ///
+    /// Tags and values are compared ignoring ASCII case. In `value_pattern`, `*` matches any
+    /// number of characters and `?` matches exactly one, so `V-1*` finds `V-101` and `v-102`.
+    pub fn find_inserts_with_attribute(
+        &self,
+        tag: &str,
+        value_pattern: &str,
+    ) -> Vec<AttributeMatch> {
+        let pattern: Vec<char> = value_pattern.chars().collect();
+        let mut matches = Vec::new();
+        for e in self.drawing.entities() {
+            let EntityType::Insert(ref ins) = e.specific else {
+                continue;
+            };
+            let Some(handle) = NonZeroU64::new(e.common.handle.0) else {
+                continue;
+            };
+            for a in ins.attributes() {
+                if !a.attribute_tag.eq_ignore_ascii_case(tag) {
+                    continue;
+                }
+                let value = self.attribute_value(a);
+                let text: Vec<char> = value.chars().collect();
+                if wildcard_match(&pattern, &text) {
+                    matches.push(AttributeMatch {
+                        entity: EntityHandle(handle),
+                        block_name: ins.name.clone(),
+                        tag: a.attribute_tag.clone(),
+                        value,
+                        position: point_from_dxf_point(&ins.location),
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    /// Decode the value of an attribute.
+    pub(crate) fn attribute_value(&self, a: &Attribute) -> String {
+        decode_text(&a.value, TextFlavor::Text, |b| {
+            self.code_page.decode_byte(b)
+        })
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters and `?` matches one.
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    // Greedy matching, backtracking to the most recent `*`.
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c.eq_ignore_ascii_case(&text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => {
+                let Some((sp, st)) = star else {
+                    return false;
+                };
+                // Let the `*` consume one more character.
+                star = Some((sp, st + 1));
+                p = sp + 1;
+                t = st + 1;
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        wildcard_match(&pattern, &text)
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(
+            matches("V-101", "v-101"),
+            "Literals should ignore ASCII case."
+        );
+        assert!(matches("V-1*", "V-102A"), "`*` should match a run.");
+        assert!(matches("*", ""), "`*` should match nothing.");
+        assert!(matches("V-?0?", "V-101"), "`?` should match one character.");
+        assert!(matches("*-*1", "P-V-101"), "`*` should backtrack.");
+        assert!(
+            !matches("V-?", "V-10"),
+            "`?` should not match two characters."
+        );
+        assert!(
+            !matches("V-101", "V-1011"),
+            "Patterns should match the whole value."
+        );
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Resolution of block definitions into shared chunks of geometry.
+//!
+//! Each block is resolved once, and its geometry is shared by every INSERT of it,
+//! which only adds transforms and binds BYBLOCK values.
+//!
+//! Blocks are resolved in topological order of their references, so every block
+//! an INSERT refers to is resolved before the block containing the INSERT.
+//! Blocks in the same wave of the ordering do not depend on each other, and are
+//! resolved in parallel when the `parallel` feature is enabled. Results are collected
+//! in the order of the wave, so they don't depend on how the work was scheduled.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+
+use dxf::{Block, Drawing, entities::EntityType, tables::Layer};
+use tabulon::{
+    peniko::kurbo::{Affine, BezPath},
+    shape::AnyShape,
+};
+
+use crate::{
+    dynamic_block::Representations, insert_transforms, paint::SOLID_FILL, path_from_entity,
+    recover_color_enum,
+};
+
+/// Part of a resolved block, in drawing order.
+///
+/// BYBLOCK line weight and color enumeration values are kept, to be bound when the
+/// block is inserted.
+#[derive(Clone)]
+pub(crate) enum Part {
+    /// Geometry with contiguous line weight and color enumeration values.
+    Chunk(i16, i16, Arc<AnyShape>),
+    /// Instances of another block, with the line weight and color enumeration values
+    /// that its BYBLOCK values bind to.
+    Insert(i16, i16, Vec<Affine>, Parts),
+}
+
+/// Parts of a resolved block, shared by every insert of it.
+pub(crate) type Parts = Arc<[Part]>;
+
+/// Append a path to parts as a chunk, unless it is empty.
+fn push_chunk(parts: &mut Vec<Part>, (lw, color): (i16, i16), path: BezPath) {
+    if !path.is_empty() {
+        parts.push(Part::Chunk(lw, color, Arc::new(path.into())));
+    }
+}
+
+/// Visit the chunks of an instance of a block in drawing order.
+///
+/// BYBLOCK values bind to `by_block`, and `f` gets the transform from block
+/// coordinates of the instance that each chunk is drawn for.
+pub(crate) fn for_each_chunk(
+    parts: &[Part],
+    transform: Affine,
+    by_block: (i16, i16),
+    f: &mut impl FnMut(Affine, i16, i16, &Arc<AnyShape>),
+) {
+    let bind = |lw: i16, color: i16| {
+        (
+            if lw == -1 { by_block.0 } else { lw },
+            if color == 0 { by_block.1 } else { color },
+        )
+    };
+    for part in parts {
+        match part {
+            Part::Chunk(lw, color, shape) => {
+                let (lw, color) = bind(*lw, *color);
+                f(transform, lw, color, shape);
+            }
+            Part::Insert(lw, color, transforms, parts) => {
+                let by_block = bind(*lw, *color);
+                for t in transforms {
+                    for_each_chunk(parts, transform * *t, by_block, f);
+                }
+            }
+        }
+    }
+}
+
+/// Context for resolving the blocks of a drawing.
+struct Resolver<'a> {
+    layers: BTreeMap<&'a str, &'a Layer>,
+    representations: &'a Representations<'a>,
+}
+
+impl<'a> Resolver<'a> {
+    /// Resolve BYLAYER line weights and colors for an entity on `layer`.
+    fn resolve_style(&self, layer: &str, lw: i16, ce: i16) -> (i16, i16) {
+        let layer = self.layers[layer];
+        let line_weight = if lw == -2 {
+            if layer.line_weight.raw_value() < 0 {
+                25_i16
+            } else {
+                layer.line_weight.raw_value()
+            }
+        } else {
+            lw
+        };
+        let color = if ce == 256 {
+            // BYLAYER: resolve to a palette value during block resolution.
+            if let Some(i) = layer.color.index() {
+                i as i16
+            } else {
+                // white if layer doesn't have a resolvable color.
+                7_i16
+            }
+        } else {
+            ce
+        };
+
+        (line_weight, color)
+    }
+
+    /// Names of the blocks referenced by INSERTs directly in `b`.
+    fn dependencies(&self, b: &'a Block) -> impl Iterator<Item = &'a str> + '_ {
+        b.entities.iter().filter_map(|e| match e.specific {
+            EntityType::Insert(ref ins) => Some(self.representations.insert_block(e, ins)),
+            _ => None,
+        })
+    }
+
+    /// Form up the parts of a block whose dependencies are all in `resolved`.
+    fn resolve_block(&self, b: &Block, resolved: &BTreeMap<&str, Parts>) -> Parts {
+        // Form up shapes with contiguous line weight and color.
+        let mut lines = BezPath::new();
+        // Chunk blocks by the combination of line weight and color.
+        // To retain drawing order, multiple chunks may be emitted for a single block.
+        let mut parts: Vec<Part> = vec![];
+        let Some(first) = b.entities.first() else {
+            return parts.into();
+        };
+
+        let mut cur_style = self.resolve_style(
+            first.common.layer.as_str(),
+            first.common.lineweight_enum_value,
+            recover_color_enum(&first.common.color),
+        );
+
+        for e in b.entities.iter() {
+            let style = self.resolve_style(
+                e.common.layer.as_str(),
+                if matches!(e.specific, EntityType::Solid(..)) {
+                    SOLID_FILL
+                } else {
+                    e.common.lineweight_enum_value
+                },
+                recover_color_enum(&e.common.color),
+            );
+            if style != cur_style {
+                push_chunk(&mut parts, cur_style, core::mem::take(&mut lines));
+                cur_style = style;
+            }
+
+            match e.specific {
+                EntityType::Insert(ref ins) => {
+                    // FIXME: currently only support viewing from +Z.
+                    if ins.extrusion_direction.z != 1.0 {
+                        continue;
+                    }
+                    // TODO: Clip nested inserts with XCLIP boundaries.
+                    if let Some(b) = resolved.get(self.representations.insert_block(e, ins)) {
+                        // Retain drawing order by ending the current chunk before the insert.
+                        push_chunk(&mut parts, cur_style, core::mem::take(&mut lines));
+
+                        // Refer to the shared parts of the block, with a transform for each
+                        // arrayed instance, binding its BYBLOCK values to this insert.
+                        parts.push(Part::Insert(
+                            cur_style.0,
+                            cur_style.1,
+                            insert_transforms(ins).collect(),
+                            b.clone(),
+                        ));
+                    }
+                }
+                _ => {
+                    if let Some(s) = path_from_entity(e) {
+                        lines.extend(s);
+                    }
+                }
+            }
+        }
+        push_chunk(&mut parts, cur_style, lines);
+        parts.into()
+    }
+}
+
+/// Resolve the blocks of a drawing into parts, keyed by block name.
+///
+/// References to blocks that do not exist are skipped. Blocks that refer to
+/// themselves through any chain of INSERTs are left out, along with every block
+/// that refers to them.
+pub(crate) fn resolve_blocks<'a>(
+    drawing: &'a Drawing,
+    representations: &'a Representations<'a>,
+) -> BTreeMap<&'a str, Parts> {
+    let resolver = Resolver {
+        layers: drawing.layers().map(|l| (l.name.as_str(), l)).collect(),
+        representations,
+    };
+    let by_name: BTreeMap<&str, &Block> = drawing.blocks().map(|b| (b.name.as_str(), b)).collect();
+
+    // Count the unresolved dependencies of each block, and note which blocks depend on each.
+    // Repeated references are counted once per INSERT, and released once per INSERT.
+    let mut pending: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut dependents: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (&name, &b) in by_name.iter() {
+        let mut count = 0;
+        for dependency in resolver.dependencies(b) {
+            if by_name.contains_key(dependency) {
+                count += 1;
+                dependents.entry(dependency).or_default().push(name);
+            }
+        }
+        pending.insert(name, count);
+    }
+
+    let mut resolved: BTreeMap<&str, Parts> = BTreeMap::new();
+    let mut wave: Vec<&str> = pending
+        .iter()
+        .filter_map(|(&name, &count)| (count == 0).then_some(name))
+        .collect();
+    while !wave.is_empty() {
+        let parts = resolve_wave(&resolver, &by_name, &wave, &resolved);
+        let mut next = vec![];
+        for (name, parts) in wave.iter().zip(parts) {
+            resolved.insert(name, parts);
+            for dependent in dependents.get(name).into_iter().flatten() {
+                let count = pending.get_mut(dependent).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    next.push(*dependent);
+                }
+            }
+        }
+        wave = next;
+    }
+
+    resolved
+}
+
+/// Resolve a wave of blocks whose dependencies are all resolved, in order.
+#[cfg(not(feature = "parallel"))]
+fn resolve_wave(
+    resolver: &Resolver<'_>,
+    by_name: &BTreeMap<&str, &Block>,
+    wave: &[&str],
+    resolved: &BTreeMap<&str, Parts>,
+) -> Vec<Parts> {
+    wave.iter()
+        .map(|name| resolver.resolve_block(by_name[name], resolved))
+        .collect()
+}
+
+/// Resolve a wave of blocks whose dependencies are all resolved, in order.
+#[cfg(feature = "parallel")]
+fn resolve_wave(
+    resolver: &Resolver<'_>,
+    by_name: &BTreeMap<&str, &Block>,
+    wave: &[&str],
+    resolved: &BTreeMap<&str, Parts>,
+) -> Vec<Parts> {
+    use rayon::prelude::*;
+    wave.par_iter()
+        .map(|name| resolver.resolve_block(by_name[name], resolved))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::ObjectIndex;
+    use dxf::{
+        Color, Point,
+        entities::{Entity, Insert, Line},
+    };
+
+    fn line() -> Entity {
+        Entity::new(EntityType::Line(Line::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )))
+    }
+
+    fn colored(e: Entity, index: u8) -> Entity {
+        let mut e = e;
+        e.common.color = Color::from_index(index);
+        e
+    }
+
+    fn insert(name: &str) -> Entity {
+        Entity::new(EntityType::Insert(Insert {
+            name: name.into(),
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn nested_and_cyclic_blocks() {
+        let mut drawing = Drawing::new();
+        for (name, entities) in [
+            // Referenced before it is defined, and by several blocks.
+            (
+                "Outer",
+                vec![
+                    line(),
+                    insert("Inner"),
+                    colored(insert("ByBlock"), 1),
+                    line(),
+                ],
+            ),
+            ("Inner", vec![colored(line(), 1)]),
+            ("ByBlock", vec![colored(line(), 0)]),
+            ("Missing", vec![insert("Nowhere"), line()]),
+            ("Cycle", vec![insert("Cycle"), line()]),
+            ("UsesCycle", vec![insert("Cycle")]),
+        ] {
+            drawing.add_block(Block {
+                name: name.into(),
+                entities,
+                ..Default::default()
+            });
+        }
+        let objects = ObjectIndex::new(&drawing);
+        let representations = Representations::new(&drawing, &objects);
+        let blocks = resolve_blocks(&drawing, &representations);
+
+        let mut colors = vec![];
+        for_each_chunk(
+            &blocks["Outer"],
+            Affine::IDENTITY,
+            (-3, 3),
+            &mut |_, _, color, _| colors.push(color),
+        );
+        assert_eq!(
+            colors,
+            [7, 1, 1, 7],
+            "Chunks should retain drawing order, with BYBLOCK bound to the insert."
+        );
+        assert!(
+            matches!(
+                &blocks["Outer"][1],
+                Part::Insert(_, _, _, inner) if Arc::ptr_eq(inner, &blocks["Inner"])
+            ),
+            "Nested inserts should share the parts of their block."
+        );
+        assert_eq!(
+            blocks["Missing"].len(),
+            1,
+            "References to missing blocks should be skipped."
+        );
+        assert!(
+            !blocks.contains_key("Cycle") && !blocks.contains_key("UsesCycle"),
+            "Recursive blocks and their dependents should be left out."
+        );
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Code pages for text in legacy drawings.
+
+use dxf::Drawing;
+
+#[cfg(feature = "encoding")]
+use dxf::enums::AcadVersion;
+#[cfg(feature = "encoding")]
+use encoding_rs::Encoding;
+#[cfg(all(feature = "encoding", feature = "std"))]
+use {
+    alloc::{borrow::ToOwned, string::String},
+    std::io::BufRead,
+};
+
+use crate::text_codes::decode_ansi_1252;
+
+/// Code page used to decode text in a drawing.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CodePage {
+    #[cfg(feature = "encoding")]
+    encoding: &'static Encoding,
+}
+
+impl CodePage {
+    /// Windows-1252, the code page the `dxf` crate decodes legacy text with.
+    pub(crate) const ANSI_1252: Self = Self {
+        #[cfg(feature = "encoding")]
+        encoding: encoding_rs::WINDOWS_1252,
+    };
+
+    /// Get the code page named by a drawing's `$DWGCODEPAGE`.
+    ///
+    /// Returns `None` if the code page is not recognized, or is not
+    /// Windows-1252 when the `encoding` feature is disabled.
+    pub(crate) fn for_drawing(drawing: &Drawing) -> Option<Self> {
+        let name = drawing.header.drawing_code_page.as_str();
+        #[cfg(feature = "encoding")]
+        if let Some(encoding) = encoding_for_code_page(name) {
+            return Some(Self { encoding });
+        }
+        if !name.is_empty() && !name.eq_ignore_ascii_case("ANSI_1252") {
+            tracing::warn!(code_page = name, "unsupported drawing code page");
+            return None;
+        }
+        Some(Self::ANSI_1252)
+    }
+
+    /// Check whether text in `drawing` needs to be reloaded with this code page.
+    ///
+    /// Text in drawings from R2007 onward is always UTF-8.
+    #[cfg(feature = "encoding")]
+    pub(crate) fn needs_reload(self, drawing: &Drawing) -> bool {
+        drawing.header.version < AcadVersion::R2007 && self.encoding != encoding_rs::WINDOWS_1252
+    }
+
+    /// The encoding for this code page.
+    #[cfg(feature = "encoding")]
+    pub(crate) fn encoding(self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Decode a single byte, as in `%%nnn` character codes.
+    pub(crate) fn decode_byte(self, b: u8) -> char {
+        #[cfg(feature = "encoding")]
+        if self.encoding != encoding_rs::WINDOWS_1252 {
+            let bytes = [b];
+            let (s, _) = self.encoding.decode_without_bom_handling(&bytes);
+            return s.chars().next().unwrap_or(char::REPLACEMENT_CHARACTER);
+        }
+        decode_ansi_1252(b)
+    }
+}
+
+/// Read the header variables that decide how text is decoded from an ASCII DXF,
+/// without parsing the rest of it.
+///
+/// Lines are read up to the end of the header section, and `$ACADVER` and
+/// `$DWGCODEPAGE` are set in an otherwise empty drawing, so that a drawing in a legacy
+/// code page can be parsed once, with the right encoding.
+///
+/// Returns `None` if the header can't be read this way, such as for binary DXF, in
+/// which case the drawing has to be loaded to find its code page.
+#[cfg(all(feature = "encoding", feature = "std"))]
+pub(crate) fn read_header(reader: impl BufRead) -> Option<Drawing> {
+    let mut drawing = Drawing::new();
+    let mut lines = reader.split(b'\n');
+    let mut variable = String::new();
+    loop {
+        let code = lines.next()?.ok()?;
+        let code: i32 = core::str::from_utf8(&code).ok()?.trim().parse().ok()?;
+        let value = lines.next()?.ok()?;
+        let value = String::from_utf8_lossy(&value);
+        let value = value.trim();
+        match (code, value) {
+            // Only the header is read, and a drawing may not have one.
+            (0, "ENDSEC" | "EOF") => return Some(drawing),
+            (2, section) if section != "HEADER" => return Some(drawing),
+            (9, name) => name.clone_into(&mut variable),
+            (1, version) if variable == "$ACADVER" => {
+                drawing.header.version =
+                    AcadVersion::from(version.to_owned()).unwrap_or(AcadVersion::R12);
+            }
+            (3, name) if variable == "$DWGCODEPAGE" => {
+                name.clone_into(&mut drawing.header.drawing_code_page);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Get the encoding for a `$DWGCODEPAGE` value.
+#[cfg(feature = "encoding")]
+fn encoding_for_code_page(name: &str) -> Option<&'static Encoding> {
+    use encoding_rs::*;
+    let name = name.to_ascii_uppercase();
+    Some(match name.as_str() {
+        "ANSI_874" => WINDOWS_874,
+        "ANSI_932" | "DOS932" => SHIFT_JIS,
+        "ANSI_936" | "GB2312" => GBK,
+        "ANSI_949" | "KSC5601" => EUC_KR,
+        "ANSI_950" | "BIG5" => BIG5,
+        "ANSI_1250" => WINDOWS_1250,
+        "ANSI_1251" => WINDOWS_1251,
+        "ANSI_1252" | "ISO8859-1" => WINDOWS_1252,
+        "ANSI_1253" => WINDOWS_1253,
+        "ANSI_1254" | "ISO8859-9" => WINDOWS_1254,
+        "ANSI_1255" => WINDOWS_1255,
+        "ANSI_1256" => WINDOWS_1256,
+        "ANSI_1257" => WINDOWS_1257,
+        "ANSI_1258" => WINDOWS_1258,
+        "DOS866" => IBM866,
+        "ISO8859-2" => ISO_8859_2,
+        "ISO8859-3" => ISO_8859_3,
+        "ISO8859-4" => ISO_8859_4,
+        "ISO8859-5" => ISO_8859_5,
+        "ISO8859-6" => ISO_8859_6,
+        "ISO8859-7" => ISO_8859_7,
+        "ISO8859-8" => ISO_8859_8,
+        "ISO8859-10" => ISO_8859_10,
+        "ISO8859-13" => ISO_8859_13,
+        "ISO8859-14" => ISO_8859_14,
+        "ISO8859-15" => ISO_8859_15,
+        "ISO8859-16" => ISO_8859_16,
+        "KOI8-R" => KOI8_R,
+        "KOI8-U" => KOI8_U,
+        "MACINTOSH" => MACINTOSH,
+        _ => return None,
+    })
+}
+
+#[cfg(all(test, feature = "encoding"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_code_page() {
+        let mut drawing = Drawing::new();
+        drawing.header.drawing_code_page = "ANSI_1251".into();
+        let code_page = CodePage::for_drawing(&drawing).unwrap();
+        assert!(
+            code_page.needs_reload(&drawing),
+            "R12 drawings in other code pages should be reloaded."
+        );
+        assert_eq!(
+            code_page.decode_byte(192),
+            'А',
+            "Character codes should use the drawing code page."
+        );
+
+        drawing.header.version = AcadVersion::R2007;
+        assert!(
+            !code_page.needs_reload(&drawing),
+            "R2007 drawings are always UTF-8."
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn header_only() {
+        let header = read_header(
+            &b"  0\r\nSECTION\r\n  2\r\nHEADER\r\n  9\r\n$ACADVER\r\n  1\r\nAC1015\r\n  \
+               9\r\n$DWGCODEPAGE\r\n  3\r\nANSI_1251\r\n  0\r\nENDSEC\r\n  0\r\n\xC0"[..],
+        )
+        .expect("The header of an ASCII DXF should be read.");
+        assert_eq!(
+            (
+                header.header.version,
+                header.header.drawing_code_page.as_str()
+            ),
+            (AcadVersion::R2000, "ANSI_1251"),
+            "The version and code page should be read, up to the end of the header."
+        );
+        assert!(
+            read_header(&b"AutoCAD Binary DXF\r\n\x1a\0"[..]).is_none(),
+            "Binary DXF should be loaded to find its code page."
+        );
+    }
+}
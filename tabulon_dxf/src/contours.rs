@@ -0,0 +1,190 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Elevation labels along contours.
+//!
+//! Contours in site plans are usually polylines at the elevation they trace, so their
+//! labels can be made from the drawing rather than drafted. Labels are placed at even
+//! distances along each contour, measured by arc length, as text following the contour.
+
+extern crate alloc;
+use alloc::{format, sync::Arc, vec::Vec};
+
+use dxf::entities::EntityType;
+use parley::StyleSet;
+use tabulon::{
+    PaintHandle,
+    peniko::kurbo::{BezPath, DEFAULT_ACCURACY},
+    render_layer::RenderLayer,
+    text_on_path::{FatTextOnPath, MeasuredPath},
+};
+
+use crate::{EntityHandle, TDDrawing, entity_elevation};
+
+/// Placement and style of contour labels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContourLabels {
+    /// Distance along each contour between the middles of its labels, in drawing units.
+    pub interval: f64,
+    /// Distance along each contour to the middle of its first label, in drawing units.
+    pub start: f64,
+    /// Font size of the labels, in drawing units.
+    pub size: f32,
+    /// Number of decimal places of the elevations shown.
+    pub precision: usize,
+    /// Paint of the labels, which are filled.
+    pub paint: PaintHandle,
+}
+
+impl TDDrawing {
+    /// Get the polylines that are drawn and have a nonzero [elevation](Self::elevation),
+    /// which are taken to be contours.
+    pub fn contour_entities(&self) -> Vec<EntityHandle> {
+        let mut contours: Vec<_> = self
+            .item_entity_map
+            .iter()
+            .map(|(_, eh)| eh)
+            .filter(|eh| {
+                let e = self.info.get_entity(*eh);
+                matches!(
+                    e.specific,
+                    EntityType::LwPolyline(_) | EntityType::Polyline(_)
+                ) && entity_elevation(e) != 0.0
+            })
+            .collect();
+        contours.sort_unstable();
+        contours.dedup();
+        contours
+    }
+
+    /// Add elevation labels along contours to [`graphics`](Self::graphics).
+    ///
+    /// Each shape drawn for the `contours` is labeled with the entity's elevation at
+    /// `start`, and then every `interval` along it, where the whole label fits on the
+    /// contour. Labels follow the contour in whichever direction keeps them from being
+    /// upside down, with their baseline on it. Label widths are estimated at one em per
+    /// character, so labels are not placed where they might not fit.
+    ///
+    /// The labels are mapped to their contours in
+    /// [`item_entity_map`](Self::item_entity_map), but are not in any render layer of
+    /// the drawing, nor in [`entity_extents`](Self::entity_extents); they are returned
+    /// in a render layer to be drawn over the drawing when wanted.
+    pub fn add_contour_labels(
+        &mut self,
+        contours: impl IntoIterator<Item = EntityHandle>,
+        labels: &ContourLabels,
+    ) -> RenderLayer {
+        let mut layer = RenderLayer::default();
+        if labels.interval.is_nan() || labels.interval <= 0.0 {
+            return layer;
+        }
+        let style = StyleSet::new(labels.size);
+        for eh in contours {
+            let text: Arc<str> = format!("{:.p$}", self.elevation(eh), p = labels.precision).into();
+            let half_width = text.chars().count() as f64 * f64::from(labels.size) * 0.5;
+            for s in self.entity_shapes(eh) {
+                let forward = Arc::new(s.shape.path(DEFAULT_ACCURACY).into_owned());
+                let measured = MeasuredPath::new(&forward);
+                let length = measured.length();
+                let mut backward: Option<Arc<BezPath>> = None;
+                let mut middle = labels.start;
+                while middle + half_width <= length {
+                    let placed = (middle - half_width >= 0.0)
+                        .then(|| measured.at(middle))
+                        .flatten();
+                    if let Some((_, tangent)) = placed {
+                        // Loaded coordinates are y down, so text reads left to right
+                        // where the path heads right.
+                        let (path, offset) = if tangent.x >= 0.0 {
+                            (forward.clone(), middle - half_width)
+                        } else {
+                            let reversed = backward
+                                .get_or_insert_with(|| Arc::new(forward.reverse_subpaths()));
+                            (reversed.clone(), length - middle - half_width)
+                        };
+                        let ih = layer.push_with_bag(
+                            &mut self.graphics,
+                            FatTextOnPath {
+                                transform: s.transform,
+                                paint: labels.paint,
+                                text: text.clone(),
+                                style: style.clone(),
+                                path,
+                                offset,
+                            },
+                        );
+                        self.item_entity_map.insert(ih, eh);
+                    }
+                    middle += labels.interval;
+                }
+            }
+        }
+        layer
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::load_drawing;
+    use dxf::{
+        Drawing, LwPolylineVertex,
+        entities::{Entity, LwPolyline},
+        enums::AcadVersion,
+    };
+    use tabulon::GraphicsItem;
+
+    #[test]
+    fn labels_along_contour() {
+        let mut drawing = Drawing::new();
+        // Lightweight polylines are not written to R12 drawings.
+        drawing.header.version = AcadVersion::R2010;
+        let lwp = LwPolyline {
+            // Heading left, so labels should follow the reversed contour.
+            vertices: [(100.0, 0.0), (0.0, 0.0)]
+                .map(|(x, y)| LwPolylineVertex {
+                    x,
+                    y,
+                    ..Default::default()
+                })
+                .into(),
+            ..Default::default()
+        };
+        let mut entity = Entity::new(EntityType::LwPolyline(lwp));
+        entity.common.elevation = 12.5;
+        drawing.add_entity(entity);
+        let mut td = load_drawing("labels_along_contour", &drawing);
+
+        let contours = td.contour_entities();
+        assert_eq!(contours.len(), 1, "The polyline should be a contour.");
+        let layer = td.add_contour_labels(
+            contours,
+            &ContourLabels {
+                interval: 30.0,
+                start: 3.0,
+                size: 2.0,
+                precision: 1,
+                paint: PaintHandle::default(),
+            },
+        );
+        // The first label doesn't fit before the start of the contour.
+        let labels: Vec<_> = layer
+            .indices
+            .iter()
+            .map(|ih| match td.graphics.get(*ih) {
+                Some(GraphicsItem::FatTextOnPath(t)) => (t.text.clone(), t.offset, t.path.clone()),
+                _ => panic!("Labels are text on paths."),
+            })
+            .collect();
+        assert_eq!(
+            labels.iter().map(|l| l.1).collect::<Vec<_>>(),
+            [63.0, 33.0, 3.0],
+            "Labels should be placed every interval where they fit."
+        );
+        assert_eq!(&*labels[0].0, "12.5", "Labels should show the elevation.");
+        assert!(
+            labels[0].2.elements()[0].end_point().unwrap().x == 0.0,
+            "Labels should read left to right."
+        );
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Resolution of dynamic block instances to their saved representation.
+//!
+//! An instance of a dynamic block whose parameters differ from the definition
+//! has its geometry stored in an anonymous `*U` block. Usually the INSERT names
+//! that block directly, but it can also name the dynamic block definition and
+//! point to the representation through its extension dictionary:
+//!
+//! ```text
+//! INSERT -> {ACAD_XDICTIONARY} -> AcDbBlockRepresentation -> AcDbRepData -> BLOCK_RECORD
+//! ```
+
+extern crate alloc;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use dxf::{
+    Drawing,
+    entities::{Entity, Insert},
+    objects::ObjectType,
+};
+
+use crate::objects::{ObjectIndex, handle};
+
+/// Lookup of dynamic block representations in a drawing.
+pub(crate) struct Representations<'a> {
+    objects: &'a ObjectIndex<'a>,
+    /// Names of blocks that are present, by block record handle.
+    block_names: BTreeMap<u64, &'a str>,
+}
+
+impl<'a> Representations<'a> {
+    pub(crate) fn new(drawing: &'a Drawing, objects: &'a ObjectIndex<'a>) -> Self {
+        let blocks: BTreeSet<&str> = drawing.blocks().map(|b| b.name.as_str()).collect();
+        Self {
+            objects,
+            block_names: drawing
+                .block_records()
+                .filter(|r| blocks.contains(r.name.as_str()))
+                .map(|r| (r.handle.0, r.name.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Get the name of the block holding the saved representation of an INSERT,
+    /// if it has one in its extension dictionary.
+    pub(crate) fn block_name(&self, e: &Entity) -> Option<&'a str> {
+        let rep_data = self
+            .objects
+            .lookup(e, &["AcDbBlockRepresentation", "AcDbRepData"])?;
+        let ObjectType::XRecordObject(ref x) = rep_data.specific else {
+            return None;
+        };
+        let record = x
+            .data_pairs
+            .iter()
+            .find(|p| p.code == 340)
+            .and_then(|p| handle(&p.value))?;
+        self.block_names.get(&record).copied()
+    }
+
+    /// Get the name of the block to draw for an INSERT.
+    ///
+    /// This is the saved representation if there is one, otherwise the named block.
+    pub(crate) fn insert_block(&self, e: &Entity, ins: &'a Insert) -> &'a str {
+        self.block_name(e).unwrap_or(ins.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::XDICTIONARY_GROUP;
+    use dxf::{
+        Block, CodePair, ExtensionGroup, ExtensionGroupItem, Handle,
+        entities::EntityType,
+        objects::{Dictionary, Object, XRecordObject},
+    };
+
+    fn add_dictionary(drawing: &mut Drawing, key: &str, value: Handle) -> Handle {
+        let mut d = Dictionary::default();
+        d.value_handles.insert(key.into(), value);
+        drawing
+            .add_object(Object::new(ObjectType::Dictionary(d)))
+            .common
+            .handle
+    }
+
+    #[test]
+    fn representation_from_extension_dictionary() {
+        let mut drawing = Drawing::new();
+        drawing.add_block(Block {
+            name: "*U7".into(),
+            ..Default::default()
+        });
+        let record = drawing
+            .block_records()
+            .find(|r| r.name == "*U7")
+            .unwrap()
+            .handle;
+
+        let rep_data = drawing
+            .add_object(Object::new(ObjectType::XRecordObject(XRecordObject {
+                data_pairs: vec![CodePair::new_str(340, &format!("{:X}", record.0))],
+                ..Default::default()
+            })))
+            .common
+            .handle;
+        let representation = add_dictionary(&mut drawing, "AcDbRepData", rep_data);
+        let xdictionary = add_dictionary(&mut drawing, "AcDbBlockRepresentation", representation);
+
+        let plain = Entity::new(EntityType::Insert(Insert {
+            name: "DOOR".into(),
+            ..Default::default()
+        }));
+        let mut dynamic = plain.clone();
+        dynamic.common.extension_data_groups = vec![ExtensionGroup {
+            application_name: XDICTIONARY_GROUP.into(),
+            items: vec![ExtensionGroupItem::CodePair(CodePair::new_str(
+                360,
+                &format!("{:X}", xdictionary.0),
+            ))],
+        }];
+
+        let objects = ObjectIndex::new(&drawing);
+        let representations = Representations::new(&drawing, &objects);
+        assert_eq!(
+            representations.block_name(&dynamic),
+            Some("*U7"),
+            "The representation block should be found through the extension dictionary."
+        );
+        assert_eq!(
+            representations.block_name(&plain),
+            None,
+            "Inserts without an extension dictionary have no representation."
+        );
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Elevation and thickness of entities.
+//!
+//! Site plans and other 2.5D drawings place entities at an elevation along the z axis,
+//! and extrude them by a thickness along it. Neither is visible from +Z, so they are
+//! reported here, and thickness can be shown as an outline offset from the entity.
+
+extern crate alloc;
+use alloc::{sync::Arc, vec::Vec};
+
+use dxf::entities::{Entity, EntityType};
+use tabulon::{
+    GraphicsItem, TransformHandle,
+    peniko::kurbo::{Affine, DEFAULT_ACCURACY, Line, Shape, Vec2},
+    render_layer::RenderLayer,
+    shape::FatShape,
+};
+
+use crate::{EntityHandle, TDDrawing};
+
+/// Get the elevation of an entity, its position along the z axis.
+///
+/// This is the z of the insertion point, center, location, or first point for entities
+/// that have one, and the elevation group code (38) otherwise, which is where
+/// lightweight polylines keep it.
+pub fn entity_elevation(e: &Entity) -> f64 {
+    let p = match e.specific {
+        EntityType::Arc(ref a) => &a.center,
+        EntityType::Circle(ref c) => &c.center,
+        EntityType::Ellipse(ref el) => &el.center,
+        EntityType::Line(ref l) => &l.p1,
+        EntityType::Polyline(ref pl) => &pl.location,
+        EntityType::Insert(ref ins) => &ins.location,
+        EntityType::Text(ref t) => &t.location,
+        EntityType::MText(ref mt) => &mt.insertion_point,
+        EntityType::ModelPoint(ref mp) => &mp.location,
+        EntityType::Shape(ref s) => &s.location,
+        EntityType::Solid(ref s) => &s.first_corner,
+        EntityType::Trace(ref t) => &t.first_corner,
+        _ => return e.common.elevation,
+    };
+    p.z
+}
+
+/// Get the thickness of an entity, the distance it is extruded along its normal.
+///
+/// Entities that can't have a thickness have a thickness of zero.
+pub fn entity_thickness(e: &Entity) -> f64 {
+    match e.specific {
+        EntityType::Arc(ref a) => a.thickness,
+        EntityType::Circle(ref c) => c.thickness,
+        EntityType::Line(ref l) => l.thickness,
+        EntityType::LwPolyline(ref lwp) => lwp.thickness,
+        EntityType::Polyline(ref pl) => pl.thickness,
+        EntityType::Text(ref t) => t.thickness,
+        EntityType::ModelPoint(ref mp) => mp.thickness,
+        EntityType::Shape(ref s) => s.thickness,
+        EntityType::Solid(ref s) => s.thickness,
+        EntityType::Trace(ref t) => t.thickness,
+        _ => 0.0,
+    }
+}
+
+impl TDDrawing {
+    /// Get the [elevation](entity_elevation) of an entity.
+    pub fn elevation(&self, eh: EntityHandle) -> f64 {
+        entity_elevation(self.info.get_entity(eh))
+    }
+
+    /// Get the [thickness](entity_thickness) of an entity.
+    pub fn thickness(&self, eh: EntityHandle) -> f64 {
+        entity_thickness(self.info.get_entity(eh))
+    }
+
+    /// Add outlines showing the thickness of entities to [`graphics`](Self::graphics).
+    ///
+    /// Each shape drawn for an entity with a thickness is drawn again, offset by
+    /// `offset` times the thickness, with its own paint, and the ends of its segments
+    /// are joined to their offset copies, giving an oblique view of the extrusion.
+    /// `offset` is in the same coordinates as loaded items, where y points down the
+    /// drawing, so `Vec2::new(0.5, -0.5)` draws thick entities extending up and right.
+    ///
+    /// The outlines are mapped to their entities in
+    /// [`item_entity_map`](Self::item_entity_map), but are not in any render layer of
+    /// the drawing, nor in [`entity_extents`](Self::entity_extents); they are returned
+    /// in a render layer to be drawn over the drawing when wanted.
+    pub fn add_thickness_outlines(&mut self, offset: Vec2) -> RenderLayer {
+        let root = TransformHandle::default();
+        let to_drawing = self.graphics.get_transform(root).inverse();
+        let thick: Vec<_> = self
+            .render_layer
+            .indices
+            .iter()
+            .filter_map(|ih| {
+                let eh = self.item_entity_map.get(ih)?;
+                let thickness = self.thickness(eh);
+                let Some(GraphicsItem::FatShape(s)) = self.graphics.get(*ih) else {
+                    return None;
+                };
+                (thickness != 0.0).then(|| (eh, s.clone(), thickness))
+            })
+            .collect();
+
+        let mut layer = RenderLayer::default();
+        for (eh, s, thickness) in thick {
+            let transform = to_drawing * self.graphics.get_transform(s.transform);
+            let path = transform * s.shape.path(DEFAULT_ACCURACY).into_owned();
+            let shift = Affine::translate(offset * thickness);
+            let mut outline = shift * &path;
+            for el in path.elements() {
+                if let Some(p) = el.end_point() {
+                    outline.extend(Line::new(p, shift * p).path_elements(DEFAULT_ACCURACY));
+                }
+            }
+            let ih = layer.push_with_bag(
+                &mut self.graphics,
+                FatShape {
+                    shape: Arc::new(outline.into()),
+                    paint: s.paint,
+                    clip: s.clip,
+                    ..Default::default()
+                },
+            );
+            self.item_entity_map.insert(ih, eh);
+        }
+        layer
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::load_drawing;
+    use dxf::{Drawing, LwPolylineVertex, entities::LwPolyline, enums::AcadVersion};
+    use tabulon::peniko::kurbo::{Point, Rect};
+
+    #[test]
+    fn thick_polyline() {
+        let mut drawing = Drawing::new();
+        // Lightweight polylines are not written to R12 drawings.
+        drawing.header.version = AcadVersion::R2010;
+        let mut lwp = LwPolyline {
+            thickness: 2.0,
+            ..Default::default()
+        };
+        lwp.vertices = [(0.0, 0.0), (10.0, 0.0)]
+            .map(|(x, y)| LwPolylineVertex {
+                x,
+                y,
+                ..Default::default()
+            })
+            .into();
+        let mut entity = Entity::new(EntityType::LwPolyline(lwp));
+        entity.common.elevation = 5.0;
+        let id = drawing.add_entity(entity).common.handle.0;
+        let mut td = load_drawing("thick_polyline", &drawing);
+        let eh = td.entity_by_stable_id(id).unwrap();
+
+        assert_eq!(
+            (td.elevation(eh), td.thickness(eh)),
+            (5.0, 2.0),
+            "Elevation and thickness should be read from the polyline."
+        );
+        let layer = td.add_thickness_outlines(Vec2::new(0.5, -0.5));
+        assert_eq!(layer.indices.len(), 1, "The polyline should be outlined.");
+        let ih = layer.indices[0];
+        assert_eq!(
+            td.item_entity_map.get(&ih),
+            Some(eh),
+            "The outline should map to the polyline."
+        );
+        let Some(GraphicsItem::FatShape(s)) = td.graphics.get(ih) else {
+            panic!("Outlines are shapes.");
+        };
+        assert_eq!(
+            s.bounding_box(),
+            Some(Rect::from_points(Point::ZERO, Point::new(11.0, -1.0))),
+            "The outline should be offset by the thickness and joined to the polyline."
+        );
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Explosion of inserts into independent items.
+
+extern crate alloc;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync,
+    vec::Vec,
+};
+
+use dxf::{
+    Block, DrawingItem, Handle,
+    entities::{Entity, EntityCommon, EntityType, Insert},
+    tables::Layer,
+};
+use tabulon::{
+    GraphicsItem, ItemHandle,
+    bounds::EstimatedText,
+    peniko::kurbo::{Affine, BezPath},
+    shape::FatShape,
+};
+
+use crate::{
+    EntityHandle, TDDrawing, add_extent, dynamic_block::Representations, insert_transforms,
+    objects::ObjectIndex, paint::SOLID_FILL, path_from_entity, recover_color_enum,
+};
+
+/// Line weight and color of an entity, with BYLAYER and BYBLOCK resolved.
+#[derive(Clone, Copy)]
+struct Style<'a> {
+    /// Line weight enumeration value.
+    lw: i16,
+    /// Color enumeration value.
+    color: i16,
+    /// Entity providing BYENTITY colors and transparency.
+    common: &'a EntityCommon,
+}
+
+/// Blocks and layers of the drawing being exploded.
+struct Context<'a> {
+    blocks: BTreeMap<&'a str, &'a Block>,
+    layers: BTreeMap<&'a str, &'a Layer>,
+    representations: Representations<'a>,
+}
+
+impl<'a> Context<'a> {
+    /// Resolve the style of an entity in a block, inheriting BYBLOCK values from `by_block`.
+    fn style(&self, e: &'a Entity, by_block: Style<'a>) -> Style<'a> {
+        let layer = self.layers.get(e.common.layer.as_str());
+        let lw = if matches!(e.specific, EntityType::Solid(..)) {
+            SOLID_FILL
+        } else {
+            match e.common.lineweight_enum_value {
+                -1 => by_block.lw,
+                -2 => layer
+                    .map(|l| l.line_weight.raw_value())
+                    .filter(|&w| w >= 0)
+                    .unwrap_or(25),
+                lw => lw,
+            }
+        };
+        let (color, common) = match recover_color_enum(&e.common.color) {
+            0 => (by_block.color, by_block.common),
+            // White if the layer doesn't have a resolvable color.
+            256 => (
+                layer.and_then(|l| l.color.index()).map_or(7, |i| i as i16),
+                &e.common,
+            ),
+            c => (c, &e.common),
+        };
+        Style { lw, color, common }
+    }
+
+    /// Collect the transformed paths of the entities in the block of an INSERT.
+    ///
+    /// `active` holds the blocks being exploded, so that malformed drawings with
+    /// recursive blocks terminate.
+    fn explode_insert(
+        &self,
+        e: &'a Entity,
+        ins: &'a Insert,
+        transform: Affine,
+        style: Style<'a>,
+        active: &mut Vec<&'a str>,
+        pieces: &mut Vec<(Style<'a>, BezPath)>,
+    ) {
+        let name = self.representations.insert_block(e, ins);
+        let Some(block) = self.blocks.get(name) else {
+            return;
+        };
+        if active.contains(&name) {
+            return;
+        }
+        active.push(name);
+        for instance in insert_transforms(ins) {
+            let transform = transform * instance;
+            for sub in block.entities.iter() {
+                let sub_style = self.style(sub, style);
+                match sub.specific {
+                    // FIXME: currently only support viewing from +Z.
+                    EntityType::Insert(ref nested) if nested.extrusion_direction.z == 1.0 => {
+                        self.explode_insert(sub, nested, transform, sub_style, active, pieces);
+                    }
+                    EntityType::Insert(..) => {}
+                    _ => {
+                        if let Some(path) = path_from_entity(sub) {
+                            pieces.push((sub_style, transform * path));
+                        }
+                    }
+                }
+            }
+        }
+        active.pop();
+    }
+}
+
+impl TDDrawing {
+    /// Replace the items drawn for an INSERT with an item for each entity in its block.
+    ///
+    /// Block geometry is transformed into drawing coordinates for every instance of
+    /// the insert, nested inserts are exploded too, and BYBLOCK colors and line weights
+    /// are inherited from the insert. The new items take the place of the insert's items
+    /// in drawing order, keep its clip, and map to its [`EntityHandle`].
+    ///
+    /// Paints created for the new items are added to `restroke_paints` without a stroke
+    /// width, so [restroke](Self::restroke) them before rendering.
+    ///
+    /// Returns the new items, or nothing if `eh` is not a drawn INSERT.
+    pub fn explode(&mut self, eh: EntityHandle) -> Vec<ItemHandle> {
+        let drawing = &self.info.drawing;
+        let Some(DrawingItem::Entity(e)) = drawing.item_by_handle(Handle(eh.0.get())) else {
+            return Vec::new();
+        };
+        let EntityType::Insert(ref ins) = e.specific else {
+            return Vec::new();
+        };
+        let old: BTreeSet<ItemHandle> = self.item_entity_map.items_of(eh).collect();
+        let Some(position) = self
+            .render_layer
+            .indices
+            .iter()
+            .position(|ih| old.contains(ih))
+        else {
+            return Vec::new();
+        };
+        let clip = match self.graphics.get(self.render_layer.indices[position]) {
+            Some(GraphicsItem::FatShape(s)) => s.clip,
+            _ => Default::default(),
+        };
+
+        let objects = ObjectIndex::new(drawing);
+        let context = Context {
+            blocks: drawing.blocks().map(|b| (b.name.as_str(), b)).collect(),
+            layers: drawing.layers().map(|l| (l.name.as_str(), l)).collect(),
+            representations: Representations::new(drawing, &objects),
+        };
+        let Some(&layer) = context.layers.get(e.common.layer.as_str()) else {
+            return Vec::new();
+        };
+        // BYBLOCK has no meaning for the insert itself, so it gets the default line weight and white.
+        let by_block = Style {
+            lw: -3,
+            color: 7,
+            common: &e.common,
+        };
+        let mut pieces = Vec::new();
+        context.explode_insert(
+            e,
+            ins,
+            Affine::IDENTITY,
+            context.style(e, by_block),
+            &mut Vec::new(),
+            &mut pieces,
+        );
+
+        let new: Vec<ItemHandle> = pieces
+            .into_iter()
+            .map(|(style, path)| {
+                let paint = self.paints.resolve(
+                    &mut self.graphics,
+                    layer,
+                    style.common,
+                    style.lw,
+                    style.color,
+                );
+                self.graphics.push(FatShape {
+                    shape: sync::Arc::new(path.into()),
+                    paint,
+                    clip,
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let replace = |indices: &mut Vec<ItemHandle>| {
+            if let Some(position) = indices.iter().position(|ih| old.contains(ih)) {
+                indices.retain(|ih| !old.contains(ih));
+                indices.splice(position..position, new.iter().copied());
+            }
+        };
+        replace(&mut self.render_layer.indices);
+        if let Some(sl) = self
+            .entity_layer_map
+            .get(&eh)
+            .and_then(|lh| self.stacked_layers.get(lh))
+            .and_then(|slh| self.layer_stack.get_mut(*slh))
+        {
+            replace(&mut sl.layer.indices);
+        }
+        for ih in old {
+            self.item_entity_map.remove(&ih);
+        }
+        self.item_entity_map.extend(new.iter().map(|ih| (*ih, eh)));
+        self.entity_extents.remove(&eh);
+        for ih in &new {
+            add_extent(
+                &mut self.entity_extents,
+                eh,
+                self.graphics
+                    .item_bounds_below_root_with(*ih, &mut EstimatedText),
+            );
+        }
+        self.restroke_paints = sync::Arc::from(self.paints.restroke_paints().as_slice());
+        self.restroked = None;
+
+        new
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{aci_palette::ACI, tests::load_drawing};
+    use dxf::{Color, Drawing, Point, entities::Line};
+    use tabulon::peniko::{self, kurbo::Rect};
+
+    #[test]
+    fn explode_arrayed_insert() {
+        let mut drawing = Drawing::new();
+        let mut by_block = Entity::new(EntityType::Line(Line::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )));
+        by_block.common.color = Color::by_block();
+        let mut red = by_block.clone();
+        red.common.color = Color::from_index(1);
+        drawing.add_block(Block {
+            name: "B".into(),
+            entities: vec![by_block, red],
+            ..Default::default()
+        });
+        let mut insert = Entity::new(EntityType::Insert(Insert {
+            name: "B".into(),
+            column_count: 2,
+            column_spacing: 10.0,
+            ..Default::default()
+        }));
+        insert.common.color = Color::from_index(3);
+        let eh = EntityHandle(
+            core::num::NonZeroU64::new(drawing.add_entity(insert).common.handle.0).unwrap(),
+        );
+
+        let mut td = load_drawing("explode_arrayed_insert", &drawing);
+
+        assert_eq!(
+            td.render_layer.indices.len(),
+            4,
+            "The insert should load as one item per style chunk of each instance."
+        );
+        let loaded = |i: usize| match td.graphics.get(td.render_layer.indices[i]) {
+            Some(GraphicsItem::FatShape(s)) => s.clone(),
+            _ => unreachable!(),
+        };
+        assert!(
+            sync::Arc::ptr_eq(&loaded(0).shape, &loaded(2).shape)
+                && loaded(0).transform != loaded(2).transform,
+            "Instances should share the block geometry, with their own transforms."
+        );
+        let extent = Some(Rect::new(0.0, 0.0, 11.0, 0.0));
+        assert_eq!(
+            td.entity_extents.get(&eh).copied(),
+            extent,
+            "The extent should cover every instance, without strokes."
+        );
+        // Extents are in drawing coordinates, whatever the view.
+        td.graphics
+            .update_transform(Default::default(), Affine::translate((100.0, 50.0)));
+        let exploded = td.explode(eh);
+        assert_eq!(
+            td.entity_extents.get(&eh).copied(),
+            extent,
+            "Exploding should keep the extent, in drawing coordinates."
+        );
+        assert_eq!(
+            exploded.len(),
+            4,
+            "Each entity of each instance should get an item."
+        );
+        assert_eq!(
+            td.render_layer.indices, exploded,
+            "Exploded items should replace the insert's items."
+        );
+        let shape = |ih| {
+            let Some(GraphicsItem::FatShape(s)) = td.graphics.get(ih) else {
+                unreachable!();
+            };
+            s
+        };
+        assert_eq!(
+            shape(exploded[2]).bounding_box(),
+            Some(Rect::new(10.0, 0.0, 11.0, 0.0)),
+            "Instances should be transformed into drawing coordinates."
+        );
+        let color = |ih| td.graphics.get_paint(shape(ih).paint).stroke_paint.clone();
+        let aci = |i: usize| {
+            let [_, r, g, b] = ACI[i].to_be_bytes();
+            Some(peniko::Brush::from(peniko::Color::from_rgba8(r, g, b, 255)))
+        };
+        assert_eq!(
+            color(exploded[0]),
+            aci(3),
+            "BYBLOCK colors should come from the insert."
+        );
+        assert_eq!(color(exploded[1]), aci(1), "Other colors should be kept.");
+    }
+}
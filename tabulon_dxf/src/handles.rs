@@ -0,0 +1,95 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Repair of layer and entity handles that are missing or repeated.
+//!
+//! Layers and entities are keyed by their handles, which are nonzero and unique in
+//! well formed drawings. Some exporters write no handles, which read as zero, or
+//! repeat them. Such handles are replaced with synthetic ones past the largest handle
+//! in the drawing, so the drawing still loads, and the count is reported.
+
+extern crate alloc;
+use alloc::collections::BTreeSet;
+
+use dxf::{Drawing, Handle};
+
+/// Give layers and entities without a unique nonzero handle a new handle.
+///
+/// The first layer or entity with a handle keeps it. Returns the number of handles
+/// that were replaced.
+pub(crate) fn repair_handles(drawing: &mut Drawing) -> usize {
+    let largest = drawing
+        .layers()
+        .map(|l| l.handle.0)
+        .chain(drawing.entities().map(|e| e.common.handle.0))
+        .chain(drawing.objects().map(|o| o.common.handle.0))
+        .chain(
+            drawing
+                .blocks()
+                .flat_map(|b| b.entities.iter().map(|e| e.common.handle.0)),
+        )
+        .max()
+        .unwrap_or(0);
+    let mut next = largest.max(drawing.header.next_available_handle.0.saturating_sub(1)) + 1;
+    let mut seen = BTreeSet::new();
+    let mut replaced = 0;
+    let mut repair = |handle: &mut Handle| {
+        if handle.0 == 0 || !seen.insert(handle.0) {
+            *handle = Handle(next);
+            next += 1;
+            replaced += 1;
+        }
+    };
+
+    for l in drawing.layers_mut() {
+        repair(&mut l.handle);
+    }
+    for e in drawing.entities_mut() {
+        repair(&mut e.common.handle);
+    }
+    if replaced > 0 {
+        drawing.header.next_available_handle = Handle(next);
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dxf::entities::{Entity, EntityType, Line};
+
+    #[test]
+    fn zero_and_repeated_handles() {
+        let mut drawing = Drawing::new();
+        for _ in 0..4 {
+            drawing.add_entity(Entity::new(EntityType::Line(Line::default())));
+        }
+        let handles = |d: &Drawing| d.entities().map(|e| e.common.handle.0).collect::<Vec<_>>();
+        let assigned = handles(&drawing);
+        assert_eq!(
+            repair_handles(&mut drawing),
+            0,
+            "Unique handles should be kept."
+        );
+
+        let mut entities = drawing.entities_mut();
+        entities.next().unwrap().common.handle = Handle(0);
+        entities.next().unwrap().common.handle = Handle(assigned[3]);
+        drop(entities);
+        assert_eq!(
+            repair_handles(&mut drawing),
+            2,
+            "Zero and repeated handles should be replaced."
+        );
+        let repaired = handles(&drawing);
+        assert_eq!(
+            repaired[1], assigned[3],
+            "The first entity with a handle should keep it."
+        );
+        assert!(
+            repaired.iter().all(|h| *h != 0)
+                && repaired.iter().collect::<BTreeSet<_>>().len() == repaired.len(),
+            "Handles should be nonzero and unique: {repaired:?}"
+        );
+    }
+}
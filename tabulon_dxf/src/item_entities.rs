@@ -0,0 +1,280 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Attribution of graphics items to the entities they are drawn for.
+//!
+//! Most items are drawn for a single entity. An item may also merge the paths of
+//! several entities, such as to draw many lines with the same paint as one path, in
+//! which case ranges of its path segments are attributed to each entity. Segments are
+//! counted in the order of [`Shape::segments`], which is how
+//! [`SpatialIndex::nearest_segment`] numbers them, so picking a merged item still
+//! finds the entity under the pointer.
+//!
+//! [`Shape::segments`]: tabulon::peniko::kurbo::Shape::segments
+
+extern crate alloc;
+use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+
+use tabulon::{ItemHandle, peniko::kurbo::Point, spatial_index::SpatialIndex};
+
+use crate::EntityHandle;
+
+/// The entities of an item, as the first segment of each range and its entity.
+#[derive(Debug, Clone)]
+enum Attribution {
+    /// The whole item is drawn for one entity.
+    Entity((usize, EntityHandle)),
+    /// Ranges of segments, sorted by their first segment, starting at 0.
+    Merged(Vec<(usize, EntityHandle)>),
+}
+
+impl Attribution {
+    fn ranges(&self) -> &[(usize, EntityHandle)] {
+        match self {
+            Self::Entity(r) => core::slice::from_ref(r),
+            Self::Merged(ranges) => ranges,
+        }
+    }
+}
+
+/// Mapping from graphics items to the entities they are drawn for.
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug, Clone, Default)]
+pub struct ItemEntityMap {
+    items: BTreeMap<ItemHandle, Attribution>,
+}
+
+impl ItemEntityMap {
+    /// Make an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attribute the whole of an item to one entity, replacing any previous attribution.
+    pub fn insert(&mut self, item: ItemHandle, entity: EntityHandle) {
+        self.items.insert(item, Attribution::Entity((0, entity)));
+    }
+
+    /// Attribute ranges of the segments of a merged item to entities, replacing any
+    /// previous attribution.
+    ///
+    /// Each range is given by its first segment, and runs up to the first segment of
+    /// the next range, or to the end of the item. Ranges may be given in any order;
+    /// segments before the first range are attributed to it. Adjacent ranges of the
+    /// same entity are joined, and an item with no ranges is removed.
+    pub fn insert_merged(
+        &mut self,
+        item: ItemHandle,
+        ranges: impl IntoIterator<Item = (usize, EntityHandle)>,
+    ) {
+        let mut ranges: Vec<_> = ranges.into_iter().collect();
+        ranges.sort_by_key(|(first, _)| *first);
+        ranges.dedup_by(|next, prev| next.1 == prev.1);
+        let attribution = match ranges.as_slice() {
+            [] => {
+                self.items.remove(&item);
+                return;
+            }
+            [(_, entity)] => Attribution::Entity((0, *entity)),
+            _ => {
+                ranges[0].0 = 0;
+                Attribution::Merged(ranges)
+            }
+        };
+        self.items.insert(item, attribution);
+    }
+
+    /// Get the entity an item is drawn for.
+    ///
+    /// This is `None` for merged items drawn for more than one entity, see
+    /// [`entity_at`](Self::entity_at).
+    pub fn get(&self, item: &ItemHandle) -> Option<EntityHandle> {
+        match self.items.get(item)? {
+            Attribution::Entity((_, entity)) => Some(*entity),
+            Attribution::Merged(_) => None,
+        }
+    }
+
+    /// Get the entity a segment of an item is drawn for.
+    pub fn entity_at(&self, item: &ItemHandle, segment: usize) -> Option<EntityHandle> {
+        let ranges = self.items.get(item)?.ranges();
+        let after = ranges.partition_point(|(first, _)| *first <= segment);
+        Some(ranges[after.max(1) - 1].1)
+    }
+
+    /// Iterate over the entities an item is drawn for, in the order of their segments.
+    ///
+    /// Entities with several ranges in a merged item are yielded once for each range.
+    pub fn entities(&self, item: &ItemHandle) -> impl Iterator<Item = EntityHandle> + '_ {
+        self.items
+            .get(item)
+            .into_iter()
+            .flat_map(|a| a.ranges().iter().map(|(_, entity)| *entity))
+    }
+
+    /// Iterate over the items drawn for an entity, whole or in part, in handle order.
+    pub fn items_of(&self, entity: EntityHandle) -> impl Iterator<Item = ItemHandle> + '_ {
+        self.items
+            .iter()
+            .filter(move |(_, a)| a.ranges().iter().any(|(_, e)| *e == entity))
+            .map(|(item, _)| *item)
+    }
+
+    /// Iterate over pairs of items and the entities they are drawn for, in handle order.
+    ///
+    /// Merged items are yielded once for each of their ranges.
+    pub fn iter(&self) -> impl Iterator<Item = (ItemHandle, EntityHandle)> + '_ {
+        self.items
+            .iter()
+            .flat_map(|(item, a)| a.ranges().iter().map(move |(_, entity)| (*item, *entity)))
+    }
+
+    /// Find the entity under `point`, within `tolerance`.
+    ///
+    /// This picks an item like [`SpatialIndex::pick`], and resolves the entity of the
+    /// nearest segment for shapes, so that merged items resolve to the exact entity.
+    pub fn pick(&self, index: &SpatialIndex, point: Point, tolerance: f64) -> Option<EntityHandle> {
+        if let Some(n) = index.nearest_segment(point, tolerance) {
+            return self.entity_at(&n.item, n.index);
+        }
+        let item = index.pick(point, tolerance)?;
+        self.entities(&item).next()
+    }
+
+    /// Find every entity within `tolerance` of `point`, once each, in the order
+    /// [`pick`](Self::pick) prefers them, so the first is the one it picks.
+    ///
+    /// Entities of shapes are resolved by segment, as by `pick`, so each entity merged
+    /// into an item is a candidate of its own.
+    pub fn pick_candidates(
+        &self,
+        index: &SpatialIndex,
+        point: Point,
+        tolerance: f64,
+    ) -> Vec<EntityHandle> {
+        let candidates = index.pick_candidates(point, tolerance);
+        let mut entities = Vec::new();
+        let found = candidates
+            .segments
+            .iter()
+            .filter_map(|n| self.entity_at(&n.item, n.index))
+            .chain(
+                candidates
+                    .bounds
+                    .iter()
+                    .filter_map(|item| self.entities(item).next()),
+            );
+        for entity in found {
+            if !entities.contains(&entity) {
+                entities.push(entity);
+            }
+        }
+        entities
+    }
+
+    /// Remove an item, returning whether it was in the map.
+    pub fn remove(&mut self, item: &ItemHandle) -> bool {
+        self.items.remove(item).is_some()
+    }
+
+    /// Count the items in the map.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check whether the map has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Extend<(ItemHandle, EntityHandle)> for ItemEntityMap {
+    fn extend<I: IntoIterator<Item = (ItemHandle, EntityHandle)>>(&mut self, iter: I) {
+        for (item, entity) in iter {
+            self.insert(item, entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::num::NonZeroU64;
+    use tabulon::{
+        GraphicsBag,
+        peniko::kurbo::{BezPath, Line},
+        render_layer::RenderLayer,
+        shape::FatShape,
+    };
+
+    extern crate alloc;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn merged_items_pick_exact_entities() {
+        let entity = |h| EntityHandle(NonZeroU64::new(h).unwrap());
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let single = layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                shape: Arc::new(Line::new((0.0, 100.0), (10.0, 100.0)).into()),
+                ..Default::default()
+            },
+        );
+        // Three horizontal lines merged into one path, the last two from one entity.
+        let mut path = BezPath::new();
+        for y in [0.0, 10.0, 20.0] {
+            path.move_to((0.0, y));
+            path.line_to((10.0, y));
+        }
+        let merged = layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                shape: Arc::new(path.into()),
+                ..Default::default()
+            },
+        );
+
+        let mut map = ItemEntityMap::new();
+        map.insert(single, entity(1));
+        map.insert_merged(merged, [(2, entity(3)), (0, entity(2)), (1, entity(3))]);
+        assert_eq!(
+            map.entities(&merged).collect::<Vec<_>>(),
+            [entity(2), entity(3)],
+            "Adjacent ranges of the same entity should be joined."
+        );
+        assert_eq!(
+            (map.get(&single), map.get(&merged)),
+            (Some(entity(1)), None),
+            "Only items drawn for one entity should have a single entity."
+        );
+
+        let index = SpatialIndex::new(&graphics, &layer);
+        let picks: Vec<_> = [0.0, 10.0, 20.0, 100.0]
+            .into_iter()
+            .map(|y| map.pick(&index, Point::new(5.0, y + 0.5), 1.0))
+            .collect();
+        assert_eq!(
+            picks,
+            [
+                Some(entity(2)),
+                Some(entity(3)),
+                Some(entity(3)),
+                Some(entity(1))
+            ],
+            "Picking should resolve the entity of the nearest segment."
+        );
+        assert_eq!(
+            map.pick_candidates(&index, Point::new(5.0, 4.0), 7.0),
+            [entity(2), entity(3)],
+            "Each entity of a merged item in range should be a candidate, nearest first."
+        );
+        assert_eq!(
+            map.items_of(entity(3)).collect::<Vec<_>>(),
+            [merged],
+            "Merged items should be found by any of their entities."
+        );
+    }
+}
@@ -2,25 +2,51 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 //! DXF loader for Tabulon
+//!
+//! ## Features
+//!
+//! - `std` (enabled by default): Load drawings from files.
+//! - `encoding`: Decode text in drawings older than R2007 using the code page named
+//!   by `$DWGCODEPAGE`, rather than always as Windows-1252.
+//! - `parallel`: Resolve independent block definitions in parallel with Rayon.
+//! - `serde`: Implement serialization for markup documents, bookmarks, and text records.
+//! - `text` (enabled by default): Translate TEXT and MTEXT entities to text items, styled
+//!   with Parley. Without it, Parley is not compiled, and text entities are reported in
+//!   [`LoadReport`] as unsupported, which suits converting and measuring geometry.
 
 pub use dxf;
 use dxf::{Drawing, DxfResult, entities::EntityType};
 
 use tabulon::{
-    DirectIsometry, GraphicsBag, GraphicsItem, ItemHandle, PaintHandle,
+    GraphicsBag, GraphicsItem, PaintHandle, TransformHandle,
+    bounds::EstimatedText,
+    layer_stack::{LayerStack, StackedLayer, StackedLayerHandle},
     peniko::{
         Color,
         kurbo::{
-            Affine, Arc, BezPath, Circle, DEFAULT_ACCURACY, PathEl, Point, Shape, Stroke, Vec2,
+            Affine, Arc, BezPath, Circle, DEFAULT_ACCURACY, Line, PathEl, Point, Rect, Shape,
+            Stroke, Vec2,
         },
     },
     render_layer::RenderLayer,
-    shape::{FatPaint, FatShape},
-    text::{AttachmentPoint, FatText},
+    shape::{AnyShape, FatClip, FatPaint, FatShape},
+    transform::uniform_scale,
 };
 
-use joto_constants::u64::MICROMETER;
-use parley::{Alignment, LineHeight, StyleSet};
+#[cfg(feature = "text")]
+use {
+    aci_palette::ACI,
+    dxf::enums::BackgroundFillSetting,
+    parley::{
+        Alignment, FontFamily, FontStack, FontStyle, FontWeight, FontWidth, GenericFamily,
+        LineHeight, StyleProperty, StyleSet,
+    },
+    tabulon::{
+        DirectIsometry,
+        text::{AttachmentPoint, FatText, StyleSpan, TextBackground, TextColumns},
+    },
+    text_codes::{TextFlavor, decode_mtext_colors, decode_text},
+};
 
 extern crate alloc;
 use alloc::{
@@ -34,19 +60,100 @@ use std::path::Path;
 use core::{cmp::Ordering, num::NonZeroU64};
 
 mod aci_palette;
-use aci_palette::ACI;
+
+mod align;
+
+mod anchor;
+pub use anchor::{Anchor, Bookmark};
+
+mod angle;
+pub use angle::AngleUnits;
+
+mod attributes;
+pub use attributes::AttributeMatch;
+
+mod blocks;
+use blocks::{for_each_chunk, resolve_blocks};
+
+mod code_page;
+use code_page::CodePage;
+
+#[cfg(feature = "text")]
+mod contours;
+#[cfg(feature = "text")]
+pub use contours::ContourLabels;
+
+mod dynamic_block;
+use dynamic_block::Representations;
+
+mod elevation;
+pub use elevation::{entity_elevation, entity_thickness};
+
+mod explode;
+
+mod handles;
+use handles::repair_handles;
+
+mod item_entities;
+pub use item_entities::ItemEntityMap;
+
+mod merge;
+
+mod markup;
+pub use markup::{Markup, MarkupDocument, MarkupId, MarkupRecord};
+
+mod objects;
+use objects::ObjectIndex;
+
+mod paint;
+use paint::{PaintTable, SOLID_FILL};
+
+mod point;
+use point::point_item;
+
+mod reveal;
+pub use reveal::Reveal;
+
+mod text_codes;
+
+mod text_records;
+pub use text_records::{TextRecord, write_text_records_csv};
+
+mod xclip;
+use xclip::clip_boundary;
+
+mod report;
+pub use report::{LoadReport, LoadWarning};
+
+mod schedule;
+pub use schedule::{ScheduleGrouping, ScheduleRow, write_schedule_csv};
 
 /// A valid handle for an [`Entity`](dxf::entities::Entity) present in the drawing.
+///
+/// Entity handles are serialized as their [stable IDs](Self::stable_id).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
 pub struct EntityHandle(pub(crate) NonZeroU64);
 
+impl EntityHandle {
+    /// Get the stable ID of the entity, which is its handle in the DXF.
+    ///
+    /// Handles are saved in the file, so this identifies the same entity after reloading.
+    pub fn stable_id(self) -> u64 {
+        self.0.get()
+    }
+}
+
 /// A valid handle for a [`Layer`](dxf::tables::Layer) present in the drawing.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct LayerHandle(pub(crate) NonZeroU64);
 
-/// Convert an entity to a [`BezPath`].
+/// Convert an entity to an [`AnyShape`].
+///
+/// Lines, circles, arcs, and elliptical arcs are kept as primitives, and other entities
+/// are converted to paths with [`path_from_entity`].
 #[tracing::instrument(skip_all)]
-pub fn path_from_entity(e: &dxf::entities::Entity) -> Option<BezPath> {
+pub fn shape_from_entity(e: &dxf::entities::Entity) -> Option<AnyShape> {
     match e.specific {
         EntityType::Arc(ref a) => {
             // FIXME: currently only support viewing from +Z.
@@ -73,7 +180,7 @@ pub fn path_from_entity(e: &dxf::entities::Entity) -> Option<BezPath> {
                     sweep_angle: -(end_angle - start_angle).rem_euclid(360.0).to_radians(),
                     x_rotation: 0.0,
                 }
-                .to_path(DEFAULT_ACCURACY),
+                .into(),
             )
         }
         EntityType::Line(ref line) => {
@@ -82,10 +189,13 @@ pub fn path_from_entity(e: &dxf::entities::Entity) -> Option<BezPath> {
                 return None;
             }
 
-            let mut l = BezPath::new();
-            l.move_to(point_from_dxf_point(&line.p1));
-            l.line_to(point_from_dxf_point(&line.p2));
-            Some(l)
+            Some(
+                Line::new(
+                    point_from_dxf_point(&line.p1),
+                    point_from_dxf_point(&line.p2),
+                )
+                .into(),
+            )
         }
         EntityType::Circle(ref circle) => {
             // FIXME: currently only support viewing from +Z.
@@ -98,7 +208,7 @@ pub fn path_from_entity(e: &dxf::entities::Entity) -> Option<BezPath> {
                     center: point_from_dxf_point(&circle.center),
                     radius: circle.radius,
                 }
-                .to_path(DEFAULT_ACCURACY),
+                .into(),
             )
         }
         EntityType::Ellipse(ref ellipse) => {
@@ -126,9 +236,21 @@ pub fn path_from_entity(e: &dxf::entities::Entity) -> Option<BezPath> {
                         .rem_euclid(2.0 * std::f64::consts::PI),
                     x_rotation: major_axis.angle(),
                 }
-                .to_path(DEFAULT_ACCURACY),
+                .into(),
             )
         }
+        _ => path_from_entity(e).map(AnyShape::from),
+    }
+}
+
+/// Convert an entity to a [`BezPath`].
+#[tracing::instrument(skip_all)]
+pub fn path_from_entity(e: &dxf::entities::Entity) -> Option<BezPath> {
+    match e.specific {
+        EntityType::Arc(..)
+        | EntityType::Line(..)
+        | EntityType::Circle(..)
+        | EntityType::Ellipse(..) => shape_from_entity(e).map(|s| s.into_path(DEFAULT_ACCURACY)),
         EntityType::LwPolyline(ref lwp) => {
             // FIXME: currently only support viewing from +Z.
             if lwp.extrusion_direction.z != 1.0 {
@@ -466,11 +588,13 @@ pub fn point_from_dxf_point(p: &dxf::Point) -> Point {
 )]
 pub struct DrawingInfo {
     drawing: Drawing,
+    /// Code page for decoding legacy text.
+    code_page: CodePage,
 }
 
 impl DrawingInfo {
-    pub(crate) fn new(drawing: Drawing) -> Self {
-        Self { drawing }
+    pub(crate) fn new(drawing: Drawing, code_page: CodePage) -> Self {
+        Self { drawing, code_page }
     }
 
     /// Get an entity in the drawing.
@@ -484,6 +608,14 @@ impl DrawingInfo {
         };
         e
     }
+
+    /// Get the [font family names](font_family_name) that text styles in the drawing ask for.
+    pub fn font_family_names(&self) -> BTreeSet<String> {
+        self.drawing
+            .styles()
+            .filter_map(|s| font_family_name(&s.primary_font_file_name))
+            .collect()
+    }
 }
 
 /// Adapt line weights to [`FatPaint`] strokes for rendering.
@@ -493,6 +625,10 @@ pub struct RestrokePaint {
     pub weight: u64,
     /// The target [`PaintHandle`].
     pub handle: PaintHandle,
+    /// Uniform scale of the item transforms the paint is drawn with, below the view.
+    ///
+    /// This is not 1.0 for paints of shared block geometry in scaled inserts.
+    pub scale: f64,
 }
 
 impl RestrokePaint {
@@ -526,14 +662,17 @@ impl RestrokePaint {
         max_stroke: f64,
     ) {
         let pxw = (self.weight as f64 / pitch as f64).clamp(min_stroke, max_stroke);
-        let p = graphics.get_paint_mut(self.handle);
-        p.stroke = Stroke::new(pxw / view_scale);
+        *graphics.get_stroke_mut(self.handle) = Stroke::new(pxw / (view_scale * self.scale));
     }
 }
 
 impl From<(u64, PaintHandle)> for RestrokePaint {
     fn from((weight, handle): (u64, PaintHandle)) -> Self {
-        Self { weight, handle }
+        Self {
+            weight,
+            handle,
+            scale: 1.0,
+        }
     }
 }
 
@@ -545,33 +684,105 @@ impl From<(u64, PaintHandle)> for RestrokePaint {
 pub struct TDDrawing {
     /// `GraphicsBag` containing drawn items.
     pub graphics: GraphicsBag,
-    /// Mapping from graphics items to entity handles.
-    pub item_entity_map: BTreeMap<ItemHandle, EntityHandle>,
+    /// Mapping from graphics items to the entities they are drawn for.
+    pub item_entity_map: ItemEntityMap,
+    /// Bounds of the items drawn for each entity, in drawing coordinates.
+    ///
+    /// These are computed while loading, before paints are [restroked](Self::restroke),
+    /// so they cover geometry without stroke widths, which depend on the view; text is
+    /// [estimated](tabulon::bounds::EstimatedText).
+    pub entity_extents: BTreeMap<EntityHandle, Rect>,
     /// Entities for layers.
     pub entity_layer_map: BTreeMap<EntityHandle, LayerHandle>,
     /// Render layer in drawing order.
     pub render_layer: RenderLayer,
+    /// Render layers for each DXF layer, in layer table order.
+    ///
+    /// Layers that are off in the drawing are not visible.
+    pub layer_stack: LayerStack,
+    /// Mapping from DXF layers to layers in `layer_stack`.
+    pub stacked_layers: BTreeMap<LayerHandle, StackedLayerHandle>,
     /// Enabled layers.
     pub enabled_layers: BTreeSet<LayerHandle>,
     /// Layer names.
     pub layer_names: BTreeMap<LayerHandle, sync::Arc<str>>,
     /// Drawing information object.
     pub info: DrawingInfo,
+    /// Drawing version and content that is not displayed faithfully.
+    pub report: LoadReport,
     /// Paints that need stroke widths computed relative to view.
     ///
     /// See [`RestrokePaint`].
     pub restroke_paints: sync::Arc<[RestrokePaint]>,
+    /// Paints shared by entities with the same style.
+    paints: PaintTable,
+    /// Pitch, view scale, and stroke limits that paints were last
+    /// [restroked](Self::restroke) for.
+    restroked: Option<(u64, f64, f64, f64)>,
 }
 
-use parley::{FontStyle, FontWeight, FontWidth, GenericFamily, StyleProperty};
+impl TDDrawing {
+    /// Adapt the strokes of `restroke_paints` to a view, as by [`RestrokePaint::adapt`].
+    ///
+    /// Paints are only rewritten when the arguments differ from the last time, or when
+    /// paints have been added since, such as by [`explode`](Self::explode), so calling
+    /// this for every view change costs nothing while panning. Returns whether paints
+    /// were rewritten.
+    pub fn restroke(
+        &mut self,
+        pitch: u64,
+        view_scale: f64,
+        min_stroke: f64,
+        max_stroke: f64,
+    ) -> bool {
+        let args = Some((pitch, view_scale, min_stroke, max_stroke));
+        if self.restroked == args {
+            return false;
+        }
+        for r in self.restroke_paints.iter() {
+            r.adapt(
+                &mut self.graphics,
+                pitch,
+                view_scale,
+                min_stroke,
+                max_stroke,
+            );
+        }
+        self.restroked = args;
+        true
+    }
+}
 
 /// Check if the font size of a [`StyleSet`] is zero.
+#[cfg(feature = "text")]
 fn style_size_is_zero(s: &StyleSet<Option<Color>>) -> bool {
     s.inner()
         .get(&core::mem::discriminant(&StyleProperty::FontSize(0_f32)))
         .is_none_or(|x| matches!(x, StyleProperty::FontSize(0_f32)))
 }
 
+/// Get the family name that text styles ask for with a font file, such as `romans` for
+/// `romans.shx` or `arial` for `Arial.ttf`.
+///
+/// Text styles name this family before a generic family guessed from the font, so
+/// applications can supply fonts for drawings, for example by mapping `romans` to a
+/// bundled font with `tabulon_vello`'s `Environment::map_font_family`. Fonts
+/// that are installed with that family name are used without doing anything.
+///
+/// Returns `None` if no font file is named.
+pub fn font_family_name(font_file: &str) -> Option<String> {
+    let name = font_file.rsplit(['/', '\\']).next().unwrap_or(font_file);
+    let stem = match name.rsplit_once('.') {
+        Some((stem, ext))
+            if ["shx", "ttf", "otf", "ttc"].contains(&ext.to_ascii_lowercase().as_str()) =>
+        {
+            stem
+        }
+        _ => name,
+    };
+    (!stem.is_empty()).then(|| stem.to_ascii_lowercase())
+}
+
 /// Recover color enum value from [`dxf::Color`] as it is currently not in the API.
 fn recover_color_enum(c: &dxf::Color) -> i16 {
     if c.is_by_layer() {
@@ -593,8 +804,9 @@ fn recover_color_enum(c: &dxf::Color) -> i16 {
 pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing> {
     let mut gb = GraphicsBag::default();
     let mut rl = RenderLayer::default();
-    let mut item_entity_map = BTreeMap::new();
+    let mut item_entity_map = ItemEntityMap::new();
     let mut entity_layer_map = BTreeMap::new();
+    let mut entity_extents = BTreeMap::new();
 
     // FIXME: use real colors and line widths, and expose information for line scaling.
     //        This currently sets the paint at position 0/default in the palette.
@@ -602,9 +814,46 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
         stroke: Default::default(),
         stroke_paint: Some(Color::BLACK.into()),
         fill_paint: None,
+        fill_rule: None,
     });
 
-    let drawing = Drawing::load_file(path)?;
+    // Legacy text is decoded with the drawing's own code page, which is found from the
+    // header alone, so that the drawing is only parsed once.
+    #[cfg(feature = "encoding")]
+    let header = code_page::read_header(std::io::BufReader::new(std::fs::File::open(&path)?));
+    #[cfg(feature = "encoding")]
+    let drawing = match header
+        .as_ref()
+        .and_then(|h| CodePage::for_drawing(h).filter(|c| c.needs_reload(h)))
+    {
+        Some(code_page) => Drawing::load_file_with_encoding(&path, code_page.encoding())?,
+        None => Drawing::load_file(&path)?,
+    };
+    #[cfg(not(feature = "encoding"))]
+    let drawing = Drawing::load_file(&path)?;
+    let mut report = LoadReport::new(&drawing);
+    let code_page = CodePage::for_drawing(&drawing).unwrap_or_else(|| {
+        report.warnings.push(LoadWarning::UnsupportedCodePage(
+            drawing.header.drawing_code_page.clone(),
+        ));
+        CodePage::ANSI_1252
+    });
+    // If the header couldn't be read alone, such as from binary DXF, legacy text was
+    // decoded as Windows-1252, so load again with the drawing's own code page. This
+    // parses the drawing twice.
+    #[cfg(feature = "encoding")]
+    let drawing = if header.is_none() && code_page.needs_reload(&drawing) {
+        Drawing::load_file_with_encoding(&path, code_page.encoding())?
+    } else {
+        drawing
+    };
+    let mut drawing = drawing;
+    let count = repair_handles(&mut drawing);
+    if count > 0 {
+        report
+            .warnings
+            .push(LoadWarning::SyntheticHandles { count });
+    }
 
     let visible_layers: BTreeSet<&str> = drawing
         .layers()
@@ -629,6 +878,20 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
         })
         .collect();
 
+    let mut layer_stack = LayerStack::default();
+    let stacked_layers: BTreeMap<LayerHandle, StackedLayerHandle> = drawing
+        .layers()
+        .map(|l| {
+            (
+                LayerHandle(NonZeroU64::new(l.handle.0).unwrap()),
+                layer_stack.push(StackedLayer {
+                    visible: l.is_layer_on,
+                    ..Default::default()
+                }),
+            )
+        })
+        .collect();
+
     let handle_for_layer_name: BTreeMap<&str, LayerHandle> = drawing
         .layers()
         .map(|l| {
@@ -644,153 +907,13 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
         .map(|l| (LayerHandle(NonZeroU64::new(l.handle.0).unwrap()), l))
         .collect();
 
-    let mut blocks: BTreeMap<&str, Vec<(i16, i16, BezPath)>> = BTreeMap::new();
-    {
-        // Blocks that depend on another block which is not realized.
-        let mut unresolved_blocks: Vec<&dxf::Block> = drawing.blocks().collect();
-        let mut there_is_absolutely_no_hope = false;
-        while !unresolved_blocks.is_empty() && !there_is_absolutely_no_hope {
-            // I acknowledge that this is technically not very efficient in some cases
-            // but I am too lazy to build a DAG here, and rarely will it matter.
-            there_is_absolutely_no_hope = true;
-            'block: for b in unresolved_blocks.iter() {
-                // Form up shapes with contiguous line weight and color.
-                let mut lines = BezPath::new();
-                // Chunk blocks by the combination of line weight and color.
-                // To retain drawing order, multiple chunks may be emitted for a single block.
-                let mut chunks: Vec<(i16, i16, BezPath)> = vec![];
-                if b.entities.is_empty() {
-                    blocks.insert(b.name.as_str(), chunks);
-                    continue;
-                }
-
-                let resolve_style = |lh: LayerHandle, lw: i16, ce: i16| {
-                    let layer = layers[&lh];
-                    let line_weight = if lw == -2 {
-                        if layer.line_weight.raw_value() < 0 {
-                            25_i16
-                        } else {
-                            layer.line_weight.raw_value()
-                        }
-                    } else {
-                        lw
-                    };
-                    let color = if ce == 256 {
-                        // BYLAYER: resolve to a palette value during block resolution.
-                        if let Some(i) = layer.color.index() {
-                            i as i16
-                        } else {
-                            // white if layer doesn't have a resolvable color.
-                            7_i16
-                        }
-                    } else {
-                        ce
-                    };
+    // Dynamic block instances are drawn in their saved state when it is available.
+    let objects = ObjectIndex::new(&drawing);
+    let representations = Representations::new(&drawing, &objects);
 
-                    (line_weight, color)
-                };
-
-                let mut cur_style = resolve_style(
-                    handle_for_layer_name[b.entities[0].common.layer.as_str()],
-                    b.entities[0].common.lineweight_enum_value,
-                    recover_color_enum(&b.entities[0].common.color),
-                );
-
-                for e in b.entities.iter() {
-                    let lh = handle_for_layer_name[e.common.layer.as_str()];
-                    let style = resolve_style(
-                        lh,
-                        if matches!(e.specific, EntityType::Solid(..)) {
-                            // Use `i16::MIN` for solid fills.
-                            i16::MIN
-                        } else {
-                            e.common.lineweight_enum_value
-                        },
-                        recover_color_enum(&e.common.color),
-                    );
-                    if style != cur_style {
-                        chunks.push((cur_style.0, cur_style.1, lines));
-                        lines = BezPath::new();
-                        cur_style = style;
-                    }
-
-                    match e.specific {
-                        // Try the next block if this one depends on an unresolved block.
-                        EntityType::Insert(dxf::entities::Insert { ref name, .. })
-                            if !blocks.contains_key(name.as_str()) =>
-                        {
-                            continue 'block;
-                        }
-                        EntityType::Insert(ref ins) => {
-                            // FIXME: currently only support viewing from +Z.
-                            if ins.extrusion_direction.z != 1.0 {
-                                continue;
-                            }
-                            if let Some(b) = blocks.get(ins.name.as_str()) {
-                                let base_transform = Affine::scale_non_uniform(
-                                    ins.x_scale_factor,
-                                    ins.y_scale_factor,
-                                );
-                                let location = point_from_dxf_point(&ins.location);
-
-                                if !lines.is_empty() {
-                                    // Always push a chunk before an insert if not empty.
-                                    chunks.push((cur_style.0, cur_style.1, lines));
-                                }
-
-                                // Push arrayed/transformed versions of each chunk in the block.
-                                for (lw, ce, clines) in b {
-                                    let local_linewidth = if *lw == -1 {
-                                        // BYBLOCK: inherit from this insert.
-                                        cur_style.0
-                                    } else {
-                                        // Other values are already realized in the chunk as
-                                        // either absolute widths, or the default width `-3`.
-                                        *lw
-                                    };
-                                    let local_color = if *ce == 0 {
-                                        // BYBLOCK: inherit from this insert.
-                                        cur_style.1
-                                    } else {
-                                        // Other values are already realized in the chunk.
-                                        *ce
-                                    };
-                                    lines = BezPath::new();
-                                    for i in 0..ins.row_count {
-                                        for j in 0..ins.column_count {
-                                            let transform = base_transform
-                                                .then_translate(Vec2::new(
-                                                    j as f64 * ins.column_spacing,
-                                                    i as f64 * ins.row_spacing,
-                                                ))
-                                                .then_rotate(-ins.rotation.to_radians())
-                                                .then_translate(location.to_vec2());
-                                            // Add the transformed instance to the new path.
-                                            lines.extend(transform * clines);
-                                        }
-                                    }
-                                    chunks.push((local_linewidth, local_color, lines));
-                                }
-                                lines = BezPath::new();
-                            }
-                        }
-                        _ => {
-                            if let Some(s) = path_from_entity(e) {
-                                lines.extend(s);
-                            }
-                        }
-                    }
-                }
-                if !lines.is_empty() {
-                    chunks.push((cur_style.0, cur_style.1, lines));
-                }
-                there_is_absolutely_no_hope = false;
-                blocks.insert(b.name.as_str(), chunks);
-            }
-            unresolved_blocks.retain(|b| !blocks.contains_key(b.name.as_str()));
-        }
-    }
+    let blocks = resolve_blocks(&drawing, &representations);
 
+    #[cfg(feature = "text")]
     let styles: BTreeMap<&str, StyleSet<Option<Color>>> = drawing
         .styles()
         .map(
@@ -812,9 +935,8 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                     ))));
                 }
 
-                // TODO: Handle text_generation_flags somehow; My understanding is:
-                //        - The second bit means the text is mirrored lengthwise
-                //        - The third bit means the text is mirrored vertically
+                // The style's text_generation_flags are only defaults for new text,
+                // TEXT entities have their own copy, which is what gets applied.
 
                 // This is a selection of shx file names I've seen in the wild.
                 //
@@ -823,44 +945,51 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                 //
                 //       Sometimes the file names have the .shx, sometimes they do not,
                 //       there appears to be neither rhyme nor reason to it.
-                match s.primary_font_file_name.as_str() {
+                let generic = match s.primary_font_file_name.as_str() {
                     // Monospace version of txt.shx
-                    "monotxt" | "monotxt.shx" => pstyle.insert(GenericFamily::Monospace.into()),
+                    "monotxt" | "monotxt.shx" => GenericFamily::Monospace,
                     // Italic roman type lined once.
                     "italic" | "italic.shx" => {
-                        pstyle.insert(GenericFamily::Serif.into());
-                        pstyle.insert(StyleProperty::FontStyle(FontStyle::Italic))
+                        pstyle.insert(StyleProperty::FontStyle(FontStyle::Italic));
+                        GenericFamily::Serif
                     }
                     // Roman (serif) type lined once.
-                    "romans" | "romans.shx" => pstyle.insert(GenericFamily::Serif.into()),
+                    "romans" | "romans.shx" => GenericFamily::Serif,
                     // Condensed Roman type lined once.
                     "romanc" | "romanc.shx" => {
-                        pstyle.insert(GenericFamily::Serif.into());
-                        pstyle.insert(StyleProperty::FontWidth(FontWidth::CONDENSED))
+                        pstyle.insert(StyleProperty::FontWidth(FontWidth::CONDENSED));
+                        GenericFamily::Serif
                     }
                     // Roman type lined twice, seems like bold.
                     "romand" | "romand.shx" => {
-                        pstyle.insert(GenericFamily::Serif.into());
-                        pstyle.insert(StyleProperty::FontWeight(FontWeight::BOLD))
+                        pstyle.insert(StyleProperty::FontWeight(FontWeight::BOLD));
+                        GenericFamily::Serif
                     }
                     // Roman type lined thrice, seems like bolder.
                     "romant" | "romant.shx" => {
-                        pstyle.insert(GenericFamily::Serif.into());
-                        pstyle.insert(StyleProperty::FontWeight(FontWeight::EXTRA_BOLD))
+                        pstyle.insert(StyleProperty::FontWeight(FontWeight::EXTRA_BOLD));
+                        GenericFamily::Serif
                     }
-                    "script" | "script.shx" => pstyle.insert(GenericFamily::Cursive.into()),
+                    "script" | "script.shx" => GenericFamily::Cursive,
                     // Covers common "txt" | "txt.shx" | "simplex.shx" | "isocp.shx" | "gothic.shx"
-                    _ => pstyle.insert(GenericFamily::SansSerif.into()),
+                    _ => GenericFamily::SansSerif,
                 };
+                // The font is named first, so applications can supply it.
+                let families = font_family_name(&s.primary_font_file_name)
+                    .map(|name| FontFamily::Named(name.into()))
+                    .into_iter()
+                    .chain([FontFamily::Generic(generic)])
+                    .collect::<Vec<_>>();
+                pstyle.insert(StyleProperty::FontStack(FontStack::List(families.into())));
 
                 (s.name.as_str(), pstyle)
             },
         )
         .collect();
 
-    // Paints keyed on concrete rgba color, and concrete line width (in iotas).
-    let mut paints: BTreeMap<(u32, u64), PaintHandle> = BTreeMap::new();
-    let mut fills: BTreeMap<u32, PaintHandle> = BTreeMap::new();
+    let mut paints = PaintTable::default();
+    // Number of entities of each type that are not drawn.
+    let mut unsupported: BTreeMap<&'static str, usize> = BTreeMap::new();
 
     for e in drawing.entities() {
         if !e.common.is_visible
@@ -874,77 +1003,8 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
 
         let layer = layers[&lh];
 
-        let mut resolve_paint = |gb: &mut GraphicsBag, lw: i16, c: i16| {
-            // Resolve color.
-            let opaque_color = match c {
-                // BYENTITY
-                257 => e.common.color_24_bit as u32,
-                // BYLAYER
-                256 => {
-                    if let Some(i) = layer.color.index() {
-                        ACI[i as usize]
-                    } else {
-                        u32::MAX
-                    }
-                }
-                // Indexed colors.
-                1..=255 => ACI[c as usize],
-                // Other values generally not valid in this context.
-                _ => u32::MAX,
-            };
-            let combined_color =
-                (opaque_color << 8) | (0xFF - (e.common.transparency as u32 & 0xFF));
-
-            /// Default line weight.
-            const LWDEFAULT: u64 = 250 * MICROMETER;
-
-            // Resolve line width.
-            let lwconcrete = match lw {
-                -3 => LWDEFAULT,
-                // BYLAYER.
-                -2 => {
-                    if layer.line_weight.raw_value() <= 0 {
-                        // BYLAYER and BYBLOCK are both meaningless in a layer,
-                        // therefore, use the default for all enumerations.
-                        LWDEFAULT
-                    } else {
-                        layer.line_weight.raw_value() as u64 * 10 * MICROMETER
-                    }
-                }
-                // BYBLOCK (-1) Should not occur at the entity level, use default.
-                //
-                // Other negative values occur in the wild but have no standard
-                // meaning, as such all negative values not specifically handled
-                // above should have the default line width.
-                i if i < 0 => LWDEFAULT,
-                i => i as u64 * 10 * MICROMETER,
-            };
-
-            let r = ((combined_color >> 24) & 0xFF) as u8;
-            let g = ((combined_color >> 16) & 0xFF) as u8;
-            let b = ((combined_color >> 8) & 0xFF) as u8;
-            let a = (combined_color & 0xFF) as u8;
-
-            if lw == i16::MIN {
-                // `i16::MIN` reserved for solid fills
-                *fills.entry(combined_color).or_insert_with(|| {
-                    gb.register_paint(FatPaint {
-                        fill_paint: Some(Color::from_rgba8(r, g, b, a).into()),
-                        ..Default::default()
-                    })
-                })
-            } else {
-                *paints
-                    .entry((combined_color, lwconcrete))
-                    .or_insert_with(|| {
-                        // At first these do not have stroke width, this needs to be set afterward.
-                        gb.register_paint(FatPaint {
-                            stroke_paint: Some(Color::from_rgba8(r, g, b, a).into()),
-                            ..Default::default()
-                        })
-                    })
-            }
-        };
+        let mut resolve_paint =
+            |gb: &mut GraphicsBag, lw: i16, c: i16| paints.resolve(gb, layer, &e.common, lw, c);
 
         // Get or create the appropriate PaintHandle for this entity.
         let entity_paint = resolve_paint(
@@ -953,8 +1013,7 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                 e.specific,
                 EntityType::Solid(..) | EntityType::Text(..) | EntityType::MText(..)
             ) {
-                // Use `i16::MIN` for solid fills.
-                i16::MIN
+                SOLID_FILL
             } else {
                 e.common.lineweight_enum_value
             },
@@ -963,8 +1022,16 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
 
         let mut push_item = |gb: &mut GraphicsBag, item: GraphicsItem| {
             let ih = rl.push_with_bag(gb, item);
+            if let Some(sl) = layer_stack.get_mut(stacked_layers[&lh]) {
+                sl.layer.indices.push(ih);
+            }
             item_entity_map.insert(ih, eh);
             entity_layer_map.insert(eh, lh);
+            add_extent(
+                &mut entity_extents,
+                eh,
+                gb.item_bounds_below_root_with(ih, &mut EstimatedText),
+            );
         };
 
         match e.specific {
@@ -974,53 +1041,62 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                     continue;
                 }
 
-                if let Some(b) = blocks.get(ins.name.as_str()) {
-                    let base_transform =
-                        Affine::scale_non_uniform(ins.x_scale_factor, ins.y_scale_factor);
-                    let location = point_from_dxf_point(&ins.location);
-
-                    for (lw, ce, clines) in b {
-                        let chunk_paint = resolve_paint(
-                            &mut gb,
-                            if *lw == -1 {
-                                // BYBLOCK: inherit from this insert.
-                                e.common.lineweight_enum_value
-                            } else {
-                                *lw
-                            },
-                            if *ce == 0 {
-                                // BYBLOCK: inherit from this insert.
-                                recover_color_enum(&e.common.color)
-                            } else {
-                                *ce
-                            },
-                        );
-                        let mut path = BezPath::new();
-                        for i in 0..ins.row_count {
-                            for j in 0..ins.column_count {
-                                let transform = base_transform
-                                    .then_translate(Vec2::new(
-                                        j as f64 * ins.column_spacing,
-                                        i as f64 * ins.row_spacing,
-                                    ))
-                                    .then_rotate(-ins.rotation.to_radians())
-                                    .then_translate(location.to_vec2());
-
-                                path.extend(transform * clines);
+                if let Some(b) = blocks.get(representations.insert_block(e, ins)) {
+                    // Inserts clipped with XCLIP are clipped to the boundary of each instance.
+                    let clip = clip_boundary(&objects, e)
+                        .map(|boundary| {
+                            let mut path = BezPath::new();
+                            for transform in insert_transforms(ins) {
+                                path.extend(transform * &boundary);
                             }
-                        }
-                        push_item(
-                            &mut gb,
-                            FatShape {
+                            gb.register_clip(FatClip {
+                                transform: Default::default(),
                                 path: sync::Arc::from(path),
-                                paint: chunk_paint,
-                                ..Default::default()
-                            }
-                            .into(),
-                        );
+                            })
+                        })
+                        .unwrap_or_default();
+
+                    // Draw the shared chunks of the block for each instance, with a transform
+                    // for the instance, binding BYBLOCK values to this insert.
+                    let by_block = (
+                        e.common.lineweight_enum_value,
+                        recover_color_enum(&e.common.color),
+                    );
+                    let mut instance: Option<(Affine, TransformHandle)> = None;
+                    for transform in insert_transforms(ins) {
+                        for_each_chunk(b, transform, by_block, &mut |transform, lw, c, shape| {
+                            // Chunks of the same instance share its transform.
+                            let th = match instance {
+                                Some((t, th)) if t == transform => th,
+                                _ => {
+                                    let th = gb.register_transform(Default::default(), transform);
+                                    instance = Some((transform, th));
+                                    th
+                                }
+                            };
+                            let paint = paints.resolve_scaled(
+                                &mut gb,
+                                layer,
+                                &e.common,
+                                lw,
+                                c,
+                                uniform_scale(transform),
+                            );
+                            push_item(
+                                &mut gb,
+                                FatShape {
+                                    transform: th,
+                                    shape: shape.clone(),
+                                    paint,
+                                    clip,
+                                }
+                                .into(),
+                            );
+                        });
                     }
                 }
             }
+            #[cfg(feature = "text")]
             #[allow(clippy::cast_possible_truncation, reason = "It doesn't matter")]
             EntityType::MText(ref mt) => {
                 // FIXME: currently only support viewing from +Z.
@@ -1028,10 +1104,7 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                     continue;
                 }
 
-                // TODO: Parse MTEXT encoded characters to Unicode equivalents.
-                // TODO: Set up background fills.
                 // TODO: Handle inline style changes?
-                // TODO: Handle columns.
                 // TODO: Handle paragraph styles.
                 // TODO: Handle rotation.
                 let mut nt = mt.text.clone();
@@ -1039,28 +1112,18 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                     nt.push_str(ext);
                 }
 
-                // TODO: Implement a shared parser for scanning formatting codes into styled text
-                //       and doing unicode substitution for special character codes.
-                let nt = nt
-                    .replace("%%c", "∅")
-                    .replace("%%d", "°")
-                    .replace("%%p", "±")
-                    .replace("%%C", "∅")
-                    .replace("%%D", "°")
-                    .replace("%%P", "±")
-                    .replace("%%%", "%")
-                    // TODO: Implement start/stop underline with styled text.
-                    .replace("\\L", "")
-                    .replace("\\l", "")
-                    // TODO: Implement start/stop overline with styled text.
-                    .replace("\\O", "")
-                    .replace("\\o", "")
-                    // TODO: Implement start/stop strikethrough with styled text.
-                    .replace("\\S", "")
-                    .replace("\\s", "")
-                    .replace("\\P", "\n")
-                    .replace("\\A1;", "")
-                    .replace("\\A0;", "");
+                // TODO: Scan more formatting codes into styled text.
+                let (nt, colors) = decode_mtext_colors(&nt, |b| code_page.decode_byte(b));
+                let spans = colors
+                    .into_iter()
+                    .map(|(range, rgb)| {
+                        let [_, r, g, b] = rgb.to_be_bytes();
+                        StyleSpan::new(
+                            range,
+                            [StyleProperty::Brush(Some(Color::from_rgb8(r, g, b)))],
+                        )
+                    })
+                    .collect();
 
                 let x_angle = Vec2 {
                     x: mt.x_axis_direction.x,
@@ -1092,6 +1155,42 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                     }
                 };
 
+                let background = match mt.background_fill_setting {
+                    BackgroundFillSetting::Off => None,
+                    setting => {
+                        let opaque_color = match setting {
+                            BackgroundFillSetting::UseBackgroundFillColor
+                                if mt.background_color_rgb != 0 =>
+                            {
+                                mt.background_color_rgb as u32 & 0xFF_FFFF
+                            }
+                            BackgroundFillSetting::UseBackgroundFillColor => mt
+                                .background_fill_color
+                                .index()
+                                .map_or(ACI[7], |i| ACI[i as usize]),
+                            // Drawings assume a black background, see the ACI palette.
+                            _ => 0,
+                        };
+                        Some(TextBackground {
+                            paint: paints.fill(&mut gb, (opaque_color << 8) | 0xFF),
+                            // The box scale is relative to the text height.
+                            margin: (mt.fill_box_scale - 1.0).max(0.0) * mt.initial_text_height,
+                        })
+                    }
+                };
+
+                // TODO: Reverse the column order when `is_column_flow_reversed` is set.
+                #[allow(clippy::cast_possible_truncation, reason = "It doesn't matter")]
+                let columns = (mt.column_type != 0 && mt.column_width > 0.0).then(|| TextColumns {
+                    count: mt.column_count.max(0) as u32,
+                    width: mt.column_width as f32,
+                    gutter: mt.column_gutter as f32,
+                    // Columns with automatic heights are balanced, as the defined height isn't available.
+                    height: (!mt.is_column_auto_height)
+                        .then(|| mt.column_heights.first().map(|&h| h as f32))
+                        .flatten(),
+                });
+
                 push_item(
                     &mut gb,
                     FatText {
@@ -1113,18 +1212,26 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                                 }
                             },
                         ),
+                        spans,
                         alignment,
+                        direction: Default::default(),
                         insertion: DirectIsometry::new(
                             // As far as I'm aware, x_axis_direction and rotation are exclusive.
                             -mt.rotation_angle.to_radians() + x_angle,
                             point_from_dxf_point(&mt.insertion_point).to_vec2(),
                         ),
                         max_inline_size,
+                        columns,
+                        background,
+                        // MTEXT can't be mirrored other than through its transform.
+                        mirror_x: false,
+                        mirror_y: false,
                         attachment_point,
                     }
                     .into(),
                 );
             }
+            #[cfg(feature = "text")]
             EntityType::Text(ref t) => {
                 // FIXME: currently only support viewing from +Z.
                 if t.normal.z != 1.0 {
@@ -1134,21 +1241,8 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                 // TODO: Handle second_alignment_point etc?
                 // TODO: Handle relative_x_scale_factor.
 
-                // TODO: Implement a shared parser for scanning formatting codes into styled text
-                //       and doing unicode substitution for special character codes.
-                let text = t
-                    .value
-                    .replace("%%c", "∅")
-                    .replace("%%d", "°")
-                    .replace("%%p", "±")
-                    .replace("%%C", "∅")
-                    .replace("%%D", "°")
-                    .replace("%%P", "±")
-                    .replace("%%%", "%")
-                    // TODO: implement toggle underline with styled text.
-                    .replace("%%u", "")
-                    // TODO: implement toggle overline with styled text.
-                    .replace("%%o", "");
+                // TODO: Scan formatting codes into styled text.
+                let text = decode_text(&t.value, TextFlavor::Text, |b| code_page.decode_byte(b));
 
                 #[allow(clippy::cast_possible_truncation, reason = "It doesn't matter")]
                 push_item(
@@ -1175,49 +1269,112 @@ pub fn load_file_default_layers(path: impl AsRef<Path>) -> DxfResult<TDDrawing>
                                 sized
                             },
                         ),
+                        spans: Default::default(),
                         alignment: Default::default(),
+                        direction: Default::default(),
                         insertion: DirectIsometry::new(
                             -t.rotation.to_radians(),
                             point_from_dxf_point(&t.location).to_vec2(),
                         ),
                         max_inline_size: None,
+                        columns: None,
+                        background: None,
+                        mirror_x: t.is_text_backwards(),
+                        mirror_y: t.is_text_upside_down(),
                         attachment_point: Default::default(),
                     }
                     .into(),
                 );
             }
+            EntityType::ModelPoint(ref mp) => {
+                if let Some(item) = point_item(
+                    mp,
+                    drawing.header.point_display_mode,
+                    drawing.header.point_display_size,
+                    entity_paint,
+                ) {
+                    push_item(&mut gb, item);
+                }
+            }
             _ => {
-                if let Some(s) = path_from_entity(e) {
+                if let Some(s) = shape_from_entity(e) {
                     push_item(
                         &mut gb,
                         FatShape {
-                            path: sync::Arc::from(s),
+                            shape: sync::Arc::new(s),
                             paint: entity_paint,
                             ..Default::default()
                         }
                         .into(),
                     );
+                } else if !is_path_entity_type(&e.specific) {
+                    *unsupported
+                        .entry(dxf_entity_type_name(&e.specific))
+                        .or_default() += 1;
                 }
             }
         }
     }
+    report.add_unsupported_entities(unsupported);
 
-    let restroke_paints: Vec<RestrokePaint> =
-        paints.iter().map(|((_, w), h)| (*w, *h).into()).collect();
+    // Filled boundaries in drawings are meant to be closed, even when their last
+    // vertex doesn't repeat the first, so close them as they are meant to be stroked.
+    let count = gb.close_open_fills().len();
+    if count > 0 {
+        report.warnings.push(LoadWarning::OpenFills { count });
+    }
+
+    let restroke_paints = paints.restroke_paints();
 
     Ok(TDDrawing {
         graphics: gb,
         render_layer: rl,
+        layer_stack,
+        stacked_layers,
         item_entity_map,
         entity_layer_map,
+        entity_extents,
         enabled_layers,
         layer_names,
-        info: DrawingInfo::new(drawing),
+        info: DrawingInfo::new(drawing, code_page),
+        report,
         restroke_paints: sync::Arc::from(restroke_paints.as_slice()),
+        paints,
+        restroked: None,
+    })
+}
+
+/// Grow the extent of an entity to include the extent of one of its items.
+fn add_extent(extents: &mut BTreeMap<EntityHandle, Rect>, eh: EntityHandle, extent: Option<Rect>) {
+    if let Some(extent) = extent {
+        extents
+            .entry(eh)
+            .and_modify(|r| *r = r.union(extent))
+            .or_insert(extent);
+    }
+}
+
+/// Transforms from block coordinates for each instance of an INSERT.
+///
+/// There is more than one instance if the insert has rows or columns.
+fn insert_transforms(ins: &dxf::entities::Insert) -> impl Iterator<Item = Affine> + '_ {
+    let base_transform = Affine::scale_non_uniform(ins.x_scale_factor, ins.y_scale_factor);
+    let location = point_from_dxf_point(&ins.location);
+    (0..ins.row_count).flat_map(move |i| {
+        (0..ins.column_count).map(move |j| {
+            base_transform
+                .then_translate(Vec2::new(
+                    j as f64 * ins.column_spacing,
+                    i as f64 * ins.row_spacing,
+                ))
+                .then_rotate(-ins.rotation.to_radians())
+                .then_translate(location.to_vec2())
+        })
     })
 }
 
 /// Convert a [`dxf::enums::AttachmentPoint`] to a [`tabulon::text::AttachmentPoint`].
+#[cfg(feature = "text")]
 fn dxf_attachment_point_to_tabulon(
     attachment_point: dxf::enums::AttachmentPoint,
 ) -> AttachmentPoint {
@@ -1236,8 +1393,23 @@ fn dxf_attachment_point_to_tabulon(
     }
 }
 
+/// Check whether an entity type is converted by [`path_from_entity`].
+fn is_path_entity_type(entity_type: &EntityType) -> bool {
+    matches!(
+        entity_type,
+        EntityType::Arc(_)
+            | EntityType::Line(_)
+            | EntityType::Circle(_)
+            | EntityType::Ellipse(_)
+            | EntityType::LwPolyline(_)
+            | EntityType::Polyline(_)
+            | EntityType::Spline(_)
+            | EntityType::Solid(_)
+    )
+}
+
 /// Get the type name of a DXF `EntityType`
-fn dxf_entity_type_name(entity_type: &EntityType) -> &str {
+fn dxf_entity_type_name(entity_type: &EntityType) -> &'static str {
     match entity_type {
         EntityType::Face3D(_) => "Face3D",
         EntityType::Solid3D(_) => "Solid3D",
@@ -1288,4 +1460,340 @@ fn dxf_entity_type_name(entity_type: &EntityType) -> &str {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    /// Save `drawing` to a temporary file named for `name` and this process, and load it.
+    #[cfg(feature = "std")]
+    pub(crate) fn load_drawing(name: &str, drawing: &dxf::Drawing) -> super::TDDrawing {
+        let path = std::env::temp_dir().join(alloc::format!(
+            "tabulon_dxf_{name}_{}.dxf",
+            std::process::id()
+        ));
+        drawing.save_file(&path).unwrap();
+        let td = super::load_file_default_layers(&path);
+        std::fs::remove_file(&path).ok();
+        td.unwrap()
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reproducible_translation() {
+        use dxf::{
+            Block, Drawing, Point,
+            entities::{Circle, Entity, EntityType, Insert, Line},
+        };
+
+        let mut drawing = Drawing::new();
+        // Many independent blocks, resolved in one wave, in parallel if enabled.
+        for i in 0..32 {
+            let name = alloc::format!("B{i}");
+            drawing.add_block(Block {
+                name: name.clone(),
+                entities: alloc::vec![
+                    Entity::new(EntityType::Line(Line::new(
+                        Point::new(0.0, 0.0, 0.0),
+                        Point::new(f64::from(i), 1.0, 0.0),
+                    ))),
+                    Entity::new(EntityType::Circle(Circle::new(
+                        Point::new(0.0, 0.0, 0.0),
+                        f64::from(i) + 1.0,
+                    ))),
+                ],
+                ..Default::default()
+            });
+            drawing.add_entity(Entity::new(EntityType::Insert(Insert {
+                name,
+                location: Point::new(f64::from(i) * 10.0, 0.0, 0.0),
+                ..Default::default()
+            })));
+        }
+        let path = std::env::temp_dir().join(alloc::format!(
+            "tabulon_dxf_reproducible_{}.dxf",
+            std::process::id()
+        ));
+        drawing.save_file(&path).unwrap();
+
+        let snapshot = || {
+            let mut bytes = Vec::new();
+            super::load_file_default_layers(&path)
+                .unwrap()
+                .graphics
+                .write_snapshot(&mut bytes)
+                .unwrap();
+            bytes
+        };
+        let first = snapshot();
+        let same = (0..4).all(|_| snapshot() == first);
+        std::fs::remove_file(&path).ok();
+        assert!(
+            same,
+            "Translating the same drawing should give byte-identical graphics."
+        );
+    }
+
+    #[cfg(all(feature = "std", feature = "encoding", feature = "text"))]
+    #[test]
+    fn legacy_code_page() {
+        use tabulon::GraphicsItem;
+
+        let path = std::env::temp_dir().join(alloc::format!(
+            "tabulon_dxf_legacy_code_page_{}.dxf",
+            std::process::id()
+        ));
+        let mut bytes = b"  0\nSECTION\n  2\nHEADER\n  9\n$ACADVER\n  1\nAC1009\n  9\n\
+            $DWGCODEPAGE\n  3\nANSI_1251\n  0\nENDSEC\n  0\nSECTION\n  2\nENTITIES\n\
+            0\nTEXT\n  8\n0\n 10\n0.0\n 20\n0.0\n 40\n1.0\n  1\n"
+            .to_vec();
+        bytes.extend_from_slice(b"\xC0\xE1\n  0\nENDSEC\n  0\nEOF\n");
+        std::fs::write(&path, bytes).unwrap();
+        let drawing = super::load_file_default_layers(&path);
+        std::fs::remove_file(&path).ok();
+
+        let drawing = drawing.unwrap();
+        let text =
+            drawing
+                .render_layer
+                .indices
+                .iter()
+                .find_map(|idx| match drawing.graphics.get(*idx) {
+                    Some(GraphicsItem::FatText(t)) => Some(t.text.clone()),
+                    _ => None,
+                });
+        assert_eq!(
+            text.as_deref(),
+            Some("Аб"),
+            "Text should be decoded with the code page in the header."
+        );
+    }
+
+    /// There is no exporter from Tabulon graphics to DXF, so this round trips drawings
+    /// through the `dxf` writer, checking that loading doesn't depend on how the file
+    /// was written, such as the precision of numbers.
+    #[cfg(feature = "std")]
+    #[test]
+    fn stable_across_resave() {
+        use super::LoadWarning;
+        use dxf::{
+            Drawing, LwPolylineVertex, Point, Vector,
+            entities::{Arc, Circle, Entity, EntityType, Line, LwPolyline, XLine},
+            enums::AcadVersion,
+            tables::Layer,
+        };
+
+        let mut drawing = Drawing::new();
+        // Lightweight polylines and construction lines are not written to R12 files.
+        drawing.header.version = AcadVersion::R2000;
+        drawing.add_layer(Layer {
+            name: "walls".into(),
+            ..Default::default()
+        });
+        let on_layer = |layer: &str, specific| {
+            let mut e = Entity::new(specific);
+            e.common.layer = layer.into();
+            e
+        };
+        drawing.add_entity(on_layer(
+            "walls",
+            EntityType::Line(Line::new(
+                Point::new(0.1, 0.2, 0.0),
+                Point::new(1.0 / 3.0, 7.5, 0.0),
+            )),
+        ));
+        drawing.add_entity(on_layer(
+            "0",
+            EntityType::Circle(Circle::new(Point::new(5.0, 5.0, 0.0), 2.0_f64.sqrt())),
+        ));
+        drawing.add_entity(on_layer(
+            "walls",
+            EntityType::Arc(Arc::new(Point::new(-3.0, 1.0, 0.0), 1.5, 10.0, 200.0)),
+        ));
+        drawing.add_entity(on_layer(
+            "walls",
+            EntityType::LwPolyline(LwPolyline {
+                vertices: [(0.0, 0.0, 0.0), (4.0, 0.0, 1.0), (4.0, 3.0, 0.0)]
+                    .map(|(x, y, bulge)| LwPolylineVertex {
+                        x,
+                        y,
+                        bulge,
+                        ..Default::default()
+                    })
+                    .to_vec(),
+                ..Default::default()
+            }),
+        ));
+        // Not drawn, so it should be reported the same way both times.
+        drawing.add_entity(on_layer(
+            "0",
+            EntityType::XLine(XLine {
+                first_point: Point::origin(),
+                unit_direction_vector: Vector::x_axis(),
+            }),
+        ));
+
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let first_path = dir.join(alloc::format!("tabulon_dxf_resave_{id}_a.dxf"));
+        let second_path = dir.join(alloc::format!("tabulon_dxf_resave_{id}_b.dxf"));
+        drawing.save_file(&first_path).unwrap();
+        Drawing::load_file(&first_path)
+            .unwrap()
+            .save_file(&second_path)
+            .unwrap();
+        let first = super::load_file_default_layers(&first_path).unwrap();
+        let second = super::load_file_default_layers(&second_path).unwrap();
+        std::fs::remove_file(&first_path).ok();
+        std::fs::remove_file(&second_path).ok();
+
+        assert_eq!(
+            (first.report.version, &first.report.warnings),
+            (second.report.version, &second.report.warnings),
+            "Saving a drawing again should not change its report."
+        );
+        assert_eq!(
+            first.report.warnings,
+            [LoadWarning::UnsupportedEntities {
+                entity_type: "XLine",
+                count: 1
+            }],
+            "Content that is not drawn should be reported."
+        );
+        assert_eq!(
+            first.layer_names.values().collect::<super::BTreeSet<_>>(),
+            second.layer_names.values().collect::<super::BTreeSet<_>>(),
+            "Saving a drawing again should keep its layers."
+        );
+        assert_eq!(
+            first.item_entity_map.len(),
+            second.item_entity_map.len(),
+            "Saving a drawing again should keep its items."
+        );
+        let extents = |d: &super::TDDrawing| d.entity_extents.values().copied().collect::<Vec<_>>();
+        let (a, b) = (extents(&first), extents(&second));
+        assert!(
+            a.len() == 4
+                && a.iter().zip(&b).all(|(a, b)| {
+                    (a.x0 - b.x0).abs() < 1e-9
+                        && (a.y0 - b.y0).abs() < 1e-9
+                        && (a.x1 - b.x1).abs() < 1e-9
+                        && (a.y1 - b.y1).abs() < 1e-9
+                }),
+            "Saving a drawing again should keep its geometry within tolerance: {a:?} {b:?}"
+        );
+    }
+
+    #[test]
+    fn font_family_names() {
+        assert_eq!(
+            [
+                "romans.shx",
+                "Arial.TTF",
+                "C:\\Fonts\\isocp",
+                "my.font.shx",
+                ""
+            ]
+            .map(super::font_family_name),
+            [
+                Some("romans".into()),
+                Some("arial".into()),
+                Some("isocp".into()),
+                Some("my.font".into()),
+                None
+            ],
+            "Families should be named by the font file without its folder or extension."
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn restroke_only_on_change() {
+        use dxf::{
+            Drawing, Point,
+            entities::{Entity, EntityType, Line},
+        };
+
+        let mut drawing = Drawing::new();
+        drawing.add_entity(Entity::new(EntityType::Line(Line::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        ))));
+        let mut td = load_drawing("restroke_only_on_change", &drawing);
+
+        let r = td.restroke_paints[0];
+        let pitch = 1000;
+        assert!(
+            td.restroke(pitch, 1.0, 1.0, f64::INFINITY),
+            "Paints should be restroked the first time."
+        );
+        let strokes = td.graphics.stroke_revision();
+        assert!(
+            !td.restroke(pitch, 1.0, 1.0, f64::INFINITY),
+            "Paints should not be restroked for the same view scale."
+        );
+        assert_eq!(
+            td.graphics.stroke_revision(),
+            strokes,
+            "Strokes should be left alone for the same view scale."
+        );
+        assert!(
+            td.restroke(pitch, 2.0, 1.0, f64::INFINITY),
+            "Paints should be restroked when zooming."
+        );
+        assert_eq!(
+            td.graphics.get_paint(r.handle).stroke.width,
+            0.5,
+            "Strokes should stay at least a pixel wide on screen."
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn scaled_inserts_keep_stroke_widths() {
+        use dxf::{
+            Block, Drawing, Point,
+            entities::{Entity, EntityType, Insert, Line},
+        };
+        use tabulon::GraphicsItem;
+
+        let mut drawing = Drawing::new();
+        drawing.add_block(Block {
+            name: "B".into(),
+            entities: alloc::vec![Entity::new(EntityType::Line(Line::new(
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            )))],
+            ..Default::default()
+        });
+        for scale in [1.0, 4.0] {
+            drawing.add_entity(Entity::new(EntityType::Insert(Insert {
+                name: "B".into(),
+                x_scale_factor: scale,
+                y_scale_factor: scale,
+                ..Default::default()
+            })));
+        }
+        let mut td = load_drawing("scaled_inserts_keep_stroke_widths", &drawing);
+
+        let shapes: Vec<_> = td
+            .render_layer
+            .indices
+            .iter()
+            .map(|ih| match td.graphics.get(*ih) {
+                Some(GraphicsItem::FatShape(s)) => s.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert!(
+            shapes.len() == 2 && super::sync::Arc::ptr_eq(&shapes[0].shape, &shapes[1].shape),
+            "Inserts should share the block geometry."
+        );
+        td.restroke(1000, 1.0, 1.0, f64::INFINITY);
+        let width = |i: usize| {
+            td.graphics.get_paint(shapes[i].paint).stroke.width
+                * super::uniform_scale(td.graphics.get_transform(shapes[i].transform))
+        };
+        assert_eq!(
+            width(0),
+            width(1),
+            "Stroke widths should not be scaled by the insert."
+        );
+    }
+}
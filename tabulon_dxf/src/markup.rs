@@ -0,0 +1,267 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Markup documents shared between reviewers of a drawing.
+//!
+//! Markup refers to entities by their [stable ID](crate::EntityHandle::stable_id),
+//! so it is kept separately from the drawing and never modifies it.
+//!
+//! Each collaborator edits their own copy of a [`MarkupDocument`] with a unique site ID.
+//! Edits are stamped with a Lamport revision, and [`MarkupDocument::merge`] keeps the
+//! latest edit of each item, breaking ties by site ID. Merging is commutative, associative,
+//! and idempotent, so copies that have seen the same edits are identical regardless of
+//! the order they were merged in. Removed items are kept as tombstones so that removals
+//! are merged like any other edit.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use tabulon::peniko::kurbo::Affine;
+
+use crate::{Anchor, Bookmark};
+
+/// Identifier of a markup item, unique across collaborators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarkupId {
+    /// Site ID of the collaborator that created the item.
+    pub site: u64,
+    /// Sequence number of the item among those created by `site`.
+    pub serial: u64,
+}
+
+/// An item of markup.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Markup {
+    /// A comment at a point.
+    Annotation {
+        /// Where the comment is.
+        anchor: Anchor,
+        /// Comment text.
+        text: String,
+    },
+    /// A distance measured between two points.
+    Measurement {
+        /// Start of the measurement.
+        start: Anchor,
+        /// End of the measurement.
+        end: Anchor,
+    },
+    /// A named place in the drawing.
+    Bookmark(Bookmark),
+    /// A saved view.
+    View {
+        /// Name of the view.
+        name: String,
+        /// View transform, from drawing coordinates to the viewport.
+        transform: Affine,
+    },
+}
+
+/// The latest state of a markup item.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarkupRecord {
+    /// Item identifier.
+    pub id: MarkupId,
+    /// Lamport revision of the latest edit.
+    pub revision: u64,
+    /// Site ID of the collaborator that made the latest edit.
+    pub editor: u64,
+    /// Content of the item, or `None` if it was removed.
+    pub markup: Option<Markup>,
+}
+
+impl MarkupRecord {
+    /// Check whether this edit supersedes `other`.
+    fn supersedes(&self, other: &Self) -> bool {
+        (self.revision, self.editor) > (other.revision, other.editor)
+    }
+}
+
+/// A collection of markup that can be merged with copies edited concurrently.
+///
+/// See the [module documentation](self) for the merge semantics.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarkupDocument {
+    /// Records sorted by ID.
+    records: Vec<MarkupRecord>,
+}
+
+impl MarkupDocument {
+    /// Get an item, if it exists and was not removed.
+    pub fn get(&self, id: MarkupId) -> Option<&Markup> {
+        self.record(id)?.markup.as_ref()
+    }
+
+    /// Iterate over the items that were not removed, in order of ID.
+    pub fn iter(&self) -> impl Iterator<Item = (MarkupId, &Markup)> {
+        self.records
+            .iter()
+            .filter_map(|r| Some((r.id, r.markup.as_ref()?)))
+    }
+
+    /// Get the records of all items, including removed items.
+    pub fn records(&self) -> &[MarkupRecord] {
+        &self.records
+    }
+
+    /// Add an item as the collaborator `site`.
+    pub fn insert(&mut self, site: u64, markup: Markup) -> MarkupId {
+        let serial = self
+            .records
+            .iter()
+            .filter(|r| r.id.site == site)
+            .map(|r| r.id.serial + 1)
+            .max()
+            .unwrap_or_default();
+        let id = MarkupId { site, serial };
+        let record = MarkupRecord {
+            id,
+            revision: self.next_revision(),
+            editor: site,
+            markup: Some(markup),
+        };
+        let i = self.records.partition_point(|r| r.id < id);
+        self.records.insert(i, record);
+        id
+    }
+
+    /// Replace an item as the collaborator `site`.
+    ///
+    /// Returns `false` if the item does not exist or was removed.
+    pub fn update(&mut self, site: u64, id: MarkupId, markup: Markup) -> bool {
+        self.edit(site, id, Some(markup))
+    }
+
+    /// Remove an item as the collaborator `site`.
+    ///
+    /// Returns `false` if the item does not exist or was already removed.
+    pub fn remove(&mut self, site: u64, id: MarkupId) -> bool {
+        self.edit(site, id, None)
+    }
+
+    /// Merge edits from another copy of the document.
+    pub fn merge(&mut self, other: &Self) {
+        for theirs in other.records.iter() {
+            match self.records.binary_search_by_key(&theirs.id, |r| r.id) {
+                Ok(i) => {
+                    if theirs.supersedes(&self.records[i]) {
+                        self.records[i] = theirs.clone();
+                    }
+                }
+                Err(i) => self.records.insert(i, theirs.clone()),
+            }
+        }
+    }
+
+    fn record(&self, id: MarkupId) -> Option<&MarkupRecord> {
+        let i = self.records.binary_search_by_key(&id, |r| r.id).ok()?;
+        Some(&self.records[i])
+    }
+
+    fn next_revision(&self) -> u64 {
+        self.records
+            .iter()
+            .map(|r| r.revision + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn edit(&mut self, site: u64, id: MarkupId, markup: Option<Markup>) -> bool {
+        let revision = self.next_revision();
+        let Ok(i) = self.records.binary_search_by_key(&id, |r| r.id) else {
+            return false;
+        };
+        let record = &mut self.records[i];
+        if record.markup.is_none() {
+            return false;
+        }
+        record.revision = revision;
+        record.editor = site;
+        record.markup = markup;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::peniko::kurbo::Vec2;
+
+    fn note(text: &str) -> Markup {
+        Markup::Annotation {
+            anchor: Anchor {
+                entity: 0x2A,
+                offset: Vec2::new(1.0, 2.0),
+            },
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn concurrent_edits_converge() {
+        let mut base = MarkupDocument::default();
+        let shared = base.insert(1, note("Check clearance"));
+
+        let mut alice = base.clone();
+        let mut bob = base.clone();
+        alice.update(1, shared, note("Clearance is fine"));
+        let bobs = bob.insert(2, note("Missing valve tag"));
+        bob.update(2, shared, note("Clearance too small"));
+
+        let mut ab = alice.clone();
+        ab.merge(&bob);
+        let mut ba = bob.clone();
+        ba.merge(&alice);
+        assert_eq!(ab, ba, "Merging should not depend on order.");
+        assert_eq!(
+            ab.get(shared),
+            Some(&note("Clearance too small")),
+            "Concurrent edits should be resolved by site ID."
+        );
+        assert_eq!(
+            ab.get(bobs),
+            Some(&note("Missing valve tag")),
+            "New items should be merged."
+        );
+
+        let mut merged = ab.clone();
+        merged.merge(&ab);
+        assert_eq!(merged, ab, "Merging should be idempotent.");
+
+        bob.update(2, bobs, note("Missing valve tag V-101"));
+        alice.merge(&bob);
+        assert!(alice.remove(1, bobs), "Merged items can be removed.");
+        bob.merge(&alice);
+        assert_eq!(
+            bob.get(bobs),
+            None,
+            "The later removal should win over the earlier edit."
+        );
+        assert_eq!(bob.iter().count(), 1, "Removed items should not be listed.");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let mut doc = MarkupDocument::default();
+        doc.insert(
+            7,
+            Markup::View {
+                name: "Pump room".into(),
+                transform: Affine::scale(2.0),
+            },
+        );
+        let id = doc.insert(7, note("Gone"));
+        doc.remove(7, id);
+        let json = serde_json::to_string(&doc).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MarkupDocument>(&json).unwrap(),
+            doc,
+            "Documents should round trip through JSON, including removed items."
+        );
+    }
+}
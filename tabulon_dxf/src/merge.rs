@@ -0,0 +1,233 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Merging of shapes into fewer items.
+//!
+//! Drawings often have many thousands of lines and polylines with the same few
+//! paints, and each item costs some time to encode however small it is. Shapes that
+//! are drawn one after another with the same paint, transform and clip, on the same
+//! layer, can be drawn as one path instead. The [`ItemEntityMap`] of the drawing keeps
+//! the range of segments drawn for each entity, so picking still finds entities.
+//!
+//! [`ItemEntityMap`]: crate::ItemEntityMap
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+use tabulon::{
+    ClipHandle, GraphicsItem, ItemHandle, PaintHandle, TransformHandle,
+    peniko::kurbo::{BezPath, DEFAULT_ACCURACY, ParamCurve, PathEl, Shape},
+    shape::FatShape,
+};
+
+use crate::{EntityHandle, LayerHandle, TDDrawing};
+
+/// What shapes must share to be merged.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct MergeKey {
+    transform: TransformHandle,
+    paint: PaintHandle,
+    clip: ClipHandle,
+    layer: LayerHandle,
+}
+
+impl TDDrawing {
+    /// Merge runs of shapes that are drawn with the same paint, transform, clip and
+    /// layer into single items.
+    ///
+    /// Only shapes drawn for a single entity, whose paint has no fill, are merged, as
+    /// overlapping fills in one path cover differently. Strokes that overlap are drawn
+    /// once where they overlap, which shows with translucent paints. Merged items take
+    /// the place of the first shape of their run, in the render layer and in the layer
+    /// stack, and their segments are attributed to the entities of the shapes in the
+    /// [`item_entity_map`](Self::item_entity_map).
+    ///
+    /// Returns how many fewer items the render layer has.
+    pub fn merge_shapes(&mut self) -> usize {
+        let mut runs: Vec<Vec<(ItemHandle, EntityHandle)>> = Vec::new();
+        let mut last: Option<MergeKey> = None;
+        for ih in &self.render_layer.indices {
+            let key = self.merge_key(*ih);
+            if key.is_none() || key != last {
+                runs.push(Vec::new());
+            }
+            last = key;
+            if let Some(eh) = key.and_then(|_| self.item_entity_map.get(ih)) {
+                runs.last_mut().unwrap().push((*ih, eh));
+            }
+        }
+
+        let mut replacements: BTreeMap<ItemHandle, Option<ItemHandle>> = BTreeMap::new();
+        for run in runs.into_iter().filter(|run| run.len() > 1) {
+            let Some(GraphicsItem::FatShape(first)) = self.graphics.get(run[0].0) else {
+                continue;
+            };
+            let first = first.clone();
+            let mut path = BezPath::new();
+            let mut ranges = Vec::with_capacity(run.len());
+            let mut segments = 0;
+            for (ih, eh) in &run {
+                let Some(GraphicsItem::FatShape(s)) = self.graphics.get(*ih) else {
+                    continue;
+                };
+                let piece = s.shape.path(DEFAULT_ACCURACY);
+                ranges.push((segments, *eh));
+                segments += piece.segments().count();
+                path.extend(piece.iter());
+            }
+            let merged = self.graphics.push(FatShape {
+                shape: Arc::new(path.into()),
+                ..first
+            });
+            self.item_entity_map.insert_merged(merged, ranges);
+            replacements.insert(run[0].0, Some(merged));
+            for (ih, _) in &run[1..] {
+                replacements.insert(*ih, None);
+            }
+        }
+        for ih in replacements.keys() {
+            self.item_entity_map.remove(ih);
+        }
+
+        let replace = |indices: &mut Vec<ItemHandle>| {
+            *indices = indices
+                .iter()
+                .filter_map(|ih| replacements.get(ih).copied().unwrap_or(Some(*ih)))
+                .collect();
+        };
+        let before = self.render_layer.indices.len();
+        replace(&mut self.render_layer.indices);
+        for slh in self.stacked_layers.values() {
+            if let Some(sl) = self.layer_stack.get_mut(*slh) {
+                replace(&mut sl.layer.indices);
+            }
+        }
+        before - self.render_layer.indices.len()
+    }
+
+    /// Get the shapes drawn for an entity.
+    ///
+    /// Shapes that were [merged](Self::merge_shapes) are cut down to the segments
+    /// drawn for the entity.
+    pub fn entity_shapes(&self, eh: EntityHandle) -> Vec<FatShape> {
+        self.item_entity_map
+            .items_of(eh)
+            .filter_map(|ih| {
+                let Some(GraphicsItem::FatShape(s)) = self.graphics.get(ih) else {
+                    return None;
+                };
+                if self.item_entity_map.get(&ih).is_some() {
+                    return Some(s.clone());
+                }
+                let mut path = BezPath::new();
+                for (i, seg) in s.shape.segments().enumerate() {
+                    if self.item_entity_map.entity_at(&ih, i) != Some(eh) {
+                        continue;
+                    }
+                    if path.elements().last().and_then(PathEl::end_point) != Some(seg.start()) {
+                        path.move_to(seg.start());
+                    }
+                    path.push(seg.as_path_el());
+                }
+                Some(FatShape {
+                    shape: Arc::new(path.into()),
+                    ..s.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Get what a shape must share with others to be merged with them, or `None` if it
+    /// can't be merged.
+    fn merge_key(&self, ih: ItemHandle) -> Option<MergeKey> {
+        let Some(GraphicsItem::FatShape(s)) = self.graphics.get(ih) else {
+            return None;
+        };
+        if self.graphics.get_paint(s.paint).fill_paint.is_some() {
+            return None;
+        }
+        let starts_subpath = matches!(
+            s.shape.path_elements(DEFAULT_ACCURACY).next(),
+            Some(PathEl::MoveTo(_))
+        );
+        let eh = self.item_entity_map.get(&ih)?;
+        let layer = *self.entity_layer_map.get(&eh)?;
+        starts_subpath.then_some(MergeKey {
+            transform: s.transform,
+            paint: s.paint,
+            clip: s.clip,
+            layer,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::load_drawing;
+    use core::num::NonZeroU64;
+    use dxf::{
+        Drawing,
+        entities::{Entity, EntityType, Line},
+    };
+    use tabulon::{peniko::kurbo::Point, spatial_index::SpatialIndex};
+
+    #[test]
+    fn merged_lines_pick_and_highlight_entities() {
+        let mut drawing = Drawing::new();
+        let handles: Vec<EntityHandle> = [0.0, 10.0, 20.0]
+            .into_iter()
+            .map(|y| {
+                let line = drawing.add_entity(Entity::new(EntityType::Line(Line::new(
+                    dxf::Point::new(0.0, y, 0.0),
+                    dxf::Point::new(10.0, y, 0.0),
+                ))));
+                EntityHandle(NonZeroU64::new(line.common.handle.0).unwrap())
+            })
+            .collect();
+        let mut td = load_drawing("merged_lines", &drawing);
+
+        assert_eq!(
+            td.merge_shapes(),
+            2,
+            "Three lines should merge into one item."
+        );
+        let merged = td.render_layer.indices.clone();
+        assert_eq!(merged.len(), 1, "The merged item should replace the lines.");
+        let stacked: Vec<_> = td
+            .layer_stack
+            .visible_layers()
+            .flat_map(|l| l.layer.indices.iter().copied())
+            .collect();
+        assert_eq!(
+            stacked, merged,
+            "The layer stack should draw the merged item."
+        );
+
+        // Items are in drawing coordinates with y down.
+        let index = SpatialIndex::new(&td.graphics, &td.render_layer);
+        let picks: Vec<_> = [0.0, 10.0, 20.0]
+            .into_iter()
+            .map(|y| td.item_entity_map.pick(&index, Point::new(5.0, -y), 0.5))
+            .collect();
+        assert_eq!(
+            picks,
+            handles.iter().copied().map(Some).collect::<Vec<_>>(),
+            "Picking the merged item should find each line."
+        );
+        let highlight = td.entity_shapes(handles[1]);
+        assert_eq!(
+            highlight
+                .iter()
+                .map(|s| s.shape.segments().count())
+                .collect::<Vec<_>>(),
+            [1],
+            "An entity's shapes should be cut from the merged item."
+        );
+        assert_eq!(
+            td.merge_shapes(),
+            0,
+            "Merging again should not change anything."
+        );
+    }
+}
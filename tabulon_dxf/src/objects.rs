@@ -0,0 +1,72 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Lookup of objects attached to entities through extension dictionaries.
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+
+use dxf::{
+    CodePairValue, Drawing, ExtensionGroupItem,
+    entities::Entity,
+    objects::{Object, ObjectType},
+};
+
+/// Extension group holding the handle of an item's extension dictionary.
+pub(crate) const XDICTIONARY_GROUP: &str = "ACAD_XDICTIONARY";
+
+/// Parse a handle from a code pair value.
+pub(crate) fn handle(value: &CodePairValue) -> Option<u64> {
+    match value {
+        CodePairValue::Str(s) => u64::from_str_radix(s, 16).ok(),
+        _ => None,
+    }
+}
+
+/// Objects in a drawing by handle.
+pub(crate) struct ObjectIndex<'a> {
+    objects: BTreeMap<u64, &'a Object>,
+}
+
+impl<'a> ObjectIndex<'a> {
+    pub(crate) fn new(drawing: &'a Drawing) -> Self {
+        Self {
+            objects: drawing.objects().map(|o| (o.common.handle.0, o)).collect(),
+        }
+    }
+
+    /// Get an object.
+    pub(crate) fn get(&self, handle: u64) -> Option<&'a Object> {
+        self.objects.get(&handle).copied()
+    }
+
+    /// Get the handle of an entity's extension dictionary.
+    pub(crate) fn xdictionary(e: &Entity) -> Option<u64> {
+        e.common
+            .extension_data_groups
+            .iter()
+            .filter(|g| g.application_name == XDICTIONARY_GROUP)
+            .flat_map(|g| g.items.iter())
+            .find_map(|i| match i {
+                ExtensionGroupItem::CodePair(p) if p.code == 360 => handle(&p.value),
+                _ => None,
+            })
+    }
+
+    /// Get the handle of an entry in a dictionary.
+    pub(crate) fn entry(&self, dictionary: u64, key: &str) -> Option<u64> {
+        let ObjectType::Dictionary(ref d) = self.get(dictionary)?.specific else {
+            return None;
+        };
+        d.value_handles.get(key).map(|h| h.0)
+    }
+
+    /// Follow a path of dictionary keys from an entity's extension dictionary.
+    pub(crate) fn lookup(&self, e: &Entity, keys: &[&str]) -> Option<&'a Object> {
+        let mut h = Self::xdictionary(e)?;
+        for key in keys {
+            h = self.entry(h, key)?;
+        }
+        self.get(h)
+    }
+}
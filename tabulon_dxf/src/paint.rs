@@ -0,0 +1,151 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Resolution of entity colors and line weights to shared paints.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use dxf::{entities::EntityCommon, tables::Layer};
+use joto_constants::u64::MICROMETER;
+use tabulon::{
+    GraphicsBag, PaintHandle,
+    peniko::{Color, kurbo::Stroke},
+    shape::FatPaint,
+};
+
+use crate::{RestrokePaint, aci_palette::ACI};
+
+/// Line weight enumeration value reserved for solid fills.
+pub(crate) const SOLID_FILL: i16 = i16::MIN;
+
+/// Paints keyed on concrete color and line width, so entities with the same style share a paint.
+#[derive(Default)]
+pub(crate) struct PaintTable {
+    /// Stroke paints keyed on concrete rgba color, concrete line width (in iotas), and
+    /// the bits of the uniform scale of the transforms they are drawn with.
+    strokes: BTreeMap<(u32, u64, u64), PaintHandle>,
+    /// Fill paints keyed on concrete rgba color.
+    fills: BTreeMap<u32, PaintHandle>,
+}
+
+impl PaintTable {
+    /// Get or create the paint for a line weight and color enumeration value.
+    ///
+    /// BYLAYER values are resolved against `layer`, and BYENTITY colors and transparency
+    /// come from `common`. Line weight [`SOLID_FILL`] makes a fill paint.
+    pub(crate) fn resolve(
+        &mut self,
+        gb: &mut GraphicsBag,
+        layer: &Layer,
+        common: &EntityCommon,
+        lw: i16,
+        c: i16,
+    ) -> PaintHandle {
+        self.resolve_scaled(gb, layer, common, lw, c, 1.0)
+    }
+
+    /// Get or create the paint for items drawn with a transform of uniform `scale`.
+    ///
+    /// Stroke widths are scaled by item transforms, so strokes for each scale are
+    /// separate paints, which are [restroked](RestrokePaint::adapt) to compensate.
+    pub(crate) fn resolve_scaled(
+        &mut self,
+        gb: &mut GraphicsBag,
+        layer: &Layer,
+        common: &EntityCommon,
+        lw: i16,
+        c: i16,
+        scale: f64,
+    ) -> PaintHandle {
+        // Resolve color.
+        let opaque_color = match c {
+            // BYENTITY
+            257 => common.color_24_bit as u32,
+            // BYLAYER
+            256 => {
+                if let Some(i) = layer.color.index() {
+                    ACI[i as usize]
+                } else {
+                    u32::MAX
+                }
+            }
+            // Indexed colors.
+            1..=255 => ACI[c as usize],
+            // Other values generally not valid in this context.
+            _ => u32::MAX,
+        };
+        let combined_color = (opaque_color << 8) | (0xFF - (common.transparency as u32 & 0xFF));
+
+        /// Default line weight.
+        const LWDEFAULT: u64 = 250 * MICROMETER;
+
+        // Resolve line width.
+        let lwconcrete = match lw {
+            -3 => LWDEFAULT,
+            // BYLAYER.
+            -2 => {
+                if layer.line_weight.raw_value() <= 0 {
+                    // BYLAYER and BYBLOCK are both meaningless in a layer,
+                    // therefore, use the default for all enumerations.
+                    LWDEFAULT
+                } else {
+                    layer.line_weight.raw_value() as u64 * 10 * MICROMETER
+                }
+            }
+            // BYBLOCK (-1) Should not occur at the entity level, use default.
+            //
+            // Other negative values occur in the wild but have no standard
+            // meaning, as such all negative values not specifically handled
+            // above should have the default line width.
+            i if i < 0 => LWDEFAULT,
+            i => i as u64 * 10 * MICROMETER,
+        };
+
+        let r = ((combined_color >> 24) & 0xFF) as u8;
+        let g = ((combined_color >> 16) & 0xFF) as u8;
+        let b = ((combined_color >> 8) & 0xFF) as u8;
+        let a = (combined_color & 0xFF) as u8;
+
+        if lw == SOLID_FILL {
+            self.fill(gb, combined_color)
+        } else {
+            // Degenerate transforms draw nothing, so any scale will do.
+            let scale = if scale.is_normal() { scale } else { 1.0 };
+            *self
+                .strokes
+                .entry((combined_color, lwconcrete, scale.to_bits()))
+                .or_insert_with(|| {
+                    // At first these do not have stroke width, this needs to be set afterward.
+                    gb.register_paint(FatPaint {
+                        stroke: Stroke::new(0.0),
+                        stroke_paint: Some(Color::from_rgba8(r, g, b, a).into()),
+                        ..Default::default()
+                    })
+                })
+        }
+    }
+
+    /// Get or create a fill paint for a concrete rgba color.
+    pub(crate) fn fill(&mut self, gb: &mut GraphicsBag, rgba: u32) -> PaintHandle {
+        *self.fills.entry(rgba).or_insert_with(|| {
+            let [r, g, b, a] = rgba.to_be_bytes();
+            gb.register_paint(FatPaint {
+                fill_paint: Some(Color::from_rgba8(r, g, b, a).into()),
+                ..Default::default()
+            })
+        })
+    }
+
+    /// Get the stroke paints with their line weights and scales.
+    pub(crate) fn restroke_paints(&self) -> Vec<RestrokePaint> {
+        self.strokes
+            .iter()
+            .map(|((_, weight, scale), handle)| RestrokePaint {
+                weight: *weight,
+                handle: *handle,
+                scale: f64::from_bits(*scale),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! POINT entities, drawn as set by the `$PDMODE` and `$PDSIZE` header variables.
+//!
+//! `$PDMODE` is a figure in its low bits (0 dot, 1 nothing, 2 plus, 3 cross, 4 tick),
+//! with 32 added for a circle around it and 64 for a square. `$PDSIZE` is the size in
+//! drawing units when positive, a percentage of the viewport height when negative, and
+//! 5% of the viewport height when zero.
+
+extern crate alloc;
+use alloc::sync::Arc;
+
+use dxf::entities::ModelPoint;
+use tabulon::{
+    GraphicsItem, PaintHandle,
+    marker::{FatMarker, MarkerShape, MarkerSize},
+    peniko::kurbo::{Affine, BezPath, Circle, Line, Point, Rect, Shape},
+    shape::FatShape,
+};
+
+use crate::point_from_dxf_point;
+
+/// Viewport height assumed for point sizes relative to the viewport, in device pixels.
+///
+/// Markers don't change size with the view, so the actual viewport is not followed.
+const REFERENCE_VIEWPORT_HEIGHT: f64 = 600.0;
+
+/// Get the figure for `$PDMODE`, one unit across and centered on the origin.
+///
+/// Returns `None` if points are not displayed.
+fn figure(mode: i32) -> Option<MarkerShape> {
+    let circle = mode & 32 != 0;
+    let square = mode & 64 != 0;
+    let shape = match (mode & 7, circle, square) {
+        (1, false, false) => return None,
+        (2, false, false) => MarkerShape::Plus,
+        (3, false, false) => MarkerShape::Cross,
+        (1, true, false) => MarkerShape::Circle,
+        (1, false, true) => MarkerShape::Square,
+        (figure, ..) => {
+            let mut path = match figure {
+                2 => MarkerShape::Plus.unit_path(),
+                3 => MarkerShape::Cross.unit_path(),
+                // A tick points up from the position.
+                4 => Line::new((0.0, 0.0), (0.0, -0.5)).to_path(0.1),
+                1 => BezPath::new(),
+                // A dot is a small circle, so that it can be seen when stroked.
+                _ => Circle::new(Point::ZERO, 1.0 / 16.0).to_path(1e-3),
+            };
+            if circle {
+                path.extend(Circle::new(Point::ZERO, 0.5).path_elements(1e-4));
+            }
+            if square {
+                path.extend(Rect::new(-0.5, -0.5, 0.5, 0.5).path_elements(0.1));
+            }
+            MarkerShape::Custom(Arc::new(path))
+        }
+    };
+    Some(shape)
+}
+
+/// Convert a POINT entity to an item, with the `$PDMODE` and `$PDSIZE` of its drawing.
+///
+/// Points sized relative to the viewport become [markers](FatMarker) that keep their
+/// size on the display, and points sized in drawing units become shapes.
+/// Returns `None` if points are not displayed, or the point isn't viewed from +Z.
+pub(crate) fn point_item(
+    point: &ModelPoint,
+    mode: i32,
+    size: f64,
+    paint: PaintHandle,
+) -> Option<GraphicsItem> {
+    // FIXME: currently only support viewing from +Z.
+    if point.extrusion_direction.z != 1.0 {
+        return None;
+    }
+    let position = point_from_dxf_point(&point.location);
+    let mut shape = figure(mode)?;
+    // The angle rotates the figure's x axis, counterclockwise as DXF is y-up.
+    if point.angle != 0.0 {
+        shape = MarkerShape::Custom(Arc::new(
+            Affine::rotate(-point.angle.to_radians()) * shape.unit_path(),
+        ));
+    }
+
+    if size > 0.0 {
+        let path = Affine::scale(size).then_translate(position.to_vec2()) * shape.unit_path();
+        return Some(
+            FatShape {
+                shape: Arc::new(path.into()),
+                paint,
+                ..Default::default()
+            }
+            .into(),
+        );
+    }
+    let percent = if size == 0.0 { 5.0 } else { -size };
+    Some(
+        FatMarker {
+            paint,
+            ..FatMarker::new(
+                position,
+                shape,
+                MarkerSize::Pixels(percent * 0.01 * REFERENCE_VIEWPORT_HEIGHT),
+            )
+        }
+        .into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_display_modes() {
+        let point = ModelPoint {
+            location: dxf::Point::new(1.0, 2.0, 0.0),
+            ..Default::default()
+        };
+        assert!(
+            point_item(&point, 1, 0.0, PaintHandle::default()).is_none(),
+            "Mode 1 should hide points."
+        );
+
+        let Some(GraphicsItem::FatMarker(m)) = point_item(&point, 2, -10.0, Default::default())
+        else {
+            panic!("Points sized relative to the viewport should be markers.");
+        };
+        assert_eq!(
+            (m.position, &m.shape, m.size),
+            (
+                Point::new(1.0, -2.0),
+                &MarkerShape::Plus,
+                MarkerSize::Pixels(60.0)
+            ),
+            "Markers should be placed and sized from the point and header."
+        );
+
+        let Some(GraphicsItem::FatShape(s)) = point_item(&point, 35, 4.0, Default::default())
+        else {
+            panic!("Points sized in drawing units should be shapes.");
+        };
+        let bounds = s.shape.bounding_box();
+        assert!(
+            (bounds.size().width - 4.0).abs() < 1e-3
+                && (bounds.center() - Point::new(1.0, -2.0)).hypot() < 1e-9,
+            "A cross in a circle should span the point size in drawing units, got {bounds:?}."
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Reporting of drawing versions and unsupported content found while loading.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use core::fmt;
+
+use dxf::{Drawing, XData, entities::Entity, enums::AcadVersion};
+
+/// `XData` application for annotative objects.
+const ANNOTATIVE_APP: &str = "AcadAnnotative";
+
+/// `XData` application marking dynamic block definitions.
+const DYNAMIC_BLOCK_APP: &str = "AcDbDynamicBlockGUID";
+
+/// Summary of a loaded drawing, for diagnosing how it is displayed.
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    /// Version of the drawing, from `$ACADVER`.
+    pub version: AcadVersion,
+    /// Content that is not displayed faithfully.
+    pub warnings: Vec<LoadWarning>,
+}
+
+/// Content of a drawing that is not displayed faithfully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadWarning {
+    /// The `$DWGCODEPAGE` is not supported, so legacy text was decoded as Windows-1252.
+    UnsupportedCodePage(String),
+    /// Objects use annotative scaling, which is not supported.
+    ///
+    /// These are drawn at their model size, regardless of annotation scale.
+    AnnotativeScaling {
+        /// Number of annotative entities and styles.
+        count: usize,
+    },
+    /// Dynamic blocks are defined, which are not supported.
+    ///
+    /// Their parameters and actions are ignored, and instances are drawn in the
+    /// state saved in the drawing.
+    DynamicBlocks {
+        /// Names of the dynamic block definitions.
+        names: Vec<String>,
+    },
+    /// Entities of a type that is not drawn.
+    UnsupportedEntities {
+        /// Entity type name.
+        entity_type: &'static str,
+        /// Number of entities skipped.
+        count: usize,
+    },
+    /// Filled entities have open boundaries.
+    ///
+    /// These were closed, so their outlines match their fills.
+    OpenFills {
+        /// Number of filled items that were closed.
+        count: usize,
+    },
+    /// Layers or entities had no handle, or the handle of another.
+    ///
+    /// These were given new handles, so their [`EntityHandle`](crate::EntityHandle)s
+    /// don't match the handles in the file.
+    SyntheticHandles {
+        /// Number of handles that were replaced.
+        count: usize,
+    },
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedCodePage(name) => write!(
+                f,
+                "unsupported code page {name:?}, legacy text was decoded as Windows-1252"
+            ),
+            Self::AnnotativeScaling { count } => write!(
+                f,
+                "{count} annotative objects are drawn without annotation scaling"
+            ),
+            Self::DynamicBlocks { names } => write!(
+                f,
+                "{} dynamic blocks are drawn without parameters or actions: {}",
+                names.len(),
+                names.join(", ")
+            ),
+            Self::UnsupportedEntities { entity_type, count } => {
+                write!(f, "{count} {entity_type} entities are not drawn")
+            }
+            Self::OpenFills { count } => {
+                write!(
+                    f,
+                    "{count} filled objects had open boundaries, which were closed"
+                )
+            }
+            Self::SyntheticHandles { count } => {
+                write!(
+                    f,
+                    "{count} layers or entities had missing or repeated handles, which were replaced"
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DXF version {:?}", self.version)?;
+        for w in &self.warnings {
+            write!(f, "\n  warning: {w}")?;
+        }
+        Ok(())
+    }
+}
+
+impl LoadReport {
+    /// Start a report for `drawing`, with warnings for version dependent constructs.
+    pub(crate) fn new(drawing: &Drawing) -> Self {
+        let version = drawing.header.version;
+        let mut warnings = vec![];
+
+        // Annotative scaling was introduced with R2007 format drawings.
+        if version >= AcadVersion::R2007 {
+            let count = drawing
+                .entities()
+                .chain(drawing.blocks().flat_map(|b| b.entities.iter()))
+                .filter(|e| is_annotative(e))
+                .count()
+                + drawing
+                    .styles()
+                    .filter(|s| has_app(&s.x_data, ANNOTATIVE_APP))
+                    .count()
+                + drawing
+                    .dim_styles()
+                    .filter(|s| has_app(&s.x_data, ANNOTATIVE_APP))
+                    .count();
+            if count > 0 {
+                warnings.push(LoadWarning::AnnotativeScaling { count });
+            }
+        }
+
+        // Dynamic blocks were introduced with R2004 format drawings.
+        if version >= AcadVersion::R2004 {
+            let names: Vec<String> = drawing
+                .blocks()
+                .filter(|b| has_app(&b.x_data, DYNAMIC_BLOCK_APP))
+                .map(|b| b.name.clone())
+                .collect();
+            if !names.is_empty() {
+                warnings.push(LoadWarning::DynamicBlocks { names });
+            }
+        }
+
+        Self { version, warnings }
+    }
+
+    /// Add warnings for entity types that were not drawn, with their counts.
+    pub(crate) fn add_unsupported_entities(&mut self, counts: BTreeMap<&'static str, usize>) {
+        self.warnings.extend(
+            counts
+                .into_iter()
+                .map(|(entity_type, count)| LoadWarning::UnsupportedEntities {
+                    entity_type,
+                    count,
+                }),
+        );
+    }
+}
+
+/// Check whether `x_data` has data for `app`.
+fn has_app(x_data: &[XData], app: &str) -> bool {
+    x_data
+        .iter()
+        .any(|x| x.application_name.eq_ignore_ascii_case(app))
+}
+
+/// Check whether an entity is annotative.
+fn is_annotative(e: &Entity) -> bool {
+    has_app(&e.common.x_data, ANNOTATIVE_APP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dxf::{Block, entities::EntityType};
+
+    fn with_x_data(app: &str) -> Vec<XData> {
+        vec![XData {
+            application_name: app.into(),
+            items: vec![],
+        }]
+    }
+
+    #[test]
+    fn version_dependent_warnings() {
+        let mut drawing = Drawing::new();
+        let mut e = Entity::new(EntityType::Line(Default::default()));
+        e.common.x_data = with_x_data(ANNOTATIVE_APP);
+        drawing.add_entity(e);
+        drawing.add_block(Block {
+            name: "DOOR".into(),
+            x_data: with_x_data(DYNAMIC_BLOCK_APP),
+            ..Default::default()
+        });
+
+        drawing.header.version = AcadVersion::R12;
+        assert!(
+            LoadReport::new(&drawing).warnings.is_empty(),
+            "Constructs should not be reported for versions that cannot contain them."
+        );
+
+        drawing.header.version = AcadVersion::R2010;
+        let report = LoadReport::new(&drawing);
+        assert_eq!(report.version, AcadVersion::R2010);
+        assert_eq!(
+            report.warnings,
+            [
+                LoadWarning::AnnotativeScaling { count: 1 },
+                LoadWarning::DynamicBlocks {
+                    names: vec!["DOOR".into()]
+                }
+            ],
+            "Annotative entities and dynamic blocks should be reported."
+        );
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Navigating to entities, such as search results and reported errors.
+
+extern crate alloc;
+use alloc::sync::Arc;
+
+use tabulon::{
+    GraphicsBag, GraphicsItem, PaintHandle, TransformHandle,
+    animation::{Animator, Easing},
+    bounds::EstimatedText,
+    peniko::kurbo::{Affine, Rect},
+    render_layer::RenderLayer,
+    shape::{FatPaint, FatShape},
+    uniform_scale, zoom_to,
+};
+
+use crate::{EntityHandle, TDDrawing};
+
+/// A view of an entity, and a highlight to flash over it.
+///
+/// Created by [`TDDrawing::reveal`].
+#[derive(Debug)]
+pub struct Reveal {
+    /// Local part of the root transform of [`TDDrawing::graphics`] that shows the entity.
+    pub view: Affine,
+    /// Bag holding the highlight, in drawing coordinates under its root transform, which
+    /// starts out as the drawing's.
+    pub highlight: GraphicsBag,
+    /// Highlight items, to be drawn over the drawing.
+    pub layer: RenderLayer,
+    /// Paint of every highlight item.
+    pub paint: PaintHandle,
+}
+
+impl Reveal {
+    /// Start animating the root transforms of `drawing` and the highlight to the view,
+    /// and flashing the highlight, at time `now`.
+    ///
+    /// The view is reached after `zoom` seconds, and the highlight fades out over
+    /// `flash` seconds, holding at first, so it should be longer than `zoom`. An
+    /// [`Animator`] applies to one bag, so `views` is applied to `drawing`, and
+    /// `flashes` to [`highlight`](Self::highlight). The highlight can be dropped once
+    /// its paint is no longer [flashing](Animator::is_flashing).
+    pub fn start(
+        &self,
+        drawing: &GraphicsBag,
+        views: &mut Animator,
+        flashes: &mut Animator,
+        now: f64,
+        zoom: f64,
+        flash: f64,
+    ) {
+        let root = TransformHandle::default();
+        views.animate(drawing, root, self.view, now, zoom, Easing::EaseInOut);
+        flashes.animate(
+            &self.highlight,
+            root,
+            self.view,
+            now,
+            zoom,
+            Easing::EaseInOut,
+        );
+        flashes.flash(&self.highlight, self.paint, now, flash, Easing::EaseIn);
+    }
+}
+
+impl TDDrawing {
+    /// Get a view showing an entity in `viewport`, and a highlight of it.
+    ///
+    /// The view fits the entity's [extent](TDDrawing::entity_extents) inside `viewport`
+    /// with `padding`, as by [`zoom_to`] from the current root transform. Shapes drawn
+    /// for the entity are copied into the highlight with `paint`, and other items, such
+    /// as text, are outlined by their bounds. The stroke width of `paint` is in the
+    /// coordinates of `viewport`, at the scale of the new view.
+    ///
+    /// Returns `None` if the entity has no extent.
+    pub fn reveal(
+        &self,
+        eh: EntityHandle,
+        viewport: Rect,
+        padding: f64,
+        mut paint: FatPaint,
+    ) -> Option<Reveal> {
+        let extent = *self.entity_extents.get(&eh)?;
+        let root = TransformHandle::default();
+        let current = self.graphics.get_local_transform(root);
+        let view = zoom_to(current, extent, viewport, padding);
+        paint.stroke.width /= uniform_scale(view).max(f64::EPSILON);
+
+        let mut highlight = GraphicsBag::default();
+        highlight.update_transform(root, current);
+        let paint = highlight.register_paint(paint);
+        let mut layer = RenderLayer::default();
+        let to_drawing = self.graphics.get_transform(root).inverse();
+        for s in self.entity_shapes(eh) {
+            let shape = FatShape {
+                transform: highlight.register_transform(
+                    root,
+                    to_drawing * self.graphics.get_transform(s.transform),
+                ),
+                paint,
+                shape: s.shape,
+                ..Default::default()
+            };
+            layer.push_with_bag(&mut highlight, shape);
+        }
+        for ih in self.item_entity_map.items_of(eh) {
+            if matches!(self.graphics.get(ih), Some(GraphicsItem::FatShape(_))) {
+                continue;
+            }
+            let Some(bounds) = self
+                .graphics
+                .item_bounds_below_root_with(ih, &mut EstimatedText)
+            else {
+                continue;
+            };
+            let shape = FatShape {
+                paint,
+                shape: Arc::new(bounds.into()),
+                ..Default::default()
+            };
+            layer.push_with_bag(&mut highlight, shape);
+        }
+        Some(Reveal {
+            view,
+            highlight,
+            layer,
+            paint,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::load_drawing;
+    use dxf::{
+        Drawing,
+        entities::{Entity, EntityType, Line},
+    };
+    use tabulon::peniko::kurbo::{Point, Stroke};
+
+    #[test]
+    fn reveal_line() {
+        let mut drawing = Drawing::new();
+        let id = drawing
+            .add_entity(Entity::new(EntityType::Line(Line::new(
+                dxf::Point::new(0.0, 0.0, 0.0),
+                dxf::Point::new(10.0, 0.0, 0.0),
+            ))))
+            .common
+            .handle
+            .0;
+        let td = load_drawing("reveal_line", &drawing);
+        let eh = td.entity_by_stable_id(id).unwrap();
+
+        let reveal = td
+            .reveal(
+                eh,
+                Rect::new(0.0, 0.0, 100.0, 100.0),
+                10.0,
+                FatPaint {
+                    stroke: Stroke::new(4.0),
+                    ..Default::default()
+                },
+            )
+            .expect("Lines have extents.");
+        assert_eq!(
+            (
+                reveal.view * Point::ZERO,
+                reveal.view * Point::new(10.0, 0.0)
+            ),
+            (Point::new(10.0, 50.0), Point::new(90.0, 50.0)),
+            "The line should span the padded viewport."
+        );
+        assert_eq!(
+            reveal.layer.indices.len(),
+            1,
+            "The line's shape should be highlighted."
+        );
+        assert_eq!(
+            reveal.highlight.get_paint(reveal.paint).stroke.width,
+            0.5,
+            "The highlight should be stroked in viewport units at the new scale."
+        );
+    }
+}
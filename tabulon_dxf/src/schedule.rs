@@ -0,0 +1,157 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Counts of block instances for schedules and bills of materials.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use core::{fmt, num::NonZeroU64};
+
+use dxf::entities::EntityType;
+use tabulon::peniko::kurbo::{Point, Rect};
+
+use crate::{EntityHandle, TDDrawing, point_from_dxf_point};
+
+/// How block instances are grouped in a schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleGrouping {
+    /// One row per block name.
+    Block,
+    /// One row per block name and layer.
+    Layer,
+    /// One row per block name and value of the attribute with this tag.
+    ///
+    /// Inserts without the attribute are grouped together.
+    Attribute(String),
+}
+
+/// A row of a block schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleRow {
+    /// Name of the inserted block.
+    pub block_name: String,
+    /// Layer or attribute value, depending on the [`ScheduleGrouping`].
+    pub group: Option<String>,
+    /// Number of instances, counting each instance of arrayed inserts.
+    pub count: usize,
+    /// Bounds of the instances in the same coordinates as loaded items.
+    ///
+    /// This covers the drawn geometry, or the insertion point of inserts that are not drawn.
+    pub bounds: Rect,
+}
+
+impl TDDrawing {
+    /// Count model space block instances, grouped by block name and `grouping`.
+    ///
+    /// Rows are sorted by block name and group. Dynamic block instances with a saved
+    /// representation are counted under the name they are inserted with.
+    pub fn block_schedule(&self, grouping: &ScheduleGrouping) -> Vec<ScheduleRow> {
+        let mut item_bounds: BTreeMap<EntityHandle, Rect> = BTreeMap::new();
+        for (ih, eh) in self.item_entity_map.iter() {
+            if let Some(b) = self.graphics.item_bounds(ih) {
+                item_bounds
+                    .entry(eh)
+                    .and_modify(|r| *r = r.union(b))
+                    .or_insert(b);
+            }
+        }
+
+        let mut rows: BTreeMap<(String, Option<String>), ScheduleRow> = BTreeMap::new();
+        for e in self.info.drawing.entities() {
+            let EntityType::Insert(ref ins) = e.specific else {
+                continue;
+            };
+            let group = match grouping {
+                ScheduleGrouping::Block => None,
+                ScheduleGrouping::Layer => Some(e.common.layer.clone()),
+                ScheduleGrouping::Attribute(tag) => ins
+                    .attributes()
+                    .find(|a| a.attribute_tag.eq_ignore_ascii_case(tag))
+                    .map(|a| self.info.attribute_value(a)),
+            };
+            let bounds = NonZeroU64::new(e.common.handle.0)
+                .and_then(|h| item_bounds.get(&EntityHandle(h)).copied())
+                .unwrap_or_else(|| {
+                    Rect::from_origin_size(point_from_dxf_point(&ins.location), (0.0, 0.0))
+                });
+            let count = usize::from(ins.row_count.max(0).unsigned_abs())
+                * usize::from(ins.column_count.max(0).unsigned_abs());
+
+            rows.entry((ins.name.clone(), group.clone()))
+                .and_modify(|r| {
+                    r.count += count;
+                    r.bounds = r.bounds.union(bounds);
+                })
+                .or_insert(ScheduleRow {
+                    block_name: ins.name.clone(),
+                    group,
+                    count,
+                    bounds,
+                });
+        }
+        rows.into_values().collect()
+    }
+}
+
+/// Write schedule rows as CSV, with a header row.
+///
+/// Bounds are written in drawing coordinates, with y up as in the DXF.
+pub fn write_schedule_csv(rows: &[ScheduleRow], out: &mut impl fmt::Write) -> fmt::Result {
+    writeln!(out, "block,group,count,min_x,min_y,max_x,max_y")?;
+    for row in rows {
+        // Subtract from zero rather than negating, to avoid writing `-0`.
+        let min = Point::new(row.bounds.x0, 0.0 - row.bounds.y1);
+        let max = Point::new(row.bounds.x1, 0.0 - row.bounds.y0);
+        write_csv_field(out, &row.block_name)?;
+        out.write_char(',')?;
+        write_csv_field(out, row.group.as_deref().unwrap_or_default())?;
+        writeln!(
+            out,
+            ",{},{},{},{},{}",
+            row.count, min.x, min.y, max.x, max.y
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a CSV field, quoting it if needed.
+pub(crate) fn write_csv_field(out: &mut impl fmt::Write, field: &str) -> fmt::Result {
+    if field.contains([',', '"', '\n', '\r']) {
+        write!(out, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        out.write_str(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_output() {
+        let rows = [
+            ScheduleRow {
+                block_name: "VALVE".into(),
+                group: Some("Gate, 2\"".into()),
+                count: 3,
+                bounds: Rect::new(1.0, -4.0, 3.0, -2.0),
+            },
+            ScheduleRow {
+                block_name: "PUMP".into(),
+                group: None,
+                count: 1,
+                bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+            },
+        ];
+        let mut csv = String::new();
+        write_schedule_csv(&rows, &mut csv).unwrap();
+        assert_eq!(
+            csv,
+            "block,group,count,min_x,min_y,max_x,max_y\n\
+             VALVE,\"Gate, 2\"\"\",3,1,2,3,4\n\
+             PUMP,,1,0,0,0,0\n",
+            "Fields should be quoted when needed and y should be flipped back."
+        );
+    }
+}
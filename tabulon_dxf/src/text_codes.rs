@@ -0,0 +1,360 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Parsing of special character and formatting codes in TEXT and MTEXT values.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use core::{iter::Peekable, ops::Range, str::Chars};
+
+use crate::aci_palette::ACI;
+
+/// Which kind of entity a text value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextFlavor {
+    /// TEXT and ATTRIB values, which only have `%%` codes and Unicode escapes.
+    Text,
+    /// MTEXT values, which additionally have `\` formatting codes and `{}` groups.
+    MText,
+}
+
+/// Decode a character from the Windows-1252 code page.
+///
+/// This is the default drawing code page, and is used for `%%nnn` codes when no
+/// other code page is known.
+pub(crate) fn decode_ansi_1252(b: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž',
+        '\u{8F}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}',
+        'ž', 'Ÿ',
+    ];
+    match b {
+        0x80..=0x9F => HIGH[(b - 0x80) as usize],
+        _ => char::from(b),
+    }
+}
+
+/// Replace special character codes in a text value with their Unicode equivalents,
+/// and remove formatting codes.
+///
+/// Handles `%%c`, `%%d`, `%%p`, `%%%`, `%%nnn` (decoded with `decode_byte`),
+/// `\U+XXXX` escapes, and strips `$(...)` DIESEL expressions. For [`TextFlavor::MText`],
+/// paragraph breaks become newlines, escaped characters are unescaped, stacked
+/// fractions are written inline, and all other formatting codes are removed.
+pub(crate) fn decode_text(s: &str, flavor: TextFlavor, decode_byte: impl Fn(u8) -> char) -> String {
+    decode(s, flavor, decode_byte, &mut Colors::default())
+}
+
+#[cfg(feature = "text")]
+/// Decode an MTEXT value as [`decode_text`] does, also returning the colors set by
+/// `\C` and `\c` codes.
+///
+/// Colors are given as byte ranges of the decoded text and their colors as 0xRRGGBB.
+/// Text in the color of the entity, including after `\C0;` (BYBLOCK) and `\C256;`
+/// (BYLAYER), is not in any range. Colors set in a `{}` group end with the group.
+pub(crate) fn decode_mtext_colors(
+    s: &str,
+    decode_byte: impl Fn(u8) -> char,
+) -> (String, Vec<(Range<usize>, u32)>) {
+    let mut colors = Colors::default();
+    let out = decode(s, TextFlavor::MText, decode_byte, &mut colors);
+    colors.set(None, out.len());
+    (out, colors.ranges)
+}
+
+/// Colors of ranges of decoded MTEXT.
+#[derive(Default)]
+struct Colors {
+    /// Color of the text since `start`, or `None` for the color of the entity.
+    current: Option<u32>,
+    /// Byte offset in the decoded text where `current` was set.
+    start: usize,
+    /// Colors to restore at the end of each open group.
+    groups: Vec<Option<u32>>,
+    /// Ranges of text with a color of their own.
+    ranges: Vec<(Range<usize>, u32)>,
+}
+
+impl Colors {
+    /// Change the color at byte offset `at` of the decoded text.
+    fn set(&mut self, color: Option<u32>, at: usize) {
+        if color == self.current {
+            return;
+        }
+        if let Some(c) = self.current {
+            if self.start < at {
+                self.ranges.push((self.start..at, c));
+            }
+        }
+        self.current = color;
+        self.start = at;
+    }
+}
+
+/// Decode a text value, recording MTEXT color changes in `colors`.
+fn decode(
+    s: &str,
+    flavor: TextFlavor,
+    decode_byte: impl Fn(u8) -> char,
+    colors: &mut Colors,
+) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                decode_percent(&mut chars, &mut out, &decode_byte);
+            }
+            '$' if chars.peek() == Some(&'(') => skip_diesel(&mut chars),
+            '\\' => match chars.peek().copied() {
+                Some(u @ ('U' | 'u')) => {
+                    chars.next();
+                    if let Some(decoded) = unicode_escape(&mut chars) {
+                        out.push(decoded);
+                    } else {
+                        out.push('\\');
+                        out.push(u);
+                    }
+                }
+                Some(e) if flavor == TextFlavor::MText => {
+                    chars.next();
+                    decode_mtext_escape(e, &mut chars, &mut out, colors);
+                }
+                _ => out.push('\\'),
+            },
+            '{' if flavor == TextFlavor::MText => colors.groups.push(colors.current),
+            '}' if flavor == TextFlavor::MText => {
+                if let Some(color) = colors.groups.pop() {
+                    colors.set(color, out.len());
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Decode the remainder of a `%%` code.
+fn decode_percent(
+    chars: &mut Peekable<Chars<'_>>,
+    out: &mut String,
+    decode_byte: impl Fn(u8) -> char,
+) {
+    match chars.peek().copied() {
+        Some('c' | 'C') => {
+            chars.next();
+            out.push('∅');
+        }
+        Some('d' | 'D') => {
+            chars.next();
+            out.push('°');
+        }
+        Some('p' | 'P') => {
+            chars.next();
+            out.push('±');
+        }
+        Some('%') => {
+            chars.next();
+            out.push('%');
+        }
+        // TODO: Implement toggle underline, overline, and strikethrough with styled text.
+        Some('u' | 'U' | 'o' | 'O' | 'k' | 'K') => {
+            chars.next();
+        }
+        Some('0'..='9') => {
+            let mut code = 0_u32;
+            let mut digits = String::new();
+            while digits.len() < 3 {
+                let Some(d) = chars.peek().and_then(|d| d.to_digit(10)) else {
+                    break;
+                };
+                digits.push(chars.next().unwrap());
+                code = code * 10 + d;
+            }
+            match u8::try_from(code) {
+                Ok(b) if digits.len() == 3 => out.push(decode_byte(b)),
+                _ => {
+                    out.push_str("%%");
+                    out.push_str(&digits);
+                }
+            }
+        }
+        _ => out.push_str("%%"),
+    }
+}
+
+/// Parse the hexadecimal part of a `\U+XXXX` escape, after the `U`.
+///
+/// Nothing is consumed if the escape is malformed.
+fn unicode_escape(chars: &mut Peekable<Chars<'_>>) -> Option<char> {
+    let mut ahead = chars.clone();
+    if ahead.next() != Some('+') {
+        return None;
+    }
+    let mut code = 0_u32;
+    for _ in 0..4 {
+        code = code * 16 + ahead.next()?.to_digit(16)?;
+    }
+    let u = char::from_u32(code)?;
+    *chars = ahead;
+    Some(u)
+}
+
+/// Skip a `$(...)` DIESEL expression, including nested parentheses.
+fn skip_diesel(chars: &mut Peekable<Chars<'_>>) {
+    let mut depth = 0_usize;
+    for c in chars.by_ref() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Decode an MTEXT `\` code, after the backslash and code character `e`.
+fn decode_mtext_escape(
+    e: char,
+    chars: &mut Peekable<Chars<'_>>,
+    out: &mut String,
+    colors: &mut Colors,
+) {
+    match e {
+        'P' | 'N' => out.push('\n'),
+        '~' => out.push('\u{A0}'),
+        '\\' | '{' | '}' => out.push(e),
+        // TODO: Implement underline, overline, and strikethrough with styled text.
+        'L' | 'l' | 'O' | 'o' | 'K' | 'k' => {}
+        'S' => {
+            // Stacked text, written inline as a fraction.
+            for c in chars.by_ref() {
+                match c {
+                    ';' => break,
+                    '#' | '^' => out.push('/'),
+                    _ => out.push(c),
+                }
+            }
+        }
+        'C' | 'c' => {
+            let mut value = String::new();
+            for c in chars.by_ref() {
+                if c == ';' {
+                    break;
+                }
+                value.push(c);
+            }
+            let Ok(value) = value.trim().parse::<u32>() else {
+                return;
+            };
+            let color = match (e, value) {
+                // BYBLOCK and BYLAYER, which are the color of the entity.
+                ('C', 0 | 256) => None,
+                ('C', 1..=255) => Some(ACI[value as usize]),
+                ('C', _) => return,
+                // True colors are written with red in the low byte.
+                _ => Some(((value & 0xFF) << 16) | (value & 0xFF00) | ((value >> 16) & 0xFF)),
+            };
+            colors.set(color, out.len());
+        }
+        // TODO: Apply font, height, width, oblique, tracking, and paragraph changes.
+        'A' | 'F' | 'f' | 'H' | 'Q' | 'T' | 'W' | 'p' | 'X' => {
+            for c in chars.by_ref() {
+                if c == ';' {
+                    break;
+                }
+            }
+        }
+        _ => {
+            out.push('\\');
+            out.push(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> String {
+        decode_text(s, TextFlavor::Text, decode_ansi_1252)
+    }
+
+    fn mtext(s: &str) -> String {
+        decode_text(s, TextFlavor::MText, decode_ansi_1252)
+    }
+
+    #[test]
+    fn percent_codes() {
+        assert_eq!(
+            text("%%c10 %%d %%P0.1 100%%%"),
+            "∅10 ° ±0.1 100%",
+            "Symbol codes are replaced."
+        );
+        assert_eq!(text("%%uUNDER%%u"), "UNDER", "Toggles are removed.");
+        assert_eq!(
+            text("%%169 %%128"),
+            "© €",
+            "Character codes use the code page."
+        );
+        assert_eq!(
+            text("%%12a %%999"),
+            "%%12a %%999",
+            "Invalid codes are kept."
+        );
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(text("\\U+00B0 \\U+2205"), "° ∅", "Escapes are decoded.");
+        assert_eq!(mtext("A\\U+00e9B"), "AéB", "Escapes are decoded in MTEXT.");
+        assert_eq!(text("\\U+12"), "\\U+12", "Malformed escapes are kept.");
+    }
+
+    #[test]
+    fn diesel() {
+        assert_eq!(
+            text("Date: $(edtime,$(getvar,date),DD)."),
+            "Date: .",
+            "Nested expressions are removed."
+        );
+    }
+
+    #[test]
+    fn mtext_codes() {
+        assert_eq!(
+            mtext("{\\fArial|b1|i0|c0|p34;\\H2.5x;Line 1}\\PLine\\~2 \\S1/2; \\{x\\}"),
+            "Line 1\nLine\u{A0}2 1/2 {x}",
+            "Formatting codes are removed and escapes are decoded."
+        );
+        assert_eq!(
+            mtext("\\A1;\\LUnder\\l \\C1;red"),
+            "Under red",
+            "Formatting codes are removed."
+        );
+        assert_eq!(text("\\P"), "\\P", "TEXT does not have MTEXT codes.");
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn mtext_colors() {
+        let (s, colors) = decode_mtext_colors(
+            "a\\C1;red{\\c16711680;blue}red\\C256;b\\C3;",
+            decode_ansi_1252,
+        );
+        assert_eq!(s, "aredblueredb", "Color codes are removed.");
+        assert_eq!(
+            colors,
+            [(1..4, ACI[1]), (4..8, 0x0000FF), (8..11, ACI[1])],
+            "Colors should end with their group, and at codes for the entity color."
+        );
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Extraction of the text in a drawing, for indexing and search.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use core::{fmt, num::NonZeroU64};
+
+use dxf::entities::EntityType;
+use tabulon::peniko::kurbo::{Point, Vec2};
+
+use crate::{
+    EntityHandle, TDDrawing, point_from_dxf_point,
+    schedule::write_csv_field,
+    text_codes::{TextFlavor, decode_text},
+};
+
+/// Text found in a drawing.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextRecord {
+    /// Decoded text, without formatting codes.
+    ///
+    /// Paragraphs of MTEXT are separated by newlines.
+    pub content: String,
+    /// Name of the layer of the entity.
+    pub layer: String,
+    /// Height of the text, in drawing units.
+    pub height: f64,
+    /// Insertion point, in the same coordinates as loaded items.
+    pub position: Point,
+    /// Angle of the baseline in radians, in the same coordinates as loaded items, where
+    /// angles are clockwise in the drawing.
+    pub rotation: f64,
+    /// The TEXT or MTEXT entity, or the INSERT of an attribute.
+    pub entity: EntityHandle,
+}
+
+impl TDDrawing {
+    /// Extract the text of every TEXT, MTEXT, and attribute entity in the drawing.
+    ///
+    /// This reads the drawing rather than loaded items, so it doesn't need the `text`
+    /// feature, and includes text on layers that are off. Records are in drawing
+    /// order, with the attributes of an insert in their order after it.
+    pub fn extract_text(&self) -> Vec<TextRecord> {
+        let decode =
+            |s: &str, flavor| decode_text(s, flavor, |b| self.info.code_page.decode_byte(b));
+        let mut records = Vec::new();
+        for e in self.info.drawing.entities() {
+            let Some(entity) = NonZeroU64::new(e.common.handle.0).map(EntityHandle) else {
+                continue;
+            };
+            let record = |content, height, position: &dxf::Point, degrees: f64| TextRecord {
+                content,
+                layer: e.common.layer.clone(),
+                height,
+                position: point_from_dxf_point(position),
+                rotation: -degrees.to_radians(),
+                entity,
+            };
+            match e.specific {
+                EntityType::Text(ref t) => records.push(record(
+                    decode(&t.value, TextFlavor::Text),
+                    t.text_height,
+                    &t.location,
+                    t.rotation,
+                )),
+                EntityType::MText(ref mt) => {
+                    let mut text = mt.text.clone();
+                    for ext in mt.extended_text.iter() {
+                        text.push_str(ext);
+                    }
+                    let mut r = record(
+                        decode(&text, TextFlavor::MText),
+                        mt.initial_text_height,
+                        &mt.insertion_point,
+                        mt.rotation_angle,
+                    );
+                    // As when loading, the x axis direction adds to the rotation.
+                    r.rotation += Vec2::new(mt.x_axis_direction.x, -mt.x_axis_direction.y).atan2();
+                    records.push(r);
+                }
+                EntityType::Insert(ref ins) => {
+                    records.extend(ins.attributes().map(|a| {
+                        record(
+                            self.info.attribute_value(a),
+                            a.text_height,
+                            &a.location,
+                            a.rotation,
+                        )
+                    }));
+                }
+                _ => {}
+            }
+        }
+        records
+    }
+}
+
+/// Write text records as CSV, with a header row.
+///
+/// Positions are written in drawing coordinates, with y up as in the DXF, and rotations
+/// in degrees counterclockwise, as in the DXF. Entities are written as their
+/// [stable IDs](EntityHandle::stable_id).
+pub fn write_text_records_csv(records: &[TextRecord], out: &mut impl fmt::Write) -> fmt::Result {
+    writeln!(out, "content,layer,height,x,y,rotation,entity")?;
+    for r in records {
+        write_csv_field(out, &r.content)?;
+        out.write_char(',')?;
+        write_csv_field(out, &r.layer)?;
+        // Subtract from zero rather than negating, to avoid writing `-0`.
+        writeln!(
+            out,
+            ",{},{},{},{},{}",
+            r.height,
+            r.position.x,
+            0.0 - r.position.y,
+            0.0 - r.rotation.to_degrees(),
+            r.entity.stable_id()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::tests::load_drawing;
+    use dxf::{
+        Drawing,
+        entities::{Entity, Text},
+    };
+
+    #[test]
+    fn text_and_csv() {
+        let mut drawing = Drawing::new();
+        let mut entity = Entity::new(EntityType::Text(Text {
+            value: "%%c50, \"main\"".into(),
+            location: dxf::Point::new(1.0, 2.0, 0.0),
+            text_height: 2.5,
+            rotation: 90.0,
+            ..Default::default()
+        }));
+        entity.common.layer = "NOTES".into();
+        let id = drawing.add_entity(entity).common.handle.0;
+        let td = load_drawing("text_and_csv", &drawing);
+
+        let records = td.extract_text();
+        assert_eq!(records.len(), 1, "The text entity should be extracted.");
+        assert_eq!(
+            (records[0].content.as_str(), records[0].entity.stable_id()),
+            ("∅50, \"main\"", id),
+            "Special characters should be decoded."
+        );
+        let mut csv = String::new();
+        write_text_records_csv(&records, &mut csv).unwrap();
+        assert_eq!(
+            csv,
+            alloc::format!(
+                "content,layer,height,x,y,rotation,entity\n\
+                 \"∅50, \"\"main\"\"\",NOTES,2.5,1,2,90,{id}\n"
+            ),
+            "Rows should be in drawing coordinates and quoted when needed."
+        );
+
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_value(&records[0]).unwrap()["entity"],
+            id,
+            "Entities should be serialized as their stable IDs."
+        );
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Clip boundaries of inserts clipped with XCLIP.
+//!
+//! The boundary is stored in a `SPATIAL_FILTER` object in the extension dictionary
+//! of the INSERT:
+//!
+//! ```text
+//! INSERT -> {ACAD_XDICTIONARY} -> ACAD_FILTER -> SPATIAL -> SPATIAL_FILTER
+//! ```
+
+use dxf::{entities::Entity, objects::ObjectType};
+use tabulon::peniko::kurbo::{BezPath, Point, Rect, Shape};
+
+use crate::{objects::ObjectIndex, point_from_dxf_point};
+
+/// Get the clip boundary of an INSERT, in block coordinates.
+///
+/// Returns `None` if the insert is not clipped, or its clip boundary is disabled.
+/// Front and back clipping planes are ignored.
+pub(crate) fn clip_boundary(objects: &ObjectIndex<'_>, e: &Entity) -> Option<BezPath> {
+    let filter = objects.lookup(e, &["ACAD_FILTER", "SPATIAL"])?;
+    let ObjectType::SpatialFilter(ref sf) = filter.specific else {
+        return None;
+    };
+    if !sf.is_clip_boundary_enabled {
+        return None;
+    }
+    let points: Vec<Point> = sf
+        .clip_boundary_definition_points
+        .iter()
+        .map(point_from_dxf_point)
+        .collect();
+    match points.as_slice() {
+        // Two points are opposite corners of a rectangle.
+        [a, b] => Some(Rect::from_points(*a, *b).to_path(0.1)),
+        [first, rest @ ..] if rest.len() >= 2 => {
+            let mut bp = BezPath::new();
+            bp.move_to(*first);
+            for p in rest {
+                bp.line_to(*p);
+            }
+            bp.close_path();
+            Some(bp)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::XDICTIONARY_GROUP;
+    use dxf::{
+        CodePair, Drawing, ExtensionGroup, ExtensionGroupItem, Handle,
+        entities::{EntityType, Insert},
+        objects::{Dictionary, Object, SpatialFilter},
+    };
+
+    fn add_dictionary(drawing: &mut Drawing, key: &str, value: Handle) -> Handle {
+        let mut d = Dictionary::default();
+        d.value_handles.insert(key.into(), value);
+        drawing
+            .add_object(Object::new(ObjectType::Dictionary(d)))
+            .common
+            .handle
+    }
+
+    #[test]
+    fn rectangular_clip() {
+        let mut drawing = Drawing::new();
+        let filter = drawing
+            .add_object(Object::new(ObjectType::SpatialFilter(SpatialFilter {
+                clip_boundary_definition_points: vec![
+                    dxf::Point::new(1.0, 2.0, 0.0),
+                    dxf::Point::new(5.0, 4.0, 0.0),
+                ],
+                is_clip_boundary_enabled: true,
+                ..Default::default()
+            })))
+            .common
+            .handle;
+        let filters = add_dictionary(&mut drawing, "SPATIAL", filter);
+        let xdictionary = add_dictionary(&mut drawing, "ACAD_FILTER", filters);
+
+        let mut e = Entity::new(EntityType::Insert(Insert::default()));
+        e.common.extension_data_groups = vec![ExtensionGroup {
+            application_name: XDICTIONARY_GROUP.into(),
+            items: vec![ExtensionGroupItem::CodePair(CodePair::new_str(
+                360,
+                &format!("{:X}", xdictionary.0),
+            ))],
+        }];
+
+        let objects = ObjectIndex::new(&drawing);
+        let boundary = clip_boundary(&objects, &e).expect("Insert should be clipped.");
+        assert_eq!(
+            boundary.bounding_box(),
+            Rect::new(1.0, -4.0, 5.0, -2.0),
+            "Two points should make a rectangle."
+        );
+        assert!(
+            clip_boundary(
+                &objects,
+                &Entity::new(EntityType::Insert(Insert::default()))
+            )
+            .is_none(),
+            "Inserts without a spatial filter are not clipped."
+        );
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Formatting of values as instruction parameters.
+
+extern crate alloc;
+use alloc::string::String;
+use core::fmt::{self, Display, Formatter, Write};
+
+/// A number, written with up to three decimals, or as 0 if it is not finite.
+///
+/// Plotters parse a limited number of digits, and three decimals of a degree or a
+/// millimeter is well below what they can resolve.
+#[derive(Clone, Copy)]
+pub(crate) struct Num(pub(crate) f64);
+
+impl Display for Num {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.0.is_finite() {
+            return f.write_str("0");
+        }
+        let mut s = String::new();
+        let _ = write!(s, "{:.3}", self.0);
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        f.write_str(if s == "-0" { "0" } else { s })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            (
+                Num(90.0).to_string(),
+                Num(0.35).to_string(),
+                Num(-1e-6).to_string(),
+                Num(f64::INFINITY).to_string()
+            ),
+            ("90".into(), "0.35".into(), "0".into(), "0".into()),
+            "Numbers should have up to three decimals, without trailing zeros."
+        );
+    }
+}
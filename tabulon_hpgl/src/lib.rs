@@ -0,0 +1,329 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! HP-GL/2 plotter export for Tabulon.
+//!
+//! This writes [`RenderLayer`]s and [`LayerStack`]s as HP-GL/2 plots, for pen plotters
+//! and the cutters and large format printers that still take them. Pens are selected
+//! from the colors and stroke widths of paints, with widths as physical line weights in
+//! [iota](joto_constants::u64::IOTA), the unit `tabulon_dxf` expresses line weights in.
+//! Circular arcs in paths are plotted as arcs, other curves are flattened, dash
+//! patterns become user defined line types, and fills become filled polygons.
+//!
+//! Plotters can't draw images or translucency, so images are left out, and layers and
+//! colors are plotted opaque. Clips are plotted as input windows, which are rectangles,
+//! so shapes are clipped to the bounding boxes of their clips.
+//!
+//! ## Features
+//!
+//! - `std` (enabled by default): Use the standard library.
+//! - `libm`: Use floating point implementations from libm.
+//! - `text` (enabled by default): Plot text items as glyph outlines, shaped with
+//!   Parley. Without it, Parley is not compiled, and only shapes and markers are plotted.
+
+extern crate alloc;
+use alloc::string::String;
+
+use joto_constants::u64::MICROMETER;
+use tabulon::{
+    GraphicsBag,
+    backend::{DrawOptions, Renderer, draw_items, draw_layer_stack},
+    layer_stack::LayerStack,
+    peniko::{
+        Fill,
+        kurbo::{Affine, Size},
+    },
+    render_layer::RenderLayer,
+    shape::DashUnits,
+    uniform_scale,
+};
+
+#[cfg(feature = "text")]
+use {
+    parley::{FontContext, LayoutContext},
+    tabulon::peniko::Color,
+};
+
+use writer::HpglWriter;
+
+mod format;
+mod path;
+mod writer;
+
+/// Plotter units per millimeter; a plotter unit is 0.025 mm.
+const PLOTTER_UNITS_PER_MM: f64 = 40.0;
+
+/// Options for writing plots.
+#[derive(Clone, Copy, Debug)]
+pub struct HpglOptions {
+    /// Fill rule for filling shapes whose paints do not have one.
+    pub fill_rule: Fill,
+    /// Tolerance for flattening curves and finding arcs, in millimeters on the plot.
+    pub tolerance: f64,
+    /// Whether to plot fills, as filled polygons.
+    ///
+    /// Pen plotters fill polygons by hatching them with the pen, which is slow, so
+    /// plots of outlines may want to leave them out.
+    pub fills: bool,
+    /// Whether to plot text items.
+    pub text_enabled: bool,
+    /// Most pens to select; once all are used, the pen nearest in color and weight is
+    /// used for new paints.
+    pub max_pens: usize,
+    /// Thinnest line weight in [iota](joto_constants::u64::IOTA), which thinner strokes
+    /// are widened to, and the weight of pens that are only used for fills.
+    pub min_line_weight: u64,
+    /// Units of the dash patterns of strokes.
+    pub dash_units: DashUnits,
+}
+
+impl Default for HpglOptions {
+    fn default() -> Self {
+        Self {
+            fill_rule: Fill::NonZero,
+            // A plotter unit.
+            tolerance: 0.025,
+            fills: true,
+            text_enabled: true,
+            // A common size of pen carousel.
+            max_pens: 8,
+            // The thinnest of the ISO 128 line widths.
+            min_line_weight: 130 * MICROMETER,
+            dash_units: DashUnits::Local,
+        }
+    }
+}
+
+/// Expensive state for writing plots.
+#[derive(Default)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and members don't implement Debug."
+)]
+pub struct Environment {
+    /// Font context.
+    ///
+    /// This contains a font collection that is expensive to reproduce.
+    #[cfg(feature = "text")]
+    pub(crate) font_cx: FontContext,
+    /// Layout context.
+    #[cfg(feature = "text")]
+    pub(crate) layout_cx: LayoutContext<Option<Color>>,
+}
+
+impl Environment {
+    /// Write a [`RenderLayer`] as a plot of `size` in millimeters, with `view` from the
+    /// coordinates of `graphics` to millimeters on the plot.
+    pub fn render_layer_to_hpgl(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        size: Size,
+        view: Affine,
+    ) -> String {
+        self.render_layer_to_hpgl_with_options(
+            graphics,
+            render_layer,
+            size,
+            view,
+            &HpglOptions::default(),
+        )
+    }
+
+    /// Write a [`RenderLayer`] as a plot of `size` in millimeters with [`HpglOptions`],
+    /// with `view` from the coordinates of `graphics` to millimeters on the plot.
+    ///
+    /// `view` is y down, with the origin at the top left of the plot, like a view on
+    /// screen; the plot is flipped to the y up coordinates of the plotter. Items that
+    /// are missing from `graphics`, have nothing to draw, or are text when text is
+    /// disabled are skipped.
+    pub fn render_layer_to_hpgl_with_options(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        size: Size,
+        view: Affine,
+        options: &HpglOptions,
+    ) -> String {
+        self.write_plot(size, view, options, |renderer, draw_options| {
+            draw_items(
+                renderer,
+                graphics,
+                render_layer.indices.iter().copied(),
+                draw_options,
+            );
+        })
+    }
+
+    /// Write the visible layers of a [`LayerStack`] as a plot of `size` in millimeters,
+    /// with `view` from the coordinates of `graphics` to millimeters on the plot.
+    pub fn layer_stack_to_hpgl(
+        &mut self,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        size: Size,
+        view: Affine,
+    ) -> String {
+        self.layer_stack_to_hpgl_with_options(graphics, stack, size, view, &HpglOptions::default())
+    }
+
+    /// Write the visible layers of a [`LayerStack`] as a plot of `size` in millimeters
+    /// with [`HpglOptions`], with `view` from the coordinates of `graphics` to
+    /// millimeters on the plot.
+    ///
+    /// Layers are plotted from the lowest `z` to the highest, all opaque. Layers that
+    /// are fully transparent or empty are left out.
+    pub fn layer_stack_to_hpgl_with_options(
+        &mut self,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        size: Size,
+        view: Affine,
+        options: &HpglOptions,
+    ) -> String {
+        self.write_plot(size, view, options, |renderer, draw_options| {
+            draw_layer_stack(renderer, graphics, stack, draw_options);
+        })
+    }
+
+    /// Write a plot, drawing it with `draw`.
+    fn write_plot(
+        &mut self,
+        size: Size,
+        view: Affine,
+        options: &HpglOptions,
+        draw: impl FnOnce(&mut dyn Renderer, &DrawOptions),
+    ) -> String {
+        // Millimeters on the plot per unit of the drawing.
+        let scale = uniform_scale(view).max(f64::EPSILON);
+        let draw_options = DrawOptions {
+            fill_rule: options.fill_rule,
+            tolerance: options.tolerance / scale,
+            // Markers are sized in millimeters on the plot.
+            pixels_per_millimeter: 1.0 / scale,
+            dash_units: options.dash_units,
+        };
+        let base = Affine::new([
+            PLOTTER_UNITS_PER_MM,
+            0.0,
+            0.0,
+            -PLOTTER_UNITS_PER_MM,
+            0.0,
+            size.height * PLOTTER_UNITS_PER_MM,
+        ]) * view;
+        let mut writer = HpglWriter::new(self, options, base);
+        draw(&mut writer, &draw_options);
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use tabulon::{
+        peniko::{
+            Color,
+            kurbo::{Circle, Line, Shape, Stroke},
+        },
+        shape::{FatClip, FatPaint, FatShape},
+    };
+
+    #[test]
+    fn pens_from_paints() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let paints = [
+            (Color::from_rgb8(255, 0, 0), 0.5),
+            (Color::from_rgb8(0, 0, 255), 0.25),
+            (Color::from_rgb8(255, 0, 0), 0.5),
+            (Color::from_rgb8(250, 0, 0), 0.05),
+        ];
+        for (i, (color, width)) in paints.into_iter().enumerate() {
+            let paint = graphics.register_paint(FatPaint {
+                stroke: Stroke::new(width),
+                stroke_paint: Some(color.into()),
+                ..Default::default()
+            });
+            let y = i as f64;
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Line::new((0.0, y), (10.0, y)).into()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let plot = Environment::default().render_layer_to_hpgl_with_options(
+            &graphics,
+            &layer,
+            Size::new(20.0, 10.0),
+            Affine::IDENTITY,
+            &HpglOptions {
+                max_pens: 2,
+                ..Default::default()
+            },
+        );
+        assert!(
+            plot.starts_with("IN;NP2;PC1,255,0,0;PW0.5,1;PC2,0,0,255;PW0.25,2;\n"),
+            "Pens should be defined by color and weight in millimeters: {plot}"
+        );
+        assert!(
+            plot.contains("SP1;PU0,400;PD400,400;\nSP2;PU0,360;PD400,360;\nSP1;PU0,320;PD400,320;\nPU0,280;PD400,280;\n"),
+            "Paints should select matching pens, or the nearest once all are used, y up: {plot}"
+        );
+        assert!(plot.ends_with("PU;SP0;\n"), "The pen should be put away.");
+    }
+
+    #[test]
+    fn arcs_dashes_fills_and_clips() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(0.35).with_dashes(0.0, [3.0, 1.0, 0.5, 1.0]),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: Some(Color::from_rgb8(0, 128, 0).into()),
+            fill_rule: Some(Fill::EvenOdd),
+        });
+        let clip = graphics.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(Circle::new((10.0, 10.0), 5.0).to_path(0.01)),
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                clip,
+                shape: Arc::new(Circle::new((10.0, 10.0), 5.0).into()),
+                ..Default::default()
+            },
+        );
+
+        let plot = Environment::default().render_layer_to_hpgl(
+            &graphics,
+            &layer,
+            Size::new(20.0, 20.0),
+            Affine::IDENTITY,
+        );
+        assert!(
+            plot.contains("IW200,200,600,600;") && plot.contains("IW;"),
+            "The clip should be a window around it, which is reset after: {plot}"
+        );
+        assert!(
+            plot.contains("SP1;PU600,400;PM0;PD;AA400,400,-360;PM2;FP0;"),
+            "The circle should be filled as a polygon of an arc, with its fill rule: {plot}"
+        );
+        assert!(
+            plot.contains(
+                "SP2;UL1,54.545,18.182,9.091,18.182;LT1,5.5,1;PU600,400;PD;AA400,400,-360;"
+            ),
+            "The outline should be plotted as a dashed arc, clockwise when flipped: {plot}"
+        );
+        assert!(
+            plot.starts_with("IN;NP2;PC1,0,128,0;PW0.13,1;PC2,0,0,0;PW0.35,2;"),
+            "The fill pen should have the thinnest weight: {plot}"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Writing paths as pen moves, with circular arcs as arcs.
+//!
+//! Shapes reach the renderer as Bézier paths, so arcs and circles are found again by
+//! testing each curve against the circle through its ends and middle. Curves that are
+//! not arcs are flattened to lines.
+
+extern crate alloc;
+use alloc::string::String;
+use core::f64::consts::PI;
+use core::fmt::Write;
+
+use tabulon::peniko::kurbo::{self, BezPath, CubicBez, ParamCurve, PathEl, Point, QuadBez};
+
+use crate::format::Num;
+
+/// Writes pen moves, batching pen down moves into a single instruction.
+struct Pen<'a> {
+    out: &'a mut String,
+    /// Points of the pending `PD` instruction.
+    batch: String,
+    /// Center and sweep of the pending `AA` instruction.
+    arc: Option<((i64, i64), f64)>,
+    /// Whether the pen is down.
+    down: bool,
+    /// Last point written, in whole plotter units.
+    last: (i64, i64),
+}
+
+impl Pen<'_> {
+    fn flush(&mut self) {
+        if let Some((c, sweep)) = self.arc.take() {
+            let _ = write!(self.out, "AA{},{},{};", c.0, c.1, Num(sweep));
+        }
+        if !self.batch.is_empty() {
+            let _ = write!(self.out, "PD{};", self.batch);
+            self.batch.clear();
+            self.down = true;
+        }
+    }
+
+    fn move_to(&mut self, p: Point) {
+        self.flush();
+        self.last = round(p);
+        let _ = write!(self.out, "PU{},{};", self.last.0, self.last.1);
+        self.down = false;
+    }
+
+    fn line_to(&mut self, p: Point) {
+        let p = round(p);
+        if p == self.last && self.down {
+            return;
+        }
+        if let Some((c, sweep)) = self.arc.take() {
+            let _ = write!(self.out, "AA{},{},{};", c.0, c.1, Num(sweep));
+        }
+        if !self.batch.is_empty() {
+            self.batch.push(',');
+        }
+        let _ = write!(self.batch, "{},{}", p.0, p.1);
+        self.last = p;
+    }
+
+    /// Sweep an arc around `center` to `end`, continuing the pending arc if it has the
+    /// same center and direction.
+    fn arc(&mut self, center: Point, sweep: f64, end: Point) {
+        let c = round(center);
+        self.last = round(end);
+        if let Some((pending, total)) = &mut self.arc {
+            if *pending == c && (*total > 0.0) == (sweep > 0.0) && (*total + sweep).abs() <= 360.0 {
+                *total += sweep;
+                return;
+            }
+        }
+        self.flush();
+        if !self.down {
+            self.out.push_str("PD;");
+            self.down = true;
+        }
+        self.arc = Some((c, sweep));
+    }
+}
+
+/// Write the pen moves for a path in plotter units.
+///
+/// Curves that are circular arcs within `tolerance` are written as `AA` arcs, and
+/// other curves are flattened to lines within `tolerance`. Each subpath starts with a
+/// pen up move to its start, and closed subpaths end back at it. If `polygon` is
+/// `true`, the moves are written in polygon mode, with each subpath a subpolygon, for
+/// filling with `FP`.
+pub(crate) fn write_path(out: &mut String, path: &BezPath, tolerance: f64, polygon: bool) {
+    let mut pen = Pen {
+        out,
+        batch: String::new(),
+        arc: None,
+        down: false,
+        last: (0, 0),
+    };
+    let mut start = Point::ZERO;
+    let mut last = Point::ZERO;
+    let mut first = true;
+    for el in path.iter() {
+        match el {
+            PathEl::MoveTo(p) => {
+                if polygon {
+                    pen.flush();
+                    if first {
+                        pen.move_to(p);
+                        pen.out.push_str("PM0;");
+                        first = false;
+                        (start, last) = (p, p);
+                        continue;
+                    }
+                    pen.out.push_str("PM1;");
+                }
+                pen.move_to(p);
+                (start, last) = (p, p);
+            }
+            PathEl::LineTo(p) => {
+                pen.line_to(p);
+                last = p;
+            }
+            PathEl::QuadTo(p1, p2) => {
+                curve_to(&mut pen, QuadBez::new(last, p1, p2).raise(), tolerance);
+                last = p2;
+            }
+            PathEl::CurveTo(p1, p2, p3) => {
+                curve_to(&mut pen, CubicBez::new(last, p1, p2, p3), tolerance);
+                last = p3;
+            }
+            PathEl::ClosePath => {
+                pen.line_to(start);
+                last = start;
+            }
+        }
+    }
+    pen.flush();
+    if polygon && !first {
+        pen.out.push_str("PM2;");
+    }
+}
+
+/// Write a curve as an arc if it is one, or as lines otherwise.
+fn curve_to(pen: &mut Pen<'_>, c: CubicBez, tolerance: f64) {
+    if let Some((center, sweep)) = as_arc(c, tolerance) {
+        pen.arc(center, sweep, c.p3);
+        return;
+    }
+    let mut curve = BezPath::new();
+    curve.move_to(c.p0);
+    curve.curve_to(c.p1, c.p2, c.p3);
+    kurbo::flatten(curve, tolerance, |el| {
+        if let PathEl::LineTo(p) = el {
+            pen.line_to(p);
+        }
+    });
+}
+
+/// Get the center and sweep in degrees of a curve that is a circular arc within
+/// `tolerance`, counterclockwise positive, or `None` if it isn't an arc.
+fn as_arc(c: CubicBez, tolerance: f64) -> Option<(Point, f64)> {
+    let middle = c.eval(0.5);
+    // Approximations of arcs have exact tangents at their ends, so the center is where
+    // the normals at the ends meet, unless they are parallel, as for half circles.
+    let (t0, t1) = (c.p1 - c.p0, c.p3 - c.p2);
+    let (n0, n1) = (t0.turn_90(), t1.turn_90());
+    let d = n0.cross(n1);
+    let center = if d.abs() > 1e-9 * n0.length() * n1.length() {
+        c.p0 + n0 * ((c.p3 - c.p0).cross(n1) / d)
+    } else {
+        circumcenter(c.p0, middle, c.p3)?
+    };
+    let radius = c.p0.distance(center);
+    if (c.p3.distance(center) - radius).abs() > tolerance {
+        return None;
+    }
+    // Arcs that are too slight to tell from lines are left to be flattened.
+    if radius * tolerance > 1e12 {
+        return None;
+    }
+    for t in [0.125, 0.25, 0.375, 0.625, 0.75, 0.875] {
+        if (c.eval(t).distance(center) - radius).abs() > tolerance {
+            return None;
+        }
+    }
+    let angle = |p: Point| (p - center).atan2();
+    // Each half of the curve turns by less than a half turn, so the sweep is the sum of
+    // the smallest signed turns from the start to the middle, and on to the end.
+    let turn = |a: f64| (a + PI).rem_euclid(2.0 * PI) - PI;
+    let sweep = turn(angle(middle) - angle(c.p0)) + turn(angle(c.p3) - angle(middle));
+    Some((center, sweep.to_degrees()))
+}
+
+/// Get the center of the circle through three points, or `None` if they are in a line.
+fn circumcenter(a: Point, b: Point, c: Point) -> Option<Point> {
+    let (b, c) = (b - a, c - a);
+    let d = 2.0 * b.cross(c);
+    if d.abs() < 1e-9 * b.length_squared().max(c.length_squared()) || d == 0.0 {
+        return None;
+    }
+    let (bb, cc) = (b.length_squared(), c.length_squared());
+    Some(Point::new(
+        a.x + (c.y * bb - b.y * cc) / d,
+        a.y + (b.x * cc - c.x * bb) / d,
+    ))
+}
+
+/// Round a point to whole plotter units.
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "Plotter units are far within the range of an i64."
+)]
+fn round(p: Point) -> (i64, i64) {
+    (p.x.round() as i64, p.y.round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::peniko::kurbo::{Arc, Circle, Shape, Vec2};
+
+    #[test]
+    fn arcs_are_found_in_curves() {
+        let mut out = String::new();
+        write_path(
+            &mut out,
+            &Circle::new((0.0, 0.0), 400.0).to_path(0.1),
+            1.0,
+            false,
+        );
+        assert_eq!(
+            out, "PU400,0;PD;AA0,0,360;",
+            "The circle should be plotted counterclockwise as a single arc around its center."
+        );
+
+        let mut out = String::new();
+        let arc = Arc::new((0.0, 0.0), Vec2::new(400.0, 400.0), 0.0, -PI * 0.5, 0.0);
+        write_path(&mut out, &arc.to_path(0.1), 1.0, false);
+        assert!(
+            out.ends_with("AA0,0,-90;"),
+            "Clockwise arcs should have a negative sweep: {out}"
+        );
+
+        let mut out = String::new();
+        let ellipse = Arc::new((0.0, 0.0), Vec2::new(400.0, 200.0), 0.0, PI * 0.5, 0.0);
+        write_path(&mut out, &ellipse.to_path(0.1), 1.0, false);
+        assert!(
+            !out.contains("AA") && out.starts_with("PU400,0;PD"),
+            "Elliptic arcs should be flattened to lines: {out}"
+        );
+    }
+
+    #[test]
+    fn polygons_have_subpolygons() {
+        let mut path = BezPath::new();
+        for x in [0.0, 100.0] {
+            path.move_to((x, 0.0));
+            path.line_to((x + 50.0, 0.0));
+            path.line_to((x + 50.0, 50.0));
+            path.close_path();
+        }
+        let mut out = String::new();
+        write_path(&mut out, &path, 1.0, true);
+        assert_eq!(
+            out, "PU0,0;PM0;PD50,0,50,50,0,0;PM1;PU100,0;PD150,0,150,50,100,0;PM2;",
+            "Subpaths should be closed subpolygons."
+        );
+    }
+}
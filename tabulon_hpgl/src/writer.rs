@@ -0,0 +1,420 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Writing HP-GL/2 instructions through the [`Renderer`] interface.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use joto_constants::u64::{MICROMETER, MILLIMETER};
+#[cfg(feature = "text")]
+use tabulon::{
+    ItemHandle,
+    outline::layout_text,
+    shape::FatPaint,
+    text::FatText,
+    text_on_path::{FatTextOnPath, MeasuredPath},
+};
+use tabulon::{
+    backend::Renderer,
+    peniko::{
+        Brush, Color, Fill, Image,
+        kurbo::{Affine, BezPath, Rect, Shape, Stroke},
+    },
+    uniform_scale,
+};
+
+use crate::{Environment, HpglOptions, PLOTTER_UNITS_PER_MM, format::Num, path::write_path};
+
+/// Most gaps of a user defined line type.
+const MAX_GAPS: usize = 20;
+
+/// Number of user defined line types a plotter keeps.
+const LINE_TYPES: usize = 8;
+
+/// A pen of the plotter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Pen {
+    /// Color of the pen, opaque.
+    pub(crate) color: [u8; 3],
+    /// Width of the pen in [iota](joto_constants::u64::IOTA).
+    pub(crate) weight: u64,
+}
+
+/// A clip or layer that was pushed, and is ended by the matching [`Renderer::pop`].
+enum Frame {
+    /// A clip, with the window it replaced.
+    Clip(Option<Rect>),
+    /// A layer, which plotters can't make translucent, so it is plotted as it is.
+    Layer,
+}
+
+/// Writes the instructions of a plot for what is drawn.
+///
+/// Pens are selected from the colors and widths of paints as they are drawn, and
+/// defined in the header of the plot when it is finished.
+pub(crate) struct HpglWriter<'a> {
+    /// Instructions of the plot, after its header.
+    pub(crate) body: String,
+    /// Pens selected so far, numbered from 1.
+    pens: Vec<Pen>,
+    /// Number of the selected pen.
+    pen: Option<usize>,
+    /// User defined line types, as percentages of their pattern length.
+    line_types: Vec<Vec<f64>>,
+    /// The current line type instruction.
+    line_type: String,
+    /// The current clip window, in plotter units.
+    window: Option<Rect>,
+    /// Clips and layers being drawn.
+    stack: Vec<Frame>,
+    /// Transform from the coordinates of the drawing to plotter units.
+    base: Affine,
+    /// Options for plotting.
+    options: &'a HpglOptions,
+    /// Environment for shaping text.
+    #[cfg(feature = "text")]
+    env: &'a mut Environment,
+}
+
+impl<'a> HpglWriter<'a> {
+    pub(crate) fn new(env: &'a mut Environment, options: &'a HpglOptions, base: Affine) -> Self {
+        #[cfg(not(feature = "text"))]
+        let _ = env;
+        Self {
+            body: String::new(),
+            pens: Vec::new(),
+            pen: None,
+            line_types: Vec::new(),
+            line_type: String::from("LT;"),
+            window: None,
+            stack: Vec::new(),
+            base,
+            options,
+            #[cfg(feature = "text")]
+            env,
+        }
+    }
+
+    /// Get the plot, with the header defining its pens, and the pen put away at the end.
+    pub(crate) fn finish(self) -> String {
+        let mut out = String::from("IN;");
+        if !self.pens.is_empty() {
+            let _ = write!(out, "NP{};", self.pens.len());
+        }
+        for (i, pen) in self.pens.iter().enumerate() {
+            let [r, g, b] = pen.color;
+            let _ = write!(
+                out,
+                "PC{n},{r},{g},{b};PW{},{n};",
+                Num(pen.weight as f64 / MILLIMETER as f64),
+                n = i + 1
+            );
+        }
+        out.push('\n');
+        out.push_str(&self.body);
+        out.push_str("PU;SP0;\n");
+        out
+    }
+
+    /// Select a pen for a color, and a weight in [iota](joto_constants::u64::IOTA).
+    ///
+    /// A pen of the same color and weight is reused, or for fills, which have no weight,
+    /// of the same color with any weight. Otherwise a new pen is added while there are
+    /// fewer than [`max_pens`](HpglOptions::max_pens), and once there are not, the pen
+    /// nearest in color, then weight, is used.
+    fn select_pen(&mut self, color: Color, weight: Option<u64>) {
+        let [r, g, b, _] = color.to_rgba8().to_u8_array();
+        let color = [r, g, b];
+        let found = self
+            .pens
+            .iter()
+            .position(|p| p.color == color && weight.is_none_or(|w| p.weight == w));
+        let pen = match found {
+            Some(i) => i,
+            None if self.pens.len() < self.options.max_pens.max(1) => {
+                self.pens.push(Pen {
+                    color,
+                    weight: weight.unwrap_or(self.options.min_line_weight),
+                });
+                self.pens.len() - 1
+            }
+            None => {
+                let distance = |p: &Pen| {
+                    let d: u32 = (0..3)
+                        .map(|i| u32::from(p.color[i].abs_diff(color[i])).pow(2))
+                        .sum();
+                    (d, weight.map_or(0, |w| p.weight.abs_diff(w)))
+                };
+                (0..self.pens.len())
+                    .min_by_key(|i| distance(&self.pens[*i]))
+                    .unwrap_or(0)
+            }
+        };
+        if self.pen != Some(pen + 1) {
+            let _ = write!(self.body, "SP{};", pen + 1);
+            self.pen = Some(pen + 1);
+        }
+    }
+
+    /// Set the line type for a dash pattern in millimeters, or solid lines if the
+    /// pattern is empty.
+    ///
+    /// Patterns are defined as user line types, and when all of them are used, the last
+    /// one is redefined. Plotters have no dash offset, so patterns start with their
+    /// first dash.
+    fn set_line_type(&mut self, dashes: &[f64]) {
+        let total: f64 = dashes.iter().map(|d| d.max(0.0)).sum();
+        let line_type = if total > 0.0 {
+            // Odd patterns repeat to alternate between dashes and gaps.
+            let repeat = if dashes.len() % 2 == 1 { 2 } else { 1 };
+            let gaps: Vec<f64> = dashes
+                .iter()
+                .cycle()
+                .take(dashes.len() * repeat)
+                .take(MAX_GAPS)
+                .map(|d| d.max(0.0) / total / repeat as f64 * 100.0)
+                .collect();
+            let index = match self.line_types.iter().position(|t| *t == gaps) {
+                Some(i) => i,
+                None => {
+                    let i = self.line_types.len().min(LINE_TYPES - 1);
+                    let _ = write!(self.body, "UL{}", i + 1);
+                    for gap in &gaps {
+                        let _ = write!(self.body, ",{}", Num(*gap));
+                    }
+                    self.body.push(';');
+                    if i < self.line_types.len() {
+                        self.line_types[i] = gaps;
+                    } else {
+                        self.line_types.push(gaps);
+                    }
+                    i
+                }
+            };
+            alloc::format!("LT{},{},1;", index + 1, Num(total * repeat as f64))
+        } else {
+            String::from("LT;")
+        };
+        if line_type != self.line_type {
+            self.body.push_str(&line_type);
+            self.line_type = line_type;
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill and stroke a path with a paint, as [`draw_items`](tabulon::backend::draw_items)
+    /// does for shapes.
+    fn draw_paint(&mut self, transform: Affine, paint: &FatPaint, path: &BezPath) {
+        if let Some(fill_paint) = &paint.fill_paint {
+            let fill_rule = paint.fill_rule.unwrap_or(self.options.fill_rule);
+            self.fill(transform, fill_rule, fill_paint, path);
+        }
+        if let Some(stroke_paint) = &paint.stroke_paint {
+            let stroke = self.options.dash_units.apply(&paint.stroke, transform);
+            self.stroke(transform, &stroke, stroke_paint, path);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill glyph outlines, each with its own transform within `transform`, grouping
+    /// glyphs of the same color into one polygon.
+    ///
+    /// Glyphs are filled with their own color if they have one, and with `brush` otherwise.
+    fn fill_glyphs<'g>(
+        &mut self,
+        transform: Affine,
+        brush: &Brush,
+        glyphs: impl Iterator<Item = (Option<Color>, Affine, &'g BezPath)>,
+    ) {
+        let fill = |this: &mut Self, color: Option<Color>, outlines: &BezPath| {
+            let brush = color.map_or_else(|| brush.clone(), Brush::Solid);
+            this.fill(transform, Fill::NonZero, &brush, outlines);
+        };
+        let mut run: Option<(Option<Color>, BezPath)> = None;
+        for (color, placement, outline) in glyphs {
+            match &mut run {
+                Some((c, outlines)) if *c == color => {
+                    outlines.extend(outline.iter().map(|el| placement * el));
+                }
+                _ => {
+                    if let Some((c, outlines)) = run.take() {
+                        fill(self, c, &outlines);
+                    }
+                    run = Some((color, placement * outline));
+                }
+            }
+        }
+        if let Some((c, outlines)) = run {
+            fill(self, c, &outlines);
+        }
+    }
+}
+
+impl Renderer for HpglWriter<'_> {
+    fn fill(&mut self, transform: Affine, fill_rule: Fill, brush: &Brush, path: &BezPath) {
+        if !self.options.fills || path.is_empty() {
+            return;
+        }
+        let Some(color) = brush_color(brush) else {
+            return;
+        };
+        self.select_pen(color, None);
+        write_path(
+            &mut self.body,
+            &(self.base * transform * path.clone()),
+            self.options.tolerance * PLOTTER_UNITS_PER_MM,
+            true,
+        );
+        self.body.push_str(match fill_rule {
+            Fill::EvenOdd => "FP0;\n",
+            Fill::NonZero => "FP1;\n",
+        });
+    }
+
+    fn stroke(&mut self, transform: Affine, stroke: &Stroke, brush: &Brush, path: &BezPath) {
+        // Millimeters on the plot per unit of the path.
+        let mm_per_unit = uniform_scale(self.base * transform) / PLOTTER_UNITS_PER_MM;
+        if path.is_empty() || !mm_per_unit.is_finite() || mm_per_unit <= 0.0 {
+            return;
+        }
+        let Some(color) = brush_color(brush) else {
+            return;
+        };
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "Line weights are positive, and far within the range of a u64."
+        )]
+        let weight = ((stroke.width * mm_per_unit * 1000.0).round().max(0.0) as u64 * MICROMETER)
+            .max(self.options.min_line_weight);
+        self.select_pen(color, Some(weight));
+        let dashes: Vec<f64> = stroke
+            .dash_pattern
+            .iter()
+            .map(|d| d * mm_per_unit)
+            .collect();
+        self.set_line_type(&dashes);
+        write_path(
+            &mut self.body,
+            &(self.base * transform * path.clone()),
+            self.options.tolerance * PLOTTER_UNITS_PER_MM,
+            false,
+        );
+        self.body.push('\n');
+    }
+
+    fn image(&mut self, _transform: Affine, _image: &Image, _opacity: f32) {
+        // Plotters can't draw images.
+    }
+
+    #[cfg(feature = "text")]
+    fn text(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatText,
+        paint: &FatPaint,
+        background: Option<&FatPaint>,
+    ) {
+        if !self.options.text_enabled {
+            return;
+        }
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        let laid_out = layout_text(&mut self.env.font_cx, &mut self.env.layout_cx, t, true);
+        if let (Some(background), Some(rect)) = (background, t.background_rect(laid_out.size)) {
+            let placement = transform * t.placement_for_size(laid_out.size);
+            self.draw_paint(placement, background, &rect.to_path(0.1));
+        }
+        self.fill_glyphs(
+            transform * laid_out.placement(t),
+            fill_paint,
+            laid_out.positioned_glyphs(),
+        );
+    }
+
+    #[cfg(feature = "text")]
+    fn text_on_path(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatTextOnPath,
+        paint: &FatPaint,
+    ) {
+        if !self.options.text_enabled {
+            return;
+        }
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        let laid_out = layout_text(
+            &mut self.env.font_cx,
+            &mut self.env.layout_cx,
+            &t.line_text(),
+            true,
+        );
+        let measured = MeasuredPath::new(&t.path);
+        self.fill_glyphs(
+            transform,
+            fill_paint,
+            laid_out.glyphs_on_path(&measured, t.offset),
+        );
+    }
+
+    fn push_clip(&mut self, transform: Affine, path: &BezPath) {
+        // Plotters only clip to rectangular windows, so the clip is its bounding box.
+        let bounds = (self.base * transform * path.clone()).bounding_box();
+        let window = match self.window {
+            Some(window) => window.intersect(bounds),
+            None => bounds,
+        };
+        self.stack.push(Frame::Clip(self.window));
+        self.window = Some(window);
+        write_window(&mut self.body, Some(window));
+    }
+
+    fn push_layer(&mut self, _opacity: f32, _bounds: Rect) {
+        self.stack.push(Frame::Layer);
+    }
+
+    fn pop(&mut self) {
+        if let Some(Frame::Clip(window)) = self.stack.pop() {
+            self.window = window;
+            write_window(&mut self.body, window);
+        }
+    }
+}
+
+/// Get the color to plot a brush with, or `None` if it paints nothing.
+///
+/// Pens have a single color, so gradients are plotted with the color of their first
+/// stop. Images and fully transparent colors paint nothing.
+fn brush_color(brush: &Brush) -> Option<Color> {
+    let color = match brush {
+        Brush::Solid(c) => *c,
+        Brush::Gradient(g) => g.stops.first()?.color.to_alpha_color(),
+        Brush::Image(_) => return None,
+    };
+    (color.components[3] > 0.0).then_some(color)
+}
+
+/// Write an input window instruction, clipping to `window`, or to the whole plot if
+/// it is `None`.
+fn write_window(out: &mut String, window: Option<Rect>) {
+    match window {
+        Some(w) => {
+            let _ = write!(
+                out,
+                "IW{},{},{},{};",
+                Num(w.x0.round()),
+                Num(w.y0.round()),
+                Num(w.x1.round()),
+                Num(w.y1.round())
+            );
+        }
+        None => out.push_str("IW;"),
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Writing the objects of a PDF file, and the cross-reference table to find them.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter, Write};
+
+use tabulon::peniko::kurbo::Affine;
+
+/// Reference to an indirect object, by its object number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Ref(pub(crate) usize);
+
+impl Display for Ref {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} 0 R", self.0)
+    }
+}
+
+/// A number, written with up to five decimals, or as 0 if it is not finite.
+///
+/// PDF has no exponents in numbers, and readers are not required to keep more precision.
+#[derive(Clone, Copy)]
+pub(crate) struct Num(pub(crate) f64);
+
+impl Display for Num {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.0.is_finite() {
+            return f.write_str("0");
+        }
+        let mut s = String::new();
+        let _ = write!(s, "{:.5}", self.0);
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        f.write_str(if s == "-0" { "0" } else { s })
+    }
+}
+
+/// A transform, as the operands of a `cm` operator.
+pub(crate) struct Matrix(pub(crate) Affine);
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0.as_coeffs().map(Num);
+        write!(f, "{a} {b} {c} {d} {e} {g}")
+    }
+}
+
+/// A PDF file being written.
+///
+/// Objects are numbered when they are [allocated](Self::alloc), so that they can be
+/// referred to before they are written, and written in any order.
+pub(crate) struct Document {
+    /// The file so far.
+    buf: Vec<u8>,
+    /// Offset of each object in `buf`, by object number less one, once written.
+    offsets: Vec<Option<usize>>,
+}
+
+impl Document {
+    pub(crate) fn new() -> Self {
+        let mut buf = Vec::new();
+        // A comment with bytes above 127 marks the file as binary for transfer tools.
+        buf.extend_from_slice(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n");
+        Self {
+            buf,
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Allocate an object number for an object to write later.
+    pub(crate) fn alloc(&mut self) -> Ref {
+        self.offsets.push(None);
+        Ref(self.offsets.len())
+    }
+
+    /// Start writing an object that was allocated.
+    fn start(&mut self, id: Ref) {
+        self.offsets[id.0 - 1] = Some(self.buf.len());
+        let _ = writeln!(Bytes(&mut self.buf), "{} 0 obj", id.0);
+    }
+
+    /// Write an object that is a dictionary or any other value.
+    pub(crate) fn object(&mut self, id: Ref, value: &str) {
+        self.start(id);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// Write a stream with the entries of its dictionary other than its length and
+    /// filter, compressing its data.
+    pub(crate) fn stream(&mut self, id: Ref, entries: &str, data: &[u8]) {
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+        self.start(id);
+        let _ = write!(
+            Bytes(&mut self.buf),
+            "<<{entries} /Filter /FlateDecode /Length {}>>\nstream\n",
+            data.len()
+        );
+        self.buf.extend_from_slice(&data);
+        self.buf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    /// Write the cross-reference table and trailer, and get the file.
+    ///
+    /// Objects that were allocated but never written are marked as free.
+    pub(crate) fn finish(mut self, catalog: Ref) -> Vec<u8> {
+        let xref = self.buf.len();
+        let mut out = Bytes(&mut self.buf);
+        let _ = write!(
+            out,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.offsets.len() + 1
+        );
+        for offset in &self.offsets {
+            let _ = match offset {
+                Some(offset) => writeln!(out, "{offset:010} 00000 n "),
+                None => writeln!(out, "0000000000 00000 f "),
+            };
+        }
+        let _ = write!(
+            out,
+            "trailer\n<</Size {} /Root {catalog}>>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len() + 1
+        );
+        self.buf
+    }
+}
+
+/// Writes formatted text into bytes.
+struct Bytes<'a>(&'a mut Vec<u8>);
+
+impl Write for Bytes<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            (
+                Num(2.0).to_string(),
+                Num(0.125).to_string(),
+                Num(-1.0 / 3.0).to_string(),
+                Num(-1e-9).to_string(),
+                Num(f64::NAN).to_string()
+            ),
+            (
+                "2".into(),
+                "0.125".into(),
+                "-0.33333".into(),
+                "0".into(),
+                "0".into()
+            ),
+            "Numbers should have up to five decimals, without trailing zeros or exponents."
+        );
+    }
+
+    #[test]
+    fn cross_references_point_at_objects() {
+        let mut doc = Document::new();
+        let (first, unused, second) = (doc.alloc(), doc.alloc(), doc.alloc());
+        doc.object(second, "<</Type /Catalog>>");
+        doc.stream(first, "", b"0 0 m");
+        let pdf = doc.finish(second);
+
+        let xref = pdf
+            .windows(6)
+            .position(|w| w == b"\nxref\n")
+            .expect("The file should have a cross-reference table.")
+            + 1;
+        let tail = core::str::from_utf8(&pdf[xref..]).expect("The table should be text.");
+        let entries: Vec<&str> = tail.lines().skip(3).take(3).collect();
+        assert_eq!(
+            entries[unused.0 - 1],
+            "0000000000 00000 f ",
+            "Objects that were never written should be free."
+        );
+        for id in [first, second] {
+            let offset: usize = entries[id.0 - 1][..10].parse().unwrap();
+            assert!(
+                pdf[offset..].starts_with(alloc::format!("{} 0 obj\n", id.0).as_bytes()),
+                "The entry for object {} should point at it.",
+                id.0
+            );
+        }
+        assert!(
+            tail.contains("trailer\n<</Size 4 /Root 3 0 R>>")
+                && tail.ends_with(&alloc::format!("startxref\n{xref}\n%%EOF\n")),
+            "The trailer should refer to the catalog and the table: {tail}"
+        );
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! PDF export for Tabulon.
+//!
+//! This writes [`RenderLayer`]s and [`LayerStack`]s as paged vector PDF documents, so
+//! that a view of a drawing can be printed or archived without rasterizing it. A region
+//! of the drawing is placed on sheets of a [`PageSetup`], either fitted onto a single
+//! page, or at a fixed [`PrintScale`] such as 1:50, tiled over as many pages as it
+//! takes. Stroke widths are mapped to line weights on paper with [`LineWeights`].
+//!
+//! Shapes and markers become paths with their fills and strokes, dash patterns
+//! included, clips become clipping paths, images are embedded, and layers that are not
+//! fully opaque become transparency groups. Text is shaped with Parley, and written as
+//! glyph outlines, so it prints the same everywhere without embedding fonts.
+//!
+//! ## Features
+//!
+//! - `std` (enabled by default): Use the standard library.
+//! - `libm`: Use floating point implementations from libm.
+//! - `text` (enabled by default): Write text items, shaped with Parley. Without it,
+//!   Parley is not compiled, and only shapes, images, and markers are written.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use tabulon::{
+    GraphicsBag,
+    backend::{DrawOptions, Renderer, draw_items, draw_layer_stack},
+    layer_stack::LayerStack,
+    peniko::{
+        Fill,
+        kurbo::{Affine, Insets, Rect, Size},
+    },
+    render_layer::RenderLayer,
+    shape::DashUnits,
+};
+
+#[cfg(feature = "text")]
+use {
+    parley::{FontContext, LayoutContext},
+    tabulon::peniko::Color,
+};
+
+use writer::PdfWriter;
+
+mod document;
+mod writer;
+
+/// Points per millimeter, as PDF user space is in points of 1/72 inch.
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// Tolerance for converting shapes to paths, in millimeters on paper.
+const TOLERANCE_MM: f64 = 0.01;
+
+/// ISO A0 sheet, portrait, in millimeters.
+pub const A0: Size = Size::new(841.0, 1189.0);
+/// ISO A1 sheet, portrait, in millimeters.
+pub const A1: Size = Size::new(594.0, 841.0);
+/// ISO A2 sheet, portrait, in millimeters.
+pub const A2: Size = Size::new(420.0, 594.0);
+/// ISO A3 sheet, portrait, in millimeters.
+pub const A3: Size = Size::new(297.0, 420.0);
+/// ISO A4 sheet, portrait, in millimeters.
+pub const A4: Size = Size::new(210.0, 297.0);
+/// US Letter sheet, portrait, in millimeters.
+pub const LETTER: Size = Size::new(215.9, 279.4);
+
+/// Scale of the drawing on paper.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PrintScale {
+    /// As large as the region fits on a single page, centered in its printable area.
+    #[default]
+    Fit,
+    /// Millimeters on paper per unit of the drawing.
+    ///
+    /// The region is tiled over as many pages as it needs, from the top left, in rows.
+    MillimetersPerUnit(f64),
+}
+
+impl PrintScale {
+    /// Scale for a ratio of 1:`denominator`, such as 1:50, for a drawing whose units are
+    /// `unit_millimeters` long.
+    ///
+    /// For example, a drawing in meters printed at 1:100 is `ratio(100.0, 1000.0)`, and
+    /// one in millimeters is `ratio(100.0, 1.0)`.
+    pub fn ratio(denominator: f64, unit_millimeters: f64) -> Self {
+        Self::MillimetersPerUnit(unit_millimeters / denominator)
+    }
+}
+
+/// How stroke widths map to line weights on paper.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineWeights {
+    /// Stroke widths are in the coordinates of their paths, and scale with the drawing,
+    /// as they do on screen.
+    #[default]
+    Scaled,
+    /// Stroke widths, dash patterns, and dash offsets are in millimeters on paper,
+    /// regardless of the scale, as pen widths of a plot style are.
+    Millimeters,
+}
+
+/// Sheet, margins, scale, and line weights of the pages of a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSetup {
+    /// Size of the sheet in millimeters, such as [`A3`].
+    ///
+    /// The sheet constants are portrait; swap the width and height for landscape.
+    pub sheet: Size,
+    /// Margins of the sheet in millimeters, which are left blank.
+    pub margins: Insets,
+    /// Scale of the drawing on paper.
+    pub scale: PrintScale,
+    /// How stroke widths map to line weights on paper.
+    pub line_weights: LineWeights,
+    /// Thinnest line weight in millimeters on paper, which thinner strokes are widened
+    /// to, so that they are not lost when printed.
+    pub min_line_weight: f64,
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        Self {
+            sheet: A4,
+            margins: Insets::uniform(10.0),
+            scale: PrintScale::Fit,
+            line_weights: LineWeights::Scaled,
+            // The thinnest of the ISO 128 line widths.
+            min_line_weight: 0.13,
+        }
+    }
+}
+
+impl PageSetup {
+    /// Get the millimeters on paper per unit of the drawing for printing `region`, or
+    /// `None` if nothing can be printed, because the region or printable area is empty,
+    /// or the scale is not positive.
+    pub fn millimeters_per_unit(&self, region: Rect) -> Option<f64> {
+        let printable = self.printable_size();
+        let mm_per_unit = match self.scale {
+            PrintScale::Fit => (printable.width / region.width().abs())
+                .min(printable.height / region.height().abs()),
+            PrintScale::MillimetersPerUnit(mm_per_unit) => mm_per_unit,
+        };
+        (mm_per_unit.is_finite()
+            && mm_per_unit > 0.0
+            && printable.width > 0.0
+            && printable.height > 0.0
+            && region.is_finite()
+            && !region.is_zero_area())
+        .then_some(mm_per_unit)
+    }
+
+    /// Get the transforms from the coordinates of the drawing to the points of each
+    /// page, for printing `region`, which is y down like the view it is taken from.
+    ///
+    /// Points are 1/72 inch, with the origin at the bottom left of the sheet, and y up.
+    /// With a fixed scale, pages tile the region in rows from its top left; with
+    /// [`PrintScale::Fit`], there is a single page. Returns no transforms if nothing
+    /// can be printed.
+    pub fn page_transforms(&self, region: Rect) -> Vec<Affine> {
+        let Some(mm_per_unit) = self.millimeters_per_unit(region) else {
+            return Vec::new();
+        };
+        let region = region.abs();
+        let printable = self.printable_size();
+        let k = mm_per_unit * POINTS_PER_MM;
+        // Transform placing `origin` of the drawing at `offset` millimeters from the top
+        // left of the printable area.
+        let page = |origin: (f64, f64), offset: (f64, f64)| {
+            let left = (self.margins.x0 + offset.0) * POINTS_PER_MM;
+            let top = (self.sheet.height - self.margins.y0 - offset.1) * POINTS_PER_MM;
+            Affine::new([k, 0.0, 0.0, -k, left - k * origin.0, top + k * origin.1])
+        };
+        match self.scale {
+            PrintScale::Fit => {
+                let offset = (
+                    (printable.width - region.width() * mm_per_unit) * 0.5,
+                    (printable.height - region.height() * mm_per_unit) * 0.5,
+                );
+                alloc::vec![page((region.x0, region.y0), offset)]
+            }
+            PrintScale::MillimetersPerUnit(_) => {
+                let tile = printable / mm_per_unit;
+                // Regions that fit exactly are not given an extra page for rounding.
+                let count = |length: f64, tile: f64| (length / tile - 1e-9).ceil().max(1.0);
+                let (columns, rows) = (
+                    count(region.width(), tile.width),
+                    count(region.height(), tile.height),
+                );
+                let mut pages = Vec::new();
+                let mut row = 0.0;
+                while row < rows {
+                    let mut column = 0.0;
+                    while column < columns {
+                        pages.push(page(
+                            (
+                                region.x0 + column * tile.width,
+                                region.y0 + row * tile.height,
+                            ),
+                            (0.0, 0.0),
+                        ));
+                        column += 1.0;
+                    }
+                    row += 1.0;
+                }
+                pages
+            }
+        }
+    }
+
+    /// Size of the sheet inside the margins, in millimeters.
+    fn printable_size(&self) -> Size {
+        Size::new(
+            self.sheet.width - self.margins.x_value(),
+            self.sheet.height - self.margins.y_value(),
+        )
+    }
+
+    /// Area of the sheet inside the margins, in points from the top left of the sheet.
+    fn printable_area(&self) -> Rect {
+        Rect::from_origin_size(
+            (self.margins.x0, self.margins.y0),
+            self.printable_size().max(Size::ZERO),
+        )
+        .scale_from_origin(POINTS_PER_MM)
+    }
+}
+
+/// Expensive state for writing PDF documents.
+#[derive(Default)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and members don't implement Debug."
+)]
+pub struct Environment {
+    /// Font context.
+    ///
+    /// This contains a font collection that is expensive to reproduce.
+    #[cfg(feature = "text")]
+    pub(crate) font_cx: FontContext,
+    /// Layout context.
+    #[cfg(feature = "text")]
+    pub(crate) layout_cx: LayoutContext<Option<Color>>,
+}
+
+impl Environment {
+    /// Write `region` of a [`RenderLayer`] as a PDF document with [`PageSetup`].
+    ///
+    /// `region` is in the coordinates of the drawing, after the transforms of
+    /// `graphics`. Items that are missing from `graphics` or have nothing to draw are
+    /// skipped. If nothing can be printed, the document has a single blank page.
+    pub fn render_layer_to_pdf(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        region: Rect,
+        setup: &PageSetup,
+    ) -> Vec<u8> {
+        self.write_pages(region, setup, |renderer, options| {
+            draw_items(
+                renderer,
+                graphics,
+                render_layer.indices.iter().copied(),
+                options,
+            );
+        })
+    }
+
+    /// Write `region` of the visible layers of a [`LayerStack`] as a PDF document with
+    /// [`PageSetup`].
+    ///
+    /// Layers are drawn from the lowest `z` to the highest, and layers that are not
+    /// fully opaque are drawn as transparency groups with their opacity.
+    pub fn layer_stack_to_pdf(
+        &mut self,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        region: Rect,
+        setup: &PageSetup,
+    ) -> Vec<u8> {
+        self.write_pages(region, setup, |renderer, options| {
+            draw_layer_stack(renderer, graphics, stack, options);
+        })
+    }
+
+    /// Write a page for each of the [page transforms](PageSetup::page_transforms) of
+    /// `region`, drawing it with `draw`.
+    fn write_pages(
+        &mut self,
+        region: Rect,
+        setup: &PageSetup,
+        mut draw: impl FnMut(&mut dyn Renderer, &DrawOptions),
+    ) -> Vec<u8> {
+        let Some(mm_per_unit) = setup.millimeters_per_unit(region) else {
+            let mut writer = PdfWriter::new(self, setup, 1.0);
+            writer.begin_page(Affine::IDENTITY);
+            writer.end_page();
+            return writer.finish();
+        };
+        let options = DrawOptions {
+            fill_rule: Fill::NonZero,
+            tolerance: TOLERANCE_MM / mm_per_unit,
+            // Markers are sized in millimeters on paper.
+            pixels_per_millimeter: 1.0 / mm_per_unit,
+            dash_units: DashUnits::Local,
+        };
+        let transforms = setup.page_transforms(region);
+        let mut writer = PdfWriter::new(self, setup, mm_per_unit);
+        for base in transforms {
+            writer.begin_page(base);
+            draw(&mut writer, &options);
+            writer.end_page();
+        }
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::String, sync::Arc};
+    use tabulon::{
+        peniko::{
+            Color,
+            kurbo::{Line, Point, Shape, Stroke},
+        },
+        shape::{FatClip, FatPaint, FatShape},
+    };
+
+    /// Get the text of the content streams of a document, decompressed.
+    fn contents(pdf: &[u8]) -> String {
+        let mut out = String::new();
+        let mut rest = pdf;
+        while let Some(start) = find(rest, b">>\nstream\n") {
+            rest = &rest[start + 10..];
+            let end = find(rest, b"\nendstream").expect("Streams should end.");
+            if let Ok(data) = miniz_oxide::inflate::decompress_to_vec_zlib(&rest[..end]) {
+                out.push_str(&String::from_utf8_lossy(&data));
+            }
+            rest = &rest[end..];
+        }
+        out
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|w| w == needle)
+    }
+
+    #[test]
+    fn pages_tile_at_fixed_scale() {
+        // A4 landscape, with 277 by 190 mm inside the margins.
+        let setup = PageSetup {
+            sheet: Size::new(A4.height, A4.width),
+            scale: PrintScale::ratio(2.0, 1.0),
+            ..Default::default()
+        };
+        let region = Rect::new(100.0, 100.0, 1100.0, 600.0);
+        let pages = setup.page_transforms(region);
+        assert_eq!(
+            pages.len(),
+            4,
+            "500 by 250 mm at 1:2 should take two columns of two rows."
+        );
+        let top_left = pages[0] * Point::new(100.0, 100.0);
+        assert!(
+            top_left.distance(Point::new(10.0 * POINTS_PER_MM, 200.0 * POINTS_PER_MM)) < 1e-9,
+            "The region should start at the top left of the printable area: {top_left:?}"
+        );
+        let second = pages[1] * Point::new(100.0 + 277.0 * 2.0, 100.0);
+        assert!(
+            second.distance(Point::new(10.0 * POINTS_PER_MM, 200.0 * POINTS_PER_MM)) < 1e-9,
+            "The second page should continue the first row: {second:?}"
+        );
+
+        let fitted = PageSetup::default().page_transforms(region);
+        let center = fitted[0] * region.center();
+        assert!(
+            fitted.len() == 1
+                && center.distance(Point::new(105.0 * POINTS_PER_MM, 148.5 * POINTS_PER_MM)) < 1e-9,
+            "A fitted region should be centered on a single page: {center:?}"
+        );
+        assert!(
+            setup.page_transforms(Rect::ZERO).is_empty()
+                && PageSetup::default().page_transforms(Rect::ZERO).is_empty(),
+            "An empty region should not be printed."
+        );
+    }
+
+    #[test]
+    fn document_structure_and_line_weights() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(0.5).with_dashes(0.0, [2.0, 1.0]),
+            stroke_paint: Some(Color::from_rgba8(0, 0, 255, 128).into()),
+            fill_paint: None,
+            fill_rule: None,
+        });
+        let clip = graphics.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(Rect::new(0.0, 0.0, 5.0, 5.0).to_path(0.1)),
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                clip,
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+
+        let setup = PageSetup {
+            scale: PrintScale::ratio(2.0, 1.0),
+            line_weights: LineWeights::Millimeters,
+            ..Default::default()
+        };
+        let pdf = Environment::default().render_layer_to_pdf(
+            &graphics,
+            &layer,
+            Rect::new(0.0, 0.0, 10.0, 10.0),
+            &setup,
+        );
+        assert!(
+            pdf.starts_with(b"%PDF-1.7\n") && pdf.ends_with(b"%%EOF\n"),
+            "The document should have a header and trailer."
+        );
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(
+            text.contains("/Type /Pages /Kids [") && text.contains("/Count 1>>"),
+            "The document should have a single page: {text}"
+        );
+        assert!(
+            text.contains("/ExtGState <</G1 <</CA 0.50196>>"),
+            "The alpha of the stroke should be a graphics state: {text}"
+        );
+
+        let content = contents(&pdf);
+        assert!(
+            content.contains("0 0 1 RG /G1 gs 1 w 1 J 1 j 4 M [4 2] 0 d\n0 0 m\n10 0 l\nS Q"),
+            "Line weights and dashes in millimeters should be in units of the drawing: {content}"
+        );
+        assert!(
+            content.contains("W n\nq ")
+                && content.matches('q').count() == content.matches('Q').count(),
+            "The clip should save the graphics state, and be balanced: {content}"
+        );
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_is_written_as_outlines() {
+        use tabulon::{DirectIsometry, peniko::kurbo::Vec2, text::FatText};
+
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatText {
+                transform: Default::default(),
+                paint,
+                text: "Tabulon".into(),
+                style: parley::StyleSet::new(20.0),
+                spans: Vec::new(),
+                alignment: Default::default(),
+                direction: Default::default(),
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.0, Vec2::new(4.0, 4.0)),
+                attachment_point: Default::default(),
+            },
+        );
+
+        let pdf = Environment::default().render_layer_to_pdf(
+            &graphics,
+            &layer,
+            Rect::new(0.0, 0.0, 100.0, 30.0),
+            &PageSetup::default(),
+        );
+        let content = contents(&pdf);
+        assert!(
+            content.contains("0 0 0 rg") && content.contains(" c\n") && content.contains("f Q"),
+            "Text should be filled as curves: {content}"
+        );
+        assert!(
+            !String::from_utf8_lossy(&pdf).contains("/Font"),
+            "No fonts should be embedded."
+        );
+    }
+}
@@ -0,0 +1,637 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Writing the pages of a PDF document through the [`Renderer`] interface.
+
+extern crate alloc;
+use alloc::{collections::btree_map::BTreeMap, format, string::String, vec::Vec};
+use core::fmt::Write;
+
+#[cfg(feature = "text")]
+use tabulon::{
+    ItemHandle,
+    outline::layout_text,
+    peniko::{Color, kurbo::Shape},
+    shape::FatPaint,
+    text::FatText,
+    text_on_path::{FatTextOnPath, MeasuredPath},
+};
+use tabulon::{
+    backend::Renderer,
+    peniko::{
+        Brush, Fill, Gradient, GradientKind, Image, ImageFormat,
+        color::{DynamicColor, Srgb},
+        kurbo::{Affine, BezPath, Cap, Join, PathEl, Point, QuadBez, Rect, Size, Stroke},
+    },
+    uniform_scale,
+};
+
+use crate::{
+    Environment, LineWeights, PageSetup,
+    document::{Document, Matrix, Num, Ref},
+};
+
+/// A clip or layer that was pushed, and is ended by the matching [`Renderer::pop`].
+enum Frame {
+    /// A clip, which saved the graphics state.
+    Clip,
+    /// A layer, drawn into a form of its own, which is drawn into `parent` with
+    /// `opacity` when it ends.
+    Layer { parent: String, opacity: f32 },
+}
+
+/// Writes the pages of a PDF document for what is drawn.
+///
+/// Everything is drawn with absolute transforms from the coordinates of the drawing
+/// to the points of the page, so the transform at the start of each content stream is
+/// always the identity. Resources are shared by all pages and forms.
+pub(crate) struct PdfWriter<'a> {
+    /// The document so far.
+    doc: Document,
+    /// The page tree, written at the end.
+    page_tree: Ref,
+    /// Pages written so far.
+    pages: Vec<Ref>,
+    /// The resources dictionary, written at the end.
+    resources: Ref,
+    /// Graphics states by whether they set the stroke alpha, and the alpha's bits.
+    graphics_states: BTreeMap<(bool, u32), usize>,
+    /// Patterns, named by their index from 1.
+    patterns: Vec<Ref>,
+    /// Images and forms, named by their index from 1.
+    x_objects: Vec<Ref>,
+    /// Names of images already written, by the id of their data and their size.
+    images: BTreeMap<(u64, u32, u32), usize>,
+    /// Content stream of the page or layer being drawn.
+    content: String,
+    /// Clips and layers being drawn.
+    stack: Vec<Frame>,
+    /// Transform from the coordinates of the drawing to the points of the page.
+    base: Affine,
+    /// Size of the sheet, in points.
+    sheet: Size,
+    /// Millimeters on paper per unit of the drawing.
+    mm_per_unit: f64,
+    /// How pages are set up.
+    setup: &'a PageSetup,
+    /// Environment for shaping text.
+    #[cfg(feature = "text")]
+    env: &'a mut Environment,
+}
+
+impl<'a> PdfWriter<'a> {
+    pub(crate) fn new(env: &'a mut Environment, setup: &'a PageSetup, mm_per_unit: f64) -> Self {
+        #[cfg(not(feature = "text"))]
+        let _ = env;
+        let mut doc = Document::new();
+        let page_tree = doc.alloc();
+        let resources = doc.alloc();
+        Self {
+            doc,
+            page_tree,
+            pages: Vec::new(),
+            resources,
+            graphics_states: BTreeMap::new(),
+            patterns: Vec::new(),
+            x_objects: Vec::new(),
+            images: BTreeMap::new(),
+            content: String::new(),
+            stack: Vec::new(),
+            base: Affine::IDENTITY,
+            sheet: setup.sheet * crate::POINTS_PER_MM,
+            mm_per_unit,
+            setup,
+            #[cfg(feature = "text")]
+            env,
+        }
+    }
+
+    /// Start a page, drawing with `base` from the coordinates of the drawing to the
+    /// points of the page, clipped to the printable area of the sheet.
+    pub(crate) fn begin_page(&mut self, base: Affine) {
+        self.base = base;
+        self.content.clear();
+        let printable = self.setup.printable_area();
+        let _ = writeln!(
+            self.content,
+            "q {} {} {} {} re W n",
+            Num(printable.x0),
+            Num(self.sheet.height - printable.y1),
+            Num(printable.width()),
+            Num(printable.height())
+        );
+    }
+
+    /// End the page started by [`begin_page`](Self::begin_page).
+    pub(crate) fn end_page(&mut self) {
+        // Clips and layers are balanced by `draw_items`, but make sure of it.
+        while !self.stack.is_empty() {
+            self.pop();
+        }
+        self.content.push_str("Q\n");
+        let content = self.doc.alloc();
+        self.doc.stream(content, "", self.content.as_bytes());
+        let page = self.doc.alloc();
+        self.doc.object(
+            page,
+            &format!(
+                "<</Type /Page /Parent {} /MediaBox [0 0 {} {}] /Resources {} /Contents {content}>>",
+                self.page_tree,
+                Num(self.sheet.width),
+                Num(self.sheet.height),
+                self.resources
+            ),
+        );
+        self.pages.push(page);
+    }
+
+    /// Write the resources, page tree, and catalog, and get the file.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let mut resources = String::from("<<");
+        if !self.graphics_states.is_empty() {
+            resources.push_str("/ExtGState <<");
+            for ((stroke, bits), n) in &self.graphics_states {
+                let key = if *stroke { "CA" } else { "ca" };
+                let _ = write!(
+                    resources,
+                    "/G{n} <</{key} {}>> ",
+                    Num(f32::from_bits(*bits).into())
+                );
+            }
+            resources.push_str(">> ");
+        }
+        for (key, prefix, refs) in [
+            ("Pattern", 'P', &self.patterns),
+            ("XObject", 'X', &self.x_objects),
+        ] {
+            if refs.is_empty() {
+                continue;
+            }
+            let _ = write!(resources, "/{key} <<");
+            for (i, r) in refs.iter().enumerate() {
+                let _ = write!(resources, "/{prefix}{} {r} ", i + 1);
+            }
+            resources.push_str(">> ");
+        }
+        resources.push_str(">>");
+        self.doc.object(self.resources, &resources);
+
+        let mut kids = String::new();
+        for (i, page) in self.pages.iter().enumerate() {
+            if i > 0 {
+                kids.push(' ');
+            }
+            let _ = write!(kids, "{page}");
+        }
+        self.doc.object(
+            self.page_tree,
+            &format!(
+                "<</Type /Pages /Kids [{kids}] /Count {}>>",
+                self.pages.len()
+            ),
+        );
+        let catalog = self.doc.alloc();
+        self.doc.object(
+            catalog,
+            &format!("<</Type /Catalog /Pages {}>>", self.page_tree),
+        );
+        self.doc.finish(catalog)
+    }
+
+    /// Get the name of a graphics state setting the fill alpha, or the stroke alpha if
+    /// `stroke` is `true`.
+    fn graphics_state(&mut self, stroke: bool, alpha: f32) -> usize {
+        let next = self.graphics_states.len() + 1;
+        *self
+            .graphics_states
+            .entry((stroke, alpha.clamp(0.0, 1.0).to_bits()))
+            .or_insert(next)
+    }
+
+    /// Get the operators for painting with a brush, as the fill color or the stroke
+    /// color if `stroke` is `true`, where `matrix` maps the coordinates of the brush to
+    /// the page.
+    ///
+    /// Gradients are written as shading patterns, without the alpha of their stops, as
+    /// PDF shadings are opaque, and are always padded. Sweep gradients are painted with
+    /// the color of their first stop. Returns `None` if the brush paints nothing, or is
+    /// an image.
+    fn paint_ops(&mut self, brush: &Brush, stroke: bool, matrix: Affine) -> Option<String> {
+        let mut ops = String::new();
+        match brush {
+            Brush::Solid(c) => self.color_ops(&mut ops, (*c).into(), stroke),
+            Brush::Gradient(gradient) => {
+                let first = gradient.stops.first()?;
+                let Some(shading) = shading(gradient) else {
+                    self.color_ops(&mut ops, first.color, stroke);
+                    return Some(ops);
+                };
+                let pattern = self.doc.alloc();
+                self.doc.object(
+                    pattern,
+                    &format!(
+                        "<</Type /Pattern /PatternType 2 /Shading {shading} /Matrix [{}]>>",
+                        Matrix(matrix)
+                    ),
+                );
+                self.patterns.push(pattern);
+                let n = self.patterns.len();
+                let _ = if stroke {
+                    write!(ops, "/Pattern CS /P{n} SCN ")
+                } else {
+                    write!(ops, "/Pattern cs /P{n} scn ")
+                };
+            }
+            Brush::Image(_) => return None,
+        }
+        Some(ops)
+    }
+
+    /// Write the operators for painting with a color, and its alpha if it is not opaque.
+    fn color_ops(&mut self, ops: &mut String, color: DynamicColor, stroke: bool) {
+        let color = color.to_alpha_color::<Srgb>();
+        let [r, g, b, _] = color.components.map(|c| Num(c.into()));
+        let op = if stroke { "RG" } else { "rg" };
+        let _ = write!(ops, "{r} {g} {b} {op} ");
+        if color.components[3] < 1.0 {
+            let n = self.graphics_state(stroke, color.components[3]);
+            let _ = write!(ops, "/G{n} gs ");
+        }
+    }
+
+    /// Get the name of an image, writing it if it hasn't been written yet.
+    ///
+    /// Only [`ImageFormat::Rgba8`] images are written, with their alpha as a soft mask
+    /// if they aren't opaque. Returns `None` if the image can't be written.
+    fn image_name(&mut self, image: &Image) -> Option<usize> {
+        let key = (image.data.id(), image.width, image.height);
+        if let Some(n) = self.images.get(&key) {
+            return Some(*n);
+        }
+        if !matches!(image.format, ImageFormat::Rgba8) || image.width == 0 || image.height == 0 {
+            return None;
+        }
+        let len = image.width as usize * image.height as usize * 4;
+        let pixels = image.data.data().get(..len)?;
+        let rgb: Vec<u8> = pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect();
+        let alpha: Vec<u8> = pixels.chunks_exact(4).map(|p| p[3]).collect();
+        let size = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8",
+            image.width, image.height
+        );
+
+        let id = self.doc.alloc();
+        let mut entries = format!("{size} /ColorSpace /DeviceRGB");
+        if alpha.iter().any(|a| *a < 255) {
+            let mask = self.doc.alloc();
+            self.doc
+                .stream(mask, &format!("{size} /ColorSpace /DeviceGray"), &alpha);
+            let _ = write!(entries, " /SMask {mask}");
+        }
+        self.doc.stream(id, &entries, &rgb);
+        self.x_objects.push(id);
+        let n = self.x_objects.len();
+        self.images.insert(key, n);
+        Some(n)
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill and stroke a path with a paint, as [`draw_items`](tabulon::backend::draw_items)
+    /// does for shapes.
+    fn draw_paint(&mut self, transform: Affine, paint: &FatPaint, path: &BezPath) {
+        if let Some(fill_paint) = &paint.fill_paint {
+            self.fill(
+                transform,
+                paint.fill_rule.unwrap_or(Fill::NonZero),
+                fill_paint,
+                path,
+            );
+        }
+        if let Some(stroke_paint) = &paint.stroke_paint {
+            self.stroke(transform, &paint.stroke, stroke_paint, path);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill glyph outlines, each with its own transform, grouping glyphs of the same
+    /// color into one path.
+    ///
+    /// Glyphs are filled with their own color if they have one, and with `brush` otherwise.
+    fn fill_glyphs<'g>(
+        &mut self,
+        transform: Affine,
+        brush: &Brush,
+        glyphs: impl Iterator<Item = (Option<Color>, Affine, &'g BezPath)>,
+    ) {
+        let fill = |this: &mut Self, color: Option<Color>, outlines: &BezPath| {
+            let brush = color.map_or_else(|| brush.clone(), Brush::Solid);
+            this.fill(transform, Fill::NonZero, &brush, outlines);
+        };
+        let mut run: Option<(Option<Color>, BezPath)> = None;
+        for (color, placement, outline) in glyphs {
+            match &mut run {
+                Some((c, outlines)) if *c == color => {
+                    outlines.extend(outline.iter().map(|el| placement * el));
+                }
+                _ => {
+                    if let Some((c, outlines)) = run.take() {
+                        fill(self, c, &outlines);
+                    }
+                    run = Some((color, placement * outline));
+                }
+            }
+        }
+        if let Some((c, outlines)) = run {
+            fill(self, c, &outlines);
+        }
+    }
+}
+
+impl Renderer for PdfWriter<'_> {
+    fn fill(&mut self, transform: Affine, fill_rule: Fill, brush: &Brush, path: &BezPath) {
+        let matrix = self.base * transform;
+        if path.is_empty() || !invertible(matrix) {
+            return;
+        }
+        let Some(paint) = self.paint_ops(brush, false, matrix) else {
+            return;
+        };
+        let _ = writeln!(self.content, "q {} cm {paint}", Matrix(matrix));
+        path_ops(&mut self.content, path);
+        self.content.push_str(match fill_rule {
+            Fill::NonZero => "f Q\n",
+            Fill::EvenOdd => "f* Q\n",
+        });
+    }
+
+    fn stroke(&mut self, transform: Affine, stroke: &Stroke, brush: &Brush, path: &BezPath) {
+        let matrix = self.base * transform;
+        // Millimeters on paper per unit of the path.
+        let mm_per_unit = self.mm_per_unit * uniform_scale(transform);
+        if path.is_empty() || !invertible(matrix) || mm_per_unit <= 0.0 {
+            return;
+        }
+        let Some(paint) = self.paint_ops(brush, true, matrix) else {
+            return;
+        };
+        let units = match self.setup.line_weights {
+            LineWeights::Scaled => 1.0,
+            LineWeights::Millimeters => 1.0 / mm_per_unit,
+        };
+        let width = (stroke.width * units).max(self.setup.min_line_weight / mm_per_unit);
+        // PDF has a single cap for both ends, so the start cap is used.
+        let cap = match stroke.start_cap {
+            Cap::Butt => 0,
+            Cap::Round => 1,
+            Cap::Square => 2,
+        };
+        let join = match stroke.join {
+            Join::Miter => 0,
+            Join::Round => 1,
+            Join::Bevel => 2,
+        };
+        let _ = write!(
+            self.content,
+            "q {} cm {paint}{} w {cap} J {join} j {} M",
+            Matrix(matrix),
+            Num(width),
+            Num(stroke.miter_limit.max(1.0))
+        );
+        if stroke.dash_pattern.iter().any(|d| *d > 0.0) {
+            self.content.push_str(" [");
+            for (i, dash) in stroke.dash_pattern.iter().enumerate() {
+                if i > 0 {
+                    self.content.push(' ');
+                }
+                let _ = write!(self.content, "{}", Num(dash.max(0.0) * units));
+            }
+            let _ = write!(self.content, "] {} d", Num(stroke.dash_offset * units));
+        }
+        self.content.push('\n');
+        path_ops(&mut self.content, path);
+        self.content.push_str("S Q\n");
+    }
+
+    fn image(&mut self, transform: Affine, image: &Image, opacity: f32) {
+        let (width, height) = (f64::from(image.width), f64::from(image.height));
+        // Images are drawn into the unit square, with their first row at the top.
+        let matrix = self.base * transform * Affine::new([width, 0.0, 0.0, -height, 0.0, height]);
+        let opacity = opacity * image.alpha;
+        if opacity <= 0.0 || !invertible(matrix) {
+            return;
+        }
+        let Some(n) = self.image_name(image) else {
+            return;
+        };
+        self.content.push_str("q ");
+        if opacity < 1.0 {
+            let g = self.graphics_state(false, opacity);
+            let _ = write!(self.content, "/G{g} gs ");
+        }
+        let _ = writeln!(self.content, "{} cm /X{n} Do Q", Matrix(matrix));
+    }
+
+    #[cfg(feature = "text")]
+    fn text(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatText,
+        paint: &FatPaint,
+        background: Option<&FatPaint>,
+    ) {
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        let laid_out = layout_text(&mut self.env.font_cx, &mut self.env.layout_cx, t, true);
+        if let (Some(background), Some(rect)) = (background, t.background_rect(laid_out.size)) {
+            let placement = transform * t.placement_for_size(laid_out.size);
+            self.draw_paint(placement, background, &rect.to_path(0.1));
+        }
+        self.fill_glyphs(
+            transform * laid_out.placement(t),
+            fill_paint,
+            laid_out.positioned_glyphs(),
+        );
+    }
+
+    #[cfg(feature = "text")]
+    fn text_on_path(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatTextOnPath,
+        paint: &FatPaint,
+    ) {
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        let laid_out = layout_text(
+            &mut self.env.font_cx,
+            &mut self.env.layout_cx,
+            &t.line_text(),
+            true,
+        );
+        let measured = MeasuredPath::new(&t.path);
+        self.fill_glyphs(
+            transform,
+            fill_paint,
+            laid_out.glyphs_on_path(&measured, t.offset),
+        );
+    }
+
+    fn push_clip(&mut self, transform: Affine, path: &BezPath) {
+        // The clip is written in the points of the page, so that the transform is not
+        // changed for what is clipped.
+        self.content.push_str("q\n");
+        path_ops(&mut self.content, &(self.base * transform * path.clone()));
+        self.content.push_str("W n\n");
+        self.stack.push(Frame::Clip);
+    }
+
+    fn push_layer(&mut self, opacity: f32, _bounds: Rect) {
+        let parent = core::mem::take(&mut self.content);
+        self.stack.push(Frame::Layer { parent, opacity });
+    }
+
+    fn pop(&mut self) {
+        match self.stack.pop() {
+            Some(Frame::Clip) => self.content.push_str("Q\n"),
+            Some(Frame::Layer { parent, opacity }) => {
+                let content = core::mem::replace(&mut self.content, parent);
+                let form = self.doc.alloc();
+                self.doc.stream(
+                    form,
+                    &format!(
+                        "/Type /XObject /Subtype /Form /BBox [0 0 {} {}] /Group <</S /Transparency>> /Resources {}",
+                        Num(self.sheet.width),
+                        Num(self.sheet.height),
+                        self.resources
+                    ),
+                    content.as_bytes(),
+                );
+                self.x_objects.push(form);
+                let n = self.x_objects.len();
+                let g = self.graphics_state(false, opacity);
+                let _ = writeln!(self.content, "q /G{g} gs /X{n} Do Q");
+            }
+            None => {}
+        }
+    }
+}
+
+/// Whether a transform can be inverted, so that it draws something.
+fn invertible(transform: Affine) -> bool {
+    let det = transform.determinant();
+    det.is_finite() && det != 0.0
+}
+
+/// Write the path construction operators for a path, with quadratic segments raised to
+/// cubic ones, as PDF only has cubic segments.
+fn path_ops(out: &mut String, path: &BezPath) {
+    let p = |p: Point| (Num(p.x), Num(p.y));
+    let mut start = Point::ZERO;
+    let mut last = Point::ZERO;
+    for el in path.iter() {
+        match el {
+            PathEl::MoveTo(p0) => {
+                let (x, y) = p(p0);
+                let _ = writeln!(out, "{x} {y} m");
+                (start, last) = (p0, p0);
+            }
+            PathEl::LineTo(p1) => {
+                let (x, y) = p(p1);
+                let _ = writeln!(out, "{x} {y} l");
+                last = p1;
+            }
+            PathEl::QuadTo(p1, p2) => {
+                let c = QuadBez::new(last, p1, p2).raise();
+                let ((x1, y1), (x2, y2), (x, y)) = (p(c.p1), p(c.p2), p(c.p3));
+                let _ = writeln!(out, "{x1} {y1} {x2} {y2} {x} {y} c");
+                last = p2;
+            }
+            PathEl::CurveTo(p1, p2, p3) => {
+                let ((x1, y1), (x2, y2), (x, y)) = (p(p1), p(p2), p(p3));
+                let _ = writeln!(out, "{x1} {y1} {x2} {y2} {x} {y} c");
+                last = p3;
+            }
+            PathEl::ClosePath => {
+                out.push_str("h\n");
+                last = start;
+            }
+        }
+    }
+}
+
+/// Write a gradient as a shading dictionary, or get `None` if it can't be one.
+///
+/// Gradients with fewer than two stops, or with stops that don't span a range, are
+/// painted as a single color by the caller.
+fn shading(gradient: &Gradient) -> Option<String> {
+    let stops = &gradient.stops;
+    let (first, last) = (stops.first()?, stops.last()?);
+    if stops.len() < 2 || first.offset >= last.offset {
+        return None;
+    }
+    let (kind, coords) = match gradient.kind {
+        GradientKind::Linear { start, end } => (
+            2,
+            format!(
+                "{} {} {} {}",
+                Num(start.x),
+                Num(start.y),
+                Num(end.x),
+                Num(end.y)
+            ),
+        ),
+        GradientKind::Radial {
+            start_center,
+            start_radius,
+            end_center,
+            end_radius,
+        } => (
+            3,
+            format!(
+                "{} {} {} {} {} {}",
+                Num(start_center.x),
+                Num(start_center.y),
+                Num(start_radius.into()),
+                Num(end_center.x),
+                Num(end_center.y),
+                Num(end_radius.into())
+            ),
+        ),
+        GradientKind::Sweep { .. } => return None,
+    };
+    let rgb = |c: DynamicColor| {
+        let [r, g, b, _] = c.to_alpha_color::<Srgb>().components.map(|c| Num(c.into()));
+        format!("{r} {g} {b}")
+    };
+    // Each pair of adjacent stops is interpolated by a function of its own, and the
+    // functions are stitched together over the range of the stops.
+    let mut functions = String::new();
+    let mut bounds = String::new();
+    let mut encode = String::new();
+    for (i, pair) in stops.windows(2).enumerate() {
+        let _ = write!(
+            functions,
+            "<</FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1>> ",
+            rgb(pair[0].color),
+            rgb(pair[1].color)
+        );
+        if i > 0 {
+            let _ = write!(bounds, "{} ", Num(pair[0].offset.into()));
+        }
+        encode.push_str("0 1 ");
+    }
+    let domain = format!("{} {}", Num(first.offset.into()), Num(last.offset.into()));
+    Some(format!(
+        "<</ShadingType {kind} /ColorSpace /DeviceRGB /Coords [{coords}] /Domain [{domain}] /Extend [true true] /Function <</FunctionType 3 /Domain [{domain}] /Functions [{}] /Bounds [{}] /Encode [{}]>>>>",
+        functions.trim_end(),
+        bounds.trim_end(),
+        encode.trim_end()
+    ))
+}
@@ -0,0 +1,213 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Conversion of Peniko and Kurbo types to their tiny-skia equivalents.
+//!
+//! tiny-skia works in `f32`, so coordinates are narrowed as they are converted.
+
+#![allow(
+    clippy::cast_possible_truncation,
+    reason = "tiny-skia works in f32, and drawings are placed near the origin by their views."
+)]
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use tabulon::peniko::{
+    Brush, Extend, Fill, GradientKind, Image, ImageFormat,
+    color::{DynamicColor, Srgb},
+    kurbo::{self, Affine, BezPath, Cap, Join, PathEl},
+};
+use tiny_skia::{
+    Color, ColorU8, FillRule, FilterQuality, GradientStop, LineCap, LineJoin, LinearGradient, Mask,
+    Paint, Path, PathBuilder, Pattern, Pixmap, Point, RadialGradient, Shader, SpreadMode, Stroke,
+    StrokeDash, Transform,
+};
+
+/// Convert an [`Affine`] to a [`Transform`].
+pub(crate) fn transform(affine: Affine) -> Transform {
+    let [sx, ky, kx, sy, tx, ty] = affine.as_coeffs();
+    Transform::from_row(
+        sx as f32, ky as f32, kx as f32, sy as f32, tx as f32, ty as f32,
+    )
+}
+
+/// Convert path elements to a [`Path`], or `None` if they don't make a path with an
+/// area or a length.
+pub(crate) fn path(elements: impl IntoIterator<Item = PathEl>) -> Option<Path> {
+    let mut builder = PathBuilder::new();
+    for el in elements {
+        match el {
+            PathEl::MoveTo(p) => builder.move_to(p.x as f32, p.y as f32),
+            PathEl::LineTo(p) => builder.line_to(p.x as f32, p.y as f32),
+            PathEl::QuadTo(p1, p2) => {
+                builder.quad_to(p1.x as f32, p1.y as f32, p2.x as f32, p2.y as f32);
+            }
+            PathEl::CurveTo(p1, p2, p3) => builder.cubic_to(
+                p1.x as f32,
+                p1.y as f32,
+                p2.x as f32,
+                p2.y as f32,
+                p3.x as f32,
+                p3.y as f32,
+            ),
+            PathEl::ClosePath => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+/// Convert a [`Fill`] rule to a [`FillRule`].
+pub(crate) fn fill_rule(fill: Fill) -> FillRule {
+    match fill {
+        Fill::NonZero => FillRule::Winding,
+        Fill::EvenOdd => FillRule::EvenOdd,
+    }
+}
+
+/// Convert a Kurbo [`Stroke`](kurbo::Stroke) to a tiny-skia [`Stroke`].
+///
+/// tiny-skia has a single cap for both ends, so the start cap is used. Dash patterns
+/// with an odd number of lengths are repeated, as in SVG, since tiny-skia needs pairs.
+pub(crate) fn stroke(stroke: &kurbo::Stroke) -> Stroke {
+    let mut dashes: Vec<f32> = stroke.dash_pattern.iter().map(|d| *d as f32).collect();
+    if dashes.len() % 2 == 1 {
+        dashes.extend_from_within(..);
+    }
+    Stroke {
+        width: stroke.width as f32,
+        miter_limit: stroke.miter_limit as f32,
+        line_cap: match stroke.start_cap {
+            Cap::Butt => LineCap::Butt,
+            Cap::Square => LineCap::Square,
+            Cap::Round => LineCap::Round,
+        },
+        line_join: match stroke.join {
+            Join::Bevel => LineJoin::Bevel,
+            Join::Miter => LineJoin::Miter,
+            Join::Round => LineJoin::Round,
+        },
+        dash: StrokeDash::new(dashes, stroke.dash_offset as f32),
+    }
+}
+
+/// Convert a color in any color space to a tiny-skia [`Color`].
+pub(crate) fn color(color: impl Into<DynamicColor>) -> Color {
+    let [r, g, b, a] = color
+        .into()
+        .to_alpha_color::<Srgb>()
+        .to_rgba8()
+        .to_u8_array();
+    Color::from_rgba8(r, g, b, a)
+}
+
+/// Convert an [`Extend`] mode to a [`SpreadMode`].
+fn spread_mode(extend: Extend) -> SpreadMode {
+    match extend {
+        Extend::Pad => SpreadMode::Pad,
+        Extend::Repeat => SpreadMode::Repeat,
+        Extend::Reflect => SpreadMode::Reflect,
+    }
+}
+
+/// Convert a [`Brush`] to a [`Paint`], or `None` if it draws nothing.
+///
+/// Image brushes are converted to a [`Pixmap`] kept in `image`, which the paint borrows.
+/// tiny-skia has no sweep gradients, so they are drawn with the color of their first
+/// stop, and radial gradients start from a point rather than a circle.
+pub(crate) fn paint<'a>(
+    brush: &Brush,
+    image: &'a mut Option<Pixmap>,
+    anti_alias: bool,
+) -> Option<Paint<'a>> {
+    let shader = match brush {
+        Brush::Solid(c) => Shader::SolidColor(color(*c)),
+        Brush::Gradient(gradient) => {
+            let stops: Vec<GradientStop> = gradient
+                .stops
+                .iter()
+                .map(|s| GradientStop::new(s.offset, color(s.color)))
+                .collect();
+            let mode = spread_mode(gradient.extend);
+            let point = |p: kurbo::Point| Point::from_xy(p.x as f32, p.y as f32);
+            match gradient.kind {
+                GradientKind::Linear { start, end } => LinearGradient::new(
+                    point(start),
+                    point(end),
+                    stops,
+                    mode,
+                    Transform::identity(),
+                )?,
+                GradientKind::Radial {
+                    start_center,
+                    end_center,
+                    end_radius,
+                    ..
+                } => RadialGradient::new(
+                    point(start_center),
+                    point(end_center),
+                    end_radius,
+                    stops,
+                    mode,
+                    Transform::identity(),
+                )?,
+                GradientKind::Sweep { .. } => {
+                    Shader::SolidColor(color(gradient.stops.first()?.color))
+                }
+            }
+        }
+        Brush::Image(i) => {
+            let pixmap = image.insert(image_pixmap(i)?);
+            Pattern::new(
+                pixmap.as_ref(),
+                spread_mode(i.x_extend),
+                FilterQuality::Bilinear,
+                i.alpha,
+                Transform::identity(),
+            )
+        }
+    };
+    Some(Paint {
+        shader,
+        anti_alias,
+        ..Default::default()
+    })
+}
+
+/// Convert an [`Image`] to a [`Pixmap`], premultiplying its colors.
+///
+/// Returns `None` for empty images, formats other than [`ImageFormat::Rgba8`], and
+/// images whose data is too short.
+pub(crate) fn image_pixmap(image: &Image) -> Option<Pixmap> {
+    if !matches!(image.format, ImageFormat::Rgba8) {
+        return None;
+    }
+    let mut pixmap = Pixmap::new(image.width, image.height)?;
+    let data = image.data.data();
+    if data.len() < pixmap.pixels().len() * 4 {
+        return None;
+    }
+    for (pixel, rgba) in pixmap.pixels_mut().iter_mut().zip(data.chunks_exact(4)) {
+        *pixel = ColorU8::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3]).premultiply();
+    }
+    Some(pixmap)
+}
+
+/// Make a mask of `width` by `height` pixels covering a clip path.
+pub(crate) fn clip_mask(
+    width: u32,
+    height: u32,
+    clip: &BezPath,
+    transform: Affine,
+    anti_alias: bool,
+) -> Option<Mask> {
+    let mut mask = Mask::new(width, height)?;
+    let path = path(clip.iter())?;
+    mask.fill_path(
+        &path,
+        FillRule::Winding,
+        anti_alias,
+        self::transform(transform),
+    );
+    Some(mask)
+}
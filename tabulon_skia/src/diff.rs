@@ -0,0 +1,368 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Visual differences between revisions of a drawing.
+//!
+//! Both revisions are rendered with the same view, fitted to the extents they share,
+//! so that their images line up pixel for pixel. The images can then be shown side by
+//! side, or blended into a single image with what was removed, added, and modified
+//! picked out in colors of their own, and what is unchanged faded.
+
+use tabulon::{
+    GraphicsBag,
+    peniko::{
+        Color,
+        kurbo::{Affine, Rect, Vec2},
+    },
+    render_layer::RenderLayer,
+};
+use tiny_skia::{Pixmap, PixmapPaint, PremultipliedColorU8, Transform};
+
+use crate::{Environment, RenderOptions, convert};
+
+/// A revision of a drawing to compare, as the items of a [`RenderLayer`].
+#[derive(Clone, Copy)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, as it only borrows the drawing."
+)]
+pub struct Revision<'a> {
+    /// Graphics of the revision.
+    pub graphics: &'a GraphicsBag,
+    /// Items of the revision to draw.
+    pub render_layer: &'a RenderLayer,
+}
+
+/// Options for [`Environment::visual_diff`].
+#[derive(Clone, Copy, Debug)]
+pub struct DiffOptions {
+    /// Size of the image of each revision, in pixels.
+    pub size: (u32, u32),
+    /// Region of the drawing to compare, or `None` for the union of the bounds of both
+    /// revisions.
+    pub extents: Option<Rect>,
+    /// Margin around the extents, in pixels.
+    pub margin: f64,
+    /// Background that both revisions are drawn on.
+    pub background: Color,
+    /// Greatest difference in any channel between pixels that are still the same.
+    ///
+    /// This absorbs small differences in antialiasing, such as of text that was laid
+    /// out again.
+    pub threshold: u8,
+    /// Color of what is only drawn in the earlier revision.
+    pub removed: Color,
+    /// Color of what is only drawn in the later revision.
+    pub added: Color,
+    /// Color of what is drawn in both revisions, but differently.
+    pub modified: Color,
+    /// Opacity over the background of what is the same in both revisions.
+    pub unchanged_opacity: f32,
+    /// Options for rendering each revision.
+    pub render: RenderOptions,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            size: (1024, 768),
+            extents: None,
+            margin: 16.0,
+            background: Color::WHITE,
+            threshold: 8,
+            removed: Color::from_rgb8(220, 0, 0),
+            added: Color::from_rgb8(0, 160, 0),
+            modified: Color::from_rgb8(0, 64, 224),
+            unchanged_opacity: 0.25,
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+/// Images of two revisions of a drawing at matching extents, and their difference.
+#[derive(Debug, Clone)]
+pub struct VisualDiff {
+    /// View from the coordinates of the drawing to the pixels of the images.
+    pub view: Affine,
+    /// Image of the earlier revision.
+    pub before: Pixmap,
+    /// Image of the later revision.
+    pub after: Pixmap,
+    /// The revisions blended, with what changed in the colors of [`DiffOptions`].
+    pub blended: Pixmap,
+    /// Number of pixels that changed.
+    pub changed_pixels: usize,
+    /// Bounds of what changed, in the coordinates of the drawing, or `None` if nothing
+    /// did.
+    ///
+    /// This is where to zoom to review the changes.
+    pub changed_bounds: Option<Rect>,
+}
+
+impl VisualDiff {
+    /// Put the images of the revisions side by side, the earlier on the left, with a
+    /// transparent gap of `gap` pixels between them.
+    ///
+    /// Returns `None` if the combined image would be too wide.
+    pub fn side_by_side(&self, gap: u32) -> Option<Pixmap> {
+        let width = self.before.width();
+        let mut pixmap = Pixmap::new(
+            width.checked_mul(2)?.checked_add(gap)?,
+            self.before.height(),
+        )?;
+        let x = i32::try_from(width.checked_add(gap)?).ok()?;
+        for (x, image) in [(0, &self.before), (x, &self.after)] {
+            pixmap.draw_pixmap(
+                x,
+                0,
+                image.as_ref(),
+                &PixmapPaint::default(),
+                Transform::identity(),
+                None,
+            );
+        }
+        Some(pixmap)
+    }
+}
+
+impl Environment {
+    /// Render two revisions of a drawing at matching extents, and find where they differ.
+    ///
+    /// Both revisions are drawn on the background with the same view, which fits the
+    /// [extents](DiffOptions::extents) into the images, centered. Pixels are compared
+    /// after drawing, so only changes that can be seen are found, including changes of
+    /// paint or draw order, whatever the items changed are.
+    ///
+    /// Returns `None` if either dimension of the size is zero, or there are no extents
+    /// to compare, because both revisions are empty.
+    pub fn visual_diff(
+        &mut self,
+        before: Revision<'_>,
+        after: Revision<'_>,
+        options: &DiffOptions,
+    ) -> Option<VisualDiff> {
+        let extents = options.extents.or_else(|| {
+            let a = before.graphics.layer_bounds(before.render_layer);
+            let b = after.graphics.layer_bounds(after.render_layer);
+            a.into_iter().chain(b).reduce(|a, b| a.union(b))
+        })?;
+        let view = fit_view(extents, options.size, options.margin);
+
+        let mut render = |revision: Revision<'_>| {
+            let mut pixmap = Pixmap::new(options.size.0, options.size.1)?;
+            pixmap.fill(convert::color(options.background));
+            self.draw_render_layer_with_options(
+                &mut pixmap.as_mut(),
+                revision.graphics,
+                revision.render_layer,
+                view,
+                &options.render,
+            );
+            Some(pixmap)
+        };
+        let before = render(before)?;
+        let after = render(after)?;
+
+        let mut blended = Pixmap::new(options.size.0, options.size.1)?;
+        let background = rgba8(options.background);
+        let unchanged = options.unchanged_opacity.clamp(0.0, 1.0);
+        let (removed, added, modified) = (
+            opaque(rgba8(options.removed)),
+            opaque(rgba8(options.added)),
+            opaque(rgba8(options.modified)),
+        );
+        let same = |a: [u8; 4], b: [u8; 4]| {
+            a.iter()
+                .zip(b)
+                .all(|(a, b)| a.abs_diff(b) <= options.threshold)
+        };
+        let width = before.width() as usize;
+        let mut changed_pixels = 0;
+        let mut changed: Option<Rect> = None;
+        for (i, ((b, a), out)) in before
+            .pixels()
+            .iter()
+            .zip(after.pixels())
+            .zip(blended.pixels_mut())
+            .enumerate()
+        {
+            let (b, a) = (channels(*b), channels(*a));
+            *out = if same(b, a) {
+                // Fade what is unchanged toward the background.
+                let mut c = [0; 4];
+                for ((c, a), bg) in c.iter_mut().zip(a).zip(background) {
+                    *c = mix(bg, a, unchanged);
+                }
+                opaque(c)
+            } else {
+                changed_pixels += 1;
+                #[expect(
+                    clippy::cast_precision_loss,
+                    reason = "Pixel coordinates are far below 2^52."
+                )]
+                let pixel =
+                    Rect::from_origin_size(((i % width) as f64, (i / width) as f64), (1.0, 1.0));
+                changed = Some(changed.map_or(pixel, |r| r.union(pixel)));
+                if same(b, background) {
+                    added
+                } else if same(a, background) {
+                    removed
+                } else {
+                    modified
+                }
+            };
+        }
+
+        Some(VisualDiff {
+            view,
+            before,
+            after,
+            blended,
+            changed_pixels,
+            changed_bounds: changed.map(|r| view.inverse().transform_rect_bbox(r)),
+        })
+    }
+}
+
+/// Get the view fitting `extents` into an image of `size` pixels with `margin` pixels
+/// around it, centered.
+fn fit_view(extents: Rect, (width, height): (u32, u32), margin: f64) -> Affine {
+    let (width, height) = (f64::from(width), f64::from(height));
+    let available = Vec2::new(
+        (width - 2.0 * margin).max(1.0),
+        (height - 2.0 * margin).max(1.0),
+    );
+    let size = extents.size();
+    // Extents without width or height are fitted by the other dimension alone.
+    let scale = [available.x / size.width, available.y / size.height]
+        .into_iter()
+        .filter(|s| s.is_finite())
+        .reduce(f64::min)
+        .unwrap_or(1.0);
+    Affine::translate((width * 0.5, height * 0.5))
+        * Affine::scale(scale)
+        * Affine::translate(-extents.center().to_vec2())
+}
+
+/// Get the channels of an opaque pixel, which are the same premultiplied or not.
+fn channels(p: PremultipliedColorU8) -> [u8; 4] {
+    [p.red(), p.green(), p.blue(), p.alpha()]
+}
+
+/// Get a color as 8 bit sRGB channels.
+fn rgba8(color: Color) -> [u8; 4] {
+    color.to_rgba8().to_u8_array()
+}
+
+/// Make an opaque pixel.
+fn opaque([r, g, b, _]: [u8; 4]) -> PremultipliedColorU8 {
+    PremultipliedColorU8::from_rgba(r, g, b, 255).expect("Opaque colors are premultiplied.")
+}
+
+/// Mix from `a` to `b` by `t`.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "The mix of two channels is in range."
+)]
+fn mix(a: u8, b: u8, t: f32) -> u8 {
+    (f32::from(a) + (f32::from(b) - f32::from(a)) * t).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use alloc::sync::Arc;
+    use tabulon::{
+        peniko::kurbo::Point,
+        shape::{FatPaint, FatShape},
+    };
+
+    const ALIASED: RenderOptions = RenderOptions {
+        fill_rule: tabulon::peniko::Fill::NonZero,
+        text_enabled: true,
+        anti_alias: false,
+        pixels_per_millimeter: 96.0 / 25.4,
+        dash_units: tabulon::shape::DashUnits::Local,
+    };
+
+    /// Make a revision with a black square at each of `xs`.
+    fn squares(xs: &[f64]) -> (GraphicsBag, RenderLayer) {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        for x in xs {
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Rect::new(*x, 0.0, x + 10.0, 10.0).into()),
+                    ..Default::default()
+                },
+            );
+        }
+        (graphics, layer)
+    }
+
+    #[test]
+    fn changes_are_colored_at_matching_extents() {
+        let (before_graphics, before_layer) = squares(&[0.0, 20.0]);
+        let (after_graphics, after_layer) = squares(&[0.0, 40.0]);
+        let options = DiffOptions {
+            size: (50, 10),
+            margin: 0.0,
+            render: ALIASED,
+            ..Default::default()
+        };
+        let diff = Environment::default()
+            .visual_diff(
+                Revision {
+                    graphics: &before_graphics,
+                    render_layer: &before_layer,
+                },
+                Revision {
+                    graphics: &after_graphics,
+                    render_layer: &after_layer,
+                },
+                &options,
+            )
+            .expect("The revisions should be compared.");
+
+        assert_eq!(
+            diff.view * Point::new(50.0, 10.0),
+            Point::new(50.0, 10.0),
+            "The view should fit the union of the bounds of both revisions."
+        );
+        let pixel = |x: usize, y: usize| channels(diff.blended.pixels()[y * 50 + x]);
+        assert_eq!(
+            (pixel(25, 5), pixel(45, 5)),
+            (rgba8(options.removed), rgba8(options.added)),
+            "The removed square should be red, and the added one green."
+        );
+        assert_eq!(
+            (pixel(5, 5), pixel(15, 5)),
+            ([191, 191, 191, 255], [255, 255, 255, 255]),
+            "What is unchanged should be faded, and the background kept."
+        );
+        assert_eq!(
+            (diff.changed_pixels, diff.changed_bounds),
+            (200, Some(Rect::new(20.0, 0.0, 50.0, 10.0))),
+            "The changes should be counted, and bounded in drawing coordinates."
+        );
+
+        let side_by_side = diff.side_by_side(4).unwrap();
+        assert_eq!(
+            (
+                side_by_side.width(),
+                channels(side_by_side.pixels()[25]),
+                channels(side_by_side.pixels()[54 + 25]),
+            ),
+            (104, [0, 0, 0, 255], [255, 255, 255, 255]),
+            "The earlier revision should be on the left, and the later on the right."
+        );
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! tiny-skia rendering for Tabulon.
+//!
+//! This rasterizes [`RenderLayer`]s and [`LayerStack`]s on the CPU with [tiny-skia],
+//! for headless servers and CI, where there is no GPU, or where the weight of a GPU
+//! renderer isn't wanted. Items are drawn through the [`Renderer`](backend::Renderer)
+//! interface of `tabulon`, like the export backends draw them, and text is shaped with
+//! Parley and filled as glyph outlines from [`tabulon::outline`].
+//!
+//! [`Environment::visual_diff`] renders two revisions of a drawing at matching extents,
+//! for reviewing changes side by side or in a single image with the changes colored.
+//!
+//! ## Features
+//!
+//! - `std` (enabled by default): Use the standard library.
+//! - `libm`: Use floating point implementations from libm.
+//! - `text` (enabled by default): Shape and draw text items with Parley. Without it,
+//!   Parley is not compiled, and only shapes, images, and markers are drawn.
+//!
+//! [tiny-skia]: tiny_skia
+
+use tabulon::{
+    GraphicsBag, ItemHandle, backend,
+    layer_stack::LayerStack,
+    peniko::{Fill, kurbo::Affine},
+    render_layer::RenderLayer,
+    shape::DashUnits,
+};
+use tiny_skia::{Pixmap, PixmapMut};
+
+#[cfg(feature = "text")]
+use {
+    parley::{FontContext, LayoutContext},
+    tabulon::peniko::Color,
+};
+
+use renderer::PixmapRenderer;
+
+mod convert;
+
+mod diff;
+pub use diff::{DiffOptions, Revision, VisualDiff};
+
+mod renderer;
+
+/// Tolerance for converting shapes to paths, in device pixels.
+const SHAPE_TOLERANCE: f64 = 0.1;
+
+/// Options for drawing a [`RenderLayer`] into a pixmap.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Fill rule for filling shapes whose paints do not have one.
+    pub fill_rule: Fill,
+    /// Whether to draw text items.
+    pub text_enabled: bool,
+    /// Whether to antialias edges.
+    ///
+    /// Without antialiasing, each pixel is either covered or not, which suits exact
+    /// comparisons of images, such as in tests.
+    pub anti_alias: bool,
+    /// Pixel density of the output, for sizing [markers](tabulon::marker::FatMarker)
+    /// in physical units.
+    pub pixels_per_millimeter: f64,
+    /// Units of the dash patterns of strokes.
+    pub dash_units: DashUnits,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            fill_rule: Fill::NonZero,
+            text_enabled: true,
+            anti_alias: true,
+            // The CSS reference density of 96 pixels per inch.
+            pixels_per_millimeter: 96.0 / 25.4,
+            dash_units: DashUnits::Local,
+        }
+    }
+}
+
+/// Expensive state for rendering.
+#[derive(Default)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and members don't implement Debug."
+)]
+pub struct Environment {
+    /// Font context.
+    ///
+    /// This contains a font collection that is expensive to reproduce.
+    #[cfg(feature = "text")]
+    pub(crate) font_cx: FontContext,
+    /// Layout context.
+    #[cfg(feature = "text")]
+    pub(crate) layout_cx: LayoutContext<Option<Color>>,
+}
+
+impl Environment {
+    /// Draw a [`RenderLayer`] into a pixmap, with `view` applied on top of the
+    /// transforms of `graphics`.
+    ///
+    /// Returns the number of items drawn.
+    pub fn draw_render_layer(
+        &mut self,
+        pixmap: &mut PixmapMut<'_>,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        view: Affine,
+    ) -> usize {
+        self.draw_render_layer_with_options(
+            pixmap,
+            graphics,
+            render_layer,
+            view,
+            &RenderOptions::default(),
+        )
+    }
+
+    /// Draw a [`RenderLayer`] into a pixmap with [`RenderOptions`], with `view` applied
+    /// on top of the transforms of `graphics`.
+    ///
+    /// Returns the number of items drawn.
+    pub fn draw_render_layer_with_options(
+        &mut self,
+        pixmap: &mut PixmapMut<'_>,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        view: Affine,
+        options: &RenderOptions,
+    ) -> usize {
+        self.draw_items(
+            pixmap,
+            graphics,
+            render_layer.indices.iter().copied(),
+            view,
+            options,
+        )
+    }
+
+    /// Draw [`GraphicsItem`](tabulon::GraphicsItem)s into a pixmap with
+    /// [`RenderOptions`], in iteration order.
+    ///
+    /// Items that are missing from `graphics`, have nothing to draw, or are text when
+    /// text is disabled are skipped.
+    ///
+    /// Returns the number of items drawn.
+    pub fn draw_items(
+        &mut self,
+        pixmap: &mut PixmapMut<'_>,
+        graphics: &GraphicsBag,
+        items: impl IntoIterator<Item = ItemHandle>,
+        view: Affine,
+        options: &RenderOptions,
+    ) -> usize {
+        let draw_options = PixmapRenderer::draw_options(view, options);
+        let mut renderer = PixmapRenderer::new(self, pixmap, view, options);
+        backend::draw_items(&mut renderer, graphics, items, &draw_options);
+        renderer.finish()
+    }
+
+    /// Draw the visible layers of a [`LayerStack`] into a pixmap, with `view` applied on
+    /// top of the transforms of `graphics`.
+    ///
+    /// Returns the number of items drawn in all of the layers together.
+    pub fn draw_layer_stack(
+        &mut self,
+        pixmap: &mut PixmapMut<'_>,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        view: Affine,
+    ) -> usize {
+        self.draw_layer_stack_with_options(pixmap, graphics, stack, view, &RenderOptions::default())
+    }
+
+    /// Draw the visible layers of a [`LayerStack`] into a pixmap with [`RenderOptions`],
+    /// with `view` applied on top of the transforms of `graphics`.
+    ///
+    /// Layers are drawn from the lowest `z` to the highest, and layers that are not fully
+    /// opaque are drawn into a pixmap of their own first, and then composited with their
+    /// opacity, so overlapping items within a layer do not show through each other.
+    ///
+    /// Returns the number of items drawn in all of the layers together.
+    pub fn draw_layer_stack_with_options(
+        &mut self,
+        pixmap: &mut PixmapMut<'_>,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        view: Affine,
+        options: &RenderOptions,
+    ) -> usize {
+        let draw_options = PixmapRenderer::draw_options(view, options);
+        let mut renderer = PixmapRenderer::new(self, pixmap, view, options);
+        backend::draw_layer_stack(&mut renderer, graphics, stack, &draw_options);
+        renderer.finish()
+    }
+
+    /// Render a [`RenderLayer`] to a new pixmap of `size` pixels on a transparent
+    /// background, with `view` applied on top of the transforms of `graphics`.
+    ///
+    /// This is the CPU counterpart of rendering to a texture with `tabulon_vello`, such as
+    /// for thumbnails and snapshot tests. Pixmaps are premultiplied RGBA.
+    ///
+    /// Returns `None` if either dimension of `size` is zero.
+    pub fn render_layer_to_pixmap(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        (width, height): (u32, u32),
+        view: Affine,
+        options: &RenderOptions,
+    ) -> Option<Pixmap> {
+        let mut pixmap = Pixmap::new(width, height)?;
+        self.draw_render_layer_with_options(
+            &mut pixmap.as_mut(),
+            graphics,
+            render_layer,
+            view,
+            options,
+        );
+        Some(pixmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use alloc::sync::Arc;
+    use tabulon::{
+        layer_stack::StackedLayer,
+        peniko::{
+            Color,
+            kurbo::{Rect, Shape},
+        },
+        shape::{FatClip, FatPaint, FatShape},
+    };
+
+    /// Options that draw whole pixels, for exact comparisons.
+    const ALIASED: RenderOptions = RenderOptions {
+        fill_rule: Fill::NonZero,
+        text_enabled: true,
+        anti_alias: false,
+        pixels_per_millimeter: 96.0 / 25.4,
+        dash_units: DashUnits::Local,
+    };
+
+    fn alpha(pixmap: &Pixmap, x: u32, y: u32) -> u8 {
+        pixmap.pixel(x, y).unwrap().alpha()
+    }
+
+    #[test]
+    fn shapes_are_filled_and_clipped() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let clip = graphics.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(Rect::new(20.0, 0.0, 25.0, 10.0).to_path(0.1)),
+        });
+        let mut layer = RenderLayer::default();
+        for (x, clip) in [(0.0, Default::default()), (20.0, clip)] {
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Rect::new(x, 0.0, x + 10.0, 10.0).into()),
+                    clip,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let pixmap = Environment::default()
+            .render_layer_to_pixmap(&graphics, &layer, (64, 32), Affine::scale(2.0), &ALIASED)
+            .unwrap();
+        assert_eq!(
+            (alpha(&pixmap, 19, 10), alpha(&pixmap, 21, 10)),
+            (255, 0),
+            "The view should scale the square to 20 pixels."
+        );
+        assert_eq!(
+            (alpha(&pixmap, 49, 10), alpha(&pixmap, 51, 10)),
+            (255, 0),
+            "The clip should cut the second square in half."
+        );
+    }
+
+    #[test]
+    fn translucent_layers_are_grouped() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        for x in [0.0, 5.0] {
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Rect::new(x, 0.0, x + 10.0, 10.0).into()),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut stack = LayerStack::default();
+        stack.push(StackedLayer {
+            layer,
+            opacity: 0.5,
+            ..Default::default()
+        });
+
+        let mut pixmap = Pixmap::new(16, 16).unwrap();
+        let drawn = Environment::default().draw_layer_stack_with_options(
+            &mut pixmap.as_mut(),
+            &graphics,
+            &stack,
+            Affine::IDENTITY,
+            &ALIASED,
+        );
+        assert_eq!(drawn, 2, "Both squares should be drawn.");
+        assert_eq!(
+            (alpha(&pixmap, 2, 5), alpha(&pixmap, 7, 5)),
+            (128, 128),
+            "Overlapping items should not show through each other."
+        );
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_is_drawn_as_outlines() {
+        use tabulon::{DirectIsometry, peniko::kurbo::Vec2, text::FatText};
+
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatText {
+                transform: Default::default(),
+                paint,
+                text: "Hi".into(),
+                style: parley::StyleSet::new(20.0),
+                spans: Vec::new(),
+                alignment: Default::default(),
+                direction: Default::default(),
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.0, Vec2::new(4.0, 4.0)),
+                attachment_point: Default::default(),
+            },
+        );
+
+        let mut env = Environment::default();
+        let pixmap = env
+            .render_layer_to_pixmap(
+                &graphics,
+                &layer,
+                (64, 32),
+                Affine::IDENTITY,
+                &RenderOptions::default(),
+            )
+            .unwrap();
+        let inked = pixmap.pixels().iter().filter(|p| p.alpha() > 0).count();
+        assert!(inked > 20, "Glyphs should be filled, got {inked} pixels.");
+
+        let hidden = env
+            .render_layer_to_pixmap(
+                &graphics,
+                &layer,
+                (64, 32),
+                Affine::IDENTITY,
+                &RenderOptions {
+                    text_enabled: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(
+            hidden.pixels().iter().all(|p| p.alpha() == 0),
+            "Disabled text should not be drawn."
+        );
+    }
+}
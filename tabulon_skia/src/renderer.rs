@@ -0,0 +1,348 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Drawing into a pixmap through the [`Renderer`] interface.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use tabulon::{
+    ItemHandle,
+    backend::{DrawOptions, Renderer},
+    peniko::{
+        Brush, Fill, Image,
+        kurbo::{Affine, BezPath, Rect, Stroke},
+    },
+    shape::DashUnits,
+    uniform_scale,
+};
+use tiny_skia::{Mask, Pixmap, PixmapMut, PixmapPaint, Transform};
+
+#[cfg(feature = "text")]
+use tabulon::{
+    outline::layout_text,
+    peniko::{Color, kurbo::Shape},
+    shape::FatPaint,
+    text::FatText,
+    text_on_path::{FatTextOnPath, MeasuredPath},
+};
+
+use crate::{Environment, RenderOptions, SHAPE_TOLERANCE, convert};
+
+/// A clip or layer that was pushed, and is ended by the matching [`Renderer::pop`].
+enum Frame {
+    /// A clip, with the mask it replaced.
+    Clip(Option<Mask>),
+    /// A layer, drawn into a pixmap of its own, and composited with its opacity when it
+    /// is popped, or drawn as it is if there is no pixmap for it.
+    Layer(Option<Pixmap>, f32),
+}
+
+/// Draws into a pixmap, with a view applied on top of the transforms it is given.
+pub(crate) struct PixmapRenderer<'a, 'p> {
+    /// Pixmap under all layers.
+    pixmap: &'a mut PixmapMut<'p>,
+    /// View from the coordinates of the drawing to pixels.
+    view: Affine,
+    /// Mask of the current clip, in pixels.
+    mask: Option<Mask>,
+    /// Clips and layers being drawn.
+    stack: Vec<Frame>,
+    /// Whether the item being drawn hasn't been counted yet.
+    pending: bool,
+    /// Number of items that drew anything.
+    drawn: usize,
+    /// Options for rendering.
+    options: &'a RenderOptions,
+    /// Environment for shaping text.
+    #[cfg(feature = "text")]
+    env: &'a mut Environment,
+}
+
+impl<'a, 'p> PixmapRenderer<'a, 'p> {
+    pub(crate) fn new(
+        env: &'a mut Environment,
+        pixmap: &'a mut PixmapMut<'p>,
+        view: Affine,
+        options: &'a RenderOptions,
+    ) -> Self {
+        #[cfg(not(feature = "text"))]
+        let _ = env;
+        Self {
+            pixmap,
+            view,
+            mask: None,
+            stack: Vec::new(),
+            pending: false,
+            drawn: 0,
+            options,
+            #[cfg(feature = "text")]
+            env,
+        }
+    }
+
+    /// Get the options for drawing items with this renderer.
+    ///
+    /// The renderer is given transforms without the view, so tolerances and marker sizes
+    /// are in the coordinates of the drawing, and dashes in device pixels are applied
+    /// by the renderer.
+    pub(crate) fn draw_options(view: Affine, options: &RenderOptions) -> DrawOptions {
+        let scale = uniform_scale(view).max(f64::EPSILON);
+        DrawOptions {
+            fill_rule: options.fill_rule,
+            tolerance: SHAPE_TOLERANCE / scale,
+            pixels_per_millimeter: options.pixels_per_millimeter / scale,
+            dash_units: DashUnits::Local,
+        }
+    }
+
+    /// Get the number of items that drew anything, ending any layers left open.
+    pub(crate) fn finish(mut self) -> usize {
+        while !self.stack.is_empty() {
+            self.pop();
+        }
+        self.drawn
+    }
+
+    /// Count the item being drawn, if it hasn't been counted yet.
+    fn mark(&mut self) {
+        if self.pending {
+            self.drawn += 1;
+            self.pending = false;
+        }
+    }
+
+    /// Call `f` with the pixmap of the innermost layer, and the mask of the current clip.
+    fn with_target(&mut self, f: impl FnOnce(&mut PixmapMut<'_>, Option<&Mask>)) {
+        let layer = self.stack.iter_mut().rev().find_map(|frame| match frame {
+            Frame::Layer(Some(pixmap), _) => Some(pixmap),
+            _ => None,
+        });
+        match layer {
+            Some(pixmap) => f(&mut pixmap.as_mut(), self.mask.as_ref()),
+            None => f(self.pixmap, self.mask.as_ref()),
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill and stroke a path with a paint, as [`draw_items`](tabulon::backend::draw_items)
+    /// does for shapes.
+    fn draw_paint(&mut self, transform: Affine, paint: &FatPaint, path: &BezPath) {
+        if let Some(fill_paint) = &paint.fill_paint {
+            let fill_rule = paint.fill_rule.unwrap_or(self.options.fill_rule);
+            self.fill(transform, fill_rule, fill_paint, path);
+        }
+        if let Some(stroke_paint) = &paint.stroke_paint {
+            self.stroke(transform, &paint.stroke, stroke_paint, path);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill glyph outlines, each with its own transform within `transform`, grouping
+    /// glyphs of the same color into one path.
+    ///
+    /// Glyphs are filled with their own color if they have one, and with `brush` otherwise.
+    fn fill_glyphs<'g>(
+        &mut self,
+        transform: Affine,
+        brush: &Brush,
+        glyphs: impl Iterator<Item = (Option<Color>, Affine, &'g BezPath)>,
+    ) {
+        let fill = |this: &mut Self, color: Option<Color>, outlines: &BezPath| {
+            let brush = color.map_or_else(|| brush.clone(), Brush::Solid);
+            this.fill(transform, Fill::NonZero, &brush, outlines);
+        };
+        let mut run: Option<(Option<Color>, BezPath)> = None;
+        for (color, placement, outline) in glyphs {
+            match &mut run {
+                Some((c, outlines)) if *c == color => {
+                    outlines.extend(outline.iter().map(|el| placement * el));
+                }
+                _ => {
+                    if let Some((c, outlines)) = run.take() {
+                        fill(self, c, &outlines);
+                    }
+                    run = Some((color, placement * outline));
+                }
+            }
+        }
+        if let Some((c, outlines)) = run {
+            fill(self, c, &outlines);
+        }
+    }
+}
+
+impl Renderer for PixmapRenderer<'_, '_> {
+    fn begin_item(&mut self, _item: ItemHandle) {
+        self.pending = true;
+    }
+
+    fn fill(&mut self, transform: Affine, fill_rule: Fill, brush: &Brush, path: &BezPath) {
+        let Some(path) = convert::path(path.iter()) else {
+            return;
+        };
+        self.mark();
+        let anti_alias = self.options.anti_alias;
+        let ts = convert::transform(self.view * transform);
+        self.with_target(|pixmap, mask| {
+            let mut image = None;
+            if let Some(p) = convert::paint(brush, &mut image, anti_alias) {
+                pixmap.fill_path(&path, &p, convert::fill_rule(fill_rule), ts, mask);
+            }
+        });
+    }
+
+    fn stroke(&mut self, transform: Affine, stroke: &Stroke, brush: &Brush, path: &BezPath) {
+        let Some(path) = convert::path(path.iter()) else {
+            return;
+        };
+        self.mark();
+        let anti_alias = self.options.anti_alias;
+        let transform = self.view * transform;
+        let stroke = convert::stroke(&self.options.dash_units.apply(stroke, transform));
+        let ts = convert::transform(transform);
+        self.with_target(|pixmap, mask| {
+            let mut image = None;
+            if let Some(p) = convert::paint(brush, &mut image, anti_alias) {
+                pixmap.stroke_path(&path, &p, &stroke, ts, mask);
+            }
+        });
+    }
+
+    fn image(&mut self, transform: Affine, image: &Image, opacity: f32) {
+        let Some(image) = convert::image_pixmap(image) else {
+            return;
+        };
+        self.mark();
+        let ts = convert::transform(self.view * transform);
+        self.with_target(|pixmap, mask| {
+            pixmap.draw_pixmap(
+                0,
+                0,
+                image.as_ref(),
+                &PixmapPaint {
+                    opacity: opacity.min(1.0),
+                    ..Default::default()
+                },
+                ts,
+                mask,
+            );
+        });
+    }
+
+    #[cfg(feature = "text")]
+    fn text(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatText,
+        paint: &FatPaint,
+        background: Option<&FatPaint>,
+    ) {
+        if !self.options.text_enabled {
+            return;
+        }
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        self.mark();
+        let laid_out = layout_text(&mut self.env.font_cx, &mut self.env.layout_cx, t, true);
+        if let (Some(background), Some(rect)) = (background, t.background_rect(laid_out.size)) {
+            let placement = transform * t.placement_for_size(laid_out.size);
+            self.draw_paint(placement, background, &rect.to_path(SHAPE_TOLERANCE));
+        }
+        self.fill_glyphs(
+            transform * laid_out.placement(t),
+            fill_paint,
+            laid_out.positioned_glyphs(),
+        );
+    }
+
+    #[cfg(feature = "text")]
+    fn text_on_path(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatTextOnPath,
+        paint: &FatPaint,
+    ) {
+        if !self.options.text_enabled {
+            return;
+        }
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        self.mark();
+        let laid_out = layout_text(
+            &mut self.env.font_cx,
+            &mut self.env.layout_cx,
+            &t.line_text(),
+            true,
+        );
+        let measured = MeasuredPath::new(&t.path);
+        self.fill_glyphs(
+            transform,
+            fill_paint,
+            laid_out.glyphs_on_path(&measured, t.offset),
+        );
+    }
+
+    fn push_clip(&mut self, transform: Affine, path: &BezPath) {
+        let transform = self.view * transform;
+        // Clips that don't make a path with an area leave the current clip as it is.
+        let mask = match &self.mask {
+            Some(mask) => convert::path(path.iter()).map(|clip| {
+                let mut mask = mask.clone();
+                mask.intersect_path(
+                    &clip,
+                    tiny_skia::FillRule::Winding,
+                    self.options.anti_alias,
+                    convert::transform(transform),
+                );
+                mask
+            }),
+            None => convert::clip_mask(
+                self.pixmap.width(),
+                self.pixmap.height(),
+                path,
+                transform,
+                self.options.anti_alias,
+            ),
+        };
+        let previous = match mask {
+            Some(mask) => self.mask.replace(mask),
+            None => self.mask.clone(),
+        };
+        self.stack.push(Frame::Clip(previous));
+    }
+
+    fn push_layer(&mut self, opacity: f32, _bounds: Rect) {
+        // Layers are drawn whole, so that the pixmap of a layer lines up with the
+        // pixmap under it.
+        let pixmap = Pixmap::new(self.pixmap.width(), self.pixmap.height());
+        self.stack.push(Frame::Layer(pixmap, opacity));
+    }
+
+    fn pop(&mut self) {
+        match self.stack.pop() {
+            Some(Frame::Clip(mask)) => self.mask = mask,
+            Some(Frame::Layer(Some(layer), opacity)) => {
+                // What is drawn in the layer is already clipped.
+                self.with_target(|pixmap, _| {
+                    pixmap.draw_pixmap(
+                        0,
+                        0,
+                        layer.as_ref(),
+                        &PixmapPaint {
+                            opacity,
+                            ..Default::default()
+                        },
+                        Transform::identity(),
+                        None,
+                    );
+                });
+            }
+            Some(Frame::Layer(None, _)) | None => {}
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Formatting of values as SVG attributes.
+
+extern crate alloc;
+use alloc::string::String;
+use core::fmt::{self, Display, Formatter, Write};
+
+use tabulon::peniko::{
+    color::{DynamicColor, Srgb},
+    kurbo::{Affine, BezPath, PathEl, Point},
+};
+
+/// A number, written in full, or as 0 if it is not finite.
+///
+/// Display for floats writes the shortest form that reads back exactly, without an
+/// exponent, so `f32`s are written as they are rather than widened to `f64`.
+#[derive(Clone, Copy)]
+pub(crate) struct Num<T = f64>(pub(crate) T);
+
+impl Display for Num<f64> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0.is_finite() {
+            write!(f, "{}", self.0)
+        } else {
+            f.write_str("0")
+        }
+    }
+}
+
+impl Display for Num<f32> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0.is_finite() {
+            write!(f, "{}", self.0)
+        } else {
+            f.write_str("0")
+        }
+    }
+}
+
+/// A transform, as the value of a `transform` attribute.
+pub(crate) struct Matrix(pub(crate) Affine);
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0.as_coeffs().map(Num);
+        write!(f, "matrix({a} {b} {c} {d} {e} {g})")
+    }
+}
+
+/// A path, as the value of a `d` attribute.
+pub(crate) struct PathData<'a>(pub(crate) &'a BezPath);
+
+impl Display for PathData<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let p = |p: Point| (Num(p.x), Num(p.y));
+        let mut first = true;
+        for el in self.0.iter() {
+            if !first {
+                f.write_char(' ')?;
+            }
+            first = false;
+            match el {
+                PathEl::MoveTo(p0) => {
+                    let (x, y) = p(p0);
+                    write!(f, "M{x} {y}")?;
+                }
+                PathEl::LineTo(p0) => {
+                    let (x, y) = p(p0);
+                    write!(f, "L{x} {y}")?;
+                }
+                PathEl::QuadTo(p1, p2) => {
+                    let ((x1, y1), (x, y)) = (p(p1), p(p2));
+                    write!(f, "Q{x1} {y1} {x} {y}")?;
+                }
+                PathEl::CurveTo(p1, p2, p3) => {
+                    let ((x1, y1), (x2, y2), (x, y)) = (p(p1), p(p2), p(p3));
+                    write!(f, "C{x1} {y1} {x2} {y2} {x} {y}")?;
+                }
+                PathEl::ClosePath => f.write_char('Z')?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Text with the characters that are special in XML escaped.
+#[cfg(any(feature = "text", test))]
+pub(crate) struct Escaped<'a>(pub(crate) &'a str);
+
+#[cfg(any(feature = "text", test))]
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&apos;")?,
+                _ => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write a color as a `prop` attribute, and its alpha as an `opacity_prop` attribute if
+/// it is not opaque, such as `fill="#ff0000" fill-opacity="0.5"`.
+pub(crate) fn color_attrs(
+    out: &mut String,
+    prop: &str,
+    opacity_prop: &str,
+    color: impl Into<DynamicColor>,
+) {
+    let color = color.into().to_alpha_color::<Srgb>();
+    let [r, g, b, _] = color.to_rgba8().to_u8_array();
+    let _ = write!(out, r##" {prop}="#{r:02x}{g:02x}{b:02x}""##);
+    let alpha = color.components[3];
+    if alpha < 1.0 {
+        let _ = write!(out, r#" {opacity_prop}="{}""#, Num(alpha.max(0.0)));
+    }
+}
+
+/// Encode `data` in standard Base64, with padding.
+#[cfg(feature = "std")]
+pub(crate) fn base64(out: &mut String, data: &[u8]) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn numbers_and_escapes() {
+        assert_eq!(
+            (
+                Num(2.0).to_string(),
+                Num(0.25).to_string(),
+                Num(f64::NAN).to_string()
+            ),
+            ("2".into(), "0.25".into(), "0".into()),
+            "Numbers should be written without exponents, and not finite ones as 0."
+        );
+        assert_eq!(
+            Escaped("a<b & \"c\"").to_string(),
+            "a&lt;b &amp; &quot;c&quot;",
+            "Markup characters should be escaped."
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn base64_padding() {
+        let encode = |data: &[u8]| {
+            let mut out = String::new();
+            base64(&mut out, data);
+            out
+        };
+        assert_eq!(
+            (encode(b"Man"), encode(b"Ma"), encode(b"M")),
+            ("TWFu".into(), "TWE=".into(), "TQ==".into()),
+            "Partial groups should be padded."
+        );
+    }
+}
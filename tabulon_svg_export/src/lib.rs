@@ -0,0 +1,415 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! SVG export for Tabulon.
+//!
+//! This writes [`RenderLayer`]s and [`LayerStack`]s as SVG documents, so that a view of
+//! a drawing can be handed to web tooling, or opened in a vector editor. Shapes and
+//! markers become paths with their fills and strokes, dash patterns included, clips
+//! become `<clipPath>`s, images are embedded as PNG, and each layer of a stack becomes
+//! a group with its opacity.
+//!
+//! Text is laid out with Parley, so that it is placed as `tabulon_vello` draws it, and
+//! is written either as `<text>` elements, which stay selectable and searchable but are
+//! drawn with whatever fonts the viewer has, or as glyph outlines, which look the same
+//! everywhere. See [`TextMode`].
+//!
+//! ## Features
+//!
+//! - `std` (enabled by default): Use the standard library. Images are only embedded
+//!   with `std`, as the PNG encoder needs it.
+//! - `libm`: Use floating point implementations from libm.
+//! - `text` (enabled by default): Write text items, laid out with Parley. Without it,
+//!   Parley is not compiled, and only shapes, images, and markers are written.
+
+extern crate alloc;
+use alloc::string::String;
+use core::fmt::Write;
+
+use tabulon::{
+    GraphicsBag,
+    backend::{DrawOptions, draw_items},
+    layer_stack::LayerStack,
+    peniko::{
+        Fill,
+        kurbo::{Affine, Size},
+    },
+    render_layer::RenderLayer,
+    shape::DashUnits,
+    uniform_scale,
+};
+
+#[cfg(feature = "text")]
+use {
+    parley::{FontContext, LayoutContext},
+    tabulon::peniko::Color,
+};
+
+use format::{Matrix, Num};
+use writer::SvgWriter;
+
+mod format;
+mod writer;
+
+/// How text items are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextMode {
+    /// As `<text>` elements, with a `<tspan>` for each run of text, placed where the
+    /// run was laid out.
+    ///
+    /// The text stays selectable and searchable, but is drawn with the fonts of the
+    /// viewer, so it may not fit its layout exactly if they differ from the fonts
+    /// it was laid out with.
+    #[default]
+    Text,
+    /// As the outlines of the glyphs, filled as paths.
+    Outlines,
+}
+
+/// Options for writing SVG documents.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgOptions {
+    /// Fill rule for filling shapes whose paints do not have one.
+    pub fill_rule: Fill,
+    /// Tolerance for converting shapes to paths, in units of the document.
+    pub tolerance: f64,
+    /// Whether to write text items.
+    pub text_enabled: bool,
+    /// How text items are written.
+    pub text_mode: TextMode,
+    /// Units of the document per millimeter, for sizing
+    /// [markers](tabulon::marker::FatMarker) in physical units.
+    pub pixels_per_millimeter: f64,
+    /// Units of the dash patterns of strokes.
+    pub dash_units: DashUnits,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            fill_rule: Fill::NonZero,
+            tolerance: 0.1,
+            text_enabled: true,
+            text_mode: TextMode::Text,
+            // The CSS reference density of 96 pixels per inch, which SVG user units follow.
+            pixels_per_millimeter: 96.0 / 25.4,
+            dash_units: DashUnits::Local,
+        }
+    }
+}
+
+/// Expensive state for writing SVG documents.
+#[derive(Default)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and members don't implement Debug."
+)]
+pub struct Environment {
+    /// Font context.
+    ///
+    /// This contains a font collection that is expensive to reproduce.
+    #[cfg(feature = "text")]
+    pub(crate) font_cx: FontContext,
+    /// Layout context.
+    #[cfg(feature = "text")]
+    pub(crate) layout_cx: LayoutContext<Option<Color>>,
+}
+
+impl Environment {
+    /// Write a [`RenderLayer`] as an SVG document of `size`, with `view` applied on top
+    /// of the transforms of `graphics`.
+    pub fn render_layer_to_svg(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        size: Size,
+        view: Affine,
+    ) -> String {
+        self.render_layer_to_svg_with_options(
+            graphics,
+            render_layer,
+            size,
+            view,
+            &SvgOptions::default(),
+        )
+    }
+
+    /// Write a [`RenderLayer`] as an SVG document of `size` with [`SvgOptions`], with
+    /// `view` applied on top of the transforms of `graphics`.
+    ///
+    /// The document is `size` pixels, with its user units in pixels. Items that are
+    /// missing from `graphics`, have nothing to draw, or are text when text is disabled
+    /// are skipped.
+    pub fn render_layer_to_svg_with_options(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        size: Size,
+        view: Affine,
+        options: &SvgOptions,
+    ) -> String {
+        let mut writer = SvgWriter::new(self, options);
+        start_document(&mut writer.out, size, view);
+        draw_items(
+            &mut writer,
+            graphics,
+            render_layer.indices.iter().copied(),
+            &draw_options(options, view),
+        );
+        end_document(writer.out)
+    }
+
+    /// Write the visible layers of a [`LayerStack`] as an SVG document of `size`, with
+    /// `view` applied on top of the transforms of `graphics`.
+    pub fn layer_stack_to_svg(
+        &mut self,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        size: Size,
+        view: Affine,
+    ) -> String {
+        self.layer_stack_to_svg_with_options(graphics, stack, size, view, &SvgOptions::default())
+    }
+
+    /// Write the visible layers of a [`LayerStack`] as an SVG document of `size` with
+    /// [`SvgOptions`], with `view` applied on top of the transforms of `graphics`.
+    ///
+    /// Each layer is written as a `<g>` group, from the lowest `z` to the highest, with
+    /// the opacity of the layer if it is not fully opaque. Layers that are fully
+    /// transparent or empty are left out.
+    pub fn layer_stack_to_svg_with_options(
+        &mut self,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        size: Size,
+        view: Affine,
+        options: &SvgOptions,
+    ) -> String {
+        let draw_options = draw_options(options, view);
+        let mut writer = SvgWriter::new(self, options);
+        start_document(&mut writer.out, size, view);
+        for l in stack.visible_layers() {
+            if l.opacity <= 0.0 || l.layer.indices.is_empty() {
+                continue;
+            }
+            if l.opacity < 1.0 {
+                let _ = writeln!(writer.out, r#"<g opacity="{}">"#, Num(l.opacity));
+            } else {
+                writer.out.push_str("<g>\n");
+            }
+            draw_items(
+                &mut writer,
+                graphics,
+                l.layer.indices.iter().copied(),
+                &draw_options,
+            );
+            writer.out.push_str("</g>\n");
+        }
+        end_document(writer.out)
+    }
+}
+
+/// Get the options for drawing items with `view` applied on top of their transforms.
+///
+/// The tolerance is in the coordinates of the document, so it is scaled by the view.
+fn draw_options(options: &SvgOptions, view: Affine) -> DrawOptions {
+    DrawOptions {
+        fill_rule: options.fill_rule,
+        tolerance: options.tolerance / uniform_scale(view).max(f64::EPSILON),
+        pixels_per_millimeter: options.pixels_per_millimeter,
+        dash_units: options.dash_units,
+    }
+}
+
+/// Write the start of a document of `size`, and of a group applying `view`.
+fn start_document(out: &mut String, size: Size, view: Affine) {
+    let (width, height) = (Num(size.width), Num(size.height));
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    let _ = writeln!(out, r#"<g transform="{}">"#, Matrix(view));
+}
+
+/// Close the view group and document started by [`start_document`].
+fn end_document(mut out: String) -> String {
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use tabulon::{
+        layer_stack::StackedLayer,
+        peniko::{
+            Color,
+            kurbo::{Line, Rect, Shape, Stroke},
+        },
+        shape::{FatClip, FatPaint, FatShape},
+    };
+
+    #[test]
+    fn shapes_strokes_and_clips() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(2.0).with_dashes(1.0, [4.0, 2.0]),
+            stroke_paint: Some(Color::from_rgba8(255, 0, 0, 128).into()),
+            fill_paint: Some(Color::BLACK.into()),
+            fill_rule: Some(Fill::EvenOdd),
+        });
+        let clip = graphics.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(Rect::new(0.0, 0.0, 5.0, 5.0).to_path(0.1)),
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                clip,
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+
+        let svg = Environment::default().render_layer_to_svg(
+            &graphics,
+            &layer,
+            Size::new(20.0, 10.0),
+            Affine::scale(2.0),
+        );
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10""#)
+                && svg.contains(r#"<g transform="matrix(2 0 0 2 0 0)">"#),
+            "The document should have the size, and apply the view: {svg}"
+        );
+        assert!(
+            svg.contains(r##"d="M0 0 L10 0" fill="#000000" fill-rule="evenodd"/>"##),
+            "The line should be filled with the fill rule of its paint: {svg}"
+        );
+        assert!(
+            svg.contains(r##"stroke="#ff0000" stroke-opacity="0.5019608""##)
+                && svg.contains(r#"stroke-width="2""#)
+                && svg.contains(r#"stroke-dasharray="4 2" stroke-dashoffset="1""#),
+            "The stroke should keep its color, width, and dashes: {svg}"
+        );
+        assert!(
+            svg.contains(r#"<clipPath id="c1"><path transform="matrix(1 0 0 1 0 0)" d="M0 0 L5 0 L5 5 L0 5 Z"/></clipPath>"#)
+                && svg.contains(r#"<g clip-path="url(#c1)">"#),
+            "The clip should be defined and applied to a group: {svg}"
+        );
+        assert_eq!(
+            svg.matches("<g").count(),
+            svg.matches("</g>").count(),
+            "Groups should be balanced: {svg}"
+        );
+    }
+
+    #[test]
+    fn layers_are_groups() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::WHITE.into()),
+            ..Default::default()
+        });
+        let mut stack = LayerStack::default();
+        for (opacity, z) in [(0.25, 1), (1.0, 0), (0.0, 2)] {
+            let mut layer = RenderLayer::default();
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Rect::new(0.0, 0.0, 1.0, 1.0).into()),
+                    ..Default::default()
+                },
+            );
+            stack.push(StackedLayer {
+                layer,
+                opacity,
+                z,
+                ..Default::default()
+            });
+        }
+
+        let svg = Environment::default().layer_stack_to_svg(
+            &graphics,
+            &stack,
+            Size::new(1.0, 1.0),
+            Affine::IDENTITY,
+        );
+        let opaque = svg
+            .find("<g>\n")
+            .expect("The opaque layer should be a group.");
+        let translucent = svg
+            .find(r#"<g opacity="0.25">"#)
+            .expect("The translucent layer should be a group with its opacity.");
+        assert!(
+            opaque < translucent,
+            "Layers should be written from the lowest z to the highest: {svg}"
+        );
+        assert_eq!(
+            svg.matches("<path").count(),
+            2,
+            "The transparent layer should be left out: {svg}"
+        );
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_as_elements_or_outlines() {
+        use alloc::vec::Vec;
+        use tabulon::{DirectIsometry, peniko::kurbo::Vec2, text::FatText};
+
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatText {
+                transform: Default::default(),
+                paint,
+                text: "A&B".into(),
+                style: parley::StyleSet::new(20.0),
+                spans: Vec::new(),
+                alignment: Default::default(),
+                direction: Default::default(),
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.0, Vec2::new(4.0, 4.0)),
+                attachment_point: Default::default(),
+            },
+        );
+
+        let mut env = Environment::default();
+        let size = Size::new(64.0, 32.0);
+        let svg = env.render_layer_to_svg(&graphics, &layer, size, Affine::IDENTITY);
+        assert!(
+            svg.contains(r#"font-size="20""#) && svg.contains(">A&amp;B</tspan></text>"),
+            "Text should be written as escaped text elements: {svg}"
+        );
+
+        let outlined = env.render_layer_to_svg_with_options(
+            &graphics,
+            &layer,
+            size,
+            Affine::IDENTITY,
+            &SvgOptions {
+                text_mode: TextMode::Outlines,
+                ..Default::default()
+            },
+        );
+        assert!(
+            !outlined.contains("<text")
+                && outlined.contains(r#"<path transform="matrix(1 0 0 1 4 4)""#),
+            "Outlined text should be written as paths: {outlined}"
+        );
+    }
+}
@@ -0,0 +1,492 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Writing SVG elements through the [`Renderer`] interface.
+
+extern crate alloc;
+use alloc::string::String;
+use core::fmt::Write;
+
+use tabulon::{
+    backend::Renderer,
+    peniko::{
+        Brush, Extend, Fill, GradientKind, Image,
+        kurbo::{Affine, BezPath, Cap, Join, Rect, Stroke},
+    },
+};
+#[cfg(feature = "text")]
+use {
+    crate::{TextMode, format::Escaped},
+    parley::{StyleProperty, StyleSet},
+    tabulon::{
+        ItemHandle,
+        outline::layout_text,
+        peniko::{Color, kurbo::Shape},
+        shape::FatPaint,
+        text::{FatText, font_stack_to_css},
+        text_on_path::{FatTextOnPath, MeasuredPath},
+    },
+};
+
+use crate::{
+    Environment, SvgOptions,
+    format::{Matrix, Num, PathData, color_attrs},
+};
+
+/// Writes the elements of an SVG document for what is drawn.
+///
+/// Paints with gradients or images, clips, and paths for text are defined where they
+/// are first needed, each with an id of its own.
+pub(crate) struct SvgWriter<'a> {
+    /// The document so far.
+    pub(crate) out: String,
+    /// Number of ids given out.
+    ids: u32,
+    /// Options for writing.
+    #[cfg_attr(
+        not(feature = "text"),
+        expect(dead_code, reason = "Only text is written with options of its own.")
+    )]
+    options: &'a SvgOptions,
+    /// Environment for laying out text.
+    #[cfg(feature = "text")]
+    env: &'a mut Environment,
+}
+
+impl<'a> SvgWriter<'a> {
+    pub(crate) fn new(env: &'a mut Environment, options: &'a SvgOptions) -> Self {
+        #[cfg(not(feature = "text"))]
+        let _ = env;
+        Self {
+            out: String::new(),
+            ids: 0,
+            options,
+            #[cfg(feature = "text")]
+            env,
+        }
+    }
+
+    /// Give out a new id, starting with `prefix`.
+    fn id(&mut self, prefix: char) -> String {
+        self.ids += 1;
+        alloc::format!("{prefix}{}", self.ids)
+    }
+
+    /// Get the attributes for painting `prop` with a brush, such as `fill`, defining
+    /// gradients and patterns first.
+    ///
+    /// SVG has no sweep gradients, so they are painted with the color of their first
+    /// stop. Returns `None` if the brush paints nothing.
+    fn paint_attrs(&mut self, prop: &str, brush: &Brush) -> Option<String> {
+        let mut attrs = String::new();
+        let opacity_prop = alloc::format!("{prop}-opacity");
+        match brush {
+            Brush::Solid(c) => color_attrs(&mut attrs, prop, &opacity_prop, *c),
+            Brush::Gradient(gradient) => {
+                let first = gradient.stops.first()?;
+                let (tag, geometry) = match gradient.kind {
+                    GradientKind::Linear { start, end } => (
+                        "linearGradient",
+                        alloc::format!(
+                            r#"x1="{}" y1="{}" x2="{}" y2="{}""#,
+                            Num(start.x),
+                            Num(start.y),
+                            Num(end.x),
+                            Num(end.y)
+                        ),
+                    ),
+                    GradientKind::Radial {
+                        start_center,
+                        start_radius,
+                        end_center,
+                        end_radius,
+                    } => (
+                        "radialGradient",
+                        alloc::format!(
+                            r#"cx="{}" cy="{}" r="{}" fx="{}" fy="{}" fr="{}""#,
+                            Num(end_center.x),
+                            Num(end_center.y),
+                            Num(end_radius),
+                            Num(start_center.x),
+                            Num(start_center.y),
+                            Num(start_radius)
+                        ),
+                    ),
+                    GradientKind::Sweep { .. } => {
+                        color_attrs(&mut attrs, prop, &opacity_prop, first.color);
+                        return Some(attrs);
+                    }
+                };
+                let id = self.id('g');
+                let spread = match gradient.extend {
+                    Extend::Pad => "pad",
+                    Extend::Repeat => "repeat",
+                    Extend::Reflect => "reflect",
+                };
+                let _ = write!(
+                    self.out,
+                    r#"<defs><{tag} id="{id}" gradientUnits="userSpaceOnUse" {geometry} spreadMethod="{spread}">"#
+                );
+                for stop in gradient.stops.iter() {
+                    let _ = write!(self.out, r#"<stop offset="{}""#, Num(stop.offset));
+                    color_attrs(&mut self.out, "stop-color", "stop-opacity", stop.color);
+                    self.out.push_str("/>");
+                }
+                let _ = writeln!(self.out, "</{tag}></defs>");
+                let _ = write!(attrs, r#" {prop}="url(#{id})""#);
+            }
+            Brush::Image(image) => {
+                let href = image_href(image)?;
+                let id = self.id('p');
+                let (width, height) = (image.width, image.height);
+                let _ = writeln!(
+                    self.out,
+                    r#"<defs><pattern id="{id}" patternUnits="userSpaceOnUse" width="{width}" height="{height}"><image width="{width}" height="{height}" opacity="{}" href="{href}"/></pattern></defs>"#,
+                    Num(image.alpha)
+                );
+                let _ = write!(attrs, r#" {prop}="url(#{id})""#);
+            }
+        }
+        Some(attrs)
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill and stroke a path with a paint, as [`draw_items`](tabulon::backend::draw_items)
+    /// does for shapes.
+    fn draw_paint(&mut self, transform: Affine, paint: &FatPaint, path: &BezPath) {
+        if let Some(fill_paint) = &paint.fill_paint {
+            let fill_rule = paint.fill_rule.unwrap_or(self.options.fill_rule);
+            self.fill(transform, fill_rule, fill_paint, path);
+        }
+        if let Some(stroke_paint) = &paint.stroke_paint {
+            let stroke = self.options.dash_units.apply(&paint.stroke, transform);
+            self.stroke(transform, &stroke, stroke_paint, path);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Fill glyph outlines, each with its own transform within `transform`, grouping
+    /// glyphs of the same color into one path.
+    ///
+    /// Glyphs are filled with their own color if they have one, and with `brush` otherwise.
+    fn fill_glyphs<'g>(
+        &mut self,
+        transform: Affine,
+        brush: &Brush,
+        glyphs: impl Iterator<Item = (Option<Color>, Affine, &'g BezPath)>,
+    ) {
+        let fill = |this: &mut Self, color: Option<Color>, outlines: &BezPath| {
+            let brush = color.map_or_else(|| brush.clone(), Brush::Solid);
+            this.fill(transform, Fill::NonZero, &brush, outlines);
+        };
+        let mut run: Option<(Option<Color>, BezPath)> = None;
+        for (color, placement, outline) in glyphs {
+            match &mut run {
+                Some((c, outlines)) if *c == color => {
+                    outlines.extend(outline.iter().map(|el| placement * el));
+                }
+                _ => {
+                    if let Some((c, outlines)) = run.take() {
+                        fill(self, c, &outlines);
+                    }
+                    run = Some((color, placement * outline));
+                }
+            }
+        }
+        if let Some((c, outlines)) = run {
+            fill(self, c, &outlines);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Write the start of a `<text>` element, with the font of `style`.
+    fn text_start(&mut self, transform: Affine, style: &StyleSet<Option<Color>>, paint: &str) {
+        let _ = write!(
+            self.out,
+            r#"<text transform="{}" xml:space="preserve""#,
+            Matrix(transform)
+        );
+        for prop in style.inner().values() {
+            let _ = match prop {
+                StyleProperty::FontStack(stack) => write!(
+                    self.out,
+                    r#" font-family="{}""#,
+                    Escaped(&font_stack_to_css(stack))
+                ),
+                StyleProperty::FontSize(size) => {
+                    write!(self.out, r#" font-size="{}""#, Num(*size))
+                }
+                StyleProperty::FontWeight(weight) => {
+                    write!(self.out, r#" font-weight="{}""#, Num(weight.value()))
+                }
+                StyleProperty::FontStyle(parley::FontStyle::Italic) => {
+                    write!(self.out, r#" font-style="italic""#)
+                }
+                StyleProperty::FontStyle(parley::FontStyle::Oblique(_)) => {
+                    write!(self.out, r#" font-style="oblique""#)
+                }
+                _ => Ok(()),
+            };
+        }
+        self.out.push_str(paint);
+        self.out.push('>');
+    }
+}
+
+impl Renderer for SvgWriter<'_> {
+    fn fill(&mut self, transform: Affine, fill_rule: Fill, brush: &Brush, path: &BezPath) {
+        if path.is_empty() {
+            return;
+        }
+        let Some(paint) = self.paint_attrs("fill", brush) else {
+            return;
+        };
+        let _ = write!(
+            self.out,
+            r#"<path transform="{}" d="{}"{paint}"#,
+            Matrix(transform),
+            PathData(path)
+        );
+        if fill_rule == Fill::EvenOdd {
+            self.out.push_str(r#" fill-rule="evenodd""#);
+        }
+        self.out.push_str("/>\n");
+    }
+
+    fn stroke(&mut self, transform: Affine, stroke: &Stroke, brush: &Brush, path: &BezPath) {
+        if path.is_empty() {
+            return;
+        }
+        let Some(paint) = self.paint_attrs("stroke", brush) else {
+            return;
+        };
+        // SVG has a single cap for both ends, so the start cap is used.
+        let cap = match stroke.start_cap {
+            Cap::Butt => "butt",
+            Cap::Square => "square",
+            Cap::Round => "round",
+        };
+        let _ = write!(
+            self.out,
+            r#"<path transform="{}" d="{}" fill="none"{paint} stroke-width="{}" stroke-linecap="{cap}""#,
+            Matrix(transform),
+            PathData(path),
+            Num(stroke.width),
+        );
+        let _ = match stroke.join {
+            Join::Bevel => write!(self.out, r#" stroke-linejoin="bevel""#),
+            Join::Round => write!(self.out, r#" stroke-linejoin="round""#),
+            Join::Miter => write!(
+                self.out,
+                r#" stroke-linejoin="miter" stroke-miterlimit="{}""#,
+                Num(stroke.miter_limit)
+            ),
+        };
+        if !stroke.dash_pattern.is_empty() {
+            self.out.push_str(r#" stroke-dasharray=""#);
+            for (i, dash) in stroke.dash_pattern.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(' ');
+                }
+                let _ = write!(self.out, "{}", Num(*dash));
+            }
+            let _ = write!(
+                self.out,
+                r#"" stroke-dashoffset="{}""#,
+                Num(stroke.dash_offset)
+            );
+        }
+        self.out.push_str("/>\n");
+    }
+
+    fn image(&mut self, transform: Affine, image: &Image, opacity: f32) {
+        let Some(href) = image_href(image) else {
+            return;
+        };
+        let _ = write!(
+            self.out,
+            r#"<image transform="{}" width="{}" height="{}" preserveAspectRatio="none""#,
+            Matrix(transform),
+            image.width,
+            image.height
+        );
+        let opacity = opacity * image.alpha;
+        if opacity < 1.0 {
+            let _ = write!(self.out, r#" opacity="{}""#, Num(opacity));
+        }
+        let _ = writeln!(self.out, r#" href="{href}"/>"#);
+    }
+
+    #[cfg(feature = "text")]
+    fn text(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatText,
+        paint: &FatPaint,
+        background: Option<&FatPaint>,
+    ) {
+        if !self.options.text_enabled {
+            return;
+        }
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+        let outlines = self.options.text_mode == TextMode::Outlines;
+        let laid_out = layout_text(&mut self.env.font_cx, &mut self.env.layout_cx, t, outlines);
+        let placement = transform * t.placement_for_size(laid_out.size);
+        if let (Some(background), Some(rect)) = (background, t.background_rect(laid_out.size)) {
+            self.draw_paint(placement, background, &rect.to_path(self.options.tolerance));
+        }
+
+        if outlines {
+            self.fill_glyphs(
+                transform * laid_out.placement(t),
+                fill_paint,
+                laid_out.positioned_glyphs(),
+            );
+            return;
+        }
+
+        let Some(paint) = self.paint_attrs("fill", fill_paint) else {
+            return;
+        };
+        self.text_start(
+            placement * t.mirror_for_size(laid_out.size),
+            &t.style,
+            &paint,
+        );
+        for run in &laid_out.runs {
+            let Some(text) = t.text.get(run.range.clone()) else {
+                continue;
+            };
+            let text = text.trim_end_matches(['\n', '\r']);
+            if text.is_empty() {
+                continue;
+            }
+            // Right to left runs start at their right end.
+            let x = if run.rtl {
+                run.origin.x + run.advance
+            } else {
+                run.origin.x
+            };
+            let _ = write!(
+                self.out,
+                r#"<tspan x="{}" y="{}" font-size="{}""#,
+                Num(x),
+                Num(run.origin.y),
+                Num(run.font_size)
+            );
+            if let Some(color) = run.color {
+                color_attrs(&mut self.out, "fill", "fill-opacity", color);
+            }
+            if run.rtl {
+                self.out.push_str(r#" direction="rtl""#);
+            }
+            let _ = write!(self.out, ">{}</tspan>", Escaped(text));
+        }
+        self.out.push_str("</text>\n");
+    }
+
+    #[cfg(feature = "text")]
+    fn text_on_path(
+        &mut self,
+        _item: ItemHandle,
+        transform: Affine,
+        t: &FatTextOnPath,
+        paint: &FatPaint,
+    ) {
+        if !self.options.text_enabled {
+            return;
+        }
+        let Some(fill_paint) = &paint.fill_paint else {
+            return;
+        };
+
+        if self.options.text_mode == TextMode::Outlines {
+            let laid_out = layout_text(
+                &mut self.env.font_cx,
+                &mut self.env.layout_cx,
+                &t.line_text(),
+                true,
+            );
+            let measured = MeasuredPath::new(&t.path);
+            self.fill_glyphs(
+                transform,
+                fill_paint,
+                laid_out.glyphs_on_path(&measured, t.offset),
+            );
+            return;
+        }
+
+        let Some(paint) = self.paint_attrs("fill", fill_paint) else {
+            return;
+        };
+        let id = self.id('t');
+        let _ = writeln!(
+            self.out,
+            r#"<defs><path id="{id}" d="{}"/></defs>"#,
+            PathData(&t.path)
+        );
+        self.text_start(transform, &t.style, &paint);
+        let _ = writeln!(
+            self.out,
+            r##"<textPath href="#{id}" startOffset="{}">{}</textPath></text>"##,
+            Num(t.offset),
+            Escaped(&t.text)
+        );
+    }
+
+    fn push_clip(&mut self, transform: Affine, path: &BezPath) {
+        let id = self.id('c');
+        let _ = writeln!(
+            self.out,
+            r#"<clipPath id="{id}"><path transform="{}" d="{}"/></clipPath>"#,
+            Matrix(transform),
+            PathData(path)
+        );
+        let _ = writeln!(self.out, r#"<g clip-path="url(#{id})">"#);
+    }
+
+    fn push_layer(&mut self, opacity: f32, _bounds: Rect) {
+        let _ = writeln!(self.out, r#"<g opacity="{}">"#, Num(opacity));
+    }
+
+    fn pop(&mut self) {
+        self.out.push_str("</g>\n");
+    }
+}
+
+/// Encode an image as a PNG data URL, or `None` if it can't be encoded.
+///
+/// Only [`ImageFormat::Rgba8`](tabulon::peniko::ImageFormat::Rgba8) images are encoded,
+/// and only with the `std` feature, which the PNG encoder needs.
+fn image_href(image: &Image) -> Option<String> {
+    #[cfg(feature = "std")]
+    {
+        use tabulon::peniko::ImageFormat;
+
+        if !matches!(image.format, ImageFormat::Rgba8) || image.width == 0 || image.height == 0 {
+            return None;
+        }
+        let data = image.data.data();
+        let len = image.width as usize * image.height as usize * 4;
+        let pixels = data.get(..len)?;
+        let mut png = alloc::vec::Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, image.width, image.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().ok()?;
+        writer.write_image_data(pixels).ok()?;
+        writer.finish().ok()?;
+
+        let mut href = String::from("data:image/png;base64,");
+        crate::format::base64(&mut href, &png);
+        Some(href)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = image;
+        None
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Drawing only the items of render layers that are in view, using spatial indices
+//! kept in the [`Environment`].
+
+use tabulon::{
+    GraphicsBag, TransformHandle,
+    item_set::ItemSet,
+    layer_stack::LayerStack,
+    peniko::{
+        Mix,
+        kurbo::{Affine, Rect},
+    },
+    render_layer::RenderLayer,
+};
+use vello::Scene;
+
+use crate::{EncodeStats, Environment, RenderOptions};
+
+impl Environment {
+    /// Add the items of a [`RenderLayer`] that are in `viewport` to a Vello [`Scene`].
+    ///
+    /// See [`add_render_layer_to_scene_culled_with_options`] for details.
+    ///
+    /// [`add_render_layer_to_scene_culled_with_options`]: Self::add_render_layer_to_scene_culled_with_options
+    pub fn add_render_layer_to_scene_culled(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        viewport: Rect,
+    ) -> EncodeStats {
+        self.add_render_layer_to_scene_culled_with_options(
+            scene,
+            graphics,
+            render_layer,
+            viewport,
+            &RenderOptions::default(),
+        )
+    }
+
+    /// Add the items of a [`RenderLayer`] that are in `viewport` to a Vello [`Scene`]
+    /// with [`RenderOptions`].
+    ///
+    /// `viewport` is in device coordinates, and is mapped to the local coordinates of
+    /// items with the inverse of the root transform of `graphics`. Items are found with
+    /// a [`SpatialIndex`] of the layer, kept in the environment, using
+    /// [`query_items_stroked`](tabulon::spatial_index::SpatialIndex::query_items_stroked) so that wide strokes
+    /// crossing into the viewport are kept, and text by its estimated bounds. The items
+    /// left out are counted in [`EncodeStats::culled`].
+    ///
+    /// Indices are kept for the last few layers drawn this way, and built again when a
+    /// layer's items change, or when the [epoch](GraphicsBag::epoch) of the bag changes.
+    /// Like the index, culling works in the local coordinates of items, so it suits
+    /// layers whose items share the root transform, such as a view transform.
+    ///
+    /// [`SpatialIndex`]: tabulon::spatial_index::SpatialIndex
+    #[tracing::instrument(skip_all)]
+    pub fn add_render_layer_to_scene_culled_with_options(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        viewport: Rect,
+        options: &RenderOptions<'_>,
+    ) -> EncodeStats {
+        let visible = self.visible_items(graphics, render_layer, viewport);
+        self.add_visible_items_to_scene(scene, graphics, render_layer, &visible, options)
+    }
+
+    /// Find the items of `render_layer` that are in `viewport`.
+    ///
+    /// A root transform that is not invertible shows nothing.
+    fn visible_items(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        viewport: Rect,
+    ) -> ItemSet {
+        let root = graphics.get_transform(TransformHandle::default());
+        if root.determinant() == 0.0 {
+            return ItemSet::new();
+        }
+        let local = root.inverse().transform_rect_bbox(viewport);
+        self.cull_indices
+            .get(graphics, render_layer)
+            .query_items_stroked(graphics, local)
+    }
+
+    /// Add the items of `render_layer` that are in `visible` to a scene, counting the
+    /// others as culled.
+    fn add_visible_items_to_scene(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        visible: &ItemSet,
+        options: &RenderOptions<'_>,
+    ) -> EncodeStats {
+        let mut stats = self.add_items_to_scene(
+            scene,
+            graphics,
+            render_layer.iter_filtered(|ih| visible.contains(*ih)),
+            options,
+        );
+        stats.culled = render_layer
+            .indices
+            .iter()
+            .filter(|ih| !visible.contains(**ih))
+            .count();
+        stats
+    }
+
+    /// Add the items of the visible layers of a [`LayerStack`] that are in `viewport` to a
+    /// Vello [`Scene`].
+    ///
+    /// See [`add_layer_stack_to_scene_culled_with_options`] for details.
+    ///
+    /// [`add_layer_stack_to_scene_culled_with_options`]: Self::add_layer_stack_to_scene_culled_with_options
+    pub fn add_layer_stack_to_scene_culled(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        viewport: Rect,
+    ) -> EncodeStats {
+        self.add_layer_stack_to_scene_culled_with_options(
+            scene,
+            graphics,
+            stack,
+            viewport,
+            &RenderOptions::default(),
+        )
+    }
+
+    /// Add the items of the visible layers of a [`LayerStack`] that are in `viewport` to a
+    /// Vello [`Scene`] with [`RenderOptions`].
+    ///
+    /// Like [`add_layer_stack_to_scene_with_options`], layers that are not fully opaque
+    /// are composited as a group, here clipped to `viewport` rather than to the bounds
+    /// of the layer, so that groups don't cost more than the part of the layer in view.
+    /// Each layer is culled as in [`add_render_layer_to_scene_culled_with_options`], and
+    /// a group is left out when none of its items are in view.
+    ///
+    /// Returns what was encoded for all of the layers together, see [`EncodeStats`].
+    ///
+    /// [`add_layer_stack_to_scene_with_options`]: Self::add_layer_stack_to_scene_with_options
+    /// [`add_render_layer_to_scene_culled_with_options`]: Self::add_render_layer_to_scene_culled_with_options
+    #[tracing::instrument(skip_all)]
+    pub fn add_layer_stack_to_scene_culled_with_options(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        viewport: Rect,
+        options: &RenderOptions<'_>,
+    ) -> EncodeStats {
+        let mut stats = EncodeStats::default();
+        for l in stack.visible_layers() {
+            if l.opacity <= 0.0 || l.layer.indices.is_empty() {
+                continue;
+            }
+            let visible = self.visible_items(graphics, &l.layer, viewport);
+            let group = l.opacity < 1.0 && !visible.is_empty();
+            if group {
+                scene.push_layer(Mix::Normal, l.opacity, Affine::IDENTITY, &viewport);
+            }
+            stats += self.add_visible_items_to_scene(scene, graphics, &l.layer, &visible, options);
+            if group {
+                scene.pop_layer();
+            }
+        }
+        stats
+    }
+
+    /// Drop the indices kept for [culled drawing](Self::add_render_layer_to_scene_culled)
+    /// and the [partitions by kind](Self::layer_kinds) of layers.
+    ///
+    /// This should be called when switching to a different [`GraphicsBag`] to release
+    /// memory held for the old one.
+    pub fn clear_layer_indices(&mut self) {
+        self.cull_indices.clear();
+        self.layer_kinds.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use tabulon::{
+        layer_stack::StackedLayer,
+        peniko::{
+            Color,
+            kurbo::{Line, Stroke},
+        },
+        shape::{FatPaint, FatShape},
+    };
+
+    use alloc::sync::Arc;
+
+    #[test]
+    fn offscreen_items_are_culled() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        for x in 0..10 {
+            let x = f64::from(x) * 100.0;
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Line::new((x, 0.0), (x + 10.0, 10.0)).into()),
+                    ..Default::default()
+                },
+            );
+        }
+        // The view shows drawing coordinates from 100 to 290 across 380 device pixels.
+        graphics.update_transform(
+            TransformHandle::default(),
+            Affine::scale(2.0).then_translate((-200.0, 0.0).into()),
+        );
+
+        let mut env = Environment::default();
+        let stats = env.add_render_layer_to_scene_culled(
+            &mut Scene::new(),
+            &graphics,
+            &layer,
+            Rect::new(0.0, 0.0, 380.0, 100.0),
+        );
+        assert_eq!(
+            (stats.items_encoded, stats.culled),
+            (2, 8),
+            "Only the items in the viewport should be encoded."
+        );
+        assert_eq!(
+            env.cull_indices.indices.len(),
+            1,
+            "The layer's index should be kept."
+        );
+        env.add_render_layer_to_scene_culled(
+            &mut Scene::new(),
+            &graphics,
+            &layer,
+            Rect::new(0.0, 0.0, 380.0, 100.0),
+        );
+        assert_eq!(
+            env.cull_indices.indices.len(),
+            1,
+            "The index should be reused for the same layer."
+        );
+    }
+
+    #[test]
+    fn offscreen_groups_are_left_out() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut stack = LayerStack::default();
+        for (x, opacity) in [(0.0, 1.0), (1000.0, 0.5), (0.0, 0.5)] {
+            let mut layer = RenderLayer::default();
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(Rect::new(x, 0.0, x + 10.0, 10.0).into()),
+                    ..Default::default()
+                },
+            );
+            stack.push(StackedLayer {
+                layer,
+                opacity,
+                ..Default::default()
+            });
+        }
+
+        let mut scene = Scene::new();
+        let stats = Environment::default().add_layer_stack_to_scene_culled(
+            &mut scene,
+            &graphics,
+            &stack,
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+        );
+        assert_eq!(
+            (stats.items_encoded, stats.culled),
+            (2, 1),
+            "Each layer should be culled."
+        );
+        let mut one_group = Scene::new();
+        one_group.push_layer(Mix::Normal, 0.5, Affine::IDENTITY, &Rect::ZERO);
+        one_group.pop_layer();
+        assert_eq!(
+            scene.encoding().n_clips,
+            one_group.encoding().n_clips,
+            "Only translucent layers with items in view should be grouped."
+        );
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Fonts supplied by applications.
+//!
+//! Text is shaped with the system fonts by default. Drawings often name fonts that are
+//! not installed, such as the SHX fonts of CAD applications, so applications can
+//! register fonts they bundle, under their own family names or under the names that
+//! styles ask for.
+
+extern crate alloc;
+use alloc::{
+    collections::BTreeSet,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use parley::fontique::{Blob, FontInfoOverride};
+
+use crate::Environment;
+
+impl Environment {
+    /// Register the fonts in the contents of a font file or collection.
+    ///
+    /// With `family`, the fonts are registered under that family name rather than the
+    /// names in the fonts, so that styles naming `family` are drawn with them.
+    ///
+    /// Cached text layouts are dropped, as fonts can change the layout of any text.
+    ///
+    /// Returns the names of the families that fonts were added to, which is empty if
+    /// `data` has no fonts.
+    pub fn register_fonts(&mut self, data: Vec<u8>, family: Option<&str>) -> Vec<String> {
+        let families = self.register_blob(Blob::from(data), family);
+        self.clear_text_layouts();
+        families
+    }
+
+    #[cfg(feature = "std")]
+    /// Register the fonts in a font file or collection, as by
+    /// [`register_fonts`](Self::register_fonts).
+    pub fn register_font_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        family: Option<&str>,
+    ) -> std::io::Result<Vec<String>> {
+        Ok(self.register_fonts(std::fs::read(path)?, family))
+    }
+
+    /// Make the fonts of an available `family` available under `name` as well.
+    ///
+    /// This lets styles that name fonts which are not available, such as `romans` for
+    /// the `romans.shx` SHX font, be drawn with a family that is, whether it is a
+    /// system font or one [registered](Self::register_fonts) by the application.
+    ///
+    /// Cached text layouts are dropped, as fonts can change the layout of any text.
+    ///
+    /// Returns `false` if `family` is not available.
+    pub fn map_font_family(&mut self, name: &str, family: &str) -> bool {
+        let Some(info) = self.font_cx.collection.family_by_name(family) else {
+            return false;
+        };
+        // Fonts in a collection file share a source, which is registered once.
+        let mut sources = BTreeSet::new();
+        for font in info.fonts() {
+            if !sources.insert(font.source().id().to_u64()) {
+                continue;
+            }
+            if let Some(blob) = font.load(Some(&mut self.font_cx.source_cache)) {
+                self.register_blob(blob, Some(name));
+            }
+        }
+        self.clear_text_layouts();
+        true
+    }
+
+    /// Register the fonts in `data`, optionally under `family`.
+    fn register_blob(&mut self, data: Blob<u8>, family: Option<&str>) -> Vec<String> {
+        let collection = &mut self.font_cx.collection;
+        let registered = collection.register_fonts(
+            data,
+            family.map(|f| FontInfoOverride {
+                family_name: Some(f),
+                ..Default::default()
+            }),
+        );
+        registered
+            .into_iter()
+            .filter_map(|(id, _)| collection.family_name(id).map(ToString::to_string))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_families() {
+        let mut env = Environment::default();
+        let names: Vec<String> = env
+            .font_cx
+            .collection
+            .family_names()
+            .map(ToString::to_string)
+            .collect();
+        // Some listed names, such as those of condensed faces, don't name families.
+        let Some(system) = names
+            .into_iter()
+            .find(|n| env.font_cx.collection.family_by_name(n).is_some())
+        else {
+            // Nothing to map without system fonts.
+            return;
+        };
+
+        assert!(
+            !env.map_font_family("romans", "No Such Family"),
+            "Unavailable families can't be mapped."
+        );
+        assert!(
+            env.map_font_family("romans", &system),
+            "Available families should be mapped."
+        );
+        assert!(
+            env.font_cx.collection.family_by_name("romans").is_some(),
+            "Mapped names should be available as families."
+        );
+
+        let font = env
+            .font_cx
+            .collection
+            .family_by_name(&system)
+            .unwrap()
+            .fonts()[0]
+            .clone();
+        let data = font.load(Some(&mut env.font_cx.source_cache)).unwrap();
+        assert_eq!(
+            env.register_fonts(data.data().to_vec(), Some("osifont")),
+            ["osifont"],
+            "Fonts should be registered under the requested family."
+        );
+        assert!(
+            env.register_fonts(Vec::new(), None).is_empty(),
+            "Data without fonts should register nothing."
+        );
+    }
+}
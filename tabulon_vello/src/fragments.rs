@@ -0,0 +1,306 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Scene fragments for render layers, cached in an [`Environment`].
+//!
+//! Panning and zooming only change the root transform of a [`GraphicsBag`], so
+//! encoding every layer again for each gesture repeats work whose result differs
+//! only by that transform. A fragment is a layer encoded once, as [`RestrokeScene`]s,
+//! and appended with the current root transform, with its strokes updated for the
+//! current paints.
+//!
+//! Layers are encoded in chunks of consecutive items. When items, their paints, or
+//! their transforms change, as tracked by the [revisions](GraphicsBag::revision) of the
+//! bag, only the chunks holding changed items are encoded again, so editing one entity
+//! of a large drawing costs about as much as encoding one chunk.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use core::ops::Range;
+
+use tabulon::{
+    GraphicsBag, ItemHandle, Revision, TransformHandle, peniko::Fill, render_layer::RenderLayer,
+    uniform_scale,
+};
+use vello::Scene;
+
+use crate::{Environment, Greeking, RenderOptions, RestrokeScene};
+
+/// Factor by which the view scale can change before a fragment is encoded again.
+///
+/// Flattening tolerances, [levels of detail](RenderOptions::lod_tolerance), and
+/// [greeking](RenderOptions::greek_threshold) are chosen for the scale a fragment is
+/// encoded at, and are kept close to those for the current scale.
+const RESCALE_LIMIT: f64 = 2.0;
+
+/// Number of items encoded together in a chunk of a fragment.
+const CHUNK_ITEMS: usize = 1024;
+
+/// Identifier of a scene fragment cached in an [`Environment`], chosen by the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FragmentId(pub u64);
+
+/// The [`RenderOptions`] that a fragment was encoded with, other than overrides.
+#[derive(Clone, Copy, PartialEq)]
+struct EncodedOptions {
+    fill_rule: Fill,
+    text_enabled: bool,
+    hinting: bool,
+    greek_threshold: f64,
+    greeking: Greeking,
+    lod_tolerance: f64,
+    pixels_per_millimeter: f64,
+    deterministic: bool,
+}
+
+impl From<&RenderOptions<'_>> for EncodedOptions {
+    fn from(o: &RenderOptions<'_>) -> Self {
+        Self {
+            fill_rule: o.fill_rule,
+            text_enabled: o.text_enabled,
+            hinting: o.hinting,
+            greek_threshold: o.greek_threshold,
+            greeking: o.greeking,
+            lod_tolerance: o.lod_tolerance,
+            pixels_per_millimeter: o.pixels_per_millimeter,
+            deterministic: o.deterministic,
+        }
+    }
+}
+
+/// Consecutive items of a fragment, encoded together.
+struct Chunk {
+    /// Range of the items in the fragment.
+    items: Range<usize>,
+    /// Encoded scene, and the revision of the bag when it was encoded.
+    encoded: Option<(RestrokeScene, Revision)>,
+}
+
+/// A render layer encoded for reuse.
+pub(crate) struct Fragment {
+    chunks: Vec<Chunk>,
+    /// Items of the layer, in order.
+    items: Vec<ItemHandle>,
+    /// Scale of the root transform when the fragment was encoded.
+    scale: f64,
+    options: EncodedOptions,
+    /// Revision of the bag when chunks were last checked for changes.
+    checked: Revision,
+}
+
+impl Fragment {
+    /// Check whether the fragment can still draw `render_layer` after updating it.
+    fn can_draw(&self, render_layer: &RenderLayer, scale: f64, options: &EncodedOptions) -> bool {
+        let ratio = scale / self.scale;
+        (1.0 / RESCALE_LIMIT..=RESCALE_LIMIT).contains(&ratio)
+            && self.options == *options
+            && self.items == render_layer.indices
+    }
+}
+
+/// Fragments by their identifiers.
+pub(crate) type Fragments = BTreeMap<FragmentId, Fragment>;
+
+impl Environment {
+    /// Add a [`RenderLayer`] to a Vello [`Scene`] from the fragment cached as `id`.
+    ///
+    /// The fragment is encoded the first time, and again when the layer's items or the
+    /// options change, when the [epoch](GraphicsBag::epoch) of `graphics` changes, or
+    /// when the scale of the root transform has changed by more than a factor of two.
+    /// Otherwise it is appended with the current root transform, and stroke widths from
+    /// the current paints, as by [`RestrokeScene`], and only the chunks of items that
+    /// have changed since, as told by [`GraphicsBag::item_revision`], or whose dashes
+    /// have changed, are encoded again. Changes to [`RenderOptions::overrides`], and to
+    /// [`items`](GraphicsBag::items) made directly without
+    /// [`touch`](GraphicsBag::touch), require [invalidating](Self::invalidate_fragment)
+    /// it.
+    pub fn add_cached_render_layer_to_scene(
+        &mut self,
+        scene: &mut Scene,
+        id: FragmentId,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        options: &RenderOptions<'_>,
+    ) {
+        let scale = uniform_scale(graphics.get_transform(TransformHandle::default()));
+        let encoded_options = EncodedOptions::from(options);
+        let revision = graphics.revision();
+        let reuse = self
+            .fragments
+            .get(&id)
+            .is_some_and(|f| scale > 0.0 && f.can_draw(render_layer, scale, &encoded_options));
+        if !reuse {
+            self.fragments.insert(
+                id,
+                Fragment {
+                    chunks: (0..render_layer.indices.len())
+                        .step_by(CHUNK_ITEMS)
+                        .map(|start| Chunk {
+                            items: start..(start + CHUNK_ITEMS).min(render_layer.indices.len()),
+                            encoded: None,
+                        })
+                        .collect(),
+                    items: render_layer.indices.clone(),
+                    scale,
+                    options: encoded_options,
+                    checked: revision,
+                },
+            );
+        }
+        // The chunks are taken out of the map while encoding, rather than the fragment,
+        // so that panning a fragment that needs no encoding doesn't allocate.
+        let fragment = self.fragments.get_mut(&id).unwrap();
+        let check = fragment.checked != revision;
+        fragment.checked = revision;
+        let mut chunks = core::mem::take(&mut fragment.chunks);
+        let fragment_items = core::mem::take(&mut fragment.items);
+        for chunk in &mut chunks {
+            let items = &fragment_items[chunk.items.clone()];
+            let (encoded, at) = chunk
+                .encoded
+                .take()
+                .filter(|(_, at)| !check || items.iter().all(|i| graphics.item_revision(*i) <= *at))
+                .and_then(|(mut encoded, at)| encoded.restroke(graphics).then_some((encoded, at)))
+                .unwrap_or_else(|| {
+                    (
+                        RestrokeScene::encode(self, graphics, items.iter().copied(), options),
+                        revision,
+                    )
+                });
+            encoded.append_to(scene, graphics);
+            chunk.encoded = Some((encoded, at));
+        }
+        let fragment = self.fragments.get_mut(&id).unwrap();
+        fragment.chunks = chunks;
+        fragment.items = fragment_items;
+    }
+
+    /// Drop the fragment cached as `id`, so it is encoded again when next added.
+    pub fn invalidate_fragment(&mut self, id: FragmentId) {
+        self.fragments.remove(&id);
+    }
+
+    /// Drop all cached fragments.
+    ///
+    /// This should be called when switching to a different [`GraphicsBag`] to release
+    /// memory held for the old one.
+    pub fn clear_fragments(&mut self) {
+        self.fragments.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::{
+        peniko::{
+            Color,
+            kurbo::{Affine, Line, Stroke},
+        },
+        shape::{FatPaint, FatShape},
+    };
+
+    use alloc::sync::Arc;
+
+    #[test]
+    fn fragments_follow_view() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                shape: Arc::new(Line::new((0.0, 0.0), (10.0, 0.0)).into()),
+                ..Default::default()
+            },
+        );
+
+        let mut env = Environment::default();
+        let id = FragmentId(1);
+        let options = RenderOptions::default();
+        let add = |env: &mut Environment, graphics: &GraphicsBag| {
+            let mut scene = Scene::new();
+            env.add_cached_render_layer_to_scene(&mut scene, id, graphics, &layer, &options);
+            env.fragments[&id].scale
+        };
+        add(&mut env, &graphics);
+        graphics.update_transform(Default::default(), Affine::translate((5.0, 5.0)));
+        assert_eq!(
+            add(&mut env, &graphics),
+            1.0,
+            "Panning should reuse the fragment."
+        );
+        graphics.update_transform(Default::default(), Affine::scale(4.0));
+        assert_eq!(
+            add(&mut env, &graphics),
+            4.0,
+            "Zooming in far should encode the fragment again."
+        );
+
+        env.invalidate_fragment(id);
+        assert!(
+            env.fragments.is_empty(),
+            "Invalidated fragments should be dropped."
+        );
+    }
+
+    #[test]
+    fn only_changed_chunks_are_encoded() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let paints: Vec<_> = (0..=CHUNK_ITEMS)
+            .map(|i| {
+                let paint = graphics.register_paint(FatPaint {
+                    stroke: Stroke::new(1.0),
+                    stroke_paint: Some(Color::BLACK.into()),
+                    ..Default::default()
+                });
+                let y = i as f64;
+                layer.push_with_bag(
+                    &mut graphics,
+                    FatShape {
+                        paint,
+                        shape: Arc::new(Line::new((0.0, y), (10.0, y)).into()),
+                        ..Default::default()
+                    },
+                );
+                paint
+            })
+            .collect();
+
+        let mut env = Environment::default();
+        let id = FragmentId(1);
+        let encoded_at = |env: &Environment| -> Vec<Revision> {
+            env.fragments[&id]
+                .chunks
+                .iter()
+                .map(|c| c.encoded.as_ref().unwrap().1)
+                .collect()
+        };
+        let mut scene = Scene::new();
+        let options = RenderOptions::default();
+        env.add_cached_render_layer_to_scene(&mut scene, id, &graphics, &layer, &options);
+        let first = encoded_at(&env);
+        assert_eq!(first.len(), 2, "Items past a chunk should start another.");
+
+        graphics.get_paint_mut(paints[CHUNK_ITEMS]).stroke_paint = Some(Color::WHITE.into());
+        let mut edited = Scene::new();
+        env.add_cached_render_layer_to_scene(&mut edited, id, &graphics, &layer, &options);
+        let second = encoded_at(&env);
+        assert!(
+            second[0] == first[0] && second[1] > first[1],
+            "Only the chunk with the changed item should be encoded again."
+        );
+        assert_eq!(
+            edited.encoding().path_data,
+            scene.encoding().path_data,
+            "Reusing chunks should keep the geometry."
+        );
+    }
+}
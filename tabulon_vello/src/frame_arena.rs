@@ -0,0 +1,96 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Bump allocation of buffers that only live while a scene is encoded.
+//!
+//! Encoding converts every primitive shape to path elements, and collects other short
+//! lived lists along the way. Allocating these from the global allocator for every item
+//! of every frame adds up on big drawings, so they are allocated from an arena instead,
+//! which is reset before each encoding. Resetting keeps the largest block of memory, so
+//! after the first few frames, encoding doesn't allocate these at all.
+
+use bumpalo::{Bump, collections::Vec as BumpVec};
+use tabulon::{
+    peniko::kurbo::{PathEl, Shape},
+    shape::AnyShape,
+};
+
+/// Arena for transient buffers of scene encoding.
+#[derive(Default)]
+pub(crate) struct FrameArena {
+    bump: Bump,
+}
+
+impl FrameArena {
+    /// Free everything allocated in the arena, keeping its largest block for reuse.
+    pub(crate) fn reset(&mut self) {
+        self.bump.reset();
+    }
+
+    /// Get the path elements of `shape`, borrowing them if it is already a path, and
+    /// allocating them in the arena otherwise.
+    pub(crate) fn path<'a>(&'a self, shape: &'a AnyShape, tolerance: f64) -> &'a [PathEl] {
+        match shape {
+            AnyShape::BezPath(path) => path.elements(),
+            s => BumpVec::from_iter_in(s.path_elements(tolerance), &self.bump).into_bump_slice(),
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Make an empty vector in the arena.
+    pub(crate) fn vec<T>(&self) -> BumpVec<'_, T> {
+        BumpVec::new_in(&self.bump)
+    }
+
+    /// Get the number of bytes of memory held by the arena.
+    #[cfg(test)]
+    fn allocated_bytes(&self) -> usize {
+        self.bump.allocated_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::peniko::kurbo::{BezPath, Circle};
+
+    #[test]
+    fn reused_after_reset() {
+        let mut arena = FrameArena::default();
+        let circle = AnyShape::from(Circle::new((0.0, 0.0), 10.0));
+        let expected = Circle::new((0.0, 0.0), 10.0).to_path(0.1);
+        assert_eq!(
+            arena.path(&circle, 0.1),
+            expected.elements(),
+            "Primitives should be converted to the same elements as paths."
+        );
+        let path = AnyShape::from(BezPath::from_vec(expected.elements().to_vec()));
+        let AnyShape::BezPath(ref inner) = path else {
+            unreachable!();
+        };
+        assert_eq!(
+            arena.path(&path, 0.1).as_ptr(),
+            inner.elements().as_ptr(),
+            "Paths should be borrowed rather than copied."
+        );
+
+        let frame = |arena: &mut FrameArena| {
+            arena.reset();
+            for _ in 0..1000 {
+                arena.path(&circle, 0.1);
+            }
+        };
+        for _ in 0..4 {
+            frame(&mut arena);
+        }
+        let allocated = arena.allocated_bytes();
+        for _ in 0..10 {
+            frame(&mut arena);
+        }
+        assert_eq!(
+            arena.allocated_bytes(),
+            allocated,
+            "Arenas should not grow when reset between frames."
+        );
+    }
+}
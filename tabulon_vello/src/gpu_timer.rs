@@ -0,0 +1,205 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Measuring how long frames take on the GPU.
+
+use core::time::Duration;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU8, AtomicU64, Ordering},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use vello::wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Device, Features, MapMode, QuerySet,
+    QuerySetDescriptor, QueryType, Queue,
+};
+
+/// No measurement has finished yet.
+const PENDING: u8 = 0;
+/// A measurement finished.
+const DONE: u8 = 1;
+/// A measurement could not be read back.
+const FAILED: u8 = 2;
+
+/// Timestamp queries and the buffers to read them back with.
+struct Timestamps {
+    query_set: QuerySet,
+    resolve: Buffer,
+    readback: Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f64,
+}
+
+/// Measures how long the GPU takes to render frames, to feed a
+/// [`QualityController`](crate::QualityController).
+///
+/// When the device has [`Features::TIMESTAMP_QUERY`] and
+/// [`Features::TIMESTAMP_QUERY_INSIDE_ENCODERS`], timestamps are written before and
+/// after the frame's commands. Otherwise, the time from [`begin`](Self::begin) until
+/// the queue finishes the frame's work is measured instead, which also includes the
+/// time spent encoding the frame. [`Instant`](std::time::Instant) panics on `wasm32`,
+/// so frames are only measured with timestamps there.
+///
+/// One frame is measured at a time, and frames that start while a measurement is in
+/// flight are not measured. Measurements finish as the device is polled.
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and wgpu resources don't implement Debug usefully."
+)]
+pub struct GpuTimer {
+    timestamps: Option<Timestamps>,
+    /// When the frame being measured began, when measuring without timestamps.
+    #[cfg(not(target_arch = "wasm32"))]
+    started: Option<Instant>,
+    /// Whether the current frame is being measured.
+    measuring: bool,
+    /// Whether a measurement has been submitted and not yet read.
+    in_flight: bool,
+    /// State of the measurement in flight.
+    status: Arc<AtomicU8>,
+    /// Nanoseconds measured without timestamps.
+    elapsed: Arc<AtomicU64>,
+}
+
+impl GpuTimer {
+    /// Create a timer for frames rendered on `device`, submitted to `queue`.
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let required = Features::TIMESTAMP_QUERY | Features::TIMESTAMP_QUERY_INSIDE_ENCODERS;
+        let timestamps = device.features().contains(required).then(|| {
+            let size = 2 * size_of::<u64>() as u64;
+            Timestamps {
+                query_set: device.create_query_set(&QuerySetDescriptor {
+                    label: Some("GpuTimer"),
+                    ty: QueryType::Timestamp,
+                    count: 2,
+                }),
+                resolve: device.create_buffer(&BufferDescriptor {
+                    label: Some("GpuTimer resolve"),
+                    size,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback: device.create_buffer(&BufferDescriptor {
+                    label: Some("GpuTimer readback"),
+                    size,
+                    usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                period: f64::from(queue.get_timestamp_period()),
+            }
+        });
+        Self {
+            timestamps,
+            #[cfg(not(target_arch = "wasm32"))]
+            started: None,
+            measuring: false,
+            in_flight: false,
+            status: Arc::new(AtomicU8::new(PENDING)),
+            elapsed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Check whether frames are measured with timestamp queries.
+    pub fn uses_timestamps(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
+    /// Begin measuring a frame, with `encoder` submitted before the frame's commands.
+    pub fn begin(&mut self, encoder: &mut CommandEncoder) {
+        self.measuring =
+            !self.in_flight && (self.uses_timestamps() || cfg!(not(target_arch = "wasm32")));
+        if !self.measuring {
+            return;
+        }
+        match &self.timestamps {
+            Some(t) => encoder.write_timestamp(&t.query_set, 0),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => self.started = Some(Instant::now()),
+            #[cfg(target_arch = "wasm32")]
+            None => {}
+        }
+    }
+
+    /// End measuring a frame, with `encoder` submitted after the frame's commands.
+    pub fn end(&mut self, encoder: &mut CommandEncoder) {
+        if let (true, Some(t)) = (self.measuring, &self.timestamps) {
+            encoder.write_timestamp(&t.query_set, 1);
+            encoder.resolve_query_set(&t.query_set, 0..2, &t.resolve, 0);
+            encoder.copy_buffer_to_buffer(&t.resolve, 0, &t.readback, 0, t.resolve.size());
+        }
+    }
+
+    /// Start reading the measurement back, after submitting the encoder passed to
+    /// [`end`](Self::end) to `queue`.
+    #[cfg_attr(
+        target_arch = "wasm32",
+        expect(
+            unused_variables,
+            reason = "The queue is only used without timestamps."
+        )
+    )]
+    pub fn submitted(&mut self, queue: &Queue) {
+        if !self.measuring {
+            return;
+        }
+        self.measuring = false;
+        self.in_flight = true;
+        self.status.store(PENDING, Ordering::Release);
+        let status = self.status.clone();
+        match &self.timestamps {
+            Some(t) => t
+                .readback
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    status.store(
+                        if result.is_ok() { DONE } else { FAILED },
+                        Ordering::Release,
+                    );
+                }),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => {
+                let started = self.started.take().unwrap_or_else(Instant::now);
+                let elapsed = self.elapsed.clone();
+                queue.on_submitted_work_done(move || {
+                    let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+                    elapsed.store(nanos, Ordering::Relaxed);
+                    status.store(DONE, Ordering::Release);
+                });
+            }
+            // Frames are not measured without timestamps on `wasm32`.
+            #[cfg(target_arch = "wasm32")]
+            None => {}
+        }
+    }
+
+    /// Get the last measurement, if one has finished since this was last called.
+    pub fn poll(&mut self) -> Option<Duration> {
+        if !self.in_flight {
+            return None;
+        }
+        let status = self.status.load(Ordering::Acquire);
+        if status == PENDING {
+            return None;
+        }
+        self.in_flight = false;
+        match &self.timestamps {
+            Some(t) if status == DONE => {
+                let ticks = {
+                    let range = t.readback.slice(..).get_mapped_range();
+                    let [start, end] =
+                        [0, 8].map(|i| u64::from_le_bytes(range[i..i + 8].try_into().unwrap()));
+                    end.saturating_sub(start)
+                };
+                t.readback.unmap();
+                #[allow(clippy::cast_possible_truncation, reason = "Frames take under a year.")]
+                #[allow(clippy::cast_sign_loss, reason = "Tick periods are positive.")]
+                Some(Duration::from_nanos((ticks as f64 * t.period) as u64))
+            }
+            Some(_) => None,
+            None => Some(Duration::from_nanos(self.elapsed.load(Ordering::Relaxed))),
+        }
+    }
+}
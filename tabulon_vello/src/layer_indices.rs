@@ -0,0 +1,131 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Indices of render layers kept in the [`Environment`](crate::Environment) between frames.
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use tabulon::{
+    GraphicsBag, ItemHandle,
+    render_layer::{LayerKinds, RenderLayer},
+    spatial_index::SpatialIndex,
+};
+
+/// Number of layers whose indices are kept.
+///
+/// This is enough for the layer stacks of most drawings to be drawn culled without
+/// building indices every frame.
+const KEPT_INDICES: usize = 64;
+
+/// An index built from the items of a render layer.
+pub(crate) trait LayerIndex {
+    /// Build the index of `render_layer`.
+    fn build(graphics: &GraphicsBag, render_layer: &RenderLayer) -> Self;
+    /// Check whether the index was built from the items `graphics` has now.
+    fn is_current(&self, graphics: &GraphicsBag) -> bool;
+}
+
+impl LayerIndex for SpatialIndex {
+    fn build(graphics: &GraphicsBag, render_layer: &RenderLayer) -> Self {
+        Self::new(graphics, render_layer)
+    }
+
+    fn is_current(&self, graphics: &GraphicsBag) -> bool {
+        self.is_current(graphics)
+    }
+}
+
+impl LayerIndex for LayerKinds {
+    fn build(graphics: &GraphicsBag, render_layer: &RenderLayer) -> Self {
+        Self::new(graphics, render_layer)
+    }
+
+    fn is_current(&self, graphics: &GraphicsBag) -> bool {
+        self.is_current(graphics)
+    }
+}
+
+/// Indices of the layers most recently used, the latest last.
+///
+/// Layers are recognized by their items, so an index is built again when a layer's
+/// items change, or when the [epoch](GraphicsBag::epoch) of the bag changes.
+pub(crate) struct LayerIndices<T> {
+    pub(crate) indices: Vec<(Vec<ItemHandle>, T)>,
+}
+
+impl<T> Default for LayerIndices<T> {
+    fn default() -> Self {
+        Self {
+            indices: Vec::new(),
+        }
+    }
+}
+
+impl<T: LayerIndex> LayerIndices<T> {
+    /// Get an index of `render_layer` that is current for `graphics`, building it if needed.
+    pub(crate) fn get(&mut self, graphics: &GraphicsBag, render_layer: &RenderLayer) -> &T {
+        let found = self
+            .indices
+            .iter()
+            .position(|(items, _)| *items == render_layer.indices);
+        let entry = match found {
+            Some(i) if self.indices[i].1.is_current(graphics) => self.indices.remove(i),
+            found => {
+                if let Some(i) = found {
+                    self.indices.remove(i);
+                } else if self.indices.len() >= KEPT_INDICES {
+                    self.indices.remove(0);
+                }
+                (
+                    render_layer.indices.clone(),
+                    T::build(graphics, render_layer),
+                )
+            }
+        };
+        self.indices.push(entry);
+        &self.indices.last().unwrap().1
+    }
+
+    /// Drop all kept indices.
+    pub(crate) fn clear(&mut self) {
+        self.indices.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::{ItemKind, shape::FatShape};
+
+    #[test]
+    fn rebuilt_when_stale() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(&mut graphics, FatShape::default());
+
+        let mut kinds = LayerIndices::<LayerKinds>::default();
+        assert_eq!(
+            kinds.get(&graphics, &layer).get(ItemKind::Shape).len(),
+            1,
+            "Indices should be built for new layers."
+        );
+        layer.push_with_bag(&mut graphics, FatShape::default());
+        assert_eq!(
+            kinds.get(&graphics, &layer).get(ItemKind::Shape).len(),
+            2,
+            "Indices should be built again when items are added."
+        );
+
+        for _ in 0..KEPT_INDICES {
+            let mut other = RenderLayer::default();
+            other.push_with_bag(&mut graphics, FatShape::default());
+            kinds.get(&graphics, &other);
+        }
+        assert_eq!(
+            kinds.indices.len(),
+            KEPT_INDICES,
+            "Only the most recent layers should be kept."
+        );
+    }
+}
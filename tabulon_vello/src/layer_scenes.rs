@@ -0,0 +1,237 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Separately encoded scenes for the layers of a [`LayerStack`].
+
+use tabulon::{
+    GraphicsBag, ItemHandle,
+    graphics_bag::Revision,
+    layer_stack::{LayerStack, StackedLayerHandle},
+    peniko::{Mix, kurbo::Affine},
+};
+use vello::Scene;
+
+use crate::{Environment, RenderOptions, UNCLIPPED};
+
+/// An encoded layer.
+struct EncodedLayer {
+    scene: Scene,
+    /// Items of the layer, in order.
+    items: Vec<ItemHandle>,
+    /// Revision of the bag when the layer was encoded.
+    at: Revision,
+    /// Revision of the bag when the items were last checked for changes.
+    checked: Revision,
+}
+
+/// Scenes encoded separately for each layer of a [`LayerStack`], then composited.
+///
+/// Each layer is encoded the first time it is composited, and reused until it is
+/// [invalidated](Self::invalidate). This lets static layers, such as the drawing or
+/// background tiles, be kept while overlays that change often, such as highlights,
+/// are re-encoded on their own.
+///
+/// Visibility, opacity, and z order are applied while compositing, so changing them
+/// does not require encoding a layer again. A layer is encoded again automatically when
+/// its items change, or when any of them has changed since, as told by
+/// [`GraphicsBag::item_revision`], so adding items to an overlay only encodes the
+/// overlay again. Changes to the root transform, to [`RenderOptions::overrides`], and
+/// to [`items`](GraphicsBag::items) made directly require invalidating layers.
+#[derive(Default)]
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and members don't implement Debug."
+)]
+pub struct LayerScenes {
+    /// Encoded layers, indexed by [`StackedLayerHandle`].
+    layers: Vec<Option<EncodedLayer>>,
+}
+
+impl LayerScenes {
+    /// Mark a layer for encoding again the next time it is composited.
+    pub fn invalidate(&mut self, handle: StackedLayerHandle) {
+        if let Some(l) = self.layers.get_mut(usize::from(handle)) {
+            *l = None;
+        }
+    }
+
+    /// Mark every layer for encoding again, such as when the view transform changes.
+    pub fn invalidate_all(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Check whether a layer has an encoded scene that is ready to be composited.
+    pub fn is_encoded(&self, handle: StackedLayerHandle) -> bool {
+        matches!(self.layers.get(usize::from(handle)), Some(Some(_)))
+    }
+
+    /// Composite the visible layers of a [`LayerStack`] into `scene`.
+    ///
+    /// Layers that are not encoded yet are encoded with `env` first. The result is
+    /// the same as [`Environment::add_layer_stack_to_scene_with_options`].
+    #[tracing::instrument(skip_all)]
+    pub fn compose(
+        &mut self,
+        env: &mut Environment,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        options: &RenderOptions<'_>,
+    ) {
+        for handle in stack.visible_handles() {
+            let l = &stack.layers[usize::from(handle)];
+            if l.opacity <= 0.0 || l.layer.indices.is_empty() {
+                continue;
+            }
+            let i = usize::from(handle);
+            if self.layers.len() <= i {
+                self.layers.resize_with(i + 1, || None);
+            }
+            let revision = graphics.revision();
+            if let Some(e) = &mut self.layers[i] {
+                let stale = e.items != l.layer.indices
+                    || (e.checked != revision
+                        && e.items.iter().any(|i| graphics.item_revision(*i) > e.at));
+                if stale {
+                    self.layers[i] = None;
+                } else {
+                    e.checked = revision;
+                }
+            }
+            let encoded = self.layers[i].get_or_insert_with(|| {
+                let mut scene = Scene::new();
+                env.add_render_layer_to_scene_with_options(&mut scene, graphics, &l.layer, options);
+                EncodedLayer {
+                    scene,
+                    items: l.layer.indices.clone(),
+                    at: revision,
+                    checked: revision,
+                }
+            });
+            if l.opacity < 1.0 {
+                scene.push_layer(Mix::Normal, l.opacity, Affine::IDENTITY, &UNCLIPPED);
+                scene.append(&encoded.scene, None);
+                scene.pop_layer();
+            } else {
+                scene.append(&encoded.scene, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::{
+        GraphicsItem,
+        layer_stack::StackedLayer,
+        peniko::{Color, kurbo::Rect},
+        render_layer::RenderLayer,
+        shape::{FatPaint, FatShape},
+    };
+
+    extern crate alloc;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn layers_are_encoded_once() {
+        let mut graphics = GraphicsBag::default();
+        let mut stack = LayerStack::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                shape: Arc::new(Rect::new(0.0, 0.0, 10.0, 10.0).into()),
+                ..Default::default()
+            },
+        );
+        let drawing = stack.push(layer);
+        let overlay = stack.push(StackedLayer {
+            opacity: 0.5,
+            ..Default::default()
+        });
+
+        let mut env = Environment::default();
+        let mut scenes = LayerScenes::default();
+        let mut scene = Scene::new();
+        scenes.compose(
+            &mut env,
+            &mut scene,
+            &graphics,
+            &stack,
+            &RenderOptions::default(),
+        );
+        assert!(
+            scenes.is_encoded(drawing) && !scenes.is_encoded(overlay),
+            "Visible layers with items should be encoded."
+        );
+        let encoded_len = scene.encoding().path_tags.len();
+
+        scenes.invalidate(overlay);
+        scene.reset();
+        scenes.compose(
+            &mut env,
+            &mut scene,
+            &graphics,
+            &stack,
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            scene.encoding().path_tags.len(),
+            encoded_len,
+            "Composing again should reuse the encoded layer unchanged."
+        );
+
+        let encoded_at =
+            |scenes: &LayerScenes| scenes.layers[usize::from(drawing)].as_ref().map(|e| e.at);
+        let drawn_at = encoded_at(&scenes);
+        stack.layers[usize::from(overlay)].layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                shape: Arc::new(Rect::new(20.0, 0.0, 30.0, 10.0).into()),
+                ..Default::default()
+            },
+        );
+        scenes.compose(
+            &mut env,
+            &mut scene,
+            &graphics,
+            &stack,
+            &RenderOptions::default(),
+        );
+        assert!(
+            scenes.is_encoded(overlay) && encoded_at(&scenes) == drawn_at,
+            "Adding an item to the overlay should encode only the overlay."
+        );
+
+        if let Some(GraphicsItem::FatShape(s)) =
+            graphics.get_mut(stack.layers[usize::from(drawing)].layer.indices[0])
+        {
+            s.shape = Arc::new(Rect::new(0.0, 0.0, 5.0, 5.0).into());
+        }
+        scenes.compose(
+            &mut env,
+            &mut scene,
+            &graphics,
+            &stack,
+            &RenderOptions::default(),
+        );
+        assert_ne!(
+            encoded_at(&scenes),
+            drawn_at,
+            "Changing an item should encode its layer again."
+        );
+
+        scenes.invalidate(drawing);
+        assert!(
+            !scenes.is_encoded(drawing),
+            "Invalidated layers should be encoded again."
+        );
+    }
+}
@@ -2,23 +2,210 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 //! Vello rendering utilities for Tabulon.
+//!
+//! ## Features
+//!
+//! - `std` (enabled by default): Use the standard library, and time GPU work with [`GpuTimer`].
+//! - `libm`: Use floating point implementations from libm.
+//! - `text` (enabled by default): Shape and draw text items with Parley. Without it,
+//!   Parley is not compiled, and only shapes, images, and markers are drawn.
 
 use tabulon::{
-    DirectIsometry, GraphicsBag, GraphicsItem, ItemHandle,
+    GraphicsBag, GraphicsItem, ItemHandle, PaintHandle,
+    bounds::TextMeasurer,
+    image::FatImage,
+    layer_stack::LayerStack,
     peniko::{
-        Color, Fill,
-        kurbo::{Affine, Size, Vec2},
+        Brush, Fill, Mix,
+        kurbo::{Affine, Rect, Shape, Stroke},
     },
-    render_layer::RenderLayer,
-    shape::{FatPaint, FatShape},
-    text::{AttachmentPoint, FatText},
+    render_layer::{LayerKinds, RenderLayer},
+    shape::{DashUnits, FatClip, FatPaint, FatShape},
+    spatial_index::SpatialIndex,
+    uniform_scale,
 };
 
-use parley::{FontContext, LayoutContext, PositionedLayoutItem};
-use vello::{Scene, peniko::Fill::NonZero};
+use vello::Scene;
+use vello_encoding::Encoding;
 
 extern crate alloc;
-use alloc::collections::BTreeMap;
+
+#[cfg(feature = "text")]
+use {
+    alloc::collections::BTreeMap,
+    parley::{FontContext, LayoutContext},
+    tabulon::{
+        DirectIsometry, ItemKind,
+        peniko::{
+            BrushRef, Color,
+            kurbo::{BezPath, Size, Vec2},
+        },
+        text::{AttachmentPoint, FatText},
+        text_on_path::{FatTextOnPath, MeasuredPath},
+    },
+    vello::peniko::Fill::NonZero,
+};
+
+mod culling;
+
+#[cfg(feature = "text")]
+mod fonts;
+
+mod fragments;
+pub use fragments::FragmentId;
+use fragments::Fragments;
+
+mod frame_arena;
+use frame_arena::FrameArena;
+
+#[cfg(feature = "std")]
+mod gpu_timer;
+#[cfg(feature = "std")]
+pub use gpu_timer::GpuTimer;
+
+mod layer_indices;
+use layer_indices::LayerIndices;
+
+mod layer_scenes;
+pub use layer_scenes::LayerScenes;
+
+mod lod_cache;
+use lod_cache::LodCache;
+
+#[cfg(feature = "text")]
+mod outline;
+
+mod overrides;
+use overrides::NO_OVERRIDES;
+pub use overrides::StyleOverrides;
+
+mod quality;
+pub use quality::{DEFAULT_LEVELS, Quality, QualityController};
+
+mod raster;
+pub use raster::RasterOptions;
+
+mod restroke;
+use restroke::EncodedStroke;
+pub use restroke::RestrokeScene;
+
+mod scene_renderer;
+pub use scene_renderer::SceneRenderer;
+
+mod stats;
+pub use stats::EncodeStats;
+use stats::{Stopwatch, count_segments};
+
+mod texture;
+#[cfg(feature = "text")]
+use stats::timed;
+use texture::TextureRenderer;
+
+#[cfg(feature = "text")]
+mod text_cache;
+#[cfg(feature = "text")]
+use text_cache::{PreparedRun, TextCache};
+
+#[cfg(feature = "text")]
+mod text_measure;
+#[cfg(feature = "text")]
+pub use text_measure::{TextBox, TextMeasurement};
+
+/// Tolerance for converting shapes to paths, in device pixels.
+const SHAPE_TOLERANCE: f64 = 0.1;
+
+/// Clip for compositing a layer as a group when nothing should be clipped, larger than
+/// any render target.
+///
+/// Bounds measured from the items of a layer can be smaller than what they draw, such
+/// as for text measured by estimate, markers sized in pixels, and strokes widened by
+/// overrides, and measuring them is a pass over every item.
+const UNCLIPPED: Rect = Rect::new(-1e7, -1e7, 1e7, 1e7);
+
+/// How text below the [greeking threshold](RenderOptions::greek_threshold) is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Greeking {
+    /// A bar through the middle of each line, like a strikeout.
+    #[default]
+    Bar,
+    /// The outline of the text's bounding box.
+    Box,
+}
+
+/// Options for adding a [`RenderLayer`] to a [`Scene`].
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions<'a> {
+    /// Fill rule for filling shapes whose paints do not have one.
+    pub fill_rule: Fill,
+    /// Whether to draw text items.
+    ///
+    /// Disabling text skips shaping entirely, which is useful for thumbnails and for
+    /// keeping interaction responsive in drawings with a lot of text.
+    pub text_enabled: bool,
+    /// Whether to hint glyph outlines.
+    ///
+    /// Hinting makes small text sharper, but glyphs move when the view is scaled.
+    pub hinting: bool,
+    /// Projected font size, in device pixels, below which text is greeked.
+    ///
+    /// Greeked text is replaced with a simple placeholder instead of being shaped and
+    /// drawn, which keeps the cost of text bounded when zoomed far out.
+    /// Zero disables greeking.
+    pub greek_threshold: f64,
+    /// How greeked text is drawn.
+    pub greeking: Greeking,
+    /// Tolerance, in device pixels, for drawing long paths simplified.
+    ///
+    /// Detail smaller than this is left out of paths with many segments, using
+    /// [levels of detail](tabulon::simplify::PathLod) that are built the first time
+    /// each path is drawn simplified. Zero disables simplification.
+    pub lod_tolerance: f64,
+    /// Pixel density of the display, for sizing [markers](tabulon::marker::FatMarker)
+    /// in physical units.
+    pub pixels_per_millimeter: f64,
+    /// Units of the dash patterns of strokes.
+    ///
+    /// Dashes are drawn by Vello, which splits dashed strokes into their dashes as they
+    /// are encoded. With [`DashUnits::Device`], zooming changes the dashes, so a
+    /// [`RestrokeScene`] with dashed strokes must be encoded again.
+    pub dash_units: DashUnits,
+    /// Paints to draw with instead of those in the [`GraphicsBag`].
+    pub overrides: &'a StyleOverrides,
+    /// Whether the scene should depend only on the items, their transforms, and the
+    /// settings that determine appearance.
+    ///
+    /// This pins [`lod_tolerance`](Self::lod_tolerance) and
+    /// [`greek_threshold`](Self::greek_threshold) at zero, so that paths are flattened
+    /// with fixed tolerances and all text is drawn, whatever a [`QualityController`] or
+    /// other tuning for interactive use has set. Together with the ordered iteration used
+    /// throughout Tabulon, this makes exports of the same input byte-identical across
+    /// runs, as needed for snapshot tests.
+    pub deterministic: bool,
+    /// Whether to count path segments and measure time in the [`EncodeStats`] returned.
+    ///
+    /// Items are always counted, but the rest costs a walk over every path drawn and
+    /// reading the clock for every text item, so it is off unless asked for.
+    pub collect_stats: bool,
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        Self {
+            fill_rule: Fill::NonZero,
+            text_enabled: true,
+            hinting: false,
+            greek_threshold: 0.0,
+            greeking: Greeking::Bar,
+            lod_tolerance: 0.0,
+            // The CSS reference density of 96 pixels per inch.
+            pixels_per_millimeter: 96.0 / 25.4,
+            dash_units: DashUnits::Local,
+            overrides: &NO_OVERRIDES,
+            deterministic: false,
+            collect_stats: false,
+        }
+    }
+}
 
 /// Expensive state for rendering.
 #[derive(Default)]
@@ -30,163 +217,552 @@ pub struct Environment {
     /// Font context.
     ///
     /// This contains a font collection that is expensive to reproduce.
+    #[cfg(feature = "text")]
     pub(crate) font_cx: FontContext,
     /// Layout context.
+    #[cfg(feature = "text")]
     pub(crate) layout_cx: LayoutContext<Option<Color>>,
+    /// Shaped text for text items.
+    #[cfg(feature = "text")]
+    text_cache: TextCache,
+    /// Simplified paths for shapes.
+    lod_cache: LodCache,
+    /// Encoded render layers.
+    fragments: Fragments,
+    /// Transient buffers of the scene being encoded.
+    frame_arena: FrameArena,
+    /// Spatial indices of layers drawn culled.
+    cull_indices: LayerIndices<SpatialIndex>,
+    /// Items of layers partitioned by kind.
+    layer_kinds: LayerIndices<LayerKinds>,
+    /// Renderer for rendering layers to textures.
+    texture_renderer: Option<TextureRenderer>,
 }
 
 impl Environment {
     /// Add a [`RenderLayer`] to a Vello [`Scene`].
-    #[tracing::instrument(skip_all)]
+    ///
+    /// Returns what was encoded, see [`EncodeStats`].
     pub fn add_render_layer_to_scene(
         &mut self,
         scene: &mut Scene,
         graphics: &GraphicsBag,
         render_layer: &RenderLayer,
-    ) {
-        let Self { font_cx, layout_cx } = self;
+    ) -> EncodeStats {
+        self.add_render_layer_to_scene_with_options(
+            scene,
+            graphics,
+            render_layer,
+            &RenderOptions::default(),
+        )
+    }
 
-        for idx in &render_layer.indices {
-            if let Some(ref gi) = graphics.get(*idx) {
-                match gi {
-                    GraphicsItem::FatShape(FatShape {
-                        paint,
-                        transform,
-                        path,
-                    }) => {
-                        let transform = graphics.get_transform(*transform);
-                        let FatPaint {
+    /// Add a [`RenderLayer`] to a Vello [`Scene`] with [`RenderOptions`].
+    ///
+    /// Returns what was encoded, see [`EncodeStats`].
+    pub fn add_render_layer_to_scene_with_options(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        options: &RenderOptions<'_>,
+    ) -> EncodeStats {
+        self.add_items_to_scene(
+            scene,
+            graphics,
+            render_layer.indices.iter().copied(),
+            options,
+        )
+    }
+
+    /// Add [`GraphicsItem`]s to a Vello [`Scene`] with [`RenderOptions`], in iteration order.
+    ///
+    /// This accepts [`RenderLayer::iter_filtered`] directly, so culled subsets of a layer
+    /// can be drawn without collecting them first.
+    ///
+    /// Returns what was encoded, see [`EncodeStats`].
+    #[tracing::instrument(skip_all)]
+    pub fn add_items_to_scene(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        items: impl IntoIterator<Item = ItemHandle>,
+        options: &RenderOptions<'_>,
+    ) -> EncodeStats {
+        self.encode_items(scene, graphics, items, options, &mut None)
+    }
+
+    /// Encode items into `scene`, recording the strokes of paints in `strokes` if given.
+    fn encode_items(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        items: impl IntoIterator<Item = ItemHandle>,
+        options: &RenderOptions<'_>,
+        strokes: &mut Option<&mut Vec<EncodedStroke>>,
+    ) -> EncodeStats {
+        let stopwatch = Stopwatch::start(options.collect_stats);
+        let mut stats = EncodeStats::default();
+        let Self {
+            #[cfg(feature = "text")]
+            font_cx,
+            #[cfg(feature = "text")]
+            layout_cx,
+            #[cfg(feature = "text")]
+            text_cache,
+            lod_cache,
+            fragments: _,
+            frame_arena,
+            cull_indices: _,
+            layer_kinds: _,
+            texture_renderer: _,
+        } = self;
+        frame_arena.reset();
+        let pinned = RenderOptions {
+            lod_tolerance: 0.0,
+            greek_threshold: 0.0,
+            ..*options
+        };
+        let options = if options.deterministic {
+            &pinned
+        } else {
+            options
+        };
+
+        for idx in items {
+            let Some(gi) = graphics.get(idx) else {
+                stats.skipped_missing += 1;
+                continue;
+            };
+            match gi {
+                GraphicsItem::FatShape(FatShape {
+                    paint,
+                    transform,
+                    shape,
+                    clip,
+                }) => {
+                    let transform = graphics.get_transform(*transform);
+                    let scale = uniform_scale(transform).max(f64::EPSILON);
+                    // Primitives are converted to paths in local coordinates, so the
+                    // tolerance is scaled to keep them accurate in device pixels.
+                    let mut path = frame_arena.path(shape, SHAPE_TOLERANCE / scale);
+                    if options.lod_tolerance > 0.0 {
+                        if let Some(simplified) =
+                            lod_cache.select(shape, options.lod_tolerance / scale)
+                        {
+                            path = simplified.elements();
+                        }
+                    }
+                    let FatPaint {
+                        stroke,
+                        stroke_paint,
+                        fill_paint,
+                        fill_rule,
+                    } = options.overrides.paint(graphics, *paint);
+                    if fill_paint.is_none() && stroke_paint.is_none() {
+                        stats.skipped_empty += 1;
+                        continue;
+                    }
+                    if options.collect_stats {
+                        stats.path_segments += count_segments(path);
+                    }
+
+                    let clip = graphics.get_clip(*clip);
+                    if let Some(FatClip { transform, path }) = clip {
+                        scene.push_layer(
+                            Mix::Clip,
+                            1.0,
+                            graphics.get_transform(*transform),
+                            path.as_ref(),
+                        );
+                    }
+                    if let Some(fill_paint) = fill_paint {
+                        scene.fill(
+                            fill_rule.unwrap_or(options.fill_rule),
+                            transform,
+                            fill_paint,
+                            None,
+                            &path,
+                        );
+                    }
+                    if let Some(stroke_paint) = stroke_paint {
+                        stroke_shape(
+                            scene,
+                            strokes,
+                            options,
+                            *paint,
                             stroke,
+                            transform,
                             stroke_paint,
+                            &path,
+                        );
+                    }
+                    if clip.is_some() {
+                        scene.pop_layer();
+                    }
+                }
+                #[cfg(feature = "text")]
+                GraphicsItem::FatText(t) => {
+                    if !options.text_enabled {
+                        stats.skipped_text += 1;
+                        continue;
+                    }
+                    let FatText {
+                        transform, paint, ..
+                    } = &**t;
+                    let transform = graphics.get_transform(*transform);
+
+                    let FatPaint {
+                        fill_paint: Some(fill_paint),
+                        ..
+                    } = options.overrides.paint(graphics, *paint)
+                    else {
+                        stats.skipped_empty += 1;
+                        continue;
+                    };
+                    let overridden = options.overrides.overrides_paint(*paint);
+
+                    let scale = transform.determinant().abs().sqrt();
+                    let projected_size = t.font_size().unwrap_or_default() as f64 * scale;
+                    if projected_size < options.greek_threshold {
+                        // Use the real size if the text has already been shaped.
+                        let (size, lines) = match text_cache.peek(Some(graphics), idx, t) {
+                            Some(shaped) => (shaped.size, shaped.layout.len()),
+                            None => (t.estimated_size(), t.text.lines().count()),
+                        };
+                        let placement_transform = t.placement_for_size(size);
+                        draw_text_background(
+                            scene,
+                            strokes,
+                            graphics,
+                            options,
+                            transform * placement_transform,
+                            t,
+                            size,
+                        );
+                        greek_text(
+                            scene,
+                            transform * placement_transform,
+                            scale,
+                            size,
+                            lines,
+                            options.greeking,
                             fill_paint,
-                        } = graphics.get_paint(*paint);
+                        );
+                        stats.greeked += 1;
+                        stats.items_encoded += 1;
+                        continue;
+                    }
 
-                        if let Some(fill_paint) = fill_paint {
-                            scene.fill(NonZero, transform, fill_paint, None, path.as_ref());
-                        }
-                        if let Some(stroke_paint) = stroke_paint {
-                            scene.stroke(stroke, transform, stroke_paint, None, path.as_ref());
-                        }
+                    let shaped = timed(options.collect_stats, &mut stats.shaping_time, || {
+                        text_cache.get(font_cx, layout_cx, Some(graphics), idx, t)
+                    });
+                    let placement_transform = t.placement_for_size(shaped.size);
+                    draw_text_background(
+                        scene,
+                        strokes,
+                        graphics,
+                        options,
+                        transform * placement_transform,
+                        t,
+                        shaped.size,
+                    );
+
+                    let glyphs_transform =
+                        transform * placement_transform * t.mirror_for_size(shaped.size);
+                    stats.glyph_runs += shaped.runs.len();
+                    for run in &shaped.runs {
+                        scene
+                            .draw_glyphs(&run.font)
+                            .brush(run_brush(run, fill_paint, overridden))
+                            .hint(options.hinting)
+                            .transform(glyphs_transform)
+                            .glyph_transform(Some(run.glyph_transform))
+                            .font_size(run.font_size)
+                            .normalized_coords(&run.normalized_coords)
+                            .draw(Fill::NonZero, run.glyphs.iter().copied());
                     }
-                    GraphicsItem::FatText(FatText {
+                }
+                #[cfg(feature = "text")]
+                GraphicsItem::FatTextOnPath(t) => {
+                    if !options.text_enabled {
+                        stats.skipped_text += 1;
+                        continue;
+                    }
+                    let FatTextOnPath {
                         transform,
                         paint,
-                        text,
-                        style,
-                        max_inline_size,
-                        alignment,
-                        insertion,
-                        attachment_point,
-                    }) => {
-                        let transform = graphics.get_transform(*transform);
-
-                        let mut builder = layout_cx.ranged_builder(font_cx, text, 1.0, false);
-                        for prop in style.inner().values() {
-                            builder.push_default(prop.to_owned());
-                        }
-                        let mut layout = builder.build(text);
-                        layout.break_all_lines(*max_inline_size);
-                        layout.align(*max_inline_size, *alignment, Default::default());
-                        let layout_size = Size {
-                            width: max_inline_size.unwrap_or(layout.width()) as f64,
-                            height: layout.height() as f64,
-                        };
+                        path,
+                        offset,
+                        ..
+                    } = &**t;
+                    let transform = graphics.get_transform(*transform);
+
+                    let FatPaint {
+                        fill_paint: Some(fill_paint),
+                        ..
+                    } = options.overrides.paint(graphics, *paint)
+                    else {
+                        stats.skipped_empty += 1;
+                        continue;
+                    };
+                    let overridden = options.overrides.overrides_paint(*paint);
 
-                        let placement_transform = Affine::from(*insertion)
-                            * Affine::translate(-attachment_point.select(layout_size));
+                    let shaped = timed(options.collect_stats, &mut stats.shaping_time, || {
+                        text_cache.get(font_cx, layout_cx, Some(graphics), idx, &t.line_text())
+                    });
+                    stats.glyph_runs += shaped.runs.len();
+                    let measured = MeasuredPath::new(path);
 
+                    // Each glyph is drawn separately, with the middle of its baseline
+                    // on the path and rotated to the tangent there.
+                    for run in &shaped.runs {
+                        for (glyph, advance) in run.glyphs.iter().zip(&run.advances) {
+                            let middle = f64::from(glyph.x + advance * 0.5);
+                            let Some((point, tangent)) = measured.at(offset + middle) else {
+                                continue;
+                            };
+                            let placement = Affine::translate(point.to_vec2())
+                                * Affine::rotate(tangent.atan2())
+                                * Affine::translate((-middle, -f64::from(run.baseline)));
+                            scene
+                                .draw_glyphs(&run.font)
+                                .brush(run_brush(run, fill_paint, overridden))
+                                .hint(options.hinting)
+                                .transform(transform * placement)
+                                .glyph_transform(Some(run.glyph_transform))
+                                .font_size(run.font_size)
+                                .normalized_coords(&run.normalized_coords)
+                                .draw(Fill::NonZero, core::iter::once(*glyph));
+                        }
+                    }
+                }
+                GraphicsItem::FatImage(FatImage {
+                    transform,
+                    image,
+                    placement,
+                    opacity,
+                }) => {
+                    if *opacity <= 0.0 {
+                        stats.skipped_empty += 1;
+                        continue;
+                    }
+                    let transform = graphics.get_transform(*transform) * *placement;
+                    if *opacity < 1.0 {
+                        scene.draw_image(&image.clone().multiply_alpha(*opacity), transform);
+                    } else {
+                        scene.draw_image(image, transform);
+                    }
+                }
+                GraphicsItem::FatMarker(marker) => {
+                    let transform = graphics.get_transform(marker.transform);
+                    let Some(path) = marker.local_path(transform, options.pixels_per_millimeter)
+                    else {
+                        stats.skipped_empty += 1;
+                        continue;
+                    };
+                    let FatPaint {
+                        stroke,
+                        stroke_paint,
+                        fill_paint,
+                        fill_rule,
+                    } = options.overrides.paint(graphics, marker.paint);
+                    if fill_paint.is_none() && stroke_paint.is_none() {
+                        stats.skipped_empty += 1;
+                        continue;
+                    }
+                    if options.collect_stats {
+                        stats.path_segments += count_segments(path.elements());
+                    }
+                    if let Some(fill_paint) = fill_paint {
+                        scene.fill(
+                            fill_rule.unwrap_or(options.fill_rule),
+                            transform,
+                            fill_paint,
+                            None,
+                            &path,
+                        );
+                    }
+                    if let Some(stroke_paint) = stroke_paint {
+                        stroke_shape(
+                            scene,
+                            strokes,
+                            options,
+                            marker.paint,
+                            stroke,
+                            transform,
+                            stroke_paint,
+                            &path,
+                        );
+                    }
+                }
+                GraphicsItem::FatChunk(chunk) => {
+                    let transform = graphics.get_transform(chunk.transform);
+                    let scale = uniform_scale(transform).max(f64::EPSILON);
+                    // Only the item's own paint is in the bag, so the chunk's paints
+                    // are drawn as they are, and are not recorded for restroking.
+                    let item_paint = chunk
+                        .paint
+                        .map(|p| (p, options.overrides.paint(graphics, p)));
+                    for (shape, chunk_paint) in chunk.shapes() {
+                        let path = frame_arena.path(shape, SHAPE_TOLERANCE / scale);
                         let FatPaint {
-                            fill_paint: Some(fill_paint),
-                            ..
-                        } = graphics.get_paint(*paint)
-                        else {
+                            stroke,
+                            stroke_paint,
+                            fill_paint,
+                            fill_rule,
+                        } = item_paint.map_or(chunk_paint, |(_, p)| p);
+                        if fill_paint.is_none() && stroke_paint.is_none() {
                             continue;
-                        };
-
-                        for line in layout.lines() {
-                            for item in line.items() {
-                                let PositionedLayoutItem::GlyphRun(glyph_run) = item else {
-                                    continue;
-                                };
-
-                                let mut x = glyph_run.offset();
-                                let y = glyph_run.baseline();
-                                let run = glyph_run.run();
-                                let synthesis = run.synthesis();
-                                scene
-                                    .draw_glyphs(run.font())
-                                    // TODO: Color will come from styled text.
-                                    .brush(fill_paint)
-                                    .hint(false)
-                                    .transform(transform * placement_transform)
-                                    .glyph_transform(Some(if let Some(angle) = synthesis.skew() {
-                                        Affine::scale(50_f64.recip())
-                                            * Affine::skew(angle.to_radians().tan() as f64, 0.0)
-                                    } else {
-                                        Affine::scale(50_f64.recip())
-                                    }))
-                                    // Small font sizes are quantized, multiplying by
-                                    // 50 and then scaling by 1 / 50 at the glyph level
-                                    // works around this, but it is a hack.
-                                    .font_size(run.font_size() * 50.0)
-                                    .normalized_coords(run.normalized_coords())
-                                    .draw(
-                                        Fill::NonZero,
-                                        glyph_run.glyphs().map(|g| {
-                                            let gx = x + g.x;
-                                            let gy = y - g.y;
-                                            x += g.advance;
-                                            vello::Glyph {
-                                                id: g.id as _,
-                                                x: gx,
-                                                y: gy,
-                                            }
-                                        }),
-                                    );
+                        }
+                        if options.collect_stats {
+                            stats.path_segments += count_segments(path);
+                        }
+                        if let Some(fill_paint) = fill_paint {
+                            scene.fill(
+                                fill_rule.unwrap_or(options.fill_rule),
+                                transform,
+                                fill_paint,
+                                None,
+                                &path,
+                            );
+                        }
+                        match (stroke_paint, item_paint) {
+                            (Some(stroke_paint), Some((paint, _))) => stroke_shape(
+                                scene,
+                                strokes,
+                                options,
+                                paint,
+                                stroke,
+                                transform,
+                                stroke_paint,
+                                &path,
+                            ),
+                            (Some(stroke_paint), None) => {
+                                // Don't share a recorded style, which may be restroked.
+                                if strokes.is_some() {
+                                    scene.encoding_mut().flags |= Encoding::FORCE_NEXT_STYLE;
+                                }
+                                let stroke = options.dash_units.apply(stroke, transform);
+                                scene.stroke(&stroke, transform, stroke_paint, None, &path);
                             }
+                            (None, _) => {}
                         }
                     }
                 }
+                // Text items, without the `text` feature.
+                _ => {
+                    stats.skipped_text += 1;
+                    continue;
+                }
             }
+            stats.items_encoded += 1;
         }
+
+        stats.encoding_time = stopwatch.elapsed().saturating_sub(stats.shaping_time);
+        stats
     }
 
+    /// Add the visible layers of a [`LayerStack`] to a Vello [`Scene`].
+    ///
+    /// Returns what was encoded for all of the layers together, see [`EncodeStats`].
+    pub fn add_layer_stack_to_scene(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+    ) -> EncodeStats {
+        self.add_layer_stack_to_scene_with_options(
+            scene,
+            graphics,
+            stack,
+            &RenderOptions::default(),
+        )
+    }
+
+    /// Add the visible layers of a [`LayerStack`] to a Vello [`Scene`] with [`RenderOptions`].
+    ///
+    /// Layers are drawn from the lowest `z` to the highest, and layers that are not fully
+    /// opaque are composited as a group, so overlapping items within a layer do not show
+    /// through each other.
+    ///
+    /// Returns what was encoded for all of the layers together, see [`EncodeStats`].
+    #[tracing::instrument(skip_all)]
+    pub fn add_layer_stack_to_scene_with_options(
+        &mut self,
+        scene: &mut Scene,
+        graphics: &GraphicsBag,
+        stack: &LayerStack,
+        options: &RenderOptions<'_>,
+    ) -> EncodeStats {
+        let mut stats = EncodeStats::default();
+        for l in stack.visible_layers() {
+            if l.opacity <= 0.0 || l.layer.indices.is_empty() {
+                continue;
+            }
+            if l.opacity < 1.0 {
+                scene.push_layer(Mix::Normal, l.opacity, Affine::IDENTITY, &UNCLIPPED);
+                stats +=
+                    self.add_render_layer_to_scene_with_options(scene, graphics, &l.layer, options);
+                scene.pop_layer();
+            } else {
+                stats +=
+                    self.add_render_layer_to_scene_with_options(scene, graphics, &l.layer, options);
+            }
+        }
+        stats
+    }
+
+    /// Get the items of a [`RenderLayer`] partitioned by kind.
+    ///
+    /// Partitions are kept for the last few layers they were made for, and made again
+    /// when a layer's items change, or when the [epoch](GraphicsBag::epoch) of the bag
+    /// changes. Text passes such as [`measure_text_items`](Self::measure_text_items) use
+    /// them to skip straight to text items.
+    pub fn layer_kinds(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+    ) -> &LayerKinds {
+        self.layer_kinds.get(graphics, render_layer)
+    }
+
+    #[cfg(feature = "text")]
     /// Measure text items in a [`RenderLayer`].
+    ///
+    /// See [`measure_text_detailed`](Self::measure_text_detailed) for the boxes of
+    /// each line and glyph run.
     #[tracing::instrument(skip_all)]
     pub fn measure_text_items(
         &mut self,
         graphics: &GraphicsBag,
         render_layer: &RenderLayer,
     ) -> BTreeMap<ItemHandle, (DirectIsometry, Size)> {
-        let Self { font_cx, layout_cx } = self;
+        let Self {
+            font_cx,
+            layout_cx,
+            text_cache,
+            lod_cache: _,
+            fragments: _,
+            frame_arena: _,
+            cull_indices: _,
+            layer_kinds,
+            texture_renderer: _,
+        } = self;
         let mut out = BTreeMap::new();
 
-        for idx in &render_layer.indices {
-            let Some(GraphicsItem::FatText(FatText {
-                text,
-                style,
-                max_inline_size,
-                alignment,
+        for idx in layer_kinds.get(graphics, render_layer).get(ItemKind::Text) {
+            let Some(GraphicsItem::FatText(t)) = graphics.get(*idx) else {
+                continue;
+            };
+            let FatText {
                 insertion,
                 attachment_point,
                 ..
-            })) = graphics.get(*idx)
-            else {
-                continue;
-            };
+            } = &**t;
 
-            let mut builder = layout_cx.ranged_builder(font_cx, text, 1.0, false);
-            for prop in style.inner().values() {
-                builder.push_default(prop.to_owned());
-            }
-            let mut layout = builder.build(text);
-            layout.break_all_lines(*max_inline_size);
-            layout.align(*max_inline_size, *alignment, Default::default());
-
-            let layout_size = Size {
-                width: max_inline_size.unwrap_or(layout.width()) as f64,
-                height: layout.height() as f64,
-            };
+            let shaped = text_cache.get(font_cx, layout_cx, Some(graphics), *idx, t);
+            let layout_size = shaped.size;
 
             let rotated_offset = rotate_offset(*attachment_point, layout_size, insertion.angle);
 
@@ -204,8 +780,251 @@ impl Environment {
 
         out
     }
+
+    #[cfg(feature = "text")]
+    /// Shape text items in a [`RenderLayer`] ahead of encoding, one batch at a time.
+    ///
+    /// Unshaped text items whose [estimated bounds](FatText::estimated_bounds) overlap
+    /// `viewport` are shaped first regardless of `budget`, then up to `budget` of the
+    /// remaining unshaped text items are shaped. The layouts are cached and reused by
+    /// [`Environment::add_render_layer_to_scene`] and [`Environment::measure_text_items`].
+    /// Text items with identical text, style, width, and alignment share one layout.
+    ///
+    /// Calling this once per frame until it returns zero spreads the cost of shaping
+    /// large drawings across frames, rather than paying it all before the first frame.
+    ///
+    /// `viewport` is in device coordinates, after each item's transform is applied.
+    ///
+    /// Returns the number of text items that remain unshaped.
+    #[tracing::instrument(skip_all)]
+    pub fn shape_text_items_progressively(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        viewport: Rect,
+        budget: usize,
+    ) -> usize {
+        let Self {
+            font_cx,
+            layout_cx,
+            text_cache,
+            lod_cache: _,
+            fragments: _,
+            frame_arena,
+            cull_indices: _,
+            layer_kinds,
+            texture_renderer: _,
+        } = self;
+        frame_arena.reset();
+        let mut deferred = frame_arena.vec();
+
+        for idx in layer_kinds.get(graphics, render_layer).get(ItemKind::Text) {
+            let Some(GraphicsItem::FatText(t)) = graphics.get(*idx) else {
+                continue;
+            };
+            if text_cache.is_fresh(Some(graphics), *idx, t) {
+                continue;
+            }
+
+            let bounds = graphics
+                .get_transform(t.transform)
+                .transform_rect_bbox(t.estimated_bounds());
+            if bounds.overlaps(viewport) {
+                text_cache.get(font_cx, layout_cx, Some(graphics), *idx, t);
+            } else {
+                deferred.push((*idx, t));
+            }
+        }
+
+        let batch = deferred.len().min(budget);
+        for (idx, t) in deferred.drain(..batch) {
+            text_cache.get(font_cx, layout_cx, Some(graphics), idx, t);
+        }
+
+        deferred.len()
+    }
+
+    #[cfg(feature = "text")]
+    /// Convert a text item to filled outlines, shaping it if needed.
+    ///
+    /// The outlines are in the coordinate space of the text's `transform`, so a
+    /// [`FatShape`] with the same transform and a fill paint draws the same glyphs as
+    /// the text item. This is useful for exporting vector text and for geometric
+    /// operations on text, such as offsetting or precise hit testing.
+    ///
+    /// Backgrounds are not included, and glyphs without outlines, such as color bitmaps,
+    /// are skipped.
+    pub fn text_outlines(&mut self, item: ItemHandle, t: &FatText) -> BezPath {
+        let shaped = self
+            .text_cache
+            .get(&mut self.font_cx, &mut self.layout_cx, None, item, t);
+        let transform = t.placement_for_size(shaped.size) * t.mirror_for_size(shaped.size);
+        let mut out = BezPath::new();
+        for run in &shaped.runs {
+            outline::run_outlines(run, transform, &mut out);
+        }
+        out
+    }
+
+    #[cfg(feature = "text")]
+    /// Drop all cached text layouts.
+    ///
+    /// Layouts are keyed by [`ItemHandle`], so this should be called when switching
+    /// to a different [`GraphicsBag`] to release memory held for the old one. It should
+    /// also be called when fonts are added or removed, which can change the layout of
+    /// text whose items have not changed.
+    pub fn clear_text_layouts(&mut self) {
+        self.text_cache.clear();
+    }
+
+    #[cfg(feature = "text")]
+    /// Drop the cached text layout of an item.
+    ///
+    /// Layouts are checked against their items before they are reused, so this is
+    /// only needed to release memory held for items that are no longer drawn.
+    /// Layouts that are shared with other items are kept for them.
+    pub fn invalidate_text_layout(&mut self, item: ItemHandle) {
+        self.text_cache.invalidate(item);
+    }
+
+    /// Release simplified paths built for [`RenderOptions::lod_tolerance`].
+    ///
+    /// Simplified paths keep their shapes alive, so this should be called when
+    /// switching to a different [`GraphicsBag`] to release memory held for the old one.
+    pub fn clear_path_lods(&mut self) {
+        self.lod_cache.clear();
+    }
+}
+
+impl TextMeasurer for Environment {
+    /// Measure text with its shaped layout, shaping it if needed.
+    #[cfg(feature = "text")]
+    fn text_size(&mut self, item: ItemHandle, text: &FatText) -> Size {
+        self.text_cache
+            .get(&mut self.font_cx, &mut self.layout_cx, None, item, text)
+            .size
+    }
+}
+
+#[cfg(feature = "text")]
+/// Draw the background box of a text item, if it has one.
+///
+/// `transform` maps the text's layout box of `size`, with its origin at the top left,
+/// to device coordinates.
+fn draw_text_background(
+    scene: &mut Scene,
+    strokes: &mut Option<&mut Vec<EncodedStroke>>,
+    graphics: &GraphicsBag,
+    options: &RenderOptions<'_>,
+    transform: Affine,
+    t: &FatText,
+    size: Size,
+) {
+    let (Some(background), Some(rect)) = (t.background, t.background_rect(size)) else {
+        return;
+    };
+    let FatPaint {
+        stroke,
+        stroke_paint,
+        fill_paint,
+        fill_rule,
+    } = options.overrides.paint(graphics, background.paint);
+    if let Some(fill_paint) = fill_paint {
+        let fill_rule = fill_rule.unwrap_or(options.fill_rule);
+        scene.fill(fill_rule, transform, fill_paint, None, &rect);
+    }
+    if let Some(stroke_paint) = stroke_paint {
+        stroke_shape(
+            scene,
+            strokes,
+            options,
+            background.paint,
+            stroke,
+            transform,
+            stroke_paint,
+            &rect,
+        );
+    }
+}
+
+/// Stroke a shape with the stroke of `paint`.
+///
+/// If `strokes` is given, the style is encoded separately, and where it is encoded is
+/// recorded, so that it can be [restroked](RestrokeScene::restroke) in place later.
+/// Overridden paints are not recorded, because restroking takes paints from the bag.
+fn stroke_shape(
+    scene: &mut Scene,
+    strokes: &mut Option<&mut Vec<EncodedStroke>>,
+    options: &RenderOptions<'_>,
+    paint: PaintHandle,
+    stroke: &Stroke,
+    transform: Affine,
+    brush: &Brush,
+    shape: &impl Shape,
+) {
+    if let Some(strokes) = strokes {
+        let encoding = scene.encoding_mut();
+        encoding.flags |= Encoding::FORCE_NEXT_STYLE;
+        if !options.overrides.overrides_paint(paint) {
+            strokes.push(EncodedStroke {
+                style: encoding.styles.len(),
+                paint,
+                dashes: (!stroke.dash_pattern.is_empty())
+                    .then(|| (stroke.dash_offset, stroke.dash_pattern.clone())),
+            });
+        }
+    }
+    let stroke = options.dash_units.apply(stroke, transform);
+    scene.stroke(&stroke, transform, brush, None, shape);
+}
+
+#[cfg(feature = "text")]
+/// Get the brush for a glyph run, which is the run's own color unless the paint of its
+/// item is overridden, so that highlighted and monochrome text is drawn in one color.
+fn run_brush<'a>(run: &PreparedRun, fill_paint: &'a Brush, overridden: bool) -> BrushRef<'a> {
+    match run.color {
+        Some(color) if !overridden => BrushRef::Solid(color),
+        _ => fill_paint.into(),
+    }
 }
 
+#[cfg(feature = "text")]
+/// Draw a placeholder for text that is too small to read.
+///
+/// `transform` maps the text's layout box, with its origin at the top left, to device
+/// coordinates, and `scale` is the scale factor of `transform`.
+fn greek_text(
+    scene: &mut Scene,
+    transform: Affine,
+    scale: f64,
+    size: Size,
+    lines: usize,
+    greeking: Greeking,
+    brush: &Brush,
+) {
+    match greeking {
+        Greeking::Bar => {
+            let line_height = size.height / lines.max(1) as f64;
+            for i in 0..lines.max(1) {
+                let y = (i as f64 + 0.5) * line_height;
+                let bar = Rect::new(
+                    0.0,
+                    y - line_height / 6.0,
+                    size.width,
+                    y + line_height / 6.0,
+                );
+                scene.fill(NonZero, transform, brush, None, &bar);
+            }
+        }
+        Greeking::Box => {
+            let outline = Rect::from_origin_size((0.0, 0.0), size);
+            let stroke = Stroke::new(if scale > 0.0 { scale.recip() } else { 1.0 });
+            scene.stroke(&stroke, transform, brush, None, &outline);
+        }
+    }
+}
+
+#[cfg(feature = "text")]
 /// Calculate a top left equivalent insertion point for a layout size and attachment point.
 fn rotate_offset(attachment_point: AttachmentPoint, layout_size: Size, angle: f64) -> Vec2 {
     let attachment = attachment_point.select(layout_size);
@@ -215,3 +1034,227 @@ fn rotate_offset(attachment_point: AttachmentPoint, layout_size: Size, angle: f6
         y: attachment.x * sin + attachment.y * cos,
     }
 }
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+    use parley::StyleSet;
+    use tabulon::text::{TextBackground, TextDirection};
+
+    /// Push a label at `x` along the top edge of the drawing.
+    fn label(
+        graphics: &mut GraphicsBag,
+        layer: &mut RenderLayer,
+        text: &str,
+        x: f64,
+    ) -> ItemHandle {
+        layer.push_with_bag(
+            graphics,
+            FatText {
+                transform: Default::default(),
+                paint: Default::default(),
+                text: text.into(),
+                style: StyleSet::new(10.0),
+                spans: Vec::new(),
+                alignment: Default::default(),
+                direction: TextDirection::Auto,
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(0.0, Vec2::new(x, 20.0)),
+                attachment_point: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn shape_text_progressively() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let inside = label(&mut graphics, &mut layer, "Inside", 10.0);
+        // Distinct texts, so that no label shares the layout of another.
+        let outside: Vec<ItemHandle> = (0..4)
+            .map(|i| {
+                let text = alloc::format!("Outside {i}");
+                label(
+                    &mut graphics,
+                    &mut layer,
+                    &text,
+                    1000.0 + 100.0 * f64::from(i),
+                )
+            })
+            .collect();
+        let viewport = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let mut environment = Environment::default();
+        let shaped = |environment: &Environment, ih: ItemHandle| {
+            let Some(GraphicsItem::FatText(t)) = graphics.get(ih) else {
+                unreachable!();
+            };
+            environment.text_cache.is_fresh(Some(&graphics), ih, t)
+        };
+
+        assert_eq!(
+            environment.shape_text_items_progressively(&graphics, &layer, viewport, 0),
+            4,
+            "Every label outside the viewport should remain without a budget."
+        );
+        assert!(
+            shaped(&environment, inside),
+            "Labels in the viewport should be shaped regardless of the budget."
+        );
+        assert!(
+            !outside.iter().any(|ih| shaped(&environment, *ih)),
+            "Labels outside the viewport should wait for a budget."
+        );
+
+        assert_eq!(
+            environment.shape_text_items_progressively(&graphics, &layer, viewport, 3),
+            1,
+            "The labels beyond the budget should remain."
+        );
+        assert_eq!(
+            outside
+                .iter()
+                .filter(|ih| shaped(&environment, **ih))
+                .count(),
+            3,
+            "No more labels than the budget should be shaped."
+        );
+
+        assert_eq!(
+            environment.shape_text_items_progressively(&graphics, &layer, viewport, 3),
+            0,
+            "Nothing should remain once every label is shaped."
+        );
+        assert!(
+            outside.iter().all(|ih| shaped(&environment, *ih)),
+            "Every label should be shaped in the end."
+        );
+    }
+
+    #[test]
+    fn greek_text_below_threshold() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        // Labels are 10 units high, drawn at the scale of device pixels.
+        let item = label(&mut graphics, &mut layer, "Small", 0.0);
+        let Some(GraphicsItem::FatText(t)) = graphics.get_mut(item) else {
+            unreachable!();
+        };
+        t.paint = paint;
+        let mut environment = Environment::default();
+        let mut encode = |greek_threshold| {
+            environment.add_render_layer_to_scene_with_options(
+                &mut Scene::new(),
+                &graphics,
+                &layer,
+                &RenderOptions {
+                    greek_threshold,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let greeked = encode(12.0);
+        assert_eq!(
+            (greeked.greeked, greeked.items_encoded),
+            (1, 1),
+            "Text smaller than the threshold should be drawn as a placeholder."
+        );
+        assert_eq!(
+            encode(8.0).greeked,
+            0,
+            "Text larger than the threshold should be drawn."
+        );
+        assert_eq!(
+            encode(0.0).greeked,
+            0,
+            "A zero threshold should disable greeking."
+        );
+    }
+
+    #[test]
+    fn greeking_styles() {
+        let brush = Brush::from(Color::BLACK);
+        let size = Size::new(30.0, 20.0);
+        let transform = Affine::translate((5.0, 5.0)) * Affine::scale(2.0);
+        let greeked = |greeking| {
+            let mut scene = Scene::new();
+            greek_text(&mut scene, transform, 2.0, size, 2, greeking, &brush);
+            scene
+        };
+
+        let mut bars = Scene::new();
+        for y in [5.0, 15.0] {
+            let bar = Rect::new(0.0, y - 10.0 / 6.0, 30.0, y + 10.0 / 6.0);
+            bars.fill(NonZero, transform, &brush, None, &bar);
+        }
+        let bar = greeked(Greeking::Bar);
+        assert!(
+            bar.encoding().path_data == bars.encoding().path_data
+                && bar.encoding().draw_tags == bars.encoding().draw_tags,
+            "Bars should be filled through the middle third of each line."
+        );
+
+        let mut outline = Scene::new();
+        outline.stroke(
+            &Stroke::new(0.5),
+            transform,
+            &brush,
+            None,
+            &Rect::from_origin_size((0.0, 0.0), size),
+        );
+        let boxed = greeked(Greeking::Box);
+        assert!(
+            boxed.encoding().path_data == outline.encoding().path_data
+                && boxed.encoding().styles == outline.encoding().styles,
+            "Boxes should outline the layout box one device pixel wide."
+        );
+    }
+
+    #[test]
+    fn background_box_has_margin_and_paint() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let red = Brush::from(Color::from_rgb8(255, 0, 0));
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(red.clone()),
+            ..Default::default()
+        });
+        let item = label(&mut graphics, &mut layer, "Boxed", 0.0);
+        let Some(GraphicsItem::FatText(t)) = graphics.get_mut(item) else {
+            unreachable!();
+        };
+        t.background = Some(TextBackground { paint, margin: 2.0 });
+        let Some(GraphicsItem::FatText(t)) = graphics.get(item) else {
+            unreachable!();
+        };
+
+        let transform = Affine::translate((5.0, 5.0));
+        let mut scene = Scene::new();
+        draw_text_background(
+            &mut scene,
+            &mut None,
+            &graphics,
+            &RenderOptions::default(),
+            transform,
+            t,
+            Size::new(30.0, 10.0),
+        );
+
+        let mut expected = Scene::new();
+        let rect = Rect::new(-2.0, -2.0, 32.0, 12.0);
+        expected.fill(NonZero, transform, &red, None, &rect);
+        assert!(
+            scene.encoding().path_data == expected.encoding().path_data
+                && scene.encoding().draw_data == expected.encoding().draw_data,
+            "The box should be filled with the background paint, inflated by the margin."
+        );
+    }
+}
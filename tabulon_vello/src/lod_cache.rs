@@ -0,0 +1,62 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Caching of simplified paths, shared between items with the same shape.
+
+use tabulon::{
+    peniko::kurbo::{BezPath, Shape},
+    shape::AnyShape,
+    simplify::PathLod,
+};
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc};
+
+/// Levels of detail of paths, built the first time they are drawn simplified.
+#[derive(Default)]
+pub(crate) struct LodCache {
+    /// Levels keyed by the address of the shared shape, which is kept alive so the
+    /// address is not reused while it is in the cache.
+    lods: BTreeMap<usize, (Arc<AnyShape>, PathLod)>,
+}
+
+impl LodCache {
+    /// Paths with fewer elements than this are cheap enough to draw as they are.
+    const MIN_ELEMENTS: usize = 64;
+
+    /// Tolerance of the finest level, relative to the diagonal of the path's bounds.
+    const FINEST: f64 = 1e-4;
+
+    /// Number of levels, which cover tolerances up to about the size of the path.
+    const LEVELS: usize = 8;
+
+    /// Get the simplest version of `shape` within `tolerance` of it, in its local
+    /// coordinates, if there is one simpler than the shape itself.
+    pub(crate) fn select(&mut self, shape: &Arc<AnyShape>, tolerance: f64) -> Option<&BezPath> {
+        let elements = match shape.as_ref() {
+            AnyShape::BezPath(path) => path.elements().len(),
+            AnyShape::CompactPath(path) => path.len(),
+            _ => return None,
+        };
+        if elements < Self::MIN_ELEMENTS {
+            return None;
+        }
+        let (_, lod) = self
+            .lods
+            .entry(Arc::as_ptr(shape).addr())
+            .or_insert_with(|| {
+                let path = shape.path(0.0);
+                let diagonal = path.bounding_box().size().to_vec2().hypot();
+                (
+                    shape.clone(),
+                    PathLod::new(&path, diagonal * Self::FINEST, Self::LEVELS),
+                )
+            });
+        lod.select(tolerance)
+    }
+
+    /// Release all levels.
+    pub(crate) fn clear(&mut self) {
+        self.lods.clear();
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Conversion of shaped text to outlines.
+
+use tabulon::{
+    outline::GlyphOutliner,
+    peniko::kurbo::{Affine, BezPath},
+};
+
+use crate::text_cache::PreparedRun;
+
+/// Append the outlines of the glyphs in `run` to `out`, transformed by `transform`.
+///
+/// Glyphs are placed the same way Vello draws them. Glyphs without outlines,
+/// such as color bitmaps, are skipped.
+pub(crate) fn run_outlines(run: &PreparedRun, transform: Affine, out: &mut BezPath) {
+    let Some(outliner) = GlyphOutliner::new(&run.font, run.font_size, &run.normalized_coords)
+    else {
+        return;
+    };
+    for glyph in &run.glyphs {
+        let Some(outline) = outliner.outline(glyph.id) else {
+            continue;
+        };
+        // Font outlines are y up, and are flipped onto the y down baseline.
+        let glyph_transform = transform
+            * Affine::new([1.0, 0.0, 0.0, -1.0, glyph.x.into(), glyph.y.into()])
+            * run.glyph_transform;
+        out.extend(glyph_transform * outline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Environment;
+    use parley::StyleSet;
+    use tabulon::{
+        DirectIsometry, ItemHandle,
+        bounds::TextMeasurer,
+        peniko::kurbo::{Rect, Shape, Vec2},
+        text::FatText,
+    };
+
+    #[test]
+    fn outlines_fill_layout_box() {
+        let mut env = Environment::default();
+        let t = FatText {
+            transform: Default::default(),
+            paint: Default::default(),
+            text: "Hi".into(),
+            style: StyleSet::new(10.0),
+            spans: Vec::new(),
+            alignment: Default::default(),
+            direction: Default::default(),
+            max_inline_size: None,
+            columns: None,
+            background: None,
+            mirror_x: false,
+            mirror_y: false,
+            insertion: DirectIsometry::new(0.0, Vec2::new(100.0, 50.0)),
+            attachment_point: Default::default(),
+        };
+        let item = ItemHandle::default();
+        let outlines = env.text_outlines(item, &t);
+        let size = env.text_size(item, &t);
+        let layout_box = t
+            .placement_for_size(size)
+            .transform_rect_bbox(Rect::from_origin_size((0.0, 0.0), size));
+
+        let bounds = outlines.bounding_box();
+        assert!(
+            bounds.area() > 0.0,
+            "Text with visible glyphs should have outlines."
+        );
+        assert!(
+            layout_box.inflate(1.0, 1.0).contains_rect(bounds),
+            "Outlines should be placed in the layout box."
+        );
+    }
+}
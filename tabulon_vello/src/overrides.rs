@@ -0,0 +1,103 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Paints that replace those in a [`GraphicsBag`] for a single render.
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+
+use tabulon::{GraphicsBag, PaintHandle, shape::FatPaint};
+
+/// Paints to draw with instead of the paints registered in a [`GraphicsBag`].
+///
+/// This allows drawing the same bag differently in different views, for example
+/// highlighting a selection, or printing in monochrome, without changing the bag.
+#[derive(Clone, Debug, Default)]
+pub struct StyleOverrides {
+    paints: BTreeMap<PaintHandle, FatPaint>,
+}
+
+/// Overrides that change nothing, for [`RenderOptions::default`](crate::RenderOptions).
+pub(crate) static NO_OVERRIDES: StyleOverrides = StyleOverrides::new();
+
+impl StyleOverrides {
+    /// Create empty overrides.
+    pub const fn new() -> Self {
+        Self {
+            paints: BTreeMap::new(),
+        }
+    }
+
+    /// Draw items that use `handle` with `paint` instead.
+    pub fn set_paint(&mut self, handle: PaintHandle, paint: FatPaint) {
+        self.paints.insert(handle, paint);
+    }
+
+    /// Stop overriding `handle`, returning the paint that replaced it.
+    pub fn remove_paint(&mut self, handle: PaintHandle) -> Option<FatPaint> {
+        self.paints.remove(&handle)
+    }
+
+    /// Check whether `handle` is overridden.
+    pub fn overrides_paint(&self, handle: PaintHandle) -> bool {
+        self.paints.contains_key(&handle)
+    }
+
+    /// Remove all overrides.
+    pub fn clear(&mut self) {
+        self.paints.clear();
+    }
+
+    /// Check whether nothing is overridden.
+    pub fn is_empty(&self) -> bool {
+        self.paints.is_empty()
+    }
+
+    /// Get the paint to draw with for `handle`.
+    pub fn paint<'a>(&'a self, graphics: &'a GraphicsBag, handle: PaintHandle) -> &'a FatPaint {
+        self.paints
+            .get(&handle)
+            .unwrap_or_else(|| graphics.get_paint(handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::peniko::{Color, kurbo::Stroke};
+
+    #[test]
+    fn overridden_paints() {
+        let mut graphics = GraphicsBag::default();
+        let plain = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: None,
+            fill_rule: None,
+        });
+        let highlighted = graphics.register_paint(FatPaint::default());
+
+        let mut overrides = StyleOverrides::new();
+        overrides.set_paint(
+            highlighted,
+            FatPaint {
+                stroke: Stroke::new(3.0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            overrides.paint(&graphics, plain).stroke.width,
+            1.0,
+            "Paints that are not overridden should come from the bag."
+        );
+        assert_eq!(
+            overrides.paint(&graphics, highlighted).stroke.width,
+            3.0,
+            "Overridden paints should replace the bag's paint."
+        );
+        assert!(
+            overrides.remove_paint(highlighted).is_some() && overrides.is_empty(),
+            "Removing the override should leave none."
+        );
+    }
+}
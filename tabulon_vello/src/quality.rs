@@ -0,0 +1,274 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Adapting rendering quality to keep frames within a time budget.
+
+use core::time::Duration;
+
+use vello::AaConfig;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::RenderOptions;
+
+/// Settings that trade rendering quality for speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quality {
+    /// Antialiasing method, for [`vello::RenderParams`].
+    ///
+    /// The renderer must have been created with support for it.
+    pub antialiasing: AaConfig,
+    /// See [`RenderOptions::lod_tolerance`].
+    pub lod_tolerance: f64,
+    /// See [`RenderOptions::greek_threshold`].
+    pub greek_threshold: f64,
+}
+
+impl Quality {
+    /// Set the options that this quality controls.
+    pub fn apply(&self, options: &mut RenderOptions<'_>) {
+        options.lod_tolerance = self.lod_tolerance;
+        options.greek_threshold = self.greek_threshold;
+    }
+}
+
+/// Levels for [`QualityController::new`], from best to fastest.
+///
+/// These use every antialiasing method, so the renderer must be created with
+/// [`vello::AaSupport::all`].
+pub const DEFAULT_LEVELS: [Quality; 4] = [
+    Quality {
+        antialiasing: AaConfig::Msaa16,
+        lod_tolerance: 0.25,
+        greek_threshold: 2.0,
+    },
+    Quality {
+        antialiasing: AaConfig::Msaa8,
+        lod_tolerance: 0.5,
+        greek_threshold: 3.0,
+    },
+    Quality {
+        antialiasing: AaConfig::Area,
+        lod_tolerance: 1.0,
+        greek_threshold: 4.0,
+    },
+    Quality {
+        antialiasing: AaConfig::Area,
+        lod_tolerance: 2.0,
+        greek_threshold: 6.0,
+    },
+];
+
+/// Chooses a [`Quality`] from measured frame times, such as GPU times from a
+/// [`GpuTimer`](crate::GpuTimer).
+///
+/// Quality is lowered a level when the average frame time exceeds the target, and
+/// raised again when there is plenty of headroom. Raising waits longer than lowering,
+/// so quality does not oscillate between two levels.
+#[derive(Clone, Debug)]
+pub struct QualityController {
+    levels: Vec<Quality>,
+    level: usize,
+    target: Duration,
+    /// Exponential moving average of frame times, in seconds.
+    average: Option<f64>,
+    /// Frames measured since the level last changed.
+    settled: u32,
+    /// Consecutive frames with the average well under the target.
+    fast: u32,
+}
+
+impl QualityController {
+    /// Weight of each new measurement in the moving average.
+    const SMOOTHING: f64 = 0.2;
+
+    /// Frames to measure at a level before lowering quality.
+    const LOWER_AFTER: u32 = 8;
+
+    /// Consecutive fast frames needed to raise quality.
+    const RAISE_AFTER: u32 = 60;
+
+    /// Fraction of the target the average must be below to raise quality.
+    const HEADROOM: f64 = 0.5;
+
+    /// Create a controller that starts at the best of `levels`, aiming for frames
+    /// that take `target`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `levels` is empty.
+    pub fn new(levels: impl Into<Vec<Quality>>, target: Duration) -> Self {
+        let levels = levels.into();
+        assert!(
+            !levels.is_empty(),
+            "QualityController needs at least one level."
+        );
+        Self {
+            levels,
+            level: 0,
+            target,
+            average: None,
+            settled: 0,
+            fast: 0,
+        }
+    }
+
+    /// Record how long a frame took, returning whether the quality changed.
+    pub fn record(&mut self, frame_time: Duration) -> bool {
+        let t = frame_time.as_secs_f64();
+        let average = self.average.map_or(t, |a| a + (t - a) * Self::SMOOTHING);
+        self.average = Some(average);
+        self.settled = self.settled.saturating_add(1);
+        let target = self.target.as_secs_f64();
+        if average < target * Self::HEADROOM {
+            self.fast = self.fast.saturating_add(1);
+        } else {
+            self.fast = 0;
+        }
+
+        let level = if average > target && self.settled >= Self::LOWER_AFTER {
+            (self.level + 1).min(self.levels.len() - 1)
+        } else if self.fast >= Self::RAISE_AFTER {
+            self.level.saturating_sub(1)
+        } else {
+            self.level
+        };
+        if level == self.level {
+            return false;
+        }
+        self.level = level;
+        // Measurements at the old level don't predict the new one.
+        self.average = None;
+        self.settled = 0;
+        self.fast = 0;
+        true
+    }
+
+    /// Get the current quality.
+    pub fn quality(&self) -> &Quality {
+        &self.levels[self.level]
+    }
+
+    /// Get the index of the current level, where 0 is the best.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Get the average frame time at the current level, if any have been recorded.
+    pub fn average(&self) -> Option<Duration> {
+        self.average.map(Duration::from_secs_f64)
+    }
+
+    /// Set the target frame time.
+    pub fn set_target(&mut self, target: Duration) {
+        self.target = target;
+    }
+
+    /// Return to the best level, for example when the drawing changes.
+    pub fn reset(&mut self) {
+        self.level = 0;
+        self.average = None;
+        self.settled = 0;
+        self.fast = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Environment;
+    use tabulon::{
+        GraphicsBag,
+        peniko::{
+            Color,
+            kurbo::{BezPath, Stroke},
+        },
+        render_layer::RenderLayer,
+        shape::{FatPaint, FatShape},
+    };
+    use vello::Scene;
+
+    extern crate alloc;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn adapt_to_frame_times() {
+        let mut controller = QualityController::new(DEFAULT_LEVELS, Duration::from_millis(16));
+        let slow = Duration::from_millis(30);
+        let changes = (0..7).filter(|_| controller.record(slow)).count();
+        assert_eq!(changes, 0, "A few slow frames shouldn't lower quality.");
+        assert!(
+            controller.record(slow) && controller.level() == 1,
+            "Consistently slow frames should lower quality a level."
+        );
+
+        for _ in 0..100 {
+            controller.record(slow);
+        }
+        assert_eq!(
+            controller.level(),
+            3,
+            "Quality should not go below the fastest level."
+        );
+
+        let fast = Duration::from_millis(4);
+        let raised_after = (1..=100).find(|_| controller.record(fast));
+        assert!(
+            raised_after.is_some_and(|n| n >= 60),
+            "Quality should be raised only after a longer run of fast frames, got {raised_after:?}."
+        );
+        assert_eq!(
+            controller.quality(),
+            &DEFAULT_LEVELS[2],
+            "Quality should rise a level."
+        );
+    }
+
+    #[test]
+    fn deterministic_ignores_quality() {
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: None,
+            fill_rule: None,
+        });
+        // Long enough to be simplified, with detail much finer than the tolerances.
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        for i in 1..200 {
+            path.line_to((f64::from(i), f64::from(i % 2) * 0.01));
+        }
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                shape: Arc::new(path.into()),
+                ..Default::default()
+            },
+        );
+
+        let mut env = Environment::default();
+        let mut encode = |options: &RenderOptions<'_>| {
+            let mut scene = Scene::new();
+            env.add_render_layer_to_scene_with_options(&mut scene, &graphics, &layer, options);
+            scene.encoding().path_data.clone()
+        };
+        let reference = encode(&RenderOptions::default());
+        let mut fastest = RenderOptions::default();
+        DEFAULT_LEVELS[3].apply(&mut fastest);
+        assert_ne!(
+            encode(&fastest),
+            reference,
+            "The fastest quality should simplify the path."
+        );
+        fastest.deterministic = true;
+        assert_eq!(
+            encode(&fastest),
+            reference,
+            "Deterministic scenes should not depend on the quality."
+        );
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Antialiasing, background, and processor settings for rasterizing scenes.
+//!
+//! Machines without a capable GPU, such as thin clients and virtual machines, can
+//! still render with [`use_cpu`](RasterOptions::use_cpu), which runs Vello's stages up
+//! to fine rasterization on the CPU, and rasterizes on a software adapter, such as
+//! Mesa's llvmpipe or Windows' WARP.
+
+use tabulon::peniko::Color;
+use vello::{
+    AaConfig, AaSupport, RenderParams, RendererOptions,
+    wgpu::{self, Device, Queue},
+};
+
+/// How scenes are rasterized by a [`vello::Renderer`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterOptions {
+    /// Antialiasing method.
+    ///
+    /// The renderer must have been created with support for it; see
+    /// [`aa_support`](Self::aa_support).
+    pub antialiasing: AaConfig,
+    /// Color the scene is drawn over.
+    pub base_color: Color,
+    /// Render on the CPU, for machines without a capable GPU.
+    ///
+    /// The renderer must have been created with this, and should draw to a device
+    /// from [`request_device`](Self::request_device).
+    pub use_cpu: bool,
+}
+
+impl RasterOptions {
+    /// Options for line drawings with many hairlines, drawn on white.
+    ///
+    /// Area antialiasing computes the exact coverage of each pixel, so lines thinner
+    /// than a pixel are drawn proportionally lighter, and keep the same weight wherever
+    /// they fall on the pixel grid. Multisampling rounds coverage to a few samples, so
+    /// hairlines break up and change weight as the view moves.
+    pub const HAIRLINES: Self = Self {
+        antialiasing: AaConfig::Area,
+        base_color: Color::WHITE,
+        use_cpu: false,
+    };
+
+    /// Options for drawings dominated by fills that meet edge to edge, drawn on white.
+    ///
+    /// Area antialiasing blends the antialiased edges of fills that meet, which shows as
+    /// faint seams; multisampling resolves shared edges exactly.
+    pub const ABUTTING_FILLS: Self = Self {
+        antialiasing: AaConfig::Msaa16,
+        base_color: Color::WHITE,
+        use_cpu: false,
+    };
+
+    /// Get the parameters for rendering a target of `width` by `height` pixels.
+    pub fn render_params(&self, width: u32, height: u32) -> RenderParams {
+        RenderParams {
+            base_color: self.base_color,
+            width,
+            height,
+            antialiasing_method: self.antialiasing,
+        }
+    }
+
+    /// Get the antialiasing support a renderer needs for these options.
+    pub fn aa_support(&self) -> AaSupport {
+        AaSupport {
+            area: self.antialiasing == AaConfig::Area,
+            msaa8: self.antialiasing == AaConfig::Msaa8,
+            msaa16: self.antialiasing == AaConfig::Msaa16,
+        }
+    }
+
+    /// Get the options to create a renderer with for these options.
+    pub fn renderer_options(&self) -> RendererOptions {
+        RendererOptions {
+            use_cpu: self.use_cpu,
+            antialiasing_support: self.aa_support(),
+            num_init_threads: None,
+            pipeline_cache: None,
+        }
+    }
+
+    /// Request a device to render with for these options.
+    ///
+    /// With [`use_cpu`](Self::use_cpu), this is a device of the software adapter.
+    /// Otherwise it is a device of the default adapter, or of the software adapter if
+    /// there is no other. Returns `None` if there is no adapter at all.
+    pub async fn request_device(&self, instance: &wgpu::Instance) -> Option<(Device, Queue)> {
+        let adapter = if self.use_cpu {
+            None
+        } else {
+            instance
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await
+        };
+        let adapter = match adapter {
+            Some(adapter) => adapter,
+            None => {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        force_fallback_adapter: true,
+                        ..Default::default()
+                    })
+                    .await?
+            }
+        };
+        adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .ok()
+    }
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self::HAIRLINES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::peniko::kurbo::{Affine, Line, Stroke};
+    use vello::{Renderer, Scene};
+
+    const SIZE: u32 = 64;
+
+    /// Get a device to render `options` with, or `None` if there is no adapter.
+    fn device(options: &RasterOptions) -> Option<(Device, Queue)> {
+        pollster::block_on(options.request_device(&wgpu::Instance::default()))
+    }
+
+    /// Create a renderer for `options`.
+    fn renderer(device: &Device, options: &RasterOptions) -> Renderer {
+        Renderer::new(device, options.renderer_options()).unwrap()
+    }
+
+    /// Render `scene` and get the amount of ink in it, in square pixels.
+    fn ink(
+        device: &Device,
+        queue: &Queue,
+        renderer: &mut Renderer,
+        scene: &Scene,
+        options: &RasterOptions,
+    ) -> f64 {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        renderer
+            .render_to_texture(
+                device,
+                queue,
+                scene,
+                &view,
+                &options.render_params(SIZE, SIZE),
+            )
+            .unwrap();
+
+        // Rows are padded to 256 bytes, which is exactly 64 pixels.
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: u64::from(SIZE * SIZE * 4),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(SIZE * 4),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit([encoder.finish()]);
+        buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+        // Black on white, so the red channel is enough.
+        let ink = buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks(4)
+            .map(|p| f64::from(255 - p[0]) / 255.0)
+            .sum();
+        buffer.unmap();
+        ink
+    }
+
+    #[test]
+    fn hairline_weight() {
+        let Some((device, queue)) = device(&RasterOptions::HAIRLINES) else {
+            // No adapter to render with, as on most CI machines.
+            return;
+        };
+
+        // Largest relative error in the ink of a diagonal hairline a quarter of a pixel
+        // wide, as it moves across a pixel.
+        let line = Line::new((10.0, 10.0), (50.0, 30.0));
+        let width = 0.25;
+        let error = |options: &RasterOptions| {
+            let mut renderer = renderer(&device, options);
+            (0..10_u8)
+                .map(|i| {
+                    let mut scene = Scene::new();
+                    scene.stroke(
+                        &Stroke::new(width),
+                        Affine::translate((f64::from(i) * 0.1, 0.0)),
+                        Color::BLACK,
+                        None,
+                        &line,
+                    );
+                    let ink = ink(&device, &queue, &mut renderer, &scene, options);
+                    (ink / (line.length() * width) - 1.0).abs()
+                })
+                .fold(0.0, f64::max)
+        };
+
+        let hairlines = error(&RasterOptions::HAIRLINES);
+        let msaa = error(&RasterOptions::ABUTTING_FILLS);
+        assert!(
+            hairlines < 0.02,
+            "Hairlines should keep their weight as they move, but it varied by {hairlines}."
+        );
+        assert!(
+            hairlines < msaa,
+            "Area antialiasing should draw hairlines more accurately than multisampling, got {hairlines} and {msaa}."
+        );
+    }
+
+    #[test]
+    fn cpu_matches_gpu() {
+        let cpu = RasterOptions {
+            use_cpu: true,
+            ..RasterOptions::HAIRLINES
+        };
+        let (Some(gpu_device), Some(cpu_device)) =
+            (device(&RasterOptions::HAIRLINES), device(&cpu))
+        else {
+            // No adapter to render with, as on most CI machines.
+            return;
+        };
+
+        let mut scene = Scene::new();
+        scene.stroke(
+            &Stroke::new(2.0),
+            Affine::IDENTITY,
+            Color::BLACK,
+            None,
+            &Line::new((10.0, 10.0), (50.0, 30.0)),
+        );
+        let render = |(device, queue): &(Device, Queue), options: &RasterOptions| {
+            ink(
+                device,
+                queue,
+                &mut renderer(device, options),
+                &scene,
+                options,
+            )
+        };
+        let (gpu, cpu) = (
+            render(&gpu_device, &RasterOptions::HAIRLINES),
+            render(&cpu_device, &cpu),
+        );
+        assert!(
+            (gpu - cpu).abs() < 0.5,
+            "Rendering on the CPU should draw like the GPU, got {cpu} and {gpu}."
+        );
+    }
+}
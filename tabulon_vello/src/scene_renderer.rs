@@ -0,0 +1,188 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Vello as a [`Renderer`] backend.
+
+use tabulon::{
+    GraphicsBag,
+    backend::Renderer,
+    peniko::{
+        Brush, Fill, Image, Mix,
+        kurbo::{Affine, BezPath, Rect, Stroke},
+    },
+};
+#[cfg(feature = "text")]
+use {
+    crate::overrides::NO_OVERRIDES,
+    tabulon::{ItemHandle, shape::FatPaint, text::FatText, text_on_path::FatTextOnPath},
+};
+
+use vello::Scene;
+
+use crate::{Environment, RenderOptions};
+
+/// Draws into a Vello [`Scene`] through the [`Renderer`] interface.
+///
+/// This lets code written against [`tabulon::backend`] draw with Vello. Text is shaped
+/// and drawn from its item in `graphics`, as by [`Environment::add_items_to_scene`] with
+/// `options`. Paths are drawn as they are given, so
+/// [levels of detail](RenderOptions::lod_tolerance) and
+/// [paint overrides](RenderOptions::overrides) are not applied to them, nor to text.
+/// [`Environment::add_items_to_scene`] remains the faster way to draw items with Vello.
+#[allow(
+    missing_debug_implementations,
+    reason = "Not useful, and members don't implement Debug."
+)]
+pub struct SceneRenderer<'a> {
+    /// Environment for shaping text.
+    pub env: &'a mut Environment,
+    /// Scene to draw into.
+    pub scene: &'a mut Scene,
+    /// Bag holding the text items that are drawn.
+    pub graphics: &'a GraphicsBag,
+    /// Options for drawing text.
+    pub options: RenderOptions<'a>,
+}
+
+impl<'a> SceneRenderer<'a> {
+    /// Draw into `scene` with default options.
+    pub fn new(env: &'a mut Environment, scene: &'a mut Scene, graphics: &'a GraphicsBag) -> Self {
+        Self {
+            env,
+            scene,
+            graphics,
+            options: RenderOptions::default(),
+        }
+    }
+
+    #[cfg(feature = "text")]
+    /// Draw a text item as [`Environment::add_items_to_scene`] does.
+    fn text_item(&mut self, item: ItemHandle) {
+        let options = RenderOptions {
+            overrides: &NO_OVERRIDES,
+            ..self.options
+        };
+        self.env
+            .add_items_to_scene(self.scene, self.graphics, [item], &options);
+    }
+}
+
+impl Renderer for SceneRenderer<'_> {
+    fn fill(&mut self, transform: Affine, fill_rule: Fill, brush: &Brush, path: &BezPath) {
+        self.scene.fill(fill_rule, transform, brush, None, path);
+    }
+
+    fn stroke(&mut self, transform: Affine, stroke: &Stroke, brush: &Brush, path: &BezPath) {
+        self.scene.stroke(stroke, transform, brush, None, path);
+    }
+
+    fn image(&mut self, transform: Affine, image: &Image, opacity: f32) {
+        if opacity < 1.0 {
+            self.scene
+                .draw_image(&image.clone().multiply_alpha(opacity), transform);
+        } else {
+            self.scene.draw_image(image, transform);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    fn text(
+        &mut self,
+        item: ItemHandle,
+        _transform: Affine,
+        _text: &FatText,
+        _paint: &FatPaint,
+        _background: Option<&FatPaint>,
+    ) {
+        self.text_item(item);
+    }
+
+    #[cfg(feature = "text")]
+    fn text_on_path(
+        &mut self,
+        item: ItemHandle,
+        _transform: Affine,
+        _text: &FatTextOnPath,
+        _paint: &FatPaint,
+    ) {
+        self.text_item(item);
+    }
+
+    fn push_clip(&mut self, transform: Affine, path: &BezPath) {
+        self.scene.push_layer(Mix::Clip, 1.0, transform, path);
+    }
+
+    fn push_layer(&mut self, opacity: f32, bounds: Rect) {
+        self.scene
+            .push_layer(Mix::Normal, opacity, Affine::IDENTITY, &bounds);
+    }
+
+    fn pop(&mut self) {
+        self.scene.pop_layer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::{
+        backend::{DrawOptions, draw_items},
+        peniko::{
+            Color,
+            kurbo::{Circle, Shape},
+        },
+        render_layer::RenderLayer,
+        shape::{FatClip, FatPaint, FatShape},
+    };
+
+    extern crate alloc;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn matches_environment() {
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(0.5),
+            stroke_paint: Some(Color::BLACK.into()),
+            fill_paint: Some(Color::WHITE.into()),
+            fill_rule: None,
+        });
+        let clip = graphics.register_clip(FatClip {
+            transform: Default::default(),
+            path: Arc::new(BezPath::from_vec(
+                Rect::new(0.0, 0.0, 2.0, 2.0).path_elements(0.1).collect(),
+            )),
+        });
+        let mut layer = RenderLayer::default();
+        for clip in [Default::default(), clip] {
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    clip,
+                    shape: Arc::new(Circle::new((1.0, 1.0), 2.0).into()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut env = Environment::default();
+        let mut expected = Scene::new();
+        env.add_render_layer_to_scene(&mut expected, &graphics, &layer);
+        let mut scene = Scene::new();
+        draw_items(
+            &mut SceneRenderer::new(&mut env, &mut scene, &graphics),
+            &graphics,
+            layer.indices.iter().copied(),
+            &DrawOptions::default(),
+        );
+        let (a, b) = (scene.encoding(), expected.encoding());
+        assert!(
+            a.path_tags == b.path_tags
+                && a.path_data == b.path_data
+                && a.draw_tags == b.draw_tags
+                && a.draw_data == b.draw_data,
+            "Drawing through the backend interface should encode the same scene."
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Diagnostics from encoding items into scenes.
+
+use core::{ops::AddAssign, time::Duration};
+
+use tabulon::peniko::kurbo::PathEl;
+
+/// What encoding items into a scene did, and how long it took.
+///
+/// Items are always counted. Path segments and times are only collected with
+/// [`RenderOptions::collect_stats`], and are zero otherwise.
+///
+/// Stats of several encodings, such as of the layers of a frame, can be added together.
+///
+/// [`RenderOptions::collect_stats`]: crate::RenderOptions::collect_stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeStats {
+    /// Items that were drawn, including text drawn as a placeholder.
+    pub items_encoded: usize,
+    /// Items whose handles are not in the bag.
+    pub skipped_missing: usize,
+    /// Items with nothing to draw, such as shapes whose paint has neither a fill nor a
+    /// stroke, text whose paint has no fill, and fully transparent images.
+    pub skipped_empty: usize,
+    /// Text items that were not drawn because [`RenderOptions::text_enabled`] is off, or
+    /// because the `text` feature is not enabled.
+    ///
+    /// [`RenderOptions::text_enabled`]: crate::RenderOptions::text_enabled
+    pub skipped_text: usize,
+    /// Text items that were [greeked](crate::RenderOptions::greek_threshold).
+    pub greeked: usize,
+    /// Items left out because they are outside the viewport, when drawing
+    /// [culled](crate::Environment::add_render_layer_to_scene_culled).
+    pub culled: usize,
+    /// Glyph runs drawn.
+    pub glyph_runs: usize,
+    /// Segments of the paths of shapes and markers drawn, counted once for each path
+    /// whether it is filled, stroked, or both.
+    ///
+    /// This is only counted when stats are collected.
+    pub path_segments: usize,
+    /// Time spent getting the layouts of text, which is mostly shaping text that had
+    /// no cached layout.
+    ///
+    /// This is only measured when stats are collected, with the `std` feature, and not
+    /// on `wasm32`, which has no clock.
+    pub shaping_time: Duration,
+    /// Time spent encoding, other than shaping text.
+    ///
+    /// This is only measured when stats are collected, with the `std` feature, and not
+    /// on `wasm32`, which has no clock.
+    pub encoding_time: Duration,
+}
+
+impl AddAssign for EncodeStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.items_encoded += rhs.items_encoded;
+        self.skipped_missing += rhs.skipped_missing;
+        self.skipped_empty += rhs.skipped_empty;
+        self.skipped_text += rhs.skipped_text;
+        self.greeked += rhs.greeked;
+        self.culled += rhs.culled;
+        self.glyph_runs += rhs.glyph_runs;
+        self.path_segments += rhs.path_segments;
+        self.shaping_time += rhs.shaping_time;
+        self.encoding_time += rhs.encoding_time;
+    }
+}
+
+/// Count the segments of a path, which are its elements other than moves.
+pub(crate) fn count_segments(path: &[PathEl]) -> usize {
+    path.iter()
+        .filter(|el| !matches!(el, PathEl::MoveTo(_)))
+        .count()
+}
+
+/// Measures time, if it was asked to and there is a clock.
+///
+/// There is no clock without `std`, and [`std::time::Instant`] panics on `wasm32`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stopwatch {
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    started: Option<std::time::Instant>,
+}
+
+impl Stopwatch {
+    /// Start measuring, if `enabled`.
+    pub(crate) fn start(enabled: bool) -> Self {
+        #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+        return Self {
+            started: enabled.then(std::time::Instant::now),
+        };
+        #[cfg(not(all(feature = "std", not(target_arch = "wasm32"))))]
+        {
+            let _ = enabled;
+            Self {}
+        }
+    }
+
+    /// Get the time since starting, or zero if not measuring.
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+        return self.started.map(|s| s.elapsed()).unwrap_or_default();
+        #[cfg(not(all(feature = "std", not(target_arch = "wasm32"))))]
+        Duration::ZERO
+    }
+}
+
+/// Run `f`, adding how long it takes to `elapsed` if `enabled`.
+#[cfg(feature = "text")]
+pub(crate) fn timed<T>(enabled: bool, elapsed: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let stopwatch = Stopwatch::start(enabled);
+    let result = f();
+    *elapsed += stopwatch.elapsed();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Environment, RenderOptions};
+    use tabulon::{
+        GraphicsBag,
+        peniko::{
+            Color,
+            kurbo::{Line, Rect, Stroke},
+        },
+        render_layer::RenderLayer,
+        shape::{FatPaint, FatShape},
+    };
+    use vello::Scene;
+
+    extern crate alloc;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn counts_items() {
+        let mut graphics = GraphicsBag::default();
+        let stroked = graphics.register_paint(FatPaint {
+            stroke: Stroke::new(1.0),
+            stroke_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let unpainted = graphics.register_paint(FatPaint::default());
+        let mut layer = RenderLayer::default();
+        for (paint, shape) in [
+            (stroked, Line::new((0.0, 0.0), (1.0, 1.0)).into()),
+            (stroked, Rect::new(0.0, 0.0, 1.0, 1.0).into()),
+            (unpainted, Line::new((0.0, 0.0), (1.0, 1.0)).into()),
+        ] {
+            layer.push_with_bag(
+                &mut graphics,
+                FatShape {
+                    paint,
+                    shape: Arc::new(shape),
+                    ..Default::default()
+                },
+            );
+        }
+        // A handle past the end of the bag.
+        let mut larger = GraphicsBag::default();
+        let missing = (0..4).map(|_| larger.push(FatShape::default())).last();
+
+        let mut env = Environment::default();
+        let stats = env.add_items_to_scene(
+            &mut Scene::new(),
+            &graphics,
+            layer.indices.iter().copied().chain(missing),
+            &RenderOptions {
+                collect_stats: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            (
+                stats.items_encoded,
+                stats.skipped_empty,
+                stats.skipped_missing
+            ),
+            (2, 1, 1),
+            "Items should be counted as encoded or by why they were skipped."
+        );
+        assert_eq!(
+            stats.path_segments, 5,
+            "Segments of drawn paths should be counted, with closing segments."
+        );
+
+        let mut total = stats;
+        total += stats;
+        assert_eq!(total.items_encoded, 4, "Stats should add up.");
+    }
+}
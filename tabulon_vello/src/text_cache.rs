@@ -0,0 +1,722 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Caching of shaped text, shared between identical labels.
+//!
+//! Shaped text is kept for each item, and checked against the item's inputs before it
+//! is reused. When the item is in a [`GraphicsBag`], the check is skipped while the
+//! item is unchanged since it was last checked, as told by its
+//! [revision](GraphicsBag::item_revision), so that drawing many labels again, such as
+//! while panning, doesn't compare their styles on every frame.
+
+use tabulon::{
+    Epoch, GraphicsBag, ItemHandle, Revision,
+    peniko::{
+        Color, Font,
+        kurbo::{Affine, Rect, Size},
+    },
+    text::{FatText, StyleSpan, TextColumns, TextDirection},
+};
+
+use parley::{Alignment, FontContext, Layout, LayoutContext, PositionedLayoutItem, StyleSet};
+use vello::Glyph;
+
+extern crate alloc;
+use alloc::{borrow::Cow, collections::BTreeMap, sync::Arc};
+use core::ops::Range;
+
+/// A glyph run extracted from a layout, ready to be drawn.
+pub(crate) struct PreparedRun {
+    pub(crate) font: Font,
+    /// Font size, scaled up to work around quantization of small sizes.
+    pub(crate) font_size: f32,
+    /// Transform applied to each glyph, undoing the font size scale and applying synthetic skew.
+    pub(crate) glyph_transform: Affine,
+    pub(crate) normalized_coords: Vec<i16>,
+    /// Baseline of the line the run is on.
+    pub(crate) baseline: f32,
+    pub(crate) glyphs: Vec<Glyph>,
+    /// Advance of each glyph in `glyphs`.
+    pub(crate) advances: Vec<f32>,
+    /// Color from the style of the run, if it has one, rather than the paint of the item.
+    pub(crate) color: Option<Color>,
+    /// Box of the run in the layout, from its ascent to its descent along its advance.
+    pub(crate) bounds: Rect,
+    /// Range of the item's text shaped in the run.
+    pub(crate) text_range: Range<usize>,
+}
+
+/// A line of a layout, in the layout box.
+pub(crate) struct PreparedLine {
+    /// Box of the line, from the top to the bottom of the line along its advance.
+    pub(crate) bounds: Rect,
+    /// Range of the item's text on the line.
+    pub(crate) text_range: Range<usize>,
+}
+
+/// Text shaped from a particular set of inputs.
+///
+/// This is shared by every text item with the same inputs.
+pub(crate) struct ShapedText {
+    text: Arc<str>,
+    style: StyleSet<Option<Color>>,
+    spans: Vec<StyleSpan>,
+    max_inline_size: Option<f32>,
+    columns: Option<TextColumns>,
+    alignment: Alignment,
+    direction: TextDirection,
+    pub(crate) layout: Layout<Option<Color>>,
+    /// Size of the layout box, including all columns.
+    pub(crate) size: Size,
+    pub(crate) runs: Vec<PreparedRun>,
+    pub(crate) lines: Vec<PreparedLine>,
+}
+
+impl ShapedText {
+    /// Shape a text item.
+    fn new(
+        font_cx: &mut FontContext,
+        layout_cx: &mut LayoutContext<Option<Color>>,
+        t: &FatText,
+    ) -> Self {
+        // Parley takes the base direction from the first strong directional character,
+        // so an explicit direction is applied by prepending a zero width mark.
+        let text: Cow<'_, str> = match t.direction {
+            TextDirection::Auto => Cow::Borrowed(&t.text),
+            TextDirection::LeftToRight => Cow::Owned(format!("\u{200E}{}", t.text)),
+            TextDirection::RightToLeft => Cow::Owned(format!("\u{200F}{}", t.text)),
+        };
+        // Span ranges are offset by the length of the mark, if there is one.
+        let offset = text.len() - t.text.len();
+        let mut builder = layout_cx.ranged_builder(font_cx, &text, 1.0, false);
+        for prop in t.style.inner().values() {
+            builder.push_default(prop.to_owned());
+        }
+        for span in t.spans.iter() {
+            if t.text.get(span.range.clone()).is_none() {
+                continue;
+            }
+            let range = span.range.start + offset..span.range.end + offset;
+            for prop in span.style.inner().values() {
+                builder.push(prop.to_owned(), range.clone());
+            }
+        }
+        let mut layout = builder.build(&text);
+        let max_inline_size = t.columns.map_or(t.max_inline_size, |c| Some(c.width));
+        layout.break_all_lines(max_inline_size);
+        layout.align(max_inline_size, t.alignment, Default::default());
+
+        let (offsets, size) = match t.columns {
+            Some(columns) => break_columns(&layout, &columns),
+            None => (
+                vec![(0.0, 0.0); layout.len()],
+                Size {
+                    width: t.max_inline_size.unwrap_or(layout.width()) as f64,
+                    height: layout.height() as f64,
+                },
+            ),
+        };
+        let runs = prepare_runs(&layout, &offsets, offset);
+        let lines = prepare_lines(&layout, &offsets, offset);
+
+        Self {
+            text: t.text.clone(),
+            style: t.style.clone(),
+            spans: t.spans.clone(),
+            max_inline_size: t.max_inline_size,
+            columns: t.columns,
+            alignment: t.alignment,
+            direction: t.direction,
+            layout,
+            size,
+            runs,
+            lines,
+        }
+    }
+
+    /// Check whether this was shaped from the same inputs as `t`.
+    fn matches(&self, t: &FatText) -> bool {
+        (Arc::ptr_eq(&self.text, &t.text) || self.text == t.text)
+            && self.max_inline_size == t.max_inline_size
+            && self.columns == t.columns
+            && self.alignment == t.alignment
+            && self.direction == t.direction
+            && self.style.inner() == t.style.inner()
+            && self.spans == t.spans
+    }
+
+    /// Get the key this is shared under, the same as [`shape_key`] of its inputs.
+    fn key(&self) -> ShapeKey {
+        (
+            self.text.clone(),
+            self.max_inline_size.map(f32::to_bits),
+            self.alignment as u8,
+            self.direction as u8,
+        )
+    }
+}
+
+/// Assign the lines of a layout to columns.
+///
+/// Returns the offset of each line from its position in the layout, and the size of
+/// the columns together.
+fn break_columns(layout: &Layout<Option<Color>>, columns: &TextColumns) -> (Vec<(f32, f32)>, Size) {
+    // Without a height, lines are balanced so that earlier columns are no shorter than later ones.
+    let balanced = layout.height() / columns.count.max(1) as f32;
+    let pitch = columns.width + columns.gutter;
+    let mut offsets = Vec::with_capacity(layout.len());
+    let mut column = 0;
+    // Top of the current column in layout coordinates.
+    let mut top = 0.0;
+    let mut tallest = 0_f32;
+    // Line boxes are stacked, so each line starts where the previous one ends.
+    let mut line_top = 0.0;
+    for line in layout.lines() {
+        let line_bottom = line_top + line.metrics().line_height;
+        // Allow for rounding in line heights.
+        let overflows = match columns.height {
+            Some(height) => line_bottom - top > height * (1.0 + 1e-3),
+            None => line_top - top >= balanced * (1.0 - 1e-3),
+        };
+        if overflows && line_top > top && (columns.count == 0 || column + 1 < columns.count) {
+            column += 1;
+            top = line_top;
+        }
+        tallest = tallest.max(line_bottom - top);
+        offsets.push((column as f32 * pitch, -top));
+        line_top = line_bottom;
+    }
+    let used = if columns.count == 0 {
+        column + 1
+    } else {
+        columns.count
+    };
+    let size = Size {
+        width: columns.total_width(used) as f64,
+        height: columns.height.unwrap_or(tallest) as f64,
+    };
+    (offsets, size)
+}
+
+/// Map a range of the shaped text to the item's text, which lacks the `mark` bytes
+/// prepended to set the direction.
+fn item_range(range: Range<usize>, mark: usize) -> Range<usize> {
+    range.start.saturating_sub(mark)..range.end.saturating_sub(mark)
+}
+
+/// Extract the lines of a layout, offsetting each line by `offsets`.
+fn prepare_lines(
+    layout: &Layout<Option<Color>>,
+    offsets: &[(f32, f32)],
+    mark: usize,
+) -> Vec<PreparedLine> {
+    layout
+        .lines()
+        .zip(offsets)
+        .map(|(line, (dx, dy))| {
+            let m = line.metrics();
+            PreparedLine {
+                bounds: Rect::new(
+                    f64::from(m.offset + dx),
+                    f64::from(m.min_coord + dy),
+                    f64::from(m.offset + m.advance + dx),
+                    f64::from(m.max_coord + dy),
+                ),
+                text_range: item_range(line.text_range(), mark),
+            }
+        })
+        .collect()
+}
+
+/// Extract positioned glyph runs from a layout, offsetting each line by `offsets`.
+fn prepare_runs(
+    layout: &Layout<Option<Color>>,
+    offsets: &[(f32, f32)],
+    mark: usize,
+) -> Vec<PreparedRun> {
+    let mut runs = vec![];
+    for (line, (dx, dy)) in layout.lines().zip(offsets) {
+        for item in line.items() {
+            let PositionedLayoutItem::GlyphRun(glyph_run) = item else {
+                continue;
+            };
+
+            let mut x = glyph_run.offset() + dx;
+            let y = glyph_run.baseline() + dy;
+            let run = glyph_run.run();
+            let metrics = run.metrics();
+            let bounds = Rect::new(
+                f64::from(x),
+                f64::from(y - metrics.ascent),
+                f64::from(x + glyph_run.advance()),
+                f64::from(y + metrics.descent),
+            );
+            let glyph_transform = if let Some(angle) = run.synthesis().skew() {
+                Affine::scale(50_f64.recip()) * Affine::skew(angle.to_radians().tan() as f64, 0.0)
+            } else {
+                Affine::scale(50_f64.recip())
+            };
+            runs.push(PreparedRun {
+                font: run.font().clone(),
+                // Small font sizes are quantized, multiplying by
+                // 50 and then scaling by 1 / 50 at the glyph level
+                // works around this, but it is a hack.
+                font_size: run.font_size() * 50.0,
+                glyph_transform,
+                normalized_coords: run.normalized_coords().to_vec(),
+                baseline: y,
+                advances: glyph_run.glyphs().map(|g| g.advance).collect(),
+                color: glyph_run.style().brush,
+                bounds,
+                text_range: item_range(run.text_range(), mark),
+                glyphs: glyph_run
+                    .glyphs()
+                    .map(|g| {
+                        let gx = x + g.x;
+                        let gy = y - g.y;
+                        x += g.advance;
+                        Glyph {
+                            id: g.id as _,
+                            x: gx,
+                            y: gy,
+                        }
+                    })
+                    .collect(),
+            });
+        }
+    }
+    runs
+}
+
+/// Key used to find candidate [`ShapedText`] for deduplication.
+///
+/// Styles and columns are not orderable, so they are compared separately.
+type ShapeKey = (Arc<str>, Option<u32>, u8, u8);
+
+fn shape_key(t: &FatText) -> ShapeKey {
+    (
+        t.text.clone(),
+        t.max_inline_size.map(f32::to_bits),
+        t.alignment as u8,
+        t.direction as u8,
+    )
+}
+
+/// Shaped text cached for an item.
+struct CachedText {
+    shaped: Arc<ShapedText>,
+    /// Epoch and revision of the bag when the item was last checked against `shaped`.
+    checked: Option<(Epoch, Revision)>,
+}
+
+/// Get the epoch and revision to record for an item of `graphics` checked now.
+fn checked_now(graphics: Option<&GraphicsBag>) -> Option<(Epoch, Revision)> {
+    graphics.map(|g| (g.epoch(), g.revision()))
+}
+
+/// Shaped text for items, deduplicated across items with identical inputs.
+#[derive(Default)]
+pub(crate) struct TextCache {
+    /// Shaped text for each item.
+    items: BTreeMap<ItemHandle, CachedText>,
+    /// All distinct shaped text, for reuse between items.
+    shared: BTreeMap<ShapeKey, Vec<Arc<ShapedText>>>,
+}
+
+impl TextCache {
+    /// Check whether `idx` has up to date shaped text for `t`.
+    ///
+    /// `graphics` is the bag holding the item that `t` is made from, if there is one.
+    pub(crate) fn is_fresh(
+        &self,
+        graphics: Option<&GraphicsBag>,
+        idx: ItemHandle,
+        t: &FatText,
+    ) -> bool {
+        self.peek(graphics, idx, t).is_some()
+    }
+
+    /// Get up to date shaped text for an item without shaping it.
+    ///
+    /// `graphics` is the bag holding the item that `t` is made from, if there is one.
+    pub(crate) fn peek(
+        &self,
+        graphics: Option<&GraphicsBag>,
+        idx: ItemHandle,
+        t: &FatText,
+    ) -> Option<&Arc<ShapedText>> {
+        let cached = self.items.get(&idx)?;
+        let unchanged = graphics
+            .zip(cached.checked)
+            .is_some_and(|(g, (epoch, at))| g.epoch() == epoch && g.item_revision(idx) <= at);
+        (unchanged || cached.shaped.matches(t)).then_some(&cached.shaped)
+    }
+
+    /// Get shaped text for an item, shaping it if it is missing or stale.
+    ///
+    /// `graphics` is the bag holding the item that `t` is made from, if there is one.
+    /// If another item has identical text, styles, width, columns, alignment, and direction, its shaped
+    /// text is reused instead of shaping again.
+    pub(crate) fn get(
+        &mut self,
+        font_cx: &mut FontContext,
+        layout_cx: &mut LayoutContext<Option<Color>>,
+        graphics: Option<&GraphicsBag>,
+        idx: ItemHandle,
+        t: &FatText,
+    ) -> Arc<ShapedText> {
+        if let Some(s) = self.peek(graphics, idx, t).cloned() {
+            // Record the check, so it is skipped until the item changes again.
+            if let Some(cached) = self.items.get_mut(&idx) {
+                cached.checked = checked_now(graphics);
+            }
+            return s;
+        }
+
+        let candidates = self.shared.entry(shape_key(t)).or_default();
+        let shaped = if let Some(s) = candidates.iter().find(|s| s.matches(t)) {
+            s.clone()
+        } else {
+            let s = Arc::new(ShapedText::new(font_cx, layout_cx, t));
+            candidates.push(s.clone());
+            s
+        };
+        let cached = CachedText {
+            shaped: shaped.clone(),
+            checked: checked_now(graphics),
+        };
+        if let Some(stale) = self.items.insert(idx, cached) {
+            self.release(stale.shaped);
+        }
+        shaped
+    }
+
+    /// Drop the shaped text of an item.
+    pub(crate) fn invalidate(&mut self, idx: ItemHandle) {
+        if let Some(cached) = self.items.remove(&idx) {
+            self.release(cached.shaped);
+        }
+    }
+
+    /// Drop shaped text that was used by an item, if no other item uses it.
+    fn release(&mut self, shaped: Arc<ShapedText>) {
+        // Held by `shaped` and by `shared` when no item uses it.
+        if Arc::strong_count(&shaped) > 2 {
+            return;
+        }
+        let key = shaped.key();
+        if let Some(candidates) = self.shared.get_mut(&key) {
+            candidates.retain(|s| !Arc::ptr_eq(s, &shaped));
+            if candidates.is_empty() {
+                self.shared.remove(&key);
+            }
+        }
+    }
+
+    /// Drop all shaped text.
+    pub(crate) fn clear(&mut self) {
+        self.items.clear();
+        self.shared.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parley::{LineHeight, StyleProperty};
+    use tabulon::{DirectIsometry, GraphicsItem, peniko::kurbo::Vec2};
+
+    fn shape(text: &str, direction: TextDirection, max_inline_size: Option<f32>) -> ShapedText {
+        shape_spans(text, direction, max_inline_size, Vec::new())
+    }
+
+    fn shape_spans(
+        text: &str,
+        direction: TextDirection,
+        max_inline_size: Option<f32>,
+        spans: Vec<StyleSpan>,
+    ) -> ShapedText {
+        let mut t = fat_text(text);
+        t.direction = direction;
+        t.max_inline_size = max_inline_size;
+        t.spans = spans;
+        shape_text(&t)
+    }
+
+    fn shape_text(t: &FatText) -> ShapedText {
+        ShapedText::new(&mut FontContext::new(), &mut LayoutContext::new(), t)
+    }
+
+    /// Text with 10 unit lines.
+    fn fat_text(text: &str) -> FatText {
+        let mut style = StyleSet::new(10.0);
+        style.insert(StyleProperty::LineHeight(LineHeight::Absolute(10.0)));
+        FatText {
+            transform: Default::default(),
+            paint: Default::default(),
+            text: text.into(),
+            style,
+            spans: Vec::new(),
+            alignment: Alignment::Start,
+            direction: TextDirection::Auto,
+            max_inline_size: None,
+            columns: None,
+            background: None,
+            mirror_x: false,
+            mirror_y: false,
+            insertion: DirectIsometry::new(0.0, Vec2::ZERO),
+            attachment_point: Default::default(),
+        }
+    }
+
+    /// Offset of the first line from the left edge.
+    fn line_offset(s: &ShapedText) -> f32 {
+        s.layout.lines().next().unwrap().metrics().offset
+    }
+
+    #[test]
+    fn base_direction() {
+        assert!(
+            shape("שלום", TextDirection::Auto, None).layout.is_rtl(),
+            "Hebrew text should be right to left by default."
+        );
+        assert!(
+            shape("مرحبا", TextDirection::Auto, None).layout.is_rtl(),
+            "Arabic text should be right to left by default."
+        );
+        assert!(
+            !shape("Hello", TextDirection::Auto, None).layout.is_rtl(),
+            "Latin text should be left to right by default."
+        );
+        assert!(
+            shape("Hello", TextDirection::RightToLeft, None)
+                .layout
+                .is_rtl(),
+            "An explicit direction should override the text."
+        );
+        assert!(
+            !shape("שלום", TextDirection::LeftToRight, None)
+                .layout
+                .is_rtl(),
+            "An explicit direction should override the text."
+        );
+    }
+
+    #[test]
+    fn start_alignment_follows_direction() {
+        let rtl = shape("שלום", TextDirection::Auto, Some(1000.0));
+        let ltr = shape("שלום", TextDirection::LeftToRight, Some(1000.0));
+        assert!(
+            line_offset(&rtl) > 500.0,
+            "Right to left text should start at the right edge."
+        );
+        assert!(
+            line_offset(&ltr) < 500.0,
+            "Left to right text should start at the left edge."
+        );
+    }
+
+    #[test]
+    fn spans_override_style() {
+        let tall = || {
+            vec![StyleSpan::new(
+                3..5,
+                [StyleProperty::LineHeight(LineHeight::Absolute(40.0))],
+            )]
+        };
+        assert_eq!(
+            shape("Hello", TextDirection::Auto, None).layout.height(),
+            10.0,
+            "Without spans the item style applies."
+        );
+        assert_eq!(
+            shape_spans("Hello", TextDirection::Auto, None, tall())
+                .layout
+                .height(),
+            40.0,
+            "A span should override the item style in its range."
+        );
+        assert_eq!(
+            shape_spans("Hello", TextDirection::RightToLeft, None, tall())
+                .layout
+                .height(),
+            40.0,
+            "Spans should still apply after a direction mark is added."
+        );
+        assert_eq!(
+            shape_spans(
+                "Hello",
+                TextDirection::Auto,
+                None,
+                vec![StyleSpan::new(
+                    3..9,
+                    [StyleProperty::LineHeight(LineHeight::Absolute(40.0))]
+                )]
+            )
+            .layout
+            .height(),
+            10.0,
+            "Out of range spans should be ignored."
+        );
+    }
+
+    #[test]
+    fn span_colors() {
+        let red = Color::from_rgb8(255, 0, 0);
+        let shaped = shape_spans(
+            "Hello",
+            TextDirection::Auto,
+            None,
+            vec![StyleSpan::new(3..5, [StyleProperty::Brush(Some(red))])],
+        );
+        let colors: Vec<_> = shaped
+            .runs
+            .iter()
+            .map(|r| (r.color, r.glyphs.len()))
+            .collect();
+        assert_eq!(
+            colors,
+            [(None, 3), (Some(red), 2)],
+            "Runs should take their color from the style of their range."
+        );
+    }
+
+    #[test]
+    fn columns() {
+        let columns = |count, height| {
+            let mut t = fat_text("a\nb\nc\nd\ne");
+            t.columns = Some(TextColumns {
+                count,
+                width: 50.0,
+                gutter: 10.0,
+                height,
+            });
+            shape_text(&t)
+        };
+        // Left edge and baseline of each line.
+        let lines = |s: &ShapedText| {
+            s.runs
+                .iter()
+                .map(|r| (r.glyphs[0].x, r.baseline))
+                .collect::<Vec<_>>()
+        };
+
+        let balanced = columns(2, None);
+        assert_eq!(
+            balanced.size,
+            Size::new(110.0, 30.0),
+            "Balanced columns should share the lines."
+        );
+        let l = lines(&balanced);
+        assert!(
+            l[2].0 < 60.0 && l[3].0 >= 60.0,
+            "Lines should continue in the next column."
+        );
+        assert_eq!(
+            l[3].1, l[0].1,
+            "Each column should start at the top of the layout box."
+        );
+
+        let fixed = columns(0, Some(20.0));
+        assert_eq!(
+            fixed.size,
+            Size::new(170.0, 20.0),
+            "Columns should be added as needed to fit the height."
+        );
+        assert!(
+            lines(&fixed)[4].0 >= 120.0,
+            "The last line should be in the third column."
+        );
+
+        assert_eq!(
+            columns(2, Some(10.0)).size,
+            Size::new(110.0, 10.0),
+            "The number of columns should be limited by the count."
+        );
+    }
+
+    #[test]
+    fn edited_items_are_shaped_again() {
+        let mut graphics = GraphicsBag::default();
+        let item = graphics.push(fat_text("Hello"));
+        let (mut font_cx, mut layout_cx) = (FontContext::new(), LayoutContext::new());
+        let mut cache = TextCache::default();
+        let mut get = |cache: &mut TextCache, graphics: &GraphicsBag| {
+            let Some(GraphicsItem::FatText(t)) = graphics.get(item) else {
+                unreachable!();
+            };
+            // Keep only the address, so that the shaped text can be dropped.
+            Arc::as_ptr(&cache.get(&mut font_cx, &mut layout_cx, Some(graphics), item, t))
+        };
+
+        let hello = get(&mut cache, &graphics);
+        let Some(GraphicsItem::FatText(t)) = graphics.get_mut(item) else {
+            unreachable!();
+        };
+        t.text = "Goodbye".into();
+        let goodbye = get(&mut cache, &graphics);
+        assert!(
+            hello != goodbye,
+            "Changing the text of an item should shape it again."
+        );
+        assert_eq!(
+            cache.shared.len(),
+            1,
+            "Shaped text no longer used by any item should be dropped."
+        );
+
+        // Changing items directly without touching the bag isn't noticed.
+        let GraphicsItem::FatText(t) = &mut graphics.items[0] else {
+            unreachable!();
+        };
+        t.max_inline_size = Some(1.0);
+        assert!(
+            goodbye == get(&mut cache, &graphics),
+            "Unchanged items should not be checked against their inputs again."
+        );
+        graphics.touch();
+        assert!(
+            goodbye != get(&mut cache, &graphics),
+            "Touching the bag should check items against their inputs."
+        );
+
+        cache.invalidate(item);
+        assert!(
+            cache.items.is_empty() && cache.shared.is_empty(),
+            "Invalidating the only item should drop its shaped text."
+        );
+    }
+
+    #[test]
+    fn identical_labels_share_shaped_text() {
+        let mut graphics = GraphicsBag::default();
+        // Equal text in separate allocations, as labels read from a file would be.
+        let first = graphics.push(fat_text("Label"));
+        let second = graphics.push(fat_text("Label"));
+        let mut wide = fat_text("Label");
+        wide.max_inline_size = Some(1000.0);
+        let other = graphics.push(wide);
+        let (mut font_cx, mut layout_cx) = (FontContext::new(), LayoutContext::new());
+        let mut cache = TextCache::default();
+        let mut get = |item| {
+            let Some(GraphicsItem::FatText(t)) = graphics.get(item) else {
+                unreachable!();
+            };
+            cache.get(&mut font_cx, &mut layout_cx, Some(&graphics), item, t)
+        };
+
+        let shaped = get(first);
+        assert!(
+            Arc::ptr_eq(&shaped, &get(first)),
+            "Looking up the same label again should reuse its shaped text."
+        );
+        assert!(
+            Arc::ptr_eq(&shaped, &get(second)),
+            "Identical labels should share one shaped text."
+        );
+        assert!(
+            !Arc::ptr_eq(&shaped, &get(other)),
+            "Labels with different widths should be shaped separately."
+        );
+    }
+}
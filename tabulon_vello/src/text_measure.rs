@@ -0,0 +1,209 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Measuring the lines and glyph runs of text items, for picking, selection, and culling.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+
+use tabulon::{
+    GraphicsBag, GraphicsItem, ItemHandle, ItemKind,
+    peniko::kurbo::{Affine, Point, Rect, Size},
+    render_layer::RenderLayer,
+};
+
+use crate::Environment;
+
+/// A box of laid out text, such as a line or a glyph run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBox {
+    /// The box in the layout box of the text, with its origin at the top left.
+    pub rect: Rect,
+    /// Byte range of the item's text in the box.
+    ///
+    /// For glyph runs, this is the range of the shaping run the glyphs are from, which
+    /// can hold several glyph runs where the style changes within it.
+    pub text_range: Range<usize>,
+}
+
+/// The layout of a text item, line by line and run by run.
+///
+/// Boxes are in the coordinates of the layout box, and [`TextMeasurement::layout_transform`]
+/// maps them into drawing space, the coordinate space of the item's transform. Rotated
+/// labels have rotated boxes in drawing space, so they are mapped with
+/// [`TextMeasurement::corners`] rather than as rectangles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMeasurement {
+    /// Transform from the layout box to drawing space, including the insertion point,
+    /// rotation, attachment, and mirroring of the item.
+    pub layout_transform: Affine,
+    /// Size of the layout box.
+    pub size: Size,
+    /// Lines, in the order they are laid out.
+    pub lines: Vec<TextBox>,
+    /// Glyph runs, line by line, in visual order within each line.
+    pub runs: Vec<TextBox>,
+}
+
+impl TextMeasurement {
+    /// Get the corners of a box in drawing space, clockwise from its top left in the
+    /// layout box.
+    pub fn corners(&self, rect: Rect) -> [Point; 4] {
+        [
+            Point::new(rect.x0, rect.y0),
+            Point::new(rect.x1, rect.y0),
+            Point::new(rect.x1, rect.y1),
+            Point::new(rect.x0, rect.y1),
+        ]
+        .map(|p| self.layout_transform * p)
+    }
+
+    /// Get the axis aligned bounding box of a box in drawing space.
+    pub fn bounding_box(&self, rect: Rect) -> Rect {
+        self.layout_transform.transform_rect_bbox(rect)
+    }
+
+    /// Get the index of the line containing a point in drawing space, if any.
+    ///
+    /// Degenerate transforms, such as text of zero height, contain no points.
+    pub fn line_at(&self, point: Point) -> Option<usize> {
+        if self.layout_transform.determinant() == 0.0 {
+            return None;
+        }
+        let p = self.layout_transform.inverse() * point;
+        self.lines.iter().position(|l| l.rect.contains(p))
+    }
+}
+
+impl Environment {
+    /// Measure the lines and glyph runs of text items in a [`RenderLayer`].
+    ///
+    /// This shapes text that has no cached layout, like
+    /// [`measure_text_items`](Self::measure_text_items), but gives the boxes of every
+    /// line and glyph run, not just the size of the layout. Boxes are in drawing space
+    /// as described in [`TextMeasurement`]; the item's transform is not applied.
+    #[tracing::instrument(skip_all)]
+    pub fn measure_text_detailed(
+        &mut self,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+    ) -> BTreeMap<ItemHandle, TextMeasurement> {
+        let Self {
+            font_cx,
+            layout_cx,
+            text_cache,
+            lod_cache: _,
+            fragments: _,
+            frame_arena: _,
+            cull_indices: _,
+            layer_kinds,
+            texture_renderer: _,
+        } = self;
+        let mut out = BTreeMap::new();
+
+        for idx in layer_kinds.get(graphics, render_layer).get(ItemKind::Text) {
+            let Some(GraphicsItem::FatText(t)) = graphics.get(*idx) else {
+                continue;
+            };
+            let shaped = text_cache.get(font_cx, layout_cx, Some(graphics), *idx, t);
+            out.insert(
+                *idx,
+                TextMeasurement {
+                    layout_transform: t.placement_for_size(shaped.size)
+                        * t.mirror_for_size(shaped.size),
+                    size: shaped.size,
+                    lines: shaped
+                        .lines
+                        .iter()
+                        .map(|l| TextBox {
+                            rect: l.bounds,
+                            text_range: l.text_range.clone(),
+                        })
+                        .collect(),
+                    runs: shaped
+                        .runs
+                        .iter()
+                        .map(|r| TextBox {
+                            rect: r.bounds,
+                            text_range: r.text_range.clone(),
+                        })
+                        .collect(),
+                },
+            );
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::FRAC_PI_2;
+    use parley::{Alignment, LineHeight, StyleProperty, StyleSet};
+    use tabulon::{
+        DirectIsometry,
+        peniko::kurbo::Vec2,
+        text::{FatText, TextDirection},
+    };
+
+    #[test]
+    fn rotated_lines() {
+        let mut style = StyleSet::new(10.0);
+        style.insert(StyleProperty::LineHeight(LineHeight::Absolute(10.0)));
+        let mut graphics = GraphicsBag::default();
+        let mut layer = RenderLayer::default();
+        let item = layer.push_with_bag(
+            &mut graphics,
+            FatText {
+                transform: Default::default(),
+                paint: Default::default(),
+                text: "ab\ncd".into(),
+                style,
+                spans: Vec::new(),
+                alignment: Alignment::Start,
+                direction: TextDirection::RightToLeft,
+                max_inline_size: None,
+                columns: None,
+                background: None,
+                mirror_x: false,
+                mirror_y: false,
+                insertion: DirectIsometry::new(FRAC_PI_2, Vec2::new(100.0, 0.0)),
+                attachment_point: Default::default(),
+            },
+        );
+
+        let measured = Environment::default().measure_text_detailed(&graphics, &layer);
+        let m = &measured[&item];
+        assert_eq!(
+            m.lines
+                .iter()
+                .map(|l| l.text_range.clone())
+                .collect::<Vec<_>>(),
+            [0..3, 3..5],
+            "Lines should have ranges of the item's text, without the direction mark."
+        );
+        assert!(
+            m.runs.len() >= 2 && m.runs.iter().all(|r| r.rect.height() > 0.0),
+            "Each line should have glyph runs with boxes."
+        );
+
+        let second = m.bounding_box(m.lines[1].rect);
+        assert_eq!(
+            second.width(),
+            m.lines[1].rect.height(),
+            "Rotated lines should be as wide as they are tall unrotated."
+        );
+        assert_eq!(
+            m.line_at(second.center()),
+            Some(1),
+            "Points in drawing space should find their line."
+        );
+        assert_eq!(
+            m.line_at(Point::new(-100.0, -100.0)),
+            None,
+            "Points outside the text should find no line."
+        );
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2025 the Tabulon Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Rendering layers offscreen, such as for thumbnails and exports.
+
+extern crate alloc;
+use alloc::{sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use tabulon::{
+    GraphicsBag,
+    peniko::{Blob, Image, ImageFormat, kurbo::Affine},
+    render_layer::RenderLayer,
+};
+use vello::{
+    Renderer, Scene,
+    wgpu::{self, Device, Queue},
+};
+
+use crate::{Environment, RasterOptions, RenderOptions};
+
+/// A renderer kept for offscreen rendering, with what it was made for.
+pub(crate) struct TextureRenderer {
+    device: Device,
+    options: RasterOptions,
+    renderer: Renderer,
+}
+
+impl TextureRenderer {
+    /// Check whether the renderer can render to `device` with `options`.
+    ///
+    /// The base color is a parameter of each render, so it may differ.
+    fn suits(&self, device: &Device, options: &RasterOptions) -> bool {
+        self.device == *device
+            && self.options.antialiasing == options.antialiasing
+            && self.options.use_cpu == options.use_cpu
+    }
+}
+
+impl Environment {
+    /// Render a [`RenderLayer`] to an image of `size` pixels, with `view` applied
+    /// on top of the transforms of `graphics`.
+    ///
+    /// See [`render_layer_to_texture_with_options`] for details.
+    ///
+    /// [`render_layer_to_texture_with_options`]: Self::render_layer_to_texture_with_options
+    pub fn render_layer_to_texture(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        size: (u32, u32),
+        view: Affine,
+    ) -> Result<Image, vello::Error> {
+        self.render_layer_to_texture_with_options(
+            device,
+            queue,
+            graphics,
+            render_layer,
+            size,
+            view,
+            &RenderOptions::default(),
+            &RasterOptions::default(),
+        )
+    }
+
+    /// Render a [`RenderLayer`] to an image of `size` pixels with [`RenderOptions`] and
+    /// [`RasterOptions`], with `view` applied on top of the transforms of `graphics`.
+    ///
+    /// The layer is rendered to a texture, which is copied back to memory as an
+    /// [`ImageFormat::Rgba8`] image, such as for a thumbnail, or to draw in another
+    /// bag as a [`FatImage`](tabulon::image::FatImage). The [`Renderer`] is kept in the
+    /// environment and reused while the device and antialiasing stay the same, as
+    /// creating one compiles its shaders. Empty sizes give an empty image.
+    ///
+    /// This waits for the device to finish rendering, so it suits occasional renders
+    /// rather than every frame.
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "Mirrors the plain variant, with the two kinds of options."
+    )]
+    #[tracing::instrument(skip_all)]
+    pub fn render_layer_to_texture_with_options(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        graphics: &GraphicsBag,
+        render_layer: &RenderLayer,
+        (width, height): (u32, u32),
+        view: Affine,
+        render_options: &RenderOptions<'_>,
+        raster_options: &RasterOptions,
+    ) -> Result<Image, vello::Error> {
+        if width == 0 || height == 0 {
+            let empty = Blob::new(Arc::new([]));
+            return Ok(Image::new(empty, ImageFormat::Rgba8, width, height));
+        }
+
+        let mut layer = Scene::new();
+        self.add_render_layer_to_scene_with_options(
+            &mut layer,
+            graphics,
+            render_layer,
+            render_options,
+        );
+        let mut scene = Scene::new();
+        scene.append(&layer, Some(view));
+
+        if !self
+            .texture_renderer
+            .as_ref()
+            .is_some_and(|r| r.suits(device, raster_options))
+        {
+            self.texture_renderer = Some(TextureRenderer {
+                device: device.clone(),
+                options: *raster_options,
+                renderer: Renderer::new(device, raster_options.renderer_options())?,
+            });
+        }
+        let renderer = &mut self.texture_renderer.as_mut().unwrap().renderer;
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("tabulon_vello offscreen target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        renderer.render_to_texture(
+            device,
+            queue,
+            &scene,
+            &target,
+            &raster_options.render_params(width, height),
+        )?;
+
+        // Rows of copies from textures must be aligned, so they are padded, and the
+        // padding is dropped when reading back.
+        let row = width as usize * 4;
+        let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tabulon_vello offscreen readback"),
+            size: (padded_row * height as usize) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(
+                        u32::try_from(padded_row).expect("Texture rows fit in u32."),
+                    ),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit([encoder.finish()]);
+
+        let mapped = Arc::new(AtomicBool::new(false));
+        let signal = mapped.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                signal.store(result.is_ok(), Ordering::Release);
+            });
+        device.poll(wgpu::Maintain::Wait);
+        if !mapped.load(Ordering::Acquire) {
+            return Err(wgpu::BufferAsyncError.into());
+        }
+        let mut pixels = Vec::with_capacity(row * height as usize);
+        for padded in buffer.slice(..).get_mapped_range().chunks(padded_row) {
+            pixels.extend_from_slice(&padded[..row]);
+        }
+        buffer.unmap();
+
+        Ok(Image::new(
+            Blob::new(Arc::new(pixels)),
+            ImageFormat::Rgba8,
+            width,
+            height,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tabulon::{
+        peniko::{Color, kurbo::Rect},
+        shape::{FatPaint, FatShape},
+    };
+
+    #[test]
+    fn thumbnail_pixels() {
+        let Some((device, queue)) =
+            pollster::block_on(RasterOptions::default().request_device(&wgpu::Instance::default()))
+        else {
+            // No adapter to render with, as on most CI machines.
+            return;
+        };
+
+        let mut graphics = GraphicsBag::default();
+        let paint = graphics.register_paint(FatPaint {
+            fill_paint: Some(Color::BLACK.into()),
+            ..Default::default()
+        });
+        let mut layer = RenderLayer::default();
+        layer.push_with_bag(
+            &mut graphics,
+            FatShape {
+                paint,
+                shape: Arc::new(Rect::new(0.0, 0.0, 10.0, 10.0).into()),
+                ..Default::default()
+            },
+        );
+
+        let mut env = Environment::default();
+        // An odd width, so that rows are padded for the copy.
+        let image = env
+            .render_layer_to_texture(
+                &device,
+                &queue,
+                &graphics,
+                &layer,
+                (33, 20),
+                Affine::scale(2.0),
+            )
+            .unwrap();
+        let data = image.data.data();
+        assert_eq!(data.len(), 33 * 20 * 4, "Padding should be dropped.");
+        let pixel = |x: usize, y: usize| &data[(y * 33 + x) * 4..][..4];
+        assert_eq!(
+            (pixel(19, 19), pixel(21, 19)),
+            (&[0, 0, 0, 255][..], &[255, 255, 255, 255][..]),
+            "The view should scale the square to 20 pixels, drawn on white."
+        );
+    }
+}